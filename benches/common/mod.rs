@@ -0,0 +1,100 @@
+//! Helpers shared by the benchmark binaries.
+//!
+//! Each benchmark includes this module with `mod common;` and uses a subset
+//! of it.
+#![allow(dead_code)]
+
+use std::{env, process::exit, str::FromStr, vec::IntoIter};
+
+/// Command-line arguments of a benchmark binary.
+///
+/// Iterates over the flags passed to the benchmark, skipping the `--bench`
+/// flag passed by `cargo bench` and handling `--help`.  On invalid input,
+/// prints an error along with the usage string and exits the process.
+///
+/// ```ignore
+/// let mut args = BenchArgs::new("usage: bench [--steps N]");
+/// while let Some(flag) = args.next_flag() {
+///     match flag.as_str() {
+///         "--steps" => steps = args.value(&flag),
+///         _ => args.unknown(&flag),
+///     }
+/// }
+/// ```
+pub struct BenchArgs {
+    usage: &'static str,
+    args: IntoIter<String>,
+}
+
+impl BenchArgs {
+    /// Arguments of the current process.
+    pub fn new(usage: &'static str) -> Self {
+        Self::from_args(usage, env::args().skip(1))
+    }
+
+    /// Arguments in `args`, excluding the program name.
+    pub fn from_args<I>(usage: &'static str, args: I) -> Self
+    where
+        I: IntoIterator<Item = String>,
+    {
+        Self {
+            usage,
+            args: args.into_iter().collect::<Vec<_>>().into_iter(),
+        }
+    }
+
+    /// The next flag.
+    ///
+    /// Prints the usage string and exits on `--help` or `-h`.
+    pub fn next_flag(&mut self) -> Option<String> {
+        loop {
+            let flag = self.args.next()?;
+            match flag.as_str() {
+                // Passed by `cargo bench`.
+                "--bench" => {}
+                "--help" | "-h" => {
+                    println!("{}", self.usage);
+                    exit(0)
+                }
+                _ => return Some(flag),
+            }
+        }
+    }
+
+    /// Parses the value of `flag`.
+    pub fn value<T>(&mut self, flag: &str) -> T
+    where
+        T: FromStr,
+    {
+        match self.args.next().and_then(|value| value.parse().ok()) {
+            Some(value) => value,
+            None => self.fail(&format!("invalid value of {}", flag)),
+        }
+    }
+
+    /// Parses the value of `flag` as a comma-separated list.
+    pub fn list<T>(&mut self, flag: &str) -> Vec<T>
+    where
+        T: FromStr,
+    {
+        match self
+            .args
+            .next()
+            .and_then(|value| value.split(',').map(|v| v.parse().ok()).collect())
+        {
+            Some(values) => values,
+            None => self.fail(&format!("invalid value of {}", flag)),
+        }
+    }
+
+    /// Reports an unknown flag and exits.
+    pub fn unknown(&self, flag: &str) -> ! {
+        self.fail(&format!("unknown argument '{}'", flag))
+    }
+
+    /// Prints `message` along with the usage string and exits.
+    pub fn fail(&self, message: &str) -> ! {
+        eprintln!("{}\n{}", message, self.usage);
+        exit(1)
+    }
+}
@@ -0,0 +1,76 @@
+//! Compares sequential and parallel consolidation of large unsorted batches.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use dbsp::trace::{
+    consolidation::{consolidate, par_consolidate, par_consolidate_runs},
+    layers::{
+        ordered_leaf::{OrderedLeaf, UnorderedLeafBuilder},
+        Builder, TupleBuilder,
+    },
+};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const BATCH_SIZES: [usize; 3] = [100_000, 1_000_000, 10_000_000];
+
+fn random_batch(size: usize) -> Vec<(u64, isize)> {
+    let mut rng = SmallRng::seed_from_u64(0);
+    (0..size)
+        .map(|_| (rng.gen_range(0..size as u64 / 2), rng.gen_range(-1..=1)))
+        .collect()
+}
+
+fn build(
+    mut builder: UnorderedLeafBuilder<u64, isize>,
+    batch: Vec<(u64, isize)>,
+) -> OrderedLeaf<u64, isize> {
+    for tuple in batch {
+        builder.push_tuple(tuple);
+    }
+    builder.done()
+}
+
+fn consolidation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("consolidation");
+    group.sample_size(10);
+
+    for size in BATCH_SIZES {
+        let batch = random_batch(size);
+        group.throughput(Throughput::Elements(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("sequential", size), &batch, |b, batch| {
+            b.iter_batched(
+                || batch.clone(),
+                |mut batch| consolidate(&mut batch),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("parallel", size), &batch, |b, batch| {
+            b.iter_batched(
+                || batch.clone(),
+                |mut batch| par_consolidate(&mut batch),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("runs", size), &batch, |b, batch| {
+            b.iter_batched(
+                || batch.clone(),
+                par_consolidate_runs,
+                BatchSize::LargeInput,
+            )
+        });
+        // With the `with-rayon` feature, leaf builders consolidate large
+        // batches in parallel.
+        group.bench_with_input(BenchmarkId::new("builder", size), &batch, |b, batch| {
+            b.iter_batched(
+                || batch.clone(),
+                |batch| build(UnorderedLeafBuilder::with_capacity(batch.len()), batch),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, consolidation);
+criterion_main!(benches);
@@ -1,19 +1,49 @@
 //! Galen benchmark from
 //! `https://github.com/frankmcsherry/dynamic-datalog/tree/master/problems/galen`
+//!
+//! Usage:
+//!
+//! ```text
+//! cargo bench --bench galen --features with-csv -- \
+//!     [--workers N] [--chunk-size N] [--effort N] [--data DIR]
+//! ```
+//!
+//! * `--workers` - number of worker threads (default: 1).  Relations are
+//!   sharded across workers by key.
+//! * `--chunk-size` - number of input tuples of each relation fed to each
+//!   worker per step; 0 feeds all inputs in a single step (default: 0).
+//! * `--effort` - effort multiplier of all traces, see
+//!   `dbsp::circuit::Circuit::with_trace_effort` (default: 1).
+//! * `--data` - directory containing the input relations (default:
+//!   `benches/galen_data`).
+//!
+//! The benchmark reports the throughput in input tuples per second along
+//! with the sizes of the computed relations.
 
-use csv::{Reader as CsvReader, ReaderBuilder};
+mod common;
+
+use common::BenchArgs;
+use csv::ReaderBuilder;
 use dbsp::{
-    algebra::HasZero,
-    circuit::{trace::SchedulerEvent, GlobalNodeId, Root, Runtime, Stream},
-    monitor::TraceMonitor,
-    operator::{CsvSource, DelayedFeedback},
-    profile::CPUProfiler,
+    circuit::{Circuit, Root, Runtime, Stream},
+    operator::{communication::new_exchange_operators, DelayedFeedback, Generator},
     trace::{
         ord::{OrdIndexedZSet, OrdZSet},
-        BatchReader,
+        Batch, BatchReader, EffortConfig,
+    },
+};
+use serde::de::DeserializeOwned;
+use std::{
+    cell::Cell,
+    iter::repeat_n,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
     },
+    time::Instant,
 };
-use std::{collections::HashMap, fmt::Write, fs, fs::File, path::PathBuf};
 
 /*
 .decl p(X: Number, Z: Number)
@@ -41,207 +71,335 @@ q(?x,?e,?o) :- q(?x,?y,?z),r(?y,?u,?e),q(?z,?u,?o).
 type Number = u32;
 type Weight = isize;
 
-fn csv_source<T>(file: &str) -> CsvSource<File, T, Weight, OrdZSet<T, Weight>>
+type Pair = (Number, Number);
+type Triple = (Number, Number, Number);
+
+const USAGE: &str = "usage: galen [--workers N] [--chunk-size N] [--effort N] [--data DIR]";
+
+#[derive(Clone, Debug)]
+struct Config {
+    workers: usize,
+    chunk_size: usize,
+    effort: usize,
+    data: PathBuf,
+}
+
+impl Config {
+    fn from_args() -> Self {
+        let mut config = Self {
+            workers: 1,
+            chunk_size: 0,
+            effort: 1,
+            data: ["benches", "galen_data"].iter().collect(),
+        };
+
+        let mut args = BenchArgs::new(USAGE);
+        while let Some(flag) = args.next_flag() {
+            match flag.as_str() {
+                "--workers" => config.workers = args.value(&flag),
+                "--chunk-size" => config.chunk_size = args.value(&flag),
+                "--effort" => config.effort = args.value(&flag),
+                "--data" => config.data = args.value(&flag),
+                _ => args.unknown(&flag),
+            }
+        }
+
+        if config.workers == 0 {
+            args.fail("--workers must be positive")
+        }
+        config
+    }
+
+    /// Number of steps needed to feed `tuples` tuples of a relation to each
+    /// worker.
+    fn steps(&self, tuples: usize) -> usize {
+        let per_worker = tuples.div_ceil(self.workers);
+        if self.chunk_size == 0 {
+            1
+        } else {
+            per_worker.div_ceil(self.chunk_size).max(1)
+        }
+    }
+}
+
+fn read_relation<T>(dir: &Path, file: &str) -> Arc<Vec<T>>
 where
-    T: Clone + Ord,
+    T: DeserializeOwned,
 {
-    let path: PathBuf = ["benches", "galen_data", file].iter().collect();
-
-    let reader: CsvReader<File> = ReaderBuilder::new()
+    let path = dir.join(file);
+    let mut reader = ReaderBuilder::new()
         .delimiter(b',')
         .has_headers(false)
-        .from_path(path)
-        .unwrap();
-    CsvSource::from_csv_reader(reader)
+        .from_path(&path)
+        .unwrap_or_else(|e| panic!("failed to open {}: {}", path.display(), e));
+    Arc::new(reader.deserialize().map(Result::unwrap).collect())
+}
+
+/// Source that feeds this worker's share of `data` to the circuit,
+/// `chunk_size` tuples per step.
+fn chunked_source<T>(
+    circuit: &Circuit<()>,
+    data: &[T],
+    config: &Config,
+    index: usize,
+) -> Stream<Circuit<()>, OrdZSet<T, Weight>>
+where
+    T: Clone + Ord + Send + 'static,
+{
+    let tuples: Vec<_> = data
+        .iter()
+        .skip(index)
+        .step_by(config.workers)
+        .map(|t| ((t.clone(), ()), 1))
+        .collect();
+    let chunk_size = if config.chunk_size == 0 {
+        tuples.len().max(1)
+    } else {
+        config.chunk_size
+    };
+    let mut chunks: Vec<_> = tuples
+        .chunks(chunk_size)
+        .map(|chunk| chunk.to_vec())
+        .collect();
+    chunks.reverse();
+
+    circuit.add_source(Generator::new(move || {
+        OrdZSet::from_tuples((), chunks.pop().unwrap_or_default())
+    }))
 }
 
 fn main() {
-    let hruntime = Runtime::run(1, |_runtime, _index| {
-        let monitor = TraceMonitor::new_panic_on_error();
+    let config = Config::from_args();
+
+    let p_data = read_relation::<Pair>(&config.data, "p.txt");
+    let q_data = read_relation::<Triple>(&config.data, "q.txt");
+    let r_data = read_relation::<Triple>(&config.data, "r.txt");
+    let c_data = read_relation::<Triple>(&config.data, "c.txt");
+    let u_data = read_relation::<Triple>(&config.data, "u.txt");
+    let s_data = read_relation::<Pair>(&config.data, "s.txt");
 
+    let input_sizes = [
+        p_data.len(),
+        q_data.len(),
+        r_data.len(),
+        c_data.len(),
+        u_data.len(),
+        s_data.len(),
+    ];
+    let input_tuples: usize = input_sizes.iter().sum();
+    let steps = config.steps(input_sizes.into_iter().max().unwrap());
+
+    let p_size = Arc::new(AtomicUsize::new(0));
+    let q_size = Arc::new(AtomicUsize::new(0));
+
+    let start = Instant::now();
+    let workers = config.workers;
+    let worker_config = config.clone();
+    let (p_size_clone, q_size_clone) = (p_size.clone(), q_size.clone());
+    let hruntime = Runtime::run(workers, move |runtime, index| {
+        let config = worker_config;
         let root = Root::build(|circuit| {
-            /*
-            let cpu_profiler = CPUProfiler::new();
-            cpu_profiler.attach(circuit, "cpu profiler");
-
-            monitor.attach(circuit, "monitor");
-
-            let mut metadata = <HashMap<GlobalNodeId, String>>::new();
-            let mut nsteps = 0;
-            let monitor_clone = monitor.clone();
-            circuit.register_scheduler_event_handler("metadata", move |event: &SchedulerEvent| {
-                match event {
-                    SchedulerEvent::EvalEnd { node } => {
-                        let metadata_string = metadata
-                            .entry(node.global_id().clone())
-                            .or_insert_with(|| String::new());
-                        metadata_string.clear();
-                        node.summary(metadata_string);
-                    }
-                    SchedulerEvent::StepEnd => {
-                        let graph = monitor_clone.visualize_circuit_annotate(&|node_id| {
-                            let mut metadata_string = metadata
-                                .get(node_id)
-                                .map(ToString::to_string)
-                                .unwrap_or_else(|| "".to_string());
-                            writeln!(metadata_string, "id: {}", node_id).unwrap();
-                            if let Some(cpu_profile) = cpu_profiler.operator_profile(node_id) {
-                                writeln!(
-                                    metadata_string,
-                                    "invocations: {}",
-                                    cpu_profile.invocations()
-                                )
-                                .unwrap();
-                                writeln!(metadata_string, "time: {:?}", cpu_profile.total_time())
-                                    .unwrap();
-                            };
-                            metadata_string
-                        });
-                        fs::write(format!("galen.{}.dot", nsteps), graph.to_dot()).unwrap();
-                        nsteps += 1;
-                    }
-                    _ => {}
-                }
-            });
-            */
-
-            let p_source = csv_source::<(Number, Number)>("p.txt");
-            let q_source = csv_source::<(Number, Number, Number)>("q.txt");
-            let r_source = csv_source::<(Number, Number, Number)>("r.txt");
-            let c_source = csv_source::<(Number, Number, Number)>("c.txt");
-            let u_source = csv_source::<(Number, Number, Number)>("u.txt");
-            let s_source = csv_source::<(Number, Number)>("s.txt");
-
-            let p: Stream<_, OrdZSet<_, Weight>> =
-                circuit.region("p", || circuit.add_source(p_source));
-            let q: Stream<_, OrdZSet<_, Weight>> =
-                circuit.region("q", || circuit.add_source(q_source));
-            let r: Stream<_, OrdZSet<_, Weight>> =
-                circuit.region("r", || circuit.add_source(r_source));
-            let c: Stream<_, OrdZSet<_, Weight>> =
-                circuit.region("c", || circuit.add_source(c_source));
-            let u: Stream<_, OrdZSet<_, Weight>> =
-                circuit.region("u", || circuit.add_source(u_source));
-            let s: Stream<_, OrdZSet<_, Weight>> =
-                circuit.region("s", || circuit.add_source(s_source));
-
-            let (outp, outq) = circuit
-                .fixedpoint(|child| {
-                    let pvar: DelayedFeedback<_, OrdZSet<(Number, Number), Weight>> =
-                        DelayedFeedback::new(child);
-                    let qvar: DelayedFeedback<_, OrdZSet<(Number, Number, Number), Weight>> =
-                        DelayedFeedback::new(child);
-
-                    let p_by_1 = pvar.stream().index::<OrdIndexedZSet<_, _, _>>();
-                    let p_by_2 = pvar
-                        .stream()
-                        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y)| (y, x));
-                    let p_by_12 = pvar
-                        .stream()
-                        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y)| ((x, y), ()));
-                    let u_by_1 = u
-                        .delta0(child)
-                        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| (x, (y, z)));
-                    let q_by_1 = qvar
-                        .stream()
-                        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| (x, (y, z)));
-                    let q_by_2 = qvar
-                        .stream()
-                        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| (y, (x, z)));
-                    let q_by_12 = qvar
-                        .stream()
-                        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| ((x, y), z));
-                    let q_by_23 = qvar
-                        .stream()
-                        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| ((y, z), x));
-                    let c_by_2 = c
-                        .delta0(child)
-                        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| (y, (x, z)));
-                    let r_by_1 = r
-                        .delta0(child)
-                        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| (x, (y, z)));
-                    let s_by_1 = s.delta0(child).index::<OrdIndexedZSet<_, _, _>>();
-
-                    // IR1: p(x,z) :- p(x,y), p(y,z).
-                    let ir1 =
-                        child.region("IR1", || p_by_2.join_trace(&p_by_1, |&_y, &x, &z| (x, z)));
-                    ir1.inspect(|zs: &OrdZSet<_, _>| println!("ir1: {}", zs.len()));
-
-                    // IR2: q(x,r,z) := p(x,y), q(y,r,z)
-                    let ir2 = child.region("IR2", || {
-                        p_by_2.join_trace(&q_by_1, |&_y, &x, &(r, z)| (x, r, z))
-                    });
+            // Apply the configured effort to all traces in the circuit.
+            circuit.with_trace_effort(EffortConfig::new(config.effort), || {
+                let p = chunked_source(circuit, &p_data, &config, index);
+                let q = chunked_source(circuit, &q_data, &config, index);
+                let r = chunked_source(circuit, &r_data, &config, index);
+                let c = chunked_source(circuit, &c_data, &config, index);
+                let u = chunked_source(circuit, &u_data, &config, index);
+                let s = chunked_source(circuit, &s_data, &config, index);
 
-                    ir2.inspect(|zs: &OrdZSet<_, _>| println!("ir2: {}", zs.len()));
+                // Shard base relations by their join keys.
+                let u_by_1 = u
+                    .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| (x, (y, z)))
+                    .shard(runtime);
+                let c_by_2 = c
+                    .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| (y, (x, z)))
+                    .shard(runtime);
+                let r_by_1 = r
+                    .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| (x, (y, z)))
+                    .shard(runtime);
+                let s_by_1 = s.index::<OrdIndexedZSet<_, _, _>>().shard(runtime);
 
-                    // IR3: p(x,z) := p(y,w), u(w,r,z), q(x,r,y)
-                    let ir3 = child.region("IR3", || {
-                        p_by_2
-                            .join_trace::<_, _, OrdZSet<_, _>>(&u_by_1, |&_w, &y, &(r, z)| {
-                                ((r, y), z)
-                            })
+                let (outp, outq) = circuit
+                    .iterate(|child| {
+                        let pvar: DelayedFeedback<_, OrdZSet<Pair, Weight>> =
+                            DelayedFeedback::new(child);
+                        let qvar: DelayedFeedback<_, OrdZSet<Triple, Weight>> =
+                            DelayedFeedback::new(child);
+
+                        let p_by_1 = pvar
+                            .stream()
                             .index::<OrdIndexedZSet<_, _, _>>()
-                            .join_trace(&q_by_23, |&(_r, _y), &z, &x| (x, z))
-                    });
-                    ir3.inspect(|zs: &OrdZSet<_, _>| println!("ir3: {}", zs.len()));
+                            .shard(runtime);
+                        let p_by_2 = pvar
+                            .stream()
+                            .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y)| (y, x))
+                            .shard(runtime);
+                        let p_by_12 = pvar
+                            .stream()
+                            .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y)| ((x, y), ()))
+                            .shard(runtime);
+                        let u_by_1 = u_by_1.delta0(child);
+                        let q_by_1 = qvar
+                            .stream()
+                            .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| (x, (y, z)))
+                            .shard(runtime);
+                        let q_by_2 = qvar
+                            .stream()
+                            .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| (y, (x, z)))
+                            .shard(runtime);
+                        let q_by_12 = qvar
+                            .stream()
+                            .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| ((x, y), z))
+                            .shard(runtime);
+                        let q_by_23 = qvar
+                            .stream()
+                            .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(x, y, z)| ((y, z), x))
+                            .shard(runtime);
+                        let c_by_2 = c_by_2.delta0(child);
+                        let r_by_1 = r_by_1.delta0(child);
+                        let s_by_1 = s_by_1.delta0(child);
 
-                    // IR4: p(x,z) := c(y,w,z), p(x,w), p(x,y)
-                    let ir4_1 = child.region("IR4-1", || {
-                        c_by_2.join_trace::<_, _, OrdZSet<_, _>>(&p_by_2, |&_w, &(y, z), &x| {
-                            ((x, y), z)
-                        })
-                    });
-                    ir4_1.inspect(|zs: &OrdZSet<_, _>| println!("ir4_1: {}", zs.len()));
+                        // IR1: p(x,z) :- p(x,y), p(y,z).
+                        let ir1 = child
+                            .region("IR1", || p_by_2.join_trace(&p_by_1, |&_y, &x, &z| (x, z)));
 
-                    let ir4 = child.region("IR4-2", || {
-                        ir4_1
-                            .index::<OrdIndexedZSet<_, _, _>>()
-                            .join_trace(&p_by_12, |&(x, _y), &z, &()| (x, z))
-                    });
-                    ir4.inspect(|zs: &OrdZSet<_, _>| println!("ir4: {}", zs.len()));
+                        // IR2: q(x,r,z) := p(x,y), q(y,r,z)
+                        let ir2 = child.region("IR2", || {
+                            p_by_2.join_trace(&q_by_1, |&_y, &x, &(r, z)| (x, r, z))
+                        });
 
-                    // IR5: q(x,q,z) := q(x,r,z), s(r,q)
-                    let ir5 = child.region("IR5", || {
-                        q_by_2.join_trace(&s_by_1, |&_r, &(x, z), &q| (x, q, z))
-                    });
-                    ir5.inspect(|zs: &OrdZSet<_, _>| println!("ir5: {}", zs.len()));
+                        // IR3: p(x,z) := p(y,w), u(w,r,z), q(x,r,y)
+                        let ir3 = child.region("IR3", || {
+                            p_by_2
+                                .join_trace::<_, _, OrdZSet<_, _>>(&u_by_1, |&_w, &y, &(r, z)| {
+                                    ((r, y), z)
+                                })
+                                .index::<OrdIndexedZSet<_, _, _>>()
+                                .shard(runtime)
+                                .join_trace(&q_by_23, |&(_r, _y), &z, &x| (x, z))
+                        });
 
-                    // IR6: q(x,e,o) := q(x,y,z), r(y,u,e), q(z,u,o)
-                    let ir6_1 = child.region("IR6_1", || {
-                        q_by_2
-                            .join_trace::<_, _, OrdZSet<_, _>>(&r_by_1, |&_y, &(x, z), &(u, e)| {
-                                ((z, u), (x, e))
+                        // IR4: p(x,z) := c(y,w,z), p(x,w), p(x,y)
+                        let ir4_1 = child.region("IR4-1", || {
+                            c_by_2.join_trace::<_, _, OrdZSet<_, _>>(&p_by_2, |&_w, &(y, z), &x| {
+                                ((x, y), z)
                             })
-                            .index::<OrdIndexedZSet<_, _, _>>()
-                    });
-                    let ir6 = child.region("IR6", || {
-                        ir6_1.join_trace(&q_by_12, |&(_z, _u), &(x, e), &o| (x, e, o))
-                    });
+                        });
+
+                        let ir4 = child.region("IR4-2", || {
+                            ir4_1
+                                .index::<OrdIndexedZSet<_, _, _>>()
+                                .shard(runtime)
+                                .join_trace(&p_by_12, |&(x, _y), &z, &()| (x, z))
+                        });
+
+                        // IR5: q(x,q,z) := q(x,r,z), s(r,q)
+                        let ir5 = child.region("IR5", || {
+                            q_by_2.join_trace(&s_by_1, |&_r, &(x, z), &q| (x, q, z))
+                        });
+
+                        // IR6: q(x,e,o) := q(x,y,z), r(y,u,e), q(z,u,o)
+                        let ir6_1 = child.region("IR6_1", || {
+                            q_by_2
+                                .join_trace::<_, _, OrdZSet<_, _>>(
+                                    &r_by_1,
+                                    |&_y, &(x, z), &(u, e)| ((z, u), (x, e)),
+                                )
+                                .index::<OrdIndexedZSet<_, _, _>>()
+                                .shard(runtime)
+                        });
+                        let ir6 = child.region("IR6", || {
+                            ir6_1.join_trace(&q_by_12, |&(_z, _u), &(x, e), &o| (x, e, o))
+                        });
 
-                    ir6.inspect(|zs: &OrdZSet<_, _>| println!("ir6: {}", zs.len()));
+                        let p = p
+                            .delta0(child)
+                            .sum([&ir1, &ir3, &ir4])
+                            .shard(runtime)
+                            .distinct_trace();
 
-                    let p = p.delta0(child).sum([&ir1, &ir3, &ir4]).distinct_trace();
+                        let q = q
+                            .delta0(child)
+                            .sum([&ir2, &ir5, &ir6])
+                            .shard(runtime)
+                            .distinct_trace();
 
-                    let q = q.delta0(child).sum([&ir2, &ir5, &ir6]).distinct_trace();
+                        pvar.connect(&p);
+                        qvar.connect(&q);
 
-                    pvar.connect(&p);
-                    qvar.connect(&q);
+                        // Workers must agree on when to stop iterating, since
+                        // they exchange data at every iteration.  Iteration stops
+                        // once no worker has produced new tuples and the
+                        // iteration count has reached that of all previous
+                        // epochs, beyond which traces contain no history that
+                        // can produce new tuples.
+                        let (sender, receiver) = new_exchange_operators(
+                            runtime,
+                            index,
+                            move |changed: bool| repeat_n(changed, workers),
+                            |any: &mut bool, changed: bool| *any |= changed,
+                        );
+                        let changed = p.apply2(&q, |p, q| !p.is_empty() || !q.is_empty());
+                        let quiescent = Rc::new(Cell::new(false));
+                        let quiescent_clone = quiescent.clone();
+                        child
+                            .add_exchange(sender, receiver, &changed)
+                            .inspect(move |any: &bool| quiescent_clone.set(!any));
 
-                    Ok((p.integrate_trace().export(), q.integrate_trace().export()))
-                })
-                .unwrap();
-            outp.consolidate::<OrdZSet<_, _>>()
-                .inspect(|zs: &OrdZSet<_, _>| println!("outp: {}", zs.len()));
-            outq.consolidate::<OrdZSet<_, _>>()
-                .inspect(|zs: &OrdZSet<_, _>| println!("outq: {}", zs.len()));
+                        let iteration = Cell::new(0);
+                        let max_iterations = Cell::new(0);
+                        let termination = move || {
+                            iteration.set(iteration.get() + 1);
+                            if quiescent.get() && iteration.get() >= max_iterations.get() {
+                                max_iterations.set(iteration.get());
+                                iteration.set(0);
+                                true
+                            } else {
+                                false
+                            }
+                        };
+
+                        Ok((
+                            termination,
+                            (p.integrate_trace().export(), q.integrate_trace().export()),
+                        ))
+                    })
+                    .unwrap();
+
+                outp.consolidate::<OrdZSet<_, _>>()
+                    .inspect(move |zs: &OrdZSet<_, _>| {
+                        p_size_clone.fetch_add(zs.len(), Ordering::Relaxed);
+                    });
+                outq.consolidate::<OrdZSet<_, _>>()
+                    .inspect(move |zs: &OrdZSet<_, _>| {
+                        q_size_clone.fetch_add(zs.len(), Ordering::Relaxed);
+                    });
+            })
         })
         .unwrap();
 
-        let graph = monitor.visualize_circuit();
-        fs::write("galen.dot", graph.to_dot()).unwrap();
-
-        root.step().unwrap();
+        for _ in 0..steps {
+            root.step().unwrap();
+        }
     });
 
     hruntime.join().unwrap();
+    let elapsed = start.elapsed();
+
+    println!(
+        "workers: {}, chunk size: {}, effort: {}, steps: {}",
+        workers, config.chunk_size, config.effort, steps
+    );
+    println!(
+        "{} input tuples in {:?}: {:.0} tuples/s",
+        input_tuples,
+        elapsed,
+        input_tuples as f64 / elapsed.as_secs_f64()
+    );
+    println!(
+        "p: {} tuples, q: {} tuples",
+        p_size.load(Ordering::Relaxed),
+        q_size.load(Ordering::Relaxed)
+    );
 }
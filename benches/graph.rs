@@ -0,0 +1,303 @@
+//! Graph benchmark with incremental updates.
+//!
+//! Loads a synthetic power-law graph generated using the R-MAT model with
+//! the parameters of the Graph500 benchmark, then applies a stream of random
+//! edge insertions and deletions while maintaining:
+//!
+//! * the set of vertices reachable from vertex 0 (a recursive query), and
+//! * the number of triangles in the graph (a three-way join).
+//!
+//! Usage:
+//!
+//! ```text
+//! cargo bench --bench graph --features bench -- [--scale N] \
+//!     [--edge-factor N] [--batch-size N] [--steps N] [--delete-ratio R]
+//! ```
+//!
+//! * `--scale` - the graph has `2^scale` vertices (default: 10).
+//! * `--edge-factor` - the graph initially has `edge-factor * 2^scale` edges
+//!   (default: 8).
+//! * `--batch-size` - number of edge updates per step (default: 100).
+//! * `--steps` - number of update steps (default: 100).
+//! * `--delete-ratio` - fraction of updates that delete an existing edge
+//!   (default: 0.5).
+//!
+//! The benchmark reports the latency of the initial load and the
+//! distribution of update latencies, along with the number of allocations
+//! per step.
+
+mod common;
+
+use common::BenchArgs;
+use dbsp::{
+    bench_support::{BenchReport, CountingAllocator, OperatorBench},
+    circuit::{Circuit, Stream},
+    operator::{DelayedFeedback, Generator, InputHandle},
+    trace::{
+        ord::{OrdIndexedZSet, OrdZSet},
+        Batch, BatchReader, Cursor,
+    },
+};
+use hashbrown::HashSet;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::{cell::Cell, rc::Rc};
+
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
+
+type Vertex = u32;
+type Weight = isize;
+type Edge = (Vertex, Vertex);
+type Triangle = (Vertex, Vertex, Vertex);
+
+/// Vertex from which reachability is computed.
+const ROOT: Vertex = 0;
+
+const USAGE: &str =
+    "usage: graph [--scale N] [--edge-factor N] [--batch-size N] [--steps N] [--delete-ratio R]";
+
+struct Config {
+    scale: u32,
+    edge_factor: usize,
+    batch_size: usize,
+    steps: usize,
+    delete_ratio: f64,
+}
+
+impl Config {
+    fn from_args() -> Self {
+        let mut config = Self {
+            scale: 10,
+            edge_factor: 8,
+            batch_size: 100,
+            steps: 100,
+            delete_ratio: 0.5,
+        };
+
+        let mut args = BenchArgs::new(USAGE);
+        while let Some(flag) = args.next_flag() {
+            match flag.as_str() {
+                "--scale" => config.scale = args.value(&flag),
+                "--edge-factor" => config.edge_factor = args.value(&flag),
+                "--batch-size" => config.batch_size = args.value(&flag),
+                "--steps" => config.steps = args.value(&flag),
+                "--delete-ratio" => config.delete_ratio = args.value(&flag),
+                _ => args.unknown(&flag),
+            }
+        }
+
+        if !(1..=31).contains(&config.scale) || !(0.0..=1.0).contains(&config.delete_ratio) {
+            args.fail("invalid arguments")
+        }
+        config
+    }
+}
+
+/// Undirected graph that generates random edge updates.
+///
+/// Edges are stored as `(u, v)` pairs with `u < v`.
+struct Graph {
+    scale: u32,
+    edges: Vec<Edge>,
+    edge_set: HashSet<Edge>,
+    rng: SmallRng,
+}
+
+impl Graph {
+    fn new(scale: u32) -> Self {
+        Self {
+            scale,
+            edges: Vec::new(),
+            edge_set: HashSet::new(),
+            rng: SmallRng::seed_from_u64(0),
+        }
+    }
+
+    // Generate a random edge using the R-MAT model with Graph500 parameters
+    // `a = 0.57`, `b = 0.19`, `c = 0.19`.
+    fn rmat_edge(&mut self) -> Edge {
+        let (mut u, mut v) = (0, 0);
+        for _ in 0..self.scale {
+            let p: f64 = self.rng.gen();
+            let (bit_u, bit_v) = if p < 0.57 {
+                (0, 0)
+            } else if p < 0.76 {
+                (0, 1)
+            } else if p < 0.95 {
+                (1, 0)
+            } else {
+                (1, 1)
+            };
+            u = (u << 1) | bit_u;
+            v = (v << 1) | bit_v;
+        }
+        (u.min(v), u.max(v))
+    }
+
+    /// Insert a random edge that is not yet in the graph.  Returns `None` if
+    /// no new edge was found after a bounded number of attempts.
+    fn insert(&mut self) -> Option<Edge> {
+        for _ in 0..100 {
+            let edge = self.rmat_edge();
+            if edge.0 != edge.1 && self.edge_set.insert(edge) {
+                self.edges.push(edge);
+                return Some(edge);
+            }
+        }
+        None
+    }
+
+    /// Delete a random edge.
+    fn delete(&mut self) -> Option<Edge> {
+        if self.edges.is_empty() {
+            return None;
+        }
+        let edge = self
+            .edges
+            .swap_remove(self.rng.gen_range(0..self.edges.len()));
+        self.edge_set.remove(&edge);
+        Some(edge)
+    }
+
+    /// Generate `n` random updates, deleting an existing edge with
+    /// probability `delete_ratio`.
+    fn updates(&mut self, n: usize, delete_ratio: f64) -> Vec<((Edge, ()), Weight)> {
+        (0..n)
+            .filter_map(|_| {
+                if self.rng.gen_bool(delete_ratio) {
+                    self.delete().map(|edge| ((edge, ()), -1))
+                } else {
+                    self.insert().map(|edge| ((edge, ()), 1))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Sum of weights in `batch`.
+fn weight_sum<K>(batch: &OrdZSet<K, Weight>) -> Weight
+where
+    K: Ord + Clone + Send + 'static,
+{
+    let mut sum = 0;
+    let mut cursor = batch.cursor();
+    while cursor.key_valid(batch) {
+        sum += cursor.weight(batch);
+        cursor.step_key(batch);
+    }
+    sum
+}
+
+/// Maintains the set of vertices reachable from `ROOT`.  Returns the
+/// changes to this set.
+fn reachability(
+    edges: &Stream<Circuit<()>, OrdZSet<Edge, Weight>>,
+) -> Stream<Circuit<()>, OrdZSet<Vertex, Weight>> {
+    let circuit = edges.circuit();
+
+    // Edges are undirected.
+    let edges = edges.plus(&edges.map_keys::<OrdZSet<_, _>, _>(|&(u, v)| (v, u)));
+
+    let mut first = true;
+    let roots = circuit.add_source(Generator::new(move || {
+        if first {
+            first = false;
+            OrdZSet::from_tuples((), vec![((ROOT, ()), 1)])
+        } else {
+            OrdZSet::from_tuples((), Vec::new())
+        }
+    }));
+
+    circuit
+        .fixedpoint(|child| {
+            let edges = edges
+                .delta0(child)
+                .index::<OrdIndexedZSet<Vertex, Vertex, Weight>>();
+            let roots = roots.delta0(child);
+            let reachable = <DelayedFeedback<_, OrdZSet<Vertex, Weight>>>::new(child);
+
+            let next = reachable
+                .stream()
+                .index_with::<OrdIndexedZSet<_, _, _>, _>(|&v| (v, ()))
+                .join_trace(&edges, |_v, &(), &w| w);
+            let reachable_new = roots.plus(&next).distinct_trace();
+            reachable.connect(&reachable_new);
+
+            Ok(reachable_new.integrate_trace().export())
+        })
+        .unwrap()
+        .consolidate()
+}
+
+/// Maintains the set of triangles `(a, b, c)`, `a < b < c`.  Returns the
+/// changes to this set.
+fn triangles(
+    edges: &Stream<Circuit<()>, OrdZSet<Edge, Weight>>,
+) -> Stream<Circuit<()>, OrdZSet<Triangle, Weight>> {
+    let by_src = edges.index::<OrdIndexedZSet<Vertex, Vertex, Weight>>();
+    let by_dst = edges.index_with::<OrdIndexedZSet<_, _, _>, _>(|&(a, b)| (b, a));
+    let closing = edges.index_with::<OrdIndexedZSet<_, _, _>, _>(|&(a, c)| ((a, c), ()));
+
+    // Wedges `a - b - c` with `a < b < c`.
+    let wedges = by_dst
+        .join_incremental::<_, _, OrdZSet<_, _>>(&by_src, |&b, &a, &c| ((a, c), b))
+        .index::<OrdIndexedZSet<_, _, _>>();
+
+    wedges.join_incremental(&closing, |&(a, c), &b, &()| (a, b, c))
+}
+
+fn main() {
+    let config = Config::from_args();
+
+    let mut graph = Graph::new(config.scale);
+    let initial_edges = config.edge_factor << config.scale;
+    let initial: Vec<_> = (0..initial_edges)
+        .filter_map(|_| graph.insert())
+        .map(|edge| ((edge, ()), 1))
+        .collect();
+
+    let reachable = Rc::new(Cell::new(0));
+    let triangle_count = Rc::new(Cell::new(0));
+
+    let mut input: Option<InputHandle<OrdZSet<Edge, Weight>>> = None;
+    let (reachable_clone, triangle_clone) = (reachable.clone(), triangle_count.clone());
+    let bench = OperatorBench::new(|circuit| {
+        let (edges, handle) = circuit.add_input::<OrdZSet<Edge, Weight>>();
+        input = Some(handle);
+
+        reachability(&edges)
+            .inspect(move |delta| reachable_clone.set(reachable_clone.get() + weight_sum(delta)));
+        triangles(&edges)
+            .inspect(move |delta| triangle_clone.set(triangle_clone.get() + weight_sum(delta)));
+    })
+    .unwrap();
+    let input = input.unwrap();
+
+    let edges = initial.len();
+    input.extend(initial);
+    let load = bench.step().unwrap();
+    println!(
+        "load: {} vertices, {} edges in {:?}; {} reachable from {}, {} triangles",
+        1u64 << config.scale,
+        edges,
+        load.latency,
+        reachable.get(),
+        ROOT,
+        triangle_count.get()
+    );
+
+    let mut report = BenchReport::default();
+    for _ in 0..config.steps {
+        input.extend(graph.updates(config.batch_size, config.delete_ratio));
+        report.steps.push(bench.step().unwrap());
+    }
+
+    println!("updates: {} per step, {}", config.batch_size, report);
+    println!(
+        "final: {} edges, {} reachable from {}, {} triangles",
+        graph.edges.len(),
+        reachable.get(),
+        ROOT,
+        triangle_count.get()
+    );
+}
@@ -0,0 +1,111 @@
+//! Per-operator micro-benchmarks.
+//!
+//! Each benchmark builds a circuit around a single operator and measures the
+//! latency of a step that feeds it one batch of synthetic updates.  A new
+//! circuit is built for every sample, so each sample starts from empty
+//! operator state.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use dbsp::{
+    bench_support::{BatchGenerator, CountingAllocator, OperatorBench},
+    operator::{DataDistribution, Generator},
+    trace::ord::OrdZSet,
+};
+
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
+
+const BATCH_SIZE: usize = 1_000;
+const KEYS: u64 = 100_000;
+
+fn uniform() -> BatchGenerator {
+    BatchGenerator::new(DataDistribution::Uniform { range: KEYS }, BATCH_SIZE)
+        .with_values(100)
+        .with_retractions(0.1)
+}
+
+fn zipf() -> BatchGenerator {
+    BatchGenerator::new(
+        DataDistribution::Zipf {
+            range: KEYS,
+            exponent: 1.0,
+        },
+        BATCH_SIZE,
+    )
+    .with_values(100)
+    .with_retractions(0.1)
+}
+
+// Benchmark the circuit built by `bench` using `iters` steps.
+fn bench_operator<F>(c: &mut Criterion, name: &str, bench: F)
+where
+    F: Fn() -> OperatorBench,
+{
+    c.bench_function(name, |b| {
+        b.iter_custom(|iters| bench().run(iters as usize).unwrap().total_time())
+    });
+
+    // Report allocations, which criterion does not measure.
+    println!("{}: {}", name, bench().run(100).unwrap());
+}
+
+fn joins(c: &mut Criterion) {
+    for (name, keys) in [
+        (
+            "join_incremental/uniform",
+            uniform as fn() -> BatchGenerator,
+        ),
+        ("join_incremental/zipf", zipf),
+    ] {
+        bench_operator(c, name, || {
+            let mut left = keys();
+            let mut right = keys().with_seed(1);
+            OperatorBench::binary(
+                move || left.indexed_zset(),
+                move || right.indexed_zset(),
+                |left, right| {
+                    left.join_incremental::<_, _, OrdZSet<_, _>>(right, |&k, &v1, &v2| (k, v1, v2))
+                },
+            )
+            .unwrap()
+        });
+    }
+}
+
+fn aggregates(c: &mut Criterion) {
+    bench_operator(c, "aggregate_incremental/count", || {
+        let mut input = uniform();
+        OperatorBench::unary(
+            move || input.indexed_zset(),
+            |input| input.aggregate_incremental::<_, OrdZSet<_, _>>(|&k, vals| (k, vals.len())),
+        )
+        .unwrap()
+    });
+
+    bench_operator(c, "distinct_incremental", || {
+        let mut input = zipf();
+        OperatorBench::unary(move || input.zset(), |input| input.distinct_incremental()).unwrap()
+    });
+}
+
+fn merges(c: &mut Criterion) {
+    bench_operator(c, "sum_batches/3", || {
+        let inputs = [uniform(), uniform().with_seed(1), uniform().with_seed(2)];
+        OperatorBench::new(|circuit| {
+            let streams: Vec<_> = inputs
+                .into_iter()
+                .map(|mut input| circuit.add_source(Generator::new(move || input.zset())))
+                .collect();
+            streams[0].sum_batches(&streams[1..]).inspect(|_| ());
+        })
+        .unwrap()
+    });
+
+    bench_operator(c, "integrate_trace", || {
+        let mut input = uniform();
+        OperatorBench::unary(move || input.zset(), |input| input.integrate_trace()).unwrap()
+    });
+}
+
+criterion_group!(benches, joins, aggregates, merges);
+criterion_main!(benches);
@@ -0,0 +1,417 @@
+//! Incremental view maintenance benchmark derived from TPC-H.
+//!
+//! Maintains simplified versions of the following TPC-H queries over
+//! synthetic `customer`, `orders`, and `lineitem` relations:
+//!
+//! * Q1 (pricing summary report) - aggregate over `lineitem` grouped by
+//!   return flag and line status.
+//! * Q3 (shipping priority) - join of all three relations, aggregated by
+//!   order and followed by a top-10 by revenue.
+//! * Q13 (customer distribution) - left outer join of `customer` with
+//!   `orders`, counting orders per customer and then customers per order
+//!   count.  The outer join is computed as the union of an incremental inner
+//!   join and the set of customers without orders, which is maintained
+//!   incrementally as the difference between all customers and the
+//!   customers that have at least one order.
+//!
+//! After loading the initial database, the benchmark runs a sequence of
+//! refreshes.  Each refresh applies the TPC-H refresh functions in a single
+//! step: RF1 inserts `SF * 1500` new orders along with their line items and
+//! RF2 deletes the same number of the oldest orders along with their line
+//! items.
+//!
+//! Usage:
+//!
+//! ```text
+//! cargo bench --bench tpch --features bench -- [--scale-factors SF,...] \
+//!     [--refreshes N] [--queries Q,...]
+//! ```
+//!
+//! * `--scale-factors` - comma-separated list of scale factors to run the
+//!   benchmark at (default: `0.01,0.02,0.05`).  Scale factor 1 corresponds
+//!   to 150,000 customers and 1,500,000 orders.
+//! * `--refreshes` - number of refreshes per scale factor (default: 20).
+//! * `--queries` - comma-separated list of queries to maintain, out of `q1`,
+//!   `q3`, and `q13` (default: all).
+//!
+//! For each scale factor, the benchmark reports the latency of the initial
+//! load and the distribution of refresh latencies.
+
+mod common;
+
+use common::BenchArgs;
+use dbsp::{
+    bench_support::{BenchReport, CountingAllocator, OperatorBench},
+    circuit::{Circuit, Stream},
+    operator::InputHandle,
+    trace::ord::{OrdIndexedZSet, OrdZSet},
+};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::collections::VecDeque;
+
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
+
+type Weight = isize;
+
+/// Date, in days since 1992-01-01.
+type Date = u32;
+
+/// `(custkey, mktsegment)`.
+type Customer = (u32, u8);
+
+/// `(orderkey, custkey, orderdate, orderpriority)`.
+type Order = (u64, u32, Date, u8);
+
+/// `(orderkey, linenumber, quantity, extendedprice, discount, returnflag,
+/// linestatus, shipdate)`.  Prices are in cents and discounts in percent.
+type LineItem = (u64, u8, i64, i64, i64, u8, u8, Date);
+
+/// Last order date: 1998-12-31 minus 151 days.
+const END_DATE: Date = 2405;
+
+/// Date that determines return flags and line statuses: 1995-06-17.
+const CURRENT_DATE: Date = 1263;
+
+/// Q1 ship date cutoff: 1998-12-01 minus 90 days.
+const Q1_CUTOFF: Date = 2436;
+
+/// Q3 date: 1995-03-15.
+const Q3_DATE: Date = 1169;
+
+/// Q3 market segment.
+const BUILDING: u8 = 1;
+
+const USAGE: &str = "usage: tpch [--scale-factors SF,...] [--refreshes N] [--queries Q,...]";
+
+struct Config {
+    scale_factors: Vec<f64>,
+    refreshes: usize,
+    queries: Vec<String>,
+}
+
+impl Config {
+    fn from_args() -> Self {
+        let mut config = Self {
+            scale_factors: vec![0.01, 0.02, 0.05],
+            refreshes: 20,
+            queries: vec!["q1".to_string(), "q3".to_string(), "q13".to_string()],
+        };
+
+        let mut args = BenchArgs::new(USAGE);
+        while let Some(flag) = args.next_flag() {
+            match flag.as_str() {
+                "--scale-factors" => config.scale_factors = args.list(&flag),
+                "--refreshes" => config.refreshes = args.value(&flag),
+                "--queries" => config.queries = args.list(&flag),
+                _ => args.unknown(&flag),
+            }
+        }
+
+        if config.scale_factors.iter().any(|&sf| sf <= 0.0)
+            || config
+                .queries
+                .iter()
+                .any(|q| !["q1", "q3", "q13"].contains(&q.as_str()))
+        {
+            args.fail("invalid arguments")
+        }
+        config
+    }
+
+    fn query(&self, query: &str) -> bool {
+        self.queries.iter().any(|q| q == query)
+    }
+}
+
+/// Generator of the database and its refreshes at a given scale factor.
+struct Database {
+    num_customers: u32,
+    refresh_size: usize,
+    next_orderkey: u64,
+    // Live orders in insertion order, along with their line items.
+    orders: VecDeque<(Order, Vec<LineItem>)>,
+    rng: SmallRng,
+}
+
+impl Database {
+    fn new(scale_factor: f64) -> Self {
+        Self {
+            num_customers: ((150_000.0 * scale_factor) as u32).max(3),
+            refresh_size: ((1_500.0 * scale_factor) as usize).max(1),
+            next_orderkey: 1,
+            orders: VecDeque::new(),
+            rng: SmallRng::seed_from_u64(0),
+        }
+    }
+
+    fn customers(&mut self) -> Vec<((Customer, ()), Weight)> {
+        (1..=self.num_customers)
+            .map(|custkey| (((custkey, self.rng.gen_range(0..5)), ()), 1))
+            .collect()
+    }
+
+    // Generate a new order with 1 to 7 line items.
+    fn order(&mut self) -> (Order, Vec<LineItem>) {
+        let orderkey = self.next_orderkey;
+        self.next_orderkey += 1;
+
+        // As in TPC-H, every third customer never places an order.
+        let mut custkey = self.rng.gen_range(1..=self.num_customers);
+        if custkey % 3 == 0 {
+            custkey -= 1;
+        }
+        let orderdate = self.rng.gen_range(0..=END_DATE);
+        let order = (orderkey, custkey, orderdate, self.rng.gen_range(0..5));
+
+        let lineitems = (1..=self.rng.gen_range(1..=7))
+            .map(|linenumber| {
+                let quantity = self.rng.gen_range(1..=50);
+                let price = quantity * self.rng.gen_range(90_000..=200_000);
+                let shipdate = orderdate + self.rng.gen_range(1..=121);
+                let (returnflag, linestatus) = if shipdate <= CURRENT_DATE {
+                    (if self.rng.gen_bool(0.5) { b'R' } else { b'A' }, b'F')
+                } else {
+                    (b'N', b'O')
+                };
+                (
+                    orderkey,
+                    linenumber,
+                    quantity,
+                    price,
+                    self.rng.gen_range(0..=10),
+                    returnflag,
+                    linestatus,
+                    shipdate,
+                )
+            })
+            .collect();
+
+        (order, lineitems)
+    }
+
+    /// Insert `n` new orders.
+    fn insert(
+        &mut self,
+        n: usize,
+        orders: &mut Vec<((Order, ()), Weight)>,
+        lineitems: &mut Vec<((LineItem, ()), Weight)>,
+    ) {
+        for _ in 0..n {
+            let (order, items) = self.order();
+            orders.push(((order, ()), 1));
+            lineitems.extend(items.iter().map(|item| ((*item, ()), 1)));
+            self.orders.push_back((order, items));
+        }
+    }
+
+    /// Delete the `n` oldest orders.
+    fn delete(
+        &mut self,
+        n: usize,
+        orders: &mut Vec<((Order, ()), Weight)>,
+        lineitems: &mut Vec<((LineItem, ()), Weight)>,
+    ) {
+        for (order, items) in self.orders.drain(..n.min(self.orders.len())) {
+            orders.push(((order, ()), -1));
+            lineitems.extend(items.into_iter().map(|item| ((item, ()), -1)));
+        }
+    }
+}
+
+/// `(returnflag, linestatus, sum_qty, sum_base_price, sum_disc_price,
+/// count_order)`.
+type Q1Row = (u8, u8, i64, i64, i64, i64);
+
+/// Q1: pricing summary report.
+fn q1(
+    lineitems: &Stream<Circuit<()>, OrdZSet<LineItem, Weight>>,
+) -> Stream<Circuit<()>, OrdZSet<Q1Row, Weight>> {
+    lineitems
+        .filter_keys::<OrdZSet<_, _>, _>(|item| item.7 <= Q1_CUTOFF)
+        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(_, _, qty, price, disc, flag, status, _)| {
+            ((flag, status), (qty, price, disc))
+        })
+        .aggregate_incremental(|&(flag, status), items| {
+            let (mut sum_qty, mut sum_price, mut sum_disc_price, mut count) = (0, 0, 0, 0);
+            for (item, w) in items.iter() {
+                let (qty, price, disc) = **item;
+                let w = *w as i64;
+                sum_qty += qty * w;
+                sum_price += price * w;
+                sum_disc_price += price * (100 - disc) / 100 * w;
+                count += w;
+            }
+            (flag, status, sum_qty, sum_price, sum_disc_price, count)
+        })
+}
+
+/// `(orderkey, orderdate, orderpriority, revenue)`.
+type Q3Row = (u64, Date, u8, i64);
+
+/// Q3: shipping priority.
+///
+/// Outputs the 10 rows with the highest revenue.
+fn q3(
+    customers: &Stream<Circuit<()>, OrdZSet<Customer, Weight>>,
+    orders: &Stream<Circuit<()>, OrdZSet<Order, Weight>>,
+    lineitems: &Stream<Circuit<()>, OrdZSet<LineItem, Weight>>,
+) -> Stream<Circuit<()>, OrdZSet<Q3Row, Weight>> {
+    let customers = customers
+        .filter_keys::<OrdZSet<_, _>, _>(|&(_, segment)| segment == BUILDING)
+        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(custkey, _)| (custkey, ()));
+    let orders = orders
+        .filter_keys::<OrdZSet<_, _>, _>(|order| order.2 < Q3_DATE)
+        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(orderkey, custkey, date, priority)| {
+            (custkey, (orderkey, date, priority))
+        });
+    let lineitems = lineitems
+        .filter_keys::<OrdZSet<_, _>, _>(|item| item.7 > Q3_DATE)
+        .index_with::<OrdIndexedZSet<_, _, _>, _>(|item| (item.0, item.3 * (100 - item.4) / 100));
+
+    customers
+        .join_incremental::<_, _, OrdZSet<_, _>>(&orders, |_, &(), &(orderkey, date, priority)| {
+            (orderkey, (date, priority))
+        })
+        .index::<OrdIndexedZSet<_, _, _>>()
+        .join_incremental::<_, _, OrdZSet<_, _>>(
+            &lineitems,
+            |&orderkey, &(date, priority), &revenue| ((orderkey, date, priority), revenue),
+        )
+        .index::<OrdIndexedZSet<_, _, _>>()
+        .aggregate_incremental::<_, OrdZSet<_, _>>(|&(orderkey, date, priority), revenues| {
+            let revenue = revenues
+                .iter()
+                .map(|(revenue, w)| **revenue * *w as i64)
+                .sum::<i64>();
+            (orderkey, date, priority, revenue)
+        })
+        .topk_with_offset(10, 0, |a: &Q3Row, b: &Q3Row| {
+            b.3.cmp(&a.3).then(a.1.cmp(&b.1))
+        })
+}
+
+/// Q13: customer distribution.
+///
+/// Outputs `(order_count, customer_count)` tuples.
+fn q13(
+    customers: &Stream<Circuit<()>, OrdZSet<Customer, Weight>>,
+    orders: &Stream<Circuit<()>, OrdZSet<Order, Weight>>,
+) -> Stream<Circuit<()>, OrdZSet<(Weight, Weight), Weight>> {
+    let custkeys = customers.map_keys::<OrdZSet<_, _>, _>(|&(custkey, _)| custkey);
+    let custkeys_indexed =
+        custkeys.index_with::<OrdIndexedZSet<_, _, _>, _>(|&custkey| (custkey, ()));
+
+    // customer LEFT OUTER JOIN orders.
+    let matched = custkeys_indexed.join_incremental::<_, _, OrdZSet<_, _>>(
+        &orders.index_with::<OrdIndexedZSet<_, _, _>, _>(|&(orderkey, custkey, _, _)| {
+            (custkey, orderkey)
+        }),
+        |&custkey, &(), &orderkey| (custkey, Some(orderkey)),
+    );
+
+    // Customers without orders: all customers minus those with at least one
+    // order.  Both terms are maintained incrementally, so the cost of a
+    // refresh does not depend on the size of the database.
+    let with_orders = orders
+        .map_keys::<OrdZSet<_, _>, _>(|&(_, custkey, _, _)| custkey)
+        .distinct_incremental()
+        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&custkey| (custkey, ()));
+    let unmatched = custkeys
+        .minus(
+            &custkeys_indexed
+                .join_incremental::<_, _, OrdZSet<_, _>>(&with_orders, |&custkey, &(), &()| {
+                    custkey
+                }),
+        )
+        .map_keys::<OrdZSet<_, _>, _>(|&custkey| (custkey, None));
+
+    matched
+        .plus(&unmatched)
+        .index::<OrdIndexedZSet<_, _, _>>()
+        .aggregate_incremental::<_, OrdZSet<_, _>>(|&custkey, orders| {
+            let count = orders
+                .iter()
+                .filter(|(orderkey, _)| orderkey.is_some())
+                .map(|(_, w)| *w)
+                .sum::<Weight>();
+            (custkey, count)
+        })
+        .index_with::<OrdIndexedZSet<_, _, _>, _>(|&(custkey, count)| (count, custkey))
+        .aggregate_incremental(|&count, customers| {
+            (count, customers.iter().map(|(_, w)| *w).sum::<Weight>())
+        })
+}
+
+/// Input handles of the `customer`, `orders`, and `lineitem` relations.
+type InputHandles = (
+    InputHandle<OrdZSet<Customer, Weight>>,
+    InputHandle<OrdZSet<Order, Weight>>,
+    InputHandle<OrdZSet<LineItem, Weight>>,
+);
+
+fn run(config: &Config, scale_factor: f64) {
+    let mut db = Database::new(scale_factor);
+
+    let mut handles: Option<InputHandles> = None;
+    let bench = OperatorBench::new(|circuit| {
+        let (customers, customers_handle) = circuit.add_input();
+        let (orders, orders_handle) = circuit.add_input();
+        let (lineitems, lineitems_handle) = circuit.add_input();
+        handles = Some((customers_handle, orders_handle, lineitems_handle));
+
+        if config.query("q1") {
+            q1(&lineitems).inspect(|_| ());
+        }
+        if config.query("q3") {
+            q3(&customers, &orders, &lineitems).inspect(|_| ());
+        }
+        if config.query("q13") {
+            q13(&customers, &orders).inspect(|_| ());
+        }
+    })
+    .unwrap();
+    let (customers, orders, lineitems) = handles.unwrap();
+
+    let (mut order_updates, mut lineitem_updates) = (Vec::new(), Vec::new());
+    customers.extend(db.customers());
+    db.insert(
+        db.refresh_size * 1000,
+        &mut order_updates,
+        &mut lineitem_updates,
+    );
+    let tuples = lineitem_updates.len();
+    orders.extend(order_updates.drain(..));
+    lineitems.extend(lineitem_updates.drain(..));
+    let load = bench.step().unwrap();
+
+    let mut report = BenchReport::default();
+    for _ in 0..config.refreshes {
+        db.insert(db.refresh_size, &mut order_updates, &mut lineitem_updates);
+        db.delete(db.refresh_size, &mut order_updates, &mut lineitem_updates);
+        orders.extend(order_updates.drain(..));
+        lineitems.extend(lineitem_updates.drain(..));
+        report.steps.push(bench.step().unwrap());
+    }
+
+    println!(
+        "SF {}: load of {} customers, {} orders, {} line items in {:?}",
+        scale_factor,
+        db.num_customers,
+        db.refresh_size * 1000,
+        tuples,
+        load.latency
+    );
+    println!(
+        "SF {}: refreshes of {} orders: {}",
+        scale_factor, db.refresh_size, report
+    );
+}
+
+fn main() {
+    let config = Config::from_args();
+
+    for &scale_factor in config.scale_factors.iter() {
+        run(&config, scale_factor);
+    }
+}
@@ -21,6 +21,21 @@ pub use zset::{IndexedZSet, ZSet};
 pub trait HasZero {
     fn is_zero(&self) -> bool;
     fn zero() -> Self;
+
+    /// True if the value is close enough to zero to be dropped when
+    /// consolidating weights.
+    ///
+    /// Consolidation (see [`consolidate`](`crate::trace::consolidation::consolidate`))
+    /// and trace merges discard tuples whose accumulated weight is
+    /// negligible.  By default, only zero is negligible.  Weight types with
+    /// inexact arithmetic, e.g., floating-point weights or approximate
+    /// semirings, can override this method to also treat values within some
+    /// epsilon of zero as negligible.  Otherwise, rounding errors leave
+    /// behind tuples whose weights never cancel out exactly, and traces grow
+    /// without bound.
+    fn is_negligible(&self) -> bool {
+        self.is_zero()
+    }
 }
 
 /// Implement `HasZero` for types that already implement `Zero`.
@@ -61,6 +76,9 @@ where
     fn zero() -> Self {
         Self(T::zero())
     }
+    fn is_negligible(&self) -> bool {
+        self.0.is_negligible()
+    }
 }
 
 impl<T> HasZero for Rc<T>
@@ -73,6 +91,9 @@ where
     fn zero() -> Self {
         Rc::new(T::zero())
     }
+    fn is_negligible(&self) -> bool {
+        T::is_negligible(self.as_ref())
+    }
 }
 
 /// A trait for types that have a one value.
@@ -0,0 +1,414 @@
+//! Support for benchmarking individual operators.
+//!
+//! [`OperatorBench`] builds a micro-circuit around one or two operators,
+//! feeds them batches produced by a [`BatchGenerator`] and measures the
+//! latency of each step.  When [`CountingAllocator`] is installed as the
+//! global allocator of the benchmark binary, it additionally reports the
+//! number of allocations and allocated bytes per step.
+//!
+//! The harness is independent of any benchmarking framework.  To integrate
+//! with `criterion`, run the benchmark from `Bencher::iter_custom`:
+//!
+//! ```ignore
+//! c.bench_function("join", |b| {
+//!     b.iter_custom(|iters| bench.run(iters as usize).unwrap().total_time())
+//! });
+//! ```
+//!
+//! See `benches/operators.rs` for complete examples.
+//!
+//! This module is only available with the `bench` feature, which benchmark
+//! targets require.
+
+use crate::{
+    circuit::{schedule::Error as SchedulerError, Circuit, Root, Stream},
+    operator::{load_generator::Sampler, DataDistribution, Generator},
+    trace::{
+        ord::{OrdIndexedZSet, OrdZSet},
+        Batch,
+    },
+};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fmt::{self, Display},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Global allocator that counts allocations.
+///
+/// Forwards all requests to the system allocator.  Install it in a benchmark
+/// binary to report allocations in [`BenchReport`]:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOC: CountingAllocator = CountingAllocator;
+/// ```
+///
+/// Without it, all allocation counters remain zero.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+// Source of keys of a `BatchGenerator`.
+#[derive(Clone)]
+enum Keys {
+    Random(Sampler),
+    // Consecutive keys starting from the given one.
+    Sequential(u64),
+}
+
+/// Generator of synthetic input batches.
+///
+/// Each batch contains `batch_size` updates with keys drawn from a
+/// [`DataDistribution`] or generated sequentially, and values drawn
+/// uniformly from `0..values`.  A fraction of updates can be generated as
+/// retractions (weight `-1`) to exercise the handling of negative weights.
+/// The generator is seeded, so that repeated runs of a benchmark see
+/// identical inputs.
+#[derive(Clone)]
+pub struct BatchGenerator {
+    keys: Keys,
+    batch_size: usize,
+    values: u64,
+    retractions: f64,
+    rng: SmallRng,
+}
+
+impl BatchGenerator {
+    /// Create a generator of batches of `batch_size` updates with keys drawn
+    /// from `keys`.
+    pub fn new(keys: DataDistribution, batch_size: usize) -> Self {
+        Self::with_keys(Keys::Random(Sampler::new(&keys)), batch_size)
+    }
+
+    /// Create a generator of batches of `batch_size` updates with consecutive
+    /// keys that never repeat, e.g., ever-increasing event ids.
+    pub fn sequential(batch_size: usize) -> Self {
+        Self::with_keys(Keys::Sequential(0), batch_size)
+    }
+
+    fn with_keys(keys: Keys, batch_size: usize) -> Self {
+        Self {
+            keys,
+            batch_size,
+            values: 1,
+            retractions: 0.0,
+            rng: SmallRng::seed_from_u64(0),
+        }
+    }
+
+    /// Draw values uniformly from `0..values` (default: `1`).
+    pub fn with_values(mut self, values: u64) -> Self {
+        assert!(values > 0, "BatchGenerator: values must be positive");
+        self.values = values;
+        self
+    }
+
+    /// Generate a fraction `ratio` of updates as retractions (default: `0`).
+    pub fn with_retractions(mut self, ratio: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&ratio),
+            "BatchGenerator: ratio must be between 0 and 1"
+        );
+        self.retractions = ratio;
+        self
+    }
+
+    /// Use `seed` to initialize the random number generator.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = SmallRng::seed_from_u64(seed);
+        self
+    }
+
+    fn key(&mut self) -> u64 {
+        match &mut self.keys {
+            Keys::Random(sampler) => sampler.sample(&mut self.rng),
+            Keys::Sequential(next) => {
+                *next += 1;
+                *next - 1
+            }
+        }
+    }
+
+    fn update(&mut self) -> (u64, u64, isize) {
+        let key = self.key();
+        let val = self.rng.gen_range(0..self.values);
+        let weight = if self.rng.gen_bool(self.retractions) {
+            -1
+        } else {
+            1
+        };
+        (key, val, weight)
+    }
+
+    /// Generate a batch of keys.
+    pub fn zset(&mut self) -> OrdZSet<u64, isize> {
+        let tuples = (0..self.batch_size)
+            .map(|_| {
+                let (key, _val, weight) = self.update();
+                ((key, ()), weight)
+            })
+            .collect();
+        OrdZSet::from_tuples((), tuples)
+    }
+
+    /// Generate a batch of key/value pairs.
+    pub fn indexed_zset(&mut self) -> OrdIndexedZSet<u64, u64, isize> {
+        let tuples = (0..self.batch_size)
+            .map(|_| {
+                let (key, val, weight) = self.update();
+                ((key, val), weight)
+            })
+            .collect();
+        OrdIndexedZSet::from_tuples((), tuples)
+    }
+}
+
+/// Measurements of a single step of an [`OperatorBench`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StepMeasurement {
+    /// Wall-clock time of the step.
+    pub latency: Duration,
+    /// Number of allocations performed during the step.
+    pub allocations: u64,
+    /// Number of bytes allocated during the step.
+    pub allocated_bytes: u64,
+}
+
+/// Measurements of a sequence of steps.
+#[derive(Clone, Debug, Default)]
+pub struct BenchReport {
+    /// Measurements of individual steps in the order of execution.
+    pub steps: Vec<StepMeasurement>,
+}
+
+impl BenchReport {
+    /// Total time of all steps.
+    pub fn total_time(&self) -> Duration {
+        self.steps.iter().map(|step| step.latency).sum()
+    }
+
+    /// Average step latency.
+    pub fn mean_latency(&self) -> Duration {
+        if self.steps.is_empty() {
+            Duration::from_secs(0)
+        } else {
+            self.total_time() / self.steps.len() as u32
+        }
+    }
+
+    /// Step latency at percentile `p`, e.g., `0.99`.
+    pub fn percentile_latency(&self, p: f64) -> Duration {
+        let mut latencies: Vec<_> = self.steps.iter().map(|step| step.latency).collect();
+        if latencies.is_empty() {
+            return Duration::from_secs(0);
+        }
+        latencies.sort();
+        let index = ((latencies.len() - 1) as f64 * p).round() as usize;
+        latencies[index.min(latencies.len() - 1)]
+    }
+
+    /// Average number of allocations per step.
+    pub fn allocations_per_step(&self) -> f64 {
+        if self.steps.is_empty() {
+            0.0
+        } else {
+            self.steps.iter().map(|step| step.allocations).sum::<u64>() as f64
+                / self.steps.len() as f64
+        }
+    }
+
+    /// Average number of bytes allocated per step.
+    pub fn bytes_per_step(&self) -> f64 {
+        if self.steps.is_empty() {
+            0.0
+        } else {
+            self.steps
+                .iter()
+                .map(|step| step.allocated_bytes)
+                .sum::<u64>() as f64
+                / self.steps.len() as f64
+        }
+    }
+}
+
+impl Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} steps: mean {:?}, p50 {:?}, p99 {:?}, {:.1} allocations/step, {:.0} bytes/step",
+            self.steps.len(),
+            self.mean_latency(),
+            self.percentile_latency(0.5),
+            self.percentile_latency(0.99),
+            self.allocations_per_step(),
+            self.bytes_per_step()
+        )
+    }
+}
+
+/// A micro-circuit used to benchmark individual operators.
+///
+/// The circuit consists of one or two generator sources that produce a new
+/// input batch at each step, the operators under test, and a sink that
+/// consumes their output.  Since inputs are produced inside the circuit,
+/// step measurements include the time to generate inputs, which should be
+/// small compared to the operators being measured.
+pub struct OperatorBench {
+    root: Root,
+}
+
+impl OperatorBench {
+    /// Build a benchmark circuit using `constructor`.
+    pub fn new<F>(constructor: F) -> Result<Self, SchedulerError>
+    where
+        F: FnOnce(&mut Circuit<()>),
+    {
+        Ok(Self {
+            root: Root::build(constructor)?,
+        })
+    }
+
+    /// Benchmark a unary operator.
+    ///
+    /// At each step, `input` produces a new input batch and `operator` builds
+    /// the operators under test on top of the input stream.
+    pub fn unary<T, O, G, F>(input: G, operator: F) -> Result<Self, SchedulerError>
+    where
+        T: Clone + 'static,
+        O: Clone + 'static,
+        G: FnMut() -> T + 'static,
+        F: FnOnce(&Stream<Circuit<()>, T>) -> Stream<Circuit<()>, O>,
+    {
+        Self::new(|circuit| {
+            let input = circuit.add_source(Generator::new(input));
+            operator(&input).inspect(|_| ());
+        })
+    }
+
+    /// Benchmark a binary operator.
+    ///
+    /// Like [`Self::unary`], but with two input streams.
+    pub fn binary<T1, T2, O, G1, G2, F>(
+        input1: G1,
+        input2: G2,
+        operator: F,
+    ) -> Result<Self, SchedulerError>
+    where
+        T1: Clone + 'static,
+        T2: Clone + 'static,
+        O: Clone + 'static,
+        G1: FnMut() -> T1 + 'static,
+        G2: FnMut() -> T2 + 'static,
+        F: FnOnce(&Stream<Circuit<()>, T1>, &Stream<Circuit<()>, T2>) -> Stream<Circuit<()>, O>,
+    {
+        Self::new(|circuit| {
+            let input1 = circuit.add_source(Generator::new(input1));
+            let input2 = circuit.add_source(Generator::new(input2));
+            operator(&input1, &input2).inspect(|_| ());
+        })
+    }
+
+    /// Evaluate one step of the circuit.
+    pub fn step(&self) -> Result<StepMeasurement, SchedulerError> {
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+        let start = Instant::now();
+
+        self.root.step()?;
+
+        Ok(StepMeasurement {
+            latency: start.elapsed(),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+            allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes,
+        })
+    }
+
+    /// Evaluate `steps` steps of the circuit.
+    ///
+    /// Stateful operators accumulate state across calls, so the latency of
+    /// later steps reflects the cost of processing updates against a larger
+    /// state.
+    pub fn run(&self, steps: usize) -> Result<BenchReport, SchedulerError> {
+        let steps = (0..steps)
+            .map(|_| self.step())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BenchReport { steps })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BatchGenerator, OperatorBench};
+    use crate::{
+        operator::DataDistribution,
+        trace::{ord::OrdZSet, Batch, BatchReader},
+    };
+
+    #[test]
+    fn operator_bench_test() {
+        let mut generator = BatchGenerator::sequential(10);
+        let batch = generator.zset();
+        assert_eq!(batch.len(), 10);
+        assert_eq!(
+            generator.zset(),
+            OrdZSet::from_tuples((), (10..20).map(|k| ((k, ()), 1)).collect())
+        );
+
+        let mut left = BatchGenerator::new(DataDistribution::Uniform { range: 100 }, 100)
+            .with_values(10)
+            .with_retractions(0.2);
+        let mut right = BatchGenerator::new(
+            DataDistribution::Zipf {
+                range: 100,
+                exponent: 1.0,
+            },
+            100,
+        )
+        .with_seed(1);
+
+        let bench = OperatorBench::binary(
+            move || left.indexed_zset(),
+            move || right.indexed_zset(),
+            |left, right| {
+                left.join_incremental::<_, _, OrdZSet<_, _>>(right, |&k, &v1, &v2| (k, v1, v2))
+            },
+        )
+        .unwrap();
+
+        let report = bench.run(5).unwrap();
+        assert_eq!(report.steps.len(), 5);
+        assert!(report.percentile_latency(0.0) <= report.percentile_latency(1.0));
+        assert_eq!(
+            report.total_time(),
+            report.steps.iter().map(|s| s.latency).sum()
+        );
+    }
+}
@@ -89,3 +89,20 @@ macro_rules! circuit_cache_key {
         }
     }
 }
+
+/// Number of values created and reused via a circuit cache key type, reported
+/// by [`Circuit::explain`](`crate::circuit::Circuit::explain`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of cache misses, i.e., derived streams constructed.
+    pub created: usize,
+    /// Number of cache hits, i.e., derived streams reused.
+    pub reused: usize,
+}
+
+/// Short name of cache key type `K` used in reports, e.g., `TraceId`.
+pub(crate) fn key_name<K>() -> &'static str {
+    let name = std::any::type_name::<K>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
@@ -28,28 +28,32 @@
 
 use std::{
     borrow::Cow,
-    cell::{Ref, RefCell, RefMut, UnsafeCell},
-    collections::HashMap,
+    cell::{Cell, Ref, RefCell, RefMut, UnsafeCell},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt,
     fmt::{Debug, Display, Write},
     marker::PhantomData,
+    mem::replace,
     rc::Rc,
+    time::Instant,
 };
 
 use crate::{
     circuit::{
-        cache::{CircuitCache, CircuitStoreMarker},
+        cache::{key_name, CacheStats, CircuitCache, CircuitStoreMarker},
         operator_traits::{
-            BinaryOperator, Data, ImportOperator, NaryOperator, SinkOperator, SourceOperator,
-            StrictUnaryOperator, UnaryOperator,
+            BinaryOperator, Data, ImportOperator, NaryOperator, Operator, SinkOperator,
+            SourceOperator, StrictUnaryOperator, UnaryOperator,
         },
         schedule::{
             DynamicScheduler, Error as SchedulerError, Executor, IterativeExecutor, OnceExecutor,
             Scheduler,
         },
         trace::{CircuitEvent, SchedulerEvent},
+        StepStats, StepSummary,
     },
     circuit_cache_key,
+    trace::EffortConfig,
 };
 use typedmap::{TypedMap, TypedMapKey};
 
@@ -68,6 +72,11 @@ struct StreamValue<D> {
     /// an owned value rather than a borrow.  See description of
     /// [ownership-aware scheduling](`OwnershipPreference`) for details.
     tokens: usize,
+    /// `true` if the value was produced without evaluating the operator
+    /// that writes to the stream, because the operator was idle (see
+    /// [`UnaryOperator::idle_output`]).  Idle values are empty, which allows
+    /// consumers of the stream to skip evaluation in turn.
+    idle: bool,
 }
 
 impl<D> StreamValue<D> {
@@ -76,6 +85,7 @@ impl<D> StreamValue<D> {
             val: None,
             consumers: 0,
             tokens: 0,
+            idle: false,
         }
     }
 }
@@ -224,6 +234,7 @@ where
     /// The caller must have exclusive access to the current stream.
     unsafe fn put(&self, d: D) {
         let val = &mut *self.val.get();
+        val.idle = false;
         // If the stream is not connected to any consumers, drop the output
         // on the floor.
         if val.consumers > 0 {
@@ -231,6 +242,27 @@ where
             val.val = Some(d);
         }
     }
+
+    /// Puts the output of an idle operator in the stream (see
+    /// [`StreamValue::idle`]).
+    ///
+    /// #Safety
+    ///
+    /// The caller must have exclusive access to the current stream.
+    unsafe fn put_idle(&self, d: D) {
+        self.put(d);
+        (*self.val.get()).idle = true;
+    }
+
+    /// `true` if the current value in the stream was produced by an idle
+    /// operator.
+    ///
+    /// #Safety
+    ///
+    /// The caller must have exclusive access to the current stream.
+    unsafe fn is_idle(&self) -> bool {
+        (*self.val.get()).idle
+    }
 }
 
 /// Stream whose final value is exported to the parent circuit.
@@ -289,6 +321,23 @@ pub trait Node {
     /// another node).
     unsafe fn eval(&mut self) -> Result<(), SchedulerError>;
 
+    /// Try to skip evaluation of an idle node.
+    ///
+    /// Returns `true` if the node's inputs are idle and the operator can
+    /// compute its output without evaluation (see
+    /// [`UnaryOperator::idle_output`](super::operator_traits::UnaryOperator::idle_output)),
+    /// in which case the node consumes its inputs and pushes an idle value
+    /// to its output stream without evaluating the operator.  Returns
+    /// `false` without modifying any streams otherwise.  The default
+    /// implementation always returns `false`.
+    ///
+    /// # Safety
+    ///
+    /// See [`Self::eval`].
+    unsafe fn skip(&mut self) -> bool {
+        false
+    }
+
     /// Notify the node about start of a clock epoch.
     ///
     /// The node should forward the notification to its inner operator.
@@ -316,7 +365,57 @@ pub trait Node {
 
     fn summary(&self, output: &mut String);
 
+    /// Serializable parameters of the operator (see
+    /// [`Operator::params`](super::operator_traits::Operator::params)).
+    /// Returns an empty vector for subcircuits.
+    fn params(&self) -> Vec<(Cow<'static, str>, String)> {
+        Vec::new()
+    }
+
     fn fixedpoint(&self) -> bool;
+
+    /// `true` if the node encapsulates an operator that holds state across
+    /// clock cycles (see
+    /// [`Operator::is_stateful`](super::operator_traits::Operator::is_stateful)).
+    /// Only the output half of a strict operator is considered stateful.
+    fn is_stateful(&self) -> bool {
+        false
+    }
+
+    /// Append the description of the nested circuit encapsulated by the node,
+    /// if any, to `output` (see [`Circuit::explain`]).
+    fn explain_children(&self, _output: &mut String, _indent: usize) {}
+
+    /// Perform background maintenance work (see
+    /// [`Operator::exert`](super::operator_traits::Operator::exert)).  Only
+    /// the output half of a strict operator forwards the call to the
+    /// operator.
+    fn exert(&mut self, _effort: &mut isize) -> bool {
+        false
+    }
+
+    /// `false` if the node encapsulates a non-monotone operator (see
+    /// [`Operator::is_monotone()`](super::operator_traits::Operator::is_monotone))
+    /// or a subcircuit that contains one.
+    fn is_monotone(&self) -> bool {
+        true
+    }
+}
+
+/// Reports the error recorded by `operator` during the last `eval`, if any
+/// (see [`Operator::take_error`]).
+fn operator_status<Op>(operator: &mut Op, node_id: &GlobalNodeId) -> Result<(), SchedulerError>
+where
+    Op: Operator + ?Sized,
+{
+    match operator.take_error() {
+        None => Ok(()),
+        Some(error) => Err(SchedulerError::OperatorError {
+            node_id: node_id.clone(),
+            name: operator.name(),
+            error,
+        }),
+    }
 }
 
 /// Id of an operator, guaranteed to be unique within a circuit.
@@ -548,10 +647,21 @@ struct CircuitInner<P> {
     node_id: NodeId,
     global_node_id: GlobalNodeId,
     nodes: Vec<Box<dyn Node>>,
+    // Scheduling priorities of nodes, indexed by node id.
+    priorities: Vec<isize>,
+    // Priority assigned to new nodes (see `Circuit::with_priority`).
+    current_priority: isize,
+    // Merge effort of traces created by new operators (see
+    // `Circuit::with_trace_effort`).
+    trace_effort: Option<EffortConfig>,
     edges: Vec<Edge>,
     circuit_event_handlers: CircuitEventHandlers,
     scheduler_event_handlers: SchedulerEventHandlers,
     store: CircuitCache,
+    // Cache hits and misses by cache key type.
+    cache_stats: BTreeMap<&'static str, CacheStats>,
+    // Shared by all circuits in the hierarchy.
+    step_stats: Rc<StepStats>,
 }
 
 impl<P> CircuitInner<P> {
@@ -561,16 +671,22 @@ impl<P> CircuitInner<P> {
         global_node_id: GlobalNodeId,
         circuit_event_handlers: CircuitEventHandlers,
         scheduler_event_handlers: SchedulerEventHandlers,
+        step_stats: Rc<StepStats>,
     ) -> Self {
         Self {
             node_id,
             global_node_id,
             parent,
             nodes: Vec::new(),
+            priorities: Vec::new(),
+            current_priority: 0,
+            trace_effort: None,
             edges: Vec::new(),
             circuit_event_handlers,
             scheduler_event_handlers,
             store: TypedMap::new(),
+            cache_stats: BTreeMap::new(),
+            step_stats,
         }
     }
 
@@ -583,10 +699,12 @@ impl<P> CircuitInner<P> {
         N: Node + 'static,
     {
         self.nodes.push(Box::new(node) as Box<dyn Node>);
+        self.priorities.push(self.current_priority);
     }
 
     fn clear(&mut self) {
         self.nodes.clear();
+        self.priorities.clear();
         self.edges.clear();
         self.store.clear();
     }
@@ -667,6 +785,7 @@ impl Circuit<()> {
             GlobalNodeId::root(),
             Rc::new(RefCell::new(HashMap::new())),
             Rc::new(RefCell::new(HashMap::new())),
+            Rc::new(StepStats::default()),
         ))))
     }
 }
@@ -740,14 +859,19 @@ impl<P> Circuit<Circuit<P>> {
         let global_node_id = GlobalNodeId::child_of(&parent, id);
         let circuit_handlers = parent.inner().circuit_event_handlers.clone();
         let sched_handlers = parent.inner().scheduler_event_handlers.clone();
+        let step_stats = parent.inner().step_stats.clone();
+        let trace_effort = parent.inner().trace_effort.clone();
 
-        Circuit(Rc::new(RefCell::new(CircuitInner::new(
+        let mut inner = CircuitInner::new(
             parent,
             id,
             global_node_id,
             circuit_handlers,
             sched_handlers,
-        ))))
+            step_stats,
+        );
+        inner.trace_effort = trace_effort;
+        Circuit(Rc::new(RefCell::new(inner)))
     }
 
     /// `true` if `self` is a subcircuit of `other`.
@@ -778,6 +902,13 @@ impl<P> Circuit<P> {
         self.inner().global_node_id.clone()
     }
 
+    /// Counters of updates ingested and produced by the circuit during the
+    /// current step, shared by the top-level circuit and all its
+    /// subcircuits (see [`StepStats`]).
+    pub fn step_stats(&self) -> Rc<StepStats> {
+        self.inner().step_stats.clone()
+    }
+
     /// Connect `stream` as input to `to`.
     fn connect_stream<T>(
         &self,
@@ -856,6 +987,53 @@ impl<P> Circuit<P> {
         Ok(res)
     }
 
+    /// Check that no non-monotone node is part of a feedback loop.
+    ///
+    /// Feedback loops are closed by strict operators, which are split into
+    /// an output node and an input node connected by a dependency edge.  A node
+    /// belongs to a loop if it is reachable via stream edges from the output
+    /// half of a strict operator and the input half is reachable from it.
+    fn check_stratification(&self) -> Result<(), SchedulerError> {
+        let inner = self.inner();
+
+        let mut successors: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        let mut predecessors: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for edge in inner.edges.iter().filter(|edge| edge.is_stream()) {
+            successors.entry(edge.from).or_default().push(edge.to);
+            predecessors.entry(edge.to).or_default().push(edge.from);
+        }
+
+        let reachable = |graph: &HashMap<NodeId, Vec<NodeId>>, start: NodeId| {
+            let mut visited = HashSet::new();
+            let mut stack = vec![start];
+            while let Some(node_id) = stack.pop() {
+                if visited.insert(node_id) {
+                    stack.extend(graph.get(&node_id).into_iter().flatten().cloned());
+                }
+            }
+            visited
+        };
+
+        for edge in inner.edges.iter().filter(|edge| edge.is_dependency()) {
+            let forward = reachable(&successors, edge.from);
+            if !forward.contains(&edge.to) {
+                continue;
+            }
+            let backward = reachable(&predecessors, edge.to);
+            for node_id in forward.intersection(&backward) {
+                let node = &inner.nodes[node_id.0];
+                if !node.is_monotone() {
+                    return Err(SchedulerError::NonMonotoneRecursion {
+                        node_id: node.global_id().clone(),
+                        name: node.name(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     pub(super) fn edges(&self) -> Ref<'_, [Edge]> {
         let circuit = self.inner();
         Ref::map(circuit, |c| c.edges.as_slice())
@@ -875,6 +1053,29 @@ impl<P> Circuit<P> {
             .collect()
     }
 
+    /// Apply `f` to each node in the circuit in the order of node ids.
+    pub(super) fn map_nodes<F, T>(&self, f: F) -> Vec<T>
+    where
+        F: FnMut(&dyn Node) -> T,
+    {
+        self.inner()
+            .nodes
+            .iter()
+            .map(|node| node.as_ref())
+            .map(f)
+            .collect()
+    }
+
+    /// Cache hits and misses in the circuit cache by cache key type.
+    pub(super) fn cache_stats(&self) -> BTreeMap<&'static str, CacheStats> {
+        self.inner().cache_stats.clone()
+    }
+
+    /// Scheduling priority of node `id` (see [`Circuit::with_priority`]).
+    pub(super) fn node_priority(&self, id: NodeId) -> isize {
+        self.inner().priorities[id.0]
+    }
+
     /// Deliver `clock_start` notification to all nodes in the circuit.
     pub(super) fn clock_start(&self, scope: Scope) {
         for node in self.inner_mut().nodes.iter_mut() {
@@ -882,6 +1083,17 @@ impl<P> Circuit<P> {
         }
     }
 
+    /// Perform up to `effort` units of background maintenance work in each
+    /// node of the circuit.  Returns `true` if more work remains.
+    pub(super) fn exert(&self, effort: isize) -> bool {
+        let mut more_work = false;
+        for node in self.inner_mut().nodes.iter_mut() {
+            let mut node_effort = effort;
+            more_work |= node.exert(&mut node_effort);
+        }
+        more_work
+    }
+
     /// Deliver `clock_end` notification to all nodes in the circuit.
     pub(super) unsafe fn clock_end(&self, scope: Scope) {
         for node in self.inner_mut().nodes.iter_mut() {
@@ -932,10 +1144,17 @@ where
         // Don't use `store.entry()`, since `f` may need to perform
         // its own cache lookup.
         if self.inner().store.contains_key(&key) {
-            return RefMut::map(self.inner_mut(), |c| c.store.get_mut(&key).unwrap());
+            let mut inner = self.inner_mut();
+            inner.cache_stats.entry(key_name::<K>()).or_default().reused += 1;
+            return RefMut::map(inner, |c| c.store.get_mut(&key).unwrap());
         }
 
         let new = f();
+        self.inner_mut()
+            .cache_stats
+            .entry(key_name::<K>())
+            .or_default()
+            .created += 1;
 
         // TODO: Use `RefMut::filter_map()` to only perform one lookup in the happy path
         //       https://github.com/rust-lang/rust/issues/81061
@@ -990,6 +1209,29 @@ where
         Ok(())
     }
 
+    /// Skip evaluation of an idle node with the given id (see
+    /// [`Node::skip`]).
+    ///
+    /// Returns `false` if the node cannot be skipped and must be evaluated
+    /// using [`Self::eval_node`].  This method should only be used by
+    /// schedulers.
+    pub(crate) fn skip_node(&self, id: NodeId) -> bool {
+        let mut circuit = self.inner_mut();
+        debug_assert!(id.0 < circuit.nodes.len());
+
+        // Safety: see `eval_node`.
+        if unsafe { circuit.nodes[id.0].skip() } {
+            // Skipped nodes are reported as evaluated, so that monitors and
+            // profilers observe each node exactly once per clock cycle.
+            let node = circuit.nodes[id.0].as_ref();
+            circuit.log_scheduler_event(&SchedulerEvent::eval_start(node));
+            circuit.log_scheduler_event(&SchedulerEvent::eval_end(node));
+            true
+        } else {
+            false
+        }
+    }
+
     /// Evaluate closure `f` inside a new circuit region.
     ///
     /// A region is a logical grouping of circuit nodes.  Regions are used
@@ -1007,6 +1249,57 @@ where
         res
     }
 
+    /// Evaluate closure `f` with the scheduling priority of new nodes set to
+    /// `priority`.
+    ///
+    /// Any operators or subcircuits created by `f` are assigned `priority`
+    /// (nodes are created with priority 0 by default).  Within a clock
+    /// cycle, schedulers evaluate higher-priority nodes first, as long as
+    /// this does not violate dataflow dependencies.  The priority of a node
+    /// is inherited by all its upstream nodes, so that the entire path
+    /// that feeds a latency-critical operator, e.g., a small alerting
+    /// query, is evaluated before heavyweight subgraphs of the circuit that
+    /// it does not depend on.
+    ///
+    /// Priorities are local to the circuit: nodes of a nested circuit are
+    /// only ordered relative to each other, while the subcircuit as a whole
+    /// is scheduled according to the priority of its node in the parent
+    /// circuit.
+    pub fn with_priority<F, T>(&self, priority: isize, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let old_priority = replace(&mut self.inner_mut().current_priority, priority);
+        let res = f();
+        self.inner_mut().current_priority = old_priority;
+        res
+    }
+
+    /// Evaluate closure `f` with the merge effort of new traces set to
+    /// `config`.
+    ///
+    /// Applies to the traces of all operators created by `f`, e.g.,
+    /// [`Stream::integrate_trace`] and the operators that use it internally,
+    /// including operators in nested circuits created by `f`.  Traces are
+    /// created with the default effort otherwise.  Since operators share
+    /// the trace of a stream, a trace created before `f` is reused without
+    /// changing its effort.
+    pub fn with_trace_effort<F, T>(&self, config: EffortConfig, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let old_effort = self.inner_mut().trace_effort.replace(config);
+        let res = f();
+        self.inner_mut().trace_effort = old_effort;
+        res
+    }
+
+    /// Merge effort of traces created by new operators (see
+    /// [`Self::with_trace_effort`]).
+    pub(crate) fn trace_effort(&self) -> Option<EffortConfig> {
+        self.inner().trace_effort.clone()
+    }
+
     /// Add a source operator to the circuit.  See [`SourceOperator`].
     pub fn add_source<O, Op>(&self, operator: Op) -> Stream<Self, O>
     where
@@ -1476,6 +1769,10 @@ where
     /// user-defined return value that typically contains output streams
     /// of the child.
     ///
+    /// Returns [`SchedulerError::NonMonotoneRecursion`] if a non-monotone
+    /// operator, e.g., [`Stream::antijoin`], is part of a feedback loop in
+    /// the child circuit.
+    ///
     /// # Examples
     ///
     /// ```
@@ -1539,6 +1836,7 @@ where
     {
         self.subcircuit(true, |child| {
             let (termination_check, res) = constructor(child)?;
+            child.check_stratification()?;
             let executor = <IterativeExecutor<_, S>>::new(child, termination_check)?;
             Ok((res, executor))
         })
@@ -1597,6 +1895,7 @@ where
     {
         self.subcircuit(true, |child| {
             let res = constructor(child)?;
+            child.check_stratification()?;
             let child_clone = child.clone();
             let termination_check = move || child_clone.inner().fixedpoint();
             let executor = <IterativeExecutor<_, S>>::new(child, termination_check)?;
@@ -1720,7 +2019,7 @@ where
 
     unsafe fn eval(&mut self) -> Result<(), SchedulerError> {
         self.output_stream.put(self.operator.eval());
-        Ok(())
+        operator_status(&mut self.operator, &self.id)
     }
 
     fn clock_start(&mut self, scope: Scope) {
@@ -1741,9 +2040,25 @@ where
         self.operator.summary(output);
     }
 
+    fn params(&self) -> Vec<(Cow<'static, str>, String)> {
+        self.operator.params()
+    }
+
+    fn is_stateful(&self) -> bool {
+        self.operator.is_stateful()
+    }
+
+    fn exert(&mut self, effort: &mut isize) -> bool {
+        self.operator.exert(effort)
+    }
+
     fn fixedpoint(&self) -> bool {
         self.operator.fixedpoint()
     }
+
+    fn is_monotone(&self) -> bool {
+        self.operator.is_monotone()
+    }
 }
 
 struct SourceNode<C, O, Op> {
@@ -1800,8 +2115,23 @@ where
     }
 
     unsafe fn eval(&mut self) -> Result<(), SchedulerError> {
+        if self.skip() {
+            return Ok(());
+        }
         self.output_stream.put(self.operator.eval());
-        Ok(())
+        operator_status(&mut self.operator, &self.id)
+    }
+
+    unsafe fn skip(&mut self) -> bool {
+        if self.operator.has_pending_work() {
+            return false;
+        }
+        if let Some(output) = self.operator.idle_output() {
+            self.output_stream.put_idle(output);
+            true
+        } else {
+            false
+        }
     }
 
     fn clock_start(&mut self, scope: Scope) {
@@ -1816,9 +2146,25 @@ where
         self.operator.summary(output);
     }
 
+    fn params(&self) -> Vec<(Cow<'static, str>, String)> {
+        self.operator.params()
+    }
+
+    fn is_stateful(&self) -> bool {
+        self.operator.is_stateful()
+    }
+
+    fn exert(&mut self, effort: &mut isize) -> bool {
+        self.operator.exert(effort)
+    }
+
     fn fixedpoint(&self) -> bool {
         self.operator.fixedpoint()
     }
+
+    fn is_monotone(&self) -> bool {
+        self.operator.is_monotone()
+    }
 }
 
 struct UnaryNode<C, I, O, Op> {
@@ -1883,11 +2229,28 @@ where
     }
 
     unsafe fn eval(&mut self) -> Result<(), SchedulerError> {
+        if self.skip() {
+            return Ok(());
+        }
+
         self.output_stream.put(match self.input_stream.take() {
             Cow::Owned(v) => self.operator.eval_owned(v),
             Cow::Borrowed(v) => self.operator.eval(v),
         });
-        Ok(())
+        operator_status(&mut self.operator, &self.id)
+    }
+
+    unsafe fn skip(&mut self) -> bool {
+        // Skip evaluation if the input is idle and the operator has no
+        // pending work.
+        if self.input_stream.is_idle() && !self.operator.has_pending_work() {
+            if let Some(output) = self.operator.idle_output() {
+                let _ = self.input_stream.take();
+                self.output_stream.put_idle(output);
+                return true;
+            }
+        }
+        false
     }
 
     fn clock_start(&mut self, scope: Scope) {
@@ -1902,9 +2265,25 @@ where
         self.operator.summary(output);
     }
 
+    fn params(&self) -> Vec<(Cow<'static, str>, String)> {
+        self.operator.params()
+    }
+
+    fn is_stateful(&self) -> bool {
+        self.operator.is_stateful()
+    }
+
+    fn exert(&mut self, effort: &mut isize) -> bool {
+        self.operator.exert(effort)
+    }
+
     fn fixedpoint(&self) -> bool {
         self.operator.fixedpoint()
     }
+
+    fn is_monotone(&self) -> bool {
+        self.operator.is_monotone()
+    }
 }
 
 struct SinkNode<C, I, Op> {
@@ -1965,7 +2344,7 @@ where
             Cow::Owned(v) => self.operator.eval_owned(v),
             Cow::Borrowed(v) => self.operator.eval(v),
         };
-        Ok(())
+        operator_status(&mut self.operator, &self.id)
     }
 
     fn clock_start(&mut self, scope: Scope) {
@@ -1980,9 +2359,25 @@ where
         self.operator.summary(output);
     }
 
+    fn params(&self) -> Vec<(Cow<'static, str>, String)> {
+        self.operator.params()
+    }
+
+    fn is_stateful(&self) -> bool {
+        self.operator.is_stateful()
+    }
+
+    fn exert(&mut self, effort: &mut isize) -> bool {
+        self.operator.exert(effort)
+    }
+
     fn fixedpoint(&self) -> bool {
         self.operator.fixedpoint()
     }
+
+    fn is_monotone(&self) -> bool {
+        self.operator.is_monotone()
+    }
 }
 
 struct BinaryNode<C, I1, I2, O, Op> {
@@ -2055,6 +2450,10 @@ where
     }
 
     unsafe fn eval(&mut self) -> Result<(), SchedulerError> {
+        if self.skip() {
+            return Ok(());
+        }
+
         // If the two input streams are aliases, we cannot remove the owned
         // value from `input_stream2`, as this will invalidate the borrow
         // from `input_stream1`.  Instead use `peek` to obtain the value by
@@ -2079,7 +2478,23 @@ where
                 },
             );
         }
-        Ok(())
+        operator_status(&mut self.operator, &self.id)
+    }
+
+    unsafe fn skip(&mut self) -> bool {
+        // Skip evaluation if the operator's output is known for idle inputs
+        // and the operator has no pending work.
+        let idle1 = self.input_stream1.is_idle();
+        let idle2 = self.input_stream2.is_idle();
+        if (idle1 || idle2) && !self.operator.has_pending_work() {
+            if let Some(output) = self.operator.idle_output(idle1, idle2) {
+                let _ = self.input_stream1.take();
+                let _ = self.input_stream2.take();
+                self.output_stream.put_idle(output);
+                return true;
+            }
+        }
+        false
     }
 
     fn clock_start(&mut self, scope: Scope) {
@@ -2094,9 +2509,25 @@ where
         self.operator.summary(output);
     }
 
+    fn params(&self) -> Vec<(Cow<'static, str>, String)> {
+        self.operator.params()
+    }
+
+    fn is_stateful(&self) -> bool {
+        self.operator.is_stateful()
+    }
+
+    fn exert(&mut self, effort: &mut isize) -> bool {
+        self.operator.exert(effort)
+    }
+
     fn fixedpoint(&self) -> bool {
         self.operator.fixedpoint()
     }
+
+    fn is_monotone(&self) -> bool {
+        self.operator.is_monotone()
+    }
 }
 
 struct NaryNode<C, I, O, Op>
@@ -2200,7 +2631,7 @@ where
         for i in self.aliases.iter() {
             let _ = self.input_streams[*i].0.take();
         }
-        Ok(())
+        operator_status(&mut self.operator, &self.id)
     }
 
     fn clock_start(&mut self, scope: Scope) {
@@ -2215,9 +2646,25 @@ where
         self.operator.summary(output);
     }
 
+    fn params(&self) -> Vec<(Cow<'static, str>, String)> {
+        self.operator.params()
+    }
+
+    fn is_stateful(&self) -> bool {
+        self.operator.is_stateful()
+    }
+
+    fn exert(&mut self, effort: &mut isize) -> bool {
+        self.operator.exert(effort)
+    }
+
     fn fixedpoint(&self) -> bool {
         self.operator.fixedpoint()
     }
+
+    fn is_monotone(&self) -> bool {
+        self.operator.is_monotone()
+    }
 }
 
 // The output half of a feedback node.  We implement a feedback node using a
@@ -2313,6 +2760,18 @@ where
         }
     }
 
+    fn params(&self) -> Vec<(Cow<'static, str>, String)> {
+        unsafe { &*self.operator.get() }.params()
+    }
+
+    fn is_stateful(&self) -> bool {
+        unsafe { &*self.operator.get() }.is_stateful()
+    }
+
+    fn exert(&mut self, effort: &mut isize) -> bool {
+        unsafe { &mut *self.operator.get() }.exert(effort)
+    }
+
     fn fixedpoint(&self) -> bool {
         unsafe { (&*self.operator.get()).fixedpoint() }
     }
@@ -2390,6 +2849,10 @@ where
         }
     }
 
+    fn params(&self) -> Vec<(Cow<'static, str>, String)> {
+        unsafe { &*self.operator.get() }.params()
+    }
+
     fn fixedpoint(&self) -> bool {
         unsafe { (&*self.operator.get()).fixedpoint() }
     }
@@ -2486,6 +2949,14 @@ impl<P> Node for ChildNode<P>
 where
     P: 'static,
 {
+    fn explain_children(&self, output: &mut String, indent: usize) {
+        self.circuit.explain_nodes(output, indent);
+    }
+
+    fn exert(&mut self, effort: &mut isize) -> bool {
+        self.circuit.exert(*effort)
+    }
+
     fn name(&self) -> Cow<'static, str> {
         Cow::Borrowed("Subcircuit")
     }
@@ -2525,12 +2996,30 @@ where
     fn fixedpoint(&self) -> bool {
         unimplemented!()
     }
+
+    fn is_monotone(&self) -> bool {
+        self.circuit
+            .inner()
+            .nodes
+            .iter()
+            .all(|node| node.is_monotone())
+    }
 }
 
+/// Hooks invoked before each step.
+type BeforeStepHooks = RefCell<Vec<(String, Box<dyn FnMut(u64)>)>>;
+
+/// Hooks invoked after each step.
+type AfterStepHooks = RefCell<Vec<(String, Box<dyn FnMut(&StepSummary)>)>>;
+
 /// Top-level circuit with executor.
 pub struct Root {
     circuit: Circuit<()>,
     executor: Box<dyn Executor<()>>,
+    // Number of completed steps.
+    step: Cell<u64>,
+    before_step_hooks: BeforeStepHooks,
+    after_step_hooks: AfterStepHooks,
 }
 
 impl Drop for Root {
@@ -2580,7 +3069,13 @@ impl Root {
         // from clean state without having to rebuild it from scratch.
         circuit.log_scheduler_event(&SchedulerEvent::clock_start());
         circuit.clock_start(0);
-        Ok(Self { circuit, executor })
+        Ok(Self {
+            circuit,
+            executor,
+            step: Cell::new(0),
+            before_step_hooks: RefCell::new(Vec::new()),
+            after_step_hooks: RefCell::new(Vec::new()),
+        })
     }
 
     /// Function that drives the execution of the circuit.
@@ -2588,11 +3083,121 @@ impl Root {
     /// Every call to `step()` corresponds to one tick of the global logical
     /// clock and causes each operator in the circuit to get evaluated once,
     /// consuming one value from each of its input streams.
+    ///
+    /// Hooks registered with [`before_step`](`Self::before_step`) and
+    /// [`after_step`](`Self::after_step`) are invoked before and after
+    /// evaluating the circuit.  After-step hooks only run if the step
+    /// succeeds.
     pub fn step(&self) -> Result<(), SchedulerError> {
         // TODO: Add a runtime check to prevent re-entering this method from an
         // operator.
 
-        self.executor.run(&self.circuit)
+        let step = self.step.get();
+        for (_, hook) in self.before_step_hooks.borrow_mut().iter_mut() {
+            hook(step);
+        }
+
+        let stats = self.circuit.step_stats();
+        stats.reset();
+        let start = Instant::now();
+        self.executor.run(&self.circuit)?;
+        let summary = StepSummary {
+            step,
+            inputs: stats.inputs(),
+            outputs: stats.outputs(),
+            duration: start.elapsed(),
+        };
+        self.step.set(step + 1);
+
+        for (_, hook) in self.after_step_hooks.borrow_mut().iter_mut() {
+            hook(&summary);
+        }
+        Ok(())
+    }
+
+    /// Register a hook to run at the start of every [`step`](`Self::step`),
+    /// before any operator is evaluated.  The hook receives the number of
+    /// the step about to be evaluated.
+    ///
+    /// `name` - user-readable name assigned to the hook.  If a hook with the
+    /// same name exists, it is replaced by the new hook.  Hooks run in the
+    /// order of registration.
+    ///
+    /// Hooks must not register or remove hooks or call `step` on this
+    /// circuit.
+    pub fn before_step<F>(&self, name: &str, hook: F)
+    where
+        F: FnMut(u64) + 'static,
+    {
+        Self::register_hook(&self.before_step_hooks, name, Box::new(hook));
+    }
+
+    /// Register a hook to run at the end of every successful
+    /// [`step`](`Self::step`), after all operators have been evaluated.
+    /// The hook receives a [`StepSummary`].
+    ///
+    /// Hooks allow applications embedding the circuit to implement commit
+    /// protocols, e.g., to acknowledge inputs or publish outputs once the
+    /// step that processed them has completed.  Naming and ordering rules
+    /// are the same as for [`before_step`](`Self::before_step`).
+    pub fn after_step<F>(&self, name: &str, hook: F)
+    where
+        F: FnMut(&StepSummary) + 'static,
+    {
+        Self::register_hook(&self.after_step_hooks, name, Box::new(hook));
+    }
+
+    /// Remove before- and after-step hooks named `name`.
+    ///
+    /// Returns `true` if a hook was removed.
+    pub fn remove_step_hook(&self, name: &str) -> bool {
+        let mut before_hooks = self.before_step_hooks.borrow_mut();
+        let mut after_hooks = self.after_step_hooks.borrow_mut();
+        let len = before_hooks.len() + after_hooks.len();
+
+        before_hooks.retain(|(hook_name, _)| hook_name != name);
+        after_hooks.retain(|(hook_name, _)| hook_name != name);
+        before_hooks.len() + after_hooks.len() != len
+    }
+
+    /// Perform up to `effort` units of background maintenance work, e.g.,
+    /// merging trace batches, in each operator of the circuit (see
+    /// [`Operator::exert`](super::operator_traits::Operator::exert)).
+    ///
+    /// Must be invoked between steps.  Returns `true` if more maintenance
+    /// work remains.
+    pub fn exert(&self, effort: isize) -> bool {
+        self.circuit.exert(effort)
+    }
+
+    /// Use idle time between steps for maintenance.
+    ///
+    /// Calls [`Self::exert`] with `effort` repeatedly, for at most
+    /// `max_rounds` rounds, while `idle` returns `true` (e.g., while no input
+    /// is pending, see [`InputHandle::pending`](`crate::operator::InputHandle::pending`))
+    /// and maintenance work remains.  Compacting traces during quiescent
+    /// periods reduces the number of batches that operators must read at
+    /// subsequent steps.  Returns the number of rounds performed.
+    pub fn compact_while_idle<F>(&self, effort: isize, max_rounds: usize, mut idle: F) -> usize
+    where
+        F: FnMut() -> bool,
+    {
+        let mut rounds = 0;
+        while rounds < max_rounds && idle() {
+            rounds += 1;
+            if !self.exert(effort) {
+                break;
+            }
+        }
+        rounds
+    }
+
+    fn register_hook<H: ?Sized>(hooks: &RefCell<Vec<(String, Box<H>)>>, name: &str, hook: Box<H>) {
+        let mut hooks = hooks.borrow_mut();
+        match hooks.iter_mut().find(|(hook_name, _)| hook_name == name) {
+            Some((_, old_hook)) => *old_hook = hook,
+            None => hooks.push((name.to_string(), hook)),
+        }
     }
 
     /// Attach a scheduler event handler to the circuit.
@@ -2625,11 +3230,350 @@ impl Root {
 mod tests {
     use super::Root;
     use crate::{
-        circuit::schedule::{DynamicScheduler, Scheduler, StaticScheduler},
+        circuit::{
+            operator_traits::{Operator, SourceOperator, UnaryOperator},
+            schedule::{DynamicScheduler, Scheduler, StaticScheduler},
+        },
         monitor::TraceMonitor,
         operator::{Apply2, Generator, Inspect, Z1},
+        trace::{ord::OrdZSet, Batch, BatchReader, EffortConfig, TraceReader},
+        zset,
     };
-    use std::{cell::RefCell, ops::Deref, rc::Rc, vec::Vec};
+    use std::{
+        borrow::Cow,
+        cell::{Cell, RefCell},
+        ops::Deref,
+        rc::Rc,
+        vec::Vec,
+    };
+
+    #[test]
+    fn step_hooks() {
+        let mut input = None;
+        let root = Root::build(|circuit| {
+            let (stream, handle) = circuit.add_input::<OrdZSet<usize, isize>>();
+            let stats = circuit.step_stats();
+            stream.inspect(move |batch| stats.record_outputs(batch.len()));
+            input = Some(handle);
+        })
+        .unwrap();
+        let input = input.unwrap();
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events_clone = events.clone();
+        root.before_step("log", move |step| {
+            events_clone.borrow_mut().push(format!("before {}", step))
+        });
+        let events_clone = events.clone();
+        root.after_step("log", move |summary| {
+            events_clone.borrow_mut().push(format!(
+                "after {}: {} -> {}",
+                summary.step, summary.inputs, summary.outputs
+            ))
+        });
+
+        input.push(1, (), 1);
+        input.push(2, (), 1);
+        root.step().unwrap();
+        root.step().unwrap();
+
+        assert!(root.remove_step_hook("log"));
+        assert!(!root.remove_step_hook("log"));
+        input.push(3, (), 1);
+        root.step().unwrap();
+
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                "before 0".to_string(),
+                "after 0: 2 -> 2".to_string(),
+                "before 1".to_string(),
+                "after 1: 0 -> 0".to_string(),
+            ]
+        );
+    }
+
+    // Operator that counts its evaluations and can be skipped when idle,
+    // unless it reports pending work.
+    struct CountEvals(Rc<RefCell<usize>>, Rc<Cell<bool>>);
+
+    impl Operator for CountEvals {
+        fn name(&self) -> Cow<'static, str> {
+            Cow::from("CountEvals")
+        }
+        fn fixedpoint(&self) -> bool {
+            true
+        }
+        fn has_pending_work(&self) -> bool {
+            self.1.get()
+        }
+    }
+
+    impl UnaryOperator<OrdZSet<usize, isize>, OrdZSet<usize, isize>> for CountEvals {
+        fn eval(&mut self, input: &OrdZSet<usize, isize>) -> OrdZSet<usize, isize> {
+            *self.0.borrow_mut() += 1;
+            input.clone()
+        }
+
+        fn idle_output(&mut self) -> Option<OrdZSet<usize, isize>> {
+            Some(OrdZSet::empty(()))
+        }
+    }
+
+    #[test]
+    fn compact_while_idle() {
+        let batches = Rc::new(RefCell::new(Vec::new()));
+        let batches_clone = batches.clone();
+
+        let mut input = None;
+        let root = Root::build(|circuit| {
+            let (stream, handle) = circuit.add_input::<OrdZSet<usize, isize>>();
+            stream.integrate_trace().inspect(move |trace| {
+                let mut count = 0;
+                trace.map_batches(|batch| {
+                    if !batch.is_empty() {
+                        count += 1
+                    }
+                });
+                batches_clone.borrow_mut().push(count);
+            });
+            input = Some(handle);
+        })
+        .unwrap();
+        let input = input.unwrap();
+
+        for i in 0..100 {
+            input.push(i, (), 1);
+            root.step().unwrap();
+        }
+        assert!(*batches.borrow().last().unwrap() > 1);
+
+        // No compaction while input is pending.
+        input.push(100, (), 1);
+        assert_eq!(
+            root.compact_while_idle(1 << 12, 10, || input.pending() == 0),
+            0
+        );
+        root.step().unwrap();
+
+        let rounds = root.compact_while_idle(1 << 12, 10, || input.pending() == 0);
+        assert!(rounds > 0 && rounds < 10);
+        assert!(!root.exert(1 << 12));
+
+        root.step().unwrap();
+        assert_eq!(*batches.borrow().last().unwrap(), 1);
+    }
+
+    #[test]
+    fn trace_effort() {
+        let efforts = Rc::new(RefCell::new(Vec::new()));
+        let efforts_clone = efforts.clone();
+
+        let root = Root::build(move |circuit| {
+            let stream = circuit.add_source(Generator::new(|| zset! { 1usize => 1isize }));
+            let default_log = efforts_clone.clone();
+            stream
+                .integrate_trace()
+                .inspect(move |trace| default_log.borrow_mut().push(("default", trace.effort())));
+
+            circuit.with_trace_effort(EffortConfig::new(4), || {
+                let stream = stream.map_keys::<OrdZSet<usize, isize>, _>(|x| x + 1);
+                let log = efforts_clone.clone();
+                stream
+                    .integrate_trace()
+                    .inspect(move |trace| log.borrow_mut().push(("outer", trace.effort())));
+
+                // Nested circuits inherit the effort.
+                circuit
+                    .iterate(|child| {
+                        let log = efforts_clone.clone();
+                        stream
+                            .delta0(child)
+                            .integrate_trace()
+                            .inspect(move |trace| {
+                                log.borrow_mut().push(("nested", trace.effort()))
+                            });
+                        Ok((|| true, ()))
+                    })
+                    .unwrap();
+            });
+        })
+        .unwrap();
+
+        root.step().unwrap();
+        efforts.borrow_mut().sort();
+        assert_eq!(
+            *efforts.borrow(),
+            vec![("default", 1), ("nested", 4), ("outer", 4)]
+        );
+    }
+
+    #[test]
+    fn skip_idle_operators_static() {
+        skip_idle_operators::<StaticScheduler>();
+    }
+
+    #[test]
+    fn skip_idle_operators_dynamic() {
+        skip_idle_operators::<DynamicScheduler>();
+    }
+
+    fn skip_idle_operators<S>()
+    where
+        S: Scheduler + 'static,
+    {
+        let evals1 = Rc::new(RefCell::new(0));
+        let evals2 = Rc::new(RefCell::new(0));
+        let pending2 = Rc::new(Cell::new(false));
+        let outputs = Rc::new(RefCell::new(Vec::new()));
+
+        let evals1_clone = evals1.clone();
+        let evals2_clone = evals2.clone();
+        let pending2_clone = pending2.clone();
+        let outputs_clone = outputs.clone();
+        let mut input = None;
+        let root = Root::build_with_scheduler::<_, S>(|circuit| {
+            TraceMonitor::new_panic_on_error().attach(circuit, "monitor");
+            let (stream, handle) = circuit.add_input::<OrdZSet<usize, isize>>();
+            let stream = stream.map_keys::<OrdZSet<usize, isize>, _>(|x| x + 1);
+            let stream = circuit
+                .add_unary_operator(CountEvals(evals1_clone, Rc::new(Cell::new(false))), &stream);
+            circuit
+                .add_unary_operator(CountEvals(evals2_clone, pending2_clone), &stream)
+                .inspect(move |batch| outputs_clone.borrow_mut().push(batch.len()));
+            input = Some(handle);
+        })
+        .unwrap();
+        let input = input.unwrap();
+
+        input.push(1, (), 1);
+        root.step().unwrap();
+        root.step().unwrap();
+        root.step().unwrap();
+        input.push(2, (), 1);
+        input.push(3, (), 1);
+        root.step().unwrap();
+
+        // Operators are only evaluated when the input is not idle; sinks
+        // are evaluated at every step.
+        assert_eq!(*evals1.borrow(), 2);
+        assert_eq!(*evals2.borrow(), 2);
+        assert_eq!(*outputs.borrow(), vec![1, 0, 0, 2]);
+
+        // An operator with pending work is evaluated even if its input is
+        // idle.
+        pending2.set(true);
+        root.step().unwrap();
+        assert_eq!(*evals1.borrow(), 2);
+        assert_eq!(*evals2.borrow(), 3);
+
+        pending2.set(false);
+        root.step().unwrap();
+        assert_eq!(*evals2.borrow(), 3);
+    }
+
+    #[test]
+    fn priorities_static() {
+        priorities::<StaticScheduler>();
+    }
+
+    #[test]
+    fn priorities_dynamic() {
+        priorities::<DynamicScheduler>();
+    }
+
+    // High-priority sink and its upstream nodes are evaluated before an
+    // unrelated subgraph created earlier.
+    fn priorities<S>()
+    where
+        S: Scheduler + 'static,
+    {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let log_clone = log.clone();
+
+        let root = Root::build_with_scheduler::<_, S>(move |circuit| {
+            let heavy_log = log_clone.clone();
+            circuit
+                .add_source(Generator::new(|| 0usize))
+                .apply(|x| x + 1)
+                .inspect(move |_| heavy_log.borrow_mut().push("heavy"));
+
+            let source = circuit.add_source(Generator::new(|| 0usize));
+            circuit.with_priority(1, || {
+                source
+                    .apply(|x| x + 1)
+                    .inspect(move |_| log_clone.borrow_mut().push("alert"));
+            });
+        })
+        .unwrap();
+
+        for _ in 0..2 {
+            root.step().unwrap();
+        }
+
+        assert_eq!(*log.borrow(), vec!["alert", "heavy", "alert", "heavy"]);
+    }
+
+    // Source that is always idle and logs its name when skipped.
+    struct IdleSource(&'static str, Rc<RefCell<Vec<&'static str>>>);
+
+    impl Operator for IdleSource {
+        fn name(&self) -> Cow<'static, str> {
+            Cow::from("IdleSource")
+        }
+        fn fixedpoint(&self) -> bool {
+            true
+        }
+    }
+
+    impl SourceOperator<usize> for IdleSource {
+        fn eval(&mut self) -> usize {
+            0
+        }
+
+        fn idle_output(&mut self) -> Option<usize> {
+            self.1.borrow_mut().push(self.0);
+            Some(0)
+        }
+    }
+
+    #[test]
+    fn quiescent_priorities_static() {
+        quiescent_priorities::<StaticScheduler>();
+    }
+
+    #[test]
+    fn quiescent_priorities_dynamic() {
+        quiescent_priorities::<DynamicScheduler>();
+    }
+
+    // Skipped nodes are processed in priority order.
+    fn quiescent_priorities<S>()
+    where
+        S: Scheduler + 'static,
+    {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let log_clone = log.clone();
+
+        let root = Root::build_with_scheduler::<_, S>(move |circuit| {
+            circuit.with_priority(1, || {
+                circuit
+                    .add_source(IdleSource("alert", log_clone.clone()))
+                    .inspect(|_| {});
+            });
+
+            circuit
+                .add_source(IdleSource("heavy", log_clone))
+                .inspect(|_| {});
+        })
+        .unwrap();
+
+        for _ in 0..2 {
+            root.step().unwrap();
+        }
+
+        assert_eq!(*log.borrow(), vec!["alert", "heavy", "alert", "heavy"]);
+    }
 
     // Compute the sum of numbers from 0 to 99.
     #[test]
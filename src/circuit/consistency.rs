@@ -0,0 +1,309 @@
+//! Coordination of source positions with checkpoints.
+//!
+//! External sources, such as message queues, write-ahead logs, or files,
+//! identify the data they have delivered to the circuit by a position (e.g.,
+//! an offset).  For end-to-end exactly-once processing, a source must only
+//! acknowledge data to the external system, e.g., by committing its offset,
+//! once the state derived from that data has been durably checkpointed.
+//! Otherwise, the data is lost if the process fails after the acknowledgement
+//! but before the checkpoint.
+//!
+//! [`ConsistencyCoordinator`] tracks the position reached by each source at
+//! each step of the circuit and advances the durable position of all sources
+//! when the application reports that the checkpoint of a step has completed.
+
+use crate::circuit::Root;
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    mem::replace,
+    rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+// Source of unique coordinator ids, used to name step hooks.
+static NEXT_COORDINATOR_ID: AtomicUsize = AtomicUsize::new(0);
+
+// Type-erased state of a source registered with the coordinator.
+trait SourceState {
+    // Commit the latest position recorded at or before `step`.
+    fn commit(&mut self, step: u64);
+}
+
+struct TypedSourceState<P> {
+    // Positions recorded at each step not yet committed.
+    pending: BTreeMap<u64, P>,
+    committed: Option<P>,
+    on_commit: Box<dyn FnMut(&P)>,
+}
+
+impl<P> SourceState for TypedSourceState<P> {
+    fn commit(&mut self, step: u64) {
+        let later = self.pending.split_off(&(step + 1));
+        let committed = replace(&mut self.pending, later);
+
+        if let Some((_, position)) = committed.into_iter().next_back() {
+            (self.on_commit)(&position);
+            self.committed = Some(position);
+        }
+    }
+}
+
+/// Tracks positions of external sources at each step and commits them once
+/// the corresponding checkpoint has completed.
+///
+/// Sources register with the coordinator using
+/// [`register_source`](`Self::register_source`) and report their positions
+/// via the returned [`SourceHandle`] as they feed data to the circuit.
+/// Positions are associated with the current step of the circuit, which the
+/// coordinator tracks after being attached to a [`Root`] with
+/// [`attach`](`Self::attach`).  When the application completes a checkpoint
+/// of the circuit state as of the end of some step, it calls
+/// [`checkpoint_complete`](`Self::checkpoint_complete`), which invokes the
+/// commit callback of each source with the latest position recorded at or
+/// before that step.
+///
+/// Combined with transactional sinks that publish outputs of a step
+/// atomically with the checkpoint, this provides end-to-end exactly-once
+/// semantics: after a failure, the circuit restarts from the last
+/// checkpoint and sources resume from the last committed positions.
+///
+/// # Examples
+///
+/// ```
+/// # use dbsp::circuit::{ConsistencyCoordinator, Root};
+/// # use std::{cell::Cell, rc::Rc};
+/// let root = Root::build(|_circuit| {}).unwrap();
+///
+/// let coordinator = ConsistencyCoordinator::new();
+/// coordinator.attach(&root);
+///
+/// let committed_offset = Rc::new(Cell::new(0));
+/// let committed_offset_clone = committed_offset.clone();
+/// let source = coordinator.register_source("log", move |offset: &u64| {
+///     committed_offset_clone.set(*offset)
+/// });
+///
+/// // Ingest records up to offset 100 in step 0 and up to 150 in step 1.
+/// source.record(100);
+/// root.step().unwrap();
+/// source.record(150);
+/// root.step().unwrap();
+///
+/// // Checkpoint of step 0 completed.
+/// coordinator.checkpoint_complete(0);
+/// assert_eq!(committed_offset.get(), 100);
+/// ```
+#[derive(Clone, Default)]
+pub struct ConsistencyCoordinator {
+    inner: Rc<CoordinatorInner>,
+}
+
+// Registered sources along with their names.
+type Sources = Vec<(String, Rc<RefCell<dyn SourceState>>)>;
+
+struct CoordinatorInner {
+    // Unique id of the coordinator.
+    id: usize,
+    // Current step of the circuit.
+    step: Cell<u64>,
+    // Latest step whose checkpoint has completed.
+    checkpointed: Cell<Option<u64>>,
+    sources: RefCell<Sources>,
+}
+
+impl Default for CoordinatorInner {
+    fn default() -> Self {
+        Self {
+            id: NEXT_COORDINATOR_ID.fetch_add(1, Ordering::Relaxed),
+            step: Cell::new(0),
+            checkpointed: Cell::new(None),
+            sources: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl ConsistencyCoordinator {
+    /// Create a coordinator without any sources.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track steps of `root`.
+    ///
+    /// Registers an after-step hook (see [`Root::after_step`]) that advances
+    /// the current step of the coordinator, so that positions recorded
+    /// while feeding inputs to the next step, or during the step itself,
+    /// are associated with that step.
+    ///
+    /// The hook is named after the coordinator's unique id, so several
+    /// coordinators can be attached to the same root.
+    pub fn attach(&self, root: &Root) {
+        let inner = self.inner.clone();
+        root.after_step(
+            &format!("ConsistencyCoordinator({})", self.inner.id),
+            move |summary| inner.step.set(summary.step + 1),
+        );
+    }
+
+    /// Register a source named `name`.
+    ///
+    /// `on_commit` is invoked with the latest position recorded by the
+    /// source whenever it becomes durable, i.e., the checkpoint of the step
+    /// at which it was recorded completes.  The source should acknowledge
+    /// the position to the external system, e.g., commit its offset.
+    pub fn register_source<P, F>(&self, name: &str, on_commit: F) -> SourceHandle<P>
+    where
+        P: 'static,
+        F: FnMut(&P) + 'static,
+    {
+        let state = Rc::new(RefCell::new(TypedSourceState {
+            pending: BTreeMap::new(),
+            committed: None,
+            on_commit: Box::new(on_commit),
+        }));
+        self.inner.sources.borrow_mut().push((
+            name.to_string(),
+            state.clone() as Rc<RefCell<dyn SourceState>>,
+        ));
+
+        SourceHandle {
+            coordinator: self.inner.clone(),
+            state,
+        }
+    }
+
+    /// Names of registered sources.
+    pub fn sources(&self) -> Vec<String> {
+        self.inner
+            .sources
+            .borrow()
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Step of the circuit that positions are currently recorded for.
+    pub fn step(&self) -> u64 {
+        self.inner.step.get()
+    }
+
+    /// Latest step whose checkpoint has completed, if any.
+    pub fn checkpointed_step(&self) -> Option<u64> {
+        self.inner.checkpointed.get()
+    }
+
+    /// Notify the coordinator that the checkpoint of the circuit state as of
+    /// the end of `step` has completed.
+    ///
+    /// Commits, for each source, the latest position recorded at or before
+    /// `step`.  Checkpoints must complete in order; notifications for steps
+    /// that precede an already completed checkpoint are ignored.
+    pub fn checkpoint_complete(&self, step: u64) {
+        if matches!(self.inner.checkpointed.get(), Some(checkpointed) if checkpointed >= step) {
+            return;
+        }
+        self.inner.checkpointed.set(Some(step));
+
+        for (_, source) in self.inner.sources.borrow().iter() {
+            source.borrow_mut().commit(step);
+        }
+    }
+}
+
+/// Handle used by a source to report its positions to a
+/// [`ConsistencyCoordinator`].
+pub struct SourceHandle<P> {
+    coordinator: Rc<CoordinatorInner>,
+    state: Rc<RefCell<TypedSourceState<P>>>,
+}
+
+impl<P> SourceHandle<P> {
+    /// Record that the source has delivered all data up to `position` to
+    /// the circuit for the current step (see
+    /// [`ConsistencyCoordinator::step`]).
+    ///
+    /// If the source records multiple positions during the same step, the
+    /// last one wins.
+    pub fn record(&self, position: P) {
+        let step = self.coordinator.step.get();
+        self.state.borrow_mut().pending.insert(step, position);
+    }
+
+    /// Latest committed position of the source, if any.
+    pub fn committed(&self) -> Option<P>
+    where
+        P: Clone,
+    {
+        self.state.borrow().committed.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ConsistencyCoordinator;
+    use crate::circuit::Root;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn coordinator_test() {
+        let root = Root::build(|_circuit| {}).unwrap();
+        let coordinator = ConsistencyCoordinator::new();
+        coordinator.attach(&root);
+
+        let commits = Rc::new(RefCell::new(Vec::new()));
+        let commits_clone = commits.clone();
+        let kafka = coordinator.register_source("kafka", move |offsets: &Vec<u64>| {
+            commits_clone.borrow_mut().push(offsets.clone())
+        });
+        let wal = coordinator.register_source("wal", |_lsn: &u64| {});
+        assert_eq!(
+            coordinator.sources(),
+            vec!["kafka".to_string(), "wal".to_string()]
+        );
+
+        // Step 0.
+        kafka.record(vec![10, 20]);
+        wal.record(5);
+        root.step().unwrap();
+
+        // Step 1: no new kafka data.
+        wal.record(7);
+        root.step().unwrap();
+
+        // Step 2.
+        kafka.record(vec![15, 20]);
+        kafka.record(vec![18, 25]);
+        root.step().unwrap();
+
+        assert_eq!(kafka.committed(), None);
+
+        coordinator.checkpoint_complete(1);
+        assert_eq!(coordinator.checkpointed_step(), Some(1));
+        assert_eq!(kafka.committed(), Some(vec![10, 20]));
+        assert_eq!(wal.committed(), Some(7));
+
+        // Out-of-order notification is ignored.
+        coordinator.checkpoint_complete(0);
+        assert_eq!(wal.committed(), Some(7));
+
+        coordinator.checkpoint_complete(2);
+        assert_eq!(kafka.committed(), Some(vec![18, 25]));
+        assert_eq!(*commits.borrow(), vec![vec![10, 20], vec![18, 25]]);
+    }
+
+    #[test]
+    fn multiple_coordinators() {
+        let root = Root::build(|_circuit| {}).unwrap();
+        let coordinator1 = ConsistencyCoordinator::new();
+        let coordinator2 = ConsistencyCoordinator::new();
+        coordinator1.attach(&root);
+        coordinator2.attach(&root);
+
+        root.step().unwrap();
+        root.step().unwrap();
+
+        assert_eq!(coordinator1.step(), 2);
+        assert_eq!(coordinator2.step(), 2);
+    }
+}
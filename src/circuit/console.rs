@@ -0,0 +1,376 @@
+//! Interactive debug console for inspecting a running circuit.
+//!
+//! A [`Console`] serves line-based commands read from any input stream, e.g.,
+//! standard input or a TCP connection, against a top-level circuit.  It can
+//! list the operators of the circuit, report the size of the state
+//! registered with it, dump the contents of registered traces, and step the
+//! circuit, which gives basic observability to long-running deployments
+//! without custom instrumentation.
+//!
+//! Circuits are not thread-safe, so the console runs in the thread that owns
+//! the [`Root`] and blocks it while serving a session.  In a multi-worker
+//! [`Runtime`](`crate::circuit::Runtime`), each worker owns a separate
+//! circuit; create a console in each worker that needs to be inspected,
+//! e.g., listening on a different port per worker.
+//!
+//! # Commands
+//!
+//! * `help` - list commands.
+//! * `operators` - list the operators of the circuit with their summaries.
+//! * `sizes` - print the size of each trace registered with
+//!   [`Console::track_state`].
+//! * `traces` - list the traces registered with [`Console::register_trace`].
+//! * `dump <trace> [<key>]` - dump the contents of a trace as CSV (see
+//!   [`TraceReader::dump`]), optionally only the updates for the key whose
+//!   [`Debug`] representation is `<key>`.
+//! * `step [<n>]` - evaluate `n` (default 1) steps of the circuit.
+//! * `quit` - end the session.
+
+use crate::{
+    circuit::{circuit_builder::Node, trace::SchedulerEvent, Circuit, Root, Stream},
+    trace::{dump::dump, DumpFormat, TraceReader, TraceSnapshot},
+};
+use deepsize::DeepSizeOf;
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    fmt::Debug,
+    io::{self, BufRead, BufReader, Write},
+    net::TcpListener,
+    rc::Rc,
+};
+
+// Writes the contents of a trace, optionally restricted to one key, to the
+// output of the console.
+type TraceDumper = Box<dyn Fn(Option<&str>, &mut dyn Write) -> io::Result<()>>;
+
+// Computes the size of the latest snapshot of a trace in bytes.
+type StateSizer = Box<dyn Fn() -> usize>;
+
+// Latest snapshot of a trace, if any.
+type LatestSnapshot<B> = Rc<RefCell<Option<TraceSnapshot<B>>>>;
+
+/// Debug console attached to a top-level circuit.
+///
+/// See [module-level documentation](`self`).
+pub struct Console {
+    circuit: Circuit<()>,
+    states: BTreeMap<String, StateSizer>,
+    traces: BTreeMap<String, TraceDumper>,
+}
+
+impl Console {
+    /// Create a console for `circuit`.
+    ///
+    /// Must be called from the constructor of the circuit (see
+    /// [`Root::build`]), which is also where streams are registered with
+    /// the console.
+    pub fn new(circuit: &Circuit<()>) -> Self {
+        Self {
+            circuit: circuit.clone(),
+            states: BTreeMap::new(),
+            traces: BTreeMap::new(),
+        }
+    }
+
+    /// Report the size of the trace in `stream`, e.g., the output of
+    /// [`Stream::integrate_trace`], under `name` in the `sizes` command.
+    ///
+    /// The size is computed from a snapshot of the trace (see
+    /// [`Self::register_trace`]) when the `sizes` command runs, so tracking
+    /// a trace does not traverse it while nobody is looking.
+    pub fn track_state<T>(&mut self, name: &str, stream: &Stream<Circuit<()>, T>)
+    where
+        T: TraceReader + Clone + 'static,
+        T::Batch: DeepSizeOf,
+    {
+        let latest = self.latest_snapshot(&format!("console state {}", name), stream);
+        self.states.insert(
+            name.to_string(),
+            Box::new(move || {
+                latest
+                    .borrow()
+                    .as_ref()
+                    .map(|snapshot| {
+                        snapshot
+                            .batches()
+                            .iter()
+                            .map(DeepSizeOf::deep_size_of)
+                            .sum()
+                    })
+                    .unwrap_or(0)
+            }),
+        );
+    }
+
+    /// Make the contents of the trace in `stream` available to the `dump`
+    /// command under `name`.
+    ///
+    /// The console takes a snapshot of the trace at the end of each clock
+    /// cycle and drops it at the start of the next one, so that the snapshot
+    /// does not share batches with the trace while the circuit is updating it
+    /// (see [`TraceSnapshot`]).
+    pub fn register_trace<T>(&mut self, name: &str, stream: &Stream<Circuit<()>, T>)
+    where
+        T: TraceReader + Clone + 'static,
+        T::Key: Debug + Ord,
+        T::Val: Debug + Ord,
+        T::Time: Debug,
+        T::R: Debug,
+    {
+        let latest = self.latest_snapshot(&format!("console trace {}", name), stream);
+        self.traces.insert(
+            name.to_string(),
+            Box::new(move |key, output| match &*latest.borrow() {
+                None => writeln!(output, "trace is empty"),
+                Some(snapshot) => dump(snapshot, output, DumpFormat::Csv, |k| {
+                    key.map(|key| format!("{:?}", k) == key).unwrap_or(true)
+                }),
+            }),
+        );
+    }
+
+    // Keeps a snapshot of the trace in `stream` taken at the end of each
+    // clock cycle and dropped at the start of the next one.
+    fn latest_snapshot<T>(
+        &self,
+        handler_name: &str,
+        stream: &Stream<Circuit<()>, T>,
+    ) -> LatestSnapshot<T::Batch>
+    where
+        T: TraceReader + Clone + 'static,
+    {
+        let latest: LatestSnapshot<T::Batch> = Rc::new(RefCell::new(None));
+        let latest_clone = latest.clone();
+        stream.inspect(move |trace| *latest_clone.borrow_mut() = Some(trace.snapshot()));
+
+        // Scheduler events of nested circuits are delivered to the same
+        // handler; only drop the snapshot at the start of a top-level step.
+        let latest_clone = latest.clone();
+        let depth = Cell::new(0usize);
+        self.circuit
+            .register_scheduler_event_handler(handler_name, move |event| match event {
+                SchedulerEvent::StepStart => {
+                    if depth.get() == 0 {
+                        *latest_clone.borrow_mut() = None;
+                    }
+                    depth.set(depth.get() + 1);
+                }
+                SchedulerEvent::StepEnd => depth.set(depth.get() - 1),
+                _ => {}
+            });
+
+        latest
+    }
+
+    /// Execute a single command against `root`, writing the response to
+    /// `output`.
+    ///
+    /// Returns `false` if the command ends the session.
+    pub fn execute<W>(&self, root: &Root, command: &str, output: &mut W) -> io::Result<bool>
+    where
+        W: Write,
+    {
+        let command = command.trim();
+        let (name, args) = command
+            .split_once(char::is_whitespace)
+            .map(|(name, args)| (name, args.trim()))
+            .unwrap_or((command, ""));
+
+        match name {
+            "" => {}
+            "help" => {
+                writeln!(output, "operators            list operators")?;
+                writeln!(output, "sizes                print state sizes")?;
+                writeln!(output, "traces               list traces")?;
+                writeln!(output, "dump <trace> [<key>] dump a trace as CSV")?;
+                writeln!(output, "step [<n>]           evaluate n steps")?;
+                writeln!(output, "quit                 end the session")?;
+            }
+            "operators" => {
+                let lines = self.circuit.map_nodes(|node: &dyn Node| {
+                    let mut summary = String::new();
+                    node.summary(&mut summary);
+                    format!(
+                        "[{}] {}{}{}",
+                        node.local_id().id(),
+                        node.name(),
+                        if node.is_stateful() {
+                            " (stateful)"
+                        } else {
+                            ""
+                        },
+                        if summary.is_empty() {
+                            String::new()
+                        } else {
+                            format!(": {}", summary)
+                        }
+                    )
+                });
+                for line in lines {
+                    writeln!(output, "{}", line)?;
+                }
+            }
+            "sizes" => {
+                for (name, size) in self.states.iter() {
+                    writeln!(output, "{}: {} bytes", name, size())?;
+                }
+            }
+            "traces" => {
+                for name in self.traces.keys() {
+                    writeln!(output, "{}", name)?;
+                }
+            }
+            "dump" => {
+                let (trace, key) = args
+                    .split_once(char::is_whitespace)
+                    .map(|(trace, key)| (trace, Some(key.trim())))
+                    .unwrap_or((args, None));
+                match self.traces.get(trace) {
+                    None => writeln!(output, "error: unknown trace '{}'", trace)?,
+                    Some(dumper) => dumper(key, output)?,
+                }
+            }
+            "step" => {
+                let steps = if args.is_empty() {
+                    Ok(1)
+                } else {
+                    args.parse::<usize>()
+                };
+                match steps {
+                    Err(_) => writeln!(output, "error: invalid number of steps '{}'", args)?,
+                    Ok(steps) => {
+                        for step in 0..steps {
+                            if let Err(error) = root.step() {
+                                writeln!(output, "error: step failed: {:?}", error)?;
+                                writeln!(output, "completed {} step(s)", step)?;
+                                return Ok(true);
+                            }
+                        }
+                        writeln!(output, "completed {} step(s)", steps)?;
+                    }
+                }
+            }
+            "quit" | "exit" => return Ok(false),
+            _ => writeln!(output, "error: unknown command '{}', try 'help'", name)?,
+        }
+
+        Ok(true)
+    }
+
+    /// Serve commands read from `input` one per line until `quit` or the end
+    /// of input, e.g., `console.serve(&root, io::stdin().lock(), io::stdout())`.
+    pub fn serve<R, W>(&self, root: &Root, input: R, mut output: W) -> io::Result<()>
+    where
+        R: BufRead,
+        W: Write,
+    {
+        write!(output, "> ")?;
+        output.flush()?;
+        for line in input.lines() {
+            if !self.execute(root, &line?, &mut output)? {
+                break;
+            }
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Accept a single connection on `listener` and serve commands sent over
+    /// it until the client quits or disconnects.
+    pub fn serve_tcp(&self, root: &Root, listener: &TcpListener) -> io::Result<()> {
+        let (stream, _address) = listener.accept()?;
+        self.serve(root, BufReader::new(stream.try_clone()?), stream)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Console;
+    use crate::{circuit::Root, operator::Generator, zset};
+    use std::cell::Cell;
+
+    #[test]
+    fn console_test() {
+        let mut console = None;
+        let root = Root::build(|circuit| {
+            let mut n = 0usize;
+            let input = circuit.add_source(Generator::new(move || {
+                n += 1;
+                zset! { n => 1isize }
+            }));
+            let trace = input.integrate_trace();
+
+            let mut c = Console::new(circuit);
+            c.track_state("numbers", &trace);
+            c.register_trace("numbers", &trace);
+            console = Some(c);
+        })
+        .unwrap();
+        let console = console.unwrap();
+
+        let mut output = Vec::new();
+        console
+            .serve(
+                &root,
+                &b"step 3\ntraces\ndump numbers 2\nsizes\nfoo\nquit\nstep\n"[..],
+                &mut output,
+            )
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("completed 3 step(s)"));
+        assert!(output.contains("key,val,time,weight\n2,(),(),1\n"));
+        assert!(!output.contains("\n1,(),(),1\n"));
+        assert!(output.contains("numbers: "));
+        assert!(!output.contains("numbers: 0 bytes"));
+        assert!(output.contains("error: unknown command 'foo'"));
+        // Commands after `quit` are not executed.
+        assert!(!output.contains("completed 1 step(s)"));
+
+        let mut output = Vec::new();
+        console.execute(&root, "operators", &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("[0] Generator"));
+        assert!(output.contains("(stateful)"));
+    }
+
+    // Steps of nested circuits evaluated after the trace don't drop its
+    // snapshot.
+    #[test]
+    fn console_nested_steps() {
+        let mut console = None;
+        let root = Root::build(|circuit| {
+            let input = circuit.add_source(Generator::new(|| zset! { 1usize => 1isize }));
+            let trace = input.integrate_trace();
+
+            // Evaluate the trace before the nested circuit.
+            let mut c = Console::new(circuit);
+            circuit.with_priority(1, || c.register_trace("numbers", &trace));
+            console = Some(c);
+
+            circuit
+                .iterate(|child| {
+                    child.add_source(Generator::new(|| 0usize));
+                    let iterations = Cell::new(0);
+                    Ok((
+                        move || {
+                            iterations.set(iterations.get() + 1);
+                            iterations.get() >= 3
+                        },
+                        (),
+                    ))
+                })
+                .unwrap();
+        })
+        .unwrap();
+        let console = console.unwrap();
+
+        root.step().unwrap();
+        let mut output = Vec::new();
+        console.execute(&root, "dump numbers", &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("key,val,time,weight\n1,(),(),1\n"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Human-readable description of a constructed circuit.
+
+use crate::circuit::{circuit_builder::Node, Circuit};
+use std::fmt::Write;
+
+impl<P> Circuit<P>
+where
+    P: 'static,
+{
+    /// Returns a human-readable description of the circuit.
+    ///
+    /// The description lists the nodes of the circuit in the order of their
+    /// ids, one node per line, along with operator parameters (see
+    /// [`Operator::params`](`crate::circuit::operator_traits::Operator::params`))
+    /// and the ids of the nodes that feed its input streams, e.g.:
+    ///
+    /// ```text
+    /// [0] Input
+    /// [1] MapKeys <- 0
+    /// [2] Z1 (trace) (stateful)
+    /// [3] UntimedTraceAppend <- 2, 1
+    /// [4] Z1 (trace) <- 3
+    /// cache: IntegrateTraceId created 1, reused 2
+    /// ```
+    ///
+    /// Nodes that hold state across clock cycles (see
+    /// [`Operator::is_stateful`](`crate::circuit::operator_traits::Operator::is_stateful`)),
+    /// e.g., `z^-1` operators that store the state of integrals, traces, and
+    /// delayed streams, are marked as `(stateful)`.  Nodes of nested
+    /// circuits are listed under their parent node with increased
+    /// indentation.  The last line of each circuit summarizes the use of
+    /// the circuit cache (see [`cache`](`crate::circuit::cache`)): the
+    /// number of derived streams, such as integrals and arrangements,
+    /// constructed and reused by operators in the circuit, which helps
+    /// estimating the cost of the circuit before running it.
+    pub fn explain(&self) -> String {
+        let mut output = String::new();
+        self.explain_nodes(&mut output, 0);
+        output
+    }
+
+    /// Append the description of the nodes of the circuit to `output`,
+    /// indented by `indent` levels.
+    pub(super) fn explain_nodes(&self, output: &mut String, indent: usize) {
+        let edges = self.edges();
+        let prefix = "    ".repeat(indent);
+
+        let nodes = self.map_nodes(|node: &dyn Node| {
+            let id = node.local_id();
+            let mut line = format!("{}[{}] {}", prefix, id.id(), node.name());
+
+            let params = node.params();
+            if !params.is_empty() {
+                let params: Vec<_> = params
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect();
+                let _ = write!(line, " {{{}}}", params.join(", "));
+            }
+
+            if node.is_stateful() {
+                line.push_str(" (stateful)");
+            }
+
+            let inputs: Vec<_> = edges
+                .iter()
+                .filter(|edge| edge.is_stream() && edge.to == id)
+                .map(|edge| edge.from.id().to_string())
+                .collect();
+            if !inputs.is_empty() {
+                let _ = write!(line, " <- {}", inputs.join(", "));
+            }
+            line.push('\n');
+
+            node.explain_children(&mut line, indent + 1);
+            line
+        });
+        drop(edges);
+
+        for node in nodes.iter() {
+            output.push_str(node);
+        }
+
+        let cache_stats = self.cache_stats();
+        if !cache_stats.is_empty() {
+            let stats: Vec<_> = cache_stats
+                .iter()
+                .map(|(key, stats)| {
+                    format!("{} created {}, reused {}", key, stats.created, stats.reused)
+                })
+                .collect();
+            let _ = writeln!(output, "{}cache: {}", prefix, stats.join("; "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{circuit::Root, operator::Generator, trace::ord::OrdZSet, zset};
+
+    #[test]
+    fn explain_test() {
+        let mut explanation = String::new();
+        Root::build(|circuit| {
+            let input = circuit.add_source(Generator::new(|| zset! { 1 => 1 }));
+            let input = input.map_keys::<OrdZSet<usize, isize>, _>(|x: &usize| x + 1);
+            input.integrate_trace();
+            input.integrate_trace();
+            input.topk_with_offset(10, 0, |x, y| x.cmp(y));
+            circuit
+                .iterate(|child| {
+                    let n = child.add_source(Generator::new(|| 1usize));
+                    Ok((|| true, n.delay()))
+                })
+                .unwrap();
+            explanation = circuit.explain();
+        })
+        .unwrap();
+
+        let lines: Vec<_> = explanation.lines().collect();
+        assert!(lines[0].starts_with("[0] Generator"));
+        assert_eq!(lines[1], "[1] MapKeys <- 0");
+        assert!(lines
+            .iter()
+            .any(|line| line.ends_with("(stateful)") && !line.starts_with(' ')));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("[") && line.contains("TopK {k=10, offset=0}")));
+        assert!(lines.iter().any(|line| line.contains("Subcircuit")));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("    [0] Generator")));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("    cache:")
+                && line.contains("DelayedId created 1, reused 0")));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("    [") && line.ends_with("Z^-1 (stateful) <- 0")));
+        assert!(lines
+            .last()
+            .unwrap()
+            .contains("IntegrateTraceId created 1, reused 1"));
+    }
+}
@@ -16,12 +16,21 @@ pub mod circuit_builder;
 mod runtime;
 
 pub mod cache;
+mod consistency;
+pub mod console;
+mod explain;
 pub mod operator_traits;
+pub mod plan;
 pub mod schedule;
+mod step;
+pub mod tenant;
 pub mod trace;
 
 pub use circuit_builder::{
     Circuit, ExportId, ExportStream, FeedbackConnector, GlobalNodeId, NodeId, OwnershipPreference,
     Root, Scope, Stream,
 };
-pub use runtime::{LocalStore, LocalStoreMarker, Runtime, RuntimeHandle};
+pub use consistency::{ConsistencyCoordinator, SourceHandle};
+pub use step::{StepStats, StepSummary};
+
+pub use runtime::{Error as RuntimeError, LocalStore, LocalStoreMarker, Runtime, RuntimeHandle};
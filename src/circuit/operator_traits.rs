@@ -165,6 +165,35 @@ pub trait Operator: 'static {
     /// ([`Stream::integrate`](`crate::circuit::Stream::integrate`)).
     fn fixedpoint(&self) -> bool;
 
+    /// Returns `false` if the operator is not monotone, i.e., adding tuples
+    /// to its inputs can remove tuples from its output.
+    ///
+    /// Negation (e.g., [`Stream::antijoin`](`crate::circuit::Stream::antijoin`))
+    /// is the canonical example.  Such operators can only consume the output of
+    /// a recursive computation once it has reached a fixed point, i.e., they
+    /// must belong to a later stratum than their inputs.  A fixed point circuit
+    /// (see [`Circuit::fixedpoint`](`crate::circuit::Circuit::fixedpoint`))
+    /// that contains a non-monotone operator in one of its feedback loops is
+    /// rejected with
+    /// [`SchedulerError::NonMonotoneRecursion`](`crate::circuit::schedule::Error::NonMonotoneRecursion`).
+    fn is_monotone(&self) -> bool {
+        true
+    }
+
+    /// Returns the error encountered by the operator during the last call to
+    /// `eval`, if any, and clears it.
+    ///
+    /// Operators cannot fail from `eval`, which must always produce an
+    /// output.  An operator that detects a condition that prevents it from
+    /// producing a meaningful output (e.g., the output exceeds a configured
+    /// size limit) instead produces an empty output and records the error,
+    /// which the circuit then reports as
+    /// [`SchedulerError::OperatorError`](`crate::circuit::schedule::Error::OperatorError`),
+    /// aborting the current step.
+    fn take_error(&mut self) -> Option<Cow<'static, str>> {
+        None
+    }
+
     /// Returns printable operator metadata, e.g., number of entries, heap
     /// usage, etc.
     // TODO: metadata is operator-specific, so we cannot use a pre-defined structure
@@ -172,6 +201,53 @@ pub trait Operator: 'static {
     fn summary(&self, output: &mut String) {
         output.clear();
     }
+
+    /// `true` if the operator holds state across clock cycles, e.g., the
+    /// `z^-1` operator, which stores the state of integrals and traces.
+    ///
+    /// Used to identify state-holding nodes in circuit descriptions (see
+    /// [`Circuit::explain`](`crate::circuit::Circuit::explain`)).  The
+    /// default implementation returns `false`.
+    fn is_stateful(&self) -> bool {
+        false
+    }
+
+    /// Returns serializable parameters of the operator as `(name, value)`
+    /// pairs, e.g., the `k` of a top-k operator.
+    ///
+    /// Parameters are included in the circuit plan (see
+    /// [`Circuit::plan`](`crate::circuit::Circuit::plan`)) and are passed to
+    /// operator constructors when the circuit is rebuilt from the plan.
+    /// Operators whose parameters are closures or are otherwise not
+    /// serializable return an empty vector (the default).
+    fn params(&self) -> Vec<(Cow<'static, str>, String)> {
+        Vec::new()
+    }
+
+    /// Perform up to `effort` units of background maintenance work on the
+    /// state of the operator, e.g., merging batches in a trace (see
+    /// [`Trace::exert`](`crate::trace::Trace::exert`)).
+    ///
+    /// Invoked between clock cycles when the circuit is idle (see
+    /// [`Root::exert`](`crate::circuit::Root::exert`)).  Returns `true` if
+    /// more maintenance work remains.  The default implementation does
+    /// nothing and returns `false`.
+    fn exert(&mut self, _effort: &mut isize) -> bool {
+        false
+    }
+
+    /// Returns `true` if the operator has pending internal work that
+    /// requires evaluating it at the current clock cycle even if its inputs
+    /// are idle, e.g., a trace with merges in progress or with updates not
+    /// yet observed by downstream operators (see
+    /// [`Trace::dirty`](`crate::trace::Trace::dirty`)).
+    ///
+    /// The circuit never skips an operator with pending work, regardless of
+    /// its [`idle_output`](`UnaryOperator::idle_output`).  The default
+    /// implementation returns `false`.
+    fn has_pending_work(&self) -> bool {
+        false
+    }
 }
 
 /// A source operator that injects data from the outside world or from the
@@ -180,6 +256,19 @@ pub trait Operator: 'static {
 pub trait SourceOperator<O>: Operator {
     /// Yield the next value.
     fn eval(&mut self) -> O;
+
+    /// Returns an empty output if the source has no data to yield at the
+    /// current clock cycle.
+    ///
+    /// Invoked before `eval` at every clock cycle.  If it returns `Some`, the
+    /// circuit uses the returned value as the output of the operator instead
+    /// of calling `eval`, and marks it as idle, which allows downstream
+    /// operators to skip evaluation as well (see
+    /// [`UnaryOperator::idle_output`]).  The default implementation returns
+    /// `None`.
+    fn idle_output(&mut self) -> Option<O> {
+        None
+    }
 }
 
 /// A sink operator consumes an input stream, but does not produce an output
@@ -207,6 +296,25 @@ pub trait UnaryOperator<I, O>: Operator {
     /// Consume input by reference.
     fn eval(&mut self, input: &I) -> O;
 
+    /// Returns the output of the operator for an idle input, if it can be
+    /// computed without evaluating the operator.
+    ///
+    /// An input is idle if it was produced by an upstream operator that
+    /// skipped evaluation, in which case it is empty.  Operators that
+    /// produce an empty output given an empty input should return an empty
+    /// output.  This method is only invoked if the operator has no pending
+    /// internal work (see [`Operator::has_pending_work`]).  The circuit then skips
+    /// `eval` and marks the output as idle, so that whole subgraphs of
+    /// operators whose inputs have not changed are skipped at each clock
+    /// cycle.  The operator must not rely on `eval` being invoked at every
+    /// clock cycle once it returns `Some`.
+    ///
+    /// The default implementation returns `None`, i.e., the operator is
+    /// always evaluated.
+    fn idle_output(&mut self) -> Option<O> {
+        None
+    }
+
     /// Consume input by value.
     fn eval_owned(&mut self, input: I) -> O {
         self.eval(&input)
@@ -225,6 +333,17 @@ pub trait BinaryOperator<I1, I2, O>: Operator {
     /// Consume input by reference.
     fn eval(&mut self, lhs: &I1, rhs: &I2) -> O;
 
+    /// Returns the output of the operator if at least one of its inputs is
+    /// idle, and the output can be computed without evaluating the operator.
+    ///
+    /// `lhs_idle` and `rhs_idle` indicate which inputs are idle.  For
+    /// example, the join of a change stream with a trace is empty when the
+    /// change stream is idle, regardless of the contents of the trace.  See
+    /// [`UnaryOperator::idle_output`].
+    fn idle_output(&mut self, _lhs_idle: bool, _rhs_idle: bool) -> Option<O> {
+        None
+    }
+
     /// Consume input by value.
     fn eval_owned(&mut self, lhs: I1, rhs: I2) -> O {
         self.eval(&lhs, &rhs)
@@ -0,0 +1,649 @@
+//! Portable descriptions of circuit topology.
+//!
+//! A [`Plan`] lists the nodes of a circuit along with their operator names,
+//! serializable parameters (see
+//! [`Operator::params`](`crate::circuit::operator_traits::Operator::params`)),
+//! and input streams.  A plan can be extracted from a constructed circuit
+//! using [`Circuit::plan`], converted to and from a line-oriented text format
+//! (via `Display` and `FromStr`), and used to build a circuit with a
+//! [`PlanRegistry`] of operator constructors.  This enables config-driven
+//! deployments, where circuits are described in configuration files rather
+//! than in code, as well as diffing plans across versions of a program.
+//!
+//! # Text format
+//!
+//! Each node is described by a line with tab-separated fields:
+//!
+//! ```text
+//! <node id> <operator> <comma-separated input ids> <name=value> ...
+//! ```
+//!
+//! Parameters are optional.  Backslashes, tabs, newlines, and `=` characters
+//! in operator names and parameters are escaped with a backslash (`\\`,
+//! `\t`, `\n`, `\=`).  Empty lines and lines starting with `#` are ignored.
+//!
+//! # Limitations
+//!
+//! Plans only describe the nodes of a single circuit; subcircuits appear as
+//! opaque nodes.  Strict operators (e.g., [`Z1`](`crate::operator::Z1`)),
+//! which are split into a pair of nodes, cannot be rebuilt from a plan, as
+//! [`Plan::build`] requires the inputs of each node to precede it in the
+//! plan.  Such operators, along with the feedback loops they close, must be
+//! encapsulated in registered constructors.
+
+use crate::circuit::{circuit_builder::Node, Circuit, Stream};
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    fmt::{Display, Write},
+    str::FromStr,
+};
+
+/// Errors building a circuit from a plan or parsing a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    /// Malformed line in the text representation of a plan.
+    Parse { line: usize, reason: String },
+    /// Two nodes in the plan have the same id.
+    DuplicateNode { node: usize },
+    /// The operator of a node is not in the registry.
+    UnknownOperator { node: usize, operator: String },
+    /// A node refers to an input that is not defined before it in the plan.
+    UnknownInput { node: usize, input: usize },
+    /// A node refers to an input node that doesn't produce an output stream,
+    /// e.g., a sink.
+    NoOutput { node: usize, input: usize },
+    /// A node has a wrong number of inputs for its operator.
+    InputCount {
+        node: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// The type of an input stream doesn't match the type expected by the
+    /// operator.
+    InputType { node: usize, input: usize },
+    /// A required parameter is missing.
+    MissingParam { node: usize, name: String },
+    /// A parameter has an invalid value.
+    InvalidParam {
+        node: usize,
+        name: String,
+        value: String,
+    },
+}
+
+/// A node in a [`Plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanNode {
+    /// Node id, unique within the plan.
+    pub id: usize,
+    /// Operator name used to look up the node's constructor in a
+    /// [`PlanRegistry`].
+    pub operator: String,
+    /// Ids of the nodes whose output streams are inputs of this node, in the
+    /// order of operator inputs.
+    pub inputs: Vec<usize>,
+    /// Operator parameters.
+    pub params: BTreeMap<String, String>,
+}
+
+impl PlanNode {
+    pub fn new<S>(id: usize, operator: S, inputs: Vec<usize>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id,
+            operator: operator.into(),
+            inputs,
+            params: BTreeMap::new(),
+        }
+    }
+
+    /// Add parameter `name` to the node.
+    pub fn with_param<N, V>(mut self, name: N, value: V) -> Self
+    where
+        N: Into<String>,
+        V: ToString,
+    {
+        self.params.insert(name.into(), value.to_string());
+        self
+    }
+
+    /// Returns the value of parameter `name`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+
+    /// Parses the value of a required parameter.
+    pub fn parse_param<T>(&self, name: &str) -> Result<T, PlanError>
+    where
+        T: FromStr,
+    {
+        let value = self.param(name).ok_or_else(|| PlanError::MissingParam {
+            node: self.id,
+            name: name.to_string(),
+        })?;
+
+        value.parse().map_err(|_| PlanError::InvalidParam {
+            node: self.id,
+            name: name.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+/// Description of the topology of a circuit.
+///
+/// See [module documentation](`crate::circuit::plan`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plan {
+    pub nodes: Vec<PlanNode>,
+}
+
+impl Plan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a node to the plan.
+    pub fn add_node(&mut self, node: PlanNode) {
+        self.nodes.push(node);
+    }
+
+    /// Returns the node with the given id.
+    pub fn node(&self, id: usize) -> Option<&PlanNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Instantiate the plan in `circuit`, using operator constructors in
+    /// `registry`.
+    ///
+    /// Nodes are created in the order they appear in the plan.  Returns the
+    /// output streams of the new nodes indexed by plan node ids.  The streams
+    /// are of type `Stream<Circuit<P>, D>`, where `D` is the output type of
+    /// the node's operator, and can be downcast to this type, e.g., to attach
+    /// additional operators to the circuit.
+    pub fn build<P>(
+        &self,
+        circuit: &Circuit<P>,
+        registry: &PlanRegistry<P>,
+    ) -> Result<BTreeMap<usize, Box<dyn Any>>, PlanError>
+    where
+        P: Clone + 'static,
+    {
+        // `None` for nodes without an output stream.
+        let mut streams: BTreeMap<usize, Option<Box<dyn Any>>> = BTreeMap::new();
+
+        for node in self.nodes.iter() {
+            if streams.contains_key(&node.id) {
+                return Err(PlanError::DuplicateNode { node: node.id });
+            }
+
+            let constructor = registry.constructors.get(&node.operator).ok_or_else(|| {
+                PlanError::UnknownOperator {
+                    node: node.id,
+                    operator: node.operator.clone(),
+                }
+            })?;
+
+            let mut inputs = Vec::with_capacity(node.inputs.len());
+            for input in node.inputs.iter() {
+                match streams.get(input) {
+                    None => {
+                        return Err(PlanError::UnknownInput {
+                            node: node.id,
+                            input: *input,
+                        })
+                    }
+                    Some(None) => {
+                        return Err(PlanError::NoOutput {
+                            node: node.id,
+                            input: *input,
+                        })
+                    }
+                    Some(Some(stream)) => inputs.push(&**stream),
+                }
+            }
+
+            let output = constructor(circuit, node, &inputs)?;
+            streams.insert(node.id, output);
+        }
+
+        Ok(streams
+            .into_iter()
+            .filter_map(|(id, stream)| stream.map(|stream| (id, stream)))
+            .collect())
+    }
+}
+
+impl<P> Circuit<P>
+where
+    P: Clone + 'static,
+{
+    /// Describe the topology of the circuit as a [`Plan`].
+    ///
+    /// The plan contains a node for each operator or subcircuit in the
+    /// circuit, labeled with the operator's name (see
+    /// [`Operator::name`](`crate::circuit::operator_traits::Operator::name`))
+    /// and parameters.  Plan node ids are equal to circuit node ids.
+    pub fn plan(&self) -> Plan {
+        let mut nodes = self.map_nodes(|node: &dyn Node| PlanNode {
+            id: node.local_id().id(),
+            operator: node.name().into_owned(),
+            inputs: Vec::new(),
+            params: node
+                .params()
+                .into_iter()
+                .map(|(name, value)| (name.into_owned(), value))
+                .collect(),
+        });
+
+        for edge in self.edges().iter().filter(|edge| edge.is_stream()) {
+            nodes[edge.to.id()].inputs.push(edge.from.id());
+        }
+
+        Plan { nodes }
+    }
+}
+
+/// Type-erased operator constructor.
+///
+/// Takes the circuit, the plan node, and references to the node's input
+/// streams, and returns the node's output stream, if any.
+type Constructor<P> =
+    Box<dyn Fn(&Circuit<P>, &PlanNode, &[&dyn Any]) -> Result<Option<Box<dyn Any>>, PlanError>>;
+
+/// A registry of operator constructors used to build circuits from plans
+/// (see [`Plan::build`]).
+///
+/// Constructors are registered under operator names used in plans.  Typed
+/// helpers, e.g., [`Self::register_unary`], check that the node has the
+/// expected number of inputs of the expected types before invoking the
+/// constructor.
+pub struct PlanRegistry<P> {
+    constructors: HashMap<String, Constructor<P>>,
+}
+
+impl<P> Default for PlanRegistry<P> {
+    fn default() -> Self {
+        Self {
+            constructors: HashMap::new(),
+        }
+    }
+}
+
+impl<P> PlanRegistry<P>
+where
+    P: Clone + 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `true` if a constructor is registered for `operator`.
+    pub fn contains(&self, operator: &str) -> bool {
+        self.constructors.contains_key(operator)
+    }
+
+    /// Register an untyped constructor for `operator`, replacing any
+    /// existing constructor.
+    ///
+    /// The constructor receives type-erased input streams of type
+    /// `Stream<Circuit<P>, D>` and returns its output stream, if any, in the
+    /// same form.
+    pub fn register<F>(&mut self, operator: &str, constructor: F)
+    where
+        F: Fn(&Circuit<P>, &PlanNode, &[&dyn Any]) -> Result<Option<Box<dyn Any>>, PlanError>
+            + 'static,
+    {
+        self.constructors
+            .insert(operator.to_string(), Box::new(constructor));
+    }
+
+    /// Register a constructor for a source operator.
+    pub fn register_source<O, F>(&mut self, operator: &str, constructor: F)
+    where
+        O: 'static,
+        F: Fn(&Circuit<P>, &PlanNode) -> Result<Stream<Circuit<P>, O>, PlanError> + 'static,
+    {
+        self.register(operator, move |circuit, node, inputs| {
+            check_inputs(node, inputs, 0)?;
+            Ok(Some(Box::new(constructor(circuit, node)?)))
+        });
+    }
+
+    /// Register a constructor for a unary operator.
+    pub fn register_unary<I, O, F>(&mut self, operator: &str, constructor: F)
+    where
+        I: 'static,
+        O: 'static,
+        F: Fn(&Stream<Circuit<P>, I>, &PlanNode) -> Result<Stream<Circuit<P>, O>, PlanError>
+            + 'static,
+    {
+        self.register(operator, move |_circuit, node, inputs| {
+            check_inputs(node, inputs, 1)?;
+            let input = input_stream::<P, I>(node, inputs, 0)?;
+            Ok(Some(Box::new(constructor(input, node)?)))
+        });
+    }
+
+    /// Register a constructor for a binary operator.
+    pub fn register_binary<I1, I2, O, F>(&mut self, operator: &str, constructor: F)
+    where
+        I1: 'static,
+        I2: 'static,
+        O: 'static,
+        F: Fn(
+                &Stream<Circuit<P>, I1>,
+                &Stream<Circuit<P>, I2>,
+                &PlanNode,
+            ) -> Result<Stream<Circuit<P>, O>, PlanError>
+            + 'static,
+    {
+        self.register(operator, move |_circuit, node, inputs| {
+            check_inputs(node, inputs, 2)?;
+            let input1 = input_stream::<P, I1>(node, inputs, 0)?;
+            let input2 = input_stream::<P, I2>(node, inputs, 1)?;
+            Ok(Some(Box::new(constructor(input1, input2, node)?)))
+        });
+    }
+
+    /// Register a constructor for a sink operator, which doesn't produce an
+    /// output stream.
+    pub fn register_sink<I, F>(&mut self, operator: &str, constructor: F)
+    where
+        I: 'static,
+        F: Fn(&Stream<Circuit<P>, I>, &PlanNode) -> Result<(), PlanError> + 'static,
+    {
+        self.register(operator, move |_circuit, node, inputs| {
+            check_inputs(node, inputs, 1)?;
+            constructor(input_stream::<P, I>(node, inputs, 0)?, node)?;
+            Ok(None)
+        });
+    }
+}
+
+fn check_inputs(node: &PlanNode, inputs: &[&dyn Any], expected: usize) -> Result<(), PlanError> {
+    if inputs.len() == expected {
+        Ok(())
+    } else {
+        Err(PlanError::InputCount {
+            node: node.id,
+            expected,
+            actual: inputs.len(),
+        })
+    }
+}
+
+fn input_stream<'a, P, D>(
+    node: &PlanNode,
+    inputs: &[&'a dyn Any],
+    index: usize,
+) -> Result<&'a Stream<Circuit<P>, D>, PlanError>
+where
+    P: 'static,
+    D: 'static,
+{
+    inputs[index].downcast_ref().ok_or(PlanError::InputType {
+        node: node.id,
+        input: node.inputs[index],
+    })
+}
+
+fn escape(s: &str) -> Cow<'_, str> {
+    if !s.contains(&['\\', '\t', '\n', '='][..]) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut escaped = String::with_capacity(s.len() + 1);
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '=' => escaped.push_str("\\="),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+fn unescape(s: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('\\') => unescaped.push('\\'),
+                Some('t') => unescaped.push('\t'),
+                Some('n') => unescaped.push('\n'),
+                Some('=') => unescaped.push('='),
+                Some(c) => return Err(format!("invalid escape sequence '\\{}'", c)),
+                None => return Err("unterminated escape sequence".to_string()),
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    Ok(unescaped)
+}
+
+// Split a `name=value` parameter at the first unescaped `=`.
+fn split_param(s: &str) -> Option<(&str, &str)> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '=' => return Some((&s[..i], &s[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in self.nodes.iter() {
+            write!(f, "{}\t{}\t", node.id, escape(&node.operator))?;
+            for (i, input) in node.inputs.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(',')?;
+                }
+                write!(f, "{}", input)?;
+            }
+            for (name, value) in node.params.iter() {
+                write!(f, "\t{}={}", escape(name), escape(value))?;
+            }
+            f.write_char('\n')?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Plan {
+    type Err = PlanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut plan = Plan::new();
+
+        for (line_no, line) in s.lines().enumerate() {
+            let line_no = line_no + 1;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |reason: String| PlanError::Parse {
+                line: line_no,
+                reason,
+            };
+
+            let mut fields = line.split('\t');
+            let id = fields
+                .next()
+                .unwrap()
+                .parse()
+                .map_err(|e| error(format!("invalid node id: {}", e)))?;
+            let operator = unescape(
+                fields
+                    .next()
+                    .ok_or_else(|| error("missing operator name".to_string()))?,
+            )
+            .map_err(error)?;
+            let inputs = match fields.next() {
+                None | Some("") => Vec::new(),
+                Some(inputs) => inputs
+                    .split(',')
+                    .map(|input| input.parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| error(format!("invalid input id: {}", e)))?,
+            };
+
+            let mut node = PlanNode::new(id, operator, inputs);
+            for param in fields {
+                let (name, value) = split_param(param)
+                    .ok_or_else(|| error(format!("invalid parameter '{}'", param)))?;
+                node.params.insert(
+                    unescape(name).map_err(error)?,
+                    unescape(value).map_err(error)?,
+                );
+            }
+
+            plan.add_node(node);
+        }
+
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Plan, PlanError, PlanNode, PlanRegistry};
+    use crate::{circuit::Root, operator::Generator};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn plan_text_format() {
+        let mut plan = Plan::new();
+        plan.add_node(PlanNode::new(0, "Generator", vec![]).with_param("start", 1));
+        plan.add_node(PlanNode::new(1, "Odd\tname", vec![0, 0]).with_param("a=b", "c\\d\ne"));
+        plan.add_node(PlanNode::new(2, "Inspect", vec![1]));
+
+        let text = plan.to_string();
+        assert_eq!(
+            text,
+            "0\tGenerator\t\tstart=1\n1\tOdd\\tname\t0,0\ta\\=b=c\\\\d\\ne\n2\tInspect\t1\n"
+        );
+        assert_eq!(text.parse::<Plan>().unwrap(), plan);
+
+        assert_eq!(
+            "# comment\n0\tGenerator\t\tstart\n".parse::<Plan>(),
+            Err(PlanError::Parse {
+                line: 2,
+                reason: "invalid parameter 'start'".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn build_from_plan() {
+        let outputs = Rc::new(RefCell::new(Vec::new()));
+        let outputs_clone = outputs.clone();
+
+        let mut registry = PlanRegistry::<()>::new();
+        registry.register_source("Counter", |circuit, node| {
+            let mut n: usize = node.parse_param("start")?;
+            Ok(circuit.add_source(Generator::new(move || {
+                n += 1;
+                n - 1
+            })))
+        });
+        registry.register_unary::<usize, usize, _>("Scale", |stream, node| {
+            let factor: usize = node.parse_param("factor")?;
+            Ok(stream.apply(move |x: &usize| x * factor))
+        });
+        registry.register_binary::<usize, usize, usize, _>("Add", |stream1, stream2, _node| {
+            Ok(stream1.apply2(stream2, |x: &usize, y: &usize| x + y))
+        });
+        registry.register_sink::<usize, _>("Collect", move |stream, _node| {
+            let outputs = outputs_clone.clone();
+            stream.inspect(move |x: &usize| outputs.borrow_mut().push(*x));
+            Ok(())
+        });
+
+        let plan: Plan = "0\tCounter\t\tstart=1\n\
+                          1\tScale\t0\tfactor=10\n\
+                          2\tAdd\t0,1\n\
+                          3\tCollect\t2\n"
+            .parse()
+            .unwrap();
+
+        let mut described = None;
+        let root = Root::build(|circuit| {
+            let streams = plan.build(circuit, &registry).unwrap();
+            assert_eq!(streams.keys().cloned().collect::<Vec<_>>(), vec![0, 1, 2]);
+            described = Some(circuit.plan());
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+        assert_eq!(*outputs.borrow(), vec![11, 22, 33]);
+
+        // The circuit has the same topology as the plan.
+        let described = described.unwrap();
+        let topology: Vec<_> = described
+            .nodes
+            .iter()
+            .map(|node| (node.operator.as_str(), node.inputs.clone()))
+            .collect();
+        assert_eq!(
+            topology,
+            vec![
+                ("Generator", vec![]),
+                ("Apply", vec![0]),
+                ("Apply2", vec![0, 1]),
+                ("Inspect", vec![2]),
+            ]
+        );
+
+        // Errors.
+        let build_error = |plan: &str| {
+            let plan: Plan = plan.parse().unwrap();
+            let mut error = None;
+            Root::build(|circuit| error = plan.build(circuit, &registry).err()).unwrap();
+            error.unwrap()
+        };
+        assert_eq!(
+            build_error("0\tCounter\t\tstart=1\n1\tFoo\t0\n"),
+            PlanError::UnknownOperator {
+                node: 1,
+                operator: "Foo".to_string()
+            }
+        );
+        assert_eq!(
+            build_error("0\tCounter\t\tstart=x\n"),
+            PlanError::InvalidParam {
+                node: 0,
+                name: "start".to_string(),
+                value: "x".to_string()
+            }
+        );
+        assert_eq!(
+            build_error("0\tCounter\t\tstart=1\n1\tAdd\t0\n"),
+            PlanError::InputCount {
+                node: 1,
+                expected: 2,
+                actual: 1
+            }
+        );
+        assert_eq!(
+            build_error("0\tScale\t1\tfactor=1\n1\tCounter\t\tstart=1\n"),
+            PlanError::UnknownInput { node: 0, input: 1 }
+        );
+    }
+}
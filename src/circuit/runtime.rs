@@ -2,11 +2,16 @@
 //! fashion.
 
 use crossbeam_utils::sync::{Parker, Unparker};
+use once_cell::sync::OnceCell;
 use std::{
+    any::Any,
+    cell::Cell,
+    fmt::{Display, Error as FmtError, Formatter},
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
     sync::{
         atomic::{AtomicBool, Ordering},
         mpsc::sync_channel,
-        Arc,
+        Arc, Mutex,
     },
     thread::{Builder, JoinHandle, LocalKey, Result as ThreadResult},
 };
@@ -22,8 +27,47 @@ thread_local! {
     // Schedulers must check this signal before evaluating each operator
     // and exit immediately returning `SchedulerError::Killed`.
     static KILL_SIGNAL: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+
+    // Index of the worker running in this thread.
+    static WORKER_INDEX: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Error returned by [`RuntimeHandle::join`] and [`RuntimeHandle::kill`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// A worker thread panicked.
+    ///
+    /// When a worker panics, the runtime signals all other workers to
+    /// terminate, so that they do not block forever waiting for messages
+    /// from the failed worker.  Peers terminated this way are likely to
+    /// panic too (e.g., on `root.step().unwrap()`); this error describes
+    /// the first panic observed by the runtime, which is the root cause of
+    /// the failure.
+    WorkerPanic {
+        /// Index of the worker thread that panicked.
+        worker_index: usize,
+        /// Panic message, if the panic payload was a string.
+        message: Option<String>,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Self::WorkerPanic {
+                worker_index,
+                message: Some(message),
+            } => write!(f, "worker {} panicked: {}", worker_index, message),
+            Self::WorkerPanic {
+                worker_index,
+                message: None,
+            } => write!(f, "worker {} panicked", worker_index),
+        }
+    }
 }
 
+impl std::error::Error for Error {}
+
 pub struct LocalStoreMarker;
 
 /// Local data store shared by all workers in a runtime.
@@ -32,6 +76,14 @@ pub type LocalStore = TypedDashMap<LocalStoreMarker>;
 struct RuntimeInner {
     nworkers: usize,
     store: LocalStore,
+    // Termination controls of all workers, used to cancel peers when
+    // one of the workers panics.  Initialized by `Runtime::run` once all
+    // workers have been spawned.
+    controls: OnceCell<Vec<(Unparker, Arc<AtomicBool>)>>,
+    // Set to `true` by the first worker that panics.
+    panicked: AtomicBool,
+    // Information about the first panic.
+    panic_info: Mutex<Option<Error>>,
 }
 
 impl RuntimeInner {
@@ -39,7 +91,47 @@ impl RuntimeInner {
         Self {
             nworkers,
             store: TypedDashMap::new(),
+            controls: OnceCell::new(),
+            panicked: AtomicBool::new(false),
+            panic_info: Mutex::new(None),
+        }
+    }
+
+    /// Signal all workers to terminate.
+    ///
+    /// Does nothing if worker controls haven't been initialized yet, in which
+    /// case `Runtime::run` will invoke this method once they are.
+    fn kill_workers(&self) {
+        if let Some(controls) = self.controls.get() {
+            for (unparker, kill_signal) in controls.iter() {
+                kill_signal.store(true, Ordering::SeqCst);
+                unparker.unpark();
+            }
+        }
+    }
+
+    /// Record a panic in worker `worker_index` and cancel all other workers.
+    fn worker_panicked(&self, worker_index: usize, payload: &(dyn Any + Send)) {
+        {
+            let mut panic_info = self.panic_info.lock().unwrap();
+            if panic_info.is_none() {
+                *panic_info = Some(Error::WorkerPanic {
+                    worker_index,
+                    message: panic_message(payload),
+                });
+            }
         }
+        self.panicked.store(true, Ordering::SeqCst);
+        self.kill_workers();
+    }
+}
+
+/// Extract panic message from panic payload.
+fn panic_message(payload: &(dyn Any + Send)) -> Option<String> {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        Some(message.to_string())
+    } else {
+        payload.downcast_ref::<String>().cloned()
     }
 }
 
@@ -58,6 +150,10 @@ impl Runtime {
     ///
     /// Returns a handle through which the caller can interact with the runtime.
     ///
+    /// If any of the workers panics, the runtime signals all other workers to
+    /// terminate and [`RuntimeHandle::join`] returns [`Error::WorkerPanic`]
+    /// describing the failed worker.
+    ///
     /// # Arguments
     ///
     /// * `nworkers` - the number of worker threads to spawn.
@@ -112,7 +208,11 @@ impl Runtime {
                             KILL_SIGNAL.with(|s| s.clone()),
                         ))
                         .unwrap();
-                    f(&runtime, i);
+                    WORKER_INDEX.with(|index| index.set(i));
+                    if let Err(payload) = catch_unwind(AssertUnwindSafe(|| f(&runtime, i))) {
+                        runtime.inner().worker_panicked(i, &*payload);
+                        resume_unwind(payload);
+                    }
                 })
                 .unwrap_or_else(|_| panic!("failed to spawn worker thread {}", i));
 
@@ -120,6 +220,17 @@ impl Runtime {
             workers.push(WorkerHandle::new(join_handle, unparker, kill_signal));
         }
 
+        let controls = workers
+            .iter()
+            .map(|worker| (worker.unparker.clone(), worker.kill_signal.clone()))
+            .collect();
+        let _ = runtime.inner().controls.set(controls);
+
+        // A worker may have panicked before controls were initialized.
+        if runtime.inner().panicked.load(Ordering::SeqCst) {
+            runtime.inner().kill_workers();
+        }
+
         RuntimeHandle::new(runtime, workers)
     }
 
@@ -132,6 +243,13 @@ impl Runtime {
         self.inner().nworkers
     }
 
+    /// Returns the index of the worker running in the current thread, or 0
+    /// if the current thread is not a worker thread of a runtime (e.g., a
+    /// circuit evaluated by the thread that created it).
+    pub fn worker_index() -> usize {
+        WORKER_INDEX.with(|index| index.get())
+    }
+
     /// Returns reference to the data store shared by all workers within the
     /// runtime.
     ///
@@ -220,27 +338,40 @@ impl RuntimeHandle {
     /// evaluated to completion, after which the worker thread terminates
     /// even if the circuit has not been fully evaluated for the current
     /// clock cycle.
-    pub fn kill(self) -> ThreadResult<()> {
-        for worker in self.workers.iter() {
-            worker.kill_signal.store(true, Ordering::SeqCst);
-            worker.unparker.unpark();
-        }
-
+    pub fn kill(self) -> Result<(), Error> {
+        self.runtime.inner().kill_workers();
         self.join()
     }
 
     /// Wait for all workers in the runtime to terminate.
     ///
     /// The calling thread blocks until all worker threads have terminated.
-    pub fn join(self) -> ThreadResult<()> {
+    /// Returns [`Error::WorkerPanic`] if any of the workers panicked.
+    pub fn join(self) -> Result<(), Error> {
         // Insist on joining all threads even if some of them fail.
         #[allow(clippy::needless_collect)]
-        let results: Vec<ThreadResult<()>> = self
+        let results: Vec<(usize, ThreadResult<()>)> = self
             .workers
             .into_iter()
             .map(|h| h.join_handle.join())
+            .enumerate()
             .collect();
-        results.into_iter().collect::<ThreadResult<()>>()
+
+        match results.into_iter().find(|(_, result)| result.is_err()) {
+            None => Ok(()),
+            Some((worker_index, Err(payload))) => Err(self
+                .runtime
+                .inner()
+                .panic_info
+                .lock()
+                .unwrap()
+                .take()
+                .unwrap_or_else(|| Error::WorkerPanic {
+                    worker_index,
+                    message: panic_message(&*payload),
+                })),
+            Some((_, Ok(()))) => unreachable!(),
+        }
     }
 }
 
@@ -254,7 +385,7 @@ impl TypedMapKey<LocalStoreMarker> for WorkerId {
 #[cfg(test)]
 mod tests {
 
-    use super::Runtime;
+    use super::{Error, Runtime};
     use crate::{
         circuit::{
             schedule::{DynamicScheduler, Scheduler, StaticScheduler},
@@ -346,4 +477,60 @@ mod tests {
         sleep(Duration::from_millis(100));
         hruntime.kill().unwrap();
     }
+
+    #[test]
+    fn test_worker_panic_static() {
+        test_worker_panic::<StaticScheduler>();
+    }
+
+    #[test]
+    fn test_worker_panic_dynamic() {
+        test_worker_panic::<DynamicScheduler>();
+    }
+
+    // A panic in one worker must terminate its peers and be reported by
+    // `RuntimeHandle::join`.
+    fn test_worker_panic<S>()
+    where
+        S: Scheduler + 'static,
+    {
+        let hruntime = Runtime::run(4, |_runtime, index| {
+            // Create a nested circuit that iterates forever.
+            let root = Root::build_with_scheduler::<_, S>(move |circuit| {
+                circuit
+                    .iterate_with_scheduler::<_, _, _, S>(|child| {
+                        let mut n: usize = 0;
+                        let source = child.add_source(Generator::new(move || {
+                            n += 1;
+                            n
+                        }));
+                        child.add_sink(
+                            Inspect::new(move |n: &usize| {
+                                if index == 2 && *n == 1000 {
+                                    panic!("worker 2 failed");
+                                }
+                            }),
+                            &source,
+                        );
+                        Ok((|| false, ()))
+                    })
+                    .unwrap();
+            })
+            .unwrap();
+
+            loop {
+                if root.step().is_err() {
+                    return;
+                }
+            }
+        });
+
+        assert_eq!(
+            hruntime.join(),
+            Err(Error::WorkerPanic {
+                worker_index: 2,
+                message: Some("worker 2 failed".to_string()),
+            })
+        );
+    }
 }
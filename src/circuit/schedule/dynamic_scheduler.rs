@@ -6,7 +6,9 @@
 //! 2. An async node can only be evaluated in a ready state (see
 //!    [`Operator::ready`](`crate::circuit::operator_traits::Operator::ready`)).
 //! 3. Pick a highest priority node among nodes that satisfy the first two
-//!    conditions.
+//!    conditions.  User-assigned priorities (see
+//!    [`Circuit::with_priority`](`crate::circuit::Circuit::with_priority`))
+//!    take precedence over heuristic priorities.
 //!
 //! Unlike [`StaticScheduler`](`crate::circuit::schedule::StaticScheduler`),
 //! the dynamic scheduler blocks when there are no runnable nodes instead of
@@ -25,8 +27,22 @@
 //! ## Run queue
 //!
 //! The run queue is organized as a priority queue, with the scheduler picking
-//! one of the highest-priority runnable tasks to run next.  Priority assignment
-//! is heuristic.
+//! one of the highest-priority runnable tasks to run next.  Tasks are ordered
+//! by their effective user-assigned priority, which a node inherits from its
+//! downstream nodes, and then by a heuristic priority.
+//!
+//! ## Activation
+//!
+//! Most nodes in a large circuit typically don't receive new inputs at a
+//! given clock cycle.  The scheduler tracks which nodes produced new outputs
+//! during the current clock cycle.  A node is activated when at least one of
+//! its predecessors is evaluated.  Once all dependencies of a node that is
+//! not activated are satisfied, the scheduler attempts to skip it (see
+//! `Node::skip`), bypassing the run queue.  Quiescent nodes are processed in
+//! the same priority order as the run queue.  Skipped nodes don't activate
+//! their successors, so entire quiescent subgraphs are processed at a
+//! fraction of the cost of evaluating them.  Nodes that cannot be skipped
+//! are moved to the run queue.
 //!
 //! ## Notification processing
 //!
@@ -48,7 +64,7 @@ use std::{
 use crate::circuit::{
     runtime::Runtime,
     schedule::{
-        util::{circuit_graph, ownership_constraints},
+        util::{circuit_graph, effective_priorities, ownership_constraints},
         Error, Scheduler,
     },
     trace::SchedulerEvent,
@@ -72,9 +88,10 @@ struct Task {
     /// Successors of the node in the circuit graph.
     successors: Vec<NodeId>,
 
-    /// Scheduling priority.  The scheduler picks the top priority node out
+    /// Scheduling priority: effective user-assigned priority followed by
+    /// heuristic priority.  The scheduler picks the top priority node out
     /// of all runnable nodes in the current state.
-    priority: isize,
+    priority: (isize, isize),
 
     /// `true` if this is an async node.  The node can only be evaluated in a
     /// ready state.
@@ -89,9 +106,13 @@ struct Task {
     /// `true` for non-async nodes.
     is_ready: bool,
 
-    /// Task has been scheduled (put on the run queue) in the current clock
-    /// cycle.
+    /// Task has been scheduled (put on the run queue or the list of
+    /// quiescent tasks) in the current clock cycle.
     scheduled: bool,
+
+    /// At least one predecessor of the node has been evaluated rather than
+    /// skipped in the current clock cycle.
+    activated: bool,
 }
 
 /// The set of async nodes for which the scheduler has received ready
@@ -120,8 +141,8 @@ impl Notifications {
     }
 }
 
-/// Runnable tasks sorted by priority.
-struct RunQueue(PriorityQueue<NodeId, isize>);
+/// Tasks sorted by priority.
+struct RunQueue(PriorityQueue<NodeId, (isize, isize)>);
 
 impl RunQueue {
     fn with_capacity(capacity: usize) -> Self {
@@ -132,7 +153,7 @@ impl RunQueue {
         self.0.is_empty()
     }
 
-    /// Add `task` to runnable queue.
+    /// Add `task` to the queue.
     fn push(&mut self, task: &mut Task) {
         debug_assert!(task.unsatisfied_dependencies == 0);
         debug_assert!(task.is_ready);
@@ -142,7 +163,7 @@ impl RunQueue {
         task.scheduled = true;
     }
 
-    fn pop(&mut self) -> Option<(NodeId, isize)> {
+    fn pop(&mut self) -> Option<(NodeId, (isize, isize))> {
         self.0.pop()
     }
 }
@@ -161,34 +182,49 @@ struct Inner {
 
     /// Tasks that are ready to be executed.
     runnable: RunQueue,
+
+    /// Tasks whose dependencies are satisfied, but that have not been
+    /// activated in the current clock cycle, sorted by priority.
+    quiescent: RunQueue,
 }
 
 impl Inner {
     /// Dequeue a highest-priority task from the runnable queue.
-    /// Update all successors of the task, reducing their unsatisfied
-    /// dependencies by 1.  Move successors to the runnable queue
-    /// when possible.
     fn dequeue_next_task(&mut self) -> Option<NodeId> {
-        if let Some((node_id, _)) = self.runnable.pop() {
-            let id = node_id.id();
-            debug_assert!(id < self.tasks.len());
-
-            // Update its successor dependencies.
-
-            // Don't use iterator, as we will borrow `tasks` again below.
-            for i in 0..self.tasks[id].successors.len() {
-                let succ_id = self.tasks[id].successors[i];
-                debug_assert!(succ_id.id() < self.tasks.len());
-                let successor = &mut self.tasks[succ_id.id()];
-                debug_assert!(successor.unsatisfied_dependencies != 0);
-                successor.unsatisfied_dependencies -= 1;
-                if successor.unsatisfied_dependencies == 0 && successor.is_ready {
-                    self.runnable.push(successor);
-                }
-            }
-            Some(node_id)
+        self.runnable.pop().map(|(node_id, _)| node_id)
+    }
+
+    /// Schedule a task whose dependencies are satisfied: move it to the
+    /// runnable queue if it is activated or async, or to the list of
+    /// quiescent tasks otherwise.
+    fn schedule_task(&mut self, node_id: NodeId) {
+        let task = &mut self.tasks[node_id.id()];
+        if task.activated || task.is_async {
+            self.runnable.push(task);
         } else {
-            None
+            self.quiescent.push(task);
+        }
+    }
+
+    /// Update all successors of a completed task, reducing their unsatisfied
+    /// dependencies by 1 and activating them if the task was evaluated
+    /// (`evaluated = true`) rather than skipped.  Schedule successors when
+    /// possible.
+    fn complete_task(&mut self, node_id: NodeId, evaluated: bool) {
+        let id = node_id.id();
+        debug_assert!(id < self.tasks.len());
+
+        // Don't use iterator, as we will borrow `tasks` again below.
+        for i in 0..self.tasks[id].successors.len() {
+            let succ_id = self.tasks[id].successors[i];
+            debug_assert!(succ_id.id() < self.tasks.len());
+            let successor = &mut self.tasks[succ_id.id()];
+            debug_assert!(successor.unsatisfied_dependencies != 0);
+            successor.unsatisfied_dependencies -= 1;
+            successor.activated |= evaluated;
+            if successor.unsatisfied_dependencies == 0 && successor.is_ready {
+                self.schedule_task(succ_id);
+            }
         }
     }
 
@@ -234,9 +270,10 @@ impl Inner {
         }
 
         // `toposort` fails if the graph contains cycles.
-        toposort(&g, None).map_err(|e| Error::CyclicCircuit {
+        let order = toposort(&g, None).map_err(|e| Error::CyclicCircuit {
             node_id: GlobalNodeId::child_of(circuit, e.node_id()),
         })?;
+        let user_priorities = effective_priorities(circuit, &g, &order);
 
         let num_nodes = circuit.num_nodes();
         let mut successors: HashMap<NodeId, Vec<NodeId>> = HashMap::with_capacity(num_nodes);
@@ -273,7 +310,10 @@ impl Inner {
             // streams during the evaluation of the circuit.
             let num_predecessors = predecessors.entry(node_id).or_default().len();
             let num_successors = successors.entry(node_id).or_default().len();
-            let priority = num_predecessors as isize - num_successors as isize;
+            let priority = (
+                user_priorities[i],
+                num_predecessors as isize - num_successors as isize,
+            );
 
             let is_async = circuit.is_async_node(node_id);
             if is_async {
@@ -288,6 +328,7 @@ impl Inner {
                 unsatisfied_dependencies: num_predecessors,
                 is_ready: !is_async,
                 scheduled: false,
+                activated: false,
             });
         }
 
@@ -296,6 +337,7 @@ impl Inner {
             tasks,
             notifications: Notifications::new(num_async_nodes, unparker),
             runnable: RunQueue::with_capacity(num_nodes),
+            quiescent: RunQueue::with_capacity(num_nodes),
         };
 
         // Setup scheduler callbacks.
@@ -330,8 +372,11 @@ impl Inner {
         for task in self.tasks.iter_mut() {
             task.unsatisfied_dependencies = task.num_predecessors;
             task.scheduled = false;
-            if task.unsatisfied_dependencies == 0 && task.is_ready {
-                self.runnable.push(task);
+            task.activated = false;
+        }
+        for i in 0..self.tasks.len() {
+            if self.tasks[i].unsatisfied_dependencies == 0 && self.tasks[i].is_ready {
+                self.schedule_task(self.tasks[i].node_id);
             }
         }
 
@@ -339,6 +384,21 @@ impl Inner {
             if Runtime::kill_in_progress() {
                 return Err(Error::Killed);
             }
+
+            // Quiescent tasks are cheap to process and can unblock more
+            // tasks, so we process them first, in priority order.
+            if let Some((node_id, _)) = self.quiescent.pop() {
+                if circuit.skip_node(node_id) {
+                    completed_tasks += 1;
+                    self.complete_task(node_id, false);
+                } else {
+                    let task = &mut self.tasks[node_id.id()];
+                    task.scheduled = false;
+                    self.runnable.push(task);
+                }
+                continue;
+            }
+
             match self.dequeue_next_task() {
                 None => {
                     // No more tasks in the run queue -- try to add some by
@@ -357,6 +417,7 @@ impl Inner {
                         self.tasks[node_id.id()].is_ready = false;
                     }
                     completed_tasks += 1;
+                    self.complete_task(node_id, true);
                 }
             }
         }
@@ -1,6 +1,7 @@
 //! The scheduling framework controls the execution of a circuit at runtime.
 
 use super::{trace::SchedulerEvent, Circuit, GlobalNodeId};
+use std::borrow::Cow;
 
 mod static_scheduler;
 pub use static_scheduler::StaticScheduler;
@@ -20,6 +21,22 @@ pub enum Error {
     },
     /// Ownership constraints introduce a cycle in the circuit graph.
     CyclicCircuit { node_id: GlobalNodeId },
+    /// A non-monotone operator (see
+    /// [`Operator::is_monotone`](`crate::circuit::operator_traits::Operator::is_monotone`))
+    /// is part of a feedback loop in a fixed point circuit.  Recursion through
+    /// negation is not stratified; the operator must be moved to a separate
+    /// stratum (see [`Stream::stratify`](`crate::circuit::Stream::stratify`)).
+    NonMonotoneRecursion {
+        node_id: GlobalNodeId,
+        name: Cow<'static, str>,
+    },
+    /// An operator failed during evaluation (see
+    /// [`Operator::take_error`](`crate::circuit::operator_traits::Operator::take_error`)).
+    OperatorError {
+        node_id: GlobalNodeId,
+        name: Cow<'static, str>,
+        error: Cow<'static, str>,
+    },
     /// Execution of the circuit interrupted by the user (via
     /// [`RuntimeHandle::kill`](`crate::circuit::RuntimeHandle::kill`)).
     Killed,
@@ -153,8 +170,12 @@ where
 mod util {
 
     use crate::circuit::{schedule::Error, Circuit, GlobalNodeId, NodeId, OwnershipPreference};
-    use petgraph::graphmap::DiGraphMap;
-    use std::{collections::HashMap, ops::Deref};
+    use petgraph::{graphmap::DiGraphMap, Direction};
+    use std::{
+        cmp::Reverse,
+        collections::{BinaryHeap, HashMap},
+        ops::Deref,
+    };
 
     /// Dump circuit topology as a graph.
     pub(crate) fn circuit_graph<P>(circuit: &Circuit<P>) -> DiGraphMap<NodeId, ()> {
@@ -171,6 +192,75 @@ mod util {
         g
     }
 
+    /// Compute effective scheduling priorities of circuit nodes.
+    ///
+    /// The effective priority of a node is the largest priority (see
+    /// [`Circuit::with_priority`]) of the node and all nodes reachable from
+    /// it in `g`, i.e., nodes inherit the priorities of their downstream
+    /// nodes, which must wait for them.  `order` is a topological order of
+    /// `g`.  Returns a vector of priorities indexed by node id.
+    pub(crate) fn effective_priorities<P>(
+        circuit: &Circuit<P>,
+        g: &DiGraphMap<NodeId, ()>,
+        order: &[NodeId],
+    ) -> Vec<isize> {
+        let mut priorities: Vec<isize> = circuit
+            .node_ids()
+            .into_iter()
+            .map(|node_id| circuit.node_priority(node_id))
+            .collect();
+
+        for node_id in order.iter().rev() {
+            let priority = g
+                .neighbors_directed(*node_id, Direction::Outgoing)
+                .map(|succ| priorities[succ.id()])
+                .fold(priorities[node_id.id()], isize::max);
+            priorities[node_id.id()] = priority;
+        }
+
+        priorities
+    }
+
+    /// Arrange nodes in a topological order of `g` that evaluates nodes with
+    /// higher `priorities` as early as possible.
+    ///
+    /// Ties are broken in favor of nodes with smaller ids, i.e., nodes
+    /// created earlier.
+    pub(crate) fn prioritized_toposort(
+        g: &DiGraphMap<NodeId, ()>,
+        priorities: &[isize],
+    ) -> Vec<NodeId> {
+        let mut in_degree: HashMap<NodeId, usize> = g
+            .nodes()
+            .map(|node_id| {
+                (
+                    node_id,
+                    g.neighbors_directed(node_id, Direction::Incoming).count(),
+                )
+            })
+            .collect();
+
+        let mut runnable: BinaryHeap<_> = in_degree
+            .iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(node_id, _)| (priorities[node_id.id()], Reverse(*node_id)))
+            .collect();
+
+        let mut order = Vec::with_capacity(in_degree.len());
+        while let Some((_, Reverse(node_id))) = runnable.pop() {
+            order.push(node_id);
+            for succ in g.neighbors_directed(node_id, Direction::Outgoing) {
+                let degree = in_degree.get_mut(&succ).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    runnable.push((priorities[succ.id()], Reverse(succ)));
+                }
+            }
+        }
+
+        order
+    }
+
     /// Helper function used by schedulers to enforce ownership preferences.
     ///
     /// Individual schedulers can implement their own algorithms to enforce (or
@@ -3,7 +3,7 @@
 use crate::circuit::{
     runtime::Runtime,
     schedule::{
-        util::{circuit_graph, ownership_constraints},
+        util::{circuit_graph, effective_priorities, ownership_constraints, prioritized_toposort},
         Error, Scheduler,
     },
     trace::SchedulerEvent,
@@ -38,10 +38,17 @@ impl Scheduler for StaticScheduler {
 
         // `toposort` fails if the graph contains cycles.
         // The circuit_builder API makes it impossible to construct such graphs.
-        let schedule = toposort(&g, None)
-            .map_err(|e| Error::CyclicCircuit {
-                node_id: GlobalNodeId::child_of(circuit, e.node_id()),
-            })?
+        let mut order = toposort(&g, None).map_err(|e| Error::CyclicCircuit {
+            node_id: GlobalNodeId::child_of(circuit, e.node_id()),
+        })?;
+
+        // Evaluate high-priority nodes and their upstream nodes first.
+        let priorities = effective_priorities(circuit, &g, &order);
+        if priorities.iter().any(|priority| *priority != 0) {
+            order = prioritized_toposort(&g, &priorities);
+        }
+
+        let schedule = order
             .into_iter()
             .map(|node_id| (node_id, circuit.is_async_node(node_id)))
             .collect();
@@ -0,0 +1,64 @@
+//! Per-step statistics reported to step hooks (see
+//! [`Root::after_step`](`crate::circuit::Root::after_step`)).
+
+use std::{cell::Cell, time::Duration};
+
+/// Counters of updates that entered and left the circuit during the current
+/// step.
+///
+/// The counters are shared by all subcircuits of a top-level circuit (see
+/// [`Circuit::step_stats`](`crate::circuit::Circuit::step_stats`)).  Input
+/// operators, e.g., the one created by
+/// [`Circuit::add_input`](`crate::circuit::Circuit::add_input`), report the
+/// number of updates they ingest, and output operators report the number of
+/// updates they produce.  Custom source and sink operators connecting the
+/// circuit to external systems should do the same.  The counters are reset
+/// at the start of each step.
+#[derive(Debug, Default)]
+pub struct StepStats {
+    inputs: Cell<usize>,
+    outputs: Cell<usize>,
+}
+
+impl StepStats {
+    /// Record `n` updates ingested by the circuit.
+    pub fn record_inputs(&self, n: usize) {
+        self.inputs.set(self.inputs.get() + n);
+    }
+
+    /// Record `n` updates produced by the circuit.
+    pub fn record_outputs(&self, n: usize) {
+        self.outputs.set(self.outputs.get() + n);
+    }
+
+    /// Number of updates ingested during the current step.
+    pub fn inputs(&self) -> usize {
+        self.inputs.get()
+    }
+
+    /// Number of updates produced during the current step.
+    pub fn outputs(&self) -> usize {
+        self.outputs.get()
+    }
+
+    pub(crate) fn reset(&self) {
+        self.inputs.set(0);
+        self.outputs.set(0);
+    }
+}
+
+/// Summary of a completed step passed to hooks registered with
+/// [`Root::after_step`](`crate::circuit::Root::after_step`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StepSummary {
+    /// Step number, starting from 0.
+    pub step: u64,
+    /// Number of updates ingested by the circuit during the step (see
+    /// [`StepStats`]).
+    pub inputs: usize,
+    /// Number of updates produced by the circuit during the step (see
+    /// [`StepStats`]).
+    pub outputs: usize,
+    /// Wall-clock time it took to evaluate the circuit.
+    pub duration: Duration,
+}
@@ -0,0 +1,658 @@
+//! Hosting multiple independent circuits with resource quotas.
+//!
+//! [`Tenants`] owns a set of top-level circuits, e.g., incremental queries
+//! of different users of a shared service, and steps them on behalf of
+//! their owners.  Each tenant has a [`Quota`] that limits the CPU time spent
+//! evaluating its circuit and the memory used by its state.  Pending steps
+//! are executed in a fair order: the tenant that has spent the least CPU time
+//! stepping its circuit so far goes first, so a tenant with expensive queries
+//! cannot starve the others.
+//!
+//! CPU time is measured for the hosting thread with
+//! `getrusage(RUSAGE_THREAD)`, so time during which the thread was preempted
+//! or blocked is not charged to the tenant.  On platforms other than Linux,
+//! the wall-clock time of [`Root::step`] is used instead.
+//!
+//! Memory usage is the size of the state registered with the
+//! [`StateTracker`].  Measuring it requires traversing the state, so it is
+//! measured at the first step and then once every [`DEFAULT_SAMPLE_INTERVAL`]
+//! steps (see [`Tenants::with_sample_interval`]).
+//!
+//! Quotas are checked before each step.  A step is never interrupted and
+//! the state may grow between two measurements, so a tenant can exceed its
+//! quota by the resources consumed since the last check, after which it is
+//! suspended.
+//!
+//! # Multiple workers
+//!
+//! Circuits are not thread-safe, so each worker of a [`Runtime`] hosts its
+//! own circuits in an instance created with [`Tenants::for_runtime`].  The
+//! instances created by all workers form a single set of tenants: a tenant
+//! added by every worker has the same id in all of them, its usage is the
+//! total across workers, and its quota limits this total.  This requires
+//! workers to create their instances and add tenants to them in the same
+//! order.  Each worker checks quotas independently against the usage last
+//! reported by its peers, so workers may execute different numbers of steps
+//! of a tenant before suspending it.  Tenants whose circuits exchange data
+//! between workers can therefore block when their peers are suspended and
+//! should not be given quotas.
+
+use crate::{
+    circuit::{
+        schedule::Error as SchedulerError, Circuit, LocalStoreMarker, Root, Runtime, Stream,
+    },
+    operator::{StateBudget, DEFAULT_SAMPLE_INTERVAL},
+    trace::Batch,
+};
+use deepsize::DeepSizeOf;
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt::{self, Display},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use typedmap::TypedMapKey;
+
+/// Resource limits of a tenant.
+///
+/// `None` means unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quota {
+    /// Total CPU time that the tenant's circuit may spend in
+    /// [`Root::step`].
+    pub cpu_time: Option<Duration>,
+    /// Maximal size in bytes of the state tracked with [`StateTracker`].
+    pub memory: Option<usize>,
+}
+
+/// Resources consumed by a tenant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Total CPU time spent evaluating the tenant's circuit.
+    pub cpu_time: Duration,
+    /// Size in bytes of the tracked state at the last measurement.
+    pub memory: usize,
+    /// Number of completed steps.
+    pub steps: u64,
+}
+
+impl Usage {
+    fn add(self, other: &Self) -> Self {
+        Self {
+            cpu_time: self.cpu_time + other.cpu_time,
+            memory: self.memory + other.memory,
+            steps: self.steps + other.steps,
+        }
+    }
+}
+
+/// Unique identifier of a tenant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TenantId(usize);
+
+/// Errors reported by [`Tenants`].
+#[derive(Debug)]
+pub enum TenantError {
+    /// There is no tenant with this id.
+    UnknownTenant(TenantId),
+    /// The tenant was suspended after its circuit failed.
+    Suspended(TenantId),
+    /// The tenant exhausted its CPU time quota.  The tenant is suspended.
+    CpuTimeQuotaExceeded {
+        tenant: TenantId,
+        used: Duration,
+        quota: Duration,
+    },
+    /// The state of the tenant exceeds its memory quota.  The tenant is
+    /// suspended.
+    MemoryQuotaExceeded {
+        tenant: TenantId,
+        used: usize,
+        quota: usize,
+    },
+    /// Evaluation of the tenant's circuit failed.  The tenant is suspended.
+    Scheduler {
+        tenant: TenantId,
+        error: SchedulerError,
+    },
+}
+
+impl Display for TenantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownTenant(tenant) => write!(f, "unknown tenant {}", tenant.0),
+            Self::Suspended(tenant) => write!(f, "tenant {} is suspended", tenant.0),
+            Self::CpuTimeQuotaExceeded {
+                tenant,
+                used,
+                quota,
+            } => write!(
+                f,
+                "tenant {} spent {:?} of CPU time stepping its circuit, exhausting its quota of {:?}",
+                tenant.0, used, quota
+            ),
+            Self::MemoryQuotaExceeded {
+                tenant,
+                used,
+                quota,
+            } => write!(
+                f,
+                "tenant {} uses {} bytes of memory, exceeding its quota of {} bytes",
+                tenant.0, used, quota
+            ),
+            Self::Scheduler { tenant, error } => {
+                write!(f, "tenant {} failed: {:?}", tenant.0, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TenantError {}
+
+/// Tracks the state of a tenant's circuit for enforcing its memory quota.
+///
+/// Passed to the constructor of the circuit in [`Tenants::add_tenant`].
+/// The memory usage of the tenant is the total size of all streams
+/// registered with the tracker, measured once every
+/// [`Tenants::with_sample_interval`] steps.
+pub struct StateTracker {
+    budgets: RefCell<Vec<StateBudget>>,
+    sample_interval: usize,
+}
+
+impl StateTracker {
+    fn new(sample_interval: usize) -> Self {
+        Self {
+            budgets: RefCell::new(Vec::new()),
+            sample_interval,
+        }
+    }
+
+    /// Count the size of the values in `stream`, e.g., the output of
+    /// [`Stream::integrate`], towards the tenant's memory usage.
+    pub fn track<T>(&self, stream: &Stream<Circuit<()>, T>)
+    where
+        T: DeepSizeOf + Clone + 'static,
+    {
+        self.budgets.borrow_mut().push(
+            stream
+                .state_budget(usize::MAX)
+                .with_sample_interval(self.sample_interval),
+        );
+    }
+
+    /// Count the size of the trace of `stream` (see
+    /// [`Stream::trace_budget`]) towards the tenant's memory usage.
+    pub fn track_trace<T>(&self, stream: &Stream<Circuit<()>, T>)
+    where
+        T: Batch + DeepSizeOf + Clone + 'static,
+        T::Key: Ord,
+        T::Val: Ord,
+    {
+        self.budgets.borrow_mut().push(
+            stream
+                .trace_budget(usize::MAX)
+                .with_sample_interval(self.sample_interval),
+        );
+    }
+
+    fn size(&self) -> usize {
+        self.budgets
+            .borrow()
+            .iter()
+            .map(|budget| budget.size())
+            .sum()
+    }
+}
+
+// Usage of the tenants of a set by each of the workers hosting it.
+#[derive(Default)]
+struct SharedUsage {
+    usage: Mutex<BTreeMap<(TenantId, usize), Usage>>,
+}
+
+impl SharedUsage {
+    fn update(&self, id: TenantId, worker: usize, usage: Usage) {
+        self.usage.lock().unwrap().insert((id, worker), usage);
+    }
+
+    fn remove(&self, id: TenantId, worker: usize) {
+        self.usage.lock().unwrap().remove(&(id, worker));
+    }
+
+    // Usage of tenant `id` summed across workers.
+    fn total(&self, id: TenantId) -> Usage {
+        self.usage
+            .lock()
+            .unwrap()
+            .range((id, 0)..=(id, usize::MAX))
+            .fold(Usage::default(), |total, (_, usage)| total.add(usage))
+    }
+}
+
+// Key of the usage of a set of tenants in the local store of a runtime.
+#[derive(Hash, PartialEq, Eq)]
+struct TenantsId(usize);
+
+impl TypedMapKey<LocalStoreMarker> for TenantsId {
+    type Value = Arc<SharedUsage>;
+}
+
+struct Tenant {
+    root: Root,
+    quota: Quota,
+    state: StateTracker,
+    // Usage by this worker.
+    usage: Usage,
+    pending: usize,
+    suspended: bool,
+}
+
+impl Tenant {
+    // Check that the tenant has quota left for another step given its total
+    // usage across workers.
+    fn check_quota(&self, id: TenantId, usage: &Usage) -> Result<(), TenantError> {
+        if let Some(quota) = self.quota.cpu_time {
+            if usage.cpu_time >= quota {
+                return Err(TenantError::CpuTimeQuotaExceeded {
+                    tenant: id,
+                    used: usage.cpu_time,
+                    quota,
+                });
+            }
+        }
+        if let Some(quota) = self.quota.memory {
+            if usage.memory > quota {
+                return Err(TenantError::MemoryQuotaExceeded {
+                    tenant: id,
+                    used: usage.memory,
+                    quota,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+// CPU time consumed by the current thread.
+#[cfg(target_os = "linux")]
+fn thread_cpu_time() -> Duration {
+    let timeval = |tv: libc::timeval| {
+        Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+    };
+
+    // SAFETY: `rusage` is a plain C struct, for which all zeroes is a valid
+    // value, and `getrusage` only writes to it.
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::getrusage(libc::RUSAGE_THREAD, &mut usage) };
+    assert_eq!(result, 0, "getrusage failed");
+    timeval(usage.ru_utime) + timeval(usage.ru_stime)
+}
+
+// Wall-clock time elapsed since an arbitrary point in time, which stands in
+// for CPU time on platforms without per-thread resource usage.
+#[cfg(not(target_os = "linux"))]
+fn thread_cpu_time() -> Duration {
+    thread_local! {
+        static EPOCH: std::time::Instant = std::time::Instant::now();
+    }
+    EPOCH.with(|epoch| epoch.elapsed())
+}
+
+/// A set of independent circuits with per-circuit resource quotas.
+///
+/// See [module-level documentation](`self`).
+pub struct Tenants {
+    tenants: BTreeMap<TenantId, Tenant>,
+    next_id: usize,
+    // Index of the worker hosting this instance.
+    worker: usize,
+    shared: Arc<SharedUsage>,
+    sample_interval: usize,
+}
+
+impl Default for Tenants {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tenants {
+    /// Create an empty set of tenants hosted by the current thread.
+    pub fn new() -> Self {
+        Self {
+            tenants: BTreeMap::new(),
+            next_id: 0,
+            worker: 0,
+            shared: Arc::new(SharedUsage::default()),
+            sample_interval: DEFAULT_SAMPLE_INTERVAL,
+        }
+    }
+
+    /// Create the current worker's instance of a set of tenants hosted by
+    /// all workers of `runtime`.
+    ///
+    /// Must be called from a worker thread of `runtime`.  Every worker must
+    /// create its instances in the same order (see [module-level
+    /// documentation](`self`)).
+    pub fn for_runtime(runtime: &Runtime) -> Self {
+        let worker = Runtime::worker_index();
+        let id = TenantsId(runtime.sequence_next(worker));
+        let shared = runtime
+            .local_store()
+            .entry(id)
+            .or_insert_with(|| Arc::new(SharedUsage::default()))
+            .value()
+            .clone();
+        Self {
+            worker,
+            shared,
+            ..Self::new()
+        }
+    }
+
+    /// Measure the memory usage of tenants once every `cycles` steps instead
+    /// of every [`DEFAULT_SAMPLE_INTERVAL`] steps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cycles` is 0 or if the set already contains tenants.
+    pub fn with_sample_interval(mut self, cycles: usize) -> Self {
+        assert_ne!(cycles, 0, "sample interval must be positive");
+        assert!(
+            self.tenants.is_empty(),
+            "the sample interval must be set before adding tenants"
+        );
+        self.sample_interval = cycles;
+        self
+    }
+
+    /// Build a circuit for a new tenant with quota `quota`.
+    ///
+    /// `constructor` populates the circuit and registers the streams that
+    /// hold its state with the [`StateTracker`].
+    pub fn add_tenant<F>(
+        &mut self,
+        quota: Quota,
+        constructor: F,
+    ) -> Result<TenantId, SchedulerError>
+    where
+        F: FnOnce(&mut Circuit<()>, &StateTracker),
+    {
+        let state = StateTracker::new(self.sample_interval);
+        let root = Root::build(|circuit| constructor(circuit, &state))?;
+
+        let id = TenantId(self.next_id);
+        self.next_id += 1;
+        self.shared.update(id, self.worker, Usage::default());
+        self.tenants.insert(
+            id,
+            Tenant {
+                root,
+                quota,
+                state,
+                usage: Usage::default(),
+                pending: 0,
+                suspended: false,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Remove a tenant, dropping its circuit.
+    ///
+    /// Returns `false` if there is no such tenant.
+    pub fn remove_tenant(&mut self, id: TenantId) -> bool {
+        self.shared.remove(id, self.worker);
+        self.tenants.remove(&id).is_some()
+    }
+
+    /// Resources consumed by tenant `id` in all workers.
+    pub fn usage(&self, id: TenantId) -> Option<Usage> {
+        self.tenants.get(&id).map(|_| self.shared.total(id))
+    }
+
+    /// `true` if tenant `id` has been suspended because it exceeded its
+    /// quota or its circuit failed.
+    pub fn is_suspended(&self, id: TenantId) -> bool {
+        self.tenants
+            .get(&id)
+            .map(|tenant| tenant.suspended)
+            .unwrap_or(false)
+    }
+
+    /// Request a step of the circuit of tenant `id` to be executed by the
+    /// next call to [`Self::run`].
+    ///
+    /// Fails if the tenant does not exist, has exceeded its quota, or has
+    /// been suspended.
+    pub fn request_step(&mut self, id: TenantId) -> Result<(), TenantError> {
+        let tenant = self
+            .tenants
+            .get_mut(&id)
+            .ok_or(TenantError::UnknownTenant(id))?;
+        tenant.check_quota(id, &self.shared.total(id))?;
+        if tenant.suspended {
+            return Err(TenantError::Suspended(id));
+        }
+        tenant.pending += 1;
+        Ok(())
+    }
+
+    /// Execute all pending steps.
+    ///
+    /// Steps are executed one at a time, each time picking the tenant with
+    /// pending steps that has spent the least CPU time stepping its circuit
+    /// in this worker.
+    /// Quotas are checked before each step.  A tenant that has exhausted its
+    /// quota or whose circuit fails is suspended and its remaining steps are
+    /// dropped.  Returns the errors of all tenants
+    /// suspended during this call.
+    pub fn run(&mut self) -> Vec<TenantError> {
+        let mut errors = Vec::new();
+
+        while let Some((&id, tenant)) = self
+            .tenants
+            .iter_mut()
+            .filter(|(_, tenant)| tenant.pending > 0 && !tenant.suspended)
+            .min_by_key(|(_, tenant)| tenant.usage.cpu_time)
+        {
+            tenant.pending -= 1;
+
+            let result = tenant
+                .check_quota(id, &self.shared.total(id))
+                .and_then(|()| {
+                    let start = thread_cpu_time();
+                    let result = tenant.root.step();
+                    tenant.usage.cpu_time += thread_cpu_time().saturating_sub(start);
+
+                    let result = result
+                        .map_err(|error| TenantError::Scheduler { tenant: id, error })
+                        .map(|()| {
+                            tenant.usage.steps += 1;
+                            tenant.usage.memory = tenant.state.size();
+                        });
+                    self.shared.update(id, self.worker, tenant.usage);
+                    result
+                });
+
+            if let Err(error) = result {
+                tenant.suspended = true;
+                tenant.pending = 0;
+                errors.push(error);
+            }
+        }
+
+        errors
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Quota, TenantError, Tenants};
+    use crate::{
+        circuit::Runtime,
+        operator::Generator,
+        trace::{ord::OrdZSet, Batch},
+        zset,
+    };
+    use std::{
+        sync::{Arc, Barrier},
+        time::{Duration, Instant},
+    };
+
+    // Generates 100 new keys at every step.
+    fn growing_zset() -> impl FnMut() -> OrdZSet<usize, isize> {
+        let mut n = 0;
+        move || {
+            n += 100;
+            OrdZSet::from_tuples((), (n - 100..n).map(|i| ((i, ()), 1)).collect())
+        }
+    }
+
+    #[test]
+    fn tenants_test() {
+        let mut tenants = Tenants::new().with_sample_interval(4);
+
+        // A tenant whose state grows at every step.
+        let greedy = tenants
+            .add_tenant(
+                Quota {
+                    cpu_time: None,
+                    memory: Some(4096),
+                },
+                |circuit, state| {
+                    let input = circuit.add_source(Generator::new(growing_zset()));
+                    state.track_trace(&input);
+                },
+            )
+            .unwrap();
+
+        let modest = tenants
+            .add_tenant(Quota::default(), |circuit, state| {
+                let input = circuit.add_source(Generator::new(|| zset! { 1usize => 1isize }));
+                state.track(&input.integrate());
+            })
+            .unwrap();
+
+        for _ in 0..10 {
+            tenants.request_step(greedy).unwrap();
+            tenants.request_step(modest).unwrap();
+        }
+        let errors = tenants.run();
+
+        // Memory is measured at steps 1, 5, 9, ..., so the quota is exceeded
+        // at step 5 and the tenant is suspended before step 6.
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            TenantError::MemoryQuotaExceeded { tenant, quota: 4096, .. } if tenant == greedy
+        ));
+        assert!(tenants.is_suspended(greedy));
+        assert_eq!(tenants.usage(greedy).unwrap().steps, 5);
+        assert!(tenants.request_step(greedy).is_err());
+
+        assert!(!tenants.is_suspended(modest));
+        let usage = tenants.usage(modest).unwrap();
+        assert_eq!(usage.steps, 10);
+        assert!(usage.memory > 0);
+    }
+
+    #[test]
+    fn cpu_time_quota() {
+        let mut tenants = Tenants::new();
+
+        let busy = tenants
+            .add_tenant(
+                Quota {
+                    cpu_time: Some(Duration::from_millis(10)),
+                    memory: None,
+                },
+                |circuit, _state| {
+                    circuit.add_source(Generator::new(|| {
+                        let start = Instant::now();
+                        while start.elapsed() < Duration::from_millis(20) {}
+                        0usize
+                    }));
+                },
+            )
+            .unwrap();
+
+        // Sleeping does not consume CPU time.
+        let sleepy = tenants
+            .add_tenant(
+                Quota {
+                    cpu_time: Some(Duration::from_millis(10)),
+                    memory: None,
+                },
+                |circuit, _state| {
+                    circuit.add_source(Generator::new(|| {
+                        std::thread::sleep(Duration::from_millis(20));
+                        0usize
+                    }));
+                },
+            )
+            .unwrap();
+
+        for _ in 0..2 {
+            tenants.request_step(busy).unwrap();
+            tenants.request_step(sleepy).unwrap();
+        }
+        let errors = tenants.run();
+
+        // The first step exhausts the quota; the second one is not executed.
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            TenantError::CpuTimeQuotaExceeded { tenant, .. } if tenant == busy
+        ));
+        assert_eq!(tenants.usage(busy).unwrap().steps, 1);
+        assert!(tenants.is_suspended(busy));
+        assert_eq!(tenants.usage(sleepy).unwrap().steps, 2);
+    }
+
+    // Quotas limit the total usage of a tenant across workers.
+    #[test]
+    fn runtime_tenants() {
+        // Memory used by one worker after the first step.
+        let mut tenants = Tenants::new().with_sample_interval(1);
+        let id = tenants
+            .add_tenant(Quota::default(), |circuit, state| {
+                state.track_trace(&circuit.add_source(Generator::new(growing_zset())));
+            })
+            .unwrap();
+        tenants.request_step(id).unwrap();
+        tenants.run();
+        let memory = tenants.usage(id).unwrap().memory;
+
+        let barrier = Arc::new(Barrier::new(2));
+        let handle = Runtime::run(2, move |runtime, _index| {
+            let mut tenants = Tenants::for_runtime(runtime).with_sample_interval(1);
+            let id = tenants
+                .add_tenant(
+                    Quota {
+                        cpu_time: None,
+                        memory: Some(memory + memory / 2),
+                    },
+                    |circuit, state| {
+                        state.track_trace(&circuit.add_source(Generator::new(growing_zset())));
+                    },
+                )
+                .unwrap();
+
+            tenants.request_step(id).unwrap();
+            assert!(tenants.run().is_empty());
+            barrier.wait();
+
+            let usage = tenants.usage(id).unwrap();
+            assert_eq!(usage.steps, 2);
+            assert_eq!(usage.memory, 2 * memory);
+            assert!(matches!(
+                tenants.request_step(id),
+                Err(TenantError::MemoryQuotaExceeded { .. })
+            ));
+        });
+        handle.join().unwrap();
+    }
+}
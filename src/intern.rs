@@ -0,0 +1,222 @@
+//! String interning.
+//!
+//! Circuits that join or group on string columns spend much of their time
+//! cloning and comparing `String`s as tuples move through batchers, merges
+//! and traces.  [`InternedStr`] replaces such keys with 4-byte ids assigned
+//! by an [`Interner`].  An interner can be shared by all workers in a
+//! [`Runtime`], so that equal strings map to equal ids across workers.
+//!
+//! Interned strings are ordered by id, i.e., in the order in which they were
+//! first interned, and not lexicographically.
+
+use crate::{
+    algebra::{MonoidValue, ZSet},
+    circuit::{Circuit, LocalStoreMarker, Runtime, Stream},
+    trace::ord::OrdZSet,
+};
+use deepsize_derive::DeepSizeOf;
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::{Arc, RwLock},
+};
+use typedmap::TypedMapKey;
+
+/// An id of a string stored in an [`Interner`].
+#[derive(Clone, Copy, DeepSizeOf, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InternedStr(u32);
+
+impl InternedStr {
+    /// Numeric id of the string.
+    pub fn id(self) -> u32 {
+        self.0
+    }
+}
+
+impl Debug for InternedStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Default)]
+struct InternerInner {
+    ids: HashMap<Arc<str>, InternedStr>,
+    strings: Vec<Arc<str>>,
+}
+
+/// A thread-safe table that maps strings to [`InternedStr`] ids and back.
+///
+/// Strings are never removed from the interner.
+#[derive(Default)]
+pub struct Interner {
+    inner: RwLock<InternerInner>,
+}
+
+#[derive(Hash, PartialEq, Eq)]
+struct InternerId;
+
+impl TypedMapKey<LocalStoreMarker> for InternerId {
+    type Value = Arc<Interner>;
+}
+
+impl Interner {
+    /// Create an empty interner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the interner shared by all workers in `runtime`, creating it
+    /// on first use.
+    pub fn for_runtime(runtime: &Runtime) -> Arc<Self> {
+        runtime
+            .local_store()
+            .entry(InternerId)
+            .or_insert_with(|| Arc::new(Interner::new()))
+            .value()
+            .clone()
+    }
+
+    /// Number of distinct strings in the interner.
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().strings.len()
+    }
+
+    /// Returns `true` if the interner does not contain any strings.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the id of `s`, adding it to the interner if necessary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interner already contains `u32::MAX` strings.
+    pub fn intern(&self, s: &str) -> InternedStr {
+        if let Some(id) = self.lookup(s) {
+            return id;
+        }
+
+        let mut inner = self.inner.write().unwrap();
+        // Another thread may have interned the string while we were not
+        // holding the lock.
+        if let Some(id) = inner.ids.get(s) {
+            return *id;
+        }
+
+        let id = InternedStr(
+            u32::try_from(inner.strings.len()).expect("too many strings in the interner"),
+        );
+        let s: Arc<str> = Arc::from(s);
+        inner.strings.push(s.clone());
+        inner.ids.insert(s, id);
+        id
+    }
+
+    /// Returns the id of `s` if it has been interned.
+    pub fn lookup(&self, s: &str) -> Option<InternedStr> {
+        self.inner.read().unwrap().ids.get(s).copied()
+    }
+
+    /// Returns the string with id `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was produced by a different interner.
+    pub fn resolve(&self, id: InternedStr) -> Arc<str> {
+        self.inner.read().unwrap().strings[id.0 as usize].clone()
+    }
+}
+
+impl<P, Z> Stream<Circuit<P>, Z>
+where
+    P: Clone + 'static,
+    Z: ZSet,
+    Z::Key: AsRef<str>,
+    Z::R: MonoidValue,
+{
+    /// Replace string keys in `self` with ids assigned by `interner`.
+    pub fn intern_keys(
+        &self,
+        interner: Arc<Interner>,
+    ) -> Stream<Circuit<P>, OrdZSet<InternedStr, Z::R>> {
+        self.map_keys(move |s: &Z::Key| interner.intern(s.as_ref()))
+    }
+}
+
+impl<P, Z> Stream<Circuit<P>, Z>
+where
+    P: Clone + 'static,
+    Z: ZSet<Key = InternedStr>,
+    Z::R: MonoidValue,
+{
+    /// Replace interned keys in `self` with the strings they refer to.
+    pub fn resolve_keys(
+        &self,
+        interner: Arc<Interner>,
+    ) -> Stream<Circuit<P>, OrdZSet<String, Z::R>> {
+        self.map_keys(move |id: &InternedStr| interner.resolve(*id).to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Interner;
+    use crate::{
+        circuit::{Root, Runtime},
+        operator::Generator,
+        trace::ord::OrdZSet,
+        zset,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn interner() {
+        let interner = Interner::new();
+        assert!(interner.is_empty());
+
+        let foo = interner.intern("foo");
+        let bar = interner.intern("bar");
+        assert_ne!(foo, bar);
+        assert_eq!(interner.intern("foo"), foo);
+        assert_eq!(interner.lookup("bar"), Some(bar));
+        assert_eq!(interner.lookup("baz"), None);
+        assert_eq!(&*interner.resolve(bar), "bar");
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn shared_interner() {
+        let hruntime = Runtime::run(4, |runtime, _index| {
+            // All workers observe the same interner.
+            let interner = Interner::for_runtime(runtime);
+            assert_eq!(interner.intern("shared").id(), 0);
+        });
+        hruntime.join().unwrap();
+    }
+
+    #[test]
+    fn intern_stream() {
+        let interner = Arc::new(Interner::new());
+        let interner_clone = interner.clone();
+
+        let root = Root::build(move |circuit| {
+            let strings = circuit.add_source(Generator::new(|| -> OrdZSet<String, isize> {
+                zset! { "foo".to_string() => 1, "bar".to_string() => -1 }
+            }));
+            strings
+                .intern_keys(interner_clone.clone())
+                .resolve_keys(interner_clone)
+                .inspect(|resolved| {
+                    assert_eq!(
+                        resolved,
+                        &zset! { "foo".to_string() => 1, "bar".to_string() => -1 }
+                    )
+                });
+        })
+        .unwrap();
+
+        root.step().unwrap();
+        assert_eq!(interner.len(), 2);
+    }
+}
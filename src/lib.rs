@@ -10,10 +10,18 @@ pub use ref_pair::RefPair;
 mod num_entries;
 pub use num_entries::NumEntries;
 
+mod ord_by;
+pub use ord_by::{OrdBy, Projection};
+
+pub mod intern;
+
 pub mod algebra;
+#[cfg(feature = "bench")]
+pub mod bench_support;
 pub mod circuit;
 pub mod lattice;
 pub mod monitor;
 pub mod operator;
+pub mod prelude;
 pub mod profile;
 pub mod trace;
@@ -1,14 +1,18 @@
 //! Aggregation operators.
 
-use std::{borrow::Cow, marker::PhantomData, ops::Neg};
+use std::{
+    borrow::Cow, cmp::max, collections::BTreeSet, fmt::Write, marker::PhantomData, mem::take,
+    ops::Neg,
+};
 
 use crate::{
-    algebra::{HasOne, HasZero, IndexedZSet, ZRingValue, ZSet},
+    algebra::{AddAssignByRef, AddByRef, HasOne, HasZero, IndexedZSet, ZRingValue, ZSet},
     circuit::{
         operator_traits::{BinaryOperator, Operator, UnaryOperator},
-        Circuit, Stream,
+        Circuit, Scope, Stream,
     },
-    trace::{cursor::Cursor, BatchReader},
+    time::NestedTimestamp32,
+    trace::{cursor::Cursor, ord::OrdValSpine, BatchReader, Trace, TraceReader},
     NumEntries,
 };
 use deepsize::DeepSizeOf;
@@ -155,6 +159,42 @@ where
     */
 }
 
+impl<P, Z> Stream<Circuit<P>, Z>
+where
+    P: Clone + 'static,
+{
+    // TODO: Document it better (we need a better framework to explain nested
+    // incremental computations).
+    /// Incremental nested version of the [`Aggregate`] operator.
+    ///
+    /// This is equivalent to
+    /// `self.integrate().integrate_nested().aggregate(f).differentiate().
+    /// differentiate_nested()`.  Unlike
+    /// [`Stream::aggregate_incremental_nested`], this implementation
+    /// integrates the input stream into a trace indexed by nested timestamps
+    /// and only re-evaluates the aggregate for keys that can change at the
+    /// current iteration, which makes it usable inside recursive
+    /// (`fixedpoint`) scopes, e.g., to compute shortest paths with a `min`
+    /// aggregate.
+    pub fn aggregate_nested<F, O>(&self, f: F) -> Stream<Circuit<P>, O>
+    where
+        Z: IndexedZSet + DeepSizeOf,
+        Z::Key: Clone + Ord + DeepSizeOf,
+        Z::Val: Clone + Ord + DeepSizeOf,
+        Z::R: ZRingValue + DeepSizeOf,
+        F: Fn(&Z::Key, &mut Vec<(&Z::Val, Z::R)>) -> O::Key + 'static,
+        O: Clone + ZSet<R = Z::R> + 'static,
+    {
+        self.circuit().add_binary_operator(
+            AggregateTrace::new(f),
+            self,
+            &self
+                .trace::<OrdValSpine<Z::Key, Z::Val, NestedTimestamp32, Z::R>>()
+                .delay_trace(),
+        )
+    }
+}
+
 pub struct Aggregate<Z, F, O> {
     agg_func: F,
     _type: PhantomData<(Z, O)>,
@@ -301,13 +341,310 @@ where
     }
 }
 
+// Weights with which a single value appears in the history of a key,
+// split up the same way as in `DistinctTrace::eval_value`:
+//
+// * `w1` - previous epochs at times `t < self.time`.
+// * `w2` - previous epochs at time `self.time`.
+// * `w3` - the current epoch at times `t < self.time`.
+// * `w4` - the current input `delta`.
+struct ValWeights<R> {
+    w1: R,
+    w2: R,
+    w3: R,
+    w4: R,
+}
+
+impl<R> ValWeights<R>
+where
+    R: HasZero,
+{
+    fn new() -> Self {
+        Self {
+            w1: HasZero::zero(),
+            w2: HasZero::zero(),
+            w3: HasZero::zero(),
+            w4: HasZero::zero(),
+        }
+    }
+}
+
+/// Incremental nested version of the `Aggregate` operator.
+///
+/// Takes a stream of changes to an indexed Z-set in a nested scope and a
+/// delayed trace of the same stream and computes
+/// `(↑((↑aggregate)∆))∆`, i.e., the change to the aggregate at the current
+/// iteration of the current epoch.  See [`Stream::aggregate_nested`].
+pub struct AggregateTrace<Z, T, F, O>
+where
+    Z: IndexedZSet,
+{
+    agg_func: F,
+    // Keeps track of keys that need to be considered at future times.
+    // Specifically, `future_updates[i]` accumulates all keys observed during
+    // the current epoch whose aggregate can change at time `i`.
+    future_updates: Vec<BTreeSet<Z::Key>>,
+    // TODO: not needed once timekeeping is handled by the circuit.
+    time: u32,
+    empty_input: bool,
+    empty_output: bool,
+    _type: PhantomData<(Z, T, O)>,
+}
+
+impl<Z, T, F, O> AggregateTrace<Z, T, F, O>
+where
+    Z: IndexedZSet,
+{
+    pub fn new(agg_func: F) -> Self {
+        Self {
+            agg_func,
+            future_updates: Vec::new(),
+            time: 0,
+            empty_input: false,
+            empty_output: false,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<Z, T, F, O> AggregateTrace<Z, T, F, O>
+where
+    Z: IndexedZSet,
+    Z::Key: Clone + Ord,
+    Z::Val: Ord,
+    Z::R: ZRingValue,
+    T: TraceReader<Key = Z::Key, Val = Z::Val, Time = NestedTimestamp32, R = Z::R> + 'static,
+    F: Fn(&Z::Key, &mut Vec<(&Z::Val, Z::R)>) -> O::Key,
+    O: ZSet<R = Z::R>,
+{
+    // Evaluate nested incremental aggregate for a single key.
+    //
+    // Using the notation of `ValWeights`, the aggregate of the key is
+    // computed at four points in time:
+    //
+    // * previous epoch, previous iteration: `w1`;
+    // * previous epoch, current iteration: `w1 + w2`;
+    // * current epoch, previous iteration: `w1 + w3`;
+    // * current epoch, current iteration: `w1 + w2 + w3 + w4`.
+    //
+    // The output is the second-order difference of these four aggregates, i.e.,
+    // `(↑((↑aggregate)∆))∆`.
+    #[allow(clippy::type_complexity)]
+    fn eval_key<'a>(
+        &mut self,
+        trace_cursor: &mut T::Cursor,
+        trace: &'a T,
+        key: &Z::Key,
+        delta_vals: Vec<(&'a Z::Val, Z::R)>,
+        output: &mut Vec<((O::Key, ()), O::R)>,
+    ) {
+        let time = self.time;
+        let mut weights: Vec<(&'a Z::Val, ValWeights<Z::R>)> = Vec::new();
+        let mut next_ts: Option<NestedTimestamp32> = None;
+
+        trace_cursor.seek_key(trace, key);
+
+        if trace_cursor.key_valid(trace) && trace_cursor.key(trace) == key {
+            while trace_cursor.val_valid(trace) {
+                let mut w = ValWeights::<Z::R>::new();
+                trace_cursor.map_times(trace, |t, weight| {
+                    if !t.epoch() {
+                        if t.inner() < time {
+                            w.w1.add_assign_by_ref(weight);
+                        } else if t.inner() == time {
+                            w.w2.add_assign_by_ref(weight);
+                        } else if next_ts.is_none() || t < next_ts.as_ref().unwrap() {
+                            next_ts = Some(t.clone());
+                        }
+                    } else if t.inner() < time {
+                        w.w3.add_assign_by_ref(weight);
+                    }
+                });
+                weights.push((trace_cursor.val(trace), w));
+                trace_cursor.step_val(trace);
+            }
+        }
+
+        // Record next_ts in `self.future_updates`.
+        if let Some(next_ts) = next_ts {
+            let idx: usize = next_ts.inner() as usize;
+            self.future_updates[idx].insert(key.clone());
+        }
+
+        // Merge values from `delta` into `weights`.  Both are sorted by value.
+        let mut delta_vals = delta_vals.into_iter().peekable();
+        let mut vals = Vec::with_capacity(weights.len());
+        for (val, mut w) in weights.into_iter() {
+            while let Some((v, weight)) = delta_vals.next_if(|(v, _)| *v < val) {
+                let mut w = ValWeights::new();
+                w.w4 = weight;
+                vals.push((v, w));
+            }
+            if let Some((_, weight)) = delta_vals.next_if(|(v, _)| *v == val) {
+                w.w4 = weight;
+            }
+            vals.push((val, w));
+        }
+        for (v, weight) in delta_vals {
+            let mut w = ValWeights::new();
+            w.w4 = weight;
+            vals.push((v, w));
+        }
+
+        // The key has not changed during the current epoch: its aggregates in
+        // the current and previous epochs are identical.
+        if vals.iter().all(|(_, w)| w.w3.is_zero() && w.w4.is_zero()) {
+            return;
+        }
+
+        let groups: [(fn(&ValWeights<Z::R>) -> Z::R, Z::R); 4] = [
+            (|w| w.w1.clone(), Z::R::one()),
+            (|w| w.w1.add_by_ref(&w.w2), Z::R::one().neg()),
+            (|w| w.w1.add_by_ref(&w.w3), Z::R::one().neg()),
+            (
+                |w| w.w1.add_by_ref(&w.w2).add_by_ref(&w.w3).add_by_ref(&w.w4),
+                Z::R::one(),
+            ),
+        ];
+
+        let mut group = Vec::with_capacity(vals.len());
+        for (select, sign) in groups.into_iter() {
+            for (v, w) in vals.iter() {
+                let w = select(w);
+                // Skip values with weight zero.
+                if !w.is_zero() {
+                    group.push((*v, w));
+                }
+            }
+            // Skip groups that only contain values with weight zero.
+            if !group.is_empty() {
+                output.push((((self.agg_func)(key, &mut group), ()), sign));
+            }
+            group.clear();
+        }
+    }
+}
+
+impl<Z, T, F, O> Operator for AggregateTrace<Z, T, F, O>
+where
+    Z: IndexedZSet,
+    Z::Key: DeepSizeOf + Ord,
+    T: 'static,
+    F: 'static,
+    O: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("AggregateTrace")
+    }
+    fn clock_start(&mut self, scope: Scope) {
+        if scope == 0 {
+            self.time = 0;
+        }
+    }
+    fn clock_end(&mut self, scope: Scope) {
+        if scope == 0 {
+            self.future_updates.clear();
+            self.empty_input = false;
+            self.empty_output = false;
+        }
+    }
+    fn fixedpoint(&self) -> bool {
+        self.empty_input
+            && self.empty_output
+            && self
+                .future_updates
+                .iter()
+                .skip(self.time as usize)
+                .all(|keys| keys.is_empty())
+    }
+
+    fn summary(&self, summary: &mut String) {
+        let size: usize = self.future_updates.iter().map(|keys| keys.len()).sum();
+        writeln!(summary, "size: {}", size).unwrap();
+
+        let bytes = self.future_updates.deep_size_of();
+        writeln!(summary, "bytes: {}", bytes).unwrap();
+    }
+}
+
+impl<Z, T, F, O> BinaryOperator<Z, T, O> for AggregateTrace<Z, T, F, O>
+where
+    Z: IndexedZSet,
+    Z::Key: Clone + Ord + DeepSizeOf,
+    Z::Val: Ord,
+    Z::R: ZRingValue,
+    T: Trace<Key = Z::Key, Val = Z::Val, Time = NestedTimestamp32, R = Z::R> + 'static,
+    F: Fn(&Z::Key, &mut Vec<(&Z::Val, Z::R)>) -> O::Key + 'static,
+    O: Clone + ZSet<R = Z::R> + 'static,
+{
+    // Only keys in the current input `delta` and keys from earlier inputs
+    // observed during the current epoch that appeared in one of the previous
+    // epochs at the current time can change their aggregate.  The latter are
+    // tracked in `future_updates`, exactly like in `DistinctTrace`.
+    fn eval(&mut self, delta: &Z, trace: &T) -> O {
+        self.empty_input = delta.is_zero();
+
+        // Make sure we have enough room in `future_updates` to
+        // accommodate the largest timestamp in the trace.
+        let mut new_len: u32 = self.time + 1;
+        trace.map_batches(|batch| {
+            for ts in batch.upper().elements().iter() {
+                new_len = max(new_len, ts.inner() + 1);
+            }
+        });
+
+        self.future_updates
+            .resize(new_len as usize, BTreeSet::new());
+
+        let mut result = Vec::with_capacity(delta.len());
+
+        let mut trace_cursor = trace.cursor();
+        let mut delta_cursor = delta.cursor();
+
+        let candidates = take(&mut self.future_updates[self.time as usize]);
+        let mut candidates = candidates.iter().peekable();
+
+        // Iterate over keys that appear in either `future_updates[self.time]` or
+        // `delta`.
+        while delta_cursor.key_valid(delta) {
+            let key = delta_cursor.key(delta);
+
+            while let Some(candidate) = candidates.next_if(|candidate| *candidate < key) {
+                self.eval_key(&mut trace_cursor, trace, candidate, Vec::new(), &mut result);
+            }
+            candidates.next_if(|candidate| *candidate == key);
+
+            let mut delta_vals = Vec::new();
+            while delta_cursor.val_valid(delta) {
+                let w = delta_cursor.weight(delta);
+                if !w.is_zero() {
+                    delta_vals.push((delta_cursor.val(delta), w));
+                }
+                delta_cursor.step_val(delta);
+            }
+
+            self.eval_key(&mut trace_cursor, trace, key, delta_vals, &mut result);
+            delta_cursor.step_key(delta);
+        }
+        for candidate in candidates {
+            self.eval_key(&mut trace_cursor, trace, candidate, Vec::new(), &mut result);
+        }
+
+        self.time += 1;
+
+        let result = O::from_tuples((), result);
+        self.empty_output = result.is_zero();
+        result
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use std::{cell::RefCell, rc::Rc};
+    use std::{cell::RefCell, rc::Rc, vec};
 
     use crate::{
         circuit::{Root, Stream},
-        operator::{Apply2, GeneratorNested},
+        operator::{Apply2, DelayedFeedback, Generator, GeneratorNested},
         trace::ord::{OrdIndexedZSet, OrdZSet},
         zset,
     };
@@ -385,6 +722,21 @@ mod test {
                             assert_eq!(d1, d2);
                         });
 
+                    let sum_nested = input.aggregate_nested(sum);
+
+                    child
+                        .add_binary_operator(
+                            Apply2::new(
+                                |d1: &OrdZSet<(usize, isize), isize>,
+                                 d2: &OrdZSet<(usize, isize), isize>| {
+                                    (d1.clone(), d2.clone())
+                                },
+                            ),
+                            &sum_nested,
+                            &sum_noninc,
+                        )
+                        .inspect(|(d1, d2)| assert_eq!(d1, d2));
+
                     /*child
                     .add_binary_operator(
                         Apply2::new(
@@ -436,6 +788,21 @@ mod test {
                             assert_eq!(d1, d2);
                         });
 
+                    let min_nested = input.aggregate_nested(min);
+
+                    child
+                        .add_binary_operator(
+                            Apply2::new(
+                                |d1: &OrdZSet<(usize, usize), isize>,
+                                 d2: &OrdZSet<(usize, usize), isize>| {
+                                    (d1.clone(), d2.clone())
+                                },
+                            ),
+                            &min_nested,
+                            &min_noninc,
+                        )
+                        .inspect(|(d1, d2)| assert_eq!(d1, d2));
+
                     Ok((
                         move || {
                             *counter.borrow_mut() += 1;
@@ -452,4 +819,82 @@ mod test {
             root.step().unwrap();
         }
     }
+
+    #[test]
+    #[allow(clippy::type_complexity)]
+    fn aggregate_nested_shortest_path() {
+        let root = Root::build(move |circuit| {
+            // Changes to the weighted edges relation `(from, to, weight)`.
+            let mut edges: vec::IntoIter<OrdZSet<(usize, usize, usize), isize>> = vec![
+                zset! { (1, 2, 1) => 1, (2, 3, 1) => 1, (1, 3, 5) => 1 },
+                zset! { (3, 1, 1) => 1, (3, 4, 2) => 1 },
+                zset! { (2, 3, 1) => -1 },
+                zset! { (1, 4, 1) => 1 },
+            ]
+            .into_iter();
+
+            let mut roots: vec::IntoIter<OrdZSet<(usize, usize), isize>> =
+                vec![zset! { (1, 0) => 1 }].into_iter();
+
+            // Expected shortest distances from node 1.
+            let mut outputs: vec::IntoIter<OrdZSet<(usize, usize), isize>> = vec![
+                zset! { (1, 0) => 1, (2, 1) => 1, (3, 2) => 1 },
+                zset! { (1, 0) => 1, (2, 1) => 1, (3, 2) => 1, (4, 4) => 1 },
+                zset! { (1, 0) => 1, (2, 1) => 1, (3, 5) => 1, (4, 7) => 1 },
+                zset! { (1, 0) => 1, (2, 1) => 1, (3, 5) => 1, (4, 1) => 1 },
+            ]
+            .into_iter();
+
+            let edges: Stream<_, OrdZSet<(usize, usize, usize), isize>> =
+                circuit.add_source(Generator::new(move || edges.next().unwrap()));
+            let roots: Stream<_, OrdZSet<(usize, usize), isize>> =
+                circuit.add_source(Generator::new(move || {
+                    roots.next().unwrap_or_else(|| zset! {})
+                }));
+
+            let distances = circuit
+                .fixedpoint(|child| {
+                    let edges: Stream<_, OrdZSet<(usize, (usize, usize)), isize>> = edges
+                        .delta0(child)
+                        .map_keys(|&(from, to, weight)| (from, (to, weight)));
+                    let edges = edges.index::<OrdIndexedZSet<usize, (usize, usize), isize>>();
+                    let roots = roots.delta0(child);
+
+                    let distances_delayed =
+                        <DelayedFeedback<_, OrdZSet<(usize, usize), isize>>>::new(child);
+                    let distances_indexed: Stream<_, OrdIndexedZSet<usize, usize, isize>> =
+                        distances_delayed.stream().index();
+
+                    let candidates: Stream<_, OrdIndexedZSet<usize, usize, isize>> = roots
+                        .plus(
+                            &distances_indexed
+                                .join_trace(&edges, |_node, distance, (to, weight)| {
+                                    (*to, distance + weight)
+                                }),
+                        )
+                        .index();
+
+                    // The first value in each group is the smallest one.
+                    let distances = candidates.aggregate_nested(
+                        |node: &usize, distances: &mut Vec<(&usize, isize)>| {
+                            (*node, *distances[0].0)
+                        },
+                    );
+                    distances_delayed.connect(&distances);
+
+                    Ok(distances.integrate_trace().export())
+                })
+                .unwrap();
+
+            distances
+                .consolidate::<OrdZSet<_, _>>()
+                .integrate()
+                .inspect(move |ds| assert_eq!(*ds, outputs.next().unwrap()));
+        })
+        .unwrap();
+
+        for _ in 0..4 {
+            root.step().unwrap();
+        }
+    }
 }
@@ -1,8 +1,61 @@
 //! Binary operator that applies an arbitrary binary function to its inputs.
 
-use crate::circuit::operator_traits::{BinaryOperator, Operator};
+use crate::circuit::{
+    operator_traits::{BinaryOperator, Operator},
+    Circuit, Stream,
+};
 use std::borrow::Cow;
 
+impl<P, T1> Stream<Circuit<P>, T1>
+where
+    P: Clone + 'static,
+    T1: Clone + 'static,
+{
+    /// Apply the [`Apply2`] operator to `self` and `other`.
+    ///
+    /// Combines values of two streams of arbitrary types, e.g., auxiliary
+    /// control streams such as configuration parameters, counters, or
+    /// watermarks.
+    pub fn apply2<F, T2, T3>(
+        &self,
+        other: &Stream<Circuit<P>, T2>,
+        func: F,
+    ) -> Stream<Circuit<P>, T3>
+    where
+        F: Fn(&T1, &T2) -> T3 + 'static,
+        T2: Clone + 'static,
+        T3: Clone + 'static,
+    {
+        self.circuit()
+            .add_binary_operator(Apply2::new(func), self, other)
+    }
+
+    /// Apply a ternary function to `self`, `other1`, and `other2`.
+    pub fn apply3<F, T2, T3, T4>(
+        &self,
+        other1: &Stream<Circuit<P>, T2>,
+        other2: &Stream<Circuit<P>, T3>,
+        func: F,
+    ) -> Stream<Circuit<P>, T4>
+    where
+        F: Fn(&T1, &T2, &T3) -> T4 + 'static,
+        T2: Clone + 'static,
+        T3: Clone + 'static,
+        T4: Clone + 'static,
+    {
+        self.zip(other1)
+            .apply2(other2, move |(v1, v2), v3| func(v1, v2, v3))
+    }
+
+    /// Combine `self` and `other` into a stream of pairs.
+    pub fn zip<T2>(&self, other: &Stream<Circuit<P>, T2>) -> Stream<Circuit<P>, (T1, T2)>
+    where
+        T2: Clone + 'static,
+    {
+        self.apply2(other, |v1, v2| (v1.clone(), v2.clone()))
+    }
+}
+
 /// Applies a user-provided binary function to its inputs at each timestamp.
 pub struct Apply2<F> {
     func: F,
@@ -40,3 +93,37 @@ where
         (self.func)(i1, i2)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{circuit::Root, operator::Generator};
+
+    #[test]
+    fn apply3_test() {
+        let root = Root::build(move |circuit| {
+            let mut n = 0;
+            let counter = circuit.add_source(Generator::new(move || {
+                n += 1;
+                n
+            }));
+            let limit = circuit.add_source(Generator::new(|| 3));
+            let label = circuit.add_source(Generator::new(|| "step".to_string()));
+
+            let mut expected = 0;
+            counter
+                .apply3(&limit, &label, |n, limit, label| {
+                    (format!("{} {}", label, n), n >= limit)
+                })
+                .inspect(move |(label, done)| {
+                    expected += 1;
+                    assert_eq!(label, &format!("step {}", expected));
+                    assert_eq!(*done, expected >= 3);
+                });
+        })
+        .unwrap();
+
+        for _ in 0..5 {
+            root.step().unwrap();
+        }
+    }
+}
@@ -0,0 +1,436 @@
+//! Centralized management of multiple arrangements of a relation.
+//!
+//! Queries that join the same relation on different columns need several
+//! indexed copies (arrangements) of it, one per key permutation.  Building
+//! them independently with [`Stream::index_with`] and
+//! [`Stream::integrate_trace`] at every use site easily leads to an explosion
+//! of redundant spines whose combined size is hard to track.
+//! [`ArrangementSet`] keeps one primary arrangement of the relation and
+//! derives secondary orderings lazily, on first use, from the change stream
+//! of the relation, so that each ordering is maintained exactly once and the
+//! memory used by all of them can be capped.
+
+use crate::{
+    algebra::{ZRingValue, ZSet},
+    circuit::{
+        operator_traits::{Operator, SinkOperator},
+        Circuit, Stream,
+    },
+    trace::{ord::OrdIndexedZSet, spine_fueled::Spine},
+};
+use deepsize::DeepSizeOf;
+use std::{
+    any::Any, borrow::Cow, cell::RefCell, collections::BTreeMap, marker::PhantomData, rc::Rc,
+};
+
+/// Name under which the size of the primary arrangement is reported.
+pub const PRIMARY_ARRANGEMENT: &str = "<primary>";
+
+/// Default number of clock cycles between two measurements of the memory
+/// footprint of arrangements (see [`ArrangementSet::with_sample_interval`]).
+pub const DEFAULT_SAMPLE_INTERVAL: usize = 16;
+
+type ArrangementSizes = Rc<RefCell<BTreeMap<String, usize>>>;
+
+/// A relation together with its arrangements.
+///
+/// The relation is represented by its stream of changes (`delta`).  The
+/// primary arrangement is the integral of `delta` ([`Self::primary`]).
+/// Secondary arrangements are registered by name with [`Self::arrange_by`],
+/// which re-indexes the changes of the relation by a different key.  Each
+/// ordering is only built once per name: subsequent calls with the same name
+/// return the existing stream, so operators that need the same ordering
+/// share its spine (operators such as [`Stream::join_incremental`]
+/// integrate the indexed stream with [`Stream::integrate_trace`], which is
+/// cached per stream).  Secondary arrangements are updated incrementally
+/// from the delta of the relation and are never recomputed from the primary
+/// arrangement.
+///
+/// When created with [`Self::with_memory_budget`], the set measures the
+/// memory footprint of all its arrangements at the first clock cycle and
+/// then once every [`DEFAULT_SAMPLE_INTERVAL`] cycles (see
+/// [`Self::with_sample_interval`]), and fails the step with
+/// [`SchedulerError::OperatorError`](`crate::circuit::schedule::Error::OperatorError`)
+/// once their total size exceeds the budget.  Measuring requires traversing
+/// the traces, so it is disabled for sets without a budget, and the
+/// arrangements may grow beyond the budget between two measurements.
+///
+/// # Examples
+///
+/// ```
+/// # use dbsp::{
+/// #     circuit::Root,
+/// #     operator::{ArrangementSet, Generator},
+/// #     trace::ord::OrdZSet,
+/// #     zset,
+/// # };
+/// let root = Root::build(move |circuit| {
+///     // Edges `(from, to)` of a graph.
+///     let edges = circuit.add_source(Generator::new(|| {
+///         zset! { (1, 2) => 1isize, (2, 3) => 1 }
+///     }));
+///     let arrangements = ArrangementSet::new(&edges);
+///
+///     // Edges indexed by source and by destination node.
+///     let by_from = arrangements.arrange_by("by_from", |&(from, to)| (from, to));
+///     let by_to = arrangements.arrange_by("by_to", |&(from, to)| (to, from));
+///
+///     // Paths of length 2.
+///     by_to.join_incremental::<_, _, OrdZSet<_, _>>(&by_from, |&mid, &from, &to| {
+///         (from, mid, to)
+///     });
+/// })
+/// .unwrap();
+///
+/// root.step().unwrap();
+/// ```
+#[allow(clippy::type_complexity)]
+pub struct ArrangementSet<P, Z>
+where
+    Z: ZSet,
+{
+    delta: Stream<Circuit<P>, Z>,
+    primary: RefCell<Option<Stream<Circuit<P>, Spine<Rc<Z>>>>>,
+    // Type-erased `Stream<Circuit<P>, OrdIndexedZSet<K, V, Z::R>>` by name.
+    secondary: RefCell<BTreeMap<String, Box<dyn Any>>>,
+    sizes: ArrangementSizes,
+    budget: Option<usize>,
+    sample_interval: usize,
+}
+
+impl<P, Z> ArrangementSet<P, Z>
+where
+    P: Clone + 'static,
+    Z: ZSet + DeepSizeOf,
+    Z::Key: Ord + Clone + DeepSizeOf,
+    Z::R: ZRingValue + DeepSizeOf,
+{
+    /// Create an arrangement set for the relation whose changes are given
+    /// by `delta`.
+    pub fn new(delta: &Stream<Circuit<P>, Z>) -> Self {
+        Self {
+            delta: delta.clone(),
+            primary: RefCell::new(None),
+            secondary: RefCell::new(BTreeMap::new()),
+            sizes: Rc::new(RefCell::new(BTreeMap::new())),
+            budget: None,
+            sample_interval: DEFAULT_SAMPLE_INTERVAL,
+        }
+    }
+
+    /// Create an arrangement set whose arrangements may use at most `bytes`
+    /// bytes of memory.
+    pub fn with_memory_budget(delta: &Stream<Circuit<P>, Z>, bytes: usize) -> Self {
+        Self {
+            budget: Some(bytes),
+            ..Self::new(delta)
+        }
+    }
+
+    /// Measure the memory footprint of arrangements once every `cycles`
+    /// clock cycles instead of every [`DEFAULT_SAMPLE_INTERVAL`] cycles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cycles` is 0 or if the set already contains arrangements.
+    pub fn with_sample_interval(mut self, cycles: usize) -> Self {
+        assert_ne!(cycles, 0, "sample interval must be positive");
+        assert!(
+            self.primary.borrow().is_none() && self.secondary.borrow().is_empty(),
+            "the sample interval must be set before creating arrangements"
+        );
+        self.sample_interval = cycles;
+        self
+    }
+
+    /// Stream of changes to the relation.
+    pub fn delta(&self) -> &Stream<Circuit<P>, Z> {
+        &self.delta
+    }
+
+    /// The primary arrangement, i.e., the trace of the relation ordered by
+    /// its own key.
+    pub fn primary(&self) -> Stream<Circuit<P>, Spine<Rc<Z>>> {
+        self.primary
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                let trace = self.delta.integrate_trace();
+                self.measure(PRIMARY_ARRANGEMENT, &trace);
+                trace
+            })
+            .clone()
+    }
+
+    /// Stream of changes to the relation indexed by the key-value pairs
+    /// computed by `f`.
+    ///
+    /// The ordering is built on the first call with a given `name`;
+    /// subsequent calls return the same stream and ignore `f`.  Use
+    /// [`Stream::integrate_trace`] on the returned stream to access the
+    /// arrangement itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an arrangement called `name` with a different key or value
+    /// type already exists in the set.
+    pub fn arrange_by<K, V, F>(
+        &self,
+        name: &str,
+        f: F,
+    ) -> Stream<Circuit<P>, OrdIndexedZSet<K, V, Z::R>>
+    where
+        K: Ord + Clone + DeepSizeOf + Send + 'static,
+        V: Ord + Clone + DeepSizeOf + Send + 'static,
+        F: Fn(&Z::Key) -> (K, V) + Clone + 'static,
+    {
+        if let Some(stream) = self.secondary.borrow().get(name) {
+            return stream
+                .downcast_ref::<Stream<Circuit<P>, OrdIndexedZSet<K, V, Z::R>>>()
+                .unwrap_or_else(|| {
+                    panic!(
+                        "ArrangementSet: arrangement '{}' has a different type",
+                        name
+                    )
+                })
+                .clone();
+        }
+
+        let indexed = self.delta.index_with::<OrdIndexedZSet<K, V, Z::R>, _>(f);
+        if self.budget.is_some() {
+            self.measure(name, &indexed.integrate_trace());
+        }
+        self.secondary
+            .borrow_mut()
+            .insert(name.to_string(), Box::new(indexed.clone()));
+
+        indexed
+    }
+
+    /// Names of the secondary arrangements in the set.
+    pub fn arrangements(&self) -> Vec<String> {
+        self.secondary.borrow().keys().cloned().collect()
+    }
+
+    /// Memory used by each arrangement at the last measurement, as
+    /// measured by [`DeepSizeOf`].
+    ///
+    /// Only available for sets with a memory budget; empty otherwise.
+    pub fn memory_usage_by_arrangement(&self) -> BTreeMap<String, usize> {
+        self.sizes.borrow().clone()
+    }
+
+    /// Total memory used by all arrangements at the last measurement.
+    ///
+    /// Only available for sets with a memory budget; 0 otherwise.
+    pub fn memory_usage(&self) -> usize {
+        self.sizes.borrow().values().sum()
+    }
+
+    // Attach a sink that records the size of `trace` every
+    // `self.sample_interval` clock cycles.
+    fn measure<T>(&self, name: &str, trace: &Stream<Circuit<P>, T>)
+    where
+        T: DeepSizeOf + Clone + 'static,
+    {
+        if let Some(budget) = self.budget {
+            self.delta.circuit().add_sink(
+                ArrangementSize::new(name, self.sizes.clone(), budget, self.sample_interval),
+                trace,
+            );
+        }
+    }
+}
+
+// Sink that records the size of an arrangement in a map shared by all
+// arrangements of a set and reports an error when their total size exceeds
+// the budget.
+struct ArrangementSize<T> {
+    name: String,
+    sizes: ArrangementSizes,
+    budget: usize,
+    sample_interval: usize,
+    // Number of clock cycles evaluated so far.
+    step: usize,
+    error: Option<Cow<'static, str>>,
+    _type: PhantomData<T>,
+}
+
+impl<T> ArrangementSize<T> {
+    fn new(name: &str, sizes: ArrangementSizes, budget: usize, sample_interval: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            sizes,
+            budget,
+            sample_interval,
+            step: 0,
+            error: None,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T> Operator for ArrangementSize<T>
+where
+    T: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("ArrangementSize")
+    }
+    fn summary(&self, output: &mut String) {
+        let size = self.sizes.borrow().get(&self.name).cloned().unwrap_or(0);
+        *output = format!("{}: {} bytes", self.name, size);
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+    fn take_error(&mut self) -> Option<Cow<'static, str>> {
+        self.error.take()
+    }
+}
+
+impl<T> SinkOperator<T> for ArrangementSize<T>
+where
+    T: DeepSizeOf + 'static,
+{
+    fn eval(&mut self, trace: &T) {
+        let sample = self.step.is_multiple_of(self.sample_interval);
+        self.step += 1;
+        if !sample {
+            return;
+        }
+
+        let mut sizes = self.sizes.borrow_mut();
+        sizes.insert(self.name.clone(), trace.deep_size_of());
+
+        let total: usize = sizes.values().sum();
+        if total > self.budget {
+            self.error = Some(Cow::from(format!(
+                "arrangements use {} bytes, exceeding the memory budget of {} bytes",
+                total, self.budget
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ArrangementSet, PRIMARY_ARRANGEMENT};
+    use crate::{
+        circuit::{schedule::Error as SchedulerError, Root},
+        operator::Generator,
+        trace::ord::OrdZSet,
+        zset,
+    };
+    use std::{cell::RefCell, rc::Rc, vec};
+
+    #[test]
+    fn arrangement_set_test() {
+        let set = Rc::new(RefCell::new(None));
+        let set_clone = set.clone();
+
+        let root = Root::build(move |circuit| {
+            let mut input: vec::IntoIter<OrdZSet<(usize, usize), isize>> = vec![
+                zset! { (1, 2) => 1, (2, 3) => 1 },
+                zset! { (3, 4) => 1, (1, 2) => -1 },
+            ]
+            .into_iter();
+            let mut expected: vec::IntoIter<OrdZSet<(usize, usize, usize), isize>> = vec![
+                zset! { (1, 2, 3) => 1 },
+                zset! { (2, 3, 4) => 1, (1, 2, 3) => -1 },
+            ]
+            .into_iter();
+
+            let edges = circuit.add_source(Generator::new(move || input.next().unwrap()));
+            let arrangements = ArrangementSet::with_memory_budget(&edges, usize::MAX);
+
+            let by_to = arrangements.arrange_by("by_to", |&(from, to)| (to, from));
+            let by_from = arrangements.arrange_by("by_from", |&(from, to)| (from, to));
+            assert_eq!(
+                arrangements
+                    .arrange_by("by_to", |&(from, to)| (to, from))
+                    .local_node_id(),
+                by_to.local_node_id()
+            );
+            assert_eq!(
+                arrangements.arrangements(),
+                vec!["by_from".to_string(), "by_to".to_string()]
+            );
+            arrangements.primary();
+
+            by_to
+                .join_incremental::<_, _, OrdZSet<_, _>>(&by_from, |&mid, &from, &to| {
+                    (from, mid, to)
+                })
+                .inspect(move |paths| assert_eq!(*paths, expected.next().unwrap()));
+
+            *set_clone.borrow_mut() = Some(arrangements);
+        })
+        .unwrap();
+
+        for _ in 0..2 {
+            root.step().unwrap();
+        }
+
+        let set = set.borrow();
+        let usage = set.as_ref().unwrap().memory_usage_by_arrangement();
+        assert_eq!(
+            usage.keys().cloned().collect::<Vec<_>>(),
+            vec![
+                PRIMARY_ARRANGEMENT.to_string(),
+                "by_from".to_string(),
+                "by_to".to_string()
+            ]
+        );
+        assert_eq!(
+            set.as_ref().unwrap().memory_usage(),
+            usage.values().sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn arrangement_set_budget() {
+        let root = Root::build(move |circuit| {
+            let edges = circuit.add_source(Generator::new(|| zset! { (1usize, 2usize) => 1isize }));
+            let arrangements = ArrangementSet::with_memory_budget(&edges, 1);
+            arrangements.arrange_by("by_to", |&(from, to)| (to, from));
+        })
+        .unwrap();
+
+        match root.step() {
+            Err(SchedulerError::OperatorError { name, .. }) => {
+                assert_eq!(name, "ArrangementSize")
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn arrangement_set_sample_interval() {
+        let set = Rc::new(RefCell::new(None));
+        let set_clone = set.clone();
+
+        let root = Root::build(move |circuit| {
+            let mut n = 0;
+            let edges = circuit.add_source(Generator::new(move || {
+                n += 1;
+                zset! { (n, n + 1) => 1isize }
+            }));
+            let arrangements =
+                ArrangementSet::with_memory_budget(&edges, usize::MAX).with_sample_interval(3);
+            arrangements.primary();
+            *set_clone.borrow_mut() = Some(arrangements);
+        })
+        .unwrap();
+
+        // Sizes are measured at steps 0 and 3.
+        let mut usage = Vec::new();
+        for _ in 0..5 {
+            root.step().unwrap();
+            usage.push(set.borrow().as_ref().unwrap().memory_usage());
+        }
+        assert_eq!(usage[0], usage[1]);
+        assert_eq!(usage[1], usage[2]);
+        assert!(usage[3] > usage[2]);
+        assert_eq!(usage[3], usage[4]);
+    }
+}
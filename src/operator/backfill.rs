@@ -0,0 +1,311 @@
+//! Operator that feeds the historical contents of a trace to a new query in
+//! bounded chunks.
+
+use crate::{
+    algebra::HasZero,
+    circuit::{
+        operator_traits::{Operator, UnaryOperator},
+        Circuit, Scope, Stream,
+    },
+    trace::{cursor::Cursor, Batch, BatchReader, Builder},
+};
+use std::{borrow::Cow, cell::Cell, rc::Rc};
+
+#[derive(Default)]
+struct BackfillState {
+    chunk_size: Cell<usize>,
+    remaining: Cell<usize>,
+    complete: Cell<bool>,
+}
+
+/// Handle used to monitor and tune a backfill started with
+/// [`Stream::backfill`].
+#[derive(Clone)]
+pub struct BackfillHandle {
+    state: Rc<BackfillState>,
+}
+
+impl BackfillHandle {
+    /// `true` once all historical updates have been output.
+    pub fn is_complete(&self) -> bool {
+        self.state.complete.get()
+    }
+
+    /// Number of historical updates not yet output.
+    pub fn remaining(&self) -> usize {
+        self.state.remaining.get()
+    }
+
+    /// Maximal number of historical updates output per clock cycle.
+    pub fn chunk_size(&self) -> usize {
+        self.state.chunk_size.get()
+    }
+
+    /// Change the number of historical updates output per clock cycle,
+    /// starting from the next clock cycle.
+    pub fn set_chunk_size(&self, chunk_size: usize) {
+        assert!(chunk_size > 0, "Backfill: chunk_size must be positive");
+        self.state.chunk_size.set(chunk_size);
+    }
+}
+
+impl<P, B> Stream<Circuit<P>, B>
+where
+    P: Clone + 'static,
+    B: Batch<Time = ()> + Clone + 'static,
+    B::Key: Clone,
+    B::Val: Clone,
+{
+    /// Apply [`Backfill`] operator to `self`.
+    ///
+    /// Returns a stream that contains all updates in `self` plus the
+    /// contents of `history`, released at most `chunk_size` updates per
+    /// clock cycle, along with a handle that tracks the progress of the
+    /// backfill.
+    ///
+    /// A circuit cannot be modified once built, so a new query over an
+    /// existing input is attached by building a new circuit, e.g., one that
+    /// imports the input's change stream via [`Circuit::import_from_pipe`].
+    /// The new query must also see the updates that arrived before it was
+    /// attached, which are available as a [snapshot](`crate::trace::TraceReader::snapshot`)
+    /// of the input's trace.  Feeding the snapshot to the new operators in
+    /// a single step would make this step as expensive as evaluating the
+    /// query over the entire history at once.  Instead, `backfill` spreads
+    /// the historical updates over multiple clock cycles, bounding the work
+    /// per step, so the latency of other circuits running in the same
+    /// worker remains stable.
+    ///
+    /// Once [`BackfillHandle::is_complete`] returns `true`, the integral of
+    /// the output stream equals the integral of `self` plus `history`.  The
+    /// new query must therefore be incremental, i.e., compute changes to
+    /// its output from changes to its input (e.g., using
+    /// [`Stream::distinct_incremental`] and [`Stream::join_incremental`]),
+    /// in which case the integral of its output is correct from that point
+    /// on.  Before that, it reflects a partial history.  Linear operators
+    /// can be applied to the change stream directly; non-linear operators
+    /// such as `distinct` and `join` applied to individual chunks would
+    /// produce incorrect results.
+    pub fn backfill<H>(
+        &self,
+        history: H,
+        chunk_size: usize,
+    ) -> (Stream<Circuit<P>, B>, BackfillHandle)
+    where
+        H: BatchReader<Key = B::Key, Val = B::Val, Time = (), R = B::R> + 'static,
+    {
+        let backfill = Backfill::new(history, chunk_size);
+        let handle = backfill.handle();
+        (self.circuit().add_unary_operator(backfill, self), handle)
+    }
+}
+
+/// Operator that adds the contents of a historical batch to its input stream
+/// in bounded chunks.
+///
+/// At each clock cycle, the operator outputs its input plus up to
+/// `chunk_size` updates of `history` in key order, starting where the
+/// previous clock cycle left off.  The sum of all outputs is equal to the sum
+/// of all inputs plus `history` once the backfill is complete.
+///
+/// See [`Stream::backfill`].
+pub struct Backfill<H>
+where
+    H: BatchReader,
+{
+    history: H,
+    // Next key/value pair of `history` to output; `None` once the backfill is
+    // complete.
+    next: Option<(H::Key, H::Val)>,
+    output: usize,
+    state: Rc<BackfillState>,
+    empty_input: bool,
+}
+
+impl<H> Backfill<H>
+where
+    H: BatchReader<Time = ()>,
+    H::Key: Clone,
+    H::Val: Clone,
+{
+    pub fn new(history: H, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "Backfill: chunk_size must be positive");
+
+        let mut cursor = history.cursor();
+        let mut next = None;
+        while cursor.key_valid(&history) {
+            if cursor.val_valid(&history) {
+                next = Some((cursor.key(&history).clone(), cursor.val(&history).clone()));
+                break;
+            }
+            cursor.step_key(&history);
+        }
+
+        let state = Rc::new(BackfillState::default());
+        state.chunk_size.set(chunk_size);
+        state.remaining.set(history.len());
+        state.complete.set(next.is_none());
+
+        Self {
+            history,
+            next,
+            output: 0,
+            state,
+            empty_input: false,
+        }
+    }
+
+    /// Handle used to monitor the progress of the backfill.
+    pub fn handle(&self) -> BackfillHandle {
+        BackfillHandle {
+            state: self.state.clone(),
+        }
+    }
+
+    // Build the next chunk of historical updates.
+    fn next_chunk<B>(&mut self) -> B
+    where
+        B: Batch<Key = H::Key, Val = H::Val, Time = (), R = H::R>,
+    {
+        let (key, val) = match self.next.take() {
+            None => return B::empty(()),
+            Some(next) => next,
+        };
+
+        let history = &self.history;
+        let chunk_size = self.state.chunk_size.get();
+        let mut builder = B::Builder::with_capacity((), chunk_size);
+        let mut cursor = history.cursor();
+        let mut released = 0;
+
+        cursor.seek_key(history, &key);
+        cursor.seek_val(history, &val);
+        'outer: while cursor.key_valid(history) {
+            while cursor.val_valid(history) {
+                if released == chunk_size {
+                    self.next = Some((cursor.key(history).clone(), cursor.val(history).clone()));
+                    break 'outer;
+                }
+                let weight = cursor.weight(history);
+                // Batches of a snapshot may cancel each other out.
+                if !weight.is_zero() {
+                    builder.push((
+                        cursor.key(history).clone(),
+                        cursor.val(history).clone(),
+                        weight,
+                    ));
+                }
+                released += 1;
+                cursor.step_val(history);
+            }
+            cursor.step_key(history);
+        }
+
+        self.output += released;
+        self.state
+            .remaining
+            .set(history.len().saturating_sub(self.output));
+        if self.next.is_none() {
+            self.state.remaining.set(0);
+            self.state.complete.set(true);
+        }
+
+        builder.done()
+    }
+}
+
+impl<H> Operator for Backfill<H>
+where
+    H: BatchReader + 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("Backfill")
+    }
+
+    fn is_stateful(&self) -> bool {
+        true
+    }
+
+    fn clock_start(&mut self, _scope: Scope) {}
+    fn clock_end(&mut self, _scope: Scope) {}
+
+    fn summary(&self, output: &mut String) {
+        *output = format!(
+            "remaining: {}, chunk size: {}",
+            self.state.remaining.get(),
+            self.state.chunk_size.get()
+        );
+    }
+
+    fn fixedpoint(&self) -> bool {
+        self.empty_input && self.next.is_none()
+    }
+}
+
+impl<H, B> UnaryOperator<B, B> for Backfill<H>
+where
+    H: BatchReader<Key = B::Key, Val = B::Val, Time = (), R = B::R> + 'static,
+    B: Batch<Time = ()> + Clone + 'static,
+    B::Key: Clone,
+    B::Val: Clone,
+{
+    fn eval(&mut self, input: &B) -> B {
+        self.empty_input = input.is_empty();
+
+        let chunk: B = self.next_chunk();
+        if chunk.is_empty() {
+            input.clone()
+        } else if input.is_empty() {
+            chunk
+        } else {
+            input.merge(&chunk)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        circuit::Root,
+        operator::Generator,
+        trace::{ord::OrdZSet, Batch, BatchReader},
+        zset,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn backfill_test() {
+        let history =
+            OrdZSet::<usize, isize>::from_tuples((), (0..10).map(|i| ((i, ()), 1)).collect());
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let output_clone = output.clone();
+
+        let mut handle = None;
+        let root = Root::build(|circuit| {
+            let input = circuit.add_source(Generator::new(|| zset! { 100usize => 1isize }));
+            let (backfilled, backfill) = input.backfill(history, 4);
+            backfilled
+                .integrate()
+                .inspect(move |zs| output_clone.borrow_mut().push(zs.len()));
+            handle = Some(backfill);
+        })
+        .unwrap();
+        let handle = handle.unwrap();
+
+        assert_eq!(handle.remaining(), 10);
+        root.step().unwrap();
+        assert_eq!(handle.remaining(), 6);
+        assert!(!handle.is_complete());
+
+        handle.set_chunk_size(3);
+        root.step().unwrap();
+        assert_eq!(handle.remaining(), 3);
+        assert!(!handle.is_complete());
+        root.step().unwrap();
+        assert!(handle.is_complete());
+        assert_eq!(handle.remaining(), 0);
+        root.step().unwrap();
+
+        // The live input is output at every step, the history in chunks.
+        assert_eq!(*output.borrow(), vec![5, 8, 11, 11]);
+    }
+}
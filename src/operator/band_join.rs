@@ -0,0 +1,354 @@
+//! Band join: stream-stream join on event time with bounded state.
+
+use crate::{
+    algebra::{IndexedZSet, MulByRef, ZSet},
+    circuit::{
+        operator_traits::{BinaryOperator, Operator},
+        Circuit, Stream,
+    },
+    operator::WithTimestamp,
+    trace::{cursor::Cursor, spine_fueled::Spine, Batch, BatchReader, Trace},
+};
+use std::{
+    borrow::Cow,
+    cmp::{max, Ordering},
+    marker::PhantomData,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering as AtomicOrdering},
+        Arc,
+    },
+};
+
+impl<P, I1> Stream<Circuit<P>, I1>
+where
+    P: Clone + 'static,
+{
+    /// Apply [`BandJoin`] operator to `self` and `other`.
+    ///
+    /// Joins updates with equal keys whose event times fall within a band
+    /// around each other: a value with event time `t1` in `self` matches a
+    /// value in `other` with event time `t2` iff
+    /// `t1 - before <= t2 <= t1 + after`.
+    ///
+    /// Unlike [`join_incremental`](`Stream::join_incremental`), which
+    /// retains the complete history of both inputs, this operator discards
+    /// updates that can no longer match any future input.  See [`BandJoin`]
+    /// for details.
+    pub fn band_join<I2, F, Z>(
+        &self,
+        other: &Stream<Circuit<P>, I2>,
+        before: u64,
+        after: u64,
+        lateness: u64,
+        join_func: F,
+    ) -> Stream<Circuit<P>, Z>
+    where
+        I1: IndexedZSet<R = Z::R>,
+        I1::Key: Ord,
+        I1::Val: WithTimestamp<Timestamp = u64> + Ord,
+        I2: IndexedZSet<Key = I1::Key, R = Z::R>,
+        I2::Val: WithTimestamp<Timestamp = u64> + Ord,
+        F: Fn(&I1::Key, &I1::Val, &I2::Val) -> Z::Key + 'static,
+        Z: ZSet,
+        Z::R: MulByRef,
+    {
+        self.circuit().add_binary_operator(
+            BandJoin::new(before, after, lateness, join_func),
+            self,
+            other,
+        )
+    }
+}
+
+/// Incremental stream-stream join restricted to a band of event times.
+///
+/// The operator consumes changes to two indexed Z-sets whose values carry
+/// event times (see [`WithTimestamp`]) and outputs changes to the join of
+/// their integrals, restricted to pairs of values whose event times `t1`
+/// and `t2` satisfy `t1 - before <= t2 <= t1 + after`.
+///
+/// The operator keeps the integral of each input in a trace.  In order to
+/// bound the size of these traces, it maintains a waterline: the largest
+/// event time observed on either input minus the allowed `lateness`.
+/// Updates with event times below the waterline are considered late and
+/// are ignored.  Since all future updates are at or above the waterline, an
+/// update in the left trace whose event time is less than
+/// `waterline - after` (respectively, `waterline - before` in the right
+/// trace) cannot match any future update and is discarded when the trace
+/// gets merged (see [`Trace::retain`]).
+///
+/// The operator is meant to be used in the top-level circuit; it does not
+/// reset its state at the start of a nested clock epoch.
+pub struct BandJoin<F, I1, I2, Z>
+where
+    I1: Batch,
+    I2: Batch,
+{
+    before: u64,
+    after: u64,
+    lateness: u64,
+    join_func: F,
+    // Largest event time observed so far.
+    max_time: Option<u64>,
+    // Updates in `left` and `right` below these thresholds can be discarded.
+    left_threshold: Arc<AtomicU64>,
+    right_threshold: Arc<AtomicU64>,
+    left: Spine<Rc<I1>>,
+    right: Spine<Rc<I2>>,
+    empty_input: bool,
+    _types: PhantomData<Z>,
+}
+
+impl<F, I1, I2, Z> BandJoin<F, I1, I2, Z>
+where
+    I1: IndexedZSet,
+    I1::Key: Ord,
+    I1::Val: WithTimestamp<Timestamp = u64> + Ord,
+    I2: IndexedZSet<Key = I1::Key>,
+    I2::Val: WithTimestamp<Timestamp = u64> + Ord,
+{
+    pub fn new(before: u64, after: u64, lateness: u64, join_func: F) -> Self {
+        let left_threshold = Arc::new(AtomicU64::new(0));
+        let right_threshold = Arc::new(AtomicU64::new(0));
+
+        let mut left = Spine::new(None);
+        let threshold = left_threshold.clone();
+        left.retain(move |_k: &I1::Key, v: &I1::Val| {
+            v.timestamp() >= threshold.load(AtomicOrdering::Relaxed)
+        });
+
+        let mut right = Spine::new(None);
+        let threshold = right_threshold.clone();
+        right.retain(move |_k: &I2::Key, v: &I2::Val| {
+            v.timestamp() >= threshold.load(AtomicOrdering::Relaxed)
+        });
+
+        Self {
+            before,
+            after,
+            lateness,
+            join_func,
+            max_time: None,
+            left_threshold,
+            right_threshold,
+            left,
+            right,
+            empty_input: false,
+            _types: PhantomData,
+        }
+    }
+
+    /// Current waterline.  Updates with event times below the waterline are
+    /// ignored.
+    pub fn waterline(&self) -> u64 {
+        self.max_time
+            .map(|t| t.saturating_sub(self.lateness))
+            .unwrap_or(0)
+    }
+}
+
+impl<F, I1, I2, Z> Operator for BandJoin<F, I1, I2, Z>
+where
+    I1: Batch + 'static,
+    I2: Batch + 'static,
+    F: 'static,
+    Z: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("BandJoin")
+    }
+
+    fn fixedpoint(&self) -> bool {
+        self.empty_input
+    }
+}
+
+// Largest event time in `batch`.
+pub(crate) fn max_timestamp<B>(batch: &B) -> Option<u64>
+where
+    B: BatchReader,
+    B::Val: WithTimestamp<Timestamp = u64>,
+{
+    let mut cursor = batch.cursor();
+    let mut result = None;
+
+    while cursor.key_valid(batch) {
+        while cursor.val_valid(batch) {
+            result = max(result, Some(cursor.val(batch).timestamp()));
+            cursor.step_val(batch);
+        }
+        cursor.step_key(batch);
+    }
+
+    result
+}
+
+impl<F, I1, I2, Z> BandJoin<F, I1, I2, Z>
+where
+    I1: IndexedZSet<R = Z::R>,
+    I1::Key: Ord,
+    I1::Val: WithTimestamp<Timestamp = u64> + Ord,
+    I2: IndexedZSet<Key = I1::Key, R = Z::R>,
+    I2::Val: WithTimestamp<Timestamp = u64> + Ord,
+    F: Fn(&I1::Key, &I1::Val, &I2::Val) -> Z::Key,
+    Z: ZSet,
+    Z::R: MulByRef,
+{
+    // Join `i1` with `i2`, pushing matching pairs to `output`.
+    #[allow(clippy::type_complexity)]
+    fn join_band<L, R>(&self, i1: &L, i2: &R, output: &mut Vec<((Z::Key, ()), Z::R)>)
+    where
+        L: BatchReader<Key = I1::Key, Val = I1::Val, Time = (), R = Z::R>,
+        R: BatchReader<Key = I1::Key, Val = I2::Val, Time = (), R = Z::R>,
+    {
+        let mut cursor1 = i1.cursor();
+        let mut cursor2 = i2.cursor();
+
+        while cursor1.key_valid(i1) && cursor2.key_valid(i2) {
+            match cursor1.key(i1).cmp(cursor2.key(i2)) {
+                Ordering::Less => cursor1.seek_key(i1, cursor2.key(i2)),
+                Ordering::Greater => cursor2.seek_key(i2, cursor1.key(i1)),
+                Ordering::Equal => {
+                    while cursor1.val_valid(i1) {
+                        let w1 = cursor1.weight(i1);
+                        let v1 = cursor1.val(i1);
+                        let t1 = v1.timestamp();
+                        while cursor2.val_valid(i2) {
+                            let v2 = cursor2.val(i2);
+                            let t2 = v2.timestamp();
+
+                            if t2.saturating_add(self.before) >= t1
+                                && t2 <= t1.saturating_add(self.after)
+                            {
+                                let w2 = cursor2.weight(i2);
+                                output.push((
+                                    ((self.join_func)(cursor1.key(i1), v1, v2), ()),
+                                    w1.mul_by_ref(&w2),
+                                ));
+                            }
+                            cursor2.step_val(i2);
+                        }
+
+                        cursor2.rewind_vals(i2);
+                        cursor1.step_val(i1);
+                    }
+
+                    cursor1.step_key(i1);
+                    cursor2.step_key(i2);
+                }
+            }
+        }
+    }
+}
+
+impl<F, I1, I2, Z> BinaryOperator<I1, I2, Z> for BandJoin<F, I1, I2, Z>
+where
+    I1: IndexedZSet<R = Z::R>,
+    I1::Key: Ord,
+    I1::Val: WithTimestamp<Timestamp = u64> + Ord,
+    I2: IndexedZSet<Key = I1::Key, R = Z::R>,
+    I2::Val: WithTimestamp<Timestamp = u64> + Ord,
+    F: Fn(&I1::Key, &I1::Val, &I2::Val) -> Z::Key + 'static,
+    Z: ZSet,
+    Z::R: MulByRef,
+{
+    fn eval(&mut self, i1: &I1, i2: &I2) -> Z {
+        self.empty_input = i1.is_empty() && i2.is_empty();
+
+        // Drop late updates.
+        let waterline = self.waterline();
+        let mut delta1 = i1.clone();
+        delta1.retain(&|_k, v| v.timestamp() >= waterline);
+        let mut delta2 = i2.clone();
+        delta2.retain(&|_k, v| v.timestamp() >= waterline);
+
+        self.max_time = max(
+            self.max_time,
+            max(max_timestamp(&delta1), max_timestamp(&delta2)),
+        );
+
+        // delta1 <> z^-1(right) + (left + delta1) <> delta2
+        let mut output = Vec::new();
+        self.join_band(&delta1, &self.right, &mut output);
+        self.left.insert(Rc::new(delta1));
+        self.join_band(&self.left, &delta2, &mut output);
+        self.right.insert(Rc::new(delta2));
+
+        let waterline = self.waterline();
+        self.left_threshold.store(
+            waterline.saturating_sub(self.after),
+            AtomicOrdering::Relaxed,
+        );
+        self.right_threshold.store(
+            waterline.saturating_sub(self.before),
+            AtomicOrdering::Relaxed,
+        );
+
+        Z::from_tuples((), output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        circuit::{Root, Stream},
+        operator::Generator,
+        trace::{
+            ord::{OrdIndexedZSet, OrdZSet},
+            Batch,
+        },
+        zset,
+    };
+    use std::vec;
+
+    #[test]
+    #[allow(clippy::type_complexity)]
+    fn band_join_test() {
+        let root = Root::build(move |circuit| {
+            // (key, (event time, value))
+            let mut left: vec::IntoIter<Vec<((usize, (u64, &'static str)), isize)>> = vec![
+                vec![((1, (10, "a")), 1), ((2, (10, "b")), 1)],
+                vec![((1, (20, "c")), 1)],
+                vec![((1, (40, "d")), 1), ((1, (12, "late")), 1)],
+                // Retracting an update below the waterline has no effect.
+                vec![((1, (40, "d")), -1), ((1, (20, "c")), -1)],
+            ]
+            .into_iter();
+            let mut right: vec::IntoIter<Vec<((usize, (u64, &'static str)), isize)>> = vec![
+                vec![((1, (12, "x")), 1)],
+                vec![((2, (11, "y")), 1), ((1, (25, "z")), 1)],
+                vec![((1, (41, "w")), 1), ((1, (9, "late")), 1)],
+                vec![],
+            ]
+            .into_iter();
+
+            let mut expected = vec![
+                zset! { (10, "a", 12, "x") => 1 },
+                zset! { (10, "b", 11, "y") => 1, (20, "c", 25, "z") => 1 },
+                zset! { (40, "d", 41, "w") => 1 },
+                zset! { (40, "d", 41, "w") => -1 },
+            ]
+            .into_iter();
+
+            let left: Stream<_, OrdIndexedZSet<usize, (u64, &'static str), isize>> = circuit
+                .add_source(Generator::new(move || {
+                    OrdIndexedZSet::from_tuples((), left.next().unwrap())
+                }));
+            let right: Stream<_, OrdIndexedZSet<usize, (u64, &'static str), isize>> = circuit
+                .add_source(Generator::new(move || {
+                    OrdIndexedZSet::from_tuples((), right.next().unwrap())
+                }));
+
+            left.band_join::<_, _, OrdZSet<_, _>>(&right, 3, 5, 10, |_k, &(t1, v1), &(t2, v2)| {
+                (t1, v1, t2, v2)
+            })
+            .inspect(move |output| assert_eq!(output, &expected.next().unwrap()));
+        })
+        .unwrap();
+
+        for _ in 0..4 {
+            root.step().unwrap();
+        }
+    }
+}
@@ -0,0 +1,421 @@
+//! Integrals with bounded history.
+//!
+//! [`Stream::integrate_trace`] retains the complete history of its input,
+//! which makes operators that only need recent state, e.g., a join of a
+//! stream of events against the last hour of another stream, pay for the
+//! entire history.  The variants in this module bound the contents of the
+//! trace by the number of clock cycles or by event time and discard older
+//! updates automatically.
+//!
+//! Like [`Stream::integrate_trace`], the returned traces can be delayed with
+//! [`Stream::delay_trace`].  The operators are meant to be used in the
+//! top-level circuit.
+
+use crate::{
+    algebra::IndexedZSet,
+    circuit::{
+        operator_traits::{BinaryOperator, Operator},
+        Circuit, ExportId, ExportStream, OwnershipPreference, Stream,
+    },
+    operator::{
+        band_join::max_timestamp,
+        trace::{DelayedTraceId, Z1Trace},
+        WithTimestamp,
+    },
+    trace::{spine_fueled::Spine, Trace},
+};
+use deepsize::DeepSizeOf;
+use std::{
+    borrow::Cow,
+    cmp::max,
+    collections::VecDeque,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering as AtomicOrdering},
+        Arc,
+    },
+};
+
+impl<P, B> Stream<Circuit<P>, B>
+where
+    P: Clone + 'static,
+    B: IndexedZSet + DeepSizeOf + 'static,
+    B::Key: Ord,
+    B::Val: Ord,
+{
+    /// Integrate the last `steps` values of `self` into a trace.
+    ///
+    /// At each clock cycle, the trace contains the sum of the inputs received
+    /// during the last `steps` clock cycles, including the current one.  The
+    /// operator that maintains the trace keeps the inputs of the last `steps`
+    /// clock cycles and retracts the oldest one by inserting its negation
+    /// into the trace, whose merges then cancel out the expired updates.
+    /// Like the output of [`Stream::integrate_trace`], the trace is a single
+    /// spine that is merged incrementally between clock cycles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `steps` is 0.
+    pub fn integrate_trace_bounded_steps(&self, steps: usize) -> Stream<Circuit<P>, Spine<Rc<B>>> {
+        assert!(
+            steps > 0,
+            "integrate_trace_bounded_steps: steps must be positive"
+        );
+        self.bounded_trace(
+            "integrate_trace_bounded_steps",
+            BoundedStepsTraceAppend::new(steps),
+        )
+    }
+
+    /// Integrate updates of `self` whose event time is within `horizon` of
+    /// the waterline into a trace.
+    ///
+    /// The waterline is the largest event time observed in the input so far.
+    /// Updates whose event time is less than `waterline - horizon` are
+    /// expired: input updates that arrive below this threshold are ignored
+    /// as late, and expired updates in the trace are discarded lazily, as
+    /// the trace merges its batches (see [`Trace::retain`]).  In addition,
+    /// the trace is compacted whenever the waterline has advanced by
+    /// `horizon` since the last compaction, so it never retains updates
+    /// more than `2 * horizon` below the waterline.  Consumers that require
+    /// an exact window must filter out expired updates themselves.
+    pub fn integrate_trace_with_horizon(&self, horizon: u64) -> Stream<Circuit<P>, Spine<Rc<B>>>
+    where
+        B::Val: WithTimestamp<Timestamp = u64>,
+    {
+        self.bounded_trace(
+            "integrate_trace_with_horizon",
+            HorizonTraceAppend::new(horizon),
+        )
+    }
+
+    // Build a trace maintained by the `append` operator.  Same as
+    // `integrate_trace`, but with a custom append operator.
+    fn bounded_trace<Op>(&self, name: &str, append: Op) -> Stream<Circuit<P>, Spine<Rc<B>>>
+    where
+        Op: BinaryOperator<Spine<Rc<B>>, B, Spine<Rc<B>>>,
+    {
+        self.circuit().region(name, || {
+            let (ExportStream { local, export }, z1feedback) =
+                self.circuit().add_feedback_with_export(
+                    Z1Trace::new(true).with_effort(self.circuit().trace_effort()),
+                );
+            let trace = self.circuit().add_binary_operator_with_preference(
+                append,
+                &local,
+                self,
+                OwnershipPreference::STRONGLY_PREFER_OWNED,
+                OwnershipPreference::PREFER_OWNED,
+            );
+            z1feedback.connect_with_preference(&trace, OwnershipPreference::STRONGLY_PREFER_OWNED);
+            self.circuit()
+                .cache_insert(DelayedTraceId::new(trace.local_node_id()), local);
+            self.circuit()
+                .cache_insert(ExportId::new(trace.local_node_id()), export);
+            trace
+        })
+    }
+}
+
+/// Operator that appends its input to a trace and retracts inputs received
+/// more than `steps` clock cycles ago.
+///
+/// See [`Stream::integrate_trace_bounded_steps`].
+pub struct BoundedStepsTraceAppend<B> {
+    steps: usize,
+    // Inputs received during the last `steps` clock cycles, shared with the
+    // trace until it merges them.
+    recent: VecDeque<Rc<B>>,
+}
+
+impl<B> BoundedStepsTraceAppend<B> {
+    pub fn new(steps: usize) -> Self {
+        Self {
+            steps,
+            recent: VecDeque::with_capacity(steps + 1),
+        }
+    }
+}
+
+impl<B> BoundedStepsTraceAppend<B>
+where
+    B: IndexedZSet + 'static,
+    B::Key: Ord,
+    B::Val: Ord,
+{
+    fn append(&mut self, mut trace: Spine<Rc<B>>, batch: B) -> Spine<Rc<B>> {
+        let batch = Rc::new(batch);
+        self.recent.push_back(batch.clone());
+        trace.insert(batch);
+
+        if self.recent.len() > self.steps {
+            let expired = self.recent.pop_front().unwrap();
+            trace.insert(Rc::new(expired.neg_by_ref()));
+        }
+        trace
+    }
+}
+
+impl<B> Operator for BoundedStepsTraceAppend<B>
+where
+    B: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("BoundedStepsTraceAppend")
+    }
+
+    fn is_stateful(&self) -> bool {
+        true
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<B> BinaryOperator<Spine<Rc<B>>, B, Spine<Rc<B>>> for BoundedStepsTraceAppend<B>
+where
+    B: IndexedZSet + 'static,
+    B::Key: Ord,
+    B::Val: Ord,
+{
+    fn eval(&mut self, _trace: &Spine<Rc<B>>, _batch: &B) -> Spine<Rc<B>> {
+        // Refuse to accept trace by reference.  This should not happen in a correctly
+        // constructed circuit.
+        unimplemented!()
+    }
+
+    fn eval_owned_and_ref(&mut self, trace: Spine<Rc<B>>, batch: &B) -> Spine<Rc<B>> {
+        self.append(trace, batch.clone())
+    }
+
+    fn eval_ref_and_owned(&mut self, _trace: &Spine<Rc<B>>, _batch: B) -> Spine<Rc<B>> {
+        // Refuse to accept trace by reference.  This should not happen in a correctly
+        // constructed circuit.
+        unimplemented!()
+    }
+
+    fn eval_owned(&mut self, trace: Spine<Rc<B>>, batch: B) -> Spine<Rc<B>> {
+        self.append(trace, batch)
+    }
+
+    fn input_preference(&self) -> (OwnershipPreference, OwnershipPreference) {
+        (
+            OwnershipPreference::PREFER_OWNED,
+            OwnershipPreference::PREFER_OWNED,
+        )
+    }
+}
+
+/// Operator that appends its input to a trace, discarding updates whose
+/// event times are more than `horizon` below the waterline.
+///
+/// See [`Stream::integrate_trace_with_horizon`].
+pub struct HorizonTraceAppend {
+    horizon: u64,
+    // Largest event time observed so far.
+    max_time: Option<u64>,
+    // Updates below this threshold are discarded by the trace.
+    threshold: Arc<AtomicU64>,
+    // Threshold at the time of the last compaction.
+    compacted: u64,
+}
+
+impl HorizonTraceAppend {
+    pub fn new(horizon: u64) -> Self {
+        Self {
+            horizon,
+            max_time: None,
+            threshold: Arc::new(AtomicU64::new(0)),
+            compacted: 0,
+        }
+    }
+
+    fn threshold(&self) -> u64 {
+        self.threshold.load(AtomicOrdering::Relaxed)
+    }
+
+    fn append<B>(&mut self, mut trace: Spine<Rc<B>>, mut batch: B) -> Spine<Rc<B>>
+    where
+        B: IndexedZSet + 'static,
+        B::Key: Ord,
+        B::Val: Ord + WithTimestamp<Timestamp = u64>,
+    {
+        // Drop late updates.
+        let threshold = self.threshold();
+        batch.retain(&|_k, v| v.timestamp() >= threshold);
+
+        self.max_time = max(self.max_time, max_timestamp(&batch));
+        let new_threshold = self
+            .max_time
+            .map(|t| t.saturating_sub(self.horizon))
+            .unwrap_or(0);
+
+        // The trace is created by `Z1Trace` at the start of the clock epoch,
+        // so (re-)install the predicate before using it.
+        let predicate = self.threshold.clone();
+        trace.retain(move |_k: &B::Key, v: &B::Val| {
+            v.timestamp() >= predicate.load(AtomicOrdering::Relaxed)
+        });
+        trace.insert(Rc::new(batch));
+
+        if new_threshold > threshold {
+            self.threshold.store(new_threshold, AtomicOrdering::Relaxed);
+
+            // Compacting the entire trace is expensive, so we only do it
+            // once per horizon and rely on merges to discard expired updates
+            // otherwise.
+            if new_threshold - self.compacted >= self.horizon {
+                self.compacted = new_threshold;
+                trace.compact();
+            }
+        }
+        trace
+    }
+}
+
+impl Operator for HorizonTraceAppend {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("HorizonTraceAppend")
+    }
+
+    fn is_stateful(&self) -> bool {
+        true
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<B> BinaryOperator<Spine<Rc<B>>, B, Spine<Rc<B>>> for HorizonTraceAppend
+where
+    B: IndexedZSet + 'static,
+    B::Key: Ord,
+    B::Val: Ord + WithTimestamp<Timestamp = u64>,
+{
+    fn eval(&mut self, _trace: &Spine<Rc<B>>, _batch: &B) -> Spine<Rc<B>> {
+        // Refuse to accept trace by reference.  This should not happen in a correctly
+        // constructed circuit.
+        unimplemented!()
+    }
+
+    fn eval_owned_and_ref(&mut self, trace: Spine<Rc<B>>, batch: &B) -> Spine<Rc<B>> {
+        self.append(trace, batch.clone())
+    }
+
+    fn eval_ref_and_owned(&mut self, _trace: &Spine<Rc<B>>, _batch: B) -> Spine<Rc<B>> {
+        // Refuse to accept trace by reference.  This should not happen in a correctly
+        // constructed circuit.
+        unimplemented!()
+    }
+
+    fn eval_owned(&mut self, trace: Spine<Rc<B>>, batch: B) -> Spine<Rc<B>> {
+        self.append(trace, batch)
+    }
+
+    fn input_preference(&self) -> (OwnershipPreference, OwnershipPreference) {
+        (
+            OwnershipPreference::PREFER_OWNED,
+            OwnershipPreference::PREFER_OWNED,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        circuit::Root,
+        indexed_zset,
+        operator::Generator,
+        trace::{cursor::Cursor, BatchReader},
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    // Collect the `(key, event time, weight)` tuples with non-zero weights in
+    // `trace`.
+    fn contents<T>(trace: &T) -> Vec<(usize, u64, isize)>
+    where
+        T: BatchReader<Key = usize, Val = (u64, ()), Time = (), R = isize>,
+    {
+        let mut result = Vec::new();
+        let mut cursor = trace.cursor();
+        while cursor.key_valid(trace) {
+            while cursor.val_valid(trace) {
+                let w = cursor.weight(trace);
+                if w != 0 {
+                    result.push((*cursor.key(trace), cursor.val(trace).0, w));
+                }
+                cursor.step_val(trace);
+            }
+            cursor.step_key(trace);
+        }
+        result
+    }
+
+    #[test]
+    fn bounded_trace_test() {
+        let steps = Rc::new(RefCell::new(Vec::new()));
+        let steps_clone = steps.clone();
+        let horizon = Rc::new(RefCell::new(Vec::new()));
+        let horizon_clone = horizon.clone();
+
+        let root = Root::build(move |circuit| {
+            // Value `10 * n`, which is also its event time, at step `n`, plus
+            // a late update at step 3.
+            let mut n = 0u64;
+            let input = circuit.add_source(Generator::new(move || {
+                n += 1;
+                if n == 3 {
+                    indexed_zset! { 1usize => { (30u64, ()) => 1isize, (4, ()) => 1 } }
+                } else {
+                    indexed_zset! { 1usize => { (10 * n, ()) => 1 } }
+                }
+            }));
+
+            let bounded = input.integrate_trace_bounded_steps(2);
+            bounded.inspect(move |trace| steps_clone.borrow_mut().push(contents(trace)));
+            // The bounded trace can be delayed like the output of `integrate_trace`.
+            bounded.delay_trace();
+
+            input
+                .integrate_trace_with_horizon(15)
+                .inspect(move |trace| horizon_clone.borrow_mut().push(contents(trace)));
+        })
+        .unwrap();
+
+        for _ in 0..4 {
+            root.step().unwrap();
+        }
+
+        assert_eq!(
+            *steps.borrow(),
+            vec![
+                vec![(1, 10, 1)],
+                vec![(1, 10, 1), (1, 20, 1)],
+                vec![(1, 4, 1), (1, 20, 1), (1, 30, 1)],
+                vec![(1, 4, 1), (1, 30, 1), (1, 40, 1)],
+            ]
+        );
+
+        // Expired updates are discarded lazily, so the trace must contain all
+        // updates above the threshold and may contain updates up to
+        // `horizon` below it.  The late update is never added to the trace.
+        for (step, contents) in horizon.borrow().iter().enumerate() {
+            let threshold = (10 * (step as u64 + 1)).saturating_sub(15);
+            let expected: Vec<_> = (1..=step as u64 + 1)
+                .map(|n| (1, 10 * n, 1))
+                .filter(|&(_, t, _)| t >= threshold)
+                .collect();
+            let retained: Vec<_> = contents
+                .iter()
+                .cloned()
+                .filter(|&(_, t, _)| t >= threshold)
+                .collect();
+            assert_eq!(retained, expected);
+            assert!(contents
+                .iter()
+                .all(|&(_, t, _)| t + 15 >= threshold && t != 4));
+        }
+    }
+}
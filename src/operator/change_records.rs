@@ -0,0 +1,190 @@
+//! Conversion of Z-set deltas into change data capture (CDC) records.
+//!
+//! Downstream systems typically consume changes as a sequence of inserts,
+//! deletes, and updates, rather than as weighted tuples.  This module
+//! converts the output of a circuit into this format.  Within each key,
+//! deletions (negative weights) are paired with insertions (positive
+//! weights) to form `Update` records, so that a retraction followed by a
+//! replacement of the value of a key, e.g., a late correction of an
+//! aggregate, is reported as a single update.
+
+use crate::{
+    algebra::{AddByRef, HasZero, IndexedZSet, NegByRef, ZRingValue},
+    circuit::{Circuit, Stream},
+    trace::{BatchReader, Cursor},
+};
+
+/// A change to a key-value collection.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ChangeRecord<K, V> {
+    /// A value was inserted under the key.
+    Insert(K, V),
+    /// A value was deleted from under the key.
+    Delete(K, V),
+    /// Value `before` was replaced with value `after`.
+    Update { key: K, before: V, after: V },
+}
+
+impl<P, Z> Stream<Circuit<P>, Z>
+where
+    P: Clone + 'static,
+    Z: IndexedZSet,
+    Z::Key: Clone,
+    Z::Val: Clone,
+    Z::R: ZRingValue,
+{
+    /// Convert each batch in `self` into a vector of weighted change records.
+    ///
+    /// See [`change_records`] for details.
+    #[allow(clippy::type_complexity)]
+    pub fn change_records(&self) -> Stream<Circuit<P>, Vec<(ChangeRecord<Z::Key, Z::Val>, Z::R)>> {
+        self.apply(change_records)
+    }
+}
+
+/// Convert an indexed Z-set into a vector of weighted change records.
+///
+/// A tuple with weight `w` is treated as `|w|` insertions (if `w` is
+/// positive) or deletions (if `w` is negative) of the same value.  For each
+/// key, deletions and insertions are paired in value order to form
+/// [`ChangeRecord::Update`] records; unpaired deletions and insertions are
+/// output as [`ChangeRecord::Delete`] and [`ChangeRecord::Insert`] records
+/// respectively.  Identical records are not repeated: each record is
+/// output once along with its (positive) multiplicity, so the size of the
+/// output is linear in the size of `batch` regardless of its weights.
+/// Records are ordered by key; within a key, updates come first, followed
+/// by deletions or insertions.
+#[allow(clippy::type_complexity)]
+pub fn change_records<Z>(batch: &Z) -> Vec<(ChangeRecord<Z::Key, Z::Val>, Z::R)>
+where
+    Z: BatchReader<Time = ()>,
+    Z::Key: Clone,
+    Z::Val: Clone,
+    Z::R: ZRingValue,
+{
+    let mut result = Vec::new();
+    // Deleted and inserted values of the current key with their (positive)
+    // multiplicities.
+    let mut deletes = Vec::new();
+    let mut inserts = Vec::new();
+
+    let mut cursor = batch.cursor();
+    while cursor.key_valid(batch) {
+        while cursor.val_valid(batch) {
+            let w = cursor.weight(batch);
+            let val = cursor.val(batch);
+
+            if w.ge0() {
+                inserts.push((val, w));
+            } else {
+                deletes.push((val, -w));
+            }
+            cursor.step_val(batch);
+        }
+
+        let key = cursor.key(batch);
+
+        // Pair deletions with insertions, splitting runs of the same value
+        // where their multiplicities differ.
+        let mut deletes_iter = deletes.drain(..).peekable();
+        let mut inserts_iter = inserts.drain(..).peekable();
+        while let (Some((before, deleted)), Some((after, inserted))) =
+            (deletes_iter.peek_mut(), inserts_iter.peek_mut())
+        {
+            let diff = deleted.add_by_ref(&inserted.neg_by_ref());
+            let updated = if diff.ge0() {
+                inserted.clone()
+            } else {
+                deleted.clone()
+            };
+            result.push((
+                ChangeRecord::Update {
+                    key: key.clone(),
+                    before: (*before).clone(),
+                    after: (*after).clone(),
+                },
+                updated.clone(),
+            ));
+            *deleted += updated.neg_by_ref();
+            *inserted += updated.neg_by_ref();
+
+            if deleted.is_zero() {
+                deletes_iter.next();
+            }
+            if inserted.is_zero() {
+                inserts_iter.next();
+            }
+        }
+        result.extend(
+            deletes_iter.map(|(val, w)| (ChangeRecord::Delete(key.clone(), val.clone()), w)),
+        );
+        result.extend(
+            inserts_iter.map(|(val, w)| (ChangeRecord::Insert(key.clone(), val.clone()), w)),
+        );
+
+        cursor.step_key(batch);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{change_records, ChangeRecord};
+    use crate::{indexed_zset, trace::ord::OrdIndexedZSet};
+
+    #[test]
+    fn cdc() {
+        let batch: OrdIndexedZSet<u64, &'static str, isize> = indexed_zset! {
+            1 => { "a" => 1 },
+            2 => { "b" => -1 },
+            3 => { "c" => -1, "d" => 1 },
+            4 => { "e" => 2, "f" => -1 },
+        };
+
+        assert_eq!(
+            change_records(&batch),
+            vec![
+                (ChangeRecord::Insert(1, "a"), 1),
+                (ChangeRecord::Delete(2, "b"), 1),
+                (
+                    ChangeRecord::Update {
+                        key: 3,
+                        before: "c",
+                        after: "d"
+                    },
+                    1
+                ),
+                (
+                    ChangeRecord::Update {
+                        key: 4,
+                        before: "f",
+                        after: "e"
+                    },
+                    1
+                ),
+                (ChangeRecord::Insert(4, "e"), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn cdc_large_weights() {
+        let batch: OrdIndexedZSet<u64, &'static str, isize> = indexed_zset! {
+            1 => { "a" => -3, "b" => -1_000_000, "c" => 2_000_000 },
+            2 => { "d" => -5, "e" => 2 },
+        };
+
+        let update = |key, before, after| ChangeRecord::Update { key, before, after };
+        assert_eq!(
+            change_records(&batch),
+            vec![
+                (update(1, "a", "c"), 3),
+                (update(1, "b", "c"), 1_000_000),
+                (ChangeRecord::Insert(1, "c"), 999_997),
+                (update(2, "d", "e"), 2),
+                (ChangeRecord::Delete(2, "d"), 3),
+            ]
+        );
+    }
+}
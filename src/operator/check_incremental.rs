@@ -0,0 +1,189 @@
+//! Runtime checks of incremental operator implementations.
+//!
+//! An incremental operator computes the changes to the output of a query
+//! from the changes to its inputs.  Its integrated output must therefore be
+//! equal to the output of the non-incremental query evaluated over the
+//! integrated inputs at every clock cycle.  [`Stream::check_incremental`] and
+//! [`Stream::check_incremental2`] evaluate both versions side by side and
+//! panic with the difference between them on the first mismatch, which
+//! catches bugs in new incremental operators at the step where the outputs
+//! diverge.
+//!
+//! The naive version does a full recomputation at every step, so checks are
+//! only performed in builds with the `with-incremental-checks` feature
+//! enabled.  Otherwise, only the incremental version is evaluated.
+
+use crate::{
+    algebra::GroupValue,
+    circuit::{Circuit, Stream},
+    NumEntries,
+};
+use deepsize::DeepSizeOf;
+use std::{cell::Cell, fmt::Debug};
+
+/// `true` if incremental operators are checked against their naive versions.
+pub const INCREMENTAL_CHECKS: bool = cfg!(feature = "with-incremental-checks");
+
+impl<P, I1> Stream<Circuit<P>, I1>
+where
+    P: Clone + 'static,
+    I1: GroupValue + DeepSizeOf + NumEntries,
+{
+    /// Evaluate `incremental` over `self` and check it against `naive`.
+    ///
+    /// `incremental` computes the changes to the output of a query from the
+    /// changes in `self`, e.g., `|s| s.distinct_incremental()`, while `naive`
+    /// computes the complete output of the same query from the integral of
+    /// `self`, e.g., `|s| s.distinct()`.  When checks are enabled (see
+    /// [`INCREMENTAL_CHECKS`]), the integral of the output of `incremental`
+    /// is compared to the output of `naive` at every clock cycle, panicking
+    /// with both outputs and their difference on mismatch.
+    ///
+    /// Returns the output of `incremental`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use dbsp::{circuit::Root, operator::Generator, zset};
+    /// let root = Root::build(|circuit| {
+    ///     let input = circuit.add_source(Generator::new(|| zset! { 1 => 1isize, 2 => -1 }));
+    ///     input.check_incremental(
+    ///         "distinct",
+    ///         |s| s.distinct_incremental(),
+    ///         |s| s.distinct(),
+    ///     );
+    /// })
+    /// .unwrap();
+    ///
+    /// root.step().unwrap();
+    /// ```
+    pub fn check_incremental<O, FI, FN>(
+        &self,
+        name: &str,
+        incremental: FI,
+        naive: FN,
+    ) -> Stream<Circuit<P>, O>
+    where
+        O: GroupValue + DeepSizeOf + NumEntries + Debug,
+        FI: FnOnce(&Self) -> Stream<Circuit<P>, O>,
+        FN: FnOnce(&Self) -> Stream<Circuit<P>, O>,
+    {
+        let output = incremental(self);
+        if INCREMENTAL_CHECKS {
+            output.assert_integral_eq(name, &naive(&self.integrate()));
+        }
+        output
+    }
+
+    /// Like [`Self::check_incremental`], but for queries over two streams.
+    ///
+    /// `naive` receives the integrals of `self` and `other`.
+    pub fn check_incremental2<I2, O, FI, FN>(
+        &self,
+        other: &Stream<Circuit<P>, I2>,
+        name: &str,
+        incremental: FI,
+        naive: FN,
+    ) -> Stream<Circuit<P>, O>
+    where
+        I2: GroupValue + DeepSizeOf + NumEntries,
+        O: GroupValue + DeepSizeOf + NumEntries + Debug,
+        FI: FnOnce(&Self, &Stream<Circuit<P>, I2>) -> Stream<Circuit<P>, O>,
+        FN: FnOnce(&Self, &Stream<Circuit<P>, I2>) -> Stream<Circuit<P>, O>,
+    {
+        let output = incremental(self, other);
+        if INCREMENTAL_CHECKS {
+            output.assert_integral_eq(name, &naive(&self.integrate(), &other.integrate()));
+        }
+        output
+    }
+}
+
+impl<P, O> Stream<Circuit<P>, O>
+where
+    P: Clone + 'static,
+    O: GroupValue + DeepSizeOf + NumEntries + Debug,
+{
+    // Panic if the integral of `self` differs from `expected`.
+    fn assert_integral_eq(&self, name: &str, expected: &Self) {
+        let name = name.to_string();
+        let step = Cell::new(0usize);
+        self.integrate().apply2(expected, move |incremental, naive| {
+            if incremental != naive {
+                panic!(
+                    "incremental operator '{}' diverged from its naive version at step {}:\n  incremental: {:?}\n  naive: {:?}\n  difference (incremental - naive): {:?}",
+                    name,
+                    step.get(),
+                    incremental,
+                    naive,
+                    incremental.add_by_ref(&naive.neg_by_ref()),
+                );
+            }
+            step.set(step.get() + 1);
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        circuit::Root,
+        operator::Generator,
+        trace::ord::{OrdIndexedZSet, OrdZSet},
+        zset,
+    };
+
+    #[test]
+    fn check_incremental_test() {
+        let root = Root::build(move |circuit| {
+            let mut step = 0;
+            let input = circuit.add_source(Generator::new(move || {
+                step += 1;
+                match step {
+                    1 => zset! { (1usize, 10usize) => 1isize, (2, 20) => 2 },
+                    2 => zset! { (1, 10) => -1, (1, 11) => 1 },
+                    _ => zset! { (2, 20) => -2 },
+                }
+            }));
+            input.check_incremental("distinct", |s| s.distinct_incremental(), |s| s.distinct());
+            input.check_incremental2(
+                &input,
+                "join",
+                |l, r| {
+                    l.index::<OrdIndexedZSet<_, _, _>>()
+                        .join_incremental::<_, _, OrdZSet<_, _>>(
+                            &r.index::<OrdIndexedZSet<_, _, _>>(),
+                            |&k: &usize, &v1: &usize, &v2: &usize| (k, v1, v2),
+                        )
+                },
+                |l, r| {
+                    l.index::<OrdIndexedZSet<_, _, _>>()
+                        .join::<_, _, OrdZSet<_, _>>(
+                            &r.index::<OrdIndexedZSet<_, _, _>>(),
+                            |&k: &usize, &v1: &usize, &v2: &usize| (k, v1, v2),
+                        )
+                },
+            );
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "with-incremental-checks")]
+    #[should_panic(expected = "incremental operator 'broken' diverged")]
+    fn check_incremental_mismatch() {
+        let root = Root::build(move |circuit| {
+            let input = circuit.add_source(Generator::new(|| zset! { 1usize => 1isize }));
+            // `distinct` is not the incremental version of `distinct`.
+            input.check_incremental("broken", |s| s.distinct(), |s| s.distinct());
+        })
+        .unwrap();
+
+        root.step().unwrap();
+        root.step().unwrap();
+    }
+}
@@ -0,0 +1,227 @@
+//! Binary encoding of batches shared by operators that write batches to and
+//! read them from byte streams.
+//!
+//! A batch is encoded as a frame consisting of a `u64` little-endian length
+//! followed by the `bincode` encoding of its `(key, value, weight)` tuples in
+//! cursor order.
+
+use crate::trace::{Batch, BatchReader, Cursor};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    io::{Error, ErrorKind, Read, Result as IoResult, Write},
+    mem::size_of,
+};
+
+/// Frame header: the length of the serialized batch in bytes.
+type FrameLen = u64;
+
+/// Default limit on the length of a frame accepted by [`read_batch`].
+///
+/// Protects readers from allocating a huge buffer for a corrupted or
+/// malicious frame header.
+pub(crate) const DEFAULT_MAX_FRAME_LEN: usize = 1 << 30;
+
+/// Write `batch` to `writer` as a single frame.
+pub(crate) fn write_batch<B, W>(batch: &B, writer: &mut W) -> IoResult<()>
+where
+    B: BatchReader<Time = ()>,
+    B::Key: Serialize,
+    B::Val: Serialize,
+    B::R: Serialize,
+    W: Write,
+{
+    let mut tuples = Vec::with_capacity(batch.len());
+    let mut cursor = batch.cursor();
+
+    while cursor.key_valid(batch) {
+        while cursor.val_valid(batch) {
+            let w = cursor.weight(batch);
+            tuples.push((cursor.key(batch), cursor.val(batch), w));
+            cursor.step_val(batch);
+        }
+        cursor.step_key(batch);
+    }
+
+    let payload = bincode::serialize(&tuples).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    writer.write_all(&(payload.len() as FrameLen).to_le_bytes())?;
+    writer.write_all(&payload)
+}
+
+/// Read the next frame from `reader` and decode it into a batch.
+///
+/// Returns `Ok(None)` when `reader` is exhausted at a frame boundary.  Returns
+/// an error of kind [`ErrorKind::InvalidData`] if the stream ends in the middle
+/// of a frame header or if the frame is longer than `max_frame_len` bytes.
+/// `buffer` is scratch space that the caller can reuse across invocations.
+///
+/// The decoded tuples are sorted and consolidated before building the batch,
+/// so a peer that sends tuples out of order or with zero weights cannot break
+/// the invariants of the batch.
+pub(crate) fn read_batch<B, R>(
+    reader: &mut R,
+    buffer: &mut Vec<u8>,
+    max_frame_len: usize,
+) -> IoResult<Option<B>>
+where
+    B: Batch<Time = ()>,
+    B::Key: DeserializeOwned,
+    B::Val: DeserializeOwned,
+    B::R: DeserializeOwned,
+    R: Read,
+{
+    let mut header = [0u8; size_of::<FrameLen>()];
+
+    // End of stream before the first byte of the header is a clean shutdown;
+    // anywhere else in the header it means that the frame was truncated.
+    loop {
+        match reader.read(&mut header[..1]) {
+            Ok(0) => return Ok(None),
+            Ok(_) => break,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    reader.read_exact(&mut header[1..]).map_err(|e| {
+        if e.kind() == ErrorKind::UnexpectedEof {
+            Error::new(ErrorKind::InvalidData, "truncated frame header")
+        } else {
+            e
+        }
+    })?;
+
+    let len = FrameLen::from_le_bytes(header);
+    match usize::try_from(len) {
+        Ok(len) if len <= max_frame_len => buffer.resize(len, 0),
+        _ => {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "frame of {} bytes exceeds the maximum frame length of {} bytes",
+                    len, max_frame_len
+                ),
+            ))
+        }
+    }
+    reader.read_exact(buffer)?;
+
+    let tuples: Vec<(B::Key, B::Val, B::R)> =
+        bincode::deserialize(buffer).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    // Do not trust the peer to send tuples in cursor order: a batch built from
+    // unsorted or unconsolidated tuples would silently corrupt the results of
+    // downstream operators.
+    Ok(Some(B::from_tuples(
+        (),
+        tuples
+            .into_iter()
+            .map(|(key, val, weight)| ((key, val), weight))
+            .collect(),
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_batch, write_batch, DEFAULT_MAX_FRAME_LEN};
+    use crate::{
+        indexed_zset,
+        trace::ord::{OrdIndexedZSet, OrdZSet},
+        zset,
+    };
+    use std::io::ErrorKind;
+
+    #[test]
+    fn round_trip() {
+        let batch1: OrdIndexedZSet<u32, String, isize> = indexed_zset! {
+            1 => { "a".to_string() => 1, "b".to_string() => -2 },
+            5 => { "c".to_string() => 3 },
+        };
+        let batch2: OrdIndexedZSet<u32, String, isize> = indexed_zset! {};
+
+        let mut bytes = Vec::new();
+        write_batch(&batch1, &mut bytes).unwrap();
+        write_batch(&batch2, &mut bytes).unwrap();
+
+        let mut reader = &bytes[..];
+        let mut buffer = Vec::new();
+        assert_eq!(
+            read_batch::<OrdIndexedZSet<u32, String, isize>, _>(
+                &mut reader,
+                &mut buffer,
+                DEFAULT_MAX_FRAME_LEN
+            )
+            .unwrap(),
+            Some(batch1)
+        );
+        assert_eq!(
+            read_batch::<OrdIndexedZSet<u32, String, isize>, _>(
+                &mut reader,
+                &mut buffer,
+                DEFAULT_MAX_FRAME_LEN
+            )
+            .unwrap(),
+            Some(batch2)
+        );
+        assert_eq!(
+            read_batch::<OrdZSet<u32, isize>, _>(&mut reader, &mut buffer, DEFAULT_MAX_FRAME_LEN)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn frame_too_long() {
+        let batch: OrdZSet<u32, isize> = zset! { 1 => 1, 2 => -1 };
+
+        let mut bytes = Vec::new();
+        write_batch(&batch, &mut bytes).unwrap();
+
+        let mut buffer = Vec::new();
+        let err =
+            read_batch::<OrdZSet<u32, isize>, _>(&mut &bytes[..], &mut buffer, 4).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn truncated_header() {
+        let batch: OrdZSet<u32, isize> = zset! { 1 => 1 };
+
+        let mut bytes = Vec::new();
+        write_batch(&batch, &mut bytes).unwrap();
+
+        let mut buffer = Vec::new();
+        let err = read_batch::<OrdZSet<u32, isize>, _>(
+            &mut &bytes[..3],
+            &mut buffer,
+            DEFAULT_MAX_FRAME_LEN,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn unsorted_frame() {
+        // A frame with unsorted keys, a duplicate key, and a zero weight, as
+        // sent by a misbehaving peer.
+        let payload = bincode::serialize(&vec![
+            (3u32, (), 1isize),
+            (1, (), 1),
+            (3, (), 1),
+            (2, (), 0),
+        ])
+        .unwrap();
+        let mut bytes = (payload.len() as u64).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&payload);
+
+        let mut buffer = Vec::new();
+        assert_eq!(
+            read_batch::<OrdZSet<u32, isize>, _>(
+                &mut &bytes[..],
+                &mut buffer,
+                DEFAULT_MAX_FRAME_LEN
+            )
+            .unwrap(),
+            Some(zset! { 1 => 1, 3 => 2 })
+        );
+    }
+}
@@ -1,2 +1,7 @@
 mod exchange;
 pub use exchange::*;
+
+mod pipe;
+pub use pipe::{PipeError, PipeReceiver, PipeSender, PIPE_CAPACITY};
+
+mod shard;
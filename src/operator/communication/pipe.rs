@@ -0,0 +1,387 @@
+//! Pipes connect the top-level circuits of different [`Root`]s running in
+//! the same [`Runtime`].
+//!
+//! A stream exported with [`Stream::export_to_pipe`] under a name becomes
+//! available as a source stream in another circuit via
+//! [`Circuit::import_from_pipe`] with the same name, so pipelines can be
+//! composed from independently built circuits.
+//!
+//! [`Root`]: crate::circuit::Root
+
+use crate::circuit::{
+    operator_traits::{Data, Operator, SinkOperator, SourceOperator},
+    Circuit, LocalStoreMarker, Runtime, Scope, Stream,
+};
+use once_cell::sync::OnceCell;
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::Duration,
+};
+use typedmap::TypedMapKey;
+
+/// The maximal number of values buffered in a pipe.  The exporting circuit
+/// blocks when it runs this many clock cycles ahead of the importing
+/// circuit.
+pub const PIPE_CAPACITY: usize = 16;
+
+/// Error returned by [`Circuit::import_from_pipe`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PipeError {
+    /// The pipe has already been imported by another circuit.
+    AlreadyImported { name: String },
+}
+
+impl Display for PipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyImported { name } => {
+                write!(f, "pipe '{}' has already been imported", name)
+            }
+        }
+    }
+}
+
+// Key of a pipe in the runtime's local store.
+struct PipeId<T> {
+    name: String,
+    // `fn() -> T` keeps the key `Send + Sync` regardless of `T`.
+    _marker: PhantomData<fn() -> T>,
+}
+
+// Implement `Hash`, `Eq` manually to avoid `T: Hash` type bound.
+impl<T> Hash for PipeId<T> {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        self.name.hash(state);
+    }
+}
+
+impl<T> PartialEq for PipeId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl<T> Eq for PipeId<T> {}
+
+impl<T> TypedMapKey<LocalStoreMarker> for PipeId<T>
+where
+    T: 'static,
+{
+    type Value = Arc<Pipe<T>>;
+}
+
+// Bounded queue of values produced by the exporting circuit and not yet
+// consumed by the importing circuit.
+struct Pipe<T> {
+    queue: Mutex<VecDeque<T>>,
+    // Signalled when a value is removed from the queue.
+    not_full: Condvar,
+    // Callback invoked when the queue has values to consume.
+    callback: OnceCell<Box<dyn Fn() + Send + Sync>>,
+    // Set once the pipe has been imported.
+    imported: AtomicBool,
+}
+
+impl<T> Pipe<T>
+where
+    T: Send + 'static,
+{
+    // Find the pipe named `name` in `runtime` or create one if it does not
+    // exist yet.
+    fn with_runtime(runtime: &Runtime, name: &str) -> Arc<Self> {
+        runtime
+            .local_store()
+            .entry(PipeId {
+                name: name.to_string(),
+                _marker: PhantomData,
+            })
+            .or_insert_with(|| {
+                Arc::new(Self {
+                    queue: Mutex::new(VecDeque::with_capacity(PIPE_CAPACITY)),
+                    not_full: Condvar::new(),
+                    callback: OnceCell::new(),
+                    imported: AtomicBool::new(false),
+                })
+            })
+            .value()
+            .clone()
+    }
+
+    // Add a value to the queue, waiting for the importing circuit to consume
+    // older values if the queue is full.
+    fn push(&self, value: T) {
+        let mut queue = self.queue.lock().unwrap();
+        while queue.len() >= PIPE_CAPACITY && !Runtime::kill_in_progress() {
+            // Wake up periodically to check for a kill signal.
+            queue = self
+                .not_full
+                .wait_timeout(queue, Duration::from_millis(10))
+                .unwrap()
+                .0;
+        }
+        queue.push_back(value);
+        drop(queue);
+
+        if let Some(cb) = self.callback.get() {
+            cb()
+        }
+    }
+
+    // Remove the oldest value from the queue.
+    fn pop(&self) -> Option<T> {
+        let mut queue = self.queue.lock().unwrap();
+        let value = queue.pop_front();
+        let more = !queue.is_empty();
+        drop(queue);
+
+        self.not_full.notify_one();
+        // The scheduler resets the ready status of the receiver after
+        // evaluating it; let it know that the next value is already here.
+        if more {
+            if let Some(cb) = self.callback.get() {
+                cb()
+            }
+        }
+        value
+    }
+}
+
+impl<T> Stream<Circuit<()>, T>
+where
+    T: Data + Send,
+{
+    /// Export `self` to other circuits in `runtime` under `name`.
+    ///
+    /// The value of the stream at each clock cycle is sent through the pipe
+    /// and becomes the value of the stream returned by
+    /// [`Circuit::import_from_pipe`] at the corresponding clock cycle of the
+    /// importing circuit.  Each pipe connects exactly one exporting and one
+    /// importing circuit, which can run in the same or in different worker
+    /// threads.  To connect multiple pairs of workers, use a separate name,
+    /// e.g., including the worker index, for each pair.
+    ///
+    /// The pipe buffers up to [`PIPE_CAPACITY`] values, so the exporting
+    /// circuit can run ahead of the importing one by this many clock
+    /// cycles, after which it blocks until the importing circuit catches up.
+    pub fn export_to_pipe(&self, runtime: &Runtime, name: &str) {
+        let pipe = Pipe::with_runtime(runtime, name);
+        self.circuit().add_sink(PipeSender::new(pipe), self);
+    }
+}
+
+impl Circuit<()> {
+    /// Import a stream exported by another circuit in `runtime` under `name`
+    /// (see [`Stream::export_to_pipe`]).
+    ///
+    /// The `n`th value of the returned stream is the `n`th value of the
+    /// exported stream, i.e., clock cycles of the two circuits are aligned
+    /// starting from the first step of each circuit.  The source is
+    /// asynchronous: when the importing circuit runs ahead of the exporting
+    /// circuit, the scheduler waits until the exporting circuit completes
+    /// the corresponding clock cycle.  Note that when both circuits run in
+    /// the same thread, the exporting circuit must be stepped first to avoid
+    /// a deadlock, and may not run more than [`PIPE_CAPACITY`] steps ahead.
+    ///
+    /// Returns [`PipeError::AlreadyImported`] if another circuit has already
+    /// imported the pipe.
+    pub fn import_from_pipe<T>(
+        &self,
+        runtime: &Runtime,
+        name: &str,
+    ) -> Result<Stream<Self, T>, PipeError>
+    where
+        T: Data + Send,
+    {
+        let pipe = Pipe::with_runtime(runtime, name);
+        if pipe.imported.swap(true, Ordering::AcqRel) {
+            return Err(PipeError::AlreadyImported {
+                name: name.to_string(),
+            });
+        }
+        Ok(self.add_source(PipeReceiver::new(pipe)))
+    }
+}
+
+/// Sink operator that sends its input to a pipe.
+///
+/// See [`Stream::export_to_pipe`].
+pub struct PipeSender<T> {
+    pipe: Arc<Pipe<T>>,
+}
+
+impl<T> PipeSender<T> {
+    fn new(pipe: Arc<Pipe<T>>) -> Self {
+        Self { pipe }
+    }
+}
+
+impl<T> Operator for PipeSender<T>
+where
+    T: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("PipeSender")
+    }
+
+    fn clock_start(&mut self, _scope: Scope) {}
+    fn clock_end(&mut self, _scope: Scope) {}
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<T> SinkOperator<T> for PipeSender<T>
+where
+    T: Clone + Send + 'static,
+{
+    fn eval(&mut self, input: &T) {
+        self.eval_owned(input.clone());
+    }
+
+    fn eval_owned(&mut self, input: T) {
+        self.pipe.push(input);
+    }
+}
+
+/// Source operator that receives values from a pipe.
+///
+/// See [`Circuit::import_from_pipe`].
+///
+/// `PipeReceiver` is an asynchronous operator: it becomes schedulable once
+/// the exporting circuit has sent the value for the current clock cycle.
+pub struct PipeReceiver<T> {
+    pipe: Arc<Pipe<T>>,
+}
+
+impl<T> PipeReceiver<T> {
+    fn new(pipe: Arc<Pipe<T>>) -> Self {
+        Self { pipe }
+    }
+}
+
+impl<T> Operator for PipeReceiver<T>
+where
+    T: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("PipeReceiver")
+    }
+
+    fn clock_start(&mut self, _scope: Scope) {}
+    fn clock_end(&mut self, _scope: Scope) {}
+
+    fn is_async(&self) -> bool {
+        true
+    }
+
+    fn register_ready_callback<F>(&mut self, cb: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        // `import_from_pipe` guarantees that each pipe has one receiver, and
+        // the scheduler registers the callback once.
+        if self
+            .pipe
+            .callback
+            .set(Box::new(cb) as Box<dyn Fn() + Send + Sync>)
+            .is_err()
+        {
+            panic!("ready callback registered more than once");
+        }
+    }
+
+    fn ready(&self) -> bool {
+        !self.pipe.queue.lock().unwrap().is_empty()
+    }
+
+    fn fixedpoint(&self) -> bool {
+        false
+    }
+}
+
+impl<T> SourceOperator<T> for PipeReceiver<T>
+where
+    T: Data + Send,
+{
+    fn eval(&mut self) -> T {
+        self.pipe
+            .pop()
+            .expect("PipeReceiver evaluated in a non-ready state")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PipeError;
+    use crate::{
+        circuit::{Root, Runtime},
+        operator::Generator,
+    };
+
+    #[test]
+    fn pipe_test() {
+        let hruntime = Runtime::run(2, |runtime, index| {
+            let runtime = runtime.clone();
+            let root = Root::build(move |circuit| {
+                if index == 0 {
+                    let mut n = 0usize;
+                    circuit
+                        .add_source(Generator::new(move || {
+                            n += 1;
+                            n
+                        }))
+                        .export_to_pipe(&runtime, "numbers");
+                } else {
+                    let mut expected = 0usize;
+                    circuit
+                        .import_from_pipe::<usize>(&runtime, "numbers")
+                        .unwrap()
+                        .inspect(move |n| {
+                            expected += 1;
+                            assert_eq!(*n, expected);
+                        });
+                }
+            })
+            .unwrap();
+
+            for _ in 0..100 {
+                root.step().unwrap();
+            }
+        });
+
+        hruntime.join().unwrap();
+    }
+
+    #[test]
+    fn pipe_imported_twice() {
+        let hruntime = Runtime::run(1, |runtime, _index| {
+            let runtime = runtime.clone();
+            Root::build(move |circuit| {
+                circuit
+                    .import_from_pipe::<usize>(&runtime, "numbers")
+                    .unwrap();
+                assert_eq!(
+                    circuit.import_from_pipe::<usize>(&runtime, "numbers").err(),
+                    Some(PipeError::AlreadyImported {
+                        name: "numbers".to_string()
+                    })
+                );
+            })
+            .unwrap();
+        });
+
+        hruntime.join().unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+//! Operator that partitions batches across workers by key.
+
+use crate::{
+    circuit::{
+        operator_traits::{Operator, UnaryOperator},
+        Circuit, Runtime, Stream,
+    },
+    operator::communication::new_exchange_operators,
+    trace::{cursor::Cursor, sharded_spine::hash_shard, Batch, Builder},
+};
+use std::{borrow::Cow, hash::Hash, marker::PhantomData};
+
+impl<P, B> Stream<Circuit<P>, B>
+where
+    P: Clone + 'static,
+    B: Batch<Time = ()> + Clone + Send + Sync + 'static,
+    B::Key: Clone + Hash,
+    B::Val: Clone,
+{
+    /// Shard batches across workers in `runtime` by key.
+    ///
+    /// At every clock cycle, each worker splits its input batch into
+    /// `runtime.num_workers()` partitions based on the hash of the key and
+    /// sends partition `i` to worker `i`.  The output of the operator in each
+    /// worker is the union of the partitions it received, so that all updates
+    /// with the same key end up in the same worker.  This is required to
+    /// evaluate operators such as joins, aggregates, and `distinct` in a
+    /// multi-worker runtime: sharding the inputs of these operators by key
+    /// makes each worker compute its part of the result independently.
+    ///
+    /// In a single-worker runtime, the operator returns `self`.
+    ///
+    /// All workers must evaluate the operator the same number of times.  In
+    /// particular, in a nested circuit, the termination condition of
+    /// [`Circuit::iterate`] must be consistent across workers, e.g., computed
+    /// from values exchanged between workers.  [`Circuit::fixedpoint`] is not
+    /// supported, since workers do not agree on the fixed point.
+    pub fn shard(&self, runtime: &Runtime) -> Stream<Circuit<P>, B> {
+        let num_workers = runtime.num_workers();
+        if num_workers == 1 {
+            return self.clone();
+        }
+
+        let (sender, receiver) = new_exchange_operators(
+            runtime,
+            Runtime::worker_index(),
+            move |batch: B| partition(&batch, num_workers).into_iter().map(Some),
+            |shards: &mut Vec<B>, shard: Option<B>| shards.extend(shard),
+        );
+        let shards = self.circuit().add_exchange(sender, receiver, self);
+        self.circuit()
+            .add_unary_operator(MergeShards::new(), &shards)
+    }
+}
+
+/// Returns the worker that `key` belongs to.
+fn shard_of<K>(key: &K, num_workers: usize) -> usize
+where
+    K: Hash,
+{
+    hash_shard(key, 0, num_workers)
+}
+
+/// Splits `batch` into `num_workers` batches by key.
+fn partition<B>(batch: &B, num_workers: usize) -> Vec<B>
+where
+    B: Batch<Time = ()>,
+    B::Key: Clone + Hash,
+    B::Val: Clone,
+{
+    let mut builders: Vec<_> = (0..num_workers)
+        .map(|_| B::Builder::with_capacity((), batch.len() / num_workers))
+        .collect();
+
+    let mut cursor = batch.cursor();
+    while cursor.key_valid(batch) {
+        let builder = &mut builders[shard_of(cursor.key(batch), num_workers)];
+        while cursor.val_valid(batch) {
+            builder.push((
+                cursor.key(batch).clone(),
+                cursor.val(batch).clone(),
+                cursor.weight(batch),
+            ));
+            cursor.step_val(batch);
+        }
+        cursor.step_key(batch);
+    }
+
+    builders.into_iter().map(|builder| builder.done()).collect()
+}
+
+/// Operator that merges the partitions received by [`Stream::shard`] into a
+/// single batch.
+struct MergeShards<B> {
+    _type: PhantomData<B>,
+}
+
+impl<B> MergeShards<B> {
+    fn new() -> Self {
+        Self { _type: PhantomData }
+    }
+}
+
+impl<B> Operator for MergeShards<B>
+where
+    B: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("MergeShards")
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<B> UnaryOperator<Vec<B>, B> for MergeShards<B>
+where
+    B: Batch<Time = ()> + Clone + 'static,
+{
+    fn eval(&mut self, shards: &Vec<B>) -> B {
+        match shards.split_first() {
+            None => B::empty(()),
+            Some((first, rest)) => rest.iter().fold(first.clone(), |acc, b| acc.merge(b)),
+        }
+    }
+
+    fn eval_owned(&mut self, shards: Vec<B>) -> B {
+        shards
+            .into_iter()
+            .reduce(|acc, b| acc.merge(&b))
+            .unwrap_or_else(|| B::empty(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::shard_of;
+    use crate::{
+        circuit::{Root, Runtime},
+        operator::Generator,
+        trace::{ord::OrdZSet, Batch, BatchReader, Cursor},
+    };
+
+    #[test]
+    fn shard_test() {
+        const WORKERS: usize = 4;
+
+        let hruntime = Runtime::run(WORKERS, |runtime, index| {
+            let runtime = runtime.clone();
+            let root = Root::build(move |circuit| {
+                // Each worker generates all keys; after sharding, each worker
+                // holds the keys it owns with weight `WORKERS`.
+                let input = circuit.add_source(Generator::new(|| {
+                    OrdZSet::<usize, isize>::from_tuples(
+                        (),
+                        (0..100).map(|k| ((k, ()), 1)).collect(),
+                    )
+                }));
+                input.shard(&runtime).inspect(move |batch| {
+                    let mut keys = 0;
+                    let mut cursor = batch.cursor();
+                    while cursor.key_valid(batch) {
+                        assert_eq!(shard_of(cursor.key(batch), WORKERS), index);
+                        assert_eq!(cursor.weight(batch), WORKERS as isize);
+                        keys += 1;
+                        cursor.step_key(batch);
+                    }
+                    assert_eq!(
+                        keys,
+                        (0..100usize)
+                            .filter(|k| shard_of(k, WORKERS) == index)
+                            .count()
+                    );
+                });
+            })
+            .unwrap();
+
+            for _ in 0..10 {
+                root.step().unwrap();
+            }
+        });
+
+        hruntime.join().unwrap();
+    }
+}
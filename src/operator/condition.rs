@@ -1,11 +1,24 @@
 //! Create subcircuits that iterate until a specified condition
 //! defined over the contents of a stream is satisfied.
 
-use crate::circuit::{
-    schedule::{Error as SchedulerError, Scheduler},
-    Circuit, Stream,
+use crate::{
+    circuit::{
+        schedule::{Error as SchedulerError, Scheduler},
+        Circuit, Stream,
+    },
+    operator::GeneratorNested,
+    trace::BatchReader,
+};
+use std::{
+    cell::RefCell,
+    marker::PhantomData,
+    ops::Not,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
-use std::{cell::RefCell, marker::PhantomData, rc::Rc};
 
 impl<P, D> Stream<Circuit<P>, D>
 where
@@ -29,10 +42,73 @@ where
     }
 }
 
+impl<P, B> Stream<Circuit<P>, B>
+where
+    P: 'static + Clone,
+    B: 'static + BatchReader + Clone,
+{
+    /// Attach a condition that is satisfied when the batch in the stream is
+    /// empty.
+    ///
+    /// Combine conditions for multiple streams with [`Condition::all`] or
+    /// [`Condition::any`] to wait until all or any of them become empty.
+    pub fn condition_empty(&self) -> Condition<Circuit<P>> {
+        self.condition(|batch| batch.is_empty())
+    }
+
+    /// Returns a stream that is `true` in each clock cycle when the batch in
+    /// `self` is empty.
+    ///
+    /// Combine multiple such streams with [`Stream::and`] and
+    /// [`Stream::or`] and convert the result into a termination condition
+    /// with [`Stream::condition_true`].
+    pub fn is_empty_stream(&self) -> Stream<Circuit<P>, bool> {
+        self.apply(|batch| batch.is_empty())
+    }
+}
+
+impl<P> Stream<Circuit<P>, bool>
+where
+    P: 'static + Clone,
+{
+    /// Returns a stream that is `true` when both `self` and `other` are
+    /// `true`.
+    pub fn and(&self, other: &Self) -> Self {
+        self.apply2(other, |x, y| *x && *y)
+    }
+
+    /// Returns a stream that is `true` when `self` or `other` is `true`.
+    pub fn or(&self, other: &Self) -> Self {
+        self.apply2(other, |x, y| *x || *y)
+    }
+
+    /// Attach a condition that is satisfied when the value in the stream is
+    /// `true`.
+    pub fn condition_true(&self) -> Condition<Circuit<P>> {
+        self.condition(|x| *x)
+    }
+}
+
 impl<P> Circuit<P>
 where
     P: 'static + Clone,
 {
+    /// Create a condition that is satisfied once the circuit has been
+    /// evaluated `max_iterations` times during the current clock epoch.
+    ///
+    /// Use this condition as a guard against non-terminating recursive
+    /// computations, e.g., `condition.or(child.max_iterations(1000))`.
+    pub fn max_iterations(&self, max_iterations: usize) -> Condition<Self> {
+        self.add_source(GeneratorNested::new(Box::new(|| {
+            let mut iteration = 0;
+            Box::new(move || {
+                iteration += 1;
+                iteration
+            })
+        })))
+        .condition(move |iteration| *iteration >= max_iterations)
+    }
+
     /// Create a subcircuit that iterates until a condition is satisfied.
     ///
     /// This method is similar to [`Circuit::iterate`], which creates
@@ -109,22 +185,116 @@ where
 /// (see [`Circuit::iterate_with_condition`] and
 /// [`Circuit::iterate_with_conditions`]).
 ///
-/// A condition is created by the [`Stream::condition`] method.
+/// A condition is created by the [`Stream::condition`] method and its
+/// specializations ([`Stream::condition_empty`], [`Stream::condition_true`],
+/// [`Circuit::max_iterations`]), or from an external signal (see
+/// [`Condition::external`]).  Conditions can be combined with
+/// [`Condition::all`], [`Condition::all_empty`], [`Condition::any`],
+/// [`Condition::and`],
+/// [`Condition::or`], and negated with `!`.
 pub struct Condition<C> {
-    cond: Rc<RefCell<bool>>,
+    check: Rc<dyn Fn() -> bool>,
     _phantom: PhantomData<C>,
 }
 
-impl<C> Condition<C> {
+impl<C> Clone for Condition<C> {
+    fn clone(&self) -> Self {
+        Self {
+            check: self.check.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C> Condition<C>
+where
+    C: 'static,
+{
     fn new(cond: Rc<RefCell<bool>>) -> Self {
+        Self::from_fn(move || *cond.borrow())
+    }
+
+    fn from_fn<F>(check: F) -> Self
+    where
+        F: Fn() -> bool + 'static,
+    {
         Self {
-            cond,
+            check: Rc::new(check),
             _phantom: PhantomData,
         }
     }
 
+    /// Create a condition controlled by an external signal, e.g., set by
+    /// another thread to stop the computation early.
+    pub fn external(signal: Arc<AtomicBool>) -> Self {
+        Self::from_fn(move || signal.load(Ordering::Acquire))
+    }
+
+    /// Condition that is satisfied when all `conditions` are satisfied
+    /// simultaneously.  Satisfied if `conditions` is empty.
+    pub fn all<I>(conditions: I) -> Self
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        let conditions: Vec<_> = conditions.into_iter().collect();
+        Self::from_fn(move || conditions.iter().all(Self::check))
+    }
+
+    /// Condition that is satisfied when any of the `conditions` is
+    /// satisfied.  Not satisfied if `conditions` is empty.
+    pub fn any<I>(conditions: I) -> Self
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        let conditions: Vec<_> = conditions.into_iter().collect();
+        Self::from_fn(move || conditions.iter().any(Self::check))
+    }
+
+    /// Condition that is satisfied when both `self` and `other` are
+    /// satisfied.
+    pub fn and(self, other: Self) -> Self {
+        Self::from_fn(move || self.check() && other.check())
+    }
+
+    /// Condition that is satisfied when `self` or `other` is satisfied.
+    pub fn or(self, other: Self) -> Self {
+        Self::from_fn(move || self.check() || other.check())
+    }
+
     fn check(&self) -> bool {
-        *self.cond.borrow()
+        (self.check)()
+    }
+}
+
+impl<P> Condition<Circuit<P>>
+where
+    P: 'static + Clone,
+{
+    /// Condition that is satisfied when the batches in all `streams` are
+    /// empty in the same clock cycle, e.g., when a recursive computation
+    /// stops producing new tuples in all of its output deltas.  Satisfied if
+    /// `streams` is empty.
+    ///
+    /// Equivalent to `Condition::all` applied to
+    /// [`Stream::condition_empty`] of each stream.
+    pub fn all_empty<'a, B, I>(streams: I) -> Self
+    where
+        B: 'static + BatchReader + Clone,
+        I: IntoIterator<Item = &'a Stream<Circuit<P>, B>>,
+    {
+        Self::all(streams.into_iter().map(Stream::condition_empty))
+    }
+}
+
+/// Condition that is satisfied when `self` is not satisfied.
+impl<C> Not for Condition<C>
+where
+    C: 'static,
+{
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self::from_fn(move || !self.check())
     }
 }
 
@@ -136,13 +306,109 @@ mod test {
             Circuit, Root, Stream,
         },
         monitor::TraceMonitor,
-        operator::{DelayedFeedback, Generator},
+        operator::{Condition, DelayedFeedback, Generator, GeneratorNested},
         trace::{
             ord::{OrdIndexedZSet, OrdZSet},
             BatchReader,
         },
         zset,
     };
+    use std::{
+        cell::Cell,
+        rc::Rc,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    #[test]
+    fn condition_combinators() {
+        let iterations = Rc::new(Cell::new(0));
+        let iterations_clone = iterations.clone();
+
+        let root = Root::build(move |circuit| {
+            let signal = Arc::new(AtomicBool::new(false));
+
+            circuit
+                .iterate_with_condition(|child| {
+                    let counter = child.add_source(GeneratorNested::new(Box::new(|| {
+                        let mut n = 0;
+                        Box::new(move || {
+                            n += 1;
+                            n
+                        })
+                    })));
+                    counter.inspect(move |n| iterations_clone.set(*n));
+
+                    // Neither of these conditions is ever satisfied.
+                    let never = counter.condition(|n| *n < 0);
+                    let condition = Condition::any([never, Condition::external(signal)])
+                        .and(!Condition::all([]))
+                        .or(child.max_iterations(5));
+
+                    Ok((condition, ()))
+                })
+                .unwrap();
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+            assert_eq!(iterations.get(), 5);
+        }
+    }
+
+    #[test]
+    fn empty_conditions() {
+        let any_iterations = Rc::new(Cell::new(0));
+        let any_iterations_clone = any_iterations.clone();
+        let all_iterations = Rc::new(Cell::new(0));
+        let all_iterations_clone = all_iterations.clone();
+
+        let root = Root::build(move |circuit| {
+            // Stream that yields non-empty batches during the first `n - 1`
+            // iterations.
+            let countdown = |child: &Circuit<Circuit<()>>, n: usize| {
+                child.add_source(GeneratorNested::new(Box::new(move || {
+                    let mut i = 0;
+                    Box::new(move || {
+                        i += 1;
+                        if i < n {
+                            zset! { i => 1isize }
+                        } else {
+                            zset! {}
+                        }
+                    })
+                })))
+            };
+
+            circuit
+                .iterate_with_condition(|child| {
+                    let short = countdown(child, 3);
+                    let long = countdown(child, 5);
+                    short
+                        .inspect(move |_| any_iterations_clone.set(any_iterations_clone.get() + 1));
+                    let condition = short
+                        .is_empty_stream()
+                        .or(&long.is_empty_stream())
+                        .condition_true();
+                    Ok((condition, ()))
+                })
+                .unwrap();
+
+            circuit
+                .iterate_with_condition(|child| {
+                    let short = countdown(child, 3);
+                    let long = countdown(child, 5);
+                    long.inspect(move |_| all_iterations_clone.set(all_iterations_clone.get() + 1));
+                    Ok((Condition::all_empty([&short, &long]), ()))
+                })
+                .unwrap();
+        })
+        .unwrap();
+
+        root.step().unwrap();
+        assert_eq!(any_iterations.get(), 3);
+        assert_eq!(all_iterations.get(), 5);
+    }
 
     #[test]
     fn iterate_with_conditions_static() {
@@ -0,0 +1,140 @@
+//! Defines a sink operator that consumes every element of its input stream by
+//! value.
+
+use crate::circuit::{
+    operator_traits::{Operator, SinkOperator},
+    Circuit, OwnershipPreference, Stream,
+};
+use std::{borrow::Cow, marker::PhantomData};
+
+impl<P, D> Stream<Circuit<P>, D>
+where
+    D: Clone + 'static,
+    P: Clone + 'static,
+{
+    /// Apply [`Consume`] operator to `self`.
+    ///
+    /// Unlike [`inspect`](`Self::inspect`), which passes each value to the
+    /// callback by reference, this method passes it by value.  The value is
+    /// moved out of the stream without copying, unless the stream has other
+    /// consumers that also need it, in which case it gets cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use dbsp::{
+    /// #     circuit::Root,
+    /// #     operator::Generator,
+    /// # };
+    /// let root = Root::build(move |circuit| {
+    ///     let stream = circuit.add_source(Generator::new(|| vec![1, 2, 3]));
+    ///     let mut output = Vec::new();
+    ///     // Append all values in `stream` to `output` without cloning them.
+    ///     stream.consume(move |mut v| output.append(&mut v));
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn consume<F>(&self, callback: F)
+    where
+        F: FnMut(D) + 'static,
+    {
+        self.circuit().add_sink(Consume::new(callback), self);
+    }
+}
+
+/// Sink operator that consumes a stream of values of type `T` by value and
+/// applies a user-provided callback to each input.
+///
+/// The operator prefers to receive its input by value (see
+/// [`OwnershipPreference::PREFER_OWNED`]), which allows terminal operators
+/// such as output adapters to take ownership of the batches they consume
+/// instead of cloning them.  If the input is only available by reference,
+/// e.g., because it is consumed by other operators, it is cloned.
+pub struct Consume<T, F> {
+    callback: F,
+    phantom: PhantomData<T>,
+}
+
+impl<T, F> Consume<T, F>
+where
+    F: FnMut(T),
+{
+    /// Create a new instance of the `Consume` operator that will apply
+    /// `callback` to each value in the input stream.
+    pub fn new(callback: F) -> Self {
+        Self {
+            callback,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, F> Operator for Consume<T, F>
+where
+    T: 'static,
+    F: FnMut(T) + 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("Consume")
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<T, F> SinkOperator<T> for Consume<T, F>
+where
+    T: Clone + 'static,
+    F: FnMut(T) + 'static,
+{
+    fn eval(&mut self, i: &T) {
+        (self.callback)(i.clone())
+    }
+
+    fn eval_owned(&mut self, i: T) {
+        (self.callback)(i)
+    }
+
+    fn input_preference(&self) -> OwnershipPreference {
+        OwnershipPreference::PREFER_OWNED
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{circuit::Root, operator::Generator};
+    use std::{cell::Cell, rc::Rc};
+
+    // Value that counts how many times it has been cloned.
+    struct Counted(Rc<Cell<usize>>);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            self.0.set(self.0.get() + 1);
+            Self(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn consume_test() {
+        let clones = Rc::new(Cell::new(0));
+        let clones_clone = clones.clone();
+        let consumed = Rc::new(Cell::new(0));
+        let consumed_clone = consumed.clone();
+
+        let root = Root::build(move |circuit| {
+            let stream = circuit.add_source(Generator::new(move || Counted(clones_clone.clone())));
+            stream.consume(move |_| consumed_clone.set(consumed_clone.get() + 1));
+        })
+        .unwrap();
+
+        for _ in 0..10 {
+            root.step().unwrap();
+        }
+
+        assert_eq!(consumed.get(), 10);
+        // The only consumer of the stream receives values by value.
+        assert_eq!(clones.get(), 0);
+    }
+}
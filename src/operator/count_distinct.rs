@@ -0,0 +1,400 @@
+//! Exact and approximate count-distinct aggregates.
+
+use crate::{
+    algebra::{HasOne, IndexedZSet, ZRingValue, ZSet},
+    circuit::{
+        operator_traits::{Operator, UnaryOperator},
+        Circuit, Scope, Stream,
+    },
+    trace::{cursor::Cursor, BatchReader},
+    NumEntries,
+};
+use deepsize::DeepSizeOf;
+use std::{
+    borrow::Cow,
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::Neg,
+};
+
+impl<P, Z> Stream<Circuit<P>, Z>
+where
+    P: Clone + 'static,
+    Z: Clone + 'static,
+{
+    /// Count distinct values of each key in an indexed Z-set.
+    ///
+    /// Outputs a Z-set containing a `(key, count)` pair with weight `+1` for
+    /// each key in the input, where `count` is the number of values
+    /// associated with the key with positive weight.  This is equivalent to
+    /// applying `distinct` to the key-value pairs of the input and counting
+    /// the pairs of each key.
+    pub fn count_distinct<O>(&self) -> Stream<Circuit<P>, O>
+    where
+        Z: IndexedZSet + 'static,
+        Z::Key: Clone,
+        Z::R: ZRingValue,
+        O: Clone + ZSet<Key = (Z::Key, usize), R = Z::R> + 'static,
+    {
+        self.aggregate(count_positive)
+    }
+
+    /// Incremental version of [`Self::count_distinct`].
+    ///
+    /// This is equivalent to
+    /// `self.integrate().count_distinct().differentiate()`, but is more
+    /// efficient.
+    pub fn count_distinct_incremental<O>(&self) -> Stream<Circuit<P>, O>
+    where
+        Z: IndexedZSet + DeepSizeOf + NumEntries,
+        Z::Key: Clone + Ord,
+        Z::Val: Ord,
+        Z::R: ZRingValue,
+        O: Clone + ZSet<Key = (Z::Key, usize), R = Z::R> + 'static,
+    {
+        self.aggregate_incremental(count_positive)
+    }
+
+    /// Apply [`ApproxCountDistinct`] operator to `self`.
+    ///
+    /// Approximates the number of distinct values of each key using a
+    /// [`HyperLogLog`] sketch with `2^precision` registers per key.  The
+    /// input and output streams are streams of changes, like the input and
+    /// output of [`Self::count_distinct_incremental`].
+    pub fn approx_count_distinct_incremental<O>(&self, precision: u8) -> Stream<Circuit<P>, O>
+    where
+        Z: IndexedZSet + 'static,
+        Z::Key: Clone + Ord,
+        Z::Val: Hash,
+        Z::R: ZRingValue,
+        O: Clone + ZSet<Key = (Z::Key, u64)> + 'static,
+        O::R: ZRingValue,
+    {
+        self.circuit()
+            .add_unary_operator(ApproxCountDistinct::new(precision), self)
+    }
+}
+
+// Aggregation function that counts values with positive weights.
+fn count_positive<K, V, R>(key: &K, vals: &mut Vec<(&V, R)>) -> (K, usize)
+where
+    K: Clone,
+    R: ZRingValue,
+{
+    (key.clone(), vals.iter().filter(|(_, w)| w.ge0()).count())
+}
+
+/// HyperLogLog sketch estimating the number of distinct values inserted in
+/// it.
+///
+/// The sketch uses `2^precision` one-byte registers and estimates
+/// cardinalities with a relative standard error of about
+/// `1.04 / sqrt(2^precision)`, e.g., 1.6% for precision 12.  Small
+/// cardinalities are estimated using linear counting, which is nearly exact.
+#[derive(Clone, Debug, PartialEq, Eq, DeepSizeOf)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Create an empty sketch with `2^precision` registers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is not in the range `4..=16`.
+    pub fn new(precision: u8) -> Self {
+        assert!(
+            (4..=16).contains(&precision),
+            "HyperLogLog: precision must be between 4 and 16"
+        );
+
+        Self {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    /// Number of bits of the hash used to select a register.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Add `value` to the sketch.
+    pub fn insert<T>(&mut self, value: &T)
+    where
+        T: Hash + ?Sized,
+    {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        self.insert_hash(hasher.finish());
+    }
+
+    /// Add a value with 64-bit hash `hash` to the sketch.
+    pub fn insert_hash(&mut self, hash: u64) {
+        let precision = self.precision as u32;
+        let index = (hash >> (64 - precision)) as usize;
+        // Position of the leftmost 1-bit in the remaining bits; the guard bit
+        // bounds the rank for hashes whose remaining bits are all 0.
+        let rank = ((hash << precision) | (1 << (precision - 1))).leading_zeros() + 1;
+
+        if self.registers[index] < rank as u8 {
+            self.registers[index] = rank as u8;
+        }
+    }
+
+    /// Add all values inserted in `other` to `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the two sketches have different precisions.
+    pub fn merge(&mut self, other: &Self) {
+        assert_eq!(
+            self.precision, other.precision,
+            "HyperLogLog: cannot merge sketches with different precisions"
+        );
+
+        for (register, other) in self.registers.iter_mut().zip(other.registers.iter()) {
+            if *register < *other {
+                *register = *other;
+            }
+        }
+    }
+
+    /// Estimated number of distinct values inserted in the sketch.
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+
+        let mut sum = 0.0;
+        let mut zeros = 0;
+        for register in self.registers.iter() {
+            sum += 1.0 / (1u64 << register) as f64;
+            if *register == 0 {
+                zeros += 1;
+            }
+        }
+
+        let estimate = alpha * m * m / sum;
+        let estimate = if estimate <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            estimate
+        };
+
+        estimate.round() as u64
+    }
+}
+
+/// Approximate count-distinct operator.
+///
+/// Maintains a [`HyperLogLog`] sketch per key in the operator and updates
+/// it with the values of the key with positive weight in each input batch.
+/// Whenever the estimate for a key changes, outputs a retraction of the old
+/// `(key, estimate)` pair and an insertion of the new one, so that the
+/// integral of the output contains the current estimate of each key.
+///
+/// The state of the operator is `O(keys * 2^precision)` bytes regardless of
+/// the number of distinct values, which makes it suitable for monitoring
+/// very high-cardinality data where maintaining
+/// [`Stream::count_distinct_incremental`] is too expensive.  Sketches cannot
+/// forget values: retractions (negative weights) are ignored, so the
+/// estimate counts all values ever inserted for a key.
+pub struct ApproxCountDistinct<Z, O>
+where
+    Z: BatchReader,
+{
+    precision: u8,
+    // Sketch and last reported estimate of each key.
+    sketches: BTreeMap<Z::Key, (HyperLogLog, u64)>,
+    _type: PhantomData<O>,
+}
+
+impl<Z, O> ApproxCountDistinct<Z, O>
+where
+    Z: BatchReader,
+    Z::Key: Ord,
+{
+    pub fn new(precision: u8) -> Self {
+        // Validate precision eagerly.
+        HyperLogLog::new(precision);
+
+        Self {
+            precision,
+            sketches: BTreeMap::new(),
+            _type: PhantomData,
+        }
+    }
+
+    /// Current estimate for `key`, if the key has been observed.
+    pub fn estimate(&self, key: &Z::Key) -> Option<u64> {
+        self.sketches.get(key).map(|(_, estimate)| *estimate)
+    }
+}
+
+impl<Z, O> Operator for ApproxCountDistinct<Z, O>
+where
+    Z: BatchReader + 'static,
+    Z::Key: Ord,
+    O: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("ApproxCountDistinct")
+    }
+
+    fn is_stateful(&self) -> bool {
+        true
+    }
+
+    fn clock_start(&mut self, scope: Scope) {
+        if scope == 0 {
+            self.sketches.clear();
+        }
+    }
+
+    fn summary(&self, output: &mut String) {
+        *output = format!("keys: {}", self.sketches.len());
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<Z, O> UnaryOperator<Z, O> for ApproxCountDistinct<Z, O>
+where
+    Z: IndexedZSet + 'static,
+    Z::Key: Clone + Ord,
+    Z::Val: Hash,
+    Z::R: ZRingValue,
+    O: Clone + ZSet<Key = (Z::Key, u64)> + 'static,
+    O::R: ZRingValue,
+{
+    fn eval(&mut self, delta: &Z) -> O {
+        let mut result = Vec::new();
+        let mut cursor = delta.cursor();
+
+        while cursor.key_valid(delta) {
+            let key = cursor.key(delta);
+            let precision = self.precision;
+            let (sketch, estimate) = self
+                .sketches
+                .entry(key.clone())
+                .or_insert_with(|| (HyperLogLog::new(precision), 0));
+            let old_estimate = *estimate;
+
+            while cursor.val_valid(delta) {
+                if cursor.weight(delta).ge0() {
+                    sketch.insert(cursor.val(delta));
+                }
+                cursor.step_val(delta);
+            }
+
+            *estimate = sketch.estimate();
+            if *estimate != old_estimate {
+                if old_estimate != 0 {
+                    result.push((((key.clone(), old_estimate), ()), O::R::one().neg()));
+                }
+                result.push((((key.clone(), *estimate), ()), O::R::one()));
+            }
+            cursor.step_key(delta);
+        }
+
+        O::from_tuples((), result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HyperLogLog;
+    use crate::{
+        circuit::Root,
+        indexed_zset,
+        operator::Generator,
+        trace::ord::{OrdIndexedZSet, OrdZSet},
+        zset,
+    };
+    use std::vec;
+
+    #[test]
+    fn hyperloglog_test() {
+        let mut sketch = HyperLogLog::new(12);
+        assert_eq!(sketch.estimate(), 0);
+
+        for i in 0..100000u64 {
+            sketch.insert(&i);
+        }
+        let estimate = sketch.estimate() as f64;
+        assert!(
+            (estimate - 100000.0).abs() < 5000.0,
+            "estimate: {}",
+            estimate
+        );
+
+        // Duplicates do not change the estimate.
+        let before = sketch.clone();
+        for i in 0..1000u64 {
+            sketch.insert(&i);
+        }
+        assert_eq!(sketch, before);
+
+        let mut other = HyperLogLog::new(12);
+        for i in 100000..200000u64 {
+            other.insert(&i);
+        }
+        sketch.merge(&other);
+        let estimate = sketch.estimate() as f64;
+        assert!(
+            (estimate - 200000.0).abs() < 10000.0,
+            "estimate: {}",
+            estimate
+        );
+    }
+
+    #[test]
+    fn count_distinct_test() {
+        let root = Root::build(move |circuit| {
+            let mut input: vec::IntoIter<OrdIndexedZSet<usize, usize, isize>> = vec![
+                indexed_zset! { 1 => { 1 => 1, 2 => 2 }, 2 => { 1 => 1 } },
+                indexed_zset! { 1 => { 3 => 1 }, 2 => { 1 => -1 }, 3 => { 5 => 1 } },
+                indexed_zset! { 1 => { 2 => -2 } },
+            ]
+            .into_iter();
+
+            let mut exact: vec::IntoIter<OrdZSet<(usize, usize), isize>> = vec![
+                zset! { (1, 2) => 1, (2, 1) => 1 },
+                zset! { (1, 2) => -1, (1, 3) => 1, (2, 1) => -1, (3, 1) => 1 },
+                zset! { (1, 3) => -1, (1, 2) => 1 },
+            ]
+            .into_iter();
+
+            // Sketches ignore retractions.
+            let mut approx: vec::IntoIter<OrdZSet<(usize, u64), isize>> = vec![
+                zset! { (1, 2) => 1, (2, 1) => 1 },
+                zset! { (1, 2) => -1, (1, 3) => 1, (3, 1) => 1 },
+                zset! {},
+            ]
+            .into_iter();
+
+            let input = circuit.add_source(Generator::new(move || input.next().unwrap()));
+
+            input
+                .count_distinct_incremental::<OrdZSet<_, _>>()
+                .inspect(move |counts| assert_eq!(*counts, exact.next().unwrap()));
+            input
+                .approx_count_distinct_incremental::<OrdZSet<_, _>>(12)
+                .inspect(move |counts| assert_eq!(*counts, approx.next().unwrap()));
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
+}
@@ -0,0 +1,356 @@
+//! Step-by-step HTML rendering of recorded streams.
+//!
+//! [`DeltaVisualizer`] renders the changes carried by one or more named
+//! streams at each clock cycle of a run as a sequence of HTML pages, one page
+//! per step.  Each page shows, for every stream, the delta at this step, with
+//! insertions (positive weights) and retractions (negative weights)
+//! highlighted in different colors, next to the contents of the stream
+//! integrated up to this step.  Streams are typically captured with
+//! [`Stream::record`](`crate::circuit::Stream::record`), which makes the
+//! visualizer a convenient way to explain the behavior of an incremental
+//! circuit after the fact or to illustrate incremental semantics when
+//! teaching.
+
+use crate::{
+    algebra::ZRingValue,
+    operator::codec::{read_batch, DEFAULT_MAX_FRAME_LEN},
+    trace::{Batch, BatchReader, Cursor},
+};
+use serde::de::DeserializeOwned;
+use std::{
+    fmt::{Debug, Write as _},
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+// A `(key, value, weight)` tuple formatted for display.
+struct Row {
+    key: String,
+    val: String,
+    weight: String,
+    insert: bool,
+}
+
+// Delta and integrated contents of a stream at one step.
+struct StepView {
+    delta: Vec<Row>,
+    contents: Vec<Row>,
+}
+
+struct StreamView {
+    name: String,
+    // `false` for Z-sets, whose values are always `()`.
+    show_vals: bool,
+    steps: Vec<StepView>,
+}
+
+/// Renders the deltas of named streams in a recorded run as HTML.
+///
+/// See [module-level documentation](`self`).
+#[derive(Default)]
+pub struct DeltaVisualizer {
+    streams: Vec<StreamView>,
+}
+
+impl DeltaVisualizer {
+    /// Create a visualizer without streams.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a stream named `name` whose value at step `i` is the `i`th
+    /// element of `batches`.
+    pub fn add_batches<B, I>(&mut self, name: &str, batches: I)
+    where
+        B: Batch<Time = ()>,
+        B::Key: Debug,
+        B::Val: Debug,
+        B::R: ZRingValue + Debug,
+        I: IntoIterator<Item = B>,
+    {
+        let mut contents = B::empty(());
+        let mut show_vals = false;
+        let mut steps = Vec::new();
+
+        for batch in batches {
+            contents = contents.merge(&batch);
+            let delta = rows(&batch);
+            show_vals |= delta.iter().any(|row| row.val != "()");
+            steps.push(StepView {
+                delta,
+                contents: rows(&contents),
+            });
+        }
+
+        self.streams.push(StreamView {
+            name: name.to_string(),
+            show_vals,
+            steps,
+        });
+    }
+
+    /// Add a stream named `name` read from a recording produced by
+    /// [`Recorder`](`crate::operator::Recorder`).
+    pub fn add_recording<B, R>(&mut self, name: &str, mut reader: R) -> io::Result<()>
+    where
+        B: Batch<Time = ()>,
+        B::Key: Debug + DeserializeOwned,
+        B::Val: Debug + DeserializeOwned,
+        B::R: ZRingValue + Debug + DeserializeOwned,
+        R: Read,
+    {
+        let mut buffer = Vec::new();
+        let mut batches = Vec::new();
+        while let Some(batch) = read_batch::<B, _>(&mut reader, &mut buffer, DEFAULT_MAX_FRAME_LEN)?
+        {
+            batches.push(batch);
+        }
+        self.add_batches(name, batches);
+        Ok(())
+    }
+
+    /// Number of steps in the longest stream.
+    pub fn steps(&self) -> usize {
+        self.streams
+            .iter()
+            .map(|stream| stream.steps.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Render the page of step `step`.
+    ///
+    /// The page contains links to the pages of the previous and next steps
+    /// and to the index, named as by [`Self::write_html`].
+    pub fn step_html(&self, step: usize) -> String {
+        let mut html = String::new();
+        let title = format!("Step {}", step);
+        page_header(&mut html, &title);
+
+        html.push_str("<nav>");
+        if step > 0 {
+            write!(
+                html,
+                "<a href=\"{}\">&larr; previous</a> | ",
+                step_file(step - 1)
+            )
+            .unwrap();
+        }
+        html.push_str("<a href=\"index.html\">index</a>");
+        if step + 1 < self.steps() {
+            write!(
+                html,
+                " | <a href=\"{}\">next &rarr;</a>",
+                step_file(step + 1)
+            )
+            .unwrap();
+        }
+        html.push_str("</nav>\n");
+
+        for stream in self.streams.iter() {
+            writeln!(html, "<h2>{}</h2>", escape(&stream.name)).unwrap();
+            match stream.steps.get(step) {
+                None => html.push_str("<p>No data for this step.</p>\n"),
+                Some(view) => {
+                    html.push_str("<div class=\"step\">\n");
+                    table(&mut html, "Delta", &view.delta, stream.show_vals);
+                    table(
+                        &mut html,
+                        &format!("Contents after step {}", step),
+                        &view.contents,
+                        stream.show_vals,
+                    );
+                    html.push_str("</div>\n");
+                }
+            }
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// Write the pages of all steps to files `step-<n>.html` in `dir`, along
+    /// with `index.html` that links to them, creating `dir` if necessary.
+    pub fn write_html<T>(&self, dir: T) -> io::Result<()>
+    where
+        T: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let mut index = String::new();
+        page_header(&mut index, "Steps");
+        index.push_str("<ul>\n");
+        for step in 0..self.steps() {
+            fs::write(dir.join(step_file(step)), self.step_html(step))?;
+
+            let changes: usize = self
+                .streams
+                .iter()
+                .filter_map(|stream| stream.steps.get(step))
+                .map(|view| view.delta.len())
+                .sum();
+            writeln!(
+                index,
+                "<li><a href=\"{}\">Step {}</a> ({} changes)</li>",
+                step_file(step),
+                step,
+                changes
+            )
+            .unwrap();
+        }
+        index.push_str("</ul>\n</body>\n</html>\n");
+        fs::write(dir.join("index.html"), index)
+    }
+}
+
+fn step_file(step: usize) -> String {
+    format!("step-{}.html", step)
+}
+
+// Format the tuples of `batch` for display.
+fn rows<B>(batch: &B) -> Vec<Row>
+where
+    B: BatchReader<Time = ()>,
+    B::Key: Debug,
+    B::Val: Debug,
+    B::R: ZRingValue + Debug,
+{
+    let mut result = Vec::with_capacity(batch.len());
+    let mut cursor = batch.cursor();
+
+    while cursor.key_valid(batch) {
+        let key = format!("{:?}", cursor.key(batch));
+        while cursor.val_valid(batch) {
+            let weight = cursor.weight(batch);
+            result.push(Row {
+                key: key.clone(),
+                val: format!("{:?}", cursor.val(batch)),
+                weight: format!("{:?}", weight),
+                insert: weight.ge0(),
+            });
+            cursor.step_val(batch);
+        }
+        cursor.step_key(batch);
+    }
+
+    result
+}
+
+fn page_header(html: &mut String, title: &str) {
+    write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n\
+         body {{ font-family: sans-serif; }}\n\
+         .step {{ display: flex; gap: 2em; align-items: flex-start; }}\n\
+         table {{ border-collapse: collapse; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 0.2em 0.6em; font-family: monospace; }}\n\
+         tr.insert {{ background: #dfd; }}\n\
+         tr.retract {{ background: #fdd; }}\n\
+         </style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        escape(title),
+        escape(title)
+    )
+    .unwrap();
+}
+
+// Render `rows` as a table with caption `caption`.
+fn table(html: &mut String, caption: &str, rows: &[Row], show_vals: bool) {
+    write!(
+        html,
+        "<table>\n<caption>{}</caption>\n<tr><th></th><th>key</th>",
+        escape(caption)
+    )
+    .unwrap();
+    if show_vals {
+        html.push_str("<th>value</th>");
+    }
+    html.push_str("<th>weight</th></tr>\n");
+
+    for row in rows {
+        let (class, sign) = if row.insert {
+            ("insert", "+")
+        } else {
+            ("retract", "&minus;")
+        };
+        write!(
+            html,
+            "<tr class=\"{}\"><td>{}</td><td>{}</td>",
+            class,
+            sign,
+            escape(&row.key)
+        )
+        .unwrap();
+        if show_vals {
+            write!(html, "<td>{}</td>", escape(&row.val)).unwrap();
+        }
+        writeln!(html, "<td>{}</td></tr>", escape(&row.weight)).unwrap();
+    }
+
+    html.push_str("</table>\n");
+}
+
+fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::DeltaVisualizer;
+    use crate::{indexed_zset, operator::codec::write_batch, trace::ord::OrdZSet, zset};
+    use std::{env, fs};
+
+    #[test]
+    fn delta_html_test() {
+        let mut recording = Vec::new();
+        for batch in [
+            zset! { 1usize => 1isize, 2 => 1 },
+            zset! { 2 => -1, 3 => 1 },
+        ] {
+            write_batch::<OrdZSet<_, _>, _>(&batch, &mut recording).unwrap();
+        }
+
+        let mut visualizer = DeltaVisualizer::new();
+        visualizer
+            .add_recording::<OrdZSet<usize, isize>, _>("numbers", &recording[..])
+            .unwrap();
+        visualizer.add_batches(
+            "<pairs>",
+            vec![indexed_zset! { 1usize => { "a" => 1isize } }],
+        );
+        assert_eq!(visualizer.steps(), 2);
+
+        let step0 = visualizer.step_html(0);
+        assert!(step0.contains("<h2>&lt;pairs&gt;</h2>"));
+        assert!(step0.contains("<td>&quot;a&quot;</td>"));
+        assert!(step0.contains("<a href=\"step-1.html\">"));
+
+        let step1 = visualizer.step_html(1);
+        assert!(step1.contains("<tr class=\"retract\"><td>&minus;</td><td>2</td><td>-1</td></tr>"));
+        assert!(step1.contains("<tr class=\"insert\"><td>+</td><td>3</td><td>1</td></tr>"));
+        // Contents after step 1: {1, 3}.
+        assert_eq!(step1.matches("<tr class=\"insert\">").count(), 3);
+        assert!(step1.contains("No data for this step."));
+        assert!(!step1.contains("next &rarr;"));
+
+        let dir = env::temp_dir().join(format!("dbsp-delta-html-{}", std::process::id()));
+        visualizer.write_html(&dir).unwrap();
+        let index = fs::read_to_string(dir.join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"step-0.html\">Step 0</a> (3 changes)"));
+        assert_eq!(fs::read_to_string(dir.join("step-1.html")).unwrap(), step1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
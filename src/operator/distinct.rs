@@ -2,12 +2,14 @@
 
 use std::{
     borrow::Cow,
+    cell::RefCell,
     cmp::{max, Ordering},
-    collections::BTreeSet,
+    collections::{BTreeMap, BTreeSet},
     fmt::Write,
     marker::PhantomData,
     mem::take,
     ops::{Add, Neg},
+    rc::Rc,
 };
 
 use crate::{
@@ -64,6 +66,42 @@ where
             .clone()
     }
 
+    /// Apply [`Threshold`] operator to `self`.
+    ///
+    /// Outputs each key whose weight is greater than or equal to `k` with
+    /// weight 1.  `threshold(1)` is equivalent to [`Self::distinct`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is not positive.
+    pub fn threshold(&self, k: Z::R) -> Stream<Circuit<P>, Z>
+    where
+        Z: ZSet,
+        Z::Key: Clone,
+        Z::R: ZRingValue,
+    {
+        self.circuit().add_unary_operator(Threshold::new(k), self)
+    }
+
+    /// Incremental version of the [`Threshold`] operator.
+    ///
+    /// This is equivalent to `self.integrate().threshold(k).differentiate()`,
+    /// but is more efficient: it outputs a key with weight `+1` when its
+    /// accumulated weight reaches `k` and retracts it when the weight drops
+    /// below `k`.
+    pub fn threshold_incremental(&self, k: Z::R) -> Stream<Circuit<P>, Z>
+    where
+        Z: DeepSizeOf + NumEntries + ZSet,
+        Z::Key: Clone + PartialEq + Ord,
+        Z::R: ZRingValue,
+    {
+        self.circuit().add_binary_operator(
+            ThresholdIncremental::new(k),
+            self,
+            &self.integrate_trace().delay_trace(),
+        )
+    }
+
     /// Incremental nested version of the [`Distinct`] operator.
     // TODO: remove this method.
     pub fn distinct_incremental_nested(&self) -> Stream<Circuit<P>, Z>
@@ -108,6 +146,50 @@ where
             })
             .clone()
     }
+
+    /// Like [`Self::distinct_trace`], but garbage collects the history of
+    /// keys whose multiplicity has been zero for `horizon` clock epochs of the
+    /// parent circuit.
+    ///
+    /// The trace maintained by [`Self::distinct_trace`] stores the history of
+    /// every key ever observed.  Updates that cancel out at the same nested
+    /// timestamp are removed when the trace is compacted at the end of each
+    /// epoch, but a key inserted and deleted at different iterations keeps
+    /// its history forever, even though its total weight is zero.  This
+    /// method tracks such keys and, once a key has stayed at zero for
+    /// `horizon` epochs, removes its history from the trace.  Removing keys
+    /// requires a pass over the trace, so expired keys are removed in
+    /// batches, possibly several epochs after they expire.  Only keys whose
+    /// weight drops to zero as a result of a deletion are collected.
+    ///
+    /// Garbage collection is exact as long as collected keys do not reappear
+    /// in the input.  A key that reappears after being collected is treated
+    /// as if it had never been seen before, so the output for this key may
+    /// differ from [`Self::distinct_trace`].  Choose `horizon` to exceed the
+    /// expected lifetime of deleted keys, e.g., the retention period of
+    /// the input.
+    ///
+    /// Unlike [`Self::distinct_trace`], the result is not cached, so
+    /// each call creates a new operator and trace.
+    pub fn distinct_trace_with_gc(&self, horizon: usize) -> Stream<Circuit<P>, Z>
+    where
+        Z: NumEntries + ZSet + DeepSizeOf,
+        Z::Key: Clone + Ord + DeepSizeOf,
+        Z::R: ZRingValue + DeepSizeOf,
+    {
+        let touched = Rc::new(RefCell::new(BTreeSet::new()));
+        let mut gc = DistinctGc::new(horizon, touched.clone());
+
+        self.circuit().add_binary_operator(
+            DistinctTrace::with_gc(touched),
+            self,
+            &self
+                .trace_with_gc::<OrdKeySpine<Z::Key, NestedTimestamp32, Z::R>, _>(move |trace| {
+                    gc.collect(trace)
+                })
+                .delay_trace(),
+        )
+    }
 }
 
 /// `Distinct` operator changes all weights in the support of a Z-set to 1.
@@ -143,6 +225,10 @@ impl<Z> UnaryOperator<Z, Z> for Distinct<Z>
 where
     Z: ZSet,
 {
+    fn idle_output(&mut self) -> Option<Z> {
+        Some(Z::empty(()))
+    }
+
     fn eval(&mut self, i: &Z) -> Z {
         i.distinct()
     }
@@ -152,6 +238,166 @@ where
     }
 }
 
+/// `Threshold` operator outputs keys whose weight is at least `k` with
+/// weight 1.
+///
+/// This generalizes [`Distinct`], which corresponds to `k = 1`, to support
+/// and threshold queries, e.g., finding items that occur in at least `k`
+/// transactions.
+pub struct Threshold<Z>
+where
+    Z: ZSet,
+{
+    k: Z::R,
+    _type: PhantomData<Z>,
+}
+
+impl<Z> Threshold<Z>
+where
+    Z: ZSet,
+    Z::R: ZRingValue,
+{
+    pub fn new(k: Z::R) -> Self {
+        assert!(
+            k.ge0() && !k.is_zero(),
+            "Threshold: threshold must be positive"
+        );
+
+        Self {
+            k,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<Z> Operator for Threshold<Z>
+where
+    Z: ZSet + 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("Threshold")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<Z> UnaryOperator<Z, Z> for Threshold<Z>
+where
+    Z: ZSet,
+    Z::Key: Clone,
+    Z::R: ZRingValue,
+{
+    fn eval(&mut self, i: &Z) -> Z {
+        let mut builder = Z::Builder::with_capacity((), i.len());
+        let mut cursor = i.cursor();
+
+        while cursor.key_valid(i) {
+            if at_least(&cursor.weight(i), &self.k) {
+                builder.push((cursor.key(i).clone(), (), HasOne::one()));
+            }
+            cursor.step_key(i);
+        }
+
+        builder.done()
+    }
+}
+
+// `weight >= k`, expressed using the operations of `ZRingValue`.
+fn at_least<R>(weight: &R, k: &R) -> bool
+where
+    R: ZRingValue,
+{
+    weight.add_by_ref(&k.clone().neg()).ge0()
+}
+
+/// Incremental version of the threshold operator.
+///
+/// Takes a stream of changes to a Z-set and the delayed integral of the
+/// stream (see [`DistinctIncremental`]) and outputs changes to the result of
+/// [`Threshold`] applied to the integral.
+struct ThresholdIncremental<Z, I>
+where
+    Z: ZSet,
+{
+    k: Z::R,
+    _type: PhantomData<(Z, I)>,
+}
+
+impl<Z, I> ThresholdIncremental<Z, I>
+where
+    Z: ZSet,
+    Z::R: ZRingValue,
+{
+    pub fn new(k: Z::R) -> Self {
+        assert!(
+            k.ge0() && !k.is_zero(),
+            "Threshold: threshold must be positive"
+        );
+
+        Self {
+            k,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<Z, I> Operator for ThresholdIncremental<Z, I>
+where
+    Z: ZSet + 'static,
+    I: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("ThresholdIncremental")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<Z, I> BinaryOperator<Z, I, Z> for ThresholdIncremental<Z, I>
+where
+    Z: ZSet,
+    Z::Key: Clone + PartialEq,
+    Z::R: ZRingValue,
+    I: BatchReader<Key = Z::Key, Val = (), Time = (), R = Z::R> + 'static,
+{
+    fn eval(&mut self, delta: &Z, delayed_integral: &I) -> Z {
+        let mut builder = Z::Builder::with_capacity((), delta.len());
+        let mut delta_cursor = delta.cursor();
+        let mut integral_cursor = delayed_integral.cursor();
+
+        while delta_cursor.key_valid(delta) {
+            let v = delta_cursor.key(delta);
+            let w = delta_cursor.weight(delta);
+            integral_cursor.seek_key(delayed_integral, v);
+            let old_weight = if integral_cursor.key_valid(delayed_integral)
+                && integral_cursor.key(delayed_integral) == v
+            {
+                integral_cursor.weight(delayed_integral)
+            } else {
+                HasZero::zero()
+            };
+
+            let new_weight = old_weight.add_by_ref(&w);
+
+            match (
+                at_least(&old_weight, &self.k),
+                at_least(&new_weight, &self.k),
+            ) {
+                // Weight reaches the threshold.
+                (false, true) => builder.push((v.clone(), (), HasOne::one())),
+                // Weight drops below the threshold.
+                (true, false) => builder.push((v.clone(), (), Z::R::one().neg())),
+                _ => {}
+            }
+            delta_cursor.step_key(delta);
+        }
+
+        builder.done()
+    }
+}
+
 /// Incremental version of the distinct operator.
 ///
 /// Takes a stream `a` of changes to relation `A` and a stream with delayed
@@ -194,6 +440,11 @@ where
     Z::R: ZRingValue,
     I: BatchReader<Key = Z::Key, Val = (), Time = (), R = Z::R> + 'static,
 {
+    // Unchanged keys cannot change their membership.
+    fn idle_output(&mut self, delta_idle: bool, _integral_idle: bool) -> Option<Z> {
+        delta_idle.then(|| Z::empty(()))
+    }
+
     fn eval(&mut self, delta: &Z, delayed_integral: &I) -> Z {
         let mut builder = Z::Builder::with_capacity((), delta.len());
         let mut delta_cursor = delta.cursor();
@@ -251,6 +502,10 @@ where
     time: u32,
     empty_input: bool,
     empty_output: bool,
+    // Keys deleted by input deltas, i.e., keys with negative weights, consumed
+    // by the garbage collector of the trace (see
+    // `Stream::distinct_trace_with_gc`).
+    touched: Option<Rc<RefCell<BTreeSet<Z::Key>>>>,
     _type: PhantomData<(Z, T)>,
 }
 
@@ -266,9 +521,17 @@ where
             time: HasZero::zero(),
             empty_input: false,
             empty_output: false,
+            touched: None,
             _type: PhantomData,
         }
     }
+
+    fn with_gc(touched: Rc<RefCell<BTreeSet<Z::Key>>>) -> Self {
+        Self {
+            touched: Some(touched),
+            ..Self::new()
+        }
+    }
 }
 
 impl<Z, T> DistinctTrace<Z, T>
@@ -474,6 +737,19 @@ where
 
         self.empty_input = delta.is_zero();
 
+        if let Some(touched) = &self.touched {
+            // Only a deletion can bring the total weight of a key to zero, so
+            // the garbage collector does not need to know about other keys.
+            let mut touched = touched.borrow_mut();
+            let mut cursor = delta.cursor();
+            while cursor.key_valid(delta) {
+                if !cursor.weight(delta).ge0() {
+                    touched.insert(cursor.key(delta).clone());
+                }
+                cursor.step_key(delta);
+            }
+        }
+
         // Make sure we have enough room in `future_updates` to
         // accommodate the largest timestamp in the trace, so we don't
         // need to worry about growing `future_updates` later on.
@@ -559,17 +835,174 @@ where
     }
 }
 
+// Minimal number of expired keys removed from the trace at once by
+// `DistinctGc`.
+const DISTINCT_GC_BATCH: usize = 1024;
+
+// Garbage collector of the trace maintained by
+// `Stream::distinct_trace_with_gc`, invoked at the end of each clock epoch.
+//
+// Removing expired keys requires a pass over the entire trace, so expired
+// keys are accumulated until there are at least `DISTINCT_GC_BATCH` of them
+// or they make up a sizable fraction of the trace.
+struct DistinctGc<K> {
+    horizon: usize,
+    // Minimal number of expired keys to remove at once.
+    batch: usize,
+    // Number of completed epochs.
+    epoch: usize,
+    // Keys deleted in the input during the current epoch.
+    touched: Rc<RefCell<BTreeSet<K>>>,
+    // Keys whose total weight is zero, along with the epoch when their
+    // weight became zero.
+    //
+    // A key can become non-zero again without being deleted, so keys are
+    // checked again before being removed from the trace.
+    zero_since: BTreeMap<K, usize>,
+    // Expired keys waiting to be removed from the trace.
+    expired: BTreeSet<K>,
+}
+
+impl<K> DistinctGc<K>
+where
+    K: Ord + Clone + Send + 'static,
+{
+    fn new(horizon: usize, touched: Rc<RefCell<BTreeSet<K>>>) -> Self {
+        Self {
+            horizon,
+            batch: DISTINCT_GC_BATCH,
+            epoch: 0,
+            touched,
+            zero_since: BTreeMap::new(),
+            expired: BTreeSet::new(),
+        }
+    }
+
+    // Total weight of `key` in `trace`, or `None` if the trace does not
+    // contain the key.
+    fn weight<R>(trace: &OrdKeySpine<K, NestedTimestamp32, R>, key: &K) -> Option<R>
+    where
+        R: ZRingValue + Send,
+    {
+        let mut cursor = trace.cursor();
+        cursor.seek_key(trace, key);
+        if !(cursor.key_valid(trace) && cursor.key(trace) == key) {
+            return None;
+        }
+
+        let mut weight = R::zero();
+        cursor.map_times(trace, |_t, w| weight.add_assign_by_ref(w));
+        Some(weight)
+    }
+
+    fn collect<R>(&mut self, trace: &mut OrdKeySpine<K, NestedTimestamp32, R>)
+    where
+        R: ZRingValue + Send,
+    {
+        // Only keys deleted during this epoch can become zero.  A deleted key
+        // may have become non-zero in the meantime, so its countdown restarts
+        // if it is zero.
+        let touched = take(&mut *self.touched.borrow_mut());
+        for key in touched.into_iter() {
+            self.expired.remove(&key);
+            match Self::weight(trace, &key) {
+                Some(weight) if weight.is_zero() => {
+                    self.zero_since.insert(key, self.epoch);
+                }
+                // All updates to the key canceled out during compaction or
+                // the key is still present.
+                _ => {
+                    self.zero_since.remove(&key);
+                }
+            }
+        }
+
+        let (epoch, horizon) = (self.epoch, self.horizon);
+        let expired = &mut self.expired;
+        self.zero_since.retain(|key, since| {
+            if epoch - *since >= horizon {
+                expired.insert(key.clone());
+                false
+            } else {
+                true
+            }
+        });
+
+        if !self.expired.is_empty()
+            && (self.expired.len() >= self.batch || 8 * self.expired.len() >= trace.len())
+        {
+            // Skip keys that reappeared without being deleted.
+            let mut expired = take(&mut self.expired);
+            expired
+                .retain(|key| matches!(Self::weight(trace, key), Some(weight) if weight.is_zero()));
+            if !expired.is_empty() {
+                trace.retain_batches(&|key, _| !expired.contains(key));
+            }
+        }
+
+        self.epoch += 1;
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use std::{cell::RefCell, rc::Rc};
+    use std::{cell::RefCell, collections::BTreeSet, rc::Rc};
 
+    use super::DistinctGc;
     use crate::{
         circuit::Root,
-        operator::{Apply2, GeneratorNested},
-        trace::ord::OrdZSet,
+        operator::{Apply2, Generator, GeneratorNested},
+        time::NestedTimestamp32,
+        trace::{
+            ord::{OrdKeyBatch, OrdKeySpine, OrdZSet},
+            Batch, BatchReader, Cursor, Trace,
+        },
         zset,
     };
 
+    #[test]
+    fn threshold_test() {
+        let root = Root::build(move |circuit| {
+            let mut input = vec![
+                zset! { 1 => 1, 2 => 2, 3 => 3, 4 => -3 },
+                zset! { 1 => 1, 2 => -1, 3 => 1 },
+                zset! { 1 => 1, 3 => -3 },
+            ]
+            .into_iter();
+            let mut threshold = vec![
+                zset! { 2 => 1, 3 => 1 },
+                zset! { 1 => 1, 3 => 1 },
+                zset! { 1 => 1 },
+            ]
+            .into_iter();
+            let mut threshold_incremental = vec![
+                zset! { 2 => 1, 3 => 1 },
+                zset! { 1 => 1, 2 => -1 },
+                zset! { 3 => -1 },
+            ]
+            .into_iter();
+
+            let input = circuit.add_source(Generator::new(move || input.next().unwrap()));
+            input
+                .integrate()
+                .threshold(2)
+                .inspect(move |output| assert_eq!(*output, threshold.next().unwrap()));
+            input
+                .threshold_incremental(2)
+                .inspect(move |output| assert_eq!(*output, threshold_incremental.next().unwrap()));
+            input
+                .threshold_incremental(1)
+                .apply2(&input.distinct_incremental(), |threshold, distinct| {
+                    assert_eq!(threshold, distinct)
+                });
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
+
     #[test]
     fn distinct_incremental_nested_test() {
         let root = Root::build(move |circuit| {
@@ -695,4 +1128,95 @@ mod test {
             root.step().unwrap();
         }
     }
+
+    #[test]
+    fn distinct_trace_with_gc_test() {
+        let root = Root::build(move |circuit| {
+            let mut inputs = vec![
+                vec![zset! { 1 => 1, 2 => 1 }, zset! { 2 => -1, 3 => 2, 4 => 2 }],
+                vec![zset! { 2 => 1, 3 => 1 }, zset! { 3 => -2, 4 => -1 }],
+                vec![
+                    zset! { 5 => 1, 6 => 1 },
+                    zset! { 2 => -1, 7 => 1 },
+                    zset! { 2 => 1, 7 => -1, 8 => 2, 9 => 1 },
+                ],
+            ]
+            .into_iter();
+
+            circuit
+                .iterate(|child| {
+                    let counter = Rc::new(RefCell::new(0));
+                    let counter_clone = counter.clone();
+
+                    let input = child.add_source(GeneratorNested::new(Box::new(move || {
+                        *counter_clone.borrow_mut() = 0;
+                        let mut deltas = inputs.next().unwrap_or_else(Vec::new).into_iter();
+                        Box::new(move || deltas.next().unwrap_or_else(|| zset! {}))
+                    })));
+
+                    // Key 2 is deleted in the first epoch and reappears in the
+                    // second one, so a horizon of one epoch preserves its
+                    // history.  Key 7 is deleted in the third epoch and
+                    // collected at the end of the fourth one.
+                    input.distinct_trace_with_gc(1).apply2(
+                        &input.distinct_trace(),
+                        |d1: &OrdZSet<usize, isize>, d2: &OrdZSet<usize, isize>| assert_eq!(d1, d2),
+                    );
+
+                    Ok((
+                        move || {
+                            *counter.borrow_mut() += 1;
+                            *counter.borrow() == 4
+                        },
+                        (),
+                    ))
+                })
+                .unwrap();
+        })
+        .unwrap();
+
+        for _ in 0..5 {
+            root.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn distinct_gc_test() {
+        let touched = Rc::new(RefCell::new(BTreeSet::new()));
+        let mut gc = DistinctGc::new(2, touched.clone());
+        let mut trace = OrdKeySpine::<usize, NestedTimestamp32, isize>::new(None);
+
+        let time = |inner| NestedTimestamp32::new(false, inner);
+        let keys = |trace: &OrdKeySpine<usize, NestedTimestamp32, isize>| {
+            let mut cursor = trace.cursor();
+            let mut keys = Vec::new();
+            while cursor.key_valid(trace) {
+                keys.push(*cursor.key(trace));
+                cursor.step_key(trace);
+            }
+            keys
+        };
+
+        // Key 1 is inserted and deleted at different iterations, key 2 is
+        // inserted.
+        trace.insert(Rc::new(OrdKeyBatch::from_tuples(
+            time(0),
+            vec![((1, ()), 1), ((2, ()), 1)],
+        )));
+        trace.insert(Rc::new(OrdKeyBatch::from_tuples(
+            time(1),
+            vec![((1, ()), -1)],
+        )));
+        touched.borrow_mut().insert(1);
+
+        gc.collect(&mut trace);
+        assert_eq!(keys(&trace), vec![1, 2]);
+        gc.collect(&mut trace);
+        assert_eq!(keys(&trace), vec![1, 2]);
+
+        // Key 1 has been zero for two epochs.
+        gc.collect(&mut trace);
+        assert_eq!(keys(&trace), vec![2]);
+        assert!(gc.zero_since.is_empty());
+    }
 }
@@ -0,0 +1,129 @@
+//! Event-time extraction.
+//!
+//! Event time is the time when a real-world event described by a record
+//! occurred, as opposed to the logical time of the circuit when the record
+//! was processed (see [`Timestamp`](`crate::time::Timestamp`)).  Operators
+//! that depend on event time, e.g., windowing and as-of operators, obtain
+//! it uniformly through the [`WithTimestamp`] trait instead of each taking
+//! its own extractor closure.  Records that do not carry an explicit event
+//! time can be wrapped in [`Timestamped`] using
+//! [`Stream::assign_timestamps`].
+
+use crate::{
+    algebra::{MonoidValue, ZSet},
+    circuit::{Circuit, Stream},
+    trace::ord::OrdZSet,
+};
+use deepsize_derive::DeepSizeOf;
+
+/// A record that carries an event time.
+pub trait WithTimestamp {
+    /// Type of event time.
+    type Timestamp: Ord + Clone;
+
+    /// Event time of the record.
+    fn timestamp(&self) -> Self::Timestamp;
+}
+
+/// A record annotated with an event time.
+///
+/// Records are ordered by event time first, so that batches of timestamped
+/// records are sorted by time.
+#[derive(Clone, Debug, Default, DeepSizeOf, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamped<T, D> {
+    /// Event time.
+    pub timestamp: T,
+    /// The record.
+    pub data: D,
+}
+
+impl<T, D> Timestamped<T, D> {
+    /// Annotate `data` with event time `timestamp`.
+    pub const fn new(timestamp: T, data: D) -> Self {
+        Self { timestamp, data }
+    }
+}
+
+impl<T, D> WithTimestamp for Timestamped<T, D>
+where
+    T: Ord + Clone,
+{
+    type Timestamp = T;
+
+    fn timestamp(&self) -> T {
+        self.timestamp.clone()
+    }
+}
+
+/// Records represented as `(timestamp, data)` pairs.
+impl<T, D> WithTimestamp for (T, D)
+where
+    T: Ord + Clone,
+{
+    type Timestamp = T;
+
+    fn timestamp(&self) -> T {
+        self.0.clone()
+    }
+}
+
+impl<P, Z> Stream<Circuit<P>, Z>
+where
+    P: Clone + 'static,
+    Z: ZSet,
+    Z::Key: Ord + Clone,
+    Z::R: MonoidValue,
+{
+    /// Annotate each record in `self` with the event time computed by
+    /// `timestamp`, producing a Z-set of [`Timestamped`] records that
+    /// implement [`WithTimestamp`].
+    #[allow(clippy::type_complexity)]
+    pub fn assign_timestamps<T, F>(
+        &self,
+        timestamp: F,
+    ) -> Stream<Circuit<P>, OrdZSet<Timestamped<T, Z::Key>, Z::R>>
+    where
+        T: Ord + Clone + Send + 'static,
+        F: Fn(&Z::Key) -> T + Clone + 'static,
+    {
+        self.map_keys(move |record| Timestamped::new(timestamp(record), record.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Timestamped, WithTimestamp};
+    use crate::{circuit::Root, operator::Generator, trace::ord::OrdZSet, zset};
+
+    #[test]
+    fn assign_timestamps() {
+        let root = Root::build(move |circuit| {
+            circuit
+                .add_source(Generator::new(|| -> OrdZSet<(&'static str, u64), isize> {
+                    zset! { ("b", 20u64) => 1, ("a", 30u64) => 1, ("c", 10u64) => -1 }
+                }))
+                .assign_timestamps(|(_, time)| *time)
+                .inspect(
+                    |batch: &OrdZSet<Timestamped<u64, (&'static str, u64)>, isize>| {
+                        assert_eq!(
+                            batch,
+                            &zset! {
+                                Timestamped::new(10, ("c", 10)) => -1,
+                                Timestamped::new(20, ("b", 20)) => 1,
+                                Timestamped::new(30, ("a", 30)) => 1,
+                            }
+                        );
+                    },
+                );
+        })
+        .unwrap();
+
+        root.step().unwrap();
+    }
+
+    #[test]
+    fn with_timestamp() {
+        assert_eq!(Timestamped::new(5, "x").timestamp(), 5);
+        assert_eq!((7, "y").timestamp(), 7);
+    }
+}
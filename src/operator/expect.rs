@@ -0,0 +1,287 @@
+//! Sink operator that checks expectations about the contents of a stream.
+
+use crate::circuit::{
+    operator_traits::{Operator, SinkOperator},
+    Circuit, Stream,
+};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt::{self, Debug, Display},
+    marker::PhantomData,
+    rc::Rc,
+    thread::panicking,
+};
+
+impl<P, D> Stream<Circuit<P>, D>
+where
+    D: Clone + Debug + 'static,
+    P: Clone + 'static,
+{
+    /// Apply [`Expect`] operator to `self`.
+    ///
+    /// Checks the value in the stream at each step using the `check`
+    /// callback, which receives the value and the step number, i.e., the
+    /// number of values received by the operator before this one, and
+    /// returns `false` if the value does not satisfy the expectation.
+    /// Failed checks are recorded and reported when the returned
+    /// [`Expectation`] is checked or when the operator is dropped together
+    /// with the circuit, whichever comes first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use dbsp::{
+    /// #     circuit::Root,
+    /// #     operator::Generator,
+    /// # };
+    /// let mut expectation = None;
+    /// let root = Root::build(|circuit| {
+    ///     let mut n = 0;
+    ///     let stream = circuit.add_source(Generator::new(move || {
+    ///         n += 2;
+    ///         n
+    ///     }));
+    ///     expectation = Some(stream.expect("even", |n, _step| n % 2 == 0));
+    /// })
+    /// .unwrap();
+    ///
+    /// for _ in 0..10 {
+    ///     root.step().unwrap();
+    /// }
+    /// expectation.unwrap().check();
+    /// ```
+    pub fn expect<F>(&self, tag: &str, check: F) -> Expectation
+    where
+        F: FnMut(&D, usize) -> bool + 'static,
+    {
+        let expectation = Expectation::new();
+        self.circuit()
+            .add_sink(Expect::new(tag, check, expectation.clone()), self);
+        expectation
+    }
+}
+
+/// A failed check recorded by the [`Expect`] operator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpectationFailure {
+    /// Tag of the expectation.
+    pub tag: String,
+    /// Step at which the check failed.
+    pub step: usize,
+    /// Debug representation of the value that failed the check.
+    pub value: String,
+}
+
+impl Display for ExpectationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expectation '{}' failed at step {}: {}",
+            self.tag, self.step, self.value
+        )
+    }
+}
+
+#[derive(Default)]
+struct ExpectationInner {
+    failures: Vec<ExpectationFailure>,
+    // Number of failures that have been reported.
+    reported: usize,
+}
+
+/// Handle to failures recorded by an [`Expect`] operator.
+#[derive(Clone, Default)]
+pub struct Expectation(Rc<RefCell<ExpectationInner>>);
+
+impl Expectation {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Failures recorded so far.
+    ///
+    /// Failures retrieved this way are considered reported and do not cause
+    /// a panic when the operator is dropped.  Failures recorded afterwards
+    /// still do.
+    pub fn failures(&self) -> Vec<ExpectationFailure> {
+        let mut inner = self.0.borrow_mut();
+        inner.reported = inner.failures.len();
+        inner.failures.clone()
+    }
+
+    /// Report failures recorded so far.
+    ///
+    /// # Panics
+    ///
+    /// Panics listing all recorded failures if there are any.
+    pub fn check(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.reported = inner.failures.len();
+        if !inner.failures.is_empty() {
+            panic!("{}", format_failures(&inner.failures));
+        }
+    }
+}
+
+fn format_failures(failures: &[ExpectationFailure]) -> String {
+    let mut result = format!("{} expectation failure(s):", failures.len());
+    for failure in failures.iter() {
+        result.push_str(&format!("\n  {}", failure));
+    }
+    result
+}
+
+/// Sink operator that checks a user-provided predicate over each value in its
+/// input stream and records failures in an [`Expectation`].
+///
+/// Unlike assertions in an [`Inspect`](`crate::operator::Inspect`) callback,
+/// which abort the computation at the first failure, this operator collects
+/// all failures along with the step numbers and values that caused them.
+/// Failures that have not been reported via [`Expectation::check`] or
+/// [`Expectation::failures`] are reported by panicking when the operator is
+/// dropped.
+pub struct Expect<T, F> {
+    tag: String,
+    check: F,
+    step: usize,
+    expectation: Expectation,
+    phantom: PhantomData<T>,
+}
+
+impl<T, F> Expect<T, F>
+where
+    F: FnMut(&T, usize) -> bool,
+{
+    /// Create a new instance of the `Expect` operator that will apply `check`
+    /// to each value in the input stream and record failures in
+    /// `expectation`.
+    pub fn new(tag: &str, check: F, expectation: Expectation) -> Self {
+        Self {
+            tag: tag.to_string(),
+            check,
+            step: 0,
+            expectation,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, F> Operator for Expect<T, F>
+where
+    T: 'static,
+    F: FnMut(&T, usize) -> bool + 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from(format!("Expect({})", self.tag))
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<T, F> SinkOperator<T> for Expect<T, F>
+where
+    T: Debug + 'static,
+    F: FnMut(&T, usize) -> bool + 'static,
+{
+    fn eval(&mut self, i: &T) {
+        if !(self.check)(i, self.step) {
+            self.expectation
+                .0
+                .borrow_mut()
+                .failures
+                .push(ExpectationFailure {
+                    tag: self.tag.clone(),
+                    step: self.step,
+                    value: format!("{:?}", i),
+                });
+        }
+        self.step += 1;
+    }
+}
+
+impl<T, F> Drop for Expect<T, F> {
+    fn drop(&mut self) {
+        let inner = self.expectation.0.borrow();
+        if inner.failures.len() > inner.reported && !panicking() {
+            panic!("{}", format_failures(&inner.failures[inner.reported..]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{circuit::Root, operator::Generator};
+
+    #[test]
+    fn expect_test() {
+        let mut expectation = None;
+        let root = Root::build(|circuit| {
+            let mut n = 0;
+            let stream = circuit.add_source(Generator::new(move || {
+                n += 1;
+                n
+            }));
+            expectation = Some(stream.expect("small", |n, _step| *n <= 3));
+        })
+        .unwrap();
+
+        for _ in 0..5 {
+            root.step().unwrap();
+        }
+
+        let failures = expectation.as_ref().unwrap().failures();
+        assert_eq!(
+            failures
+                .iter()
+                .map(|failure| failure.step)
+                .collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        assert!(failures.iter().all(|failure| failure.tag == "small"));
+    }
+
+    #[test]
+    #[should_panic(expected = "expectation 'positive' failed at step 1")]
+    fn expect_report_on_drop() {
+        let root = Root::build(|circuit| {
+            let mut n = 1;
+            circuit
+                .add_source(Generator::new(move || {
+                    n -= 1;
+                    n
+                }))
+                .expect("positive", |n, _step| *n >= 0);
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "1 expectation failure(s):\n  expectation 'small' failed at step 4")]
+    fn expect_report_new_failures_on_drop() {
+        let mut expectation = None;
+        let root = Root::build(|circuit| {
+            let mut n = 0;
+            let stream = circuit.add_source(Generator::new(move || {
+                n += 1;
+                n
+            }));
+            expectation = Some(stream.expect("small", |n, _step| *n <= 3));
+        })
+        .unwrap();
+
+        for _ in 0..4 {
+            root.step().unwrap();
+        }
+        assert_eq!(expectation.as_ref().unwrap().failures().len(), 1);
+
+        // Failures recorded after the previous report are reported on drop.
+        root.step().unwrap();
+    }
+}
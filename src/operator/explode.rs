@@ -0,0 +1,280 @@
+//! Operators that convert weights into explicit data.
+
+use std::{borrow::Cow, convert::TryInto, marker::PhantomData, ops::Neg};
+
+use crate::{
+    algebra::{AddByRef, HasOne, HasZero, ZRingValue, ZSet},
+    circuit::{
+        operator_traits::{BinaryOperator, Operator},
+        Circuit, Stream,
+    },
+    trace::{cursor::Cursor, ord::OrdZSet, Batch, BatchReader},
+};
+use deepsize::DeepSizeOf;
+
+impl<P, Z> Stream<Circuit<P>, Z>
+where
+    P: Clone + 'static,
+    Z: Clone + 'static,
+{
+    /// Convert multiplicities into explicit copies.
+    ///
+    /// Values in the input stream are changes to a Z-set.  The output stream
+    /// contains changes to a set (a Z-set with all weights equal to 1) that
+    /// contains an element `(key, i)` for each `i` in `0..w`, where `w` is the
+    /// weight of `key` in the integral of the input stream.  Keys with
+    /// non-positive weights have no copies.
+    ///
+    /// Copies are numbered consistently across clock cycles: when the weight
+    /// of a key decreases, the copies with the largest indexes are retracted.
+    /// The number of copies of each key is capped at `max_copies` to bound the
+    /// size of the output.
+    #[allow(clippy::type_complexity)]
+    pub fn explode(&self, max_copies: usize) -> Stream<Circuit<P>, OrdZSet<(Z::Key, usize), Z::R>>
+    where
+        Z: ZSet + DeepSizeOf,
+        Z::Key: Clone + Ord,
+        Z::R: ZRingValue + TryInto<usize>,
+    {
+        self.circuit().add_binary_operator(
+            Explode::new(max_copies),
+            self,
+            &self.integrate_trace().delay_trace(),
+        )
+    }
+
+    /// Attach multiplicities to keys as a value column.
+    ///
+    /// Values in the input stream are changes to a Z-set.  The output stream
+    /// contains changes to a set that contains an element `(key, w)` for each
+    /// `key` with non-zero weight `w` in the integral of the input stream.
+    /// When the weight of a key changes, the old element is retracted and the
+    /// new one is inserted.
+    #[allow(clippy::type_complexity)]
+    pub fn with_multiplicity(&self) -> Stream<Circuit<P>, OrdZSet<(Z::Key, Z::R), Z::R>>
+    where
+        Z: ZSet + DeepSizeOf,
+        Z::Key: Clone + Ord,
+        Z::R: ZRingValue + Ord,
+    {
+        self.circuit().add_binary_operator(
+            WithMultiplicity::new(),
+            self,
+            &self.integrate_trace().delay_trace(),
+        )
+    }
+}
+
+// Invokes `f` with the old and the new weight of each key in `delta`, given
+// the delayed integral of the stream of deltas.
+fn for_each_update<Z, I, F>(delta: &Z, delayed_integral: &I, mut f: F)
+where
+    Z: ZSet,
+    Z::Key: PartialEq,
+    I: BatchReader<Key = Z::Key, Val = (), Time = (), R = Z::R>,
+    F: FnMut(&Z::Key, Z::R, Z::R),
+{
+    let mut delta_cursor = delta.cursor();
+    let mut integral_cursor = delayed_integral.cursor();
+
+    while delta_cursor.key_valid(delta) {
+        let key = delta_cursor.key(delta);
+        let weight = delta_cursor.weight(delta);
+
+        integral_cursor.seek_key(delayed_integral, key);
+        let old = if integral_cursor.key_valid(delayed_integral)
+            && integral_cursor.key(delayed_integral) == key
+            && integral_cursor.val_valid(delayed_integral)
+        {
+            integral_cursor.weight(delayed_integral)
+        } else {
+            HasZero::zero()
+        };
+        let new = old.add_by_ref(&weight);
+
+        f(key, old, new);
+        delta_cursor.step_key(delta);
+    }
+}
+
+/// Operator that converts multiplicities into explicit copies.
+///
+/// See [`Stream::explode`].
+pub struct Explode<Z, I> {
+    max_copies: usize,
+    _type: PhantomData<(Z, I)>,
+}
+
+impl<Z, I> Explode<Z, I> {
+    pub fn new(max_copies: usize) -> Self {
+        Self {
+            max_copies,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<Z, I> Operator for Explode<Z, I>
+where
+    Z: 'static,
+    I: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("Explode")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<Z, I> BinaryOperator<Z, I, OrdZSet<(Z::Key, usize), Z::R>> for Explode<Z, I>
+where
+    Z: ZSet + 'static,
+    Z::Key: Clone + Ord,
+    Z::R: ZRingValue + TryInto<usize>,
+    I: BatchReader<Key = Z::Key, Val = (), Time = (), R = Z::R> + 'static,
+{
+    fn eval(&mut self, delta: &Z, delayed_integral: &I) -> OrdZSet<(Z::Key, usize), Z::R> {
+        let max_copies = self.max_copies;
+        let copies = |weight: Z::R| -> usize {
+            if weight.ge0() {
+                weight.try_into().unwrap_or(max_copies).min(max_copies)
+            } else {
+                0
+            }
+        };
+
+        let mut result = Vec::with_capacity(delta.len());
+
+        for_each_update(delta, delayed_integral, |key, old, new| {
+            let old = copies(old);
+            let new = copies(new);
+
+            if new > old {
+                for i in old..new {
+                    result.push((((key.clone(), i), ()), Z::R::one()));
+                }
+            } else {
+                for i in new..old {
+                    result.push((((key.clone(), i), ()), Z::R::one().neg()));
+                }
+            }
+        });
+
+        OrdZSet::from_tuples((), result)
+    }
+}
+
+/// Operator that attaches multiplicities to keys as a value column.
+///
+/// See [`Stream::with_multiplicity`].
+pub struct WithMultiplicity<Z, I> {
+    _type: PhantomData<(Z, I)>,
+}
+
+impl<Z, I> WithMultiplicity<Z, I> {
+    pub fn new() -> Self {
+        Self { _type: PhantomData }
+    }
+}
+
+impl<Z, I> Default for WithMultiplicity<Z, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Z, I> Operator for WithMultiplicity<Z, I>
+where
+    Z: 'static,
+    I: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("WithMultiplicity")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<Z, I> BinaryOperator<Z, I, OrdZSet<(Z::Key, Z::R), Z::R>> for WithMultiplicity<Z, I>
+where
+    Z: ZSet + 'static,
+    Z::Key: Clone + Ord,
+    Z::R: ZRingValue + Ord,
+    I: BatchReader<Key = Z::Key, Val = (), Time = (), R = Z::R> + 'static,
+{
+    fn eval(&mut self, delta: &Z, delayed_integral: &I) -> OrdZSet<(Z::Key, Z::R), Z::R> {
+        let mut result = Vec::with_capacity(2 * delta.len());
+
+        for_each_update(delta, delayed_integral, |key, old, new| {
+            if old == new {
+                return;
+            }
+            if !old.is_zero() {
+                result.push((((key.clone(), old), ()), Z::R::one().neg()));
+            }
+            if !new.is_zero() {
+                result.push((((key.clone(), new), ()), Z::R::one()));
+            }
+        });
+
+        OrdZSet::from_tuples((), result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::vec;
+
+    use crate::{
+        circuit::{Root, Stream},
+        operator::Generator,
+        trace::ord::OrdZSet,
+        zset,
+    };
+
+    #[test]
+    fn explode_test() {
+        let root = Root::build(move |circuit| {
+            let mut inputs: vec::IntoIter<OrdZSet<usize, isize>> = vec![
+                zset! { 1 => 3, 2 => 1 },
+                zset! { 1 => -2, 3 => 2 },
+                zset! { 2 => -1, 3 => 5 },
+            ]
+            .into_iter();
+
+            let mut copies: vec::IntoIter<OrdZSet<(usize, usize), isize>> = vec![
+                zset! { (1, 0) => 1, (1, 1) => 1, (1, 2) => 1, (2, 0) => 1 },
+                zset! { (1, 0) => 1, (2, 0) => 1, (3, 0) => 1, (3, 1) => 1 },
+                zset! { (1, 0) => 1, (3, 0) => 1, (3, 1) => 1, (3, 2) => 1, (3, 3) => 1 },
+            ]
+            .into_iter();
+
+            let mut multiplicities: vec::IntoIter<OrdZSet<(usize, isize), isize>> = vec![
+                zset! { (1, 3) => 1, (2, 1) => 1 },
+                zset! { (1, 1) => 1, (2, 1) => 1, (3, 2) => 1 },
+                zset! { (1, 1) => 1, (3, 7) => 1 },
+            ]
+            .into_iter();
+
+            let input: Stream<_, OrdZSet<usize, isize>> =
+                circuit.add_source(Generator::new(move || inputs.next().unwrap()));
+
+            input
+                .explode(4)
+                .integrate()
+                .inspect(move |zs| assert_eq!(*zs, copies.next().unwrap()));
+
+            input
+                .with_multiplicity()
+                .integrate()
+                .inspect(move |zs| assert_eq!(*zs, multiplicities.next().unwrap()));
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! Filtering operators.
 
 use crate::{
+    algebra::{HasZero, ZRingValue},
     circuit::{
         operator_traits::{Operator, UnaryOperator},
         Circuit, Stream,
@@ -25,6 +26,46 @@ where
         self.circuit()
             .add_unary_operator(FilterKeys::new(func), self)
     }
+
+    /// Apply [`FilterWeights`] operator to `self`.
+    pub fn filter_weights<CO, F>(&self, func: F) -> Stream<Circuit<P>, CO>
+    where
+        CO: Batch<Key = CI::Key, Val = CI::Val, Time = (), R = CI::R> + Clone + 'static,
+        F: Fn(&CI::R) -> bool + 'static,
+    {
+        self.circuit()
+            .add_unary_operator(FilterWeights::new(func), self)
+    }
+
+    /// Retain only tuples with positive weights.
+    ///
+    /// This is the positive part of each batch in the stream, i.e.,
+    /// `self.filter_weights(|w| w.ge0() && !w.is_zero())`.  Together with
+    /// [`Self::filter_weights`] it expresses threshold semantics, e.g.,
+    /// "support ≥ k".  Note that the operator is not linear and must be
+    /// applied to whole collections (e.g., the output of
+    /// [`Stream::integrate`]) rather than to streams of changes.
+    pub fn positive<CO>(&self) -> Stream<Circuit<P>, CO>
+    where
+        CI::R: ZRingValue,
+        CO: Batch<Key = CI::Key, Val = CI::Val, Time = (), R = CI::R> + Clone + 'static,
+    {
+        self.filter_weights(|w: &CI::R| w.ge0() && !w.is_zero())
+    }
+
+    /// Apply [`FilterKeysInPlace`] operator to `self`.
+    ///
+    /// Like [`Self::filter_keys`], but the output batch has the same type as
+    /// the input batch, which allows the operator to filter input batches
+    /// that it owns in place instead of building new batches.
+    pub fn filter_in_place<F>(&self, func: F) -> Stream<Circuit<P>, CI>
+    where
+        CI: Batch,
+        F: Fn(&CI::Key) -> bool + 'static,
+    {
+        self.circuit()
+            .add_unary_operator(FilterKeysInPlace::new(func), self)
+    }
 }
 
 /// Operator that filters a collection of key/value pairs based on keys.
@@ -91,7 +132,7 @@ where
         // This is probably ok, because the batch will either get freed at the end
         // of the current clock tick or get added to the trace, where it will likely
         // get merged with other batches soon, at which point the waste is gone.
-        let mut builder = CO::Builder::with_capacity((), i.len());
+        let mut builder = CO::Builder::with_size_hint((), i.size_hint());
 
         while cursor.key_valid(i) {
             let k = cursor.key(i);
@@ -113,3 +154,197 @@ where
         self.eval(&i)
     }
 }
+
+/// Operator that filters a batch of key/value pairs based on keys,
+/// producing a batch of the same type.
+///
+/// Filtering preserves the order of updates, so when the operator owns its
+/// input batch, it removes the elements that do not satisfy the filter
+/// condition in place (see [`Batch::try_retain`]).  Otherwise, it builds a
+/// new batch like [`FilterKeys`].
+///
+/// # Type arguments
+///
+/// * `B` - batch type.
+/// * `F` - filtering function type.
+pub struct FilterKeysInPlace<B, F>
+where
+    F: 'static,
+{
+    filter: FilterKeys<B, B, F>,
+}
+
+impl<B, F> FilterKeysInPlace<B, F>
+where
+    F: 'static,
+{
+    pub fn new(filter: F) -> Self {
+        Self {
+            filter: FilterKeys::new(filter),
+        }
+    }
+}
+
+impl<B, F> Operator for FilterKeysInPlace<B, F>
+where
+    B: 'static,
+    F: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("FilterKeysInPlace")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<B, F> UnaryOperator<B, B> for FilterKeysInPlace<B, F>
+where
+    B: Batch<Time = ()> + 'static,
+    B::Key: Clone,
+    B::Val: Clone,
+    F: Fn(&B::Key) -> bool + 'static,
+{
+    fn idle_output(&mut self) -> Option<B> {
+        Some(B::empty(()))
+    }
+
+    fn eval(&mut self, i: &B) -> B {
+        self.filter.eval(i)
+    }
+
+    fn eval_owned(&mut self, mut i: B) -> B {
+        let filter = &self.filter.filter;
+        if i.try_retain(&|k, _| filter(k)) {
+            i
+        } else {
+            self.filter.eval(&i)
+        }
+    }
+}
+
+/// Operator that filters a collection of key/value pairs based on weights.
+///
+/// The operator applies a filtering function to the weight of each key/value
+/// pair in the input batch and builds an output batch containing only the
+/// elements whose weights satisfy the filter condition.
+///
+/// # Type arguments
+///
+/// * `CI` - input collection type.
+/// * `CO` - output collection type.
+/// * `F` - filtering function type.
+pub struct FilterWeights<CI, CO, F>
+where
+    F: 'static,
+{
+    filter: F,
+    _type: PhantomData<(CI, CO)>,
+}
+
+impl<CI, CO, F> FilterWeights<CI, CO, F>
+where
+    F: 'static,
+{
+    pub fn new(filter: F) -> Self {
+        Self {
+            filter,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<CI, CO, F> Operator for FilterWeights<CI, CO, F>
+where
+    CI: 'static,
+    CO: 'static,
+    F: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("FilterWeights")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<CI, CO, F> UnaryOperator<CI, CO> for FilterWeights<CI, CO, F>
+where
+    CI: BatchReader<Time = ()> + 'static,
+    CI::Key: Clone,
+    CI::Val: Clone,
+    CO: Batch<Key = CI::Key, Val = CI::Val, Time = (), R = CI::R> + 'static,
+    F: Fn(&CI::R) -> bool + 'static,
+{
+    fn eval(&mut self, i: &CI) -> CO {
+        let mut cursor = i.cursor();
+        let mut builder = CO::Builder::with_size_hint((), i.size_hint());
+
+        while cursor.key_valid(i) {
+            while cursor.val_valid(i) {
+                let w = cursor.weight(i);
+                if (self.filter)(&w) {
+                    builder.push((cursor.key(i).clone(), cursor.val(i).clone(), w));
+                }
+                cursor.step_val(i);
+            }
+            cursor.step_key(i);
+        }
+        builder.done()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        circuit::{Root, Stream},
+        operator::Generator,
+        trace::{ord::OrdZSet, BatchReader},
+        zset,
+    };
+
+    #[test]
+    fn filter_weights_test() {
+        let root = Root::build(move |circuit| {
+            let input: Stream<_, OrdZSet<usize, isize>> =
+                circuit.add_source(Generator::new(|| zset! { 1 => -2, 2 => 1, 3 => 2, 4 => 3 }));
+
+            input
+                .filter_weights(|w| *w >= 2)
+                .inspect(|zs: &OrdZSet<usize, isize>| assert_eq!(*zs, zset! { 3 => 2, 4 => 3 }));
+            input.positive().inspect(|zs: &OrdZSet<usize, isize>| {
+                assert_eq!(*zs, zset! { 2 => 1, 3 => 2, 4 => 3 })
+            });
+        })
+        .unwrap();
+
+        root.step().unwrap();
+    }
+
+    #[test]
+    fn filter_in_place_test() {
+        let root = Root::build(move |circuit| {
+            let owned: Stream<_, OrdZSet<usize, isize>> =
+                circuit.add_source(Generator::new(|| zset! { 1 => 1, 2 => 1, 3 => -1, 4 => 1 }));
+
+            // `filter_in_place` is the only consumer of `owned`, so it
+            // receives owned batches and filters them in place.
+            owned
+                .filter_in_place(|k| k % 2 == 0)
+                .inspect(|zs| assert_eq!(*zs, zset! { 2 => 1, 4 => 1 }));
+
+            // A shared input is filtered by reference.
+            let shared: Stream<_, OrdZSet<usize, isize>> =
+                circuit.add_source(Generator::new(|| zset! { 1 => 1, 2 => 1, 3 => -1, 4 => 1 }));
+            shared
+                .filter_in_place(|k| k % 2 == 1)
+                .inspect(|zs| assert_eq!(*zs, zset! { 1 => 1, 3 => -1 }));
+            shared.inspect(|zs| assert_eq!(zs.len(), 4));
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
+}
@@ -105,6 +105,10 @@ where
     FB: Fn(&CI::Key) -> Option<CO::Key> + 'static,
     FO: Fn(CI::Key) -> Option<CO::Key> + 'static,
 {
+    fn idle_output(&mut self) -> Option<CO> {
+        Some(CO::empty(()))
+    }
+
     fn eval(&mut self, i: &CI) -> CO {
         let mut cursor = i.cursor();
         let mut batch = Vec::with_capacity(i.len());
@@ -0,0 +1,253 @@
+//! Generalized group transformer operator.
+
+use std::{borrow::Cow, marker::PhantomData, ops::Neg, rc::Rc};
+
+use crate::{
+    algebra::{HasZero, IndexedZSet, ZRingValue},
+    circuit::{
+        operator_traits::{BinaryOperator, Operator},
+        Circuit, Stream,
+    },
+    trace::{cursor::Cursor, spine_fueled::Spine, BatchReader},
+};
+use deepsize::DeepSizeOf;
+
+impl<P, Z> Stream<Circuit<P>, Z>
+where
+    P: Clone + 'static,
+    Z: Clone + 'static,
+{
+    /// Transform each group of values in the input stream.
+    ///
+    /// Values in the input stream are changes to an
+    /// [indexed Z-set](`crate::algebra::IndexedZSet`).  For every key
+    /// modified by the current change, the transformer `f` is invoked with
+    /// the key and a [`GroupCursor`] over all values currently associated with
+    /// the key (i.e., values in the integral of the input stream with non-zero
+    /// weights), and pushes an arbitrary set of `(value, weight)` pairs to
+    /// its output vector.  The output of the operator is a stream of changes
+    /// to the indexed Z-set that maps each key to the output of `f` for
+    /// this key.  The transformer is not invoked for keys without values.
+    ///
+    /// This is the general-purpose version of
+    /// [`aggregate_incremental`](`Self::aggregate_incremental`) that can
+    /// express, e.g., top-k or grouped ranking queries.
+    pub fn group_transform<F, O>(&self, f: F) -> Stream<Circuit<P>, O>
+    where
+        Z: IndexedZSet + DeepSizeOf,
+        Z::Key: Clone + Ord,
+        Z::Val: Ord,
+        F: Fn(&Z::Key, &mut GroupCursor<'_, Spine<Rc<Z>>>, &mut Vec<(O::Val, O::R)>)
+            + Clone
+            + 'static,
+        O: Clone + IndexedZSet<Key = Z::Key> + 'static,
+        O::R: ZRingValue,
+    {
+        // Retract old outputs for affected keys.
+        let retract_old = self.circuit().add_binary_operator(
+            GroupTransform::new(false, f.clone()),
+            self,
+            &self.integrate_trace().delay_trace(),
+        );
+
+        // Insert new outputs.
+        let insert_new = self.circuit().add_binary_operator(
+            GroupTransform::new(true, f),
+            self,
+            &self.integrate_trace(),
+        );
+
+        retract_old.plus(&insert_new)
+    }
+}
+
+/// Cursor over the values associated with a single key, passed to the
+/// transformer function of [`Stream::group_transform`].
+///
+/// Values are visited in ascending order.  Values whose weights add up to zero
+/// are skipped.
+pub struct GroupCursor<'s, T>
+where
+    T: BatchReader,
+{
+    cursor: &'s mut T::Cursor,
+    storage: &'s T,
+}
+
+impl<'s, T> GroupCursor<'s, T>
+where
+    T: BatchReader<Time = ()>,
+{
+    fn new(cursor: &'s mut T::Cursor, storage: &'s T) -> Self {
+        let mut result = Self { cursor, storage };
+        result.skip_zeros();
+        result
+    }
+
+    fn skip_zeros(&mut self) {
+        while self.cursor.val_valid(self.storage) && self.cursor.weight(self.storage).is_zero() {
+            self.cursor.step_val(self.storage);
+        }
+    }
+
+    /// Returns `false` once all values of the key have been visited.
+    pub fn val_valid(&self) -> bool {
+        self.cursor.val_valid(self.storage)
+    }
+
+    /// Returns the current value.
+    pub fn val(&self) -> &'s T::Val {
+        self.cursor.val(self.storage)
+    }
+
+    /// Returns the weight of the current value.
+    pub fn weight(&mut self) -> T::R {
+        self.cursor.weight(self.storage)
+    }
+
+    /// Advances the cursor to the next value.
+    pub fn step_val(&mut self) {
+        self.cursor.step_val(self.storage);
+        self.skip_zeros();
+    }
+
+    /// Rewinds the cursor to the first value of the key.
+    pub fn rewind_vals(&mut self) {
+        self.cursor.rewind_vals(self.storage);
+        self.skip_zeros();
+    }
+}
+
+/// Incremental group transformer operator.
+///
+/// Takes a stream `a` of changes to relation `A` and a stream with either the
+/// integral of `a` or its delayed value and evaluates the transformer function
+/// for all keys in the support of `a`.  Outputs of the transformer are
+/// inserted or retracted depending on `polarity`.
+pub struct GroupTransform<Z, I, F, O> {
+    polarity: bool,
+    transform: F,
+    _type: PhantomData<(Z, I, O)>,
+}
+
+impl<Z, I, F, O> GroupTransform<Z, I, F, O> {
+    pub fn new(polarity: bool, transform: F) -> Self {
+        Self {
+            polarity,
+            transform,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<Z, I, F, O> Operator for GroupTransform<Z, I, F, O>
+where
+    Z: 'static,
+    I: 'static,
+    F: 'static,
+    O: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("GroupTransform")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<Z, I, F, O> BinaryOperator<Z, I, O> for GroupTransform<Z, I, F, O>
+where
+    Z: IndexedZSet + 'static,
+    Z::Key: Clone + PartialEq,
+    I: BatchReader<Key = Z::Key, Val = Z::Val, Time = (), R = Z::R> + 'static,
+    F: Fn(&Z::Key, &mut GroupCursor<'_, I>, &mut Vec<(O::Val, O::R)>) + 'static,
+    O: IndexedZSet<Key = Z::Key> + 'static,
+    O::R: ZRingValue,
+{
+    fn eval(&mut self, delta: &Z, integral: &I) -> O {
+        let mut result = Vec::with_capacity(delta.len());
+        let mut outputs = Vec::new();
+
+        let mut delta_cursor = delta.cursor();
+        let mut integral_cursor = integral.cursor();
+
+        while delta_cursor.key_valid(delta) {
+            let key = delta_cursor.key(delta);
+
+            integral_cursor.seek_key(integral, key);
+
+            if integral_cursor.key_valid(integral) && integral_cursor.key(integral) == key {
+                let mut group = GroupCursor::new(&mut integral_cursor, integral);
+
+                // Skip keys that only contain values with weight 0.
+                if group.val_valid() {
+                    (self.transform)(key, &mut group, &mut outputs);
+                }
+
+                for (val, weight) in outputs.drain(..) {
+                    let weight = if self.polarity { weight } else { weight.neg() };
+                    result.push(((key.clone(), val), weight));
+                }
+            }
+            delta_cursor.step_key(delta);
+        }
+
+        O::from_tuples((), result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::vec;
+
+    use crate::{
+        circuit::{Root, Stream},
+        indexed_zset,
+        operator::Generator,
+        trace::ord::{OrdIndexedZSet, OrdZSet},
+        zset,
+    };
+
+    #[test]
+    fn group_transform_test() {
+        let root = Root::build(move |circuit| {
+            let mut inputs: vec::IntoIter<OrdZSet<(usize, usize), isize>> = vec![
+                zset! { (1, 10) => 1, (1, 20) => 1, (1, 30) => 1, (2, 5) => 1 },
+                zset! { (1, 40) => 1, (2, 5) => -1 },
+                zset! { (1, 40) => -1, (1, 30) => -1 },
+            ]
+            .into_iter();
+
+            // Two largest values for each key.
+            let mut outputs: vec::IntoIter<OrdIndexedZSet<usize, usize, isize>> = vec![
+                indexed_zset! { 1 => { 20 => 1, 30 => 1 }, 2 => { 5 => 1 } },
+                indexed_zset! { 1 => { 30 => 1, 40 => 1 } },
+                indexed_zset! { 1 => { 10 => 1, 20 => 1 } },
+            ]
+            .into_iter();
+
+            let input: Stream<_, OrdIndexedZSet<usize, usize, isize>> = circuit
+                .add_source(Generator::new(move || inputs.next().unwrap()))
+                .index();
+
+            input
+                .group_transform::<_, OrdIndexedZSet<usize, usize, isize>>(|_key, group, output| {
+                    let mut vals = Vec::new();
+                    while group.val_valid() {
+                        vals.push(*group.val());
+                        group.step_val();
+                    }
+                    for val in vals.into_iter().rev().take(2) {
+                        output.push((val, 1));
+                    }
+                })
+                .integrate()
+                .inspect(move |top2| assert_eq!(*top2, outputs.next().unwrap()));
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Histogram aggregate.
+
+use crate::{
+    algebra::{ZRingValue, ZSet},
+    circuit::{Circuit, Stream},
+    trace::ord::OrdZSet,
+};
+use deepsize::DeepSizeOf;
+use num::ToPrimitive;
+
+/// Bucketing scheme of a histogram.
+///
+/// Buckets are identified by signed integer indexes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Buckets {
+    /// Buckets of equal `width`: bucket `i` contains values in
+    /// `[start + i * width, start + (i + 1) * width)`.  Values below `start`
+    /// fall into buckets with negative indexes.
+    Fixed { start: f64, width: f64 },
+    /// Exponentially growing buckets, e.g., for latencies: bucket `i >= 0`
+    /// contains values in `[start * factor^i, start * factor^(i + 1))`.  All
+    /// values below `start` fall into bucket `-1`.
+    Exponential { start: f64, factor: f64 },
+}
+
+impl Buckets {
+    /// Buckets of equal `width` (see [`Buckets::Fixed`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is not positive.
+    pub fn fixed(start: f64, width: f64) -> Self {
+        let buckets = Self::Fixed { start, width };
+        buckets.validate();
+        buckets
+    }
+
+    /// Exponentially growing buckets (see [`Buckets::Exponential`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` is not positive or `factor` is not greater than 1.
+    pub fn exponential(start: f64, factor: f64) -> Self {
+        let buckets = Self::Exponential { start, factor };
+        buckets.validate();
+        buckets
+    }
+
+    // Panics if the parameters do not describe a valid bucketing scheme.
+    fn validate(&self) {
+        match *self {
+            Self::Fixed { width, .. } => {
+                assert!(width > 0.0, "bucket width must be positive, got {}", width)
+            }
+            Self::Exponential { start, factor } => {
+                assert!(
+                    start > 0.0,
+                    "exponential buckets must start at a positive value, got {}",
+                    start
+                );
+                assert!(
+                    factor > 1.0,
+                    "exponential bucket factor must be greater than 1, got {}",
+                    factor
+                );
+            }
+        }
+    }
+
+    /// Index of the bucket that contains `value`.
+    ///
+    /// Indexes of non-finite values saturate at the `i64` range (`NaN` maps
+    /// to bucket 0 for fixed buckets and -1 for exponential buckets).
+    pub fn bucket(&self, value: f64) -> i64 {
+        match *self {
+            Self::Fixed { start, width } => ((value - start) / width).floor() as i64,
+            Self::Exponential { start, factor } => {
+                if value >= start {
+                    let bucket = (value / start).log(factor).floor() as i64;
+                    // Correct rounding errors of the logarithm at bucket
+                    // boundaries.
+                    if !value.is_finite() {
+                        bucket
+                    } else if self.lower_bound(bucket) > value {
+                        bucket - 1
+                    } else if self.lower_bound(bucket + 1) <= value {
+                        bucket + 1
+                    } else {
+                        bucket
+                    }
+                } else {
+                    -1
+                }
+            }
+        }
+    }
+
+    /// Inclusive lower bound of the values in `bucket`.
+    pub fn lower_bound(&self, bucket: i64) -> f64 {
+        match *self {
+            Self::Fixed { start, width } => start + bucket as f64 * width,
+            Self::Exponential { start, factor } => {
+                if bucket >= 0 {
+                    start * factor.powi(bucket as i32)
+                } else {
+                    f64::NEG_INFINITY
+                }
+            }
+        }
+    }
+}
+
+impl<P, Z> Stream<Circuit<P>, Z>
+where
+    P: Clone + 'static,
+    Z: Clone + 'static,
+{
+    /// Maintain per-key histograms over a numeric value column.
+    ///
+    /// Values in the input stream are changes to a Z-set of `(key, value)`
+    /// pairs.  The output stream contains changes to a set of
+    /// `(key, bucket, count)` triples, where `count` is the total weight of
+    /// the pairs of `key` whose value falls into `bucket` (see
+    /// [`Buckets::bucket`]).  Empty buckets are omitted.  When the count of
+    /// a bucket changes, the old triple is retracted and the new one is
+    /// inserted, so the output can directly feed an incrementally updated
+    /// dashboard, e.g., of latency distributions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buckets` is not a valid bucketing scheme (see
+    /// [`Buckets::fixed`] and [`Buckets::exponential`]).
+    #[allow(clippy::type_complexity)]
+    pub fn histogram<K, V>(
+        &self,
+        buckets: Buckets,
+    ) -> Stream<Circuit<P>, OrdZSet<(K, i64, Z::R), Z::R>>
+    where
+        Z: ZSet<Key = (K, V)>,
+        K: Clone + Ord + DeepSizeOf + Send + 'static,
+        V: ToPrimitive,
+        Z::R: ZRingValue + Ord + DeepSizeOf,
+    {
+        buckets.validate();
+
+        self.map_keys::<OrdZSet<(K, i64), Z::R>, _>(move |(key, value)| {
+            (
+                key.clone(),
+                buckets.bucket(value.to_f64().unwrap_or(f64::NAN)),
+            )
+        })
+        .with_multiplicity()
+        .map_keys::<OrdZSet<(K, i64, Z::R), Z::R>, _>(|((key, bucket), count)| {
+            (key.clone(), *bucket, count.clone())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Buckets;
+    use crate::{circuit::Root, operator::Generator, trace::ord::OrdZSet, zset};
+    use std::vec;
+
+    #[test]
+    fn buckets_test() {
+        let fixed = Buckets::fixed(0.0, 10.0);
+        assert_eq!(fixed.bucket(0.0), 0);
+        assert_eq!(fixed.bucket(9.9), 0);
+        assert_eq!(fixed.bucket(25.0), 2);
+        assert_eq!(fixed.bucket(-0.5), -1);
+        assert_eq!(fixed.lower_bound(2), 20.0);
+
+        let exponential = Buckets::exponential(1.0, 2.0);
+        assert_eq!(exponential.bucket(1.0), 0);
+        assert_eq!(exponential.bucket(3.0), 1);
+        assert_eq!(exponential.bucket(1000.0), 9);
+        assert_eq!(exponential.bucket(8.0), 3);
+        assert_eq!(exponential.bucket(0.5), -1);
+        assert_eq!(exponential.lower_bound(3), 8.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "exponential bucket factor must be greater than 1")]
+    fn exponential_factor() {
+        Buckets::exponential(1.0, 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "exponential buckets must start at a positive value")]
+    fn exponential_start() {
+        Buckets::exponential(0.0, 2.0);
+    }
+
+    #[test]
+    fn histogram_test() {
+        let root = Root::build(move |circuit| {
+            let mut input: vec::IntoIter<OrdZSet<(&'static str, u64), isize>> = vec![
+                zset! { ("a", 1) => 1, ("a", 3) => 1, ("a", 2) => 1, ("b", 100) => 2 },
+                zset! { ("a", 3) => -1, ("a", 5) => 1, ("b", 100) => -2 },
+            ]
+            .into_iter();
+
+            let mut expected: vec::IntoIter<OrdZSet<(&'static str, i64, isize), isize>> = vec![
+                zset! { ("a", 0, 1) => 1, ("a", 1, 2) => 1, ("b", 6, 2) => 1 },
+                zset! {
+                    ("a", 1, 2) => -1,
+                    ("a", 1, 1) => 1,
+                    ("a", 2, 1) => 1,
+                    ("b", 6, 2) => -1,
+                },
+            ]
+            .into_iter();
+
+            circuit
+                .add_source(Generator::new(move || input.next().unwrap()))
+                .histogram(Buckets::Exponential {
+                    start: 1.0,
+                    factor: 2.0,
+                })
+                .inspect(move |histogram| assert_eq!(*histogram, expected.next().unwrap()));
+        })
+        .unwrap();
+
+        for _ in 0..2 {
+            root.step().unwrap();
+        }
+    }
+}
@@ -94,6 +94,10 @@ where
     CO::Key: Clone,
     CO::Val: Clone,
 {
+    fn idle_output(&mut self) -> Option<CO> {
+        Some(CO::empty(()))
+    }
+
     fn eval(&mut self, i: &CI) -> CO {
         let mut builder = <CO as Batch>::Builder::with_capacity((), i.len());
 
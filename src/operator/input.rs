@@ -0,0 +1,303 @@
+//! Input handles for feeding data into a circuit from outside.
+//!
+//! [`Circuit::add_input`] creates a source stream along with an
+//! [`InputHandle`] that the client uses to push updates into it.  Updates
+//! pushed between two clock cycles are assembled into a single batch that
+//! the stream yields at the next clock cycle.
+//!
+//! [`InputTransaction`] groups updates to several input handles so that
+//! they become visible to the circuit together.  This is needed, e.g., to
+//! ingest a multi-table database transaction consistently: either all of
+//! its updates are observed in the same clock cycle or none of them are.
+
+use crate::{
+    circuit::{
+        operator_traits::{Data, Operator, SourceOperator},
+        Circuit, StepStats, Stream,
+    },
+    trace::{Batch, BatchReader},
+};
+use std::{borrow::Cow, cell::RefCell, mem::take, rc::Rc};
+
+/// Updates buffered in an input handle.
+type Updates<B> = Vec<(
+    (<B as BatchReader>::Key, <B as BatchReader>::Val),
+    <B as BatchReader>::R,
+)>;
+
+impl<P> Circuit<P>
+where
+    P: Clone + 'static,
+{
+    /// Create an input stream of batches of type `B`.
+    ///
+    /// Returns the stream along with an [`InputHandle`] used to push updates
+    /// to it.  In each clock cycle, the stream yields a batch containing all
+    /// updates pushed to the handle since the previous clock cycle.
+    pub fn add_input<B>(&self) -> (Stream<Self, B>, InputHandle<B>)
+    where
+        B: Batch<Time = ()> + Data,
+    {
+        let handle = InputHandle::new();
+        let stream = self.add_source(Input::new(handle.buffer.clone(), self.step_stats()));
+        (stream, handle)
+    }
+}
+
+/// Handle used to push updates to an input stream created with
+/// [`Circuit::add_input`].
+///
+/// Handles are cheap to clone; all clones push to the same stream.  Each
+/// handle belongs to the circuit of the worker that created it and cannot
+/// be sent across threads.
+pub struct InputHandle<B>
+where
+    B: Batch,
+{
+    buffer: Rc<RefCell<Updates<B>>>,
+}
+
+impl<B> Clone for InputHandle<B>
+where
+    B: Batch,
+{
+    fn clone(&self) -> Self {
+        Self {
+            buffer: self.buffer.clone(),
+        }
+    }
+}
+
+impl<B> InputHandle<B>
+where
+    B: Batch,
+{
+    fn new() -> Self {
+        Self {
+            buffer: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Push a single update to the input stream.
+    pub fn push(&self, key: B::Key, val: B::Val, weight: B::R) {
+        self.buffer.borrow_mut().push(((key, val), weight));
+    }
+
+    /// Push multiple updates to the input stream.
+    pub fn extend<I>(&self, updates: I)
+    where
+        I: IntoIterator<Item = ((B::Key, B::Val), B::R)>,
+    {
+        self.buffer.borrow_mut().extend(updates);
+    }
+
+    /// Number of updates pushed since the last clock cycle.
+    pub fn pending(&self) -> usize {
+        self.buffer.borrow().len()
+    }
+}
+
+/// A group of updates to one or more input handles that become visible to
+/// the circuit atomically.
+///
+/// Updates added to the transaction are buffered until
+/// [`commit`](`Self::commit`) is invoked, at which point they are
+/// transferred to their respective input handles all at once, guaranteeing
+/// that the circuit observes all of them in the same clock cycle.  Dropping
+/// a transaction without committing it discards its updates.
+///
+/// # Examples
+///
+/// ```
+/// # use dbsp::{
+/// #     circuit::Root,
+/// #     operator::InputTransaction,
+/// #     trace::ord::OrdZSet,
+/// # };
+/// let mut handles = None;
+/// let root = Root::build(|circuit| {
+///     let (_orders, orders_handle) = circuit.add_input::<OrdZSet<u64, isize>>();
+///     let (_items, items_handle) = circuit.add_input::<OrdZSet<(u64, u64), isize>>();
+///     handles = Some((orders_handle, items_handle));
+/// })
+/// .unwrap();
+/// let (orders, items) = handles.unwrap();
+///
+/// // Insert an order along with its line items.
+/// let mut txn = InputTransaction::new();
+/// txn.push(&orders, 1, (), 1);
+/// txn.push(&items, (1, 100), (), 1);
+/// txn.push(&items, (1, 101), (), 1);
+/// txn.commit();
+///
+/// root.step().unwrap();
+/// ```
+#[derive(Default)]
+pub struct InputTransaction {
+    updates: Vec<Box<dyn FnOnce()>>,
+}
+
+impl InputTransaction {
+    /// Create an empty transaction.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an update to `handle` to the transaction.
+    pub fn push<B>(&mut self, handle: &InputHandle<B>, key: B::Key, val: B::Val, weight: B::R)
+    where
+        B: Batch + 'static,
+    {
+        let buffer = handle.buffer.clone();
+        self.updates.push(Box::new(move || {
+            buffer.borrow_mut().push(((key, val), weight))
+        }));
+    }
+
+    /// Add multiple updates to `handle` to the transaction.
+    pub fn extend<B, I>(&mut self, handle: &InputHandle<B>, updates: I)
+    where
+        B: Batch + 'static,
+        I: IntoIterator<Item = ((B::Key, B::Val), B::R)>,
+    {
+        let buffer = handle.buffer.clone();
+        let updates: Updates<B> = updates.into_iter().collect();
+        self.updates
+            .push(Box::new(move || buffer.borrow_mut().extend(updates)));
+    }
+
+    /// Returns `true` if the transaction does not contain any updates.
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+
+    /// Transfer all updates in the transaction to their input handles.
+    ///
+    /// The updates will be observed by the circuit in the next clock cycle.
+    pub fn commit(self) {
+        for update in self.updates {
+            update();
+        }
+    }
+}
+
+/// Source operator that yields updates pushed to an [`InputHandle`].
+struct Input<B>
+where
+    B: Batch,
+{
+    buffer: Rc<RefCell<Updates<B>>>,
+    step_stats: Rc<StepStats>,
+}
+
+impl<B> Input<B>
+where
+    B: Batch,
+{
+    fn new(buffer: Rc<RefCell<Updates<B>>>, step_stats: Rc<StepStats>) -> Self {
+        Self { buffer, step_stats }
+    }
+}
+
+impl<B> Operator for Input<B>
+where
+    B: Batch + 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("Input")
+    }
+
+    fn fixedpoint(&self) -> bool {
+        false
+    }
+}
+
+impl<B> SourceOperator<B> for Input<B>
+where
+    B: Batch<Time = ()> + Data,
+{
+    fn idle_output(&mut self) -> Option<B> {
+        if self.buffer.borrow().is_empty() {
+            Some(B::empty(()))
+        } else {
+            None
+        }
+    }
+
+    fn eval(&mut self) -> B {
+        let updates = take(&mut *self.buffer.borrow_mut());
+        self.step_stats.record_inputs(updates.len());
+        B::from_tuples((), updates)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::InputTransaction;
+    use crate::{
+        circuit::Root,
+        trace::{ord::OrdZSet, Batch},
+        zset,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn input_transaction() {
+        let outputs1 = Rc::new(RefCell::new(Vec::new()));
+        let outputs2 = Rc::new(RefCell::new(Vec::new()));
+        let outputs1_clone = outputs1.clone();
+        let outputs2_clone = outputs2.clone();
+
+        let mut handles = None;
+        let root = Root::build(|circuit| {
+            let (input1, handle1) = circuit.add_input::<OrdZSet<u64, isize>>();
+            let (input2, handle2) = circuit.add_input::<OrdZSet<u64, isize>>();
+            handles = Some((handle1, handle2));
+
+            input1.inspect(move |batch| outputs1_clone.borrow_mut().push(batch.clone()));
+            input2.inspect(move |batch| outputs2_clone.borrow_mut().push(batch.clone()));
+        })
+        .unwrap();
+        let (handle1, handle2) = handles.unwrap();
+
+        // Direct pushes are observed in the next step.
+        handle1.push(1, (), 1);
+        assert_eq!(handle1.pending(), 1);
+        root.step().unwrap();
+        assert_eq!(handle1.pending(), 0);
+
+        // Uncommitted updates are not observed.
+        let mut txn = InputTransaction::new();
+        txn.push(&handle1, 2, (), 1);
+        txn.extend(&handle2, vec![((2, ()), 1), ((3, ()), -1)]);
+        assert!(!txn.is_empty());
+        root.step().unwrap();
+
+        // Committed updates are observed together.
+        txn.commit();
+        root.step().unwrap();
+
+        // Aborted transaction.
+        let mut txn = InputTransaction::new();
+        txn.push(&handle1, 4, (), 1);
+        txn.push(&handle2, 4, (), 1);
+        drop(txn);
+        root.step().unwrap();
+
+        let empty = OrdZSet::empty(());
+        let expected1: Vec<OrdZSet<u64, isize>> = vec![
+            zset! { 1 => 1 },
+            empty.clone(),
+            zset! { 2 => 1 },
+            empty.clone(),
+        ];
+        let expected2: Vec<OrdZSet<u64, isize>> = vec![
+            empty.clone(),
+            empty.clone(),
+            zset! { 2 => 1, 3 => -1 },
+            empty,
+        ];
+        assert_eq!(*outputs1.borrow(), expected1);
+        assert_eq!(*outputs2.borrow(), expected2);
+    }
+}
@@ -3,7 +3,7 @@
 
 use crate::circuit::{
     operator_traits::{Operator, SinkOperator},
-    Circuit, Stream,
+    Circuit, Runtime, Scope, Stream,
 };
 use std::{borrow::Cow, marker::PhantomData};
 
@@ -39,6 +39,38 @@ where
     {
         self.circuit().add_sink(Inspect::new(callback), self);
     }
+
+    /// Apply [`InspectWithMeta`] operator to `self`.
+    ///
+    /// Like [`inspect`](`Self::inspect`), but the callback also receives
+    /// [`InspectMeta`] describing the context in which the value was
+    /// produced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use dbsp::{
+    /// #     circuit::Root,
+    /// #     operator::Generator,
+    /// # };
+    /// let root = Root::build(move |circuit| {
+    ///     let stream = circuit.add_source(Generator::new(|| 5));
+    ///     stream.inspect_with_meta(|n, meta| {
+    ///         println!(
+    ///             "worker {}, step {}: {}",
+    ///             meta.worker_index, meta.step, n
+    ///         )
+    ///     });
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn inspect_with_meta<F>(&self, callback: F)
+    where
+        F: FnMut(&D, &InspectMeta) + 'static,
+    {
+        self.circuit()
+            .add_sink(InspectWithMeta::new(callback), self);
+    }
 }
 
 /// Sink operator that consumes a stream of values of type `T` and
@@ -85,3 +117,144 @@ where
         (self.callback)(i)
     }
 }
+
+/// Metadata passed to the [`InspectWithMeta`] callback.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InspectMeta {
+    /// Step of the top-level circuit, counting from 0.
+    pub step: usize,
+    /// Index of the worker thread evaluating the circuit (see
+    /// [`Runtime::worker_index`]).
+    pub worker_index: usize,
+    /// Nested timestamp of the value, outermost circuit first.  The first
+    /// element is equal to `step`; the last element is the iteration of
+    /// the circuit the operator belongs to within the current parent clock
+    /// cycle.  A timestamp of an operator in the top-level circuit has a
+    /// single element.
+    pub timestamp: Vec<usize>,
+}
+
+/// Sink operator that applies a user-provided callback to each input along
+/// with the current step number, worker index, and nested timestamp (see
+/// [`InspectMeta`]).
+pub struct InspectWithMeta<T, F> {
+    callback: F,
+    // Current time in each scope, innermost scope first.
+    time: Vec<usize>,
+    meta: InspectMeta,
+    phantom: PhantomData<T>,
+}
+
+impl<T, F> InspectWithMeta<T, F>
+where
+    F: FnMut(&T, &InspectMeta),
+{
+    /// Create a new instance of the `InspectWithMeta` operator that will
+    /// apply `callback` to each value in the input stream.
+    pub fn new(callback: F) -> Self {
+        Self {
+            callback,
+            time: vec![0],
+            meta: InspectMeta {
+                worker_index: Runtime::worker_index(),
+                ..Default::default()
+            },
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, F> Operator for InspectWithMeta<T, F>
+where
+    T: 'static,
+    F: FnMut(&T, &InspectMeta) + 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("InspectWithMeta")
+    }
+
+    fn clock_start(&mut self, scope: Scope) {
+        let scope = scope as usize;
+        if self.time.len() <= scope {
+            self.time.resize(scope + 1, 0);
+        }
+        self.time[scope] = 0;
+    }
+
+    fn clock_end(&mut self, scope: Scope) {
+        // The end of an epoch of scope `scope` is a tick of the clock of its
+        // parent scope.
+        if let Some(time) = self.time.get_mut(scope as usize + 1) {
+            *time += 1;
+        }
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<T, F> SinkOperator<T> for InspectWithMeta<T, F>
+where
+    T: 'static,
+    F: FnMut(&T, &InspectMeta) + 'static,
+{
+    fn eval(&mut self, i: &T) {
+        self.meta.timestamp.clear();
+        self.meta.timestamp.extend(self.time.iter().rev());
+        self.meta.step = self.meta.timestamp[0];
+        (self.callback)(i, &self.meta);
+        self.time[0] += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{circuit::Root, operator::Generator};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn inspect_with_meta_test() {
+        let top_level = Rc::new(RefCell::new(Vec::new()));
+        let nested = Rc::new(RefCell::new(Vec::new()));
+        let top_level_clone = top_level.clone();
+        let nested_clone = nested.clone();
+
+        let root = Root::build(move |circuit| {
+            let source = circuit.add_source(Generator::new(|| 0));
+            source.inspect_with_meta(move |_, meta| {
+                assert_eq!(meta.worker_index, 0);
+                top_level_clone.borrow_mut().push(meta.timestamp.clone());
+            });
+
+            circuit
+                .iterate_with_condition(|child| {
+                    let source = source.delta0(child);
+                    source.inspect_with_meta(move |_, meta| {
+                        assert_eq!(meta.step, meta.timestamp[0]);
+                        nested_clone.borrow_mut().push(meta.timestamp.clone());
+                    });
+                    Ok((child.max_iterations(2), ()))
+                })
+                .unwrap();
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+
+        assert_eq!(*top_level.borrow(), vec![vec![0], vec![1], vec![2]]);
+        assert_eq!(
+            *nested.borrow(),
+            vec![
+                vec![0, 0],
+                vec![0, 1],
+                vec![1, 0],
+                vec![1, 1],
+                vec![2, 0],
+                vec![2, 1]
+            ]
+        );
+    }
+}
@@ -1,14 +1,15 @@
 //! Relational join operator.
 
 use crate::{
-    algebra::{IndexedZSet, MulByRef, ZSet},
+    algebra::{HasOne, HasZero, IndexedZSet, MulByRef, ZRingValue, ZSet},
     circuit::{
         operator_traits::{BinaryOperator, Operator},
         Circuit, Scope, Stream,
     },
     time::NestedTimestamp32,
     trace::{
-        cursor::Cursor as TraceCursor, ord::OrdValSpine, BatchReader, Batcher, Trace, TraceReader,
+        consolidation::consolidate, cursor::Cursor as TraceCursor, ord::OrdValSpine, Batch,
+        BatchReader, Batcher, Builder, ConsumedFrontier, Trace, TraceReader,
     },
 };
 use deepsize::DeepSizeOf;
@@ -17,7 +18,7 @@ use std::{
     cmp::{max, min, Ordering},
     fmt::Write,
     marker::PhantomData,
-    mem::swap,
+    ptr,
 };
 
 impl<P, IZ1> Stream<Circuit<P>, IZ1>
@@ -39,6 +40,91 @@ where
         self.circuit()
             .add_binary_operator(Join::new(f), self, other)
     }
+
+    /// Apply [`DistinctJoin`] operator to `self` and `other`.
+    ///
+    /// Equivalent to `self.join(other, f).distinct()`, but avoids building
+    /// and then re-scanning the intermediate join output.  See
+    /// [`DistinctJoin`] operator for more info.
+    ///
+    /// There is no incremental version of this operator: whether a tuple is
+    /// in the output depends on its accumulated weight, which requires the
+    /// integral of the join output maintained by
+    /// [`distinct_incremental`](`Stream::distinct_incremental`).  Use
+    /// `join_incremental(..).distinct_incremental()` instead.
+    pub fn join_distinct<F, IZ2, Z>(
+        &self,
+        other: &Stream<Circuit<P>, IZ2>,
+        f: F,
+    ) -> Stream<Circuit<P>, Z>
+    where
+        IZ1: BatchReader<Time = (), R = Z::R> + Clone + 'static,
+        IZ2: BatchReader<Key = IZ1::Key, Time = (), R = Z::R> + Clone + 'static,
+        IZ1::Key: Ord,
+        Z: Clone + ZSet + 'static,
+        Z::Key: Ord,
+        Z::R: MulByRef + ZRingValue,
+        F: Fn(&IZ1::Key, &IZ1::Val, &IZ2::Val) -> Z::Key + 'static,
+    {
+        self.circuit()
+            .add_binary_operator(DistinctJoin::new(f), self, other)
+    }
+
+    /// Apply [`Antijoin`] operator to `self` and `other`.
+    ///
+    /// See [`Antijoin`] operator for more info.
+    pub fn antijoin<IZ2>(&self, other: &Stream<Circuit<P>, IZ2>) -> Stream<Circuit<P>, IZ1>
+    where
+        IZ1: Batch<Time = ()> + Clone + 'static,
+        IZ1::Key: Ord + Clone,
+        IZ1::Val: Clone,
+        IZ2: BatchReader<Key = IZ1::Key, Time = ()> + Clone + 'static,
+    {
+        self.circuit()
+            .add_binary_operator(Antijoin::new(), self, other)
+    }
+
+    /// Apply [`CrossJoin`] operator to `self` and `other`.
+    ///
+    /// See [`CrossJoin`] operator for more info.
+    pub fn cross_join<F, Z2, Z>(
+        &self,
+        other: &Stream<Circuit<P>, Z2>,
+        max_output_size: usize,
+        f: F,
+    ) -> Stream<Circuit<P>, Z>
+    where
+        IZ1: BatchReader<Val = (), Time = (), R = Z::R> + Clone + 'static,
+        Z2: BatchReader<Val = (), Time = (), R = Z::R> + Clone + 'static,
+        Z: Clone + ZSet + 'static,
+        Z::R: MulByRef,
+        F: Fn(&IZ1::Key, &Z2::Key) -> Z::Key + 'static,
+    {
+        self.circuit()
+            .add_binary_operator(CrossJoin::new(max_output_size, f), self, other)
+    }
+
+    /// Apply [`PrefixJoin`] operator to `self` and `other`.
+    ///
+    /// See [`PrefixJoin`] operator for more info.
+    pub fn join_prefix<F, PF, IZ2, Z>(
+        &self,
+        other: &Stream<Circuit<P>, IZ2>,
+        prefix: PF,
+        f: F,
+    ) -> Stream<Circuit<P>, Z>
+    where
+        IZ1: BatchReader<Time = (), R = Z::R> + Clone + 'static,
+        IZ1::Key: Ord,
+        IZ2: BatchReader<Time = (), R = Z::R> + Clone + 'static,
+        Z: Clone + ZSet + 'static,
+        Z::R: MulByRef,
+        PF: Fn(&IZ2::Key) -> &IZ1::Key + 'static,
+        F: Fn(&IZ1::Key, &IZ1::Val, &IZ2::Key, &IZ2::Val) -> Z::Key + 'static,
+    {
+        self.circuit()
+            .add_binary_operator(PrefixJoin::new(prefix, f), self, other)
+    }
 }
 
 impl<P, I1> Stream<Circuit<P>, I1>
@@ -76,6 +162,37 @@ where
             .plus(&self.join(&other.integrate_trace(), join_func))
     }
 
+    /// Incremental version of [`join_prefix`](`Self::join_prefix`).
+    ///
+    /// Like [`join_incremental`](`Self::join_incremental`), but matches keys
+    /// of `self` against a prefix of the keys of `other` extracted by
+    /// `prefix`.  This allows joining with an existing arrangement of
+    /// `other` indexed by a composite key without re-indexing it by the
+    /// join attribute.
+    pub fn join_prefix_incremental<F, PF, I2, Z>(
+        &self,
+        other: &Stream<Circuit<P>, I2>,
+        prefix: PF,
+        join_func: F,
+    ) -> Stream<Circuit<P>, Z>
+    where
+        I1: IndexedZSet + DeepSizeOf,
+        I1::Key: Ord,
+        I1::Val: Ord,
+        I2: IndexedZSet<R = I1::R> + DeepSizeOf,
+        I2::Key: Ord,
+        I2::Val: Ord,
+        PF: Clone + Fn(&I2::Key) -> &I1::Key + 'static,
+        F: Clone + Fn(&I1::Key, &I1::Val, &I2::Key, &I2::Val) -> Z::Key + 'static,
+        Z: ZSet<R = I1::R>,
+        Z::R: MulByRef,
+    {
+        self.integrate_trace()
+            .delay_trace()
+            .join_prefix(other, prefix.clone(), join_func.clone())
+            .plus(&self.join_prefix(&other.integrate_trace(), prefix, join_func))
+    }
+
     /*
     /// Incremental join of two nested streams.
     ///
@@ -359,6 +476,11 @@ where
     Z: ZSet + 'static,
     Z::R: MulByRef,
 {
+    // The join of an empty batch with anything is empty.
+    fn idle_output(&mut self, lhs_idle: bool, rhs_idle: bool) -> Option<Z> {
+        (lhs_idle || rhs_idle).then(|| Z::empty(()))
+    }
+
     fn eval(&mut self, i1: &I1, i2: &I2) -> Z {
         let mut cursor1 = i1.cursor();
         let mut cursor2 = i2.cursor();
@@ -399,6 +521,218 @@ where
     }
 }
 
+/// Join operator with set semantics.
+///
+/// Computes the same tuples as [`Join`], but outputs each tuple with a
+/// positive total weight once, with weight `1`, and drops tuples with
+/// non-positive weights, i.e., it is equivalent to [`Join`] followed by
+/// [`Distinct`](`crate::operator::Distinct`).  Join outputs are collected in
+/// a vector, which is sorted and consolidated in place; since the vector is
+/// sorted after consolidation, the distinct tuples are pushed directly into
+/// a batch builder, skipping the intermediate batch of the two-operator
+/// pipeline and the second pass over it.
+pub struct DistinctJoin<F, I1, I2, Z> {
+    join_func: F,
+    _types: PhantomData<(I1, I2, Z)>,
+}
+
+impl<F, I1, I2, Z> DistinctJoin<F, I1, I2, Z> {
+    pub fn new(join_func: F) -> Self {
+        Self {
+            join_func,
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<F, I1, I2, Z> Operator for DistinctJoin<F, I1, I2, Z>
+where
+    I1: 'static,
+    I2: 'static,
+    F: 'static,
+    Z: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("DistinctJoin")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<F, I1, I2, Z> BinaryOperator<I1, I2, Z> for DistinctJoin<F, I1, I2, Z>
+where
+    I1: BatchReader<Time = (), R = Z::R> + 'static,
+    I1::Key: Ord,
+    I2: BatchReader<Key = I1::Key, Time = (), R = Z::R> + 'static,
+    F: Fn(&I1::Key, &I1::Val, &I2::Val) -> Z::Key + 'static,
+    Z: ZSet + 'static,
+    Z::Key: Ord,
+    Z::R: MulByRef + ZRingValue,
+{
+    // The join of an empty batch with anything is empty.
+    fn idle_output(&mut self, lhs_idle: bool, rhs_idle: bool) -> Option<Z> {
+        (lhs_idle || rhs_idle).then(|| Z::empty(()))
+    }
+
+    fn eval(&mut self, i1: &I1, i2: &I2) -> Z {
+        let mut cursor1 = i1.cursor();
+        let mut cursor2 = i2.cursor();
+
+        // Choose capacity heuristically.
+        let mut tuples = Vec::with_capacity(min(i1.len(), i2.len()));
+
+        while cursor1.key_valid(i1) && cursor2.key_valid(i2) {
+            match cursor1.key(i1).cmp(cursor2.key(i2)) {
+                Ordering::Less => cursor1.seek_key(i1, cursor2.key(i2)),
+                Ordering::Greater => cursor2.seek_key(i2, cursor1.key(i1)),
+                Ordering::Equal => {
+                    while cursor1.val_valid(i1) {
+                        let w1 = cursor1.weight(i1);
+                        let v1 = cursor1.val(i1);
+                        while cursor2.val_valid(i2) {
+                            tuples.push((
+                                (self.join_func)(cursor1.key(i1), v1, cursor2.val(i2)),
+                                w1.mul_by_ref(&cursor2.weight(i2)),
+                            ));
+                            cursor2.step_val(i2);
+                        }
+
+                        cursor2.rewind_vals(i2);
+                        cursor1.step_val(i1);
+                    }
+
+                    cursor1.step_key(i1);
+                    cursor2.step_key(i2);
+                }
+            }
+        }
+
+        consolidate(&mut tuples);
+
+        let mut builder = Z::Builder::with_capacity((), tuples.len());
+        for (key, weight) in tuples.into_iter() {
+            if weight.ge0() {
+                builder.push((key, (), HasOne::one()));
+            }
+        }
+        builder.done()
+    }
+}
+
+/// Join operator that matches keys of the first input against a prefix of the
+/// keys of the second input.
+///
+/// The second input is indexed by a composite key, e.g., a tuple `(x, y)`,
+/// and `prefix` extracts the component that is matched against keys of the
+/// first input, e.g., `|(x, _)| x`.  Keys of the second input must be ordered
+/// consistently with their prefixes, i.e., the prefix must be monotone with
+/// respect to the key order, which is the case for the leading components of
+/// tuples.  The operator locates matching keys using
+/// [`Cursor::seek_key_with`](`crate::trace::Cursor::seek_key_with`), so the
+/// same arrangement can serve joins on the whole key and on its prefix,
+/// without building another index of the second input by the join
+/// attribute.
+///
+/// # Type arguments
+///
+/// * `PF` - prefix function type: maps a key of the second input to a key of
+///   the first input.
+/// * `F` - join function type: maps the matching keys and values of the two
+///   inputs to an output value.
+/// * `I1` - indexed Z-set type in the first input stream.
+/// * `I2` - indexed Z-set type in the second input stream.
+/// * `Z` - output Z-set type.
+pub struct PrefixJoin<PF, F, I1, I2, Z> {
+    prefix: PF,
+    join_func: F,
+    _types: PhantomData<(I1, I2, Z)>,
+}
+
+impl<PF, F, I1, I2, Z> PrefixJoin<PF, F, I1, I2, Z> {
+    pub fn new(prefix: PF, join_func: F) -> Self {
+        Self {
+            prefix,
+            join_func,
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<PF, F, I1, I2, Z> Operator for PrefixJoin<PF, F, I1, I2, Z>
+where
+    PF: 'static,
+    F: 'static,
+    I1: 'static,
+    I2: 'static,
+    Z: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("PrefixJoin")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<PF, F, I1, I2, Z> BinaryOperator<I1, I2, Z> for PrefixJoin<PF, F, I1, I2, Z>
+where
+    I1: BatchReader<Time = (), R = Z::R> + 'static,
+    I1::Key: Ord,
+    I2: BatchReader<Time = (), R = Z::R> + 'static,
+    PF: Fn(&I2::Key) -> &I1::Key + 'static,
+    F: Fn(&I1::Key, &I1::Val, &I2::Key, &I2::Val) -> Z::Key + 'static,
+    Z: ZSet + 'static,
+    Z::R: MulByRef,
+{
+    fn eval(&mut self, i1: &I1, i2: &I2) -> Z {
+        let mut cursor1 = i1.cursor();
+        let mut cursor2 = i2.cursor();
+        let prefix = &self.prefix;
+
+        // Choose capacity heuristically.
+        let mut batch = Vec::with_capacity(min(i1.len(), i2.len()));
+
+        while cursor1.key_valid(i1) && cursor2.key_valid(i2) {
+            let key1 = cursor1.key(i1);
+            match key1.cmp(prefix(cursor2.key(i2))) {
+                Ordering::Less => cursor1.seek_key(i1, prefix(cursor2.key(i2))),
+                Ordering::Greater => cursor2.seek_key_with(i2, |key2| prefix(key2) >= key1),
+                Ordering::Equal => {
+                    // Join all keys in `i2` with the same prefix.
+                    while cursor2.key_valid(i2) && prefix(cursor2.key(i2)) == key1 {
+                        let key2 = cursor2.key(i2);
+                        while cursor1.val_valid(i1) {
+                            let w1 = cursor1.weight(i1);
+                            let v1 = cursor1.val(i1);
+                            while cursor2.val_valid(i2) {
+                                let v2 = cursor2.val(i2);
+                                let w2 = cursor2.weight(i2);
+
+                                batch.push((
+                                    ((self.join_func)(key1, v1, key2, v2), ()),
+                                    w1.mul_by_ref(&w2),
+                                ));
+                                cursor2.step_val(i2);
+                            }
+
+                            cursor2.rewind_vals(i2);
+                            cursor1.step_val(i1);
+                        }
+
+                        cursor1.rewind_vals(i1);
+                        cursor2.step_key(i2);
+                    }
+
+                    cursor1.step_key(i1);
+                }
+            }
+        }
+
+        Z::from_tuples((), batch)
+    }
+}
+
 // Computes one half of nested incremental join:
 //
 //        self                       other
@@ -444,10 +778,20 @@ where
     // Future update batches computed ahead of time, indexed by time
     // when each batch should be output.
     output_batchers: Vec<Z::Batcher>,
+    // Empty batchers left over from previous clock epochs.  Batchers keep
+    // their sorting buffers when sealed, so reusing them instead of creating
+    // new ones avoids reallocating these buffers.
+    batcher_pool: Vec<Z::Batcher>,
+    // Per-time scratch vectors used to collect output tuples before pushing
+    // them to `output_batchers`.
+    #[allow(clippy::type_complexity)]
+    output_batches: Vec<Vec<((Z::Key, ()), Z::R)>>,
     // True if empty input batch was received at the current clock cycle.
     empty_input: bool,
     // True if empty output was produced at the current clock cycle.
     empty_output: bool,
+    // Batches of the trace already accounted for in `output_batchers`.
+    consumed: ConsumedFrontier<NestedTimestamp32>,
     _types: PhantomData<(I, T, Z)>,
 }
 
@@ -461,8 +805,11 @@ where
             join_func,
             time: 0,
             output_batchers: Vec::new(),
+            batcher_pool: Vec::new(),
+            output_batches: Vec::new(),
             empty_input: false,
             empty_output: false,
+            consumed: ConsumedFrontier::new(),
             _types: PhantomData,
         }
     }
@@ -484,11 +831,19 @@ where
             self.time = 0;
             self.empty_input = false;
             self.empty_output = false;
+            // The trace may have receded to the start of the new epoch.
+            self.consumed.reset();
         }
     }
     fn clock_end(&mut self, scope: Scope) {
         if scope == 0 {
-            self.output_batchers.clear();
+            // Batchers for the times of the epoch that has just ended have
+            // already been sealed.
+            self.batcher_pool.extend(
+                self.output_batchers
+                    .drain(..)
+                    .filter(|batcher| batcher.tuples() == 0),
+            );
         }
     }
 
@@ -541,62 +896,71 @@ where
 
         self.empty_input = index.is_empty();
 
-        let mut new_len: u32 = self.time + 1;
-        trace.map_batches(|batch| {
+        // Batches consumed at earlier iterations are already covered by
+        // `output_batchers`; only look at the upper bounds of new batches.
+        let mut new_len: u32 = max(self.time + 1, self.output_batchers.len() as u32);
+        for batch in self.consumed.updates(trace).batches() {
             for ts in batch.upper().elements().iter() {
                 new_len = max(new_len, ts.inner());
             }
-        });
+        }
 
         //println!("new_len: {}", new_len);
-        self.output_batchers
-            .resize_with(new_len as usize, || Z::Batcher::new(()));
-        // TODO: keep output batches in `self` for allocation reuse across iterations
-        // (but potentially using more memory)..
-        let mut output_batches = Vec::new();
-        output_batches.resize_with((new_len - self.time) as usize, Vec::new);
+        let pool = &mut self.batcher_pool;
+        self.output_batchers.resize_with(new_len as usize, || {
+            pool.pop().unwrap_or_else(|| Z::Batcher::new(()))
+        });
+        let output_batches = &mut self.output_batches;
+        if output_batches.len() < (new_len - self.time) as usize {
+            output_batches.resize_with((new_len - self.time) as usize, Vec::new);
+        }
 
+        // Probe the trace with all keys in `index` in a single pass.
         let mut index_cursor = index.cursor();
-        let mut trace_cursor = trace.cursor();
+        let mut keys = Vec::with_capacity(index.len());
+        while index_cursor.key_valid(index) {
+            keys.push(index_cursor.key(index));
+            index_cursor.step_key(index);
+        }
+        index_cursor.rewind_keys(index);
 
-        while index_cursor.key_valid(index) && trace_cursor.key_valid(trace) {
-            match index_cursor.key(index).cmp(trace_cursor.key(trace)) {
-                Ordering::Less => {
-                    index_cursor.seek_key(index, trace_cursor.key(trace));
-                }
-                Ordering::Greater => {
-                    trace_cursor.seek_key(trace, index_cursor.key(index));
-                }
-                Ordering::Equal => {
-                    //println!("key: {}", index_cursor.key(index));
-
-                    while index_cursor.val_valid(index) {
-                        let v1 = index_cursor.val(index);
-                        let w1 = index_cursor.weight(index);
-                        //println!("v1: {}, w1: {}", v1, w1);
-
-                        while trace_cursor.val_valid(trace) {
-                            let output = (self.join_func)(
-                                index_cursor.key(index),
-                                v1,
-                                trace_cursor.val(trace),
-                            );
-                            trace_cursor.map_times(trace, |ts, w2| {
-                                let off = (max(ts.inner(), self.time) - self.time) as usize;
-                                //println!("  tuple@{}: ({:?}, {})", off, output, w1.clone() *
-                                // w2.clone());
-                                output_batches[off].push(((output.clone(), ()), w1.mul_by_ref(w2)));
-                            });
-                            trace_cursor.step_val(trace);
-                        }
-                        trace_cursor.rewind_vals(trace);
-                        index_cursor.step_val(index);
-                    }
+        let mut matches = trace.lookup_many(keys.iter().copied()).peekable();
+        let mut trace_updates = Vec::new();
+
+        while index_cursor.key_valid(index) {
+            let key = index_cursor.key(index);
 
-                    index_cursor.step_key(index);
-                    trace_cursor.step_key(trace);
+            trace_updates.clear();
+            while let Some((_, val, ts, w2)) = matches.next_if(|(k, ..)| *k == key) {
+                trace_updates.push((val, ts, w2));
+            }
+
+            if !trace_updates.is_empty() {
+                while index_cursor.val_valid(index) {
+                    let v1 = index_cursor.val(index);
+                    let w1 = index_cursor.weight(index);
+                    //println!("v1: {}, w1: {}", v1, w1);
+
+                    // Updates for the same value are adjacent in `trace_updates`;
+                    // evaluate `join_func` once per value.
+                    let mut last_output: Option<(&T::Val, Z::Key)> = None;
+                    for (v2, ts, w2) in trace_updates.iter() {
+                        let output = match &last_output {
+                            Some((val, output)) if ptr::eq(*val, *v2) => output.clone(),
+                            _ => {
+                                let output = (self.join_func)(key, v1, v2);
+                                last_output = Some((*v2, output.clone()));
+                                output
+                            }
+                        };
+                        let off = (max(ts.inner(), self.time) - self.time) as usize;
+                        output_batches[off].push(((output, ()), w1.mul_by_ref(w2)));
+                    }
+                    index_cursor.step_val(index);
                 }
             }
+
+            index_cursor.step_key(index);
         }
 
         for (i, batch) in output_batches.iter_mut().enumerate() {
@@ -605,19 +969,186 @@ where
             }
         }
 
-        let mut batcher = Z::Batcher::new(());
-        swap(&mut self.output_batchers[self.time as usize], &mut batcher);
+        let result = self.output_batchers[self.time as usize].seal_reuse(());
         self.time += 1;
-        let result = batcher.seal();
         self.empty_output = result.is_empty();
         result
     }
 }
 
+/// Antijoin operator.
+///
+/// Retains the tuples of the first input whose keys do not occur in the
+/// second input with a non-zero weight.
+///
+/// This operator is not monotone (see
+/// [`Operator::is_monotone`](`crate::circuit::operator_traits::Operator::is_monotone`)):
+/// adding keys to the second input removes tuples from the output.  It can
+/// therefore not be used inside a feedback loop of a fixed point circuit and
+/// can only negate the output of a recursive computation from a later
+/// stratum (see [`Stream::stratify`]).
+pub struct Antijoin<I1, I2> {
+    _types: PhantomData<(I1, I2)>,
+}
+
+impl<I1, I2> Antijoin<I1, I2> {
+    pub fn new() -> Self {
+        Self {
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<I1, I2> Default for Antijoin<I1, I2> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I1, I2> Operator for Antijoin<I1, I2>
+where
+    I1: 'static,
+    I2: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("Antijoin")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+    fn is_monotone(&self) -> bool {
+        false
+    }
+}
+
+impl<I1, I2> BinaryOperator<I1, I2, I1> for Antijoin<I1, I2>
+where
+    I1: Batch<Time = ()> + 'static,
+    I1::Key: Ord + Clone,
+    I1::Val: Clone,
+    I2: BatchReader<Key = I1::Key, Time = ()> + 'static,
+{
+    fn eval(&mut self, i1: &I1, i2: &I2) -> I1 {
+        let mut cursor1 = i1.cursor();
+        let mut cursor2 = i2.cursor();
+
+        let mut builder = I1::Builder::with_capacity((), i1.len());
+
+        while cursor1.key_valid(i1) {
+            let key = cursor1.key(i1);
+
+            cursor2.seek_key(i2, key);
+            let mut found = false;
+            if cursor2.key_valid(i2) && cursor2.key(i2) == key {
+                while cursor2.val_valid(i2) {
+                    if !cursor2.weight(i2).is_zero() {
+                        found = true;
+                        break;
+                    }
+                    cursor2.step_val(i2);
+                }
+            }
+
+            if !found {
+                while cursor1.val_valid(i1) {
+                    builder.push((key.clone(), cursor1.val(i1).clone(), cursor1.weight(i1)));
+                    cursor1.step_val(i1);
+                }
+            }
+            cursor1.step_key(i1);
+        }
+
+        builder.done()
+    }
+}
+
+/// Cartesian product of two Z-sets.
+///
+/// Meant for joining with small, dimension-style inputs, where the size of
+/// the output is known to be small.  The size of the output is the product
+/// of the sizes of the inputs; if it exceeds `max_output_size`, the operator
+/// produces an empty output and fails the current step with
+/// [`SchedulerError::OperatorError`](`crate::circuit::schedule::Error::OperatorError`)
+/// instead of building a quadratic batch.
+pub struct CrossJoin<F, I1, I2, Z> {
+    max_output_size: usize,
+    join_func: F,
+    error: Option<Cow<'static, str>>,
+    _types: PhantomData<(I1, I2, Z)>,
+}
+
+impl<F, I1, I2, Z> CrossJoin<F, I1, I2, Z> {
+    pub fn new(max_output_size: usize, join_func: F) -> Self {
+        Self {
+            max_output_size,
+            join_func,
+            error: None,
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<F, I1, I2, Z> Operator for CrossJoin<F, I1, I2, Z>
+where
+    I1: 'static,
+    I2: 'static,
+    F: 'static,
+    Z: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("CrossJoin")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+    fn take_error(&mut self) -> Option<Cow<'static, str>> {
+        self.error.take()
+    }
+}
+
+impl<F, I1, I2, Z> BinaryOperator<I1, I2, Z> for CrossJoin<F, I1, I2, Z>
+where
+    I1: BatchReader<Val = (), Time = (), R = Z::R> + 'static,
+    I2: BatchReader<Val = (), Time = (), R = Z::R> + 'static,
+    F: Fn(&I1::Key, &I2::Key) -> Z::Key + 'static,
+    Z: ZSet + 'static,
+    Z::R: MulByRef,
+{
+    fn eval(&mut self, i1: &I1, i2: &I2) -> Z {
+        let output_size = i1.len().saturating_mul(i2.len());
+        if output_size > self.max_output_size {
+            self.error = Some(Cow::from(format!(
+                "cross join output size {} exceeds the limit of {}",
+                output_size, self.max_output_size
+            )));
+            return Z::empty(());
+        }
+
+        let mut cursor1 = i1.cursor();
+        let mut batch = Vec::with_capacity(output_size);
+
+        while cursor1.key_valid(i1) {
+            let w1 = cursor1.weight(i1);
+            let mut cursor2 = i2.cursor();
+
+            while cursor2.key_valid(i2) {
+                batch.push((
+                    ((self.join_func)(cursor1.key(i1), cursor2.key(i2)), ()),
+                    w1.mul_by_ref(&cursor2.weight(i2)),
+                ));
+                cursor2.step_key(i2);
+            }
+            cursor1.step_key(i1);
+        }
+
+        Z::from_tuples((), batch)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
-        circuit::{Root, Stream},
+        circuit::{schedule::Error as SchedulerError, Root, Stream},
         operator::{DelayedFeedback, Generator},
         trace::ord::{OrdIndexedZSet, OrdZSet},
         zset,
@@ -902,4 +1433,145 @@ mod test {
             root.step().unwrap();
         }
     }
+
+    #[test]
+    fn cross_join_test() {
+        let root = Root::build(move |circuit| {
+            let mut dims = vec![
+                zset! { "x" => 1, "y" => 2 },
+                zset! { "x" => 1, "y" => 1, "z" => 1 },
+            ]
+            .into_iter();
+            let mut facts = vec![zset! { 1 => 1, 2 => -1 }, zset! { 1 => 1, 2 => 1 }].into_iter();
+            let mut outputs = vec![
+                zset! {
+                    (1, "x") => 1,
+                    (1, "y") => 2,
+                    (2, "x") => -1,
+                    (2, "y") => -2,
+                },
+                zset! {},
+            ]
+            .into_iter();
+
+            let dims = circuit.add_source(Generator::new(move || dims.next().unwrap()));
+            let facts = circuit.add_source(Generator::new(move || facts.next().unwrap()));
+
+            facts
+                .cross_join::<_, _, OrdZSet<_, _>>(&dims, 4, |&n, &s| (n, s))
+                .inspect(move |output| assert_eq!(*output, outputs.next().unwrap()));
+        })
+        .unwrap();
+
+        root.step().unwrap();
+
+        // The second step produces 6 tuples, exceeding the limit.
+        match root.step() {
+            Err(SchedulerError::OperatorError { name, .. }) => assert_eq!(name, "CrossJoin"),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn join_distinct_test() {
+        let root = Root::build(move |circuit| {
+            let mut input1 = vec![
+                zset! { (1, "a") => 1, (1, "b") => 2, (2, "c") => 1, (3, "d") => 1 },
+                zset! { (1, "b") => -2, (3, "e") => 1 },
+                zset! { (2, "c") => -1 },
+            ]
+            .into_iter();
+            let mut input2 = vec![
+                zset! { (1, "x") => 1, (2, "x") => 3, (3, "y") => -1 },
+                zset! { (3, "y") => 2 },
+                zset! {},
+            ]
+            .into_iter();
+
+            let index1 = circuit
+                .add_source(Generator::new(move || input1.next().unwrap()))
+                .index::<OrdIndexedZSet<usize, &'static str, isize>>();
+            let index2 = circuit
+                .add_source(Generator::new(move || input2.next().unwrap()))
+                .index::<OrdIndexedZSet<usize, &'static str, isize>>();
+
+            // Project out the values of the first input, so that several
+            // join outputs collapse into the same tuple.
+            let project = |&k: &usize, _: &&'static str, &v2: &&'static str| (k, v2);
+
+            let join_distinct = index1.join_distinct::<_, _, OrdZSet<_, _>>(&index2, project);
+            let join = index1
+                .join::<_, _, OrdZSet<_, _>>(&index2, project)
+                .distinct();
+            join_distinct.apply2(&join, |join_distinct, join| assert_eq!(join_distinct, join));
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn join_prefix_test() {
+        let root = Root::build(move |circuit| {
+            let mut names = vec![
+                zset! { (1, "a") => 1, (2, "b") => 1, (3, "c") => 1 },
+                zset! { (2, "b") => -1, (4, "d") => 1 },
+                zset! {},
+            ]
+            .into_iter();
+            let mut edges = vec![
+                zset! { ((1, 10), 100) => 1, ((1, 11), 110) => 2, ((3, 30), 300) => 1 },
+                zset! { ((2, 20), 200) => 1, ((4, 40), 400) => 1, ((5, 50), 500) => 1 },
+                zset! { ((1, 10), 100) => -1 },
+            ]
+            .into_iter();
+            let mut outputs = vec![
+                zset! {
+                    (1, "a", 10, 100) => 1,
+                    (1, "a", 11, 110) => 2,
+                    (3, "c", 30, 300) => 1,
+                },
+                zset! { (2, "b", 20, 200) => -1, (4, "d", 40, 400) => 1 },
+                zset! {},
+            ]
+            .into_iter();
+
+            let names = circuit
+                .add_source(Generator::new(move || names.next().unwrap()))
+                .index::<OrdIndexedZSet<usize, &'static str, isize>>();
+            let edges = circuit.add_source(Generator::new(move || edges.next().unwrap()));
+            let edges_by_x =
+                edges.index_with::<OrdIndexedZSet<_, _, _>, _>(|&((x, y), z)| (x, (y, z)));
+            // Indexed by `(x, y)`.
+            let edges = edges.index::<OrdIndexedZSet<(usize, usize), usize, isize>>();
+
+            names
+                .join_prefix::<_, _, _, OrdZSet<_, _>>(
+                    &edges,
+                    |(x, _)| x,
+                    |&x, &name, &(_, y), &z| (x, name, y, z),
+                )
+                .inspect(move |output| assert_eq!(*output, outputs.next().unwrap()));
+
+            // The incremental version matches a regular join with `edges`
+            // re-indexed by `x`.
+            let prefix_join = names.join_prefix_incremental::<_, _, _, OrdZSet<_, _>>(
+                &edges,
+                |(x, _)| x,
+                |&x, &name, &(_, y), &z| (x, name, y, z),
+            );
+            let join = names
+                .join_incremental::<_, _, OrdZSet<_, _>>(&edges_by_x, |&x, &name, &(y, z)| {
+                    (x, name, y, z)
+                });
+            prefix_join.apply2(&join, |prefix_join, join| assert_eq!(prefix_join, join));
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
 }
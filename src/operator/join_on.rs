@@ -0,0 +1,238 @@
+//! Joins specified by key extractors and projections over whole records.
+//!
+//! The closures passed to [`Stream::join`] and its variants take the join
+//! key and the values of the two indexed inputs as separate arguments, e.g.,
+//! `|&(_r, _y), &z, &x| (x, z)`, which makes it easy to mix up tuple
+//! positions without any compiler error.  [`Stream::join_on`] instead joins
+//! two Z-sets of records: the join key is extracted from each side by a
+//! separate closure, and the output is computed from the complete left and
+//! right records, whose fields can be accessed by name.
+
+use crate::{
+    algebra::{ZRingValue, ZSet},
+    circuit::{Circuit, Stream},
+    trace::ord::OrdIndexedZSet,
+};
+use deepsize::DeepSizeOf;
+use std::marker::PhantomData;
+
+impl<P, Z1> Stream<Circuit<P>, Z1>
+where
+    P: Clone + 'static,
+    Z1: ZSet,
+{
+    /// Start building a join of `self` with `other`.
+    ///
+    /// Specify the join key of each side with [`JoinOn::keys`] and the
+    /// output record with [`JoinOnKeys::project`] or
+    /// [`JoinOnKeys::project_incremental`]:
+    ///
+    /// ```
+    /// use dbsp::{circuit::Root, operator::Generator, trace::ord::OrdZSet, zset};
+    /// use deepsize::DeepSizeOf;
+    ///
+    /// #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, DeepSizeOf)]
+    /// struct Employee {
+    ///     name: &'static str,
+    ///     dept_id: u32,
+    /// }
+    ///
+    /// #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, DeepSizeOf)]
+    /// struct Dept {
+    ///     id: u32,
+    ///     name: &'static str,
+    /// }
+    ///
+    /// let root = Root::build(|circuit| {
+    ///     let employees = circuit.add_source(Generator::new(|| {
+    ///         zset! { Employee { name: "alice", dept_id: 1 } => 1isize }
+    ///     }));
+    ///     let depts = circuit.add_source(Generator::new(|| {
+    ///         zset! { Dept { id: 1, name: "sales" } => 1isize }
+    ///     }));
+    ///
+    ///     employees
+    ///         .join_on(&depts)
+    ///         .keys(|e| e.dept_id, |d| d.id)
+    ///         .project::<OrdZSet<_, _>, _>(|e, d| (e.name, d.name))
+    ///         .inspect(|zs| assert_eq!(*zs, zset! { ("alice", "sales") => 1 }));
+    /// })
+    /// .unwrap();
+    ///
+    /// root.step().unwrap();
+    /// ```
+    pub fn join_on<'a, Z2>(&'a self, other: &'a Stream<Circuit<P>, Z2>) -> JoinOn<'a, P, Z1, Z2> {
+        JoinOn {
+            left: self,
+            right: other,
+        }
+    }
+}
+
+/// A join of two streams of records without join keys.
+///
+/// Created by [`Stream::join_on`].
+pub struct JoinOn<'a, P, Z1, Z2> {
+    left: &'a Stream<Circuit<P>, Z1>,
+    right: &'a Stream<Circuit<P>, Z2>,
+}
+
+impl<'a, P, Z1, Z2> JoinOn<'a, P, Z1, Z2>
+where
+    Z1: ZSet,
+    Z2: ZSet,
+{
+    /// Join records of the left and right inputs on the keys extracted by
+    /// `left_key` and `right_key` respectively.
+    pub fn keys<K, LK, RK>(
+        self,
+        left_key: LK,
+        right_key: RK,
+    ) -> JoinOnKeys<'a, P, Z1, Z2, K, LK, RK>
+    where
+        LK: Fn(&Z1::Key) -> K,
+        RK: Fn(&Z2::Key) -> K,
+    {
+        JoinOnKeys {
+            left: self.left,
+            right: self.right,
+            left_key,
+            right_key,
+            _key: PhantomData,
+        }
+    }
+}
+
+/// A join of two streams of records with join keys.
+///
+/// Created by [`JoinOn::keys`].
+pub struct JoinOnKeys<'a, P, Z1, Z2, K, LK, RK> {
+    left: &'a Stream<Circuit<P>, Z1>,
+    right: &'a Stream<Circuit<P>, Z2>,
+    left_key: LK,
+    right_key: RK,
+    _key: PhantomData<K>,
+}
+
+impl<'a, P, Z1, Z2, K, LK, RK> JoinOnKeys<'a, P, Z1, Z2, K, LK, RK>
+where
+    P: Clone + 'static,
+    Z1: ZSet + 'static,
+    Z1::Key: Ord + Clone + DeepSizeOf,
+    Z1::R: ZRingValue + DeepSizeOf,
+    Z2: ZSet<R = Z1::R> + 'static,
+    Z2::Key: Ord + Clone + DeepSizeOf,
+    K: Ord + Clone + DeepSizeOf + Send + 'static,
+    LK: Fn(&Z1::Key) -> K + Clone + 'static,
+    RK: Fn(&Z2::Key) -> K + Clone + 'static,
+{
+    /// Join the records of the left and right inputs in each clock cycle
+    /// and compute the output records with `project` (see
+    /// [`Stream::join`]).
+    pub fn project<Z, F>(self, project: F) -> Stream<Circuit<P>, Z>
+    where
+        Z: ZSet<R = Z1::R> + Clone + 'static,
+        F: Fn(&Z1::Key, &Z2::Key) -> Z::Key + 'static,
+    {
+        let (left, right) = self.index();
+        left.join(&right, move |_key, l, r| project(l, r))
+    }
+
+    /// Incrementally join the records of the left and right inputs and
+    /// compute the output records with `project` (see
+    /// [`Stream::join_incremental`]).
+    pub fn project_incremental<Z, F>(self, project: F) -> Stream<Circuit<P>, Z>
+    where
+        Z: ZSet<R = Z1::R>,
+        F: Fn(&Z1::Key, &Z2::Key) -> Z::Key + Clone + 'static,
+    {
+        let (left, right) = self.index();
+        left.join_incremental(&right, move |_key, l, r| project(l, r))
+    }
+
+    // Index both inputs by their join keys.
+    #[allow(clippy::type_complexity)]
+    fn index(
+        self,
+    ) -> (
+        Stream<Circuit<P>, OrdIndexedZSet<K, Z1::Key, Z1::R>>,
+        Stream<Circuit<P>, OrdIndexedZSet<K, Z2::Key, Z1::R>>,
+    ) {
+        let left_key = self.left_key;
+        let right_key = self.right_key;
+        let left = self
+            .left
+            .index_with::<OrdIndexedZSet<K, Z1::Key, Z1::R>, _>(move |record| {
+                (left_key(record), record.clone())
+            });
+        let right = self
+            .right
+            .index_with::<OrdIndexedZSet<K, Z2::Key, Z1::R>, _>(move |record| {
+                (right_key(record), record.clone())
+            });
+        (left, right)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{circuit::Root, operator::Generator, trace::ord::OrdZSet, zset};
+    use deepsize::DeepSizeOf;
+
+    #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, DeepSizeOf)]
+    struct Order {
+        id: u32,
+        customer: u32,
+        amount: u64,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, DeepSizeOf)]
+    struct Customer {
+        id: u32,
+        name: &'static str,
+    }
+
+    #[test]
+    fn join_on_test() {
+        let root = Root::build(move |circuit| {
+            let mut step = 0;
+            let orders = circuit.add_source(Generator::new(move || {
+                step += 1;
+                if step == 1 {
+                    zset! {
+                        Order { id: 1, customer: 10, amount: 5 } => 1isize,
+                        Order { id: 2, customer: 20, amount: 7 } => 1,
+                    }
+                } else {
+                    zset! { Order { id: 3, customer: 10, amount: 1 } => 1 }
+                }
+            }));
+            let mut step = 0;
+            let customers = circuit.add_source(Generator::new(move || {
+                step += 1;
+                if step == 1 {
+                    zset! { Customer { id: 10, name: "a" } => 1isize }
+                } else {
+                    zset! { Customer { id: 20, name: "b" } => 1 }
+                }
+            }));
+
+            let mut expected = vec![
+                zset! { ("a", 5u64) => 1isize },
+                zset! { ("a", 1) => 1, ("b", 7) => 1 },
+            ]
+            .into_iter();
+            orders
+                .join_on(&customers)
+                .keys(|order| order.customer, |customer| customer.id)
+                .project_incremental::<OrdZSet<_, _>, _>(|order, customer| {
+                    (customer.name, order.amount)
+                })
+                .inspect(move |zs| assert_eq!(*zs, expected.next().unwrap()));
+        })
+        .unwrap();
+
+        root.step().unwrap();
+        root.step().unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+//! Operator that measures the size of each batch in a stream.
+
+use crate::{
+    algebra::{AddAssignByRef, HasZero},
+    circuit::{Circuit, Stream},
+    trace::{BatchReader, Cursor, SizeHint},
+};
+
+impl<P, B> Stream<Circuit<P>, B>
+where
+    P: Clone + 'static,
+    B: BatchReader<Time = ()> + Clone + 'static,
+{
+    /// Returns a stream of `(keys, tuples, total_weight)` triples that
+    /// describe the size of each batch in `self` (see [`batch_len`]).
+    ///
+    /// The resulting scalar stream can be fed into conditions, e.g., to
+    /// terminate an iteration once the number of new tuples drops to zero,
+    /// or into metrics, instead of inspecting the batches directly.
+    pub fn len_stream(&self) -> Stream<Circuit<P>, (usize, usize, B::R)> {
+        self.apply(batch_len)
+    }
+}
+
+/// Returns the number of distinct keys, the number of key/value tuples, and
+/// the sum of the weights of all tuples in `batch`.
+///
+/// Key and tuple counts are taken from the batch's
+/// [size hint](`BatchReader::size_hint`), which batches compute during
+/// construction, in constant time.  Only the total weight requires a pass
+/// over the weights of the batch.
+pub fn batch_len<B>(batch: &B) -> (usize, usize, B::R)
+where
+    B: BatchReader<Time = ()>,
+{
+    let SizeHint { keys, tuples } = batch.size_hint();
+    let mut weight = B::R::zero();
+
+    let mut cursor = batch.cursor();
+    while cursor.key_valid(batch) {
+        while cursor.val_valid(batch) {
+            weight.add_assign_by_ref(&cursor.weight(batch));
+            cursor.step_val(batch);
+        }
+        cursor.step_key(batch);
+    }
+
+    (keys, tuples, weight)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algebra::HasZero, circuit::Root, indexed_zset, operator::Generator, trace::ord::OrdZSet,
+        zset,
+    };
+
+    #[test]
+    fn len_stream_test() {
+        let root = Root::build(move |circuit| {
+            circuit
+                .add_source(Generator::new(
+                    || zset! { 1usize => 2isize, 2 => -1, 3 => 1 },
+                ))
+                .len_stream()
+                .inspect(|len| assert_eq!(*len, (3, 3, 2)));
+            circuit
+                .add_source(Generator::new(|| {
+                    indexed_zset! { 1usize => { 1usize => 1isize, 2 => 1 }, 2 => { 1 => -1 } }
+                }))
+                .len_stream()
+                .inspect(|len| assert_eq!(*len, (2, 3, 1)));
+            circuit
+                .add_source(Generator::new(OrdZSet::<usize, isize>::zero))
+                .len_stream()
+                .inspect(|len| assert_eq!(*len, (0, 0, 0)));
+        })
+        .unwrap();
+
+        root.step().unwrap();
+    }
+}
@@ -0,0 +1,328 @@
+//! Source operator that generates synthetic load for benchmarking.
+//!
+//! [`LoadGenerator`] produces a configurable number of updates per clock
+//! cycle, drawing keys and values from uniform or Zipf distributions and
+//! stamping each update with an event time from an embedded clock.  This
+//! allows operators to be benchmarked without external data files.
+
+use crate::{
+    algebra::HasOne,
+    circuit::{
+        operator_traits::{Data, Operator, SourceOperator},
+        Scope,
+    },
+    trace::Batch,
+};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use rand_distr::{Distribution, Zipf};
+use std::{borrow::Cow, marker::PhantomData};
+
+/// Number of updates generated per clock cycle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rate {
+    /// Generate the same number of updates in every clock cycle.
+    Constant(usize),
+    /// Linearly ramp the number of updates from `from` to `to` over `steps`
+    /// clock cycles; then keep generating `to` updates per cycle.
+    Ramp {
+        from: usize,
+        to: usize,
+        steps: usize,
+    },
+}
+
+impl Rate {
+    /// Number of updates to generate in clock cycle `step`.
+    pub fn events_at(&self, step: usize) -> usize {
+        match *self {
+            Self::Constant(n) => n,
+            Self::Ramp { from, to, steps } => {
+                if step >= steps {
+                    to
+                } else if to >= from {
+                    from + (to - from) * step / steps
+                } else {
+                    from - (from - to) * step / steps
+                }
+            }
+        }
+    }
+}
+
+/// Distribution of generated keys or values.
+///
+/// Both distributions produce integers in the range `[0, range)`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataDistribution {
+    /// Every integer in the range is equally likely.
+    Uniform { range: u64 },
+    /// Zipf distribution with the specified exponent; smaller integers are
+    /// more likely.
+    Zipf { range: u64, exponent: f64 },
+}
+
+/// Sampler instantiated from a [`DataDistribution`].
+#[derive(Clone)]
+pub(crate) enum Sampler {
+    Uniform(u64),
+    Zipf(Zipf<f64>),
+}
+
+impl Sampler {
+    pub(crate) fn new(distribution: &DataDistribution) -> Self {
+        match *distribution {
+            DataDistribution::Uniform { range } => {
+                assert!(range > 0, "empty range in uniform distribution");
+                Self::Uniform(range)
+            }
+            DataDistribution::Zipf { range, exponent } => Self::Zipf(
+                Zipf::new(range, exponent)
+                    .unwrap_or_else(|e| panic!("invalid Zipf distribution: {:?}", e)),
+            ),
+        }
+    }
+
+    pub(crate) fn sample(&self, rng: &mut SmallRng) -> u64 {
+        match self {
+            Self::Uniform(range) => rng.gen_range(0..*range),
+            // Zipf samples are in `[1, range]`.
+            Self::Zipf(zipf) => zipf.sample(rng) as u64 - 1,
+        }
+    }
+}
+
+/// A source operator that generates synthetic updates.
+///
+/// In each clock cycle, the operator generates [`Rate::events_at`] updates
+/// with unit weights.  For each update it samples a key and a value from the
+/// configured distributions and assigns an event time from its clock, which
+/// advances by a fixed interval per clock cycle, spreading updates generated
+/// within the cycle evenly across the interval.  A user-provided closure
+/// converts the `(key, value, time)` triple into a record of the output
+/// batch.
+///
+/// # Examples
+///
+/// ```
+/// # use dbsp::{
+/// #     circuit::Root,
+/// #     operator::{DataDistribution, LoadGenerator, Rate},
+/// #     trace::ord::OrdIndexedZSet,
+/// # };
+/// let root = Root::build(move |circuit| {
+///     // Generate between 100 and 1000 `(user, (item, time))` pairs per step,
+///     // with Zipf-distributed user ids.
+///     let generator = LoadGenerator::<OrdIndexedZSet<u64, (u64, u64), isize>, _>::new(
+///         |user: u64, item: u64, time: u64| (user, (item, time)),
+///     )
+///     .with_rate(Rate::Ramp {
+///         from: 100,
+///         to: 1000,
+///         steps: 10,
+///     })
+///     .with_keys(DataDistribution::Zipf {
+///         range: 10_000,
+///         exponent: 1.1,
+///     })
+///     .with_values(DataDistribution::Uniform { range: 1000 });
+///     circuit.add_source(generator);
+/// })
+/// .unwrap();
+///
+/// for _ in 0..20 {
+///     root.step().unwrap();
+/// }
+/// ```
+pub struct LoadGenerator<Z, F> {
+    record: F,
+    rate: Rate,
+    keys: Sampler,
+    values: Sampler,
+    rng: SmallRng,
+    seed: u64,
+    start_time: u64,
+    interval: u64,
+    // Current clock cycle.
+    step: usize,
+    _type: PhantomData<Z>,
+}
+
+impl<Z, F> LoadGenerator<Z, F> {
+    /// Create a load generator that generates one update per clock cycle
+    /// with keys and values uniformly distributed in `[0, 1000)`, using
+    /// `record` to convert a `(key, value, time)` triple into a record.
+    ///
+    /// The event time clock starts at 0 and advances by 1000 per cycle.
+    pub fn new(record: F) -> Self {
+        Self {
+            record,
+            rate: Rate::Constant(1),
+            keys: Sampler::new(&DataDistribution::Uniform { range: 1000 }),
+            values: Sampler::new(&DataDistribution::Uniform { range: 1000 }),
+            rng: SmallRng::seed_from_u64(0),
+            seed: 0,
+            start_time: 0,
+            interval: 1000,
+            step: 0,
+            _type: PhantomData,
+        }
+    }
+
+    /// Set the number of updates generated per clock cycle.
+    pub fn with_rate(mut self, rate: Rate) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Set the distribution of generated keys.
+    pub fn with_keys(mut self, keys: DataDistribution) -> Self {
+        self.keys = Sampler::new(&keys);
+        self
+    }
+
+    /// Set the distribution of generated values.
+    pub fn with_values(mut self, values: DataDistribution) -> Self {
+        self.values = Sampler::new(&values);
+        self
+    }
+
+    /// Set the initial event time and the amount by which the clock advances
+    /// in each clock cycle.
+    pub fn with_clock(mut self, start_time: u64, interval: u64) -> Self {
+        self.start_time = start_time;
+        self.interval = interval;
+        self
+    }
+
+    /// Seed the random number generator, making the generated sequence
+    /// reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self.rng = SmallRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Event time at the start of the current clock cycle.
+    pub fn current_time(&self) -> u64 {
+        self.start_time + self.interval * self.step as u64
+    }
+}
+
+impl<Z, F> Operator for LoadGenerator<Z, F>
+where
+    Z: Data,
+    F: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("LoadGenerator")
+    }
+
+    fn clock_start(&mut self, scope: Scope) {
+        if scope == 0 {
+            self.step = 0;
+            self.rng = SmallRng::seed_from_u64(self.seed);
+        }
+    }
+
+    fn fixedpoint(&self) -> bool {
+        false
+    }
+}
+
+impl<Z, F> SourceOperator<Z> for LoadGenerator<Z, F>
+where
+    Z: Batch<Time = ()> + Data,
+    Z::R: HasOne,
+    F: FnMut(u64, u64, u64) -> (Z::Key, Z::Val) + 'static,
+{
+    fn eval(&mut self) -> Z {
+        let events = self.rate.events_at(self.step);
+        let time = self.current_time();
+
+        let mut tuples = Vec::with_capacity(events);
+        for i in 0..events {
+            let key = self.keys.sample(&mut self.rng);
+            let val = self.values.sample(&mut self.rng);
+            let event_time = time + self.interval * i as u64 / events as u64;
+            tuples.push(((self.record)(key, val, event_time), Z::R::one()));
+        }
+
+        self.step += 1;
+        Z::from_tuples((), tuples)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DataDistribution, LoadGenerator, Rate};
+    use crate::{
+        circuit::Root,
+        trace::{ord::OrdZSet, BatchReader, Cursor},
+    };
+
+    #[test]
+    fn rate() {
+        assert_eq!(Rate::Constant(5).events_at(100), 5);
+
+        let ramp_up = Rate::Ramp {
+            from: 10,
+            to: 20,
+            steps: 10,
+        };
+        assert_eq!(ramp_up.events_at(0), 10);
+        assert_eq!(ramp_up.events_at(5), 15);
+        assert_eq!(ramp_up.events_at(10), 20);
+        assert_eq!(ramp_up.events_at(100), 20);
+
+        let ramp_down = Rate::Ramp {
+            from: 20,
+            to: 10,
+            steps: 10,
+        };
+        assert_eq!(ramp_down.events_at(0), 20);
+        assert_eq!(ramp_down.events_at(5), 15);
+        assert_eq!(ramp_down.events_at(100), 10);
+    }
+
+    #[test]
+    fn load_generator() {
+        let root = Root::build(move |circuit| {
+            let generator = LoadGenerator::<OrdZSet<(u64, u64, u64), isize>, _>::new(
+                |key: u64, val: u64, time: u64| ((key, val, time), ()),
+            )
+            .with_rate(Rate::Ramp {
+                from: 100,
+                to: 200,
+                steps: 10,
+            })
+            .with_keys(DataDistribution::Zipf {
+                range: 50,
+                exponent: 1.5,
+            })
+            .with_values(DataDistribution::Uniform { range: 10 })
+            .with_clock(1000, 100)
+            .with_seed(42);
+
+            let mut step = 0;
+            circuit.add_source(generator).inspect(move |batch| {
+                let mut total = 0;
+                let mut cursor = batch.cursor();
+                while cursor.key_valid(batch) {
+                    let (key, val, time) = *cursor.key(batch);
+                    assert!(key < 50);
+                    assert!(val < 10);
+                    assert!(time >= 1000 + 100 * step && time < 1000 + 100 * (step + 1));
+                    total += cursor.weight(batch);
+                    cursor.step_key(batch);
+                }
+                assert_eq!(total as usize, 100 + 10 * step.min(10) as usize);
+                step += 1;
+            });
+        })
+        .unwrap();
+
+        for _ in 0..20 {
+            root.step().unwrap();
+        }
+    }
+}
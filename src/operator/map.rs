@@ -56,6 +56,22 @@ where
     {
         self.circuit().add_unary_operator(MapValues::new(map), self)
     }
+
+    /// Apply [`MapKeysInPlace`] operator to `self`.
+    ///
+    /// Like [`Self::map_keys_owned`], but the output batch has the same type
+    /// as the input batch, which allows the operator to map keys of input
+    /// batches that it owns in place.
+    pub fn map_keys_in_place<F>(&self, map: F) -> Stream<Circuit<P>, B>
+    where
+        B: Batch<Time = ()> + 'static,
+        B::Key: Clone,
+        B::Val: Clone,
+        F: Fn(B::Key) -> B::Key + 'static,
+    {
+        self.circuit()
+            .add_unary_operator(MapKeysInPlace::new(map), self)
+    }
 }
 
 /// Operator that applies a user-defined function to each value in a collection
@@ -117,6 +133,10 @@ where
     FB: Fn(&CI::Key) -> CO::Key + 'static,
     FO: Fn(CI::Key) -> CO::Key + 'static,
 {
+    fn idle_output(&mut self) -> Option<CO> {
+        Some(CO::empty(()))
+    }
+
     fn eval(&mut self, i: &CI) -> CO {
         let mut batch = Vec::with_capacity(i.len());
 
@@ -140,6 +160,88 @@ where
     }
 }
 
+/// Operator that applies a user-defined function to each key in a batch of
+/// key/value pairs, producing a batch of the same type.
+///
+/// When the operator owns its input batch and the batch type supports it
+/// (see [`Batch::map_keys_in_place`]), keys are mapped in place, reusing the
+/// storage of the input batch.  Otherwise, the operator builds a new batch
+/// like [`MapKeys`].
+///
+/// # Type arguments
+///
+/// * `B` - batch type.
+/// * `F` - key mapping function type.
+pub struct MapKeysInPlace<B, F>
+where
+    F: 'static,
+{
+    map: F,
+    _type: PhantomData<B>,
+}
+
+impl<B, F> MapKeysInPlace<B, F>
+where
+    F: 'static,
+{
+    pub fn new(map: F) -> Self {
+        Self {
+            map,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<B, F> Operator for MapKeysInPlace<B, F>
+where
+    B: 'static,
+    F: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("MapKeysInPlace")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<B, F> UnaryOperator<B, B> for MapKeysInPlace<B, F>
+where
+    B: Batch<Time = ()> + 'static,
+    B::Key: Clone,
+    B::Val: Clone,
+    F: Fn(B::Key) -> B::Key + 'static,
+{
+    fn idle_output(&mut self) -> Option<B> {
+        Some(B::empty(()))
+    }
+
+    fn eval(&mut self, i: &B) -> B {
+        let mut batch = Vec::with_capacity(i.len());
+
+        let mut cursor = i.cursor();
+        while cursor.key_valid(i) {
+            let k = cursor.key(i);
+            while cursor.val_valid(i) {
+                let v = cursor.val(i);
+                let w = cursor.weight(i);
+                batch.push((((self.map)(k.clone()), v.clone()), w.clone()));
+                cursor.step_val(i);
+            }
+            cursor.step_key(i);
+        }
+        B::from_tuples((), batch)
+    }
+
+    fn eval_owned(&mut self, mut i: B) -> B {
+        if i.map_keys_in_place(&self.map) {
+            i
+        } else {
+            self.eval(&i)
+        }
+    }
+}
+
 /// Operator that applies a user-defined function to each value in a collection
 /// of key/value pairs.
 ///
@@ -193,6 +295,10 @@ where
     CO: Batch<Key = CI::Key, Time = (), R = CI::R> + 'static,
     F: Fn(&CI::Key, &CI::Val) -> CO::Val + 'static,
 {
+    fn idle_output(&mut self) -> Option<CO> {
+        Some(CO::empty(()))
+    }
+
     fn eval(&mut self, i: &CI) -> CO {
         let mut batch = Vec::with_capacity(i.len());
 
@@ -297,4 +403,32 @@ mod test {
             root.step().unwrap();
         }
     }
+
+    #[test]
+    fn map_keys_in_place_test() {
+        let root = Root::build(move |circuit| {
+            let owned: Stream<_, OrdZSet<isize, isize>> =
+                circuit.add_source(Generator::new(|| zset! { 1 => 1, -1 => 1, 5 => -1 }));
+
+            // `map_keys_in_place` is the only consumer of `owned`, so it
+            // receives owned batches and maps their keys in place.  The map is
+            // not monotonic and merges keys.
+            owned
+                .map_keys_in_place(|n| n.abs())
+                .inspect(|zs| assert_eq!(*zs, zset! { 1 => 2, 5 => -1 }));
+
+            // A shared input is mapped by reference.
+            let shared: Stream<_, OrdZSet<isize, isize>> =
+                circuit.add_source(Generator::new(|| zset! { 1 => 1, -1 => 1, 5 => -1 }));
+            shared
+                .map_keys_in_place(|n| n * 2)
+                .inspect(|zs| assert_eq!(*zs, zset! { 2 => 1, -2 => 1, 10 => -1 }));
+            shared.inspect(|zs| assert_eq!(*zs, zset! { 1 => 1, -1 => 1, 5 => -1 }));
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
 }
@@ -0,0 +1,212 @@
+//! Recursion helpers for (min, +) and (max, +) style computations.
+
+use crate::{
+    algebra::ZRingValue,
+    circuit::{schedule::Error as SchedulerError, Circuit, Stream},
+    operator::DelayedFeedback,
+    trace::ord::{OrdIndexedZSet, OrdZSet},
+};
+use deepsize::DeepSizeOf;
+
+impl<P, K, V, R> Stream<Circuit<P>, OrdIndexedZSet<K, V, R>>
+where
+    P: Clone + 'static,
+    K: Ord + Clone + DeepSizeOf + Send + 'static,
+    V: Ord + Clone + DeepSizeOf + Send + 'static,
+    R: ZRingValue + DeepSizeOf + Send,
+{
+    /// Compute the least fixed point of a (min, +)-style recursion.
+    ///
+    /// `self` is a stream of changes to the set of seed values, e.g., `(source,
+    /// 0)` for a single-source shortest path computation.  `edges` is a stream
+    /// of changes to a relation indexed by the same key, e.g., edges of a
+    /// graph indexed by source node.  For each value `(k, v)` computed so far
+    /// and each edge `(k, e)`, `step(k, v, e)` computes a new candidate
+    /// value, e.g., `(to, v + weight)`.  The operator assigns each key the
+    /// smallest of its candidate values and iterates until no key improves.
+    ///
+    /// Returns a stream of changes to the indexed Z-set that maps each key to
+    /// its smallest value.  Only one value is kept per key at each iteration,
+    /// so unlike an encoding of distances as part of the key followed by
+    /// `distinct`, the computation never enumerates non-optimal paths.
+    ///
+    /// The recursion is only guaranteed to converge if `step` cannot
+    /// decrease values indefinitely (e.g., there are no negative cycles).
+    #[allow(clippy::type_complexity)]
+    pub fn iterate_min_by_key<E, F>(
+        &self,
+        edges: &Stream<Circuit<P>, OrdIndexedZSet<K, E, R>>,
+        step: F,
+    ) -> Result<Stream<Circuit<P>, OrdIndexedZSet<K, V, R>>, SchedulerError>
+    where
+        E: Ord + Clone + DeepSizeOf + Send + 'static,
+        F: Fn(&K, &V, &E) -> (K, V) + Clone + 'static,
+    {
+        self.iterate_extremum_by_key(edges, step, true)
+    }
+
+    /// Compute the least fixed point of a (max, +)-style recursion.
+    ///
+    /// Like [`Self::iterate_min_by_key`], but assigns each key the largest of
+    /// its candidate values, e.g., to compute widest or longest paths.
+    #[allow(clippy::type_complexity)]
+    pub fn iterate_max_by_key<E, F>(
+        &self,
+        edges: &Stream<Circuit<P>, OrdIndexedZSet<K, E, R>>,
+        step: F,
+    ) -> Result<Stream<Circuit<P>, OrdIndexedZSet<K, V, R>>, SchedulerError>
+    where
+        E: Ord + Clone + DeepSizeOf + Send + 'static,
+        F: Fn(&K, &V, &E) -> (K, V) + Clone + 'static,
+    {
+        self.iterate_extremum_by_key(edges, step, false)
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn iterate_extremum_by_key<E, F>(
+        &self,
+        edges: &Stream<Circuit<P>, OrdIndexedZSet<K, E, R>>,
+        step: F,
+        min: bool,
+    ) -> Result<Stream<Circuit<P>, OrdIndexedZSet<K, V, R>>, SchedulerError>
+    where
+        E: Ord + Clone + DeepSizeOf + Send + 'static,
+        F: Fn(&K, &V, &E) -> (K, V) + Clone + 'static,
+    {
+        let values = self.circuit().fixedpoint(|child| {
+            let seeds = self.delta0(child);
+            let edges = edges.delta0(child);
+
+            let values_delayed = <DelayedFeedback<_, OrdIndexedZSet<K, V, R>>>::new(child);
+
+            let candidates: Stream<_, OrdZSet<(K, V), R>> =
+                values_delayed.stream().join_trace(&edges, step);
+            let candidates: Stream<_, OrdIndexedZSet<K, V, R>> = candidates.index();
+
+            // Values in each group are sorted, so the extremum is either the
+            // first or the last one.
+            let values: Stream<_, OrdZSet<(K, V), R>> = seeds.plus(&candidates).aggregate_nested(
+                move |key: &K, vals: &mut Vec<(&V, R)>| {
+                    let extremum = if min { vals.first() } else { vals.last() };
+                    let (val, _) = extremum.unwrap();
+                    (key.clone(), (*val).clone())
+                },
+            );
+            let values: Stream<_, OrdIndexedZSet<K, V, R>> = values.index();
+            values_delayed.connect(&values);
+
+            Ok(values.integrate_trace().export())
+        })?;
+
+        Ok(values.consolidate())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::vec;
+
+    use crate::{
+        circuit::{Root, Stream},
+        indexed_zset,
+        operator::Generator,
+        trace::ord::{OrdIndexedZSet, OrdZSet},
+        zset,
+    };
+
+    #[test]
+    #[allow(clippy::type_complexity)]
+    fn iterate_min_by_key_test() {
+        let root = Root::build(move |circuit| {
+            // Changes to the weighted edges relation `(from, to, weight)`.
+            let mut edges: vec::IntoIter<OrdZSet<(usize, (usize, usize)), isize>> = vec![
+                zset! { (1, (2, 1)) => 1, (2, (3, 1)) => 1, (1, (3, 5)) => 1 },
+                zset! { (3, (1, 1)) => 1, (3, (4, 2)) => 1 },
+                zset! { (2, (3, 1)) => -1 },
+                zset! { (1, (4, 1)) => 1 },
+            ]
+            .into_iter();
+
+            let mut sources: vec::IntoIter<OrdZSet<(usize, usize), isize>> =
+                vec![zset! { (1, 0) => 1 }].into_iter();
+
+            // Expected shortest distances from node 1.
+            let mut outputs: vec::IntoIter<OrdIndexedZSet<usize, usize, isize>> = vec![
+                indexed_zset! { 1 => { 0 => 1 }, 2 => { 1 => 1 }, 3 => { 2 => 1 } },
+                indexed_zset! { 1 => { 0 => 1 }, 2 => { 1 => 1 }, 3 => { 2 => 1 }, 4 => { 4 => 1 } },
+                indexed_zset! { 1 => { 0 => 1 }, 2 => { 1 => 1 }, 3 => { 5 => 1 }, 4 => { 7 => 1 } },
+                indexed_zset! { 1 => { 0 => 1 }, 2 => { 1 => 1 }, 3 => { 5 => 1 }, 4 => { 1 => 1 } },
+            ]
+            .into_iter();
+
+            let edges: Stream<_, OrdIndexedZSet<usize, (usize, usize), isize>> = circuit
+                .add_source(Generator::new(move || edges.next().unwrap()))
+                .index();
+            let sources: Stream<_, OrdIndexedZSet<usize, usize, isize>> = circuit
+                .add_source(Generator::new(move || {
+                    sources.next().unwrap_or_else(|| zset! {})
+                }))
+                .index();
+
+            sources
+                .iterate_min_by_key(&edges, |_from, distance, (to, weight)| {
+                    (*to, distance + weight)
+                })
+                .unwrap()
+                .integrate()
+                .inspect(move |distances| assert_eq!(*distances, outputs.next().unwrap()));
+        })
+        .unwrap();
+
+        for _ in 0..4 {
+            root.step().unwrap();
+        }
+    }
+
+    #[test]
+    #[allow(clippy::type_complexity)]
+    fn iterate_max_by_key_test() {
+        let root = Root::build(move |circuit| {
+            // Changes to the edges relation `(from, to, capacity)`.
+            let mut edges: vec::IntoIter<OrdZSet<(usize, (usize, usize)), isize>> = vec![
+                zset! { (1, (2, 5)) => 1, (2, (3, 3)) => 1, (1, (3, 2)) => 1 },
+                zset! { (3, (1, 50)) => 1, (3, (4, 10)) => 1, (1, (4, 1)) => 1 },
+                zset! { (2, (3, 3)) => -1 },
+            ]
+            .into_iter();
+
+            let mut sources: vec::IntoIter<OrdZSet<(usize, usize), isize>> =
+                vec![zset! { (1, 100) => 1 }].into_iter();
+
+            // Expected widest path capacities from node 1.
+            let mut outputs: vec::IntoIter<OrdIndexedZSet<usize, usize, isize>> = vec![
+                indexed_zset! { 1 => { 100 => 1 }, 2 => { 5 => 1 }, 3 => { 3 => 1 } },
+                indexed_zset! { 1 => { 100 => 1 }, 2 => { 5 => 1 }, 3 => { 3 => 1 }, 4 => { 3 => 1 } },
+                indexed_zset! { 1 => { 100 => 1 }, 2 => { 5 => 1 }, 3 => { 2 => 1 }, 4 => { 2 => 1 } },
+            ]
+            .into_iter();
+
+            let edges: Stream<_, OrdIndexedZSet<usize, (usize, usize), isize>> = circuit
+                .add_source(Generator::new(move || edges.next().unwrap()))
+                .index();
+            let sources: Stream<_, OrdIndexedZSet<usize, usize, isize>> = circuit
+                .add_source(Generator::new(move || {
+                    sources.next().unwrap_or_else(|| zset! {})
+                }))
+                .index();
+
+            sources
+                .iterate_max_by_key(&edges, |_from, width, (to, capacity)| {
+                    (*to, *width.min(capacity))
+                })
+                .unwrap()
+                .integrate()
+                .inspect(move |widths| assert_eq!(*widths, outputs.next().unwrap()));
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
+}
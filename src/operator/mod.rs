@@ -4,7 +4,16 @@ pub mod adapter;
 pub use adapter::{BinaryOperatorAdapter, UnaryOperatorAdapter};
 
 pub(crate) mod inspect;
-pub use inspect::Inspect;
+pub use inspect::{Inspect, InspectMeta, InspectWithMeta};
+
+mod consume;
+pub use consume::Consume;
+
+mod check_incremental;
+pub use check_incremental::INCREMENTAL_CHECKS;
+
+mod expect;
+pub use expect::{Expect, Expectation, ExpectationFailure};
 
 pub(crate) mod apply;
 pub use apply::Apply;
@@ -16,21 +25,36 @@ mod plus;
 pub use plus::{Minus, Plus};
 
 mod z1;
-pub use z1::{DelayedFeedback, DelayedNestedFeedback, Z1Nested, Z1};
+pub use z1::{DelayedFeedback, DelayedNestedFeedback, Z1Handle, Z1Nested, Z1};
 
 mod generator;
 pub use generator::{Generator, GeneratorNested};
 
+mod input;
+pub use input::{InputHandle, InputTransaction};
+
+mod reorder;
+pub use reorder::{ReorderBuffer, ReorderingInputHandle};
+
+mod event_time;
+pub use event_time::{Timestamped, WithTimestamp};
+
+pub(crate) mod load_generator;
+pub use load_generator::{DataDistribution, LoadGenerator, Rate};
+
 mod consolidate;
 mod integrate;
 mod trace;
 
+mod bounded_trace;
+pub use bounded_trace::{BoundedStepsTraceAppend, HorizonTraceAppend};
+
 pub mod communication;
 
 mod differentiate;
 
 mod filter;
-pub use filter::FilterKeys;
+pub use filter::{FilterKeys, FilterKeysInPlace};
 
 mod delta0;
 pub use delta0::Delta0;
@@ -42,24 +66,97 @@ mod index;
 pub use index::Index;
 
 mod join;
-pub use join::Join;
+pub use join::{Antijoin, CrossJoin, DistinctJoin, Join, PrefixJoin};
+
+mod join_on;
+pub use join_on::{JoinOn, JoinOnKeys};
+
+mod band_join;
+pub use band_join::BandJoin;
+
+mod arrangement_set;
+pub use arrangement_set::{ArrangementSet, DEFAULT_SAMPLE_INTERVAL, PRIMARY_ARRANGEMENT};
+
+mod state_budget;
+pub use state_budget::{BudgetExceeded, StateBudget};
 
 mod sum;
-pub use sum::Sum;
+pub use sum::{BatchSum, Sum};
 
 mod distinct;
-pub use distinct::Distinct;
+pub use distinct::{Distinct, Threshold};
 
 mod map;
-pub use map::{MapKeys, MapValues};
+pub use map::{MapKeys, MapKeysInPlace, MapValues};
 
 mod filter_map;
 pub use filter_map::FilterMapKeys;
 
+mod reload;
+pub use reload::UdfHandle;
+
+mod param;
+pub use param::{FilterKeysParam, ParamHandle, ParamStream};
+
+mod partition;
+pub use partition::Partition;
+
+mod throttle;
+pub use throttle::Throttle;
+
+mod backfill;
+pub use backfill::{Backfill, BackfillHandle};
+
 mod aggregate;
 pub use aggregate::Aggregate;
 
+mod count_distinct;
+pub use count_distinct::{ApproxCountDistinct, HyperLogLog};
+
+mod topk;
+pub use topk::TopK;
+
+mod pivot;
+pub use pivot::Unpivot;
+
+mod histogram;
+pub use histogram::Buckets;
+
+mod stratify;
+
+mod min_plus;
+
+mod group_transform;
+pub use group_transform::{GroupCursor, GroupTransform};
+
+mod explode;
+pub use explode::{Explode, WithMultiplicity};
+
+mod change_records;
+pub use change_records::{change_records, ChangeRecord};
+
+mod len;
+pub use len::batch_len;
+
 #[cfg(feature = "with-csv")]
 mod csv;
 #[cfg(feature = "with-csv")]
 pub use self::csv::CsvSource;
+
+#[cfg(feature = "with-bincode")]
+mod codec;
+
+#[cfg(feature = "with-bincode")]
+mod tcp;
+#[cfg(feature = "with-bincode")]
+pub use tcp::{TcpSink, TcpSource};
+
+#[cfg(feature = "with-bincode")]
+mod recorder;
+#[cfg(feature = "with-bincode")]
+pub use recorder::{GoldenChecker, Recorder, UPDATE_GOLDEN_ENV};
+
+#[cfg(feature = "with-bincode")]
+mod delta_html;
+#[cfg(feature = "with-bincode")]
+pub use delta_html::DeltaVisualizer;
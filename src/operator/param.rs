@@ -0,0 +1,303 @@
+//! Parameter streams.
+//!
+//! A parameter stream carries a scalar configuration value, such as a filter
+//! threshold or a window size, that operators consume as an additional
+//! input.  This makes reconfiguration part of the dataflow: a new value
+//! pushed to the stream reaches all operators that depend on it at the same
+//! clock cycle, and parameters can be computed by the circuit itself, e.g.,
+//! derived from an aggregate over the input data.
+
+use crate::{
+    circuit::{
+        operator_traits::{BinaryOperator, Data, Operator, SourceOperator},
+        Circuit, Stream,
+    },
+    trace::{Batch, BatchReader, Builder, Cursor},
+};
+use std::{borrow::Cow, cell::RefCell, marker::PhantomData, rc::Rc};
+
+/// A stream of configuration values of type `T`.
+///
+/// The stream yields the current value of the parameter at every clock
+/// cycle.  Parameter streams are created with [`Circuit::add_param`] for
+/// values controlled by the application or with [`Stream::as_param`] for
+/// values computed by the circuit.
+pub struct ParamStream<P, T> {
+    stream: Stream<Circuit<P>, T>,
+}
+
+impl<P, T> Clone for ParamStream<P, T> {
+    fn clone(&self) -> Self {
+        Self {
+            stream: self.stream.clone(),
+        }
+    }
+}
+
+impl<P, T> ParamStream<P, T> {
+    /// The underlying stream of parameter values.
+    pub fn stream(&self) -> &Stream<Circuit<P>, T> {
+        &self.stream
+    }
+}
+
+/// Handle used to update a parameter created with [`Circuit::add_param`].
+///
+/// Handles are cheap to clone; all clones update the same parameter.
+pub struct ParamHandle<T> {
+    value: Rc<RefCell<T>>,
+}
+
+impl<T> Clone for ParamHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<T> ParamHandle<T>
+where
+    T: Clone,
+{
+    /// Set the value of the parameter.  The new value is observed by the
+    /// circuit starting from the next clock cycle.
+    pub fn set(&self, value: T) {
+        *self.value.borrow_mut() = value;
+    }
+
+    /// Current value of the parameter.
+    pub fn get(&self) -> T {
+        self.value.borrow().clone()
+    }
+}
+
+impl<P> Circuit<P>
+where
+    P: Clone + 'static,
+{
+    /// Create a parameter stream with initial value `initial` along with a
+    /// handle used to update the value between clock cycles.
+    ///
+    /// Each worker of a multi-worker [`Runtime`](`crate::circuit::Runtime`)
+    /// owns a separate copy of the parameter, so the application must update
+    /// the handles of all workers to change the parameter consistently.
+    pub fn add_param<T>(&self, initial: T) -> (ParamStream<P, T>, ParamHandle<T>)
+    where
+        T: Data,
+    {
+        let handle = ParamHandle {
+            value: Rc::new(RefCell::new(initial)),
+        };
+        let stream = self.add_source(Param::new(handle.value.clone()));
+        (ParamStream { stream }, handle)
+    }
+}
+
+impl<P, T> Stream<Circuit<P>, T>
+where
+    P: Clone + 'static,
+{
+    /// Use the values of `self` as a parameter of other operators.
+    pub fn as_param(&self) -> ParamStream<P, T> {
+        ParamStream {
+            stream: self.clone(),
+        }
+    }
+}
+
+impl<P, CI> Stream<Circuit<P>, CI>
+where
+    CI: Clone,
+    P: Clone + 'static,
+{
+    /// Apply [`FilterKeysParam`] operator to `self` and `param`.
+    ///
+    /// Like [`Self::filter_keys`], but the `func` closure additionally takes
+    /// the current value of `param`.  Since `self` is usually a stream of
+    /// changes, a new parameter value only affects changes that arrive after
+    /// the update.
+    pub fn filter_keys_param<CO, T, F>(
+        &self,
+        param: &ParamStream<P, T>,
+        func: F,
+    ) -> Stream<Circuit<P>, CO>
+    where
+        CI: BatchReader<Time = ()> + 'static,
+        CI::Key: Clone,
+        CI::Val: Clone,
+        CO: Batch<Key = CI::Key, Val = CI::Val, Time = (), R = CI::R> + Clone + 'static,
+        T: Clone + 'static,
+        F: Fn(&CI::Key, &T) -> bool + 'static,
+    {
+        self.circuit()
+            .add_binary_operator(FilterKeysParam::new(func), self, param.stream())
+    }
+}
+
+// Source operator that yields the current value of a parameter.
+struct Param<T> {
+    value: Rc<RefCell<T>>,
+}
+
+impl<T> Param<T> {
+    fn new(value: Rc<RefCell<T>>) -> Self {
+        Self { value }
+    }
+}
+
+impl<T> Operator for Param<T>
+where
+    T: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("Param")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<T> SourceOperator<T> for Param<T>
+where
+    T: Data,
+{
+    fn eval(&mut self) -> T {
+        self.value.borrow().clone()
+    }
+}
+
+/// Operator that filters a collection of key/value pairs based on keys and a
+/// parameter.
+///
+/// The operator applies a filtering function to each key in the input batch
+/// and the current value of the parameter and builds an output batch
+/// containing only the elements that satisfy the filter condition.
+///
+/// # Type arguments
+///
+/// * `CI` - input collection type.
+/// * `CO` - output collection type.
+/// * `T` - parameter type.
+/// * `F` - filtering function type.
+pub struct FilterKeysParam<CI, CO, T, F>
+where
+    F: 'static,
+{
+    filter: F,
+    _type: PhantomData<(CI, CO, T)>,
+}
+
+impl<CI, CO, T, F> FilterKeysParam<CI, CO, T, F>
+where
+    F: 'static,
+{
+    pub fn new(filter: F) -> Self {
+        Self {
+            filter,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<CI, CO, T, F> Operator for FilterKeysParam<CI, CO, T, F>
+where
+    CI: 'static,
+    CO: 'static,
+    T: 'static,
+    F: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("FilterKeysParam")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<CI, CO, T, F> BinaryOperator<CI, T, CO> for FilterKeysParam<CI, CO, T, F>
+where
+    CI: BatchReader<Time = ()> + 'static,
+    CI::Key: Clone,
+    CI::Val: Clone,
+    CO: Batch<Key = CI::Key, Val = CI::Val, Time = (), R = CI::R> + 'static,
+    T: 'static,
+    F: Fn(&CI::Key, &T) -> bool + 'static,
+{
+    fn idle_output(&mut self, lhs_idle: bool, _rhs_idle: bool) -> Option<CO> {
+        lhs_idle.then(|| CO::empty(()))
+    }
+
+    fn eval(&mut self, i: &CI, param: &T) -> CO {
+        let mut cursor = i.cursor();
+        let mut builder = CO::Builder::with_size_hint((), i.size_hint());
+
+        while cursor.key_valid(i) {
+            let k = cursor.key(i);
+            if (self.filter)(k, param) {
+                while cursor.val_valid(i) {
+                    let val = cursor.val(i);
+                    let w = cursor.weight(i);
+                    builder.push((k.clone(), val.clone(), w.clone()));
+                    cursor.step_val(i);
+                }
+            }
+            cursor.step_key(i);
+        }
+        builder.done()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{circuit::Root, operator::Generator, trace::ord::OrdZSet, zset};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn param_test() {
+        let filtered = Rc::new(RefCell::new(Vec::new()));
+        let filtered_clone = filtered.clone();
+        let windows = Rc::new(RefCell::new(Vec::new()));
+        let windows_clone = windows.clone();
+
+        let mut handles = None;
+        let root = Root::build(|circuit| {
+            let mut step = 0;
+            let input = circuit.add_source(Generator::new(move || {
+                step += 1;
+                if step == 1 {
+                    zset! { 1usize => 1isize, 5 => 1, 10 => 1 }
+                } else {
+                    zset! { 2 => 1, 6 => 1 }
+                }
+            }));
+            let (threshold, threshold_handle) = circuit.add_param(1usize);
+            let (window, window_handle) = circuit.add_param((2usize, 0usize));
+
+            input
+                .filter_keys_param::<OrdZSet<_, _>, _, _>(&threshold, |x, t| x > t)
+                .inspect(move |zs| filtered_clone.borrow_mut().push(zs.clone()));
+            input
+                .topk_param(&window, |x, y| y.cmp(x))
+                .integrate()
+                .inspect(move |zs| windows_clone.borrow_mut().push(zs.clone()));
+            handles = Some((threshold_handle, window_handle));
+        })
+        .unwrap();
+        let (threshold, window) = handles.unwrap();
+
+        root.step().unwrap();
+        threshold.set(5);
+        window.set((3, 0));
+        root.step().unwrap();
+
+        assert_eq!(
+            *filtered.borrow(),
+            vec![zset! { 5 => 1, 10 => 1 }, zset! { 6 => 1 }]
+        );
+        assert_eq!(
+            *windows.borrow(),
+            vec![zset! { 10 => 1, 5 => 1 }, zset! { 10 => 1, 6 => 1, 5 => 1 }]
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Operator that splits a stream of batches into multiple streams.
+
+use crate::{
+    circuit::{
+        operator_traits::{Operator, UnaryOperator},
+        Circuit, Stream,
+    },
+    trace::{cursor::Cursor, Batch, Builder},
+};
+use std::{array, borrow::Cow, marker::PhantomData};
+
+impl<P, B> Stream<Circuit<P>, B>
+where
+    P: Clone + 'static,
+    B: Batch<Time = ()> + Clone + 'static,
+    B::Key: Clone,
+    B::Val: Clone,
+{
+    /// Split `self` into `N` streams using the `router` function, which maps
+    /// each key/value pair to the index of its output stream.
+    ///
+    /// This is equivalent to `N` applications of
+    /// [`filter_keys`](`Stream::filter_keys`) with disjoint predicates, but
+    /// scans the input only once.  See [`Partition`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics at runtime if `router` returns an index greater than or equal
+    /// to `N`.
+    pub fn partition<const N: usize, F>(&self, router: F) -> [Stream<Circuit<P>, B>; N]
+    where
+        F: Fn(&B::Key, &B::Val) -> usize + 'static,
+    {
+        let partitions = self
+            .circuit()
+            .add_unary_operator(Partition::new(N, router), self);
+
+        array::from_fn(|index| {
+            self.circuit()
+                .add_unary_operator(SelectPartition::new(index), &partitions)
+        })
+    }
+}
+
+/// Operator that splits a batch into a vector of `n` batches using a routing
+/// function.
+pub struct Partition<F, B> {
+    n: usize,
+    router: F,
+    _type: PhantomData<B>,
+}
+
+impl<F, B> Partition<F, B> {
+    pub fn new(n: usize, router: F) -> Self {
+        Self {
+            n,
+            router,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<F, B> Operator for Partition<F, B>
+where
+    F: 'static,
+    B: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("Partition")
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<F, B> UnaryOperator<B, Vec<B>> for Partition<F, B>
+where
+    F: Fn(&B::Key, &B::Val) -> usize + 'static,
+    B: Batch<Time = ()> + 'static,
+    B::Key: Clone,
+    B::Val: Clone,
+{
+    fn eval(&mut self, input: &B) -> Vec<B> {
+        let mut builders: Vec<_> = (0..self.n)
+            .map(|_| B::Builder::with_capacity((), 0))
+            .collect();
+
+        let mut cursor = input.cursor();
+        while cursor.key_valid(input) {
+            while cursor.val_valid(input) {
+                let key = cursor.key(input);
+                let val = cursor.val(input);
+                let index = (self.router)(key, val);
+                assert!(
+                    index < self.n,
+                    "partition index {} out of range (number of partitions: {})",
+                    index,
+                    self.n
+                );
+                builders[index].push((key.clone(), val.clone(), cursor.weight(input)));
+                cursor.step_val(input);
+            }
+            cursor.step_key(input);
+        }
+
+        builders.into_iter().map(|builder| builder.done()).collect()
+    }
+}
+
+// Extracts the `index`th partition computed by `Partition`.
+struct SelectPartition<B> {
+    index: usize,
+    _type: PhantomData<B>,
+}
+
+impl<B> SelectPartition<B> {
+    fn new(index: usize) -> Self {
+        Self {
+            index,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<B> Operator for SelectPartition<B>
+where
+    B: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("SelectPartition")
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<B> UnaryOperator<Vec<B>, B> for SelectPartition<B>
+where
+    B: Clone + 'static,
+{
+    fn eval(&mut self, partitions: &Vec<B>) -> B {
+        partitions[self.index].clone()
+    }
+
+    fn eval_owned(&mut self, mut partitions: Vec<B>) -> B {
+        partitions.swap_remove(self.index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{circuit::Root, operator::Generator, trace::ord::OrdZSet, zset};
+    use std::vec;
+
+    #[test]
+    fn partition_test() {
+        let root = Root::build(move |circuit| {
+            let mut input: vec::IntoIter<OrdZSet<usize, isize>> = vec![
+                zset! { 0 => 1, 1 => 2, 2 => -1, 3 => 1, 4 => 1, 5 => 3 },
+                zset! { 6 => 1, 9 => -1 },
+            ]
+            .into_iter();
+            let expected: [vec::IntoIter<OrdZSet<usize, isize>>; 3] = [
+                vec![zset! { 0 => 1, 3 => 1 }, zset! { 6 => 1, 9 => -1 }].into_iter(),
+                vec![zset! { 1 => 2, 4 => 1 }, zset! {}].into_iter(),
+                vec![zset! { 2 => -1, 5 => 3 }, zset! {}].into_iter(),
+            ];
+
+            let partitions = circuit
+                .add_source(Generator::new(move || input.next().unwrap()))
+                .partition::<3, _>(|&k, &()| k % 3);
+
+            for (partition, mut expected) in partitions.into_iter().zip(expected) {
+                partition.inspect(move |batch| assert_eq!(*batch, expected.next().unwrap()));
+            }
+        })
+        .unwrap();
+
+        for _ in 0..2 {
+            root.step().unwrap();
+        }
+    }
+}
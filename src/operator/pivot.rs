@@ -0,0 +1,240 @@
+//! Pivot and unpivot operators.
+//!
+//! Pivoting converts a "long" relation of `(key, column, value)` rows,
+//! represented as an indexed Z-set that maps `key` to `(column, value)`
+//! pairs, into a "wide" relation with one `(key, columns)` record per key,
+//! where `columns` is a map from column names to values.  Unpivoting performs
+//! the reverse transformation.
+
+use crate::{
+    algebra::{IndexedZSet, ZRingValue, ZSet},
+    circuit::{
+        operator_traits::{Operator, UnaryOperator},
+        Circuit, Stream,
+    },
+    trace::cursor::Cursor,
+    NumEntries,
+};
+use deepsize::DeepSizeOf;
+use std::{borrow::Cow, collections::BTreeMap, marker::PhantomData};
+
+impl<P, Z> Stream<Circuit<P>, Z>
+where
+    P: Clone + 'static,
+    Z: Clone + 'static,
+{
+    /// Pivot an indexed Z-set of `key -> (column, value)` rows into a Z-set
+    /// of wide `(key, columns)` records.
+    ///
+    /// Outputs one record with weight `+1` for each key in `self`.  Pairs
+    /// with negative weights are ignored, so a key whose pairs all have
+    /// negative weights yields an empty record `(key, {})`.  If a column has
+    /// several values for the same key, the largest one is used.
+    pub fn pivot<C, V, O>(&self) -> Stream<Circuit<P>, O>
+    where
+        Z: IndexedZSet<Val = (C, V)> + 'static,
+        Z::Key: Clone,
+        Z::R: ZRingValue,
+        C: Clone + Ord + 'static,
+        V: Clone + 'static,
+        O: Clone + ZSet<Key = (Z::Key, BTreeMap<C, V>), R = Z::R> + 'static,
+    {
+        self.aggregate(pivot_func)
+    }
+
+    /// Incremental version of [`Self::pivot`].
+    ///
+    /// This is equivalent to `self.integrate().pivot().differentiate()`, but
+    /// is more efficient.  When a row of a key is inserted or retracted, the
+    /// old record of the key is retracted and the new one is inserted.
+    pub fn pivot_incremental<C, V, O>(&self) -> Stream<Circuit<P>, O>
+    where
+        Z: IndexedZSet<Val = (C, V)> + DeepSizeOf + NumEntries,
+        Z::Key: Clone + Ord,
+        Z::R: ZRingValue,
+        C: Clone + Ord + 'static,
+        V: Clone + Ord + 'static,
+        O: Clone + ZSet<Key = (Z::Key, BTreeMap<C, V>), R = Z::R> + 'static,
+    {
+        self.aggregate_incremental(pivot_func)
+    }
+
+    /// Apply [`Unpivot`] operator to `self`.
+    ///
+    /// Since unpivoting is linear, the operator can be applied to streams of
+    /// changes directly.
+    pub fn unpivot<K, C, V, O>(&self) -> Stream<Circuit<P>, O>
+    where
+        Z: ZSet<Key = (K, BTreeMap<C, V>)> + 'static,
+        K: Clone,
+        C: Clone,
+        V: Clone,
+        O: IndexedZSet<Key = K, Val = (C, V), R = Z::R>,
+    {
+        self.circuit().add_unary_operator(Unpivot::new(), self)
+    }
+}
+
+// Aggregation function that collects `(column, value)` pairs with
+// non-negative weights into a map.
+fn pivot_func<K, C, V, R>(key: &K, vals: &mut Vec<(&(C, V), R)>) -> (K, BTreeMap<C, V>)
+where
+    K: Clone,
+    C: Clone + Ord,
+    V: Clone,
+    R: ZRingValue,
+{
+    let columns = vals
+        .iter()
+        .filter(|(_, w)| w.ge0())
+        .map(|((column, value), _)| (column.clone(), value.clone()))
+        .collect();
+
+    (key.clone(), columns)
+}
+
+/// Operator that converts a Z-set of wide `(key, columns)` records into an
+/// indexed Z-set of `key -> (column, value)` rows.
+///
+/// Each `(column, value)` entry of the `columns` map of a record yields a
+/// row with the weight of the record.
+pub struct Unpivot<Z, O> {
+    _type: PhantomData<(Z, O)>,
+}
+
+impl<Z, O> Unpivot<Z, O> {
+    pub fn new() -> Self {
+        Self { _type: PhantomData }
+    }
+}
+
+impl<Z, O> Default for Unpivot<Z, O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Z, O> Operator for Unpivot<Z, O>
+where
+    Z: 'static,
+    O: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("Unpivot")
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<Z, O, K, C, V> UnaryOperator<Z, O> for Unpivot<Z, O>
+where
+    Z: ZSet<Key = (K, BTreeMap<C, V>)> + 'static,
+    K: Clone,
+    C: Clone,
+    V: Clone,
+    O: IndexedZSet<Key = K, Val = (C, V), R = Z::R>,
+{
+    fn eval(&mut self, input: &Z) -> O {
+        let mut tuples = Vec::with_capacity(input.len());
+        let mut cursor = input.cursor();
+
+        while cursor.key_valid(input) {
+            let (key, columns) = cursor.key(input);
+            let weight = cursor.weight(input);
+            for (column, value) in columns.iter() {
+                tuples.push((
+                    (key.clone(), (column.clone(), value.clone())),
+                    weight.clone(),
+                ));
+            }
+            cursor.step_key(input);
+        }
+
+        O::from_tuples((), tuples)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        circuit::Root,
+        indexed_zset,
+        operator::Generator,
+        trace::ord::{OrdIndexedZSet, OrdZSet},
+        zset,
+    };
+    use std::{collections::BTreeMap, vec};
+
+    fn record(
+        key: usize,
+        columns: &[(&'static str, usize)],
+    ) -> (usize, BTreeMap<&'static str, usize>) {
+        (key, columns.iter().cloned().collect())
+    }
+
+    #[test]
+    #[allow(clippy::type_complexity)]
+    fn pivot_test() {
+        let root = Root::build(move |circuit| {
+            let mut input: vec::IntoIter<OrdIndexedZSet<usize, (&'static str, usize), isize>> = vec![
+                indexed_zset! { 1 => { ("x", 10) => 1, ("y", 20) => 1 }, 2 => { ("x", 5) => 1 } },
+                // Update column "y" of key 1 and delete key 2.
+                indexed_zset! { 1 => { ("y", 20) => -1, ("y", 21) => 1 }, 2 => { ("x", 5) => -1 } },
+                // Add a column.
+                indexed_zset! { 1 => { ("z", 1) => 1 } },
+            ]
+            .into_iter();
+
+            let mut expected: vec::IntoIter<OrdZSet<(usize, BTreeMap<&'static str, usize>), isize>> = vec![
+                zset! { record(1, &[("x", 10), ("y", 20)]) => 1, record(2, &[("x", 5)]) => 1 },
+                zset! {
+                    record(1, &[("x", 10), ("y", 20)]) => -1,
+                    record(1, &[("x", 10), ("y", 21)]) => 1,
+                    record(2, &[("x", 5)]) => -1,
+                },
+                zset! {
+                    record(1, &[("x", 10), ("y", 21)]) => -1,
+                    record(1, &[("x", 10), ("y", 21), ("z", 1)]) => 1,
+                },
+            ]
+            .into_iter();
+
+            let input = circuit.add_source(Generator::new(move || input.next().unwrap()));
+            let pivoted = input.pivot_incremental::<_, _, OrdZSet<_, _>>();
+            pivoted.inspect(move |records| assert_eq!(*records, expected.next().unwrap()));
+
+            // Unpivoting the pivoted stream restores the input.
+            pivoted
+                .unpivot::<_, _, _, OrdIndexedZSet<_, _, _>>()
+                .minus(&input)
+                .inspect(|diff| assert_eq!(*diff, indexed_zset! {}));
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn pivot_negative_weights() {
+        let root = Root::build(move |circuit| {
+            let input: OrdIndexedZSet<usize, (&'static str, usize), isize> =
+                indexed_zset! { 1 => { ("x", 1) => 1, ("y", 2) => -1 }, 2 => { ("x", 3) => -1 } };
+
+            circuit
+                .add_source(Generator::new(move || input.clone()))
+                .pivot::<_, _, OrdZSet<_, _>>()
+                .inspect(|records| {
+                    assert_eq!(
+                        *records,
+                        zset! { record(1, &[("x", 1)]) => 1, record(2, &[]) => 1 }
+                    )
+                });
+        })
+        .unwrap();
+
+        root.step().unwrap();
+    }
+}
@@ -169,7 +169,10 @@ where
     }
 
     fn eval_ref_and_owned(&mut self, i1: &D, i2: D) -> D {
-        i2.neg().add_by_ref(i1)
+        // Negate `i2` in place and add `i1` to it.
+        let mut i2neg = i2.neg();
+        i2neg.add_assign_by_ref(i1);
+        i2neg
     }
 
     fn eval_owned(&mut self, i1: D, i2: D) -> D {
@@ -187,10 +190,14 @@ where
 #[cfg(test)]
 mod test {
     use crate::{
-        algebra::HasZero,
+        algebra::{AddAssignByRef, HasZero, NegByRef},
         circuit::{Circuit, OwnershipPreference, Root},
+        indexed_zset,
         operator::{Generator, Inspect},
-        trace::{ord::OrdZSet, Batch},
+        trace::{
+            ord::{OrdIndexedZSet, OrdZSet},
+            Batch,
+        },
         zset,
     };
 
@@ -343,4 +350,57 @@ mod test {
             root.step().unwrap();
         }
     }
+
+    // Additions append non-overlapping batches in place instead of merging
+    // them, and owned negation updates weights in place; check that all code
+    // paths produce the same result.
+    #[test]
+    fn in_place_add() {
+        let z1: OrdZSet<usize, isize> = zset! { 1 => 1, 2 => -1 };
+        let z2: OrdZSet<usize, isize> = zset! { 3 => 1, 4 => 2 };
+        let z3: OrdZSet<usize, isize> = zset! { 2 => 1, 5 => 1 };
+
+        for (a, b) in [(&z1, &z2), (&z2, &z1), (&z1, &z3), (&z3, &z3)] {
+            let expected = a.merge(b);
+
+            assert_eq!(a.clone() + b.clone(), expected);
+
+            let mut sum = a.clone();
+            sum += b.clone();
+            assert_eq!(sum, expected);
+
+            let mut sum = a.clone();
+            sum.add_assign_by_ref(b);
+            assert_eq!(sum, expected);
+        }
+
+        let i1: OrdIndexedZSet<usize, usize, isize> = indexed_zset! {
+            1 => { 1 => 1, 2 => -1 },
+            2 => { 3 => 1 },
+        };
+        let i2: OrdIndexedZSet<usize, usize, isize> = indexed_zset! {
+            3 => { 1 => 1 },
+            4 => { 1 => 1, 2 => 1 },
+        };
+        let i3: OrdIndexedZSet<usize, usize, isize> = indexed_zset! {
+            2 => { 3 => -1, 4 => 1 },
+            5 => { 1 => 1 },
+        };
+
+        for (a, b) in [(&i1, &i2), (&i2, &i1), (&i1, &i3), (&i3, &i3)] {
+            let expected = a.merge(b);
+
+            assert_eq!(a.clone() + b.clone(), expected);
+
+            let mut sum = a.clone();
+            sum += b.clone();
+            assert_eq!(sum, expected);
+
+            let mut sum = a.clone();
+            sum.add_assign_by_ref(b);
+            assert_eq!(sum, expected);
+
+            assert_eq!(-a.clone(), a.neg_by_ref());
+        }
+    }
 }
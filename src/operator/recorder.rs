@@ -0,0 +1,430 @@
+//! Recording sink and golden-file testing support.
+//!
+//! [`Recorder`] writes every batch in a stream to a file, one batch per clock
+//! cycle.  [`GoldenChecker`] replays such a recording against a live stream,
+//! panicking with a readable diff at the first clock cycle where the stream
+//! deviates from the recording.  [`Stream::golden`] combines the two into a
+//! regression test for complex circuits: a run with the `DBSP_UPDATE_GOLDEN`
+//! environment variable set records the output of the circuit, other runs
+//! compare against it.
+
+use crate::{
+    circuit::{
+        operator_traits::{Operator, SinkOperator},
+        Circuit, Scope, Stream,
+    },
+    operator::codec::{read_batch, write_batch, DEFAULT_MAX_FRAME_LEN},
+    trace::{Batch, BatchReader, Cursor},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    env,
+    fmt::{Debug, Write as _},
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    marker::PhantomData,
+    path::Path,
+    thread::panicking,
+};
+
+/// Environment variable that makes [`Stream::golden`] record the stream,
+/// creating or overwriting the golden file, instead of checking against it.
+pub const UPDATE_GOLDEN_ENV: &str = "DBSP_UPDATE_GOLDEN";
+
+impl<P, B> Stream<Circuit<P>, B>
+where
+    P: Clone + 'static,
+    B: Batch<Time = ()> + Clone + 'static,
+    B::Key: Ord + Debug + Serialize + DeserializeOwned,
+    B::Val: Ord + Debug + Serialize + DeserializeOwned,
+    B::R: Eq + Debug + Serialize + DeserializeOwned,
+{
+    /// Write the contents of `self` to `writer` using the [`Recorder`]
+    /// operator.
+    pub fn record<W>(&self, writer: W)
+    where
+        W: Write + 'static,
+    {
+        self.circuit().add_sink(Recorder::new(writer), self);
+    }
+
+    /// Compare the contents of `self` against a recording read from `reader`
+    /// using the [`GoldenChecker`] operator.
+    pub fn check_recording<R>(&self, reader: R)
+    where
+        R: Read + 'static,
+    {
+        self.circuit().add_sink(GoldenChecker::new(reader), self);
+    }
+
+    /// Golden-file test for `self`.
+    ///
+    /// If the `DBSP_UPDATE_GOLDEN` environment variable is set, records the
+    /// contents of `self` to `path`.  Otherwise, compares the stream against
+    /// the recording stored in the file (see [`GoldenChecker`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file cannot be opened or created.  In particular, a
+    /// missing golden file is an error rather than a signal to record one,
+    /// so that a misplaced or deleted file cannot make the test pass
+    /// vacuously.
+    pub fn golden<T>(&self, path: T)
+    where
+        T: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        if env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+            let file = File::create(path).unwrap_or_else(|e| {
+                panic!("failed to create golden file '{}': {}", path.display(), e)
+            });
+            self.record(BufWriter::new(file));
+        } else {
+            let file = File::open(path).unwrap_or_else(|e| {
+                panic!(
+                    "failed to open golden file '{}': {} (set {} to record it)",
+                    path.display(),
+                    e,
+                    UPDATE_GOLDEN_ENV
+                )
+            });
+            self.check_recording(BufReader::new(file));
+        }
+    }
+}
+
+/// Sink operator that writes every input batch to a writer.
+///
+/// Batches are written in the same format used by
+/// [`TcpSink`](`crate::operator::TcpSink`), one batch per clock cycle.  The
+/// writer is flushed at the end of the clock epoch, i.e., when the circuit
+/// is dropped.
+pub struct Recorder<B, W>
+where
+    W: Write,
+{
+    writer: W,
+    _type: PhantomData<B>,
+}
+
+impl<B, W> Recorder<B, W>
+where
+    W: Write,
+{
+    /// Create a recorder that writes batches to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<B, W> Operator for Recorder<B, W>
+where
+    B: 'static,
+    W: Write + 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("Recorder")
+    }
+
+    fn clock_end(&mut self, scope: Scope) {
+        // Don't panic while unwinding, e.g., when the circuit is dropped after
+        // one of its operators has panicked.
+        if scope == 0 {
+            if let Err(e) = self.writer.flush() {
+                if !panicking() {
+                    panic!("Recorder: failed to flush recording: {}", e);
+                }
+            }
+        }
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<B, W> SinkOperator<B> for Recorder<B, W>
+where
+    B: BatchReader<Time = ()> + 'static,
+    B::Key: Serialize,
+    B::Val: Serialize,
+    B::R: Serialize,
+    W: Write + 'static,
+{
+    fn eval(&mut self, batch: &B) {
+        write_batch(batch, &mut self.writer)
+            .unwrap_or_else(|e| panic!("Recorder: failed to write batch: {}", e));
+    }
+}
+
+/// Sink operator that compares its input against a recording produced by
+/// [`Recorder`].
+///
+/// In each clock cycle, reads the next batch from the recording and compares
+/// it with the input batch.  On mismatch, panics with a diff that lists
+/// tuples that are only present in the recording (prefixed with `-`) and
+/// tuples only present in the input (prefixed with `+`).  At the end of the
+/// clock epoch, i.e., when the circuit is dropped, panics if the recording
+/// contains more steps than the circuit has executed.
+pub struct GoldenChecker<B, R> {
+    reader: R,
+    buffer: Vec<u8>,
+    step: usize,
+    _type: PhantomData<B>,
+}
+
+impl<B, R> GoldenChecker<B, R> {
+    /// Create a checker that reads the expected batches from `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            step: 0,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<B, R> Operator for GoldenChecker<B, R>
+where
+    B: 'static,
+    R: Read + 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("GoldenChecker")
+    }
+
+    fn clock_end(&mut self, scope: Scope) {
+        // Don't panic while unwinding, e.g., when the circuit is dropped after
+        // the checker has reported a mismatch.
+        if scope == 0 && !panicking() {
+            let mut byte = [0u8];
+            match self.reader.read(&mut byte) {
+                Ok(0) => {}
+                Ok(_) => panic!(
+                    "golden mismatch: the recording contains more than {} steps",
+                    self.step
+                ),
+                Err(e) => panic!("GoldenChecker: failed to read recording: {}", e),
+            }
+        }
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+impl<B, R> SinkOperator<B> for GoldenChecker<B, R>
+where
+    B: Batch<Time = ()> + 'static,
+    B::Key: Ord + Debug + DeserializeOwned,
+    B::Val: Ord + Debug + DeserializeOwned,
+    B::R: Eq + Debug + DeserializeOwned,
+    R: Read + 'static,
+{
+    fn eval(&mut self, batch: &B) {
+        let expected: B = read_batch(&mut self.reader, &mut self.buffer, DEFAULT_MAX_FRAME_LEN)
+            .unwrap_or_else(|e| panic!("GoldenChecker: failed to read recording: {}", e))
+            .unwrap_or_else(|| {
+                panic!(
+                    "golden mismatch at step {}: the recording only contains {} steps",
+                    self.step, self.step
+                )
+            });
+
+        let diff = batch_diff(&expected, batch);
+        if !diff.is_empty() {
+            panic!("golden mismatch at step {}:\n{}", self.step, diff);
+        }
+
+        self.step += 1;
+    }
+}
+
+/// Collect all `(key, value, weight)` tuples in `batch`.
+fn tuples<B>(batch: &B) -> Vec<(&B::Key, &B::Val, B::R)>
+where
+    B: BatchReader<Time = ()>,
+{
+    let mut result = Vec::with_capacity(batch.len());
+    let mut cursor = batch.cursor();
+
+    while cursor.key_valid(batch) {
+        while cursor.val_valid(batch) {
+            let w = cursor.weight(batch);
+            result.push((cursor.key(batch), cursor.val(batch), w));
+            cursor.step_val(batch);
+        }
+        cursor.step_key(batch);
+    }
+
+    result
+}
+
+/// Compute a human-readable diff between `expected` and `actual`.
+///
+/// Returns an empty string if the batches are identical.
+fn batch_diff<B>(expected: &B, actual: &B) -> String
+where
+    B: BatchReader<Time = ()>,
+    B::Key: Ord + Debug,
+    B::Val: Ord + Debug,
+    B::R: Eq + Debug,
+{
+    let expected = tuples(expected);
+    let actual = tuples(actual);
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < expected.len() || j < actual.len() {
+        let order = match (expected.get(i), actual.get(j)) {
+            (Some((k1, v1, _)), Some((k2, v2, _))) => (k1, v1).cmp(&(k2, v2)),
+            (Some(_), None) => Ordering::Less,
+            _ => Ordering::Greater,
+        };
+
+        match order {
+            Ordering::Less => {
+                let (k, v, w) = &expected[i];
+                writeln!(diff, "- ({:?}, {:?}) => {:?}", k, v, w).unwrap();
+                i += 1;
+            }
+            Ordering::Greater => {
+                let (k, v, w) = &actual[j];
+                writeln!(diff, "+ ({:?}, {:?}) => {:?}", k, v, w).unwrap();
+                j += 1;
+            }
+            Ordering::Equal => {
+                let (k, v, w1) = &expected[i];
+                let w2 = &actual[j].2;
+                if w1 != w2 {
+                    writeln!(diff, "- ({:?}, {:?}) => {:?}", k, v, w1).unwrap();
+                    writeln!(diff, "+ ({:?}, {:?}) => {:?}", k, v, w2).unwrap();
+                }
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod test {
+    use super::batch_diff;
+    use crate::{
+        circuit::{Root, Stream},
+        operator::Generator,
+        trace::ord::OrdZSet,
+        zset,
+    };
+    use std::{
+        env,
+        fs::{self, File},
+        io::BufWriter,
+        path::{Path, PathBuf},
+    };
+
+    fn golden_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("dbsp-{}-{}.golden", name, std::process::id()))
+    }
+
+    // Circuit whose output at step `n` is `{n => 1, (n - 1) * scale => -1}`.
+    // Records the output to `path` if `record` is `true` and checks it against
+    // the golden file otherwise.
+    fn golden_circuit(path: PathBuf, scale: usize, record: bool) -> Root {
+        Root::build(move |circuit| {
+            let mut n: usize = 0;
+            let stream: Stream<_, OrdZSet<usize, isize>> =
+                circuit.add_source(Generator::new(move || {
+                    n += 1;
+                    zset! { n => 1, (n - 1) * scale => -1 }
+                }));
+            if record {
+                stream.record(BufWriter::new(File::create(path).unwrap()));
+            } else {
+                stream.golden(path);
+            }
+        })
+        .unwrap()
+    }
+
+    // Record 10 steps of `golden_circuit` to `path`.
+    fn record_golden(path: &Path) {
+        let root = golden_circuit(path.to_path_buf(), 1, true);
+        for _ in 0..10 {
+            root.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn golden_match() {
+        let path = golden_path("golden_match");
+        record_golden(&path);
+
+        let root = golden_circuit(path.clone(), 1, false);
+        for _ in 0..10 {
+            root.step().unwrap();
+        }
+        drop(root);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "golden mismatch at step 1")]
+    fn golden_mismatch() {
+        let path = golden_path("golden_mismatch");
+        record_golden(&path);
+
+        // Outputs are identical in step 0 and diverge in step 1.
+        let root = golden_circuit(path.clone(), 2, false);
+        fs::remove_file(&path).unwrap();
+        for _ in 0..10 {
+            root.step().unwrap();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "the recording contains more than 5 steps")]
+    fn golden_unconsumed_steps() {
+        let path = golden_path("golden_unconsumed_steps");
+        record_golden(&path);
+
+        let root = golden_circuit(path.clone(), 1, false);
+        fs::remove_file(&path).unwrap();
+        for _ in 0..5 {
+            root.step().unwrap();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "failed to open golden file")]
+    fn golden_missing_file() {
+        let path = golden_path("golden_missing_file");
+        let _ = fs::remove_file(&path);
+
+        golden_circuit(path, 1, false);
+    }
+
+    #[test]
+    fn diff() {
+        let expected: OrdZSet<usize, isize> = zset! { 1 => 1, 2 => 1, 3 => 2 };
+        let actual: OrdZSet<usize, isize> = zset! { 2 => 1, 3 => 1, 4 => -1 };
+
+        assert_eq!(batch_diff(&expected, &expected), "");
+        assert_eq!(
+            batch_diff(&expected, &actual),
+            "- (1, ()) => 1\n- (3, ()) => 2\n+ (3, ()) => 1\n+ (4, ()) => -1\n"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Operators with user-defined functions that can be replaced at runtime.
+
+use crate::{
+    circuit::{Circuit, Stream},
+    trace::{Batch, BatchReader},
+};
+use std::{cell::RefCell, rc::Rc};
+
+/// Handle to a user-defined function that can be replaced between clock
+/// cycles.
+///
+/// A `UdfHandle` adds a level of indirection between an operator and its
+/// closure: the operator calls the closure currently installed in the handle,
+/// which the application can swap at any time between steps using
+/// [`Self::set`], e.g., to update a filter threshold.  This does not require
+/// rebuilding the circuit, so the state of all other operators is preserved.
+/// The new function takes effect at the next clock cycle.
+///
+/// Note that replacing the function of an operator whose output feeds
+/// stateful operators, e.g., [`Stream::integrate`], does not retroactively
+/// update the values already accumulated by these operators.
+#[allow(clippy::type_complexity)]
+pub struct UdfHandle<A: ?Sized, R> {
+    func: Rc<RefCell<Box<dyn Fn(&A) -> R>>>,
+}
+
+impl<A: ?Sized, R> Clone for UdfHandle<A, R> {
+    fn clone(&self) -> Self {
+        Self {
+            func: self.func.clone(),
+        }
+    }
+}
+
+impl<A, R> UdfHandle<A, R>
+where
+    A: ?Sized + 'static,
+    R: 'static,
+{
+    /// Create a handle with initial function `func`.
+    pub fn new<F>(func: F) -> Self
+    where
+        F: Fn(&A) -> R + 'static,
+    {
+        Self {
+            func: Rc::new(RefCell::new(Box::new(func))),
+        }
+    }
+
+    /// Replace the function.
+    pub fn set<F>(&self, func: F)
+    where
+        F: Fn(&A) -> R + 'static,
+    {
+        *self.func.borrow_mut() = Box::new(func);
+    }
+
+    /// Apply the current function to `arg`.
+    pub fn call(&self, arg: &A) -> R {
+        (self.func.borrow())(arg)
+    }
+
+    /// Returns a closure that forwards its argument to the current function.
+    ///
+    /// The closure can be passed to any operator that expects a function of
+    /// type `Fn(&A) -> R` to make its function replaceable.
+    pub fn func(&self) -> impl Fn(&A) -> R + Clone + 'static {
+        let handle = self.clone();
+        move |arg| handle.call(arg)
+    }
+}
+
+impl<P, B> Stream<Circuit<P>, B>
+where
+    B: Clone,
+    P: Clone + 'static,
+{
+    /// Like [`Self::map_keys`], but returns a handle that can be used to
+    /// replace the `map` closure between clock cycles.
+    #[allow(clippy::type_complexity)]
+    pub fn map_keys_reloadable<CO, F>(
+        &self,
+        map: F,
+    ) -> (Stream<Circuit<P>, CO>, UdfHandle<B::Key, CO::Key>)
+    where
+        B: BatchReader<Time = ()> + 'static,
+        CO: Batch<Val = B::Val, Time = (), R = B::R> + Clone + 'static,
+        CO::Val: Clone,
+        F: Fn(&B::Key) -> CO::Key + 'static,
+    {
+        let handle = UdfHandle::new(map);
+        (self.map_keys(handle.func()), handle)
+    }
+
+    /// Like [`Self::filter_keys`], but returns a handle that can be used to
+    /// replace the `func` closure between clock cycles.
+    pub fn filter_keys_reloadable<CO, F>(
+        &self,
+        func: F,
+    ) -> (Stream<Circuit<P>, CO>, UdfHandle<B::Key, bool>)
+    where
+        B: BatchReader<Time = ()> + 'static,
+        B::Key: Clone,
+        B::Val: Clone,
+        CO: Batch<Key = B::Key, Val = B::Val, Time = (), R = B::R> + Clone + 'static,
+        F: Fn(&B::Key) -> bool + 'static,
+    {
+        let handle = UdfHandle::new(func);
+        (self.filter_keys(handle.func()), handle)
+    }
+
+    /// Like [`Self::filter_map_keys`], but returns a handle that can be used
+    /// to replace the `func` closure between clock cycles.
+    #[allow(clippy::type_complexity)]
+    pub fn filter_map_keys_reloadable<CO, F>(
+        &self,
+        func: F,
+    ) -> (Stream<Circuit<P>, CO>, UdfHandle<B::Key, Option<CO::Key>>)
+    where
+        B: BatchReader<Time = ()> + 'static,
+        B::Val: Clone,
+        CO: Batch<Val = B::Val, Time = (), R = B::R> + Clone + 'static,
+        CO::Key: Clone,
+        F: Fn(&B::Key) -> Option<CO::Key> + 'static,
+    {
+        let handle = UdfHandle::new(func);
+        (self.filter_map_keys(handle.func()), handle)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{circuit::Root, operator::Generator, trace::ord::OrdZSet, zset};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn reload_filter_test() {
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let output_clone = output.clone();
+
+        let mut handles = None;
+        let root = Root::build(|circuit| {
+            let input = circuit.add_source(Generator::new(|| {
+                zset! { 1usize => 1isize, 5 => 1, 10 => 1 }
+            }));
+            let (filtered, threshold) =
+                input.filter_keys_reloadable::<OrdZSet<_, _>, _>(|x| *x > 1);
+            let (mapped, map) = filtered.map_keys_reloadable::<OrdZSet<_, _>, _>(|x| x * 2);
+            let integral = mapped.integrate();
+            integral.inspect(move |zs| output_clone.borrow_mut().push(zs.clone()));
+            handles = Some((threshold, map));
+        })
+        .unwrap();
+        let (threshold, map) = handles.unwrap();
+
+        root.step().unwrap();
+
+        threshold.set(|x| *x > 5);
+        map.set(|x| x + 100);
+        root.step().unwrap();
+
+        assert_eq!(
+            *output.borrow(),
+            vec![
+                zset! { 10 => 1, 20 => 1 },
+                zset! { 10 => 1, 20 => 1, 110 => 1 },
+            ]
+        );
+    }
+}
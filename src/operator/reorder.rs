@@ -0,0 +1,383 @@
+//! Ingestion-side buffer that reorders out-of-order updates by event time.
+//!
+//! [`ReorderBuffer`] implements the reordering logic.
+//! [`Circuit::add_reordering_input`] attaches it to an input stream, so that
+//! updates pushed via a [`ReorderingInputHandle`] reach the circuit in
+//! event-time order.
+
+use crate::{
+    circuit::{
+        operator_traits::{Data, Operator, SourceOperator},
+        Circuit, StepStats, Stream,
+    },
+    trace::{Batch, BatchReader},
+};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    mem::{replace, take},
+    rc::Rc,
+};
+
+/// Buffer that holds updates until they are unlikely to be followed by
+/// updates with smaller event times, and releases them in event-time order.
+///
+/// The buffer tracks the source's watermark, i.e., the largest event time
+/// pushed to it so far.  An update with event time `t` is held until
+/// `t <= watermark - lateness`, where `lateness` is the allowed lateness
+/// configured when creating the buffer.  This way, updates that arrive up
+/// to `lateness` time units out of order are released in order, so
+/// downstream windowing operators see mostly-ordered data.
+///
+/// Updates that arrive after their event time has already been released
+/// are released at the next call to [`release`](`Self::release`) instead of
+/// being dropped, since DBSP operators produce correct results for
+/// out-of-order inputs.  The number of such updates is reported by
+/// [`late_updates`](`Self::late_updates`).
+///
+/// # Examples
+///
+/// ```
+/// # use dbsp::operator::ReorderBuffer;
+/// let mut buffer = ReorderBuffer::new(10);
+///
+/// buffer.push(100, "a");
+/// buffer.push(95, "b");
+/// buffer.push(112, "c");
+///
+/// // Watermark is 112; updates with event times up to 102 are released.
+/// assert_eq!(buffer.release(), vec![(95, "b"), (100, "a")]);
+/// assert_eq!(buffer.flush(), vec![(112, "c")]);
+/// ```
+pub struct ReorderBuffer<D> {
+    lateness: u64,
+    watermark: Option<u64>,
+    // Largest event time released so far.
+    released: Option<u64>,
+    pending: BTreeMap<u64, Vec<D>>,
+    // Updates that arrived behind `released`, in arrival order.
+    late: Vec<(u64, D)>,
+    late_updates: usize,
+}
+
+impl<D> ReorderBuffer<D> {
+    /// Create a buffer with the specified allowed lateness.
+    pub fn new(lateness: u64) -> Self {
+        Self {
+            lateness,
+            watermark: None,
+            released: None,
+            pending: BTreeMap::new(),
+            late: Vec::new(),
+            late_updates: 0,
+        }
+    }
+
+    /// Current watermark, i.e., the largest event time pushed to the buffer,
+    /// or `None` if the buffer has not received any updates.
+    pub fn watermark(&self) -> Option<u64> {
+        self.watermark
+    }
+
+    /// Allowed lateness.
+    pub fn lateness(&self) -> u64 {
+        self.lateness
+    }
+
+    /// Number of updates held in the buffer.
+    pub fn len(&self) -> usize {
+        self.pending.values().map(Vec::len).sum::<usize>() + self.late.len()
+    }
+
+    /// Returns `true` if the buffer does not hold any updates.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty() && self.late.is_empty()
+    }
+
+    /// Total number of updates that arrived after their event time had
+    /// already been released.
+    pub fn late_updates(&self) -> usize {
+        self.late_updates
+    }
+
+    /// Push an update with event time `time` to the buffer, advancing the
+    /// watermark if necessary.
+    pub fn push(&mut self, time: u64, data: D) {
+        self.watermark = Some(self.watermark.map_or(time, |w| w.max(time)));
+
+        if matches!(self.released, Some(released) if time <= released) {
+            self.late_updates += 1;
+            self.late.push((time, data));
+        } else {
+            self.pending.entry(time).or_default().push(data);
+        }
+    }
+
+    /// Release all late updates followed by all updates with event times up
+    /// to `watermark - lateness`, in event-time order.
+    pub fn release(&mut self) -> Vec<(u64, D)> {
+        match self.watermark {
+            Some(watermark) if watermark >= self.lateness => {
+                self.release_up_to(watermark - self.lateness)
+            }
+            _ => take(&mut self.late),
+        }
+    }
+
+    /// Release all updates held in the buffer regardless of the watermark,
+    /// e.g., when the source reaches the end of its input.
+    pub fn flush(&mut self) -> Vec<(u64, D)> {
+        self.release_up_to(u64::MAX)
+    }
+
+    fn release_up_to(&mut self, bound: u64) -> Vec<(u64, D)> {
+        let mut result = take(&mut self.late);
+
+        let remaining = match bound.checked_add(1) {
+            Some(next) => self.pending.split_off(&next),
+            None => BTreeMap::new(),
+        };
+        let ready = replace(&mut self.pending, remaining);
+
+        for (time, updates) in ready {
+            self.released = Some(time);
+            result.extend(updates.into_iter().map(|data| (time, data)));
+        }
+
+        result
+    }
+}
+
+/// Update buffered in a reordering input handle.
+type Update<B> = (
+    (<B as BatchReader>::Key, <B as BatchReader>::Val),
+    <B as BatchReader>::R,
+);
+
+impl<P> Circuit<P>
+where
+    P: Clone + 'static,
+{
+    /// Create an input stream of batches of type `B` that reorders updates
+    /// by event time.
+    ///
+    /// Similar to [`add_input`](`Self::add_input`), but updates pushed to
+    /// the returned [`ReorderingInputHandle`] are tagged with an event time
+    /// and held in a [`ReorderBuffer`] with allowed lateness `lateness`.  In
+    /// each clock cycle, the stream yields a batch containing the updates
+    /// released by the buffer, i.e., updates whose event time is at least
+    /// `lateness` behind the largest event time pushed so far.
+    pub fn add_reordering_input<B>(
+        &self,
+        lateness: u64,
+    ) -> (Stream<Self, B>, ReorderingInputHandle<B>)
+    where
+        B: Batch<Time = ()> + Data,
+    {
+        let handle = ReorderingInputHandle::new(lateness);
+        let stream = self.add_source(ReorderingInput::new(
+            handle.buffer.clone(),
+            handle.flush.clone(),
+            self.step_stats(),
+        ));
+        (stream, handle)
+    }
+}
+
+/// Handle used to push updates to an input stream created with
+/// [`Circuit::add_reordering_input`].
+///
+/// Handles are cheap to clone; all clones push to the same stream.
+pub struct ReorderingInputHandle<B>
+where
+    B: Batch,
+{
+    buffer: Rc<RefCell<ReorderBuffer<Update<B>>>>,
+    // Release all buffered updates at the next clock cycle.
+    flush: Rc<Cell<bool>>,
+}
+
+impl<B> Clone for ReorderingInputHandle<B>
+where
+    B: Batch,
+{
+    fn clone(&self) -> Self {
+        Self {
+            buffer: self.buffer.clone(),
+            flush: self.flush.clone(),
+        }
+    }
+}
+
+impl<B> ReorderingInputHandle<B>
+where
+    B: Batch,
+{
+    fn new(lateness: u64) -> Self {
+        Self {
+            buffer: Rc::new(RefCell::new(ReorderBuffer::new(lateness))),
+            flush: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// Push an update with event time `time` to the input stream.
+    pub fn push(&self, time: u64, key: B::Key, val: B::Val, weight: B::R) {
+        self.buffer.borrow_mut().push(time, ((key, val), weight));
+    }
+
+    /// Release all buffered updates at the next clock cycle regardless of
+    /// the watermark, e.g., when the source reaches the end of its input.
+    pub fn flush(&self) {
+        self.flush.set(true);
+    }
+
+    /// Current watermark (see [`ReorderBuffer::watermark`]).
+    pub fn watermark(&self) -> Option<u64> {
+        self.buffer.borrow().watermark()
+    }
+
+    /// Number of updates held in the buffer.
+    pub fn pending(&self) -> usize {
+        self.buffer.borrow().len()
+    }
+
+    /// Total number of updates that arrived after their event time had
+    /// already been released (see [`ReorderBuffer::late_updates`]).
+    pub fn late_updates(&self) -> usize {
+        self.buffer.borrow().late_updates()
+    }
+}
+
+/// Source operator that yields updates released by the reorder buffer of a
+/// [`ReorderingInputHandle`].
+struct ReorderingInput<B>
+where
+    B: Batch,
+{
+    buffer: Rc<RefCell<ReorderBuffer<Update<B>>>>,
+    flush: Rc<Cell<bool>>,
+    step_stats: Rc<StepStats>,
+}
+
+impl<B> ReorderingInput<B>
+where
+    B: Batch,
+{
+    fn new(
+        buffer: Rc<RefCell<ReorderBuffer<Update<B>>>>,
+        flush: Rc<Cell<bool>>,
+        step_stats: Rc<StepStats>,
+    ) -> Self {
+        Self {
+            buffer,
+            flush,
+            step_stats,
+        }
+    }
+}
+
+impl<B> Operator for ReorderingInput<B>
+where
+    B: Batch + 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("ReorderingInput")
+    }
+
+    fn fixedpoint(&self) -> bool {
+        false
+    }
+}
+
+impl<B> SourceOperator<B> for ReorderingInput<B>
+where
+    B: Batch<Time = ()> + Data,
+{
+    fn idle_output(&mut self) -> Option<B> {
+        if self.buffer.borrow().is_empty() {
+            Some(B::empty(()))
+        } else {
+            None
+        }
+    }
+
+    fn eval(&mut self) -> B {
+        let mut buffer = self.buffer.borrow_mut();
+        let released = if self.flush.replace(false) {
+            buffer.flush()
+        } else {
+            buffer.release()
+        };
+        self.step_stats.record_inputs(released.len());
+        B::from_tuples((), released.into_iter().map(|(_, update)| update).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ReorderBuffer;
+    use crate::{circuit::Root, trace::ord::OrdZSet, zset};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn reorder() {
+        let mut buffer = ReorderBuffer::new(5);
+        assert!(buffer.release().is_empty());
+
+        buffer.push(3, 'a');
+        buffer.push(1, 'b');
+        assert!(buffer.release().is_empty());
+        assert_eq!(buffer.len(), 2);
+
+        buffer.push(7, 'c');
+        buffer.push(2, 'd');
+        buffer.push(3, 'e');
+        assert_eq!(buffer.watermark(), Some(7));
+        assert_eq!(buffer.release(), vec![(1, 'b'), (2, 'd')]);
+
+        buffer.push(10, 'f');
+        assert_eq!(buffer.release(), vec![(3, 'a'), (3, 'e')]);
+        assert_eq!(buffer.late_updates(), 0);
+
+        // Too late: time 3 has already been released.
+        buffer.push(2, 'g');
+        assert_eq!(buffer.late_updates(), 1);
+        assert_eq!(buffer.release(), vec![(2, 'g')]);
+
+        assert_eq!(buffer.flush(), vec![(7, 'c'), (10, 'f')]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn reordering_input() {
+        let outputs = Rc::new(RefCell::new(Vec::new()));
+        let outputs_clone = outputs.clone();
+
+        let mut handle = None;
+        let root = Root::build(|circuit| {
+            let (input, input_handle) = circuit.add_reordering_input::<OrdZSet<u64, isize>>(5);
+            handle = Some(input_handle);
+            input.inspect(move |batch| outputs_clone.borrow_mut().push(batch.clone()));
+        })
+        .unwrap();
+        let handle = handle.unwrap();
+
+        handle.push(3, 3, (), 1);
+        handle.push(1, 1, (), 1);
+        root.step().unwrap();
+
+        handle.push(7, 7, (), 1);
+        handle.push(2, 2, (), 1);
+        root.step().unwrap();
+        assert_eq!(handle.pending(), 2);
+
+        handle.flush();
+        root.step().unwrap();
+        assert_eq!(handle.pending(), 0);
+
+        let expected: Vec<OrdZSet<u64, isize>> =
+            vec![zset! {}, zset! { 1 => 1, 2 => 1 }, zset! { 3 => 1, 7 => 1 }];
+        assert_eq!(*outputs.borrow(), expected);
+    }
+}
@@ -0,0 +1,297 @@
+//! Size limits on the state of individual operators.
+
+use crate::{
+    circuit::{
+        operator_traits::{Operator, SinkOperator},
+        Circuit, Stream,
+    },
+    operator::DEFAULT_SAMPLE_INTERVAL,
+    trace::Batch,
+};
+use deepsize::DeepSizeOf;
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    fmt,
+    fmt::Display,
+    marker::PhantomData,
+    rc::Rc,
+};
+
+/// Report of a state budget violation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BudgetExceeded {
+    /// Operator state size in bytes.
+    pub size: usize,
+    /// Budget in bytes.
+    pub budget: usize,
+}
+
+impl Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "operator state uses {} bytes, exceeding the budget of {} bytes",
+            self.size, self.budget
+        )
+    }
+}
+
+struct BudgetState {
+    size: Cell<usize>,
+    sample_interval: Cell<usize>,
+    exceeded: RefCell<Option<BudgetExceeded>>,
+}
+
+impl Default for BudgetState {
+    fn default() -> Self {
+        Self {
+            size: Cell::new(0),
+            sample_interval: Cell::new(DEFAULT_SAMPLE_INTERVAL),
+            exceeded: RefCell::new(None),
+        }
+    }
+}
+
+/// Handle to a state budget attached to a stream with
+/// [`Stream::state_budget`] or [`Stream::trace_budget`].
+///
+/// Stateful operators, such as incremental joins, distinct, and aggregates,
+/// keep their state in traces (see [`Stream::integrate_trace`]), while
+/// integrals (see [`Stream::integrate`]) keep the integrated value.  Without a
+/// limit, this state grows with the data until the process runs out of
+/// memory.  A state budget measures the size of the state at the first clock
+/// cycle and then once every [`DEFAULT_SAMPLE_INTERVAL`] cycles (see
+/// [`Self::with_sample_interval`]), and fails the step with
+/// [`SchedulerError::OperatorError`](`crate::circuit::schedule::Error::OperatorError`)
+/// once it exceeds the budget, at which point the application can inspect
+/// the [`BudgetExceeded`] report via [`Self::exceeded`] and shed load or shut
+/// down gracefully.  Measuring requires traversing the state, so the state
+/// may grow beyond the budget between two measurements and the violation is
+/// only reported at the next one.
+///
+/// Traces are currently stored in memory only, so state cannot be spilled to
+/// disk when the budget is exceeded.
+#[derive(Clone)]
+pub struct StateBudget {
+    budget: usize,
+    state: Rc<BudgetState>,
+}
+
+impl StateBudget {
+    /// Budget in bytes.
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Measure the size of the state once every `cycles` clock cycles
+    /// instead of every [`DEFAULT_SAMPLE_INTERVAL`] cycles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cycles` is 0.
+    pub fn with_sample_interval(self, cycles: usize) -> Self {
+        assert_ne!(cycles, 0, "sample interval must be positive");
+        self.state.sample_interval.set(cycles);
+        self
+    }
+
+    /// Number of clock cycles between two measurements.
+    pub fn sample_interval(&self) -> usize {
+        self.state.sample_interval.get()
+    }
+
+    /// Size of the state in bytes at the last measurement.
+    pub fn size(&self) -> usize {
+        self.state.size.get()
+    }
+
+    /// Returns the first budget violation, if any.
+    pub fn exceeded(&self) -> Option<BudgetExceeded> {
+        self.state.exceeded.borrow().clone()
+    }
+}
+
+impl<P, T> Stream<Circuit<P>, T>
+where
+    P: Clone + 'static,
+    T: Clone + 'static,
+{
+    /// Limit the size of the operator state carried by `self` to `bytes`
+    /// bytes.
+    ///
+    /// `self` must be a stream that holds operator state, e.g., the output of
+    /// [`Self::integrate`] or [`Self::integrate_trace`].  The size of the
+    /// current value in the stream is measured periodically (which requires
+    /// traversing it) and the step fails if it exceeds the budget (see
+    /// [`StateBudget`]).
+    pub fn state_budget(&self, bytes: usize) -> StateBudget
+    where
+        T: DeepSizeOf,
+    {
+        let budget = StateBudget {
+            budget: bytes,
+            state: Rc::new(BudgetState::default()),
+        };
+        self.circuit()
+            .add_sink(StateSize::new(budget.clone()), self);
+        budget
+    }
+
+    /// Limit the size of the trace of `self`, i.e., of
+    /// `self.integrate_trace()`, to `bytes` bytes.
+    ///
+    /// The trace is shared by all operators that arrange `self`, e.g.,
+    /// [`Self::join_incremental`] and [`Self::distinct_incremental`], so the
+    /// budget limits their combined state derived from `self`.
+    pub fn trace_budget(&self, bytes: usize) -> StateBudget
+    where
+        T: Batch + DeepSizeOf,
+        T::Key: Ord,
+        T::Val: Ord,
+    {
+        self.integrate_trace().state_budget(bytes)
+    }
+}
+
+// Sink that measures the size of the state and reports budget violations.
+struct StateSize<T> {
+    budget: StateBudget,
+    // Number of clock cycles evaluated so far.
+    step: usize,
+    error: Option<Cow<'static, str>>,
+    _type: PhantomData<T>,
+}
+
+impl<T> StateSize<T> {
+    fn new(budget: StateBudget) -> Self {
+        Self {
+            budget,
+            step: 0,
+            error: None,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T> Operator for StateSize<T>
+where
+    T: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("StateBudget")
+    }
+    fn summary(&self, output: &mut String) {
+        *output = format!(
+            "size: {} bytes, budget: {} bytes",
+            self.budget.size(),
+            self.budget.budget()
+        );
+    }
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+    fn take_error(&mut self) -> Option<Cow<'static, str>> {
+        self.error.take()
+    }
+}
+
+impl<T> SinkOperator<T> for StateSize<T>
+where
+    T: DeepSizeOf + 'static,
+{
+    fn eval(&mut self, state: &T) {
+        let sample = self.step.is_multiple_of(self.budget.sample_interval());
+        self.step += 1;
+        if !sample {
+            return;
+        }
+
+        let size = state.deep_size_of();
+        self.budget.state.size.set(size);
+
+        if size > self.budget.budget {
+            let exceeded = BudgetExceeded {
+                size,
+                budget: self.budget.budget,
+            };
+            self.error = Some(Cow::from(exceeded.to_string()));
+            self.budget
+                .state
+                .exceeded
+                .borrow_mut()
+                .get_or_insert(exceeded);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        circuit::{schedule::Error as SchedulerError, Root},
+        trace::ord::OrdZSet,
+    };
+
+    #[test]
+    fn trace_budget_test() {
+        let mut handles = None;
+        let root = Root::build(|circuit| {
+            let (input, handle) = circuit.add_input::<OrdZSet<usize, isize>>();
+            handles = Some((handle, input.trace_budget(4096).with_sample_interval(1)));
+        })
+        .unwrap();
+        let (input, budget) = handles.unwrap();
+
+        input.push(1, (), 1);
+        root.step().unwrap();
+        assert!(budget.size() > 0);
+        assert_eq!(budget.exceeded(), None);
+
+        for i in 0..1000 {
+            input.push(i, (), 1);
+        }
+        match root.step() {
+            Err(SchedulerError::OperatorError { name, error, .. }) => {
+                assert_eq!(name, "StateBudget");
+                let exceeded = budget.exceeded().unwrap();
+                assert_eq!(exceeded.budget, 4096);
+                assert!(exceeded.size > 4096);
+                assert_eq!(error, exceeded.to_string());
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn sample_interval_test() {
+        let mut handles = None;
+        let root = Root::build(|circuit| {
+            let (input, handle) = circuit.add_input::<OrdZSet<usize, isize>>();
+            handles = Some((handle, input.trace_budget(4096).with_sample_interval(3)));
+        })
+        .unwrap();
+        let (input, budget) = handles.unwrap();
+
+        // Measured at the first step.
+        input.push(1, (), 1);
+        root.step().unwrap();
+        let size = budget.size();
+        assert!(size > 0);
+
+        // The budget is overshot between measurements.
+        for i in 0..1000 {
+            input.push(i, (), 1);
+        }
+        root.step().unwrap();
+        root.step().unwrap();
+        assert_eq!(budget.size(), size);
+        assert_eq!(budget.exceeded(), None);
+
+        // ... and the violation is reported at the next one.
+        assert!(matches!(
+            root.step(),
+            Err(SchedulerError::OperatorError { .. })
+        ));
+        assert!(budget.exceeded().unwrap().size > 4096);
+    }
+}
@@ -0,0 +1,164 @@
+//! Stratified recursive computations.
+
+use std::{convert::TryFrom, rc::Rc};
+
+use crate::{
+    algebra::HasZero,
+    circuit::{schedule::Error as SchedulerError, Circuit, Stream},
+    trace::Batch,
+};
+use deepsize::DeepSizeOf;
+
+impl<P, Z> Stream<Circuit<P>, Z>
+where
+    P: Clone + 'static,
+    Z: HasZero + Clone + 'static,
+{
+    /// Evaluate a recursive stratum over `self`.
+    ///
+    /// Imports `self` into a new fixed point circuit via [`Stream::delta0`],
+    /// builds the body of the stratum by applying `stratum` to the imported
+    /// stream and evaluates it to a fixed point at every clock cycle of the
+    /// parent circuit.  Returns the stream of changes to the output of the
+    /// stratum in the parent circuit.
+    ///
+    /// The output of a stratum only becomes visible to the parent circuit once
+    /// the stratum has converged, so it can be safely consumed by non-monotone
+    /// operators, e.g., [`Stream::antijoin`], either in the parent circuit or
+    /// in a subsequent stratum.  A non-monotone operator inside a feedback loop
+    /// of the stratum itself is rejected with
+    /// [`SchedulerError::NonMonotoneRecursion`].
+    pub fn stratify<F, B>(&self, stratum: F) -> Result<Stream<Circuit<P>, B>, SchedulerError>
+    where
+        F: FnOnce(&Stream<Circuit<Circuit<P>>, Z>) -> Stream<Circuit<Circuit<P>>, B>,
+        B: Batch<Time = ()> + TryFrom<Rc<B>> + DeepSizeOf + Clone + 'static,
+        B::Key: Ord,
+        B::Val: Ord,
+    {
+        let output = self
+            .circuit()
+            .fixedpoint(|child| Ok(stratum(&self.delta0(child)).integrate_trace().export()))?;
+
+        Ok(output.consolidate())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::vec;
+
+    use crate::{
+        circuit::{schedule::Error as SchedulerError, Root, Stream},
+        operator::{DelayedFeedback, Generator},
+        trace::ord::{OrdIndexedZSet, OrdZSet},
+        zset,
+    };
+
+    #[test]
+    fn stratify_test() {
+        let root = Root::build(move |circuit| {
+            // Changes to the edges relation.
+            let mut edges: vec::IntoIter<OrdZSet<(usize, usize), isize>> = vec![
+                zset! { (1, 2) => 1, (2, 3) => 1, (4, 5) => 1 },
+                zset! { (3, 4) => 1 },
+                zset! { (1, 2) => -1 },
+            ]
+            .into_iter();
+
+            let mut roots: vec::IntoIter<OrdZSet<usize, isize>> =
+                vec![zset! { 1 => 1 }].into_iter();
+
+            // Expected nodes that are not reachable from node 1.
+            let mut outputs: vec::IntoIter<OrdZSet<usize, isize>> = vec![
+                zset! { 4 => 1, 5 => 1 },
+                zset! {},
+                zset! { 2 => 1, 3 => 1, 4 => 1, 5 => 1 },
+            ]
+            .into_iter();
+
+            let edges: Stream<_, OrdZSet<(usize, usize), isize>> =
+                circuit.add_source(Generator::new(move || edges.next().unwrap()));
+            let roots: Stream<_, OrdZSet<usize, isize>> =
+                circuit.add_source(Generator::new(move || {
+                    roots.next().unwrap_or_else(|| zset! {})
+                }));
+            let nodes: Stream<_, OrdZSet<usize, isize>> = circuit.add_source(Generator::new(
+                || zset! { 1 => 1, 2 => 1, 3 => 1, 4 => 1, 5 => 1 },
+            ));
+
+            // First stratum: nodes reachable from `roots`.
+            let reachable = edges
+                .stratify(|edges| {
+                    let child = edges.circuit();
+                    let roots = roots.delta0(child);
+                    let edges: Stream<_, OrdIndexedZSet<usize, usize, isize>> = edges.index();
+
+                    let reachable_delayed = <DelayedFeedback<_, OrdZSet<usize, isize>>>::new(child);
+                    let reachable = roots
+                        .plus(
+                            &reachable_delayed
+                                .stream()
+                                .join_trace(&edges, |_from, (), to| *to),
+                        )
+                        .distinct_trace();
+                    reachable_delayed.connect(&reachable);
+                    reachable
+                })
+                .unwrap();
+
+            // Second stratum: negate the output of the first one.
+            nodes
+                .antijoin(&reachable.integrate())
+                .inspect(move |unreachable| assert_eq!(*unreachable, outputs.next().unwrap()));
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn non_monotone_recursion() {
+        Root::build(|circuit| {
+            let result = circuit.fixedpoint(|child| {
+                let nodes: Stream<_, OrdZSet<usize, isize>> =
+                    child.add_source(Generator::new(|| zset! { 1 => 1 }));
+                let feedback = <DelayedFeedback<_, OrdZSet<usize, isize>>>::new(child);
+                let output = nodes.antijoin(feedback.stream());
+                feedback.connect(&output);
+                Ok(())
+            });
+
+            match result {
+                Err(SchedulerError::NonMonotoneRecursion { name, .. }) => {
+                    assert_eq!(name, "Antijoin")
+                }
+                _ => panic!("non-monotone recursion not detected"),
+            }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn non_monotone_iterate() {
+        Root::build(|circuit| {
+            let result = circuit.iterate(|child| {
+                let nodes: Stream<_, OrdZSet<usize, isize>> =
+                    child.add_source(Generator::new(|| zset! { 1 => 1 }));
+                let feedback = <DelayedFeedback<_, OrdZSet<usize, isize>>>::new(child);
+                let output = nodes.antijoin(feedback.stream());
+                feedback.connect(&output);
+                Ok((|| true, ()))
+            });
+
+            match result {
+                Err(SchedulerError::NonMonotoneRecursion { name, .. }) => {
+                    assert_eq!(name, "Antijoin")
+                }
+                _ => panic!("non-monotone recursion not detected"),
+            }
+        })
+        .unwrap();
+    }
+}
@@ -6,12 +6,17 @@ use crate::{
         operator_traits::{NaryOperator, Operator},
         Circuit, OwnershipPreference, Stream,
     },
+    trace::{
+        cursor::{CursorList, CursorStorage},
+        Batch, Builder,
+    },
     NumEntries,
 };
 use std::{
     borrow::Cow,
     cmp::Reverse,
     iter::once,
+    marker::PhantomData,
     mem::{take, ManuallyDrop},
     ops::Add,
 };
@@ -31,6 +36,27 @@ where
     }
 }
 
+impl<P, B> Stream<Circuit<P>, B>
+where
+    P: Clone + 'static,
+    B: Batch<Time = ()> + Clone + 'static,
+    B::Key: Ord + Clone,
+    B::Val: Ord + Clone,
+{
+    /// Apply the [`BatchSum`] operator to `self` and all streams in
+    /// `streams`.
+    ///
+    /// Computes the same result as [`sum`](`Self::sum`), but merges all
+    /// inputs in a single pass instead of adding them pairwise.
+    pub fn sum_batches<'a, I>(&'a self, streams: I) -> Stream<Circuit<P>, B>
+    where
+        I: IntoIterator<Item = &'a Self>,
+    {
+        self.circuit()
+            .add_nary_operator(BatchSum::new(), once(self).chain(streams))
+    }
+}
+
 /// Operator that computes the sum of values across all its input streams at
 /// each timestamp.
 pub struct Sum<D>
@@ -125,12 +151,110 @@ where
     }
 }
 
+/// Operator that computes the sum of batches across all its input streams at
+/// each timestamp.
+///
+/// Unlike [`Sum`], which adds its inputs pairwise, allocating an
+/// intermediate batch for each addition, this operator performs a single
+/// k-way merge of all non-empty inputs.  If there is only one non-empty input
+/// and it is received by value, it is returned without copying.
+pub struct BatchSum<B> {
+    _type: PhantomData<B>,
+}
+
+impl<B> BatchSum<B> {
+    pub fn new() -> Self {
+        Self { _type: PhantomData }
+    }
+}
+
+impl<B> Default for BatchSum<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B> Operator for BatchSum<B>
+where
+    B: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("BatchSum")
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+}
+
+// Provides storage for cursors over `BatchSum` inputs.
+struct Inputs<'s, B: Clone>(&'s [Cow<'s, B>]);
+
+impl<'s, B: Clone> CursorStorage<'s, B> for Inputs<'s, B> {
+    #[inline]
+    fn storage(&self, index: usize) -> &'s B {
+        match &self.0[index] {
+            Cow::Borrowed(batch) => batch,
+            Cow::Owned(batch) => batch,
+        }
+    }
+}
+
+impl<B> NaryOperator<B, B> for BatchSum<B>
+where
+    B: Batch<Time = ()> + Clone + 'static,
+    B::Key: Ord + Clone,
+    B::Val: Ord + Clone,
+{
+    fn eval<'a, Iter>(&'a mut self, inputs: Iter) -> B
+    where
+        Iter: Iterator<Item = Cow<'a, B>>,
+    {
+        let mut inputs: Vec<Cow<'a, B>> = inputs.filter(|input| !input.is_empty()).collect();
+
+        match inputs.len() {
+            0 => B::empty(()),
+            1 => inputs.pop().unwrap().into_owned(),
+            _ => {
+                let capacity = inputs.iter().map(|input| input.len()).sum();
+                let storage = Inputs(inputs.as_slice());
+                let mut cursor = CursorList::new(
+                    inputs.iter().map(|input| input.cursor()).collect(),
+                    &storage,
+                );
+                let mut builder = B::Builder::with_capacity((), capacity);
+
+                while cursor.key_valid_in() {
+                    while cursor.val_valid_in() {
+                        let weight = cursor.weight_in(&storage);
+                        if !weight.is_zero() {
+                            builder.push((
+                                cursor.key_in(&storage).clone(),
+                                cursor.val_in(&storage).clone(),
+                                weight,
+                            ));
+                        }
+                        cursor.step_val_in(&storage);
+                    }
+                    cursor.step_key_in(&storage);
+                }
+
+                builder.done()
+            }
+        }
+    }
+
+    fn input_preference(&self) -> OwnershipPreference {
+        OwnershipPreference::PREFER_OWNED
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
         algebra::HasZero,
         circuit::{Circuit, OwnershipPreference, Root},
-        operator::{Generator, Inspect},
+        operator::{Apply2, Generator, Inspect},
         trace::{ord::OrdZSet, Batch},
         zset,
     };
@@ -214,4 +338,36 @@ mod test {
             root.step().unwrap();
         }
     }
+
+    #[test]
+    fn zset_sum_batches() {
+        let root = Root::build(move |circuit| {
+            let mut n = 0;
+            let source1 = circuit.add_source(Generator::new(move || {
+                n += 1;
+                zset! { n => 1, n + 1 => 1 }
+            }));
+            let mut n = 0;
+            let source2 = circuit.add_source(Generator::new(move || {
+                n += 1;
+                zset! { n => -1, n + 2 => 2 }
+            }));
+            let source3 = circuit.add_source(Generator::new(|| zset! {}));
+
+            let sum = source1.sum(&[source2.clone(), source3.clone()]);
+            let sum_batches = source1.sum_batches(&[source2, source3]);
+            circuit.add_binary_operator(
+                Apply2::new(|s1: &OrdZSet<usize, isize>, s2: &OrdZSet<usize, isize>| {
+                    assert_eq!(s1, s2)
+                }),
+                &sum,
+                &sum_batches,
+            );
+        })
+        .unwrap();
+
+        for _ in 0..100 {
+            root.step().unwrap();
+        }
+    }
 }
@@ -0,0 +1,306 @@
+//! Source and sink operators that exchange batches over TCP.
+//!
+//! [`TcpSink`] serializes each input batch with `bincode` and writes it to a
+//! TCP stream as a length-prefixed frame.  [`TcpSource`] reads one frame per
+//! clock cycle and turns it back into a batch.  Connecting the sink of one
+//! circuit to the source of another chains DBSP processes into a pipeline
+//! where each step of the upstream circuit becomes one step of the
+//! downstream circuit.
+
+// TODO:
+// - Non-blocking source (the source blocks until the next frame arrives).
+
+use crate::{
+    circuit::{
+        operator_traits::{Data, Operator, SinkOperator, SourceOperator},
+        Circuit, StepStats, Stream,
+    },
+    operator::codec::{read_batch, write_batch, DEFAULT_MAX_FRAME_LEN},
+    trace::{Batch, BatchReader},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    borrow::Cow,
+    io::{BufReader, BufWriter, Write},
+    marker::PhantomData,
+    net::TcpStream,
+    rc::Rc,
+};
+
+impl<P, B> Stream<Circuit<P>, B>
+where
+    P: Clone + 'static,
+    B: BatchReader<Time = ()> + Clone + 'static,
+    B::Key: Serialize,
+    B::Val: Serialize,
+    B::R: Serialize,
+{
+    /// Send the contents of `self` to `socket` using the [`TcpSink`]
+    /// operator.
+    pub fn tcp_sink(&self, socket: TcpStream) {
+        self.circuit().add_sink(
+            TcpSink::new(socket).with_step_stats(self.circuit().step_stats()),
+            self,
+        );
+    }
+}
+
+/// Sink operator that writes each input batch to a TCP stream.
+///
+/// Each batch is encoded as a `u64` little-endian length followed by the
+/// `bincode` encoding of its `(key, value, weight)` tuples in cursor order.
+/// The writer is flushed at the end of every clock cycle, so the peer
+/// observes exactly one frame per step, including empty batches.
+///
+/// An I/O or serialization error is reported as an
+/// [`OperatorError`](`crate::circuit::schedule::Error::OperatorError`)
+/// that aborts the current step.  After an error the connection is in an
+/// unknown state, so the sink discards all subsequent inputs.
+pub struct TcpSink<B> {
+    writer: BufWriter<TcpStream>,
+    step_stats: Option<Rc<StepStats>>,
+    failed: bool,
+    error: Option<Cow<'static, str>>,
+    _type: PhantomData<B>,
+}
+
+impl<B> TcpSink<B> {
+    /// Create a sink that writes batches to `socket`.
+    pub fn new(socket: TcpStream) -> Self {
+        Self {
+            writer: BufWriter::new(socket),
+            step_stats: None,
+            failed: false,
+            error: None,
+            _type: PhantomData,
+        }
+    }
+
+    /// Report the number of updates written by the sink to `step_stats`.
+    pub fn with_step_stats(mut self, step_stats: Rc<StepStats>) -> Self {
+        self.step_stats = Some(step_stats);
+        self
+    }
+}
+
+impl<B> Operator for TcpSink<B>
+where
+    B: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("TcpSink")
+    }
+
+    fn fixedpoint(&self) -> bool {
+        true
+    }
+
+    fn take_error(&mut self) -> Option<Cow<'static, str>> {
+        self.error.take()
+    }
+}
+
+impl<B> SinkOperator<B> for TcpSink<B>
+where
+    B: BatchReader<Time = ()> + 'static,
+    B::Key: Serialize,
+    B::Val: Serialize,
+    B::R: Serialize,
+{
+    fn eval(&mut self, batch: &B) {
+        if self.failed {
+            return;
+        }
+        if let Some(step_stats) = &self.step_stats {
+            step_stats.record_outputs(batch.len());
+        }
+        if let Err(e) = write_batch(batch, &mut self.writer).and_then(|_| self.writer.flush()) {
+            self.failed = true;
+            self.error = Some(Cow::from(format!("failed to write batch: {}", e)));
+        }
+    }
+}
+
+/// Source operator that reads batches written by a [`TcpSink`] from a TCP
+/// stream.
+///
+/// The operator reads one frame per clock cycle, blocking until it is
+/// available.  Once the peer closes the connection, the operator yields
+/// empty batches.
+///
+/// An I/O or decoding error, including a frame longer than the maximum
+/// frame length (see [`with_max_frame_len`](`Self::with_max_frame_len`)),
+/// is reported as an
+/// [`OperatorError`](`crate::circuit::schedule::Error::OperatorError`)
+/// that aborts the current step.  The connection is treated as closed
+/// afterwards.
+pub struct TcpSource<B> {
+    reader: BufReader<TcpStream>,
+    // Reused across clock cycles to avoid reallocating the frame buffer.
+    buffer: Vec<u8>,
+    max_frame_len: usize,
+    eof: bool,
+    error: Option<Cow<'static, str>>,
+    _type: PhantomData<B>,
+}
+
+impl<B> TcpSource<B> {
+    /// Create a source that reads batches from `socket`.
+    pub fn new(socket: TcpStream) -> Self {
+        Self {
+            reader: BufReader::new(socket),
+            buffer: Vec::new(),
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            eof: false,
+            error: None,
+            _type: PhantomData,
+        }
+    }
+
+    /// Reject frames longer than `max_frame_len` bytes instead of
+    /// allocating a buffer for them.  The default limit is 1 GiB.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+}
+
+impl<B> Operator for TcpSource<B>
+where
+    B: Data,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("TcpSource")
+    }
+
+    fn fixedpoint(&self) -> bool {
+        false
+    }
+
+    fn take_error(&mut self) -> Option<Cow<'static, str>> {
+        self.error.take()
+    }
+}
+
+impl<B> SourceOperator<B> for TcpSource<B>
+where
+    B: Batch<Time = ()> + Data,
+    B::Key: DeserializeOwned,
+    B::Val: DeserializeOwned,
+    B::R: DeserializeOwned,
+{
+    fn eval(&mut self) -> B {
+        if self.eof {
+            return B::empty(());
+        }
+
+        match read_batch(&mut self.reader, &mut self.buffer, self.max_frame_len) {
+            Ok(Some(batch)) => batch,
+            Ok(None) => {
+                self.eof = true;
+                B::empty(())
+            }
+            Err(e) => {
+                self.eof = true;
+                self.error = Some(Cow::from(format!("failed to read batch: {}", e)));
+                B::empty(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TcpSource;
+    use crate::{
+        circuit::{schedule::Error as SchedulerError, Root},
+        operator::Generator,
+        trace::{ord::OrdZSet, Batch},
+        zset,
+    };
+    use std::{
+        io::Write,
+        net::{TcpListener, TcpStream},
+        thread::spawn,
+    };
+
+    #[test]
+    fn tcp_pipeline() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Upstream circuit: sends `{ n => 1, n + 1 => -1 }` in step `n`.
+        let producer = spawn(move || {
+            let socket = TcpStream::connect(addr).unwrap();
+            let root = Root::build(move |circuit| {
+                let mut n: usize = 0;
+                circuit
+                    .add_source(Generator::new(move || -> OrdZSet<usize, isize> {
+                        n += 1;
+                        zset! { n => 1, n + 1 => -1 }
+                    }))
+                    .tcp_sink(socket);
+            })
+            .unwrap();
+
+            for _ in 0..10 {
+                root.step().unwrap();
+            }
+        });
+
+        let (socket, _) = listener.accept().unwrap();
+        let root = Root::build(move |circuit| {
+            let mut n: usize = 0;
+            circuit
+                .add_source(TcpSource::<OrdZSet<usize, isize>>::new(socket))
+                .inspect(move |batch| {
+                    n += 1;
+                    if n <= 10 {
+                        assert_eq!(batch, &zset! { n => 1, n + 1 => -1 });
+                    } else {
+                        // The upstream circuit has terminated.
+                        assert_eq!(batch, &OrdZSet::empty(()));
+                    }
+                });
+        })
+        .unwrap();
+
+        for _ in 0..10 {
+            root.step().unwrap();
+        }
+
+        producer.join().unwrap();
+        root.step().unwrap();
+    }
+
+    #[test]
+    fn tcp_frame_too_long() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Send a header that announces a 1 TiB frame.
+        let mut socket = TcpStream::connect(addr).unwrap();
+        socket.write_all(&(1u64 << 40).to_le_bytes()).unwrap();
+
+        let (socket, _) = listener.accept().unwrap();
+        let root = Root::build(move |circuit| {
+            circuit
+                .add_source(
+                    TcpSource::<OrdZSet<usize, isize>>::new(socket).with_max_frame_len(1 << 20),
+                )
+                .inspect(|batch| assert_eq!(batch, &OrdZSet::empty(())));
+        })
+        .unwrap();
+
+        match root.step() {
+            Err(SchedulerError::OperatorError { name, error, .. }) => {
+                assert_eq!(name, "TcpSource");
+                assert!(error.contains("exceeds the maximum frame length"));
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        // The connection is treated as closed afterwards.
+        root.step().unwrap();
+    }
+}
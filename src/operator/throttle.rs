@@ -0,0 +1,177 @@
+//! Operator that limits the number of updates released per clock cycle.
+
+use crate::{
+    circuit::{
+        operator_traits::{Operator, UnaryOperator},
+        Circuit, Scope, Stream,
+    },
+    trace::{cursor::Cursor, Batch, Builder},
+};
+use std::{borrow::Cow, mem::replace};
+
+impl<P, B> Stream<Circuit<P>, B>
+where
+    P: Clone + 'static,
+    B: Batch<Time = ()> + Clone + 'static,
+    B::Key: Clone,
+    B::Val: Clone,
+{
+    /// Apply [`Throttle`] operator to `self`.
+    ///
+    /// Releases at most `max_updates` updates per clock cycle, buffering the
+    /// rest.
+    pub fn throttle(&self, max_updates: usize) -> Stream<Circuit<P>, B> {
+        self.circuit()
+            .add_unary_operator(Throttle::new(max_updates), self)
+    }
+}
+
+/// Operator that buffers its input and releases at most `max_updates` updates
+/// per clock cycle.
+///
+/// Updates received by the operator are added to an internal buffer.  At each
+/// clock cycle, the operator outputs up to `max_updates` updates from the
+/// buffer, in key order, and keeps the rest for subsequent clock cycles.
+/// Since updates are consolidated in the buffer, an update that is retracted
+/// before being released is never output.  The sum of all outputs is equal to
+/// the sum of all inputs once the buffer has been drained.
+///
+/// This is useful for rate-limiting expensive downstream operators, e.g.,
+/// output sinks, and for feeding large inputs, e.g., a backfill, to a circuit
+/// in smaller chunks.
+pub struct Throttle<B> {
+    max_updates: usize,
+    buffer: B,
+    empty_input: bool,
+}
+
+impl<B> Throttle<B>
+where
+    B: Batch<Time = ()>,
+{
+    pub fn new(max_updates: usize) -> Self {
+        assert!(max_updates > 0, "Throttle: max_updates must be positive");
+
+        Self {
+            max_updates,
+            buffer: B::empty(()),
+            empty_input: false,
+        }
+    }
+
+    /// Number of updates buffered by the operator.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+impl<B> Operator for Throttle<B>
+where
+    B: Batch<Time = ()> + 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("Throttle")
+    }
+
+    fn is_stateful(&self) -> bool {
+        true
+    }
+
+    fn clock_start(&mut self, scope: Scope) {
+        if scope == 0 {
+            self.buffer = B::empty(());
+        }
+    }
+
+    fn summary(&self, output: &mut String) {
+        *output = format!("buffered: {}", self.buffered());
+    }
+
+    fn fixedpoint(&self) -> bool {
+        self.empty_input && self.buffer.is_empty()
+    }
+}
+
+impl<B> UnaryOperator<B, B> for Throttle<B>
+where
+    B: Batch<Time = ()> + 'static,
+    B::Key: Clone,
+    B::Val: Clone,
+{
+    fn eval(&mut self, input: &B) -> B {
+        self.empty_input = input.is_empty();
+
+        let buffer = replace(&mut self.buffer, B::empty(()));
+        let buffer = if input.is_empty() {
+            buffer
+        } else {
+            buffer.merge(input)
+        };
+
+        if buffer.len() <= self.max_updates {
+            return buffer;
+        }
+
+        let mut output = B::Builder::with_capacity((), self.max_updates);
+        let mut rest = B::Builder::with_capacity((), buffer.len() - self.max_updates);
+        let mut released = 0;
+
+        let mut cursor = buffer.cursor();
+        while cursor.key_valid(&buffer) {
+            while cursor.val_valid(&buffer) {
+                let update = (
+                    cursor.key(&buffer).clone(),
+                    cursor.val(&buffer).clone(),
+                    cursor.weight(&buffer),
+                );
+                if released < self.max_updates {
+                    output.push(update);
+                    released += 1;
+                } else {
+                    rest.push(update);
+                }
+                cursor.step_val(&buffer);
+            }
+            cursor.step_key(&buffer);
+        }
+
+        self.buffer = rest.done();
+        output.done()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{circuit::Root, operator::Generator, trace::ord::OrdZSet, zset};
+    use std::vec;
+
+    #[test]
+    fn throttle_test() {
+        let root = Root::build(move |circuit| {
+            let mut input: vec::IntoIter<OrdZSet<usize, isize>> = vec![
+                zset! { 1 => 1, 2 => 1, 3 => 1, 4 => 1, 5 => 1 },
+                zset! { 4 => -1, 6 => 1 },
+                zset! {},
+                zset! {},
+            ]
+            .into_iter();
+            let mut expected: vec::IntoIter<OrdZSet<usize, isize>> = vec![
+                zset! { 1 => 1, 2 => 1 },
+                zset! { 3 => 1, 5 => 1 },
+                zset! { 6 => 1 },
+                zset! {},
+            ]
+            .into_iter();
+
+            circuit
+                .add_source(Generator::new(move || input.next().unwrap()))
+                .throttle(2)
+                .inspect(move |batch| assert_eq!(*batch, expected.next().unwrap()));
+        })
+        .unwrap();
+
+        for _ in 0..4 {
+            root.step().unwrap();
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Global top-k operator with offset.
+
+use crate::{
+    algebra::{AddAssignByRef, HasZero, ZRingValue, ZSet},
+    circuit::{
+        operator_traits::{BinaryOperator, Operator, UnaryOperator},
+        Circuit, Scope, Stream,
+    },
+    operator::ParamStream,
+    trace::{cursor::Cursor, BatchReader},
+};
+use std::{borrow::Cow, cmp::Ordering, marker::PhantomData, ops::Neg};
+
+impl<P, Z> Stream<Circuit<P>, Z>
+where
+    P: Clone + 'static,
+    Z: Clone + 'static,
+{
+    /// Apply [`TopK`] operator to `self`.
+    ///
+    /// Takes a stream of changes to a Z-set and outputs the changes to the
+    /// window of `k` elements that follow the first `offset` elements of the
+    /// Z-set, sorted by `cmp`.
+    pub fn topk_with_offset<F>(&self, k: usize, offset: usize, cmp: F) -> Stream<Circuit<P>, Z>
+    where
+        Z: ZSet,
+        Z::Key: Clone + Ord,
+        Z::R: ZRingValue,
+        F: Fn(&Z::Key, &Z::Key) -> Ordering + 'static,
+    {
+        self.circuit()
+            .add_unary_operator(TopK::new(k, offset, cmp), self)
+    }
+
+    /// Like [`Self::topk_with_offset`], but reads the `(k, offset)` pair that
+    /// defines the window from a parameter stream.
+    ///
+    /// When the parameter changes, the operator outputs the difference
+    /// between the old window and the window computed with the new
+    /// parameters over the entire integral of the input.
+    pub fn topk_param<F>(
+        &self,
+        window: &ParamStream<P, (usize, usize)>,
+        cmp: F,
+    ) -> Stream<Circuit<P>, Z>
+    where
+        Z: ZSet,
+        Z::Key: Clone + Ord,
+        Z::R: ZRingValue,
+        F: Fn(&Z::Key, &Z::Key) -> Ordering + 'static,
+    {
+        self.circuit()
+            .add_binary_operator(TopK::new(0, 0, cmp), self, window.stream())
+    }
+}
+
+/// Incrementally maintained sorted window of a Z-set.
+///
+/// The operator integrates its input, a stream of changes to a Z-set, and
+/// keeps the elements of the integral sorted by `cmp`, breaking ties using
+/// the natural order of elements.  The window consists of the `k` elements
+/// with positive weights that follow the first `offset` such elements, e.g.,
+/// a page of a leaderboard.  At each clock cycle, the operator outputs the
+/// difference between the new and the old contents of the window, with each
+/// element weighted by its weight in the integral.
+///
+/// Retractions are handled at the window boundary: when an element of the
+/// window is deleted, the next element in sort order enters the window, and
+/// when an element is inserted before or inside the window, the last element
+/// of the window is pushed out.
+///
+/// The integral is stored in a sorted vector.  Changed elements are located
+/// by binary search, but inserting or removing an element shifts the
+/// elements that follow it, so a clock cycle costs `O(|delta| * n)` in the
+/// worst case, where `n` is the number of distinct elements in the integral,
+/// plus `O(offset + k)` to recompute the window.  Inserting and removing
+/// elements near the end of the sort order is cheap.
+pub struct TopK<Z, F>
+where
+    Z: BatchReader,
+{
+    k: usize,
+    offset: usize,
+    cmp: F,
+    // Elements of the integral with non-zero weights, sorted by `cmp`.
+    sorted: Vec<(Z::Key, Z::R)>,
+    // Current window.
+    window: Vec<(Z::Key, Z::R)>,
+    empty_output: bool,
+    _type: PhantomData<Z>,
+}
+
+impl<Z, F> TopK<Z, F>
+where
+    Z: BatchReader,
+    Z::Key: Clone + Ord,
+    Z::R: ZRingValue,
+    F: Fn(&Z::Key, &Z::Key) -> Ordering,
+{
+    pub fn new(k: usize, offset: usize, cmp: F) -> Self {
+        Self {
+            k,
+            offset,
+            cmp,
+            sorted: Vec::new(),
+            window: Vec::new(),
+            empty_output: false,
+            _type: PhantomData,
+        }
+    }
+
+    /// Current contents of the window in sort order.
+    pub fn window(&self) -> &[(Z::Key, Z::R)] {
+        &self.window
+    }
+
+    fn order(&self, k1: &Z::Key, k2: &Z::Key) -> Ordering {
+        (self.cmp)(k1, k2).then_with(|| k1.cmp(k2))
+    }
+
+    // Add `weight` to the weight of `key` in `self.sorted`.
+    fn update(&mut self, key: &Z::Key, weight: Z::R) {
+        let index = self
+            .sorted
+            .partition_point(|(k, _)| self.order(k, key) == Ordering::Less);
+
+        if index < self.sorted.len() && &self.sorted[index].0 == key {
+            self.sorted[index].1.add_assign_by_ref(&weight);
+            if self.sorted[index].1.is_zero() {
+                self.sorted.remove(index);
+            }
+        } else {
+            self.sorted.insert(index, (key.clone(), weight));
+        }
+    }
+
+    fn compute_window(&self) -> Vec<(Z::Key, Z::R)> {
+        self.sorted
+            .iter()
+            .filter(|(_, w)| w.ge0())
+            .skip(self.offset)
+            .take(self.k)
+            .cloned()
+            .collect()
+    }
+}
+
+impl<Z, F> Operator for TopK<Z, F>
+where
+    Z: BatchReader + 'static,
+    F: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("TopK")
+    }
+
+    fn is_stateful(&self) -> bool {
+        true
+    }
+
+    fn clock_start(&mut self, scope: Scope) {
+        if scope == 0 {
+            self.sorted.clear();
+            self.window.clear();
+        }
+    }
+
+    fn summary(&self, output: &mut String) {
+        *output = format!(
+            "elements: {}, window: {}",
+            self.sorted.len(),
+            self.window.len()
+        );
+    }
+
+    fn params(&self) -> Vec<(Cow<'static, str>, String)> {
+        vec![
+            (Cow::from("k"), self.k.to_string()),
+            (Cow::from("offset"), self.offset.to_string()),
+        ]
+    }
+
+    fn fixedpoint(&self) -> bool {
+        self.empty_output
+    }
+}
+
+impl<Z, F> UnaryOperator<Z, Z> for TopK<Z, F>
+where
+    Z: ZSet + 'static,
+    Z::Key: Clone + Ord,
+    Z::R: ZRingValue,
+    F: Fn(&Z::Key, &Z::Key) -> Ordering + 'static,
+{
+    fn eval(&mut self, delta: &Z) -> Z {
+        let mut cursor = delta.cursor();
+        while cursor.key_valid(delta) {
+            self.update(cursor.key(delta), cursor.weight(delta));
+            cursor.step_key(delta);
+        }
+
+        let window = self.compute_window();
+        let mut changes = Vec::with_capacity(window.len() + self.window.len());
+        for (key, weight) in self.window.drain(..) {
+            changes.push(((key, ()), weight.neg()));
+        }
+        for (key, weight) in window.iter() {
+            changes.push(((key.clone(), ()), weight.clone()));
+        }
+        self.window = window;
+
+        let output = Z::from_tuples((), changes);
+        self.empty_output = output.is_empty();
+        output
+    }
+}
+
+impl<Z, F> BinaryOperator<Z, (usize, usize), Z> for TopK<Z, F>
+where
+    Z: ZSet + 'static,
+    Z::Key: Clone + Ord,
+    Z::R: ZRingValue,
+    F: Fn(&Z::Key, &Z::Key) -> Ordering + 'static,
+{
+    fn eval(&mut self, delta: &Z, &(k, offset): &(usize, usize)) -> Z {
+        self.k = k;
+        self.offset = offset;
+        UnaryOperator::eval(self, delta)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{circuit::Root, operator::Generator, trace::ord::OrdZSet, zset};
+    use std::vec;
+
+    #[test]
+    fn topk_with_offset_test() {
+        let root = Root::build(move |circuit| {
+            let mut input: vec::IntoIter<OrdZSet<(&'static str, usize), isize>> = vec![
+                zset! { ("a", 10) => 1, ("b", 20) => 1, ("c", 30) => 1, ("d", 40) => 1 },
+                // Insert before the window: "c" enters the window, "a" leaves it.
+                zset! { ("e", 50) => 1 },
+                // Delete before the window: "a" slides back in.
+                zset! { ("d", 40) => -1 },
+                // Change outside the window.
+                zset! { ("f", 1) => 1 },
+                // Delete before the window, leaving a partial window.
+                zset! { ("e", 50) => -1, ("c", 30) => -1 },
+            ]
+            .into_iter();
+
+            // Second page of size 2 by descending score.
+            let mut expected: vec::IntoIter<OrdZSet<(&'static str, usize), isize>> = vec![
+                zset! { ("b", 20) => 1, ("a", 10) => 1 },
+                zset! { ("a", 10) => -1, ("c", 30) => 1 },
+                zset! { ("c", 30) => -1, ("a", 10) => 1 },
+                zset! {},
+                zset! { ("b", 20) => -1, ("a", 10) => -1, ("f", 1) => 1 },
+            ]
+            .into_iter();
+
+            circuit
+                .add_source(Generator::new(move || input.next().unwrap()))
+                .topk_with_offset(2, 2, |(_, s1), (_, s2)| s2.cmp(s1))
+                .inspect(move |window| assert_eq!(*window, expected.next().unwrap()));
+        })
+        .unwrap();
+
+        for _ in 0..5 {
+            root.step().unwrap();
+        }
+    }
+}
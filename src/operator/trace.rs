@@ -5,7 +5,10 @@ use crate::{
     },
     circuit_cache_key,
     time::NestedTimestamp32,
-    trace::{cursor::Cursor, spine_fueled::Spine, Batch, BatchReader, Builder, Trace, TraceReader},
+    trace::{
+        cursor::Cursor, spine_fueled::Spine, Batch, BatchReader, Builder, EffortConfig, Trace,
+        TraceReader,
+    },
     NumEntries, Timestamp,
 };
 use deepsize::DeepSizeOf;
@@ -15,8 +18,6 @@ circuit_cache_key!(TraceId<B, D>(NodeId => Stream<B, D>));
 circuit_cache_key!(DelayedTraceId<B, D>(NodeId => Stream<B, D>));
 circuit_cache_key!(IntegrateTraceId<B, D>(NodeId => Stream<B, D>));
 
-// TODO: add infrastructure to compact the trace during slack time.
-
 /// Add `timestamp` to all tuples in the input batch.
 ///
 /// Given an input batch without timing information (`BatchReader::Time = ()`),
@@ -78,30 +79,65 @@ where
     {
         self.circuit()
             .cache_get_or_insert_with(TraceId::new(self.local_node_id()), || {
-                self.circuit().region("trace", || {
-                    let (ExportStream { local, export }, z1feedback) =
-                        self.circuit().add_feedback_with_export(Z1Trace::new(false));
-                    let trace = self.circuit().add_binary_operator_with_preference(
-                        <TraceAppend<T, B>>::new(),
-                        &local,
-                        self,
-                        OwnershipPreference::STRONGLY_PREFER_OWNED,
-                        OwnershipPreference::PREFER_OWNED,
-                    );
-                    z1feedback.connect_with_preference(
-                        &trace,
-                        OwnershipPreference::STRONGLY_PREFER_OWNED,
-                    );
-                    self.circuit()
-                        .cache_insert(DelayedTraceId::new(trace.local_node_id()), local);
-                    self.circuit()
-                        .cache_insert(ExportId::new(trace.local_node_id()), export);
-                    trace
-                })
+                self.trace_with_z1(Z1Trace::new(false).with_effort(self.circuit().trace_effort()))
             })
             .clone()
     }
 
+    /// Like [`Self::trace`], but invokes `gc` on the trace at the end of each
+    /// clock epoch (see [`Z1Trace::with_gc`]).
+    ///
+    /// Unlike [`Self::trace`], the trace is not shared with other operators
+    /// that trace `self`.
+    pub(crate) fn trace_with_gc<T, F>(&self, gc: F) -> Stream<Circuit<P>, T>
+    where
+        B: BatchReader<Time = ()>,
+        B::Key: Clone,
+        B::Val: Clone,
+        T: NumEntries
+            + DeepSizeOf
+            + Trace<Key = B::Key, Val = B::Val, Time = NestedTimestamp32, R = B::R>
+            + Clone
+            + 'static,
+        F: FnMut(&mut T) + 'static,
+    {
+        self.trace_with_z1(
+            Z1Trace::new(false)
+                .with_effort(self.circuit().trace_effort())
+                .with_gc(gc),
+        )
+    }
+
+    fn trace_with_z1<T>(&self, z1: Z1Trace<T>) -> Stream<Circuit<P>, T>
+    where
+        B: BatchReader<Time = ()>,
+        B::Key: Clone,
+        B::Val: Clone,
+        T: NumEntries
+            + DeepSizeOf
+            + Trace<Key = B::Key, Val = B::Val, Time = NestedTimestamp32, R = B::R>
+            + Clone
+            + 'static,
+    {
+        self.circuit().region("trace", || {
+            let (ExportStream { local, export }, z1feedback) =
+                self.circuit().add_feedback_with_export(z1);
+            let trace = self.circuit().add_binary_operator_with_preference(
+                <TraceAppend<T, B>>::new(),
+                &local,
+                self,
+                OwnershipPreference::STRONGLY_PREFER_OWNED,
+                OwnershipPreference::PREFER_OWNED,
+            );
+            z1feedback.connect_with_preference(&trace, OwnershipPreference::STRONGLY_PREFER_OWNED);
+            self.circuit()
+                .cache_insert(DelayedTraceId::new(trace.local_node_id()), local);
+            self.circuit()
+                .cache_insert(ExportId::new(trace.local_node_id()), export);
+            trace
+        })
+    }
+
     // TODO: this method should replace `Stream::integrate()`.
     pub fn integrate_trace(&self) -> Stream<Circuit<P>, Spine<Rc<B>>>
     where
@@ -113,7 +149,9 @@ where
             .cache_get_or_insert_with(IntegrateTraceId::new(self.local_node_id()), || {
                 self.circuit().region("integrate_trace", || {
                     let (ExportStream { local, export }, z1feedback) =
-                        self.circuit().add_feedback_with_export(Z1Trace::new(true));
+                        self.circuit().add_feedback_with_export(
+                            Z1Trace::new(true).with_effort(self.circuit().trace_effort()),
+                        );
                     let trace = self.circuit().add_binary_operator_with_preference(
                         <UntimedTraceAppend<Spine<Rc<B>>, B>>::new(),
                         &local,
@@ -312,10 +350,15 @@ where
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub struct Z1Trace<T: TraceReader> {
     time: T::Time,
     trace: Option<T>,
     reset_on_clock_start: bool,
+    // Garbage collector invoked at the end of each clock epoch.
+    gc: Option<Box<dyn FnMut(&mut T)>>,
+    // Merge effort of the trace; `None` uses the default effort.
+    effort: Option<EffortConfig>,
 }
 
 impl<T> Z1Trace<T>
@@ -327,8 +370,27 @@ where
             time: T::Time::minimum(),
             trace: None,
             reset_on_clock_start,
+            gc: None,
+            effort: None,
         }
     }
+
+    /// Configure the merge effort of the trace (see
+    /// [`Trace::configure_effort`]).  `None` uses the default effort.
+    pub fn with_effort(mut self, effort: Option<EffortConfig>) -> Self {
+        self.effort = effort;
+        self
+    }
+
+    /// Invoke `gc` on the trace at the end of each clock epoch, after
+    /// compacting its timestamps (see [`Trace::recede_to`]).
+    pub fn with_gc<F>(mut self, gc: F) -> Self
+    where
+        F: FnMut(&mut T) + 'static,
+    {
+        self.gc = Some(Box::new(gc));
+        self
+    }
 }
 
 impl<T> Operator for Z1Trace<T>
@@ -340,17 +402,27 @@ where
         Cow::from("Z1 (trace)")
     }
 
+    fn is_stateful(&self) -> bool {
+        true
+    }
+
     fn clock_start(&mut self, scope: Scope) {
         self.time.advance(scope + 1);
         if scope == 0 && self.trace.is_none() {
-            // TODO: use T::with_effort with configurable effort?
-            self.trace = Some(T::new(None));
+            let mut trace = T::new(None);
+            if let Some(effort) = &self.effort {
+                trace.configure_effort(effort);
+            }
+            self.trace = Some(trace);
         }
     }
     fn clock_end(&mut self, scope: Scope) {
         if scope == 0 {
             if let Some(tr) = self.trace.as_mut() {
                 tr.recede_to(&self.time.recede(1));
+                if let Some(gc) = self.gc.as_mut() {
+                    gc(tr);
+                }
             }
         }
     }
@@ -381,6 +453,32 @@ where
             Some(trace) => !trace.dirty(),
         }
     }
+
+    fn exert(&mut self, effort: &mut isize) -> bool {
+        match self.trace.as_mut() {
+            None => false,
+            Some(trace) => {
+                trace.exert(effort);
+
+                // More work remains until all batches have been merged into
+                // one.
+                let mut batches = 0;
+                trace.map_batches(|batch| {
+                    if !batch.is_empty() {
+                        batches += 1
+                    }
+                });
+                batches > 1 || !trace.maintenance_debt().is_empty()
+            }
+        }
+    }
+
+    fn has_pending_work(&self) -> bool {
+        self.trace
+            .as_ref()
+            .map(|trace| trace.dirty() || !trace.maintenance_debt().is_empty())
+            .unwrap_or(false)
+    }
 }
 
 impl<T> StrictOperator<T> for Z1Trace<T>
@@ -395,7 +493,9 @@ where
     }
 
     fn get_final_output(&mut self) -> T {
-        if self.reset_on_clock_start {
+        // The trace is missing if the last step failed before the input half
+        // of the operator was evaluated.
+        if self.reset_on_clock_start && self.trace.is_some() {
             self.get_output()
         } else {
             T::new(None)
@@ -10,7 +10,7 @@ use crate::{
     circuit_cache_key, NumEntries,
 };
 use deepsize::DeepSizeOf;
-use std::{borrow::Cow, fmt::Write, mem::replace};
+use std::{borrow::Cow, cell::RefCell, fmt::Write, mem::replace, rc::Rc};
 
 circuit_cache_key!(DelayedId<C, D>(NodeId => Stream<C, D>));
 circuit_cache_key!(NestedDelayedId<C, D>(NodeId => Stream<C, D>));
@@ -24,6 +24,7 @@ pub struct DelayedFeedback<P, D> {
     feedback: FeedbackConnector<Circuit<P>, D, D, Z1<D>>,
     output: Stream<Circuit<P>, D>,
     export: Stream<P, D>,
+    state: Z1Handle<D>,
 }
 
 impl<P, D> DelayedFeedback<P, D>
@@ -34,13 +35,28 @@ where
     /// Create a feedback loop with `Z1` operator.  Use [`Self::connect`] to
     /// close the loop.
     pub fn new(circuit: &Circuit<P>) -> Self {
-        let (ExportStream { local, export }, feedback) =
-            circuit.add_feedback_with_export(Z1::new(D::zero()));
+        Self::with_initial(circuit, D::zero())
+    }
+
+    /// Create a feedback loop with `Z1` operator that outputs `initial`
+    /// instead of zero at the first timestamp of every clock epoch.
+    ///
+    /// In a nested circuit, this seeds each fixed point computation with
+    /// `initial`, e.g., with the base facts of a recursive query, without
+    /// having to inject them using `delta0` and a sum.  Since `initial` is
+    /// the first value of the feedback stream, it is also included in the
+    /// value exported to the parent circuit if the computation reaches a
+    /// fixed point in the first iteration.
+    pub fn with_initial(circuit: &Circuit<P>, initial: D) -> Self {
+        let z1 = Z1::with_initial(D::zero(), initial);
+        let state = z1.handle();
+        let (ExportStream { local, export }, feedback) = circuit.add_feedback_with_export(z1);
 
         Self {
             feedback,
             output: local,
             export,
+            state,
         }
     }
 
@@ -49,12 +65,18 @@ where
         &self.output
     }
 
+    /// Handle to the state of the `Z1` operator.
+    pub fn state(&self) -> Z1Handle<D> {
+        self.state.clone()
+    }
+
     /// Connect `input` stream to the input of the `Z1` operator.
     pub fn connect(self, input: &Stream<Circuit<P>, D>) {
         let Self {
             feedback,
             output,
             export,
+            ..
         } = self;
         let circuit = output.circuit().clone();
 
@@ -86,7 +108,17 @@ where
     where
         D: HasZero,
     {
-        let (output, feedback) = circuit.add_feedback(Z1Nested::new(D::zero()));
+        Self::with_initial(circuit, D::zero())
+    }
+
+    /// Create a feedback loop with `Z1Nested` operator whose output nested
+    /// stream starts with `initial` instead of zero at the first parent
+    /// timestamp.
+    pub fn with_initial(circuit: &Circuit<P>, initial: D) -> Self
+    where
+        D: HasZero,
+    {
+        let (output, feedback) = circuit.add_feedback(Z1Nested::with_initial(D::zero(), initial));
         Self { feedback, output }
     }
 
@@ -119,6 +151,22 @@ impl<P, D> Stream<Circuit<P>, D> {
             .clone()
     }
 
+    /// Applies a new [`Z1`] operator to `self` and returns a handle to its
+    /// state along with the output stream.
+    ///
+    /// Unlike [`Self::delay`], this method always creates a new operator
+    /// rather than reusing a cached one, so that the handle controls only the
+    /// state of the returned stream.
+    pub fn delay_with_handle(&self) -> (Stream<Circuit<P>, D>, Z1Handle<D>)
+    where
+        P: Clone + 'static,
+        D: Eq + DeepSizeOf + NumEntries + Clone + HasZero + 'static,
+    {
+        let z1 = Z1::new(D::zero());
+        let handle = z1.handle();
+        (self.circuit().add_unary_operator(z1, self), handle)
+    }
+
     /// Applies [`Z1Nested`] operator to `self`.
     pub fn delay_nested(&self) -> Stream<Circuit<P>, D>
     where
@@ -140,7 +188,8 @@ impl<P, D> Stream<Circuit<P>, D> {
 /// after [clock_start](`Z1::clock_start`).  For all subsequent timestamps, it
 /// outputs the value received as input at the previous timestamp.  The zero
 /// value is typically the neutral element of a monoid (e.g., 0 for addition
-/// or 1 for multiplication).
+/// or 1 for multiplication).  Use [`Z1::with_initial`] to output a different
+/// value in the first timestamp.
 ///
 /// It is a strict operator.
 ///
@@ -157,8 +206,9 @@ impl<P, D> Stream<Circuit<P>, D> {
 /// ```
 pub struct Z1<T> {
     zero: T,
+    initial: T,
     empty_output: bool,
-    val: T,
+    val: Rc<RefCell<T>>,
 }
 
 impl<T> Z1<T>
@@ -166,14 +216,64 @@ where
     T: Clone,
 {
     pub fn new(zero: T) -> Self {
+        Self::with_initial(zero.clone(), zero)
+    }
+
+    /// Create a `Z1` operator that outputs `initial` in the first timestamp
+    /// after `clock_start`.
+    pub fn with_initial(zero: T, initial: T) -> Self {
         Self {
-            zero: zero.clone(),
+            zero,
+            initial: initial.clone(),
             empty_output: false,
-            val: zero,
+            val: Rc::new(RefCell::new(initial)),
+        }
+    }
+
+    /// Returns a handle to the state of the operator.  See [`Z1Handle`].
+    pub fn handle(&self) -> Z1Handle<T> {
+        Z1Handle {
+            val: self.val.clone(),
         }
     }
 }
 
+/// Handle to the state of a [`Z1`] operator.
+///
+/// The state of a `Z1` operator between clock cycles is the value it will
+/// output at the next clock cycle, i.e., the last value it received as
+/// input.  The handle can be used to read this value, e.g., to checkpoint
+/// the state of a circuit, and to replace it, e.g., to restore a
+/// checkpoint or to start a test from a known non-empty state.  The handle
+/// must not be used while the circuit is being evaluated.
+pub struct Z1Handle<T> {
+    val: Rc<RefCell<T>>,
+}
+
+impl<T> Clone for Z1Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            val: self.val.clone(),
+        }
+    }
+}
+
+impl<T> Z1Handle<T>
+where
+    T: Clone,
+{
+    /// Current state of the operator.
+    pub fn get(&self) -> T {
+        self.val.borrow().clone()
+    }
+
+    /// Replace the state of the operator with `val`, returning the old
+    /// state.
+    pub fn replace(&self, val: T) -> T {
+        replace(&mut *self.val.borrow_mut(), val)
+    }
+}
+
 impl<T> Operator for Z1<T>
 where
     T: Eq + DeepSizeOf + NumEntries + Clone + 'static,
@@ -182,22 +282,27 @@ where
         Cow::from("Z^-1")
     }
 
+    fn is_stateful(&self) -> bool {
+        true
+    }
+
     fn clock_start(&mut self, _scope: Scope) {}
     fn clock_end(&mut self, _scope: Scope) {
         self.empty_output = false;
-        self.val = self.zero.clone();
+        *self.val.borrow_mut() = self.initial.clone();
     }
 
     fn summary(&self, summary: &mut String) {
-        writeln!(summary, "size: {}", self.val.num_entries_deep()).unwrap();
+        let val = self.val.borrow();
+        writeln!(summary, "size: {}", val.num_entries_deep()).unwrap();
 
-        let bytes = self.val.deep_size_of();
+        let bytes = val.deep_size_of();
         writeln!(summary, "bytes: {}", bytes).unwrap();
         //println!("zbytes:{}", bytes);
     }
 
     fn fixedpoint(&self) -> bool {
-        (self.val.num_entries_shallow() == 0) && self.empty_output
+        (self.val.borrow().num_entries_shallow() == 0) && self.empty_output
         /*if res == false {
             eprintln!("num_entries_shallow: {}", self.val.num_entries_shallow());
         }*/
@@ -209,11 +314,11 @@ where
     T: Eq + DeepSizeOf + NumEntries + Clone + 'static,
 {
     fn eval(&mut self, i: &T) -> T {
-        replace(&mut self.val, i.clone())
+        replace(&mut *self.val.borrow_mut(), i.clone())
     }
 
     fn eval_owned(&mut self, i: T) -> T {
-        replace(&mut self.val, i)
+        replace(&mut *self.val.borrow_mut(), i)
     }
 
     fn input_preference(&self) -> OwnershipPreference {
@@ -226,8 +331,9 @@ where
     T: Eq + DeepSizeOf + NumEntries + Clone + 'static,
 {
     fn get_output(&mut self) -> T {
-        self.empty_output = self.val.num_entries_shallow() == 0;
-        replace(&mut self.val, self.zero.clone())
+        let mut val = self.val.borrow_mut();
+        self.empty_output = val.num_entries_shallow() == 0;
+        replace(&mut *val, self.zero.clone())
     }
 
     fn get_final_output(&mut self) -> T {
@@ -240,11 +346,11 @@ where
     T: Eq + DeepSizeOf + NumEntries + Clone + 'static,
 {
     fn eval_strict(&mut self, i: &T) {
-        self.val = i.clone();
+        *self.val.borrow_mut() = i.clone();
     }
 
     fn eval_strict_owned(&mut self, i: T) {
-        self.val = i;
+        *self.val.borrow_mut() = i;
     }
 
     fn input_preference(&self) -> OwnershipPreference {
@@ -256,7 +362,9 @@ where
 ///
 /// The operator stores a complete nested stream consumed at the last iteration
 /// of the parent clock and outputs it at the next parent clock cycle.
-/// It outputs a stream of zeros in the first parent clock tick.
+/// It outputs a stream of zeros in the first parent clock tick, unless
+/// created with [`Z1Nested::with_initial`], in which case the stream starts
+/// with the initial value.
 ///
 /// One important subtlety is that mathematically speaking nested streams are
 /// infinite, but we can only compute and store finite prefixes of such
@@ -288,19 +396,41 @@ where
 /// ```
 pub struct Z1Nested<T> {
     zero: T,
+    initial: T,
     timestamp: usize,
     val: Vec<T>,
 }
 
-impl<T> Z1Nested<T> {
-    const fn new(zero: T) -> Self {
+impl<T> Z1Nested<T>
+where
+    T: Clone,
+{
+    fn new(zero: T) -> Self {
+        Self::with_initial(zero.clone(), zero)
+    }
+
+    /// Create a `Z1Nested` operator whose output stream at the first parent
+    /// timestamp starts with `initial`.
+    pub fn with_initial(zero: T, initial: T) -> Self {
         Self {
             zero,
+            initial,
             timestamp: 0,
             val: vec![],
         }
     }
 
+    // Value to output at the current timestamp if no input was stored for
+    // it: `initial` at the start of the first parent timestamp and zero
+    // otherwise.
+    fn default_output(&self) -> T {
+        if self.val.is_empty() {
+            self.initial.clone()
+        } else {
+            self.zero.clone()
+        }
+    }
+
     fn reset(&mut self) {
         self.timestamp = 0;
         self.val = vec![];
@@ -315,6 +445,10 @@ where
         Cow::from("Z^-1 (nested)")
     }
 
+    fn is_stateful(&self) -> bool {
+        true
+    }
+
     fn clock_start(&mut self, scope: Scope) {
         if scope == 0 {
             self.val.truncate(self.timestamp);
@@ -375,7 +509,7 @@ where
         debug_assert!(self.timestamp <= self.val.len());
 
         if self.timestamp == self.val.len() {
-            self.val.push(self.zero.clone());
+            self.val.push(self.default_output());
         } else if self.timestamp == self.val.len() - 1 {
             self.val.push(self.val.last().unwrap().clone());
         }
@@ -390,7 +524,7 @@ where
         debug_assert!(self.timestamp <= self.val.len());
 
         if self.timestamp == self.val.len() {
-            self.val.push(self.zero.clone());
+            self.val.push(self.default_output());
         } else if self.timestamp == self.val.len() - 1 {
             self.val.push(self.val.last().unwrap().clone());
         }
@@ -413,7 +547,7 @@ where
     fn get_output(&mut self) -> T {
         if self.timestamp >= self.val.len() {
             assert_eq!(self.timestamp, self.val.len());
-            self.val.push(self.zero.clone());
+            self.val.push(self.default_output());
         } else if self.timestamp == self.val.len() - 1 {
             self.val.push(self.val.last().unwrap().clone())
         }
@@ -456,8 +590,11 @@ where
 #[cfg(test)]
 mod test {
     use crate::{
-        circuit::operator_traits::{Operator, StrictOperator, StrictUnaryOperator, UnaryOperator},
-        operator::{Z1Nested, Z1},
+        circuit::{
+            operator_traits::{Operator, StrictOperator, StrictUnaryOperator, UnaryOperator},
+            Root,
+        },
+        operator::{DelayedFeedback, Z1Nested, Z1},
     };
 
     #[test]
@@ -581,4 +718,85 @@ mod test {
 
         z1.clock_end(1);
     }
+
+    #[test]
+    fn z1_initial_test() {
+        let mut z1 = Z1::with_initial(0, 10);
+
+        let mut res = Vec::new();
+        for _ in 0..2 {
+            z1.clock_start(0);
+            res.push(z1.get_output());
+            z1.eval_strict(&1);
+            res.push(z1.get_output());
+            z1.eval_strict(&2);
+            z1.clock_end(0);
+        }
+        assert_eq!(res, vec![10, 1, 10, 1]);
+
+        let mut z1 = Z1Nested::with_initial(0, 10);
+
+        z1.clock_start(1);
+
+        let mut res = Vec::new();
+        z1.clock_start(0);
+        res.push(z1.eval(&1));
+        res.push(z1.eval(&2));
+        z1.clock_end(0);
+        assert_eq!(res.as_slice(), &[10, 0]);
+
+        let mut res = Vec::new();
+        z1.clock_start(0);
+        res.push(z1.eval(&3));
+        res.push(z1.eval(&4));
+        z1.clock_end(0);
+        assert_eq!(res.as_slice(), &[1, 2]);
+
+        z1.clock_end(1);
+    }
+
+    #[test]
+    fn delayed_feedback_initial_test() {
+        let root = Root::build(|circuit| {
+            let feedback = DelayedFeedback::with_initial(circuit, 10usize);
+            let mut expected = 10;
+            feedback.stream().inspect(move |n| {
+                assert_eq!(*n, expected);
+                expected += 1;
+            });
+            let next = feedback.stream().apply(|n| n + 1);
+            feedback.connect(&next);
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            root.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn z1_handle_test() {
+        let mut handles = None;
+        let root = Root::build(|circuit| {
+            let feedback = DelayedFeedback::new(circuit);
+            let next = feedback.stream().apply(|n: &usize| n + 1);
+            let (delayed, delayed_handle) = next.delay_with_handle();
+            handles = Some((feedback.state(), delayed_handle));
+            feedback.connect(&next);
+            delayed.inspect(|_| {});
+        })
+        .unwrap();
+        let (state, delayed) = handles.unwrap();
+
+        root.step().unwrap();
+        root.step().unwrap();
+        assert_eq!(state.get(), 2);
+        assert_eq!(delayed.get(), 2);
+
+        // Restart the counter from a checkpointed value.
+        assert_eq!(state.replace(100), 2);
+        root.step().unwrap();
+        assert_eq!(state.get(), 101);
+        assert_eq!(delayed.get(), 101);
+    }
 }
@@ -0,0 +1,210 @@
+//! Ordering values by a projection.
+//!
+//! Arrangements, such as the batches and traces in [`trace`](`crate::trace`),
+//! keep their keys and values sorted according to their [`Ord`]
+//! implementation.  Some operators need a different order, e.g., values
+//! ordered by timestamp in descending order.  Instead of defining a newtype
+//! and implementing `Ord`, `Clone`, `DeepSizeOf`, etc. by hand, wrap values in
+//! [`OrdBy`], parameterized by a [`Projection`] that extracts the sort key.
+//! Projections are defined with the [`projection!`](`crate::projection`)
+//! macro.
+
+use deepsize::{Context, DeepSizeOf};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Debug, Display},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::Deref,
+};
+
+/// Projection of values of type `T` onto keys that determine their order in
+/// [`OrdBy`].
+pub trait Projection<T> {
+    /// Sort key.
+    type Key: Ord;
+
+    /// Extract the sort key from `value`.
+    fn project(value: &T) -> Self::Key;
+}
+
+/// Define a [`Projection`].
+///
+/// The macro declares a unit struct that implements [`Projection`] for the
+/// specified type:
+///
+/// ```
+/// use dbsp::{projection, OrdBy};
+/// use std::cmp::Reverse;
+///
+/// #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// struct Event {
+///     id: u64,
+///     timestamp: u64,
+/// }
+///
+/// projection! {
+///     /// Orders events by timestamp, latest first.
+///     pub ByTimestampDesc(event: Event) -> Reverse<u64> { Reverse(event.timestamp) }
+/// }
+///
+/// let early = OrdBy::<_, ByTimestampDesc>::new(Event { id: 1, timestamp: 10 });
+/// let late = OrdBy::<_, ByTimestampDesc>::new(Event { id: 0, timestamp: 20 });
+/// assert!(late < early);
+/// ```
+#[macro_export]
+macro_rules! projection {
+    ($(#[$meta:meta])* $vis:vis $name:ident($value:ident: $ty:ty) -> $key:ty $body:block) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        $vis struct $name;
+
+        impl $crate::Projection<$ty> for $name {
+            type Key = $key;
+
+            fn project($value: &$ty) -> $key $body
+        }
+    };
+}
+
+/// A value of type `T` ordered by projection `F`.
+///
+/// Values are compared by their projections first.  Values with equal
+/// projections are ordered by their own [`Ord`] implementation, so that the
+/// order is total and consistent with [`Eq`], as required by arrangements.
+/// All other traits, including `Eq`, `Hash`, and `DeepSizeOf`, are forwarded
+/// to the wrapped value.
+pub struct OrdBy<T, F> {
+    value: T,
+    _projection: PhantomData<F>,
+}
+
+impl<T, F> OrdBy<T, F> {
+    /// Wrap `value`.
+    pub const fn new(value: T) -> Self {
+        Self {
+            value,
+            _projection: PhantomData,
+        }
+    }
+
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T, F> Deref for OrdBy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, F> From<T> for OrdBy<T, F> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: Clone, F> Clone for OrdBy<T, F> {
+    fn clone(&self) -> Self {
+        Self::new(self.value.clone())
+    }
+}
+
+impl<T: Copy, F> Copy for OrdBy<T, F> {}
+
+impl<T: Default, F> Default for OrdBy<T, F> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Debug, F> Debug for OrdBy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<T: Display, F> Display for OrdBy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<T: PartialEq, F> PartialEq for OrdBy<T, F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Eq, F> Eq for OrdBy<T, F> {}
+
+impl<T, F> PartialOrd for OrdBy<T, F>
+where
+    T: Ord,
+    F: Projection<T>,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, F> Ord for OrdBy<T, F>
+where
+    T: Ord,
+    F: Projection<T>,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        F::project(&self.value)
+            .cmp(&F::project(&other.value))
+            .then_with(|| self.value.cmp(&other.value))
+    }
+}
+
+impl<T: Hash, F> Hash for OrdBy<T, F> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+impl<T: DeepSizeOf, F> DeepSizeOf for OrdBy<T, F> {
+    fn deep_size_of_children(&self, context: &mut Context) -> usize {
+        self.value.deep_size_of_children(context)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        trace::{cursor::Cursor, ord::OrdZSet, Batch, BatchReader},
+        OrdBy,
+    };
+    use std::cmp::Reverse;
+
+    projection! {
+        ByTimestampDesc(event: (u64, &'static str)) -> Reverse<u64> { Reverse(event.0) }
+    }
+
+    #[test]
+    fn ord_by_test() {
+        let events = [(10, "a"), (30, "b"), (20, "c"), (30, "a")];
+        let batch = OrdZSet::<OrdBy<_, ByTimestampDesc>, isize>::from_tuples(
+            (),
+            events
+                .iter()
+                .map(|event| ((OrdBy::new(*event), ()), 1))
+                .collect(),
+        );
+
+        let mut keys = Vec::new();
+        let mut cursor = batch.cursor();
+        while cursor.key_valid(&batch) {
+            keys.push(**cursor.key(&batch));
+            cursor.step_key(&batch);
+        }
+        assert_eq!(keys, vec![(30, "a"), (30, "b"), (20, "c"), (10, "a")]);
+    }
+}
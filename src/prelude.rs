@@ -0,0 +1,89 @@
+//! Commonly used types, traits, and macros.
+//!
+//! The prelude exports everything needed to build and run typical circuits:
+//! circuits and streams, the runtime, batch and trace types, input and
+//! parameter handles, feedback operators, and the `zset!` family of macros.
+//! Stream operators are methods of [`Stream`], so they do not need to be
+//! imported separately.
+//!
+//! ```
+//! use dbsp::prelude::*;
+//!
+//! let root = Root::build(|circuit| {
+//!     let (input, handle) = circuit.add_input::<OrdZSet<u64, isize>>();
+//!     input
+//!         .integrate()
+//!         .inspect(|zs: &OrdZSet<u64, isize>| println!("{} tuples", zs.len()));
+//!     handle.push(1, (), 1);
+//! })
+//! .unwrap();
+//!
+//! root.step().unwrap();
+//! ```
+//!
+//! # Memory accounting
+//!
+//! Keys and values stored in batches and traces must implement
+//! [`DeepSizeOf`], which reports the memory used by operator state, e.g., for
+//! [state budgets](`crate::operator::StateBudget`).  The prelude re-exports
+//! the trait along with its derive macro.  Since the derived code refers to
+//! the `deepsize` crate by name, crates that derive `DeepSizeOf` must also
+//! add `deepsize = "0.2"` to their dependencies:
+//!
+//! ```
+//! use dbsp::{prelude::*, trace::ord::OrdZSet, zset};
+//!
+//! #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, DeepSizeOf)]
+//! struct Edge {
+//!     from: u64,
+//!     to: u64,
+//! }
+//!
+//! let edges: OrdZSet<Edge, isize> = zset! { Edge { from: 0, to: 1 } => 1 };
+//! ```
+//!
+//! Types without heap allocations can implement the trait with
+//! [`known_deep_size`]:
+//!
+//! ```
+//! use dbsp::prelude::*;
+//!
+//! #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+//! struct Point(i32, i32);
+//!
+//! known_deep_size!(0; Point);
+//! ```
+//!
+//! `DeepSizeOf` is implemented for primitive types, strings, and standard
+//! collections.  Implementations for common third-party types are enabled by
+//! the following crate features:
+//!
+//! * `with-arrayvec` - `arrayvec::ArrayVec` and `ArrayString`.
+//! * `with-chrono` - `chrono` date and time types.
+//! * `with-indexmap` - `indexmap::IndexMap` and `IndexSet`.
+//! * `with-smallvec` - `smallvec::SmallVec`.
+//!
+//! Rust's orphan rules do not allow implementing `DeepSizeOf` for types
+//! defined in other crates, e.g., `uuid::Uuid`, outside of the crates that
+//! define these types.  Wrap such types in a newtype that implements
+//! `DeepSizeOf`, e.g., with `known_deep_size!(0; MyUuid)`.
+
+pub use crate::{
+    algebra::{HasOne, HasZero, IndexedZSet, MonoidValue, ZRingValue, ZSet},
+    circuit::{
+        schedule::Error as SchedulerError, Circuit, ExportStream, Root, Runtime, RuntimeHandle,
+        Stream,
+    },
+    indexed_zset,
+    operator::{
+        BackfillHandle, Condition, DelayedFeedback, DelayedNestedFeedback, Generator,
+        GeneratorNested, InputHandle, ParamHandle, ParamStream, StateBudget, UdfHandle, Z1Handle,
+    },
+    projection,
+    trace::{
+        ord::{OrdIndexedZSet, OrdIndexedZSetSpine, OrdZSet, OrdZSetSpine},
+        Batch, BatchReader, Cursor, Trace, TraceReader,
+    },
+    zset, OrdBy, Projection,
+};
+pub use deepsize::{known_deep_size, DeepSizeOf};
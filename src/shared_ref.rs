@@ -1,6 +1,6 @@
 //! Shared reference trait.
 
-use std::{borrow::Borrow, rc::Rc};
+use std::{borrow::Borrow, rc::Rc, sync::Arc};
 
 /// A trait that generalizes shared pointers like `Rc` and `Arc`.
 ///
@@ -87,3 +87,11 @@ impl<T> SharedRef for Rc<T> {
         Rc::try_unwrap(self)
     }
 }
+
+impl<T> SharedRef for Arc<T> {
+    type Target = T;
+
+    fn try_into_owned(self) -> Result<Self::Target, Self> {
+        Arc::try_unwrap(self)
+    }
+}
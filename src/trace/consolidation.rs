@@ -6,12 +6,29 @@
 //! supply that functionality.
 
 use crate::algebra::{AddAssignByRef, HasZero, MonoidValue};
+#[cfg(feature = "with-rayon")]
+use rayon::{iter::ParallelIterator, slice::ParallelSliceMut};
+#[cfg(feature = "with-rayon")]
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+};
+
+/// Minimal slice length for which the `par_consolidate*` functions sort the
+/// slice in parallel.  Smaller slices are sorted sequentially, as the cost of
+/// distributing work across threads outweighs the speedup.
+#[cfg(feature = "with-rayon")]
+pub const PARALLEL_CONSOLIDATION_THRESHOLD: usize = 1 << 16;
 
 /// Sorts and consolidates `vec`.
 ///
 /// This method will sort `vec` and then consolidate runs of more than one entry
 /// with identical first elements by accumulating the second elements of the
 /// pairs. Should the final accumulation be zero, the element is discarded.
+///
+/// More generally, elements whose accumulated weight is negligible according
+/// to [`HasZero::is_negligible`] are discarded, which allows inexact weight
+/// types to drop rounding residues.
 pub fn consolidate<T: Ord, R: MonoidValue>(vec: &mut Vec<(T, R)>) {
     consolidate_from(vec, 0);
 }
@@ -33,7 +50,11 @@ pub fn consolidate_slice<T: Ord, R: AddAssignByRef + HasZero>(slice: &mut [(T, R
     // consolidated runs. In a world where there are not many results, we may
     // never even need to call in to merge sort.
     slice.sort_by(|x, y| x.0.cmp(&y.0));
+    consolidate_sorted_slice(slice)
+}
 
+/// Consolidates a sorted slice, returning the valid prefix length.
+fn consolidate_sorted_slice<T: Ord, R: AddAssignByRef + HasZero>(slice: &mut [(T, R)]) -> usize {
     // Counts the number of distinct known-non-zero accumulations. Indexes the write
     // location.
     let mut offset = 0;
@@ -57,7 +78,7 @@ pub fn consolidate_slice<T: Ord, R: AddAssignByRef + HasZero>(slice: &mut [(T, R
             if (*ptr1).0 == (*ptr2).0 {
                 (*ptr1).1.add_assign_by_ref(&(*ptr2).1);
             } else {
-                if !(*ptr1).1.is_zero() {
+                if !(*ptr1).1.is_negligible() {
                     offset += 1;
                 }
                 let ptr1 = slice.as_mut_ptr().add(offset);
@@ -65,13 +86,148 @@ pub fn consolidate_slice<T: Ord, R: AddAssignByRef + HasZero>(slice: &mut [(T, R
             }
         }
     }
-    if offset < slice.len() && !slice[offset].1.is_zero() {
+    if offset < slice.len() && !slice[offset].1.is_negligible() {
         offset += 1;
     }
 
     offset
 }
 
+/// Parallel version of [`consolidate`].
+///
+/// Sorts `vec` using all threads in the rayon thread pool if it contains at
+/// least [`PARALLEL_CONSOLIDATION_THRESHOLD`] elements; otherwise behaves like
+/// [`consolidate`].
+#[cfg(feature = "with-rayon")]
+pub fn par_consolidate<T, R>(vec: &mut Vec<(T, R)>)
+where
+    T: Ord + Send,
+    R: MonoidValue + Send,
+{
+    let length = par_consolidate_slice(vec);
+    vec.truncate(length);
+}
+
+/// Parallel version of [`consolidate_slice`].
+///
+/// Sorts `slice` using all threads in the rayon thread pool if it contains at
+/// least [`PARALLEL_CONSOLIDATION_THRESHOLD`] elements; otherwise behaves like
+/// [`consolidate_slice`].
+#[cfg(feature = "with-rayon")]
+pub fn par_consolidate_slice<T, R>(slice: &mut [(T, R)]) -> usize
+where
+    T: Ord + Send,
+    R: AddAssignByRef + HasZero + Send,
+{
+    if slice.len() < PARALLEL_CONSOLIDATION_THRESHOLD {
+        return consolidate_slice(slice);
+    }
+
+    // The sort dominates the cost of consolidation; the linear scan that
+    // accumulates weights remains sequential.
+    slice.par_sort_by(|x, y| x.0.cmp(&y.0));
+    consolidate_sorted_slice(slice)
+}
+
+/// Sorts and consolidates `vec` by splitting it into runs.
+///
+/// Splits `vec` into one chunk per thread in the rayon thread pool, sorts and
+/// consolidates the chunks in parallel, and k-way merges the resulting sorted
+/// runs, accumulating the weights of identical elements from different runs.
+/// Unlike [`par_consolidate`], which only parallelizes the sort, this also
+/// parallelizes the bulk of the consolidation work.  Vectors with fewer than
+/// [`PARALLEL_CONSOLIDATION_THRESHOLD`] elements are consolidated
+/// sequentially.
+#[cfg(feature = "with-rayon")]
+pub fn par_consolidate_runs<T, R>(mut vec: Vec<(T, R)>) -> Vec<(T, R)>
+where
+    T: Ord + Send,
+    R: AddAssignByRef + HasZero + Send,
+{
+    if vec.len() < PARALLEL_CONSOLIDATION_THRESHOLD {
+        let length = consolidate_slice(&mut vec);
+        vec.truncate(length);
+        return vec;
+    }
+
+    let threads = rayon::current_num_threads().max(1);
+    let chunk_size = vec.len().div_ceil(threads);
+    let lengths: Vec<usize> = vec
+        .par_chunks_mut(chunk_size)
+        .map(consolidate_slice)
+        .collect();
+
+    // Split the vector into consolidated runs, starting from the last chunk so
+    // that each element is moved at most once.
+    let mut runs = Vec::with_capacity(lengths.len());
+    for (index, length) in lengths.into_iter().enumerate().rev() {
+        let mut run = vec.split_off(index * chunk_size);
+        run.truncate(length);
+        runs.push(run.into_iter());
+    }
+
+    let capacity = runs.iter().map(|run| run.len()).sum();
+    let mut result: Vec<(T, R)> = Vec::with_capacity(capacity);
+    let mut heap = BinaryHeap::with_capacity(runs.len());
+    for (run, iter) in runs.iter_mut().enumerate() {
+        if let Some((key, weight)) = iter.next() {
+            heap.push(Reverse(RunHead { key, weight, run }));
+        }
+    }
+
+    while let Some(Reverse(RunHead { key, weight, run })) = heap.pop() {
+        if let Some((key, weight)) = runs[run].next() {
+            heap.push(Reverse(RunHead { key, weight, run }));
+        }
+        match result.last_mut() {
+            Some((last, acc)) if *last == key => acc.add_assign_by_ref(&weight),
+            _ => {
+                if matches!(result.last(), Some((_, acc)) if acc.is_negligible()) {
+                    result.pop();
+                }
+                result.push((key, weight));
+            }
+        }
+    }
+    if matches!(result.last(), Some((_, acc)) if acc.is_negligible()) {
+        result.pop();
+    }
+
+    result
+}
+
+// The smallest remaining element of a sorted run, ordered by key only.
+#[cfg(feature = "with-rayon")]
+struct RunHead<T, R> {
+    key: T,
+    weight: R,
+    run: usize,
+}
+
+#[cfg(feature = "with-rayon")]
+impl<T: Ord, R> PartialEq for RunHead<T, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+#[cfg(feature = "with-rayon")]
+impl<T: Ord, R> Eq for RunHead<T, R> {}
+
+#[cfg(feature = "with-rayon")]
+impl<T: Ord, R> PartialOrd for RunHead<T, R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "with-rayon")]
+impl<T: Ord, R> Ord for RunHead<T, R> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
 /// Sorts and consolidates `vec`.
 ///
 /// This method will sort `vec` and then consolidate runs of more than one entry
@@ -120,7 +276,7 @@ pub fn consolidate_updates_slice<D: Ord, R: MonoidValue>(slice: &mut [(D, R)]) -
             if (*ptr1).0 == (*ptr2).0 {
                 (*ptr1).1.add_assign_by_ref(&(*ptr2).1);
             } else {
-                if !(*ptr1).1.is_zero() {
+                if !(*ptr1).1.is_negligible() {
                     offset += 1;
                 }
                 let ptr1 = slice.as_mut_ptr().add(offset);
@@ -128,7 +284,7 @@ pub fn consolidate_updates_slice<D: Ord, R: MonoidValue>(slice: &mut [(D, R)]) -
             }
         }
     }
-    if offset < slice.len() && !slice[offset].1.is_zero() {
+    if offset < slice.len() && !slice[offset].1.is_negligible() {
         offset += 1;
     }
 
@@ -155,6 +311,44 @@ mod tests {
         }
     }
 
+    // Floating-point weight that treats values within `1e-9` of zero as zero.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Approx(f64);
+
+    impl std::ops::AddAssign<&Approx> for Approx {
+        fn add_assign(&mut self, other: &Approx) {
+            self.0 += other.0;
+        }
+    }
+
+    impl HasZero for Approx {
+        fn is_zero(&self) -> bool {
+            self.0 == 0.0
+        }
+        fn zero() -> Self {
+            Approx(0.0)
+        }
+        fn is_negligible(&self) -> bool {
+            self.0.abs() < 1e-9
+        }
+    }
+
+    #[test]
+    fn test_consolidate_negligible() {
+        let mut input = vec![
+            ("a", Approx(0.1)),
+            ("b", Approx(1.0)),
+            ("a", Approx(0.2)),
+            ("a", Approx(-0.3)),
+            ("c", Approx(1e-12)),
+        ];
+        // 0.1 + 0.2 - 0.3 is not exactly zero in floating point.
+        assert!(!Approx(0.1 + 0.2 - 0.3).is_zero());
+
+        let length = consolidate_slice(&mut input);
+        assert_eq!(&input[..length], &[("b", Approx(1.0))]);
+    }
+
     #[test]
     fn test_consolidate_updates() {
         let test_cases = vec![
@@ -170,4 +364,51 @@ mod tests {
             assert_eq!(input, output);
         }
     }
+
+    #[cfg(feature = "with-rayon")]
+    #[test]
+    fn test_par_consolidate() {
+        let n = PARALLEL_CONSOLIDATION_THRESHOLD * 2;
+        let mut input: Vec<(usize, isize)> = (0..n)
+            .map(|i| ((i * 7919) % (n / 4), if i % 3 == 0 { -1 } else { 1 }))
+            .collect();
+        let mut expected = input.clone();
+
+        par_consolidate(&mut input);
+        consolidate(&mut expected);
+        assert_eq!(input, expected);
+    }
+
+    #[cfg(feature = "with-rayon")]
+    #[test]
+    fn test_par_leaf_builder() {
+        use crate::trace::layers::{ordered_leaf::UnorderedLeafBuilder, Builder, TupleBuilder};
+
+        let n = PARALLEL_CONSOLIDATION_THRESHOLD * 2;
+        let input: Vec<(usize, isize)> = (0..n)
+            .map(|i| ((i * 7919) % (n / 4), if i % 3 == 0 { -1 } else { 1 }))
+            .collect();
+
+        let mut expected = input.clone();
+        consolidate(&mut expected);
+
+        let mut builder = UnorderedLeafBuilder::with_capacity(n);
+        for tuple in input {
+            builder.push_tuple(tuple);
+        }
+        assert_eq!(builder.done().vals, expected);
+    }
+
+    #[cfg(feature = "with-rayon")]
+    #[test]
+    fn test_par_consolidate_runs() {
+        let n = PARALLEL_CONSOLIDATION_THRESHOLD * 2;
+        let input: Vec<(usize, isize)> = (0..n)
+            .map(|i| ((i * 7919) % (n / 4), if i % 3 == 0 { -1 } else { 1 }))
+            .collect();
+        let mut expected = input.clone();
+
+        consolidate(&mut expected);
+        assert_eq!(par_consolidate_runs(input), expected);
+    }
 }
@@ -11,6 +11,11 @@ use std::marker::PhantomData;
 /// The `CursorList` tracks the indices of cursors with the minimum key, and the
 /// the indices of cursors with the minimum key and minimum value. It performs
 /// no clever management of these sets otherwise.
+///
+/// The storage of the `i`th cursor in the list is obtained from a
+/// [`CursorStorage`] implementation, which allows the list to navigate
+/// batches owned by some other data structure (e.g., a trace) without
+/// copying them into a flat array.
 #[derive(Debug)]
 pub struct CursorList<K, V, T, R, C: Cursor<K, V, T, R>> {
     _phantom: PhantomData<(K, V, T, R)>,
@@ -19,13 +24,30 @@ pub struct CursorList<K, V, T, R, C: Cursor<K, V, T, R>> {
     min_val: Vec<usize>,
 }
 
+/// Provides access to the storage of each cursor in a [`CursorList`].
+pub trait CursorStorage<'s, S> {
+    /// Returns the storage of the `index`th cursor in the list.
+    fn storage(&self, index: usize) -> &'s S;
+}
+
+impl<'s, S> CursorStorage<'s, S> for &'s [S] {
+    #[inline]
+    fn storage(&self, index: usize) -> &'s S {
+        &self[index]
+    }
+}
+
 impl<K, V, T, R, C: Cursor<K, V, T, R>> CursorList<K, V, T, R, C>
 where
     K: Ord,
     V: Ord,
 {
     /// Creates a new cursor list from pre-existing cursors.
-    pub fn new(cursors: Vec<C>, storage: &[C::Storage]) -> Self {
+    pub fn new<'s, S>(cursors: Vec<C>, storage: &S) -> Self
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+    {
         let mut result = CursorList {
             _phantom: PhantomData,
             cursors,
@@ -46,13 +68,17 @@ where
     //
     // Once finished, it invokes `minimize_vals()` to ensure the value cursor is
     // in a consistent state as well.
-    fn minimize_keys(&mut self, storage: &[C::Storage]) {
+    fn minimize_keys<'s, S>(&mut self, storage: &S)
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+    {
         self.min_key.clear();
 
         // Determine the index of the cursor with minimum key.
         let mut min_key_opt: Option<&K> = None;
         for (index, cursor) in self.cursors.iter().enumerate() {
-            let key = cursor.get_key(&storage[index]);
+            let key = cursor.get_key(storage.storage(index));
             if key.is_some() {
                 if min_key_opt.is_none() || key.lt(&min_key_opt) {
                     min_key_opt = key;
@@ -74,13 +100,17 @@ where
     // the indices of cursors whose value equals the minimum valid value seen so
     // far. As it goes, if it observes an improved value it clears the current
     // list, updates the minimum value, and continues.
-    fn minimize_vals(&mut self, storage: &[C::Storage]) {
+    fn minimize_vals<'s, S>(&mut self, storage: &S)
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+    {
         self.min_val.clear();
 
         // Determine the index of the cursor with minimum value.
         let mut min_val: Option<&V> = None;
         for &index in self.min_key.iter() {
-            let val = self.cursors[index].get_val(&storage[index]);
+            let val = self.cursors[index].get_val(storage.storage(index));
             if val.is_some() {
                 if min_val.is_none() || val.lt(&min_val) {
                     min_val = val;
@@ -92,6 +122,166 @@ where
             }
         }
     }
+
+    /// Returns `true` if the list points to a valid key.
+    #[inline]
+    pub fn key_valid_in(&self) -> bool {
+        !self.min_key.is_empty()
+    }
+
+    /// Returns `true` if the list points to a valid value.
+    #[inline]
+    pub fn val_valid_in(&self) -> bool {
+        !self.min_val.is_empty()
+    }
+
+    /// Like [`Cursor::key`], but with storage provided by `storage`.
+    #[inline]
+    pub fn key_in<'s, S>(&self, storage: &S) -> &'s K
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+    {
+        debug_assert!(self.key_valid_in());
+        let index = self.min_key[0];
+        debug_assert!(self.cursors[index].key_valid(storage.storage(index)));
+        self.cursors[index].key(storage.storage(index))
+    }
+
+    /// Like [`Cursor::val`], but with storage provided by `storage`.
+    #[inline]
+    pub fn val_in<'s, S>(&self, storage: &S) -> &'s V
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+    {
+        debug_assert!(self.key_valid_in());
+        debug_assert!(self.val_valid_in());
+        let index = self.min_val[0];
+        debug_assert!(self.cursors[index].val_valid(storage.storage(index)));
+        self.cursors[index].val(storage.storage(index))
+    }
+
+    /// Like [`Cursor::map_times`], but with storage provided by `storage`.
+    #[inline]
+    pub fn map_times_in<'s, S, L>(&mut self, storage: &S, mut logic: L)
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+        L: FnMut(&T, &R),
+    {
+        for &index in self.min_val.iter() {
+            self.cursors[index].map_times(storage.storage(index), |t, d| logic(t, d));
+        }
+    }
+
+    /// Like [`Cursor::weight`], but with storage provided by `storage`.
+    #[inline]
+    pub fn weight_in<'s, S>(&mut self, storage: &S) -> R
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+        R: MonoidValue,
+    {
+        debug_assert!(self.key_valid_in());
+        debug_assert!(self.val_valid_in());
+        let mut res: R = HasZero::zero();
+        self.map_times_in(storage, |_, w| res.add_assign_by_ref(w));
+        res
+    }
+
+    /// Like [`Cursor::step_key`], but with storage provided by `storage`.
+    #[inline]
+    pub fn step_key_in<'s, S>(&mut self, storage: &S)
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+    {
+        for &index in self.min_key.iter() {
+            self.cursors[index].step_key(storage.storage(index));
+        }
+        self.minimize_keys(storage);
+    }
+
+    /// Like [`Cursor::seek_key`], but with storage provided by `storage`.
+    #[inline]
+    pub fn seek_key_in<'s, S>(&mut self, storage: &S, key: &K)
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+    {
+        for (index, cursor) in self.cursors.iter_mut().enumerate() {
+            cursor.seek_key(storage.storage(index), key);
+        }
+        self.minimize_keys(storage);
+    }
+
+    /// Like [`Cursor::seek_key_with`], but with storage provided by
+    /// `storage`.
+    #[inline]
+    pub fn seek_key_with_in<'s, S, P>(&mut self, storage: &S, predicate: P)
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+        P: Fn(&K) -> bool,
+    {
+        for (index, cursor) in self.cursors.iter_mut().enumerate() {
+            cursor.seek_key_with(storage.storage(index), &predicate);
+        }
+        self.minimize_keys(storage);
+    }
+
+    /// Like [`Cursor::step_val`], but with storage provided by `storage`.
+    #[inline]
+    pub fn step_val_in<'s, S>(&mut self, storage: &S)
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+    {
+        for &index in self.min_val.iter() {
+            self.cursors[index].step_val(storage.storage(index));
+        }
+        self.minimize_vals(storage);
+    }
+
+    /// Like [`Cursor::seek_val`], but with storage provided by `storage`.
+    #[inline]
+    pub fn seek_val_in<'s, S>(&mut self, storage: &S, val: &V)
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+    {
+        for &index in self.min_key.iter() {
+            self.cursors[index].seek_val(storage.storage(index), val);
+        }
+        self.minimize_vals(storage);
+    }
+
+    /// Like [`Cursor::rewind_keys`], but with storage provided by `storage`.
+    #[inline]
+    pub fn rewind_keys_in<'s, S>(&mut self, storage: &S)
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+    {
+        for (index, cursor) in self.cursors.iter_mut().enumerate() {
+            cursor.rewind_keys(storage.storage(index));
+        }
+        self.minimize_keys(storage);
+    }
+
+    /// Like [`Cursor::rewind_vals`], but with storage provided by `storage`.
+    #[inline]
+    pub fn rewind_vals_in<'s, S>(&mut self, storage: &S)
+    where
+        S: CursorStorage<'s, C::Storage>,
+        C::Storage: 's,
+    {
+        for &index in self.min_key.iter() {
+            self.cursors[index].rewind_vals(storage.storage(index));
+        }
+        self.minimize_vals(storage);
+    }
 }
 
 impl<K, V, T, R, C: Cursor<K, V, T, R>> Cursor<K, V, T, R> for CursorList<K, V, T, R, C>
@@ -105,32 +295,25 @@ where
     // validation methods
     #[inline]
     fn key_valid(&self, _storage: &Self::Storage) -> bool {
-        !self.min_key.is_empty()
+        self.key_valid_in()
     }
     #[inline]
     fn val_valid(&self, _storage: &Self::Storage) -> bool {
-        !self.min_val.is_empty()
+        self.val_valid_in()
     }
 
     // accessors
     #[inline]
     fn key<'a>(&self, storage: &'a Self::Storage) -> &'a K {
-        debug_assert!(self.key_valid(storage));
-        debug_assert!(self.cursors[self.min_key[0]].key_valid(&storage[self.min_key[0]]));
-        self.cursors[self.min_key[0]].key(&storage[self.min_key[0]])
+        self.key_in(&storage.as_slice())
     }
     #[inline]
     fn val<'a>(&self, storage: &'a Self::Storage) -> &'a V {
-        debug_assert!(self.key_valid(storage));
-        debug_assert!(self.val_valid(storage));
-        debug_assert!(self.cursors[self.min_val[0]].val_valid(&storage[self.min_val[0]]));
-        self.cursors[self.min_val[0]].val(&storage[self.min_val[0]])
+        self.val_in(&storage.as_slice())
     }
     #[inline]
-    fn map_times<L: FnMut(&T, &R)>(&mut self, storage: &Self::Storage, mut logic: L) {
-        for &index in self.min_val.iter() {
-            self.cursors[index].map_times(&storage[index], |t, d| logic(t, d));
-        }
+    fn map_times<L: FnMut(&T, &R)>(&mut self, storage: &Self::Storage, logic: L) {
+        self.map_times_in(&storage.as_slice(), logic)
     }
 
     #[inline]
@@ -138,59 +321,43 @@ where
     where
         T: PartialEq<()>,
     {
-        debug_assert!(self.key_valid(storage));
-        debug_assert!(self.val_valid(storage));
-        debug_assert!(self.cursors[self.min_val[0]].val_valid(&storage[self.min_val[0]]));
-        let mut res: R = HasZero::zero();
-        self.map_times(storage, |_, w| res.add_assign_by_ref(w));
-        res
+        self.weight_in(&storage.as_slice())
     }
 
     // key methods
     #[inline]
     fn step_key(&mut self, storage: &Self::Storage) {
-        for &index in self.min_key.iter() {
-            self.cursors[index].step_key(&storage[index]);
-        }
-        self.minimize_keys(storage);
+        self.step_key_in(&storage.as_slice())
     }
     #[inline]
     fn seek_key(&mut self, storage: &Self::Storage, key: &K) {
-        for (index, cursor) in self.cursors.iter_mut().enumerate() {
-            cursor.seek_key(&storage[index], key);
-        }
-        self.minimize_keys(storage);
+        self.seek_key_in(&storage.as_slice(), key)
+    }
+    #[inline]
+    fn seek_key_with<P>(&mut self, storage: &Self::Storage, predicate: P)
+    where
+        P: Fn(&K) -> bool,
+    {
+        self.seek_key_with_in(&storage.as_slice(), predicate)
     }
 
     // value methods
     #[inline]
     fn step_val(&mut self, storage: &Self::Storage) {
-        for &index in self.min_val.iter() {
-            self.cursors[index].step_val(&storage[index]);
-        }
-        self.minimize_vals(storage);
+        self.step_val_in(&storage.as_slice())
     }
     #[inline]
     fn seek_val(&mut self, storage: &Self::Storage, val: &V) {
-        for &index in self.min_key.iter() {
-            self.cursors[index].seek_val(&storage[index], val);
-        }
-        self.minimize_vals(storage);
+        self.seek_val_in(&storage.as_slice(), val)
     }
 
     // rewinding methods
     #[inline]
     fn rewind_keys(&mut self, storage: &Self::Storage) {
-        for (index, cursor) in self.cursors.iter_mut().enumerate() {
-            cursor.rewind_keys(&storage[index]);
-        }
-        self.minimize_keys(storage);
+        self.rewind_keys_in(&storage.as_slice())
     }
     #[inline]
     fn rewind_vals(&mut self, storage: &Self::Storage) {
-        for &index in self.min_key.iter() {
-            self.cursors[index].rewind_vals(&storage[index]);
-        }
-        self.minimize_vals(storage);
+        self.rewind_vals_in(&storage.as_slice())
     }
 }
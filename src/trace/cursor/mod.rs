@@ -9,7 +9,7 @@
 pub mod cursor_list;
 pub mod cursor_pair;
 
-pub use self::cursor_list::CursorList;
+pub use self::cursor_list::{CursorList, CursorStorage};
 
 /// A cursor for navigating ordered `(key, val, time, diff)` updates.
 pub trait Cursor<K, V, T, R> {
@@ -70,6 +70,23 @@ pub trait Cursor<K, V, T, R> {
     fn step_key(&mut self, storage: &Self::Storage);
     /// Advances the cursor to the specified key.
     fn seek_key(&mut self, storage: &Self::Storage, key: &K);
+    /// Advances the cursor to the first key that satisfies `predicate`.
+    ///
+    /// The predicate must be monotone, i.e., once it holds for a key, it must
+    /// hold for all subsequent keys.  This allows seeking by a prefix of a
+    /// composite key, e.g., `|(x, _)| *x >= 5` finds the first tuple whose
+    /// first component is `5` or greater.
+    ///
+    /// The default implementation steps through keys one at a time; cursors
+    /// over sorted arrays override it with exponential search.
+    fn seek_key_with<P>(&mut self, storage: &Self::Storage, predicate: P)
+    where
+        P: Fn(&K) -> bool,
+    {
+        while self.key_valid(storage) && !predicate(self.key(storage)) {
+            self.step_key(storage);
+        }
+    }
 
     /// Advances the cursor to the next value.
     fn step_val(&mut self, storage: &Self::Storage);
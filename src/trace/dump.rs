@@ -0,0 +1,168 @@
+//! Dumping the contents of traces for debugging.
+
+use crate::trace::{BatchReader, Cursor};
+use std::{
+    fmt::Debug,
+    io::{self, Write},
+};
+
+/// Output format of [`TraceReader::dump`](`super::TraceReader::dump`).
+///
+/// Keys, values, times, and weights are written using their [`Debug`]
+/// representations, so that any trace can be dumped regardless of whether
+/// its types support serialization.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// Comma-separated values with a `key,val,time,weight` header line.
+    /// Fields that contain commas, quotes, or line breaks are quoted.
+    Csv,
+    /// A JSON array of objects with `key`, `val`, `time`, and `weight`
+    /// string fields, one object per line.
+    Json,
+}
+
+// Write all `(key, val, time, weight)` tuples of `reader` whose keys satisfy
+// `filter` to `writer`.
+pub(crate) fn dump<B, W, F>(
+    reader: &B,
+    mut writer: W,
+    format: DumpFormat,
+    filter: F,
+) -> io::Result<()>
+where
+    B: BatchReader,
+    B::Key: Debug,
+    B::Val: Debug,
+    B::Time: Debug,
+    B::R: Debug,
+    W: Write,
+    F: Fn(&B::Key) -> bool,
+{
+    match format {
+        DumpFormat::Csv => writeln!(writer, "key,val,time,weight")?,
+        DumpFormat::Json => write!(writer, "[")?,
+    }
+
+    let mut first = true;
+    let mut cursor = reader.cursor();
+    while cursor.key_valid(reader) {
+        let key = cursor.key(reader);
+        if filter(key) {
+            let key = format!("{:?}", key);
+            while cursor.val_valid(reader) {
+                let val = format!("{:?}", cursor.val(reader));
+                let mut result = Ok(());
+                cursor.map_times(reader, |time, weight| {
+                    if result.is_err() {
+                        return;
+                    }
+                    let time = format!("{:?}", time);
+                    let weight = format!("{:?}", weight);
+                    result = match format {
+                        DumpFormat::Csv => writeln!(
+                            writer,
+                            "{},{},{},{}",
+                            csv_field(&key),
+                            csv_field(&val),
+                            csv_field(&time),
+                            csv_field(&weight)
+                        ),
+                        DumpFormat::Json => write!(
+                            writer,
+                            "{}\n  {{\"key\": {}, \"val\": {}, \"time\": {}, \"weight\": {}}}",
+                            if first { "" } else { "," },
+                            json_string(&key),
+                            json_string(&val),
+                            json_string(&time),
+                            json_string(&weight)
+                        ),
+                    };
+                    first = false;
+                });
+                result?;
+                cursor.step_val(reader);
+            }
+        }
+        cursor.step_key(reader);
+    }
+
+    if format == DumpFormat::Json {
+        writeln!(writer, "{}]", if first { "" } else { "\n" })?;
+    }
+    writer.flush()
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::DumpFormat;
+    use crate::trace::{
+        ord::{OrdIndexedZSet, OrdIndexedZSetSpine},
+        Batch, Trace, TraceReader,
+    };
+    use std::rc::Rc;
+
+    #[test]
+    fn dump_test() {
+        let mut trace = OrdIndexedZSetSpine::<u64, String, isize>::new(None);
+        trace.insert(Rc::new(OrdIndexedZSet::from_tuples(
+            (),
+            vec![((1, "a".to_string()), 1), ((2, "b, \"c\"".to_string()), -1)],
+        )));
+        trace.insert(Rc::new(OrdIndexedZSet::from_tuples(
+            (),
+            vec![((3, "d".to_string()), 2)],
+        )));
+
+        let mut csv = Vec::new();
+        trace.dump(&mut csv, DumpFormat::Csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "key,val,time,weight\n\
+             1,\"\"\"a\"\"\",(),1\n\
+             2,\"\"\"b, \\\"\"c\\\"\"\"\"\",(),-1\n\
+             3,\"\"\"d\"\"\",(),2\n"
+        );
+
+        let mut json = Vec::new();
+        trace
+            .dump_filtered(&mut json, DumpFormat::Json, |k| *k != 2)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "[\n  {\"key\": \"1\", \"val\": \"\\\"a\\\"\", \"time\": \"()\", \"weight\": \"1\"},\n  \
+             {\"key\": \"3\", \"val\": \"\\\"d\\\"\", \"time\": \"()\", \"weight\": \"2\"}\n]\n"
+        );
+
+        let mut empty = Vec::new();
+        OrdIndexedZSetSpine::<u64, String, isize>::new(None)
+            .dump(&mut empty, DumpFormat::Json)
+            .unwrap();
+        assert_eq!(String::from_utf8(empty).unwrap(), "[]\n");
+    }
+}
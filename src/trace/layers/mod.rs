@@ -39,6 +39,10 @@ pub trait Trie: Sized {
 
     /// The total number of tuples in the collection.
     fn tuples(&self) -> usize;
+    /// Returns the number of keys and tuples in the collection.
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::new(self.keys(), self.tuples())
+    }
     /// Returns a cursor capable of navigating the collection.
     fn cursor(&self) -> Self::Cursor {
         self.cursor_from(0, self.keys())
@@ -63,6 +67,34 @@ pub trait Trie: Sized {
 
 pub struct TrieSlice<'a, T: Trie>(&'a T, T::Cursor);
 
+/// Estimated size of a collection, used to preallocate builders.
+///
+/// For multi-layer tries, `keys` is the number of keys in the top layer and
+/// `tuples` is the number of tuples in the bottom layer, which is also an
+/// upper bound on the number of keys in every intermediate layer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SizeHint {
+    /// Estimated number of keys in the top layer.
+    pub keys: usize,
+    /// Estimated number of tuples.
+    pub tuples: usize,
+}
+
+impl SizeHint {
+    /// Creates a size hint for `keys` keys and `tuples` tuples.
+    pub const fn new(keys: usize, tuples: usize) -> Self {
+        Self { keys, tuples }
+    }
+
+    /// Size hint for the layer below a layer with size hint `self`.
+    ///
+    /// The number of keys in the child layer is only known to be between
+    /// `self.keys` and `self.tuples`; we use the upper bound.
+    pub const fn child(self) -> Self {
+        Self::new(self.tuples, self.tuples)
+    }
+}
+
 impl<T: Trie> HasZero for T {
     fn is_zero(&self) -> bool {
         self.keys() == 0
@@ -72,6 +104,24 @@ impl<T: Trie> HasZero for T {
     }
 }
 
+/// A collection that can be extended in place with the contents of another
+/// collection.
+///
+/// Appending avoids rebuilding the collection via a merge when the keys of
+/// the two collections do not overlap, e.g., when adding up batches of
+/// updates whose keys grow monotonically.
+pub trait Append: Trie {
+    /// Appends all tuples in `other` after the tuples in `self`.
+    ///
+    /// The caller must ensure that all keys in `other` are greater than all
+    /// keys in `self`.  Nested layers are appended child by child, so for
+    /// them this only applies to the keys of the outermost layer.
+    fn append(&mut self, other: Self);
+
+    /// Like [`Self::append`], but copies the tuples in `other`.
+    fn append_ref(&mut self, other: &Self);
+}
+
 /// A type used to assemble collections.
 pub trait Builder {
     /// The type of collection produced.
@@ -110,7 +160,15 @@ pub trait TupleBuilder: Builder {
     /// Allocates a new builder.
     fn new() -> Self;
     /// Allocates a new builder with capacity for at least `cap` tuples.
-    fn with_capacity(cap: usize) -> Self; // <-- unclear how to set child capacities...
+    fn with_capacity(cap: usize) -> Self;
+    /// Allocates a new builder with capacity for the number of keys and tuples
+    /// specified by `hint`, passing the hint down to child builders.
+    fn with_size_hint(hint: SizeHint) -> Self
+    where
+        Self: Sized,
+    {
+        Self::with_capacity(hint.tuples)
+    }
     /// Inserts a new into the collection.
     fn push_tuple(&mut self, tuple: Self::Item);
     fn tuples(&self) -> usize;
@@ -137,6 +195,24 @@ pub trait Cursor<Storage> {
     fn step(&mut self, storage: &Storage);
     /// Advances the cursor until the location where `key` would be expected.
     fn seek(&mut self, storage: &Storage, key: &Self::Key);
+    /// Advances the cursor to the first key that satisfies `predicate`.
+    ///
+    /// The predicate must be monotone, i.e., once it holds for a key, it must
+    /// hold for all subsequent keys.  For example, `|(x, _)| *x >= 5`
+    /// positions the cursor at the first tuple key with prefix `5` or greater,
+    /// without having to construct a sentinel key such as `(5, MIN)`.
+    ///
+    /// The default implementation steps through keys one at a time.
+    /// Implementations backed by sorted arrays use exponential search
+    /// (see [`advance`]).
+    fn seek_with<P>(&mut self, storage: &Storage, predicate: P)
+    where
+        P: Fn(&Self::Key) -> bool,
+    {
+        while self.valid(storage) && !predicate(self.key(storage)) {
+            self.step(storage);
+        }
+    }
     /// Returns `true` if the cursor points at valid data. Returns `false` if
     /// the cursor is exhausted.
     fn valid(&self, storage: &Storage) -> bool;
@@ -201,6 +277,11 @@ impl Trie for () {
     fn cursor_from(&self, _lower: usize, _upper: usize) -> Self::Cursor {}
 }
 
+impl Append for () {
+    fn append(&mut self, _other: Self) {}
+    fn append_ref(&mut self, _other: &Self) {}
+}
+
 impl Builder for () {
     type Trie = ();
 
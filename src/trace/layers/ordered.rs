@@ -2,7 +2,9 @@
 
 use crate::{
     algebra::{AddAssignByRef, AddByRef, NegByRef},
-    trace::layers::{advance, Builder, Cursor, MergeBuilder, Trie, TrieSlice, TupleBuilder},
+    trace::layers::{
+        advance, Append, Builder, Cursor, MergeBuilder, SizeHint, Trie, TrieSlice, TupleBuilder,
+    },
     NumEntries, SharedRef,
 };
 use deepsize::DeepSizeOf;
@@ -28,6 +30,198 @@ impl<O> OrdOffset for O where
 {
 }
 
+/// Offsets of an [`OrderedLayer`] into its lower layer.
+///
+/// Offsets are stored as `u32` until one of them exceeds `u32::MAX`, at
+/// which point the vector is converted to a vector of `O`.  With the default
+/// `O = usize`, this halves the size of the offsets of all but huge layers
+/// without having to pick a narrower offset type up front.
+#[derive(Clone)]
+pub enum OffsetVec<O> {
+    /// Offsets that all fit in `u32`.
+    Narrow(Vec<u32>),
+    /// Offsets after promotion.
+    Wide(Vec<O>),
+}
+
+impl<O> OffsetVec<O>
+where
+    O: OrdOffset,
+    <O as TryFrom<usize>>::Error: Debug,
+    <O as TryInto<usize>>::Error: Debug,
+{
+    /// Creates an empty offset vector.
+    pub fn new() -> Self {
+        Self::Narrow(Vec::new())
+    }
+
+    /// Creates an empty offset vector with space for `capacity` offsets.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::Narrow(Vec::with_capacity(capacity))
+    }
+
+    /// Returns the number of offsets in the vector.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Narrow(offs) => offs.len(),
+            Self::Wide(offs) => offs.len(),
+        }
+    }
+
+    /// Returns `true` if the vector contains no offsets.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the offset at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    pub fn get(&self, index: usize) -> usize {
+        match self {
+            Self::Narrow(offs) => offs[index] as usize,
+            Self::Wide(offs) => offs[index].try_into().unwrap(),
+        }
+    }
+
+    /// Returns the last offset in the vector, if any.
+    pub fn last(&self) -> Option<usize> {
+        self.len().checked_sub(1).map(|index| self.get(index))
+    }
+
+    /// Sets the offset at `index` to `offset`, promoting the vector if
+    /// `offset` does not fit in `u32`.
+    #[inline]
+    pub fn set(&mut self, index: usize, offset: usize) {
+        match self {
+            Self::Narrow(offs) => match u32::try_from(offset) {
+                Ok(offset) => offs[index] = offset,
+                Err(_) => {
+                    self.promote();
+                    self.set(index, offset);
+                }
+            },
+            Self::Wide(offs) => offs[index] = O::try_from(offset).unwrap(),
+        }
+    }
+
+    /// Appends `offset` to the vector, promoting the vector if `offset` does
+    /// not fit in `u32`.
+    #[inline]
+    pub fn push(&mut self, offset: usize) {
+        match self {
+            Self::Narrow(offs) => match u32::try_from(offset) {
+                Ok(offset) => offs.push(offset),
+                Err(_) => {
+                    self.promote();
+                    self.push(offset);
+                }
+            },
+            Self::Wide(offs) => offs.push(O::try_from(offset).unwrap()),
+        }
+    }
+
+    /// Shortens the vector to `len` offsets.
+    pub fn truncate(&mut self, len: usize) {
+        match self {
+            Self::Narrow(offs) => offs.truncate(len),
+            Self::Wide(offs) => offs.truncate(len),
+        }
+    }
+
+    /// Removes consecutive repeated offsets.
+    pub fn dedup(&mut self) {
+        match self {
+            Self::Narrow(offs) => offs.dedup(),
+            Self::Wide(offs) => offs.dedup(),
+        }
+    }
+
+    /// Returns an iterator over the offsets in the vector.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).map(|index| self.get(index))
+    }
+
+    // Converts a narrow vector to a vector of `O`.
+    #[cold]
+    fn promote(&mut self) {
+        if let Self::Narrow(offs) = self {
+            let mut wide = Vec::with_capacity(offs.capacity());
+            wide.extend(offs.iter().map(|&off| O::try_from(off as usize).unwrap()));
+            *self = Self::Wide(wide);
+        }
+    }
+}
+
+impl<O> Default for OffsetVec<O>
+where
+    O: OrdOffset,
+    <O as TryFrom<usize>>::Error: Debug,
+    <O as TryInto<usize>>::Error: Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O> Extend<usize> for OffsetVec<O>
+where
+    O: OrdOffset,
+    <O as TryFrom<usize>>::Error: Debug,
+    <O as TryInto<usize>>::Error: Debug,
+{
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for offset in iter {
+            self.push(offset);
+        }
+    }
+}
+
+// Vectors with the same offsets are equal regardless of their representation.
+impl<O> PartialEq for OffsetVec<O>
+where
+    O: OrdOffset,
+    <O as TryFrom<usize>>::Error: Debug,
+    <O as TryInto<usize>>::Error: Debug,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<O> Eq for OffsetVec<O>
+where
+    O: OrdOffset,
+    <O as TryFrom<usize>>::Error: Debug,
+    <O as TryInto<usize>>::Error: Debug,
+{
+}
+
+impl<O> Debug for OffsetVec<O>
+where
+    O: OrdOffset,
+    <O as TryFrom<usize>>::Error: Debug,
+    <O as TryInto<usize>>::Error: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<O> DeepSizeOf for OffsetVec<O>
+where
+    O: DeepSizeOf,
+{
+    fn deep_size_of_children(&self, context: &mut deepsize::Context) -> usize {
+        match self {
+            Self::Narrow(offs) => offs.deep_size_of_children(context),
+            Self::Wide(offs) => offs.deep_size_of_children(context),
+        }
+    }
+}
+
 /// A level of the trie, with keys and offsets into a lower layer.
 ///
 /// In this representation, the values for `keys[i]` are found at `vals[offs[i]
@@ -47,7 +241,7 @@ where
     /// The bounds for `keys[i]` are `(offs[i], offs[i+1]`). The offset array is
     /// guaranteed to be one element longer than the keys array, ensuring
     /// that these accesses do not panic.
-    pub offs: Vec<O>,
+    pub offs: OffsetVec<O>,
     /// The ranges of values associated with the keys.
     pub vals: L,
 }
@@ -177,28 +371,23 @@ where
 impl<K, L, O> Add<Self> for OrderedLayer<K, L, O>
 where
     K: Ord + Clone,
-    L: Trie,
+    L: Append,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
 {
     type Output = Self;
 
-    fn add(self, rhs: Self) -> Self::Output {
-        if self.is_empty() {
-            rhs
-        } else if rhs.is_empty() {
-            self
-        } else {
-            self.merge(&rhs)
-        }
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self.add_assign(rhs);
+        self
     }
 }
 
 impl<K, L, O> AddAssign<Self> for OrderedLayer<K, L, O>
 where
     K: Ord + Clone,
-    L: Trie,
+    L: Append,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -206,22 +395,119 @@ where
     fn add_assign(&mut self, rhs: Self) {
         if self.is_empty() {
             *self = rhs;
+        } else if self.precedes(&rhs) {
+            self.append(rhs);
         } else if !rhs.is_empty() {
             *self = self.merge(&rhs);
         }
     }
 }
 
-impl<K, L, O> AddAssignByRef for OrderedLayer<K, L, O>
+impl<K, L, O> OrderedLayer<K, L, O>
 where
     K: Ord + Clone,
     L: Trie,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
+{
+    /// Retains only the children for which `retain(key, vals, index)` returns
+    /// `true`, where `index` is the position of the child in `vals`.  Keys
+    /// left without children are removed.
+    pub fn retain_children<F>(&mut self, mut retain: F)
+    where
+        F: FnMut(&K, &L, usize) -> bool,
+    {
+        let mut builder =
+            <OrderedBuilder<K, L::MergeBuilder, O> as MergeBuilder>::with_key_capacity(
+                self.keys.len(),
+            );
+
+        for index in 0..self.keys.len() {
+            let key = &self.keys[index];
+            let lower = self.offs.get(index);
+            let upper = self.offs.get(index + 1);
+
+            // Copy runs of retained children.
+            let mut run_start = None;
+            for child in lower..upper {
+                if retain(key, &self.vals, child) {
+                    run_start.get_or_insert(child);
+                } else if let Some(start) = run_start.take() {
+                    builder.vals.copy_range(&self.vals, start, child);
+                }
+            }
+            if let Some(start) = run_start {
+                builder.vals.copy_range(&self.vals, start, upper);
+            }
+
+            let boundary = builder.vals.boundary();
+            if boundary > builder.offs.last().unwrap() {
+                builder.keys.push(key.clone());
+                builder.offs.push(boundary);
+            }
+        }
+
+        *self = builder.done();
+    }
+}
+
+impl<K, L, O> OrderedLayer<K, L, O>
+where
+    K: Ord,
+    O: OrdOffset,
+    <O as TryFrom<usize>>::Error: Debug,
+    <O as TryInto<usize>>::Error: Debug,
+{
+    /// Returns `true` if both `self` and `other` are non-empty and all keys
+    /// in `self` are smaller than all keys in `other`.
+    fn precedes(&self, other: &Self) -> bool {
+        match (self.keys.last(), other.keys.first()) {
+            (Some(last), Some(first)) => last < first,
+            _ => false,
+        }
+    }
+}
+
+impl<K, L, O> Append for OrderedLayer<K, L, O>
+where
+    K: Ord + Clone,
+    L: Append,
+    O: OrdOffset,
+    <O as TryFrom<usize>>::Error: Debug,
+    <O as TryInto<usize>>::Error: Debug,
+{
+    fn append(&mut self, other: Self) {
+        // Offsets in `other` are relative to the start of `other.vals`, which
+        // now starts after the last value in `self.vals`.
+        let base = self.vals.keys();
+        self.vals.append(other.vals);
+        self.offs
+            .extend(other.offs.iter().skip(1).map(|off| off + base));
+        self.keys.extend(other.keys);
+    }
+
+    fn append_ref(&mut self, other: &Self) {
+        let base = self.vals.keys();
+        self.vals.append_ref(&other.vals);
+        self.offs
+            .extend(other.offs.iter().skip(1).map(|off| off + base));
+        self.keys.extend_from_slice(&other.keys);
+    }
+}
+
+impl<K, L, O> AddAssignByRef for OrderedLayer<K, L, O>
+where
+    K: Ord + Clone,
+    L: Append,
+    O: OrdOffset,
+    <O as TryFrom<usize>>::Error: Debug,
+    <O as TryInto<usize>>::Error: Debug,
 {
     fn add_assign_by_ref(&mut self, other: &Self) {
-        if !other.is_empty() {
+        if self.is_empty() || self.precedes(other) {
+            self.append_ref(other);
+        } else if !other.is_empty() {
             *self = self.merge(other);
         }
     }
@@ -261,14 +547,11 @@ where
     }
     fn cursor_from(&self, lower: usize, upper: usize) -> Self::Cursor {
         if lower < upper {
-            let child_lower = self.offs[lower];
-            let child_upper = self.offs[lower + 1];
+            let child_lower = self.offs.get(lower);
+            let child_upper = self.offs.get(lower + 1);
             OrderedCursor {
                 bounds: (lower, upper),
-                child: self.vals.cursor_from(
-                    child_lower.try_into().unwrap(),
-                    child_upper.try_into().unwrap(),
-                ),
+                child: self.vals.cursor_from(child_lower, child_upper),
                 pos: lower,
             }
         } else {
@@ -292,7 +575,7 @@ where
     /// Keys
     pub keys: Vec<K>,
     /// Offsets
-    pub offs: Vec<O>,
+    pub offs: OffsetVec<O>,
     /// The next layer down
     pub vals: L,
 }
@@ -307,12 +590,12 @@ where
 {
     type Trie = OrderedLayer<K, L::Trie, O>;
     fn boundary(&mut self) -> usize {
-        self.offs[self.keys.len()] = O::try_from(self.vals.boundary()).unwrap();
+        self.offs.set(self.keys.len(), self.vals.boundary());
         self.keys.len()
     }
     fn done(mut self) -> Self::Trie {
-        if !self.keys.is_empty() && self.offs[self.keys.len()].try_into().unwrap() == 0 {
-            self.offs[self.keys.len()] = O::try_from(self.vals.boundary()).unwrap();
+        if !self.keys.is_empty() && self.offs.get(self.keys.len()) == 0 {
+            self.offs.set(self.keys.len(), self.vals.boundary());
         }
         OrderedLayer {
             keys: self.keys,
@@ -331,8 +614,8 @@ where
     <O as TryInto<usize>>::Error: Debug,
 {
     fn with_capacity(other1: &Self::Trie, other2: &Self::Trie) -> Self {
-        let mut offs = Vec::with_capacity(other1.keys() + other2.keys() + 1);
-        offs.push(O::try_from(0_usize).unwrap());
+        let mut offs = OffsetVec::with_capacity(other1.keys() + other2.keys() + 1);
+        offs.push(0);
         OrderedBuilder {
             keys: Vec::with_capacity(other1.keys() + other2.keys()),
             offs,
@@ -340,8 +623,8 @@ where
         }
     }
     fn with_key_capacity(cap: usize) -> Self {
-        let mut offs = Vec::with_capacity(cap + 1);
-        offs.push(O::try_from(0_usize).unwrap());
+        let mut offs = OffsetVec::with_capacity(cap + 1);
+        offs.push(0);
         OrderedBuilder {
             keys: Vec::with_capacity(cap),
             offs,
@@ -352,23 +635,16 @@ where
     #[inline]
     fn copy_range(&mut self, other: &Self::Trie, lower: usize, upper: usize) {
         debug_assert!(lower < upper);
-        let other_basis = other.offs[lower];
-        let self_basis = self
-            .offs
-            .last()
-            .copied()
-            .unwrap_or_else(|| O::try_from(0).unwrap());
+        let other_basis = other.offs.get(lower);
+        let self_basis = self.offs.last().unwrap_or(0);
 
         self.keys.extend_from_slice(&other.keys[lower..upper]);
         for index in lower..upper {
             self.offs
-                .push((other.offs[index + 1] + self_basis) - other_basis);
+                .push((other.offs.get(index + 1) + self_basis) - other_basis);
         }
-        self.vals.copy_range(
-            &other.vals,
-            other_basis.try_into().unwrap(),
-            other.offs[upper].try_into().unwrap(),
-        );
+        self.vals
+            .copy_range(&other.vals, other_basis, other.offs.get(upper));
     }
 
     fn push_merge(
@@ -435,22 +711,20 @@ where
                 let upper = self.vals.push_merge(
                     (
                         &trie1.vals,
-                        trie1.vals.cursor_from(
-                            trie1.offs[*lower1].try_into().unwrap(),
-                            trie1.offs[*lower1 + 1].try_into().unwrap(),
-                        ),
+                        trie1
+                            .vals
+                            .cursor_from(trie1.offs.get(*lower1), trie1.offs.get(*lower1 + 1)),
                     ),
                     (
                         &trie2.vals,
-                        trie2.vals.cursor_from(
-                            trie2.offs[*lower2].try_into().unwrap(),
-                            trie2.offs[*lower2 + 1].try_into().unwrap(),
-                        ),
+                        trie2
+                            .vals
+                            .cursor_from(trie2.offs.get(*lower2), trie2.offs.get(*lower2 + 1)),
                     ),
                 );
                 if upper > lower {
                     self.keys.push(trie1.keys[*lower1].clone());
-                    self.offs.push(O::try_from(upper).unwrap());
+                    self.offs.push(upper);
                 }
 
                 *lower1 += 1;
@@ -481,33 +755,46 @@ where
     fn new() -> Self {
         OrderedBuilder {
             keys: Vec::new(),
-            offs: vec![O::try_from(0).unwrap()],
+            offs: {
+                let mut offs = OffsetVec::new();
+                offs.push(0);
+                offs
+            },
             vals: L::new(),
         }
     }
     fn with_capacity(cap: usize) -> Self {
-        let mut offs = Vec::with_capacity(cap + 1);
-        offs.push(O::try_from(0).unwrap());
+        let mut offs = OffsetVec::with_capacity(cap + 1);
+        offs.push(0);
         OrderedBuilder {
             keys: Vec::with_capacity(cap),
             offs,
             vals: L::with_capacity(cap),
         }
     }
+    fn with_size_hint(hint: SizeHint) -> Self {
+        let mut offs = OffsetVec::with_capacity(hint.keys + 1);
+        offs.push(0);
+        OrderedBuilder {
+            keys: Vec::with_capacity(hint.keys),
+            offs,
+            vals: L::with_size_hint(hint.child()),
+        }
+    }
     #[inline]
     fn push_tuple(&mut self, (key, val): (K, L::Item)) {
         // if first element, prior element finish, or different element, need to push
         // and maybe punctuate.
         if self.keys.is_empty()
-            || self.offs[self.keys.len()].try_into().unwrap() != 0
+            || self.offs.get(self.keys.len()) != 0
             || self.keys[self.keys.len() - 1] != key
         {
-            if !self.keys.is_empty() && self.offs[self.keys.len()].try_into().unwrap() == 0 {
-                self.offs[self.keys.len()] = O::try_from(self.vals.boundary()).unwrap();
+            if !self.keys.is_empty() && self.offs.get(self.keys.len()) == 0 {
+                self.offs.set(self.keys.len(), self.vals.boundary());
             }
             self.keys.push(key);
-            self.offs.push(O::try_from(0).unwrap()); // <-- indicates
-                                                     // "unfinished".
+            self.offs.push(0); // <-- indicates
+                               // "unfinished".
         }
         self.vals.push_tuple(val);
     }
@@ -615,10 +902,9 @@ where
     }
     fn values<'a>(&self, storage: &'a OrderedLayer<K, L, O>) -> (&'a L, L::Cursor) {
         let child_cursor = if self.valid(storage) {
-            storage.vals.cursor_from(
-                storage.offs[self.pos].try_into().unwrap(),
-                storage.offs[self.pos + 1].try_into().unwrap(),
-            )
+            storage
+                .vals
+                .cursor_from(storage.offs.get(self.pos), storage.offs.get(self.pos + 1))
         } else {
             storage.vals.cursor_from(0, 0)
         };
@@ -629,8 +915,8 @@ where
         if self.valid(storage) {
             self.child.reposition(
                 &storage.vals,
-                storage.offs[self.pos].try_into().unwrap(),
-                storage.offs[self.pos + 1].try_into().unwrap(),
+                storage.offs.get(self.pos),
+                storage.offs.get(self.pos + 1),
             );
         } else {
             self.pos = self.bounds.1;
@@ -641,8 +927,21 @@ where
         if self.valid(storage) {
             self.child.reposition(
                 &storage.vals,
-                storage.offs[self.pos].try_into().unwrap(),
-                storage.offs[self.pos + 1].try_into().unwrap(),
+                storage.offs.get(self.pos),
+                storage.offs.get(self.pos + 1),
+            );
+        }
+    }
+    fn seek_with<P>(&mut self, storage: &OrderedLayer<K, L, O>, predicate: P)
+    where
+        P: Fn(&Self::Key) -> bool,
+    {
+        self.pos += advance(&storage.keys[self.pos..self.bounds.1], |k| !predicate(k));
+        if self.valid(storage) {
+            self.child.reposition(
+                &storage.vals,
+                storage.offs.get(self.pos),
+                storage.offs.get(self.pos + 1),
             );
         }
     }
@@ -655,8 +954,8 @@ where
         if self.valid(storage) {
             self.child.reposition(
                 &storage.vals,
-                storage.offs[self.pos].try_into().unwrap(),
-                storage.offs[self.pos + 1].try_into().unwrap(),
+                storage.offs.get(self.pos),
+                storage.offs.get(self.pos + 1),
             );
         }
     }
@@ -666,9 +965,70 @@ where
         if self.valid(storage) {
             self.child.reposition(
                 &storage.vals,
-                storage.offs[self.pos].try_into().unwrap(),
-                storage.offs[self.pos + 1].try_into().unwrap(),
+                storage.offs.get(self.pos),
+                storage.offs.get(self.pos + 1),
             );
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::trace::layers::{
+        ordered::{OffsetVec, OrderedBuilder},
+        ordered_leaf::{OrderedLeaf, OrderedLeafBuilder},
+        Builder, Cursor, Trie, TupleBuilder,
+    };
+
+    #[test]
+    fn seek_with() {
+        let mut builder =
+            <OrderedBuilder<(u64, u64), OrderedLeafBuilder<u64, isize>> as TupleBuilder>::new();
+        for x in 0..20 {
+            for y in 0..3 {
+                builder.push_tuple(((x, y), (0, 1)));
+            }
+        }
+        let layer = builder.done();
+
+        let mut cursor = layer.cursor();
+        cursor.seek_with(&layer, |(x, _)| *x >= 7);
+        assert_eq!(cursor.key(&layer), &(7, 0));
+
+        // The child cursor points to the values of the new key.
+        let (vals, mut child): (&OrderedLeaf<u64, isize>, _) = cursor.values(&layer);
+        assert_eq!(child.key(vals), &(0, 1));
+        child.seek_with(vals, |(v, _)| *v > 0);
+        assert!(!child.valid(vals));
+
+        cursor.seek_with(&layer, |(x, y)| (*x, *y) >= (12, 2));
+        assert_eq!(cursor.key(&layer), &(12, 2));
+
+        cursor.seek_with(&layer, |(x, _)| *x >= 20);
+        assert!(!cursor.valid(&layer));
+    }
+
+    #[test]
+    fn offset_vec_promotion() {
+        let mut narrow = OffsetVec::<usize>::new();
+        let mut offs = OffsetVec::<usize>::new();
+        for off in [0, 5, 7] {
+            narrow.push(off);
+            offs.push(off);
+        }
+        assert!(matches!(offs, OffsetVec::Narrow(_)));
+
+        let big = u32::MAX as usize + 1;
+        offs.push(big);
+        assert!(matches!(offs, OffsetVec::Wide(_)));
+        assert_eq!(offs.iter().collect::<Vec<_>>(), vec![0, 5, 7, big]);
+
+        offs.truncate(3);
+        assert_eq!(offs, narrow);
+
+        narrow.set(1, big);
+        assert!(matches!(narrow, OffsetVec::Wide(_)));
+        assert_eq!(narrow.get(1), big);
+        assert_eq!(narrow.last(), Some(7));
+    }
+}
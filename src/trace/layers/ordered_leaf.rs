@@ -1,10 +1,13 @@
 //! Implementation using ordered keys and exponential search.
 
+#[cfg(not(feature = "with-rayon"))]
+use crate::trace::consolidation::consolidate_slice;
+#[cfg(feature = "with-rayon")]
+use crate::trace::consolidation::par_consolidate_slice;
 use crate::{
     algebra::{AddAssignByRef, AddByRef, HasZero, NegByRef},
-    trace::{
-        consolidation::consolidate_slice,
-        layers::{advance, Builder, Cursor, MergeBuilder, Trie, TrieSlice, TupleBuilder},
+    trace::layers::{
+        advance, Append, Builder, Cursor, MergeBuilder, Trie, TrieSlice, TupleBuilder,
     },
     NumEntries, SharedRef,
 };
@@ -22,7 +25,9 @@ pub struct OrderedLeaf<K, R> {
     pub vals: Vec<(K, R)>,
 }
 
-impl<K: Ord + Clone, R: Eq + HasZero + AddAssignByRef + Clone> Trie for OrderedLeaf<K, R> {
+impl<K: Ord + Clone + Send, R: Eq + HasZero + AddAssignByRef + Clone + Send> Trie
+    for OrderedLeaf<K, R>
+{
     type Item = (K, R);
     type Cursor = OrderedLeafCursor;
     type MergeBuilder = OrderedLeafBuilder<K, R>;
@@ -43,8 +48,8 @@ impl<K: Ord + Clone, R: Eq + HasZero + AddAssignByRef + Clone> Trie for OrderedL
 
 impl<K, R> Display for OrderedLeaf<K, R>
 where
-    K: Ord + Clone + Display,
-    R: Eq + HasZero + AddAssignByRef + Clone + Display,
+    K: Ord + Clone + Display + Send,
+    R: Eq + HasZero + AddAssignByRef + Clone + Display + Send,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
         TrieSlice(self, self.cursor()).fmt(f)
@@ -53,8 +58,8 @@ where
 
 impl<'a, K, R> Display for TrieSlice<'a, OrderedLeaf<K, R>>
 where
-    K: Ord + Clone + Display,
-    R: Eq + HasZero + AddAssignByRef + Clone + Display,
+    K: Ord + Clone + Display + Send,
+    R: Eq + HasZero + AddAssignByRef + Clone + Display + Send,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
         let TrieSlice(storage, cursor) = self;
@@ -73,29 +78,28 @@ where
 // TODO: by-value merge
 impl<K, R> Add<Self> for OrderedLeaf<K, R>
 where
-    K: Ord + Clone,
-    R: Eq + HasZero + AddAssignByRef + Clone,
+    K: Ord + Clone + Send,
+    R: Eq + HasZero + AddAssignByRef + Clone + Send,
 {
     type Output = Self;
 
-    fn add(self, rhs: Self) -> Self::Output {
-        if self.is_empty() {
-            rhs
-        } else if rhs.is_empty() {
-            self
-        } else {
-            self.merge(&rhs)
-        }
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self.add_assign(rhs);
+        self
     }
 }
 
 impl<K, R> AddAssign<Self> for OrderedLeaf<K, R>
 where
-    K: Ord + Clone,
-    R: Eq + HasZero + AddAssignByRef + Clone,
+    K: Ord + Clone + Send,
+    R: Eq + HasZero + AddAssignByRef + Clone + Send,
 {
     fn add_assign(&mut self, rhs: Self) {
-        if !rhs.is_empty() {
+        if self.is_empty() {
+            *self = rhs;
+        } else if self.precedes(&rhs) {
+            self.append(rhs);
+        } else if !rhs.is_empty() {
             *self = self.merge(&rhs);
         }
     }
@@ -103,20 +107,50 @@ where
 
 impl<K, R> AddAssignByRef for OrderedLeaf<K, R>
 where
-    K: Ord + Clone,
-    R: Eq + HasZero + AddAssignByRef + Clone,
+    K: Ord + Clone + Send,
+    R: Eq + HasZero + AddAssignByRef + Clone + Send,
 {
     fn add_assign_by_ref(&mut self, other: &Self) {
-        if !other.is_empty() {
+        if self.is_empty() || self.precedes(other) {
+            self.append_ref(other);
+        } else if !other.is_empty() {
             *self = self.merge(other);
         }
     }
 }
 
+impl<K, R> OrderedLeaf<K, R>
+where
+    K: Ord + Send,
+{
+    /// Returns `true` if both `self` and `other` are non-empty and all keys
+    /// in `self` are smaller than all keys in `other`.
+    fn precedes(&self, other: &Self) -> bool {
+        match (self.vals.last(), other.vals.first()) {
+            (Some((last, _)), Some((first, _))) => last < first,
+            _ => false,
+        }
+    }
+}
+
+impl<K, R> Append for OrderedLeaf<K, R>
+where
+    K: Ord + Clone + Send,
+    R: Eq + HasZero + AddAssignByRef + Clone + Send,
+{
+    fn append(&mut self, mut other: Self) {
+        self.vals.append(&mut other.vals);
+    }
+
+    fn append_ref(&mut self, other: &Self) {
+        self.vals.extend_from_slice(&other.vals);
+    }
+}
+
 impl<K, R> AddByRef for OrderedLeaf<K, R>
 where
-    K: Ord + Clone,
-    R: Eq + HasZero + AddAssignByRef + Clone,
+    K: Ord + Clone + Send,
+    R: Eq + HasZero + AddAssignByRef + Clone + Send,
 {
     fn add_by_ref(&self, rhs: &Self) -> Self {
         self.merge(rhs)
@@ -125,8 +159,8 @@ where
 
 impl<K, R> NegByRef for OrderedLeaf<K, R>
 where
-    K: Ord + Clone,
-    R: NegByRef,
+    K: Ord + Clone + Send,
+    R: NegByRef + Send,
 {
     fn neg_by_ref(&self) -> Self {
         Self {
@@ -141,22 +175,24 @@ where
 
 impl<K, R> Neg for OrderedLeaf<K, R>
 where
-    K: Ord + Clone,
-    R: Neg<Output = R>,
+    K: Ord + Clone + Send,
+    R: NegByRef + Send,
 {
     type Output = Self;
 
-    fn neg(self) -> Self {
-        Self {
-            vals: self.vals.into_iter().map(|(k, v)| (k, v.neg())).collect(),
+    // Negates weights in place without moving keys.
+    fn neg(mut self) -> Self {
+        for (_, w) in self.vals.iter_mut() {
+            *w = w.neg_by_ref();
         }
+        self
     }
 }
 
 impl<K, R> NumEntries for OrderedLeaf<K, R>
 where
-    K: Ord + Clone,
-    R: Eq + HasZero + AddAssignByRef + Clone,
+    K: Ord + Clone + Send,
+    R: Eq + HasZero + AddAssignByRef + Clone + Send,
 {
     fn num_entries_shallow(&self) -> usize {
         self.keys()
@@ -171,8 +207,8 @@ where
 
 impl<K, R> SharedRef for OrderedLeaf<K, R>
 where
-    K: Clone,
-    R: Clone,
+    K: Clone + Send,
+    R: Clone + Send,
 {
     type Target = Self;
 
@@ -187,7 +223,7 @@ pub struct OrderedLeafBuilder<K, R> {
     pub vals: Vec<(K, R)>,
 }
 
-impl<K: Ord + Clone, R: Eq + HasZero + AddAssignByRef + Clone> Builder
+impl<K: Ord + Clone + Send, R: Eq + HasZero + AddAssignByRef + Clone + Send> Builder
     for OrderedLeafBuilder<K, R>
 {
     type Trie = OrderedLeaf<K, R>;
@@ -199,7 +235,7 @@ impl<K: Ord + Clone, R: Eq + HasZero + AddAssignByRef + Clone> Builder
     }
 }
 
-impl<K: Ord + Clone, R: Eq + HasZero + AddAssignByRef + Clone> MergeBuilder
+impl<K: Ord + Clone + Send, R: Eq + HasZero + AddAssignByRef + Clone + Send> MergeBuilder
     for OrderedLeafBuilder<K, R>
 {
     fn with_capacity(other1: &Self::Trie, other2: &Self::Trie) -> Self {
@@ -253,7 +289,7 @@ impl<K: Ord + Clone, R: Eq + HasZero + AddAssignByRef + Clone> MergeBuilder
                 Ordering::Equal => {
                     let mut sum = trie1.vals[lower1].1.clone();
                     sum.add_assign_by_ref(&trie2.vals[lower2].1);
-                    if !sum.is_zero() {
+                    if !sum.is_negligible() {
                         self.vals.push((trie1.vals[lower1].0.clone(), sum));
                     }
 
@@ -288,7 +324,7 @@ impl<K: Ord + Clone, R: Eq + HasZero + AddAssignByRef + Clone> MergeBuilder
     }
 }
 
-impl<K: Ord + Clone, R: Eq + HasZero + AddAssignByRef + Clone> TupleBuilder
+impl<K: Ord + Clone + Send, R: Eq + HasZero + AddAssignByRef + Clone + Send> TupleBuilder
     for OrderedLeafBuilder<K, R>
 {
     type Item = (K, R);
@@ -310,18 +346,27 @@ impl<K: Ord + Clone, R: Eq + HasZero + AddAssignByRef + Clone> TupleBuilder
     }
 }
 
+/// A builder for unordered values.
+///
+/// With the `with-rayon` feature, slices of at least
+/// [`PARALLEL_CONSOLIDATION_THRESHOLD`](`crate::trace::consolidation::PARALLEL_CONSOLIDATION_THRESHOLD`)
+/// tuples are sorted on all threads in the rayon thread pool by
+/// [`boundary`](`Builder::boundary`) and [`done`](`Builder::done`).
 #[derive(DeepSizeOf)]
 pub struct UnorderedLeafBuilder<K, R> {
     pub vals: Vec<(K, R)>,
     boundary: usize,
 }
 
-impl<K: Ord + Clone, R: Eq + HasZero + AddAssignByRef + Clone> Builder
+impl<K: Ord + Clone + Send, R: Eq + HasZero + AddAssignByRef + Clone + Send> Builder
     for UnorderedLeafBuilder<K, R>
 {
     type Trie = OrderedLeaf<K, R>;
 
     fn boundary(&mut self) -> usize {
+        #[cfg(feature = "with-rayon")]
+        let consolidated_len = par_consolidate_slice(&mut self.vals[self.boundary..]);
+        #[cfg(not(feature = "with-rayon"))]
         let consolidated_len = consolidate_slice(&mut self.vals[self.boundary..]);
         self.boundary += consolidated_len;
         self.vals.truncate(self.boundary);
@@ -333,7 +378,7 @@ impl<K: Ord + Clone, R: Eq + HasZero + AddAssignByRef + Clone> Builder
     }
 }
 
-impl<K: Ord + Clone, R: Eq + HasZero + AddAssignByRef + Clone> TupleBuilder
+impl<K: Ord + Clone + Send, R: Eq + HasZero + AddAssignByRef + Clone + Send> TupleBuilder
     for UnorderedLeafBuilder<K, R>
 {
     type Item = (K, R);
@@ -370,7 +415,7 @@ pub struct OrderedLeafCursor {
 }
 
 impl OrderedLeafCursor {
-    pub fn seek_key<K: Eq + Ord + Clone, R: Clone>(
+    pub fn seek_key<K: Eq + Ord + Clone + Send, R: Clone + Send>(
         &mut self,
         storage: &OrderedLeaf<K, R>,
         key: &K,
@@ -379,7 +424,7 @@ impl OrderedLeafCursor {
     }
 }
 
-impl<K: Eq + Ord + Clone, R: Clone> Cursor<OrderedLeaf<K, R>> for OrderedLeafCursor {
+impl<K: Eq + Ord + Clone + Send, R: Clone + Send> Cursor<OrderedLeaf<K, R>> for OrderedLeafCursor {
     type Key = (K, R);
     type ValueStorage = ();
 
@@ -401,6 +446,12 @@ impl<K: Eq + Ord + Clone, R: Clone> Cursor<OrderedLeaf<K, R>> for OrderedLeafCur
     fn seek(&mut self, storage: &OrderedLeaf<K, R>, key: &Self::Key) {
         self.seek_key(storage, &key.0);
     }
+    fn seek_with<P>(&mut self, storage: &OrderedLeaf<K, R>, predicate: P)
+    where
+        P: Fn(&Self::Key) -> bool,
+    {
+        self.pos += advance(&storage.vals[self.pos..self.bounds.1], |kr| !predicate(kr));
+    }
     fn valid(&self, _storage: &OrderedLeaf<K, R>) -> bool {
         self.pos < self.bounds.1
     }
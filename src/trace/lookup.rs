@@ -0,0 +1,106 @@
+//! Batched key lookups.
+
+use crate::trace::{BatchReader, Cursor};
+use std::collections::VecDeque;
+
+/// Iterator returned by [`BatchReader::lookup_many`].
+///
+/// Yields a `(key, val, time, weight)` tuple for each update in the batch
+/// whose key occurs in the probe sequence.  Since probe keys are sorted, the
+/// cursor only ever moves forward, so the cost of all seeks is amortized over
+/// a single pass through the batch.
+pub struct LookupMany<'a, B, I>
+where
+    B: BatchReader,
+{
+    batch: &'a B,
+    cursor: B::Cursor,
+    keys: I,
+    // Key and value under the cursor while iterating over the values of a
+    // matching key.
+    current: Option<(&'a B::Key, &'a B::Val)>,
+    // Updates for `current` that have not been yielded yet.
+    times: VecDeque<(B::Time, B::R)>,
+}
+
+impl<'a, B, I> LookupMany<'a, B, I>
+where
+    B: BatchReader,
+{
+    pub(crate) fn new(batch: &'a B, keys: I) -> Self {
+        Self {
+            batch,
+            cursor: batch.cursor(),
+            keys,
+            current: None,
+            times: VecDeque::new(),
+        }
+    }
+
+    // Loads updates for the value under the cursor, if any.
+    fn load_val(&mut self, key: &'a B::Key) {
+        if self.cursor.val_valid(self.batch) {
+            let times = &mut self.times;
+            self.cursor.map_times(self.batch, |time, weight| {
+                times.push_back((time.clone(), weight.clone()))
+            });
+            self.current = Some((key, self.cursor.val(self.batch)));
+        } else {
+            self.current = None;
+        }
+    }
+}
+
+impl<'a, B, I> Iterator for LookupMany<'a, B, I>
+where
+    B: BatchReader,
+    B::Key: PartialEq,
+    I: Iterator<Item = &'a B::Key>,
+{
+    type Item = (&'a B::Key, &'a B::Val, B::Time, B::R);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, val)) = self.current {
+                if let Some((time, weight)) = self.times.pop_front() {
+                    return Some((key, val, time, weight));
+                }
+                self.cursor.step_val(self.batch);
+                self.load_val(key);
+            } else {
+                let key = self.keys.next()?;
+                self.cursor.seek_key(self.batch, key);
+                if self.cursor.get_key(self.batch) == Some(key) {
+                    // The same key may occur in the probe sequence multiple
+                    // times.
+                    self.cursor.rewind_vals(self.batch);
+                    let key = self.cursor.key(self.batch);
+                    self.load_val(key);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{indexed_zset, trace::ord::OrdIndexedZSet, trace::BatchReader};
+
+    #[test]
+    fn lookup_many() {
+        let batch: OrdIndexedZSet<u64, &'static str, isize> = indexed_zset! {
+            1 => { "a" => 1, "b" => 2 },
+            3 => { "c" => -1 },
+            5 => { "d" => 1 },
+        };
+
+        let result: Vec<_> = batch
+            .lookup_many([0, 1, 3, 3, 4, 6].iter())
+            .map(|(k, v, _, w)| (*k, *v, w))
+            .collect();
+        assert_eq!(
+            result,
+            vec![(1, "a", 1), (1, "b", 2), (3, "c", -1), (3, "c", -1)]
+        );
+    }
+}
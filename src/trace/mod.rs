@@ -12,14 +12,48 @@
 
 pub mod consolidation;
 pub mod cursor;
+pub mod dump;
 pub mod layers;
+pub mod lookup;
 pub mod ord;
+pub mod sharded_spine;
+pub mod snapshot;
 pub mod spine_fueled;
 
 use crate::{algebra::MonoidValue, lattice::Lattice, time::Timestamp};
-use timely::progress::Antichain;
+use std::{
+    fmt::Debug,
+    io::{self, Write},
+    mem::replace,
+};
+use timely::{progress::Antichain, PartialOrder};
 
 pub use cursor::Cursor;
+pub use dump::DumpFormat;
+pub use layers::SizeHint;
+pub use lookup::LookupMany;
+pub use snapshot::{ConsumedFrontier, TraceSnapshot};
+pub use spine_fueled::EffortConfig;
+
+/// Outstanding maintenance work of a trace, returned by
+/// [`TraceReader::maintenance_debt`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MaintenanceDebt {
+    /// Number of merges in progress.
+    pub merges: usize,
+    /// Number of tuples in the input batches of in-progress merges.
+    pub tuples: usize,
+    /// Estimated amount of fuel (see [`Trace::exert`]) required to complete
+    /// all in-progress merges.
+    pub fuel: usize,
+}
+
+impl MaintenanceDebt {
+    /// `true` if there is no outstanding maintenance work.
+    pub fn is_empty(&self) -> bool {
+        self.merges == 0
+    }
+}
 
 /// A trace whose contents may be read.
 ///
@@ -50,6 +84,91 @@ pub trait TraceReader: BatchReader {
 
     /// Maps logic across the non-empty sequence of batches in the trace.
     fn map_batches<F: FnMut(&Self::Batch)>(&self, f: F);
+
+    /// Returns an immutable snapshot of the current contents of the trace.
+    ///
+    /// The snapshot is not affected by subsequent modifications of the
+    /// trace.  It clones the batches in the trace, which is cheap for
+    /// reference-counted batch types (see [`TraceSnapshot`]).
+    fn snapshot(&self) -> TraceSnapshot<Self::Batch> {
+        let mut batches = Vec::new();
+        self.map_batches(|batch| {
+            if !batch.is_empty() {
+                batches.push(batch.clone())
+            }
+        });
+        TraceSnapshot::new(batches, self.lower().clone(), self.upper().clone())
+    }
+
+    /// Returns a snapshot of the batches in the trace that may contain
+    /// updates at times greater than or equal to an element of `frontier`.
+    ///
+    /// Batches whose `upper` bound is less than or equal to `frontier` only
+    /// contain updates before the frontier and are skipped, so the cost of
+    /// reading the snapshot is proportional to the size of recent batches
+    /// rather than the whole trace.  Since the trace is organized in batches,
+    /// the snapshot may still contain some updates before `frontier` from
+    /// batches that straddle it.  Readers that need exact semantics must
+    /// filter timestamps in [`Cursor::map_times`].
+    ///
+    /// See [`ConsumedFrontier`] for keeping track of the updates a reader
+    /// has already consumed.
+    fn updates_since(&self, frontier: &Antichain<Self::Time>) -> TraceSnapshot<Self::Batch> {
+        let mut batches = Vec::new();
+        let mut lower = frontier.clone();
+        self.map_batches(|batch| {
+            if !batch.is_empty() && !PartialOrder::less_equal(batch.upper(), frontier) {
+                lower = lower.meet(batch.lower());
+                batches.push(batch.clone())
+            }
+        });
+        TraceSnapshot::new(batches, lower, self.upper().clone())
+    }
+
+    /// Returns the amount of outstanding maintenance work in the trace.
+    ///
+    /// Traces that merge batches in the background (e.g.,
+    /// [`Spine`](`spine_fueled::Spine`)) accumulate merge debt when updates
+    /// arrive faster than merges complete.  Operators, schedulers, and
+    /// metrics can use this gauge to decide when to call [`Trace::exert`]
+    /// proactively, e.g., during idle steps.  Traces that do not perform
+    /// background maintenance have no debt.
+    fn maintenance_debt(&self) -> MaintenanceDebt {
+        MaintenanceDebt::default()
+    }
+
+    /// Writes all `(key, val, time, weight)` tuples in the trace to `writer`
+    /// in `format`.
+    ///
+    /// Meant for inspecting the state of an operator, e.g., the output of
+    /// [`Stream::integrate_trace`](`crate::circuit::Stream::integrate_trace`),
+    /// while debugging.  Tuples are written in key order, one line per
+    /// tuple.  Since a trace consists of multiple batches, the same
+    /// key/value pair may occur more than once.
+    fn dump<W>(&self, writer: W, format: DumpFormat) -> io::Result<()>
+    where
+        Self::Key: Debug,
+        Self::Val: Debug,
+        Self::Time: Debug,
+        Self::R: Debug,
+        W: Write,
+    {
+        self.dump_filtered(writer, format, |_| true)
+    }
+
+    /// Like [`Self::dump`], but only writes tuples whose keys satisfy
+    /// `filter`.
+    fn dump_filtered<W, F>(&self, writer: W, format: DumpFormat, filter: F) -> io::Result<()>
+    where
+        Self::Key: Debug,
+        Self::Val: Debug,
+        Self::Time: Debug,
+        Self::R: Debug,
+        W: Write,
+        F: Fn(&Self::Key) -> bool,
+    {
+        dump::dump(self, writer, format, filter)
+    }
 }
 
 /// An append-only collection of `(key, val, time, diff)` tuples.
@@ -107,6 +226,24 @@ pub trait Trace: TraceReader {
     /// complain, to the extent that it cares about contiguous intervals.
     fn insert(&mut self, batch: Self::Batch);
 
+    /// Drops tuples that will never be queried again.
+    ///
+    /// Registers a predicate that the trace applies to the output of each
+    /// merge, discarding updates whose key and value do not satisfy it.  This
+    /// allows applications to garbage collect data based on domain knowledge,
+    /// e.g., to drop expired sessions, complementing frontier-based
+    /// compaction.  The predicate is applied lazily: updates remain visible
+    /// until the batch containing them participates in a merge.  Replaces the
+    /// previously registered predicate, if any.
+    fn retain<F>(&mut self, retain: F)
+    where
+        F: Fn(&Self::Key, &Self::Val) -> bool + Send + Sync + 'static;
+
+    /// Sets the merge effort of the trace (see [`EffortConfig`]).
+    ///
+    /// Traces created with [`Self::new`] use the default effort.
+    fn configure_effort(&mut self, config: &EffortConfig);
+
     /// Clears the value of the "dirty" flag to `false`.
     ///
     /// The "dirty" flag is used to efficiently track changes to the trace,
@@ -128,18 +265,22 @@ pub trait Trace: TraceReader {
 /// useful for views derived from other sources in ways that prevent the
 /// construction of batches from the type of data in the view (for example,
 /// filtered views, or views with extended time coordinates).
+///
+/// Keys, values, timestamps and weights must be `Send`, which allows batch
+/// builders to sort large batches on multiple threads (see the `with-rayon`
+/// feature).
 pub trait BatchReader
 where
     Self: Sized,
 {
     /// Key by which updates are indexed.
-    type Key;
+    type Key: Send;
     /// Values associated with keys.
-    type Val;
+    type Val: Send;
     /// Timestamps associated with updates
-    type Time: Timestamp + Lattice;
+    type Time: Timestamp + Lattice + Send;
     /// Associated update.
-    type R: MonoidValue;
+    type R: MonoidValue + Send;
 
     /// The type used to enumerate the batch's contents.
     type Cursor: Cursor<Self::Key, Self::Val, Self::Time, Self::R, Storage = Self>;
@@ -149,11 +290,32 @@ where
 
     /// The number of updates in the batch.
     fn len(&self) -> usize;
+    /// The number of keys and updates in the batch.
+    ///
+    /// The default implementation uses `len()` as an upper bound on the
+    /// number of keys.
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::new(self.len(), self.len())
+    }
     /// True if the batch is empty.
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
+    /// Looks up all updates for each key in `keys`.
+    ///
+    /// `keys` must be sorted in ascending order.  Returns an iterator over
+    /// `(key, val, time, weight)` tuples of updates whose keys occur in
+    /// `keys`, visiting the batch in a single forward pass instead of
+    /// searching for each key separately.
+    fn lookup_many<'a, I>(&'a self, keys: I) -> LookupMany<'a, Self, I::IntoIter>
+    where
+        I: IntoIterator<Item = &'a Self::Key>,
+        Self::Key: PartialEq,
+    {
+        LookupMany::new(self, keys.into_iter())
+    }
+
     /// All times in the batch are greater or equal to an element of `lower`.
     fn lower(&self) -> &Antichain<Self::Time>;
     /// All times in the batch are not greater or equal to any element of
@@ -162,7 +324,11 @@ where
 }
 
 /// An immutable collection of updates.
-pub trait Batch: BatchReader
+///
+/// Batches are usually shared behind `Rc` or `Arc`, e.g., by a trace and its
+/// snapshots.  `Clone` allows such shared batches to be copied on write when
+/// the trace needs to modify them (see [`Self::retain`]).
+pub trait Batch: BatchReader + Clone
 where
     Self: Sized,
 {
@@ -210,6 +376,31 @@ where
     /// Modifies all timestamps `t` that are not less than or equal to
     /// `frontier` to `t.meet(frontier)`.  See [`Trace::recede_to`].
     fn recede_to(&mut self, frontier: &Self::Time);
+
+    /// Removes all updates whose key and value do not satisfy `retain`.
+    ///
+    /// Reference-counted batches that are shared with other owners are
+    /// copied first, leaving the other references unchanged.  See
+    /// [`Trace::retain`].
+    fn retain(&mut self, retain: &dyn Fn(&Self::Key, &Self::Val) -> bool);
+
+    /// Like [`Self::retain`], but only modifies the batch if it can be done
+    /// without affecting other references to the batch.
+    ///
+    /// Returns `false` and leaves the batch unchanged if the batch is shared,
+    /// e.g., a reference-counted batch with more than one reference.
+    fn try_retain(&mut self, retain: &dyn Fn(&Self::Key, &Self::Val) -> bool) -> bool {
+        self.retain(retain);
+        true
+    }
+
+    /// Applies `map` to all keys in the batch, reusing the batch's storage.
+    ///
+    /// Returns `false` and leaves the batch unchanged if the batch type does
+    /// not support mapping keys in place or if the batch is shared.
+    fn map_keys_in_place(&mut self, _map: &dyn Fn(Self::Key) -> Self::Key) -> bool {
+        false
+    }
 }
 
 /// Functionality for collecting and batching updates.
@@ -223,6 +414,18 @@ pub trait Batcher<K, V, T, R, Output: Batch<Key = K, Val = V, Time = T, R = R>>
     fn tuples(&self) -> usize;
     /// Returns all updates not greater or equal to an element of `upper`.
     fn seal(self) -> Output;
+    /// Like [`Self::seal`], but leaves `self` empty and ready to accept
+    /// updates with timestamp `time`.
+    ///
+    /// Batchers that sort updates in scratch buffers keep these buffers for
+    /// reuse, so that an operator that owns a batcher and seals it at every
+    /// clock cycle does not reallocate them at each cycle.
+    fn seal_reuse(&mut self, time: T) -> Output
+    where
+        Self: Sized,
+    {
+        replace(self, Self::new(time)).seal()
+    }
 }
 
 /// Functionality for building batches from ordered update sequences.
@@ -233,6 +436,15 @@ pub trait Builder<K, V, T, R, Output: Batch<Key = K, Val = V, Time = T, R = R>>
     /// Allocates an empty builder with some capacity.  All tuples in the
     /// builder (and its output batch) will have timestamp `time`.
     fn with_capacity(time: T, cap: usize) -> Self;
+    /// Allocates an empty builder with capacity for the number of keys and
+    /// updates specified by `hint`.  All tuples in the builder (and its
+    /// output batch) will have timestamp `time`.
+    fn with_size_hint(time: T, hint: SizeHint) -> Self
+    where
+        Self: Sized,
+    {
+        Self::with_capacity(time, hint.tuples)
+    }
     /// Adds an element to the batch.
     fn push(&mut self, element: (K, V, R));
     /// Adds an ordered sequence of elements to the batch.
@@ -263,194 +475,267 @@ pub trait Merger<K, V, T, R, Output: Batch<Key = K, Val = V, Time = T, R = R>> {
     fn done(self) -> Output;
 }
 
-/// Blanket implementations for reference counted batches.
-pub mod rc_blanket_impls {
-
-    use std::{marker::PhantomData, rc::Rc};
-
-    use super::{Batch, BatchReader, Batcher, Builder, Cursor, Merger};
-    use timely::progress::Antichain;
-
-    impl<B: BatchReader> BatchReader for Rc<B> {
-        type Key = B::Key;
-        type Val = B::Val;
-        type Time = B::Time;
-        type R = B::R;
+/// Implements batch traits for a reference-counting pointer type
+/// `std::$module::$ptr` (`Rc` or `Arc`) wrapping a batch, delegating to the
+/// wrapped batch.
+///
+/// `$desc` describes the pointer type in generated doc comments.
+macro_rules! pointer_blanket_impls {
+    ($module:ident :: $ptr:ident, $desc:literal, $cursor:ident, $batcher:ident, $builder:ident, $merger:ident) => {
+        use std::{marker::PhantomData, $module::$ptr};
+
+        use super::{Batch, BatchReader, Batcher, Builder, Cursor, Merger, SizeHint};
+        use timely::progress::Antichain;
+
+        impl<B: BatchReader> BatchReader for $ptr<B> {
+            type Key = B::Key;
+            type Val = B::Val;
+            type Time = B::Time;
+            type R = B::R;
+
+            /// The type used to enumerate the batch's contents.
+            type Cursor = $cursor<B>;
+            /// Acquires a cursor to the batch's contents.
+            fn cursor(&self) -> Self::Cursor {
+                $cursor::new((&**self).cursor())
+            }
 
-        /// The type used to enumerate the batch's contents.
-        type Cursor = RcBatchCursor<B>;
-        /// Acquires a cursor to the batch's contents.
-        fn cursor(&self) -> Self::Cursor {
-            RcBatchCursor::new((&**self).cursor())
+            /// The number of updates in the batch.
+            fn len(&self) -> usize {
+                (&**self).len()
+            }
+            fn size_hint(&self) -> SizeHint {
+                (&**self).size_hint()
+            }
+            fn lower(&self) -> &Antichain<Self::Time> {
+                (&**self).lower()
+            }
+            fn upper(&self) -> &Antichain<Self::Time> {
+                (&**self).upper()
+            }
         }
 
-        /// The number of updates in the batch.
-        fn len(&self) -> usize {
-            (&**self).len()
-        }
-        fn lower(&self) -> &Antichain<Self::Time> {
-            (&**self).lower()
+        /// Wrapper to provide cursor to nested scope.
+        pub struct $cursor<B: BatchReader> {
+            phantom: PhantomData<B>,
+            cursor: B::Cursor,
         }
-        fn upper(&self) -> &Antichain<Self::Time> {
-            (&**self).upper()
-        }
-    }
-
-    /// Wrapper to provide cursor to nested scope.
-    pub struct RcBatchCursor<B: BatchReader> {
-        phantom: PhantomData<B>,
-        cursor: B::Cursor,
-    }
 
-    impl<B: BatchReader> RcBatchCursor<B> {
-        fn new(cursor: B::Cursor) -> Self {
-            RcBatchCursor {
-                cursor,
-                phantom: PhantomData,
+        impl<B: BatchReader> $cursor<B> {
+            fn new(cursor: B::Cursor) -> Self {
+                $cursor {
+                    cursor,
+                    phantom: PhantomData,
+                }
             }
         }
-    }
 
-    impl<B: BatchReader> Cursor<B::Key, B::Val, B::Time, B::R> for RcBatchCursor<B> {
-        type Storage = Rc<B>;
+        impl<B: BatchReader> Cursor<B::Key, B::Val, B::Time, B::R> for $cursor<B> {
+            type Storage = $ptr<B>;
 
-        #[inline]
-        fn key_valid(&self, storage: &Self::Storage) -> bool {
-            self.cursor.key_valid(storage)
-        }
-        #[inline]
-        fn val_valid(&self, storage: &Self::Storage) -> bool {
-            self.cursor.val_valid(storage)
-        }
+            #[inline]
+            fn key_valid(&self, storage: &Self::Storage) -> bool {
+                self.cursor.key_valid(storage)
+            }
+            #[inline]
+            fn val_valid(&self, storage: &Self::Storage) -> bool {
+                self.cursor.val_valid(storage)
+            }
 
-        #[inline]
-        fn key<'a>(&self, storage: &'a Self::Storage) -> &'a B::Key {
-            self.cursor.key(storage)
-        }
-        #[inline]
-        fn val<'a>(&self, storage: &'a Self::Storage) -> &'a B::Val {
-            self.cursor.val(storage)
-        }
+            #[inline]
+            fn key<'a>(&self, storage: &'a Self::Storage) -> &'a B::Key {
+                self.cursor.key(storage)
+            }
+            #[inline]
+            fn val<'a>(&self, storage: &'a Self::Storage) -> &'a B::Val {
+                self.cursor.val(storage)
+            }
 
-        #[inline]
-        fn map_times<L: FnMut(&B::Time, &B::R)>(&mut self, storage: &Self::Storage, logic: L) {
-            self.cursor.map_times(storage, logic)
-        }
+            #[inline]
+            fn map_times<L: FnMut(&B::Time, &B::R)>(&mut self, storage: &Self::Storage, logic: L) {
+                self.cursor.map_times(storage, logic)
+            }
 
-        #[inline]
-        fn weight(&mut self, storage: &Self::Storage) -> B::R
-        where
-            B::Time: PartialEq<()>,
-        {
-            self.cursor.weight(storage)
-        }
+            #[inline]
+            fn weight(&mut self, storage: &Self::Storage) -> B::R
+            where
+                B::Time: PartialEq<()>,
+            {
+                self.cursor.weight(storage)
+            }
 
-        #[inline]
-        fn step_key(&mut self, storage: &Self::Storage) {
-            self.cursor.step_key(storage)
-        }
-        #[inline]
-        fn seek_key(&mut self, storage: &Self::Storage, key: &B::Key) {
-            self.cursor.seek_key(storage, key)
-        }
+            #[inline]
+            fn step_key(&mut self, storage: &Self::Storage) {
+                self.cursor.step_key(storage)
+            }
+            #[inline]
+            fn seek_key(&mut self, storage: &Self::Storage, key: &B::Key) {
+                self.cursor.seek_key(storage, key)
+            }
+            #[inline]
+            fn seek_key_with<P>(&mut self, storage: &Self::Storage, predicate: P)
+            where
+                P: Fn(&B::Key) -> bool,
+            {
+                self.cursor.seek_key_with(storage, predicate)
+            }
 
-        #[inline]
-        fn step_val(&mut self, storage: &Self::Storage) {
-            self.cursor.step_val(storage)
-        }
-        #[inline]
-        fn seek_val(&mut self, storage: &Self::Storage, val: &B::Val) {
-            self.cursor.seek_val(storage, val)
-        }
+            #[inline]
+            fn step_val(&mut self, storage: &Self::Storage) {
+                self.cursor.step_val(storage)
+            }
+            #[inline]
+            fn seek_val(&mut self, storage: &Self::Storage, val: &B::Val) {
+                self.cursor.seek_val(storage, val)
+            }
 
-        #[inline]
-        fn rewind_keys(&mut self, storage: &Self::Storage) {
-            self.cursor.rewind_keys(storage)
-        }
-        #[inline]
-        fn rewind_vals(&mut self, storage: &Self::Storage) {
-            self.cursor.rewind_vals(storage)
+            #[inline]
+            fn rewind_keys(&mut self, storage: &Self::Storage) {
+                self.cursor.rewind_keys(storage)
+            }
+            #[inline]
+            fn rewind_vals(&mut self, storage: &Self::Storage) {
+                self.cursor.rewind_vals(storage)
+            }
         }
-    }
 
-    /// An immutable collection of updates.
-    impl<B: Batch> Batch for Rc<B> {
-        type Batcher = RcBatcher<B>;
-        type Builder = RcBuilder<B>;
-        type Merger = RcMerger<B>;
+        /// An immutable collection of updates.
+        impl<B: Batch> Batch for $ptr<B> {
+            type Batcher = $batcher<B>;
+            type Builder = $builder<B>;
+            type Merger = $merger<B>;
 
-        fn recede_to(&mut self, frontier: &B::Time) {
-            Rc::get_mut(self).unwrap().recede_to(frontier);
-        }
-    }
+            // Copies the batch if it is shared.
+            fn recede_to(&mut self, frontier: &B::Time) {
+                $ptr::make_mut(self).recede_to(frontier);
+            }
 
-    /// Wrapper type for batching reference counted batches.
-    pub struct RcBatcher<B: Batch> {
-        batcher: B::Batcher,
-    }
+            // Copies the batch if it is shared.
+            fn retain(&mut self, retain: &dyn Fn(&B::Key, &B::Val) -> bool) {
+                $ptr::make_mut(self).retain(retain);
+            }
 
-    /// Functionality for collecting and batching updates.
-    impl<B: Batch> Batcher<B::Key, B::Val, B::Time, B::R, Rc<B>> for RcBatcher<B> {
-        fn new(time: B::Time) -> Self {
-            RcBatcher {
-                batcher: <B::Batcher as Batcher<B::Key, B::Val, B::Time, B::R, B>>::new(time),
+            fn try_retain(&mut self, retain: &dyn Fn(&B::Key, &B::Val) -> bool) -> bool {
+                $ptr::get_mut(self)
+                    .map(|batch| batch.try_retain(retain))
+                    .unwrap_or(false)
+            }
+
+            fn map_keys_in_place(&mut self, map: &dyn Fn(B::Key) -> B::Key) -> bool {
+                $ptr::get_mut(self)
+                    .map(|batch| batch.map_keys_in_place(map))
+                    .unwrap_or(false)
             }
         }
-        fn push_batch(&mut self, batch: &mut Vec<((B::Key, B::Val), B::R)>) {
-            self.batcher.push_batch(batch)
-        }
-        fn tuples(&self) -> usize {
-            self.batcher.tuples()
-        }
-        fn seal(self) -> Rc<B> {
-            Rc::new(self.batcher.seal())
-        }
-    }
 
-    /// Wrapper type for building reference counted batches.
-    pub struct RcBuilder<B: Batch> {
-        builder: B::Builder,
-    }
+        #[doc = concat!("Wrapper type for batching ", $desc, " batches.")]
+        pub struct $batcher<B: Batch> {
+            batcher: B::Batcher,
+        }
 
-    /// Functionality for building batches from ordered update sequences.
-    impl<B: Batch> Builder<B::Key, B::Val, B::Time, B::R, Rc<B>> for RcBuilder<B> {
-        fn new(time: B::Time) -> Self {
-            RcBuilder {
-                builder: <B::Builder as Builder<B::Key, B::Val, B::Time, B::R, B>>::new(time),
+        /// Functionality for collecting and batching updates.
+        impl<B: Batch> Batcher<B::Key, B::Val, B::Time, B::R, $ptr<B>> for $batcher<B> {
+            fn new(time: B::Time) -> Self {
+                $batcher {
+                    batcher: <B::Batcher as Batcher<B::Key, B::Val, B::Time, B::R, B>>::new(time),
+                }
             }
-        }
-        fn with_capacity(time: B::Time, cap: usize) -> Self {
-            RcBuilder {
-                builder: <B::Builder as Builder<B::Key, B::Val, B::Time, B::R, B>>::with_capacity(
-                    time, cap,
-                ),
+            fn push_batch(&mut self, batch: &mut Vec<((B::Key, B::Val), B::R)>) {
+                self.batcher.push_batch(batch)
+            }
+            fn tuples(&self) -> usize {
+                self.batcher.tuples()
+            }
+            fn seal(self) -> $ptr<B> {
+                $ptr::new(self.batcher.seal())
+            }
+            fn seal_reuse(&mut self, time: B::Time) -> $ptr<B> {
+                $ptr::new(self.batcher.seal_reuse(time))
             }
         }
-        fn push(&mut self, element: (B::Key, B::Val, B::R)) {
-            self.builder.push(element)
-        }
-        fn done(self) -> Rc<B> {
-            Rc::new(self.builder.done())
-        }
-    }
 
-    /// Wrapper type for merging reference counted batches.
-    pub struct RcMerger<B: Batch> {
-        merger: B::Merger,
-    }
+        #[doc = concat!("Wrapper type for building ", $desc, " batches.")]
+        pub struct $builder<B: Batch> {
+            builder: B::Builder,
+        }
 
-    /// Represents a merge in progress.
-    impl<B: Batch> Merger<B::Key, B::Val, B::Time, B::R, Rc<B>> for RcMerger<B> {
-        fn new(source1: &Rc<B>, source2: &Rc<B>) -> Self {
-            RcMerger {
-                merger: B::begin_merge(source1, source2),
+        /// Functionality for building batches from ordered update sequences.
+        impl<B: Batch> Builder<B::Key, B::Val, B::Time, B::R, $ptr<B>> for $builder<B> {
+            fn new(time: B::Time) -> Self {
+                $builder {
+                    builder: <B::Builder as Builder<B::Key, B::Val, B::Time, B::R, B>>::new(time),
+                }
+            }
+            fn with_capacity(time: B::Time, cap: usize) -> Self {
+                $builder {
+                    builder:
+                        <B::Builder as Builder<B::Key, B::Val, B::Time, B::R, B>>::with_capacity(
+                            time, cap,
+                        ),
+                }
+            }
+            fn with_size_hint(time: B::Time, hint: SizeHint) -> Self {
+                $builder {
+                    builder:
+                        <B::Builder as Builder<B::Key, B::Val, B::Time, B::R, B>>::with_size_hint(
+                            time, hint,
+                        ),
+                }
+            }
+            fn push(&mut self, element: (B::Key, B::Val, B::R)) {
+                self.builder.push(element)
+            }
+            fn done(self) -> $ptr<B> {
+                $ptr::new(self.builder.done())
             }
         }
-        fn work(&mut self, source1: &Rc<B>, source2: &Rc<B>, fuel: &mut isize) {
-            self.merger.work(source1, source2, fuel)
+
+        #[doc = concat!("Wrapper type for merging ", $desc, " batches.")]
+        pub struct $merger<B: Batch> {
+            merger: B::Merger,
         }
-        fn done(self) -> Rc<B> {
-            Rc::new(self.merger.done())
+
+        /// Represents a merge in progress.
+        impl<B: Batch> Merger<B::Key, B::Val, B::Time, B::R, $ptr<B>> for $merger<B> {
+            fn new(source1: &$ptr<B>, source2: &$ptr<B>) -> Self {
+                $merger {
+                    merger: B::begin_merge(source1, source2),
+                }
+            }
+            fn work(&mut self, source1: &$ptr<B>, source2: &$ptr<B>, fuel: &mut isize) {
+                self.merger.work(source1, source2, fuel)
+            }
+            fn done(self) -> $ptr<B> {
+                $ptr::new(self.merger.done())
+            }
         }
-    }
+    };
+}
+
+/// Blanket implementations for reference counted batches.
+pub mod rc_blanket_impls {
+    pointer_blanket_impls!(
+        rc::Rc,
+        "reference counted",
+        RcBatchCursor,
+        RcBatcher,
+        RcBuilder,
+        RcMerger
+    );
+}
+
+/// Blanket implementations for atomically reference counted batches.
+///
+/// Unlike `Rc`-wrapped batches, `Arc`-wrapped batches can be shared across
+/// threads, e.g., handed to background merge threads or snapshot readers,
+/// provided that the wrapped batch type is `Send + Sync`.
+pub mod arc_blanket_impls {
+    pointer_blanket_impls!(
+        sync::Arc,
+        "atomically reference counted",
+        ArcBatchCursor,
+        ArcBatcher,
+        ArcBuilder,
+        ArcMerger
+    );
 }
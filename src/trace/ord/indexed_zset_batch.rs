@@ -5,6 +5,7 @@ use std::{
     marker::PhantomData,
     ops::{Add, AddAssign, Neg},
     rc::Rc,
+    sync::Arc,
 };
 
 use timely::progress::Antichain;
@@ -19,7 +20,7 @@ use crate::{
             Builder as TrieBuilder, Cursor as TrieCursor, MergeBuilder, Trie, TupleBuilder,
         },
         ord::merge_batcher::MergeBatcher,
-        Batch, BatchReader, Builder, Cursor, Merger,
+        Batch, BatchReader, Builder, Cursor, Merger, SizeHint,
     },
     NumEntries, SharedRef,
 };
@@ -31,8 +32,8 @@ use deepsize::DeepSizeOf;
 pub struct OrdIndexedZSet<K, V, R, O = usize>
 where
     K: Ord,
-    V: Ord,
-    R: Clone,
+    V: Ord + Send,
+    R: Clone + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -45,9 +46,9 @@ where
 
 impl<K, V, R, O> HasZero for OrdIndexedZSet<K, V, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -63,9 +64,9 @@ where
 
 impl<K, V, R, O> SharedRef for OrdIndexedZSet<K, V, R, O>
 where
-    K: Ord + Clone,
-    V: Ord + Clone,
-    R: Clone,
+    K: Ord + Clone + Send,
+    V: Ord + Clone + Send,
+    R: Clone + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -79,9 +80,9 @@ where
 
 impl<K, V, R, O> From<OrderedLayer<K, OrderedLeaf<V, R>, O>> for OrdIndexedZSet<K, V, R, O>
 where
-    K: Ord,
-    V: Ord,
-    R: Clone,
+    K: Ord + Send,
+    V: Ord + Send,
+    R: Clone + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -97,9 +98,9 @@ where
 
 impl<K, V, R, O> From<OrderedLayer<K, OrderedLeaf<V, R>, O>> for Rc<OrdIndexedZSet<K, V, R, O>>
 where
-    K: Ord,
-    V: Ord,
-    R: Clone,
+    K: Ord + Send,
+    V: Ord + Send,
+    R: Clone + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -111,9 +112,9 @@ where
 
 impl<K, V, R, O> TryFrom<Rc<OrdIndexedZSet<K, V, R, O>>> for OrdIndexedZSet<K, V, R, O>
 where
-    K: Ord,
-    V: Ord,
-    R: Clone,
+    K: Ord + Send,
+    V: Ord + Send,
+    R: Clone + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -125,11 +126,41 @@ where
     }
 }
 
+impl<K, V, R, O> From<OrderedLayer<K, OrderedLeaf<V, R>, O>> for Arc<OrdIndexedZSet<K, V, R, O>>
+where
+    K: Ord + Send,
+    V: Ord + Send,
+    R: Clone + Send,
+    O: OrdOffset,
+    <O as TryFrom<usize>>::Error: Debug,
+    <O as TryInto<usize>>::Error: Debug,
+{
+    fn from(layer: OrderedLayer<K, OrderedLeaf<V, R>, O>) -> Self {
+        Arc::new(From::from(layer))
+    }
+}
+
+impl<K, V, R, O> TryFrom<Arc<OrdIndexedZSet<K, V, R, O>>> for OrdIndexedZSet<K, V, R, O>
+where
+    K: Ord + Send,
+    V: Ord + Send,
+    R: Clone + Send,
+    O: OrdOffset,
+    <O as TryFrom<usize>>::Error: Debug,
+    <O as TryInto<usize>>::Error: Debug,
+{
+    type Error = Arc<OrdIndexedZSet<K, V, R, O>>;
+
+    fn try_from(batch: Arc<OrdIndexedZSet<K, V, R, O>>) -> Result<Self, Self::Error> {
+        Arc::try_unwrap(batch)
+    }
+}
+
 impl<K, V, R, O> DeepSizeOf for OrdIndexedZSet<K, V, R, O>
 where
-    K: DeepSizeOf + Ord,
-    V: DeepSizeOf + Ord,
-    R: DeepSizeOf + Clone,
+    K: DeepSizeOf + Ord + Send,
+    V: DeepSizeOf + Ord + Send,
+    R: DeepSizeOf + Clone + Send,
     O: DeepSizeOf + OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -141,9 +172,9 @@ where
 
 impl<K, V, R, O> NumEntries for OrdIndexedZSet<K, V, R, O>
 where
-    K: Clone + Ord,
-    V: Clone + Ord,
-    R: Eq + HasZero + AddAssignByRef + Clone,
+    K: Clone + Ord + Send,
+    V: Clone + Ord + Send,
+    R: Eq + HasZero + AddAssignByRef + Clone + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -162,9 +193,9 @@ where
 
 impl<K, V, R, O> NegByRef for OrdIndexedZSet<K, V, R, O>
 where
-    K: Ord + Clone,
-    V: Ord + Clone,
-    R: MonoidValue + NegByRef,
+    K: Ord + Clone + Send,
+    V: Ord + Clone + Send,
+    R: MonoidValue + NegByRef + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -180,9 +211,9 @@ where
 
 impl<K, V, R, O> Neg for OrdIndexedZSet<K, V, R, O>
 where
-    K: Ord + Clone,
-    V: Ord + Clone,
-    R: MonoidValue + Neg<Output = R>,
+    K: Ord + Clone + Send,
+    V: Ord + Clone + Send,
+    R: MonoidValue + NegByRef + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -201,9 +232,9 @@ where
 // TODO: by-value merge
 impl<K, V, R, O> Add<Self> for OrdIndexedZSet<K, V, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -224,9 +255,9 @@ where
 
 impl<K, V, R, O> AddAssign<Self> for OrdIndexedZSet<K, V, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -240,9 +271,9 @@ where
 
 impl<K, V, R, O> AddAssignByRef for OrdIndexedZSet<K, V, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -256,9 +287,9 @@ where
 
 impl<K, V, R, O> AddByRef for OrdIndexedZSet<K, V, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -274,9 +305,9 @@ where
 
 impl<K, V, R, O> BatchReader for OrdIndexedZSet<K, V, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -296,6 +327,9 @@ where
     fn len(&self) -> usize {
         <OrderedLayer<K, OrderedLeaf<V, R>, O> as Trie>::tuples(&self.layer)
     }
+    fn size_hint(&self) -> SizeHint {
+        self.layer.size_hint()
+    }
     fn lower(&self) -> &Antichain<()> {
         &self.lower
     }
@@ -306,9 +340,9 @@ where
 
 impl<K, V, R, O> Batch for OrdIndexedZSet<K, V, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -322,14 +356,19 @@ where
     }
 
     fn recede_to(&mut self, _frontier: &()) {}
+
+    fn retain(&mut self, retain: &dyn Fn(&K, &V) -> bool) {
+        self.layer
+            .retain_children(|key, vals, index| retain(key, &vals.vals[index].0));
+    }
 }
 
 /// State for an in-progress merge.
 pub struct OrdIndexedZSetMerger<K, V, R, O>
 where
     K: Ord + Clone + 'static,
-    V: Ord + Clone,
-    R: MonoidValue,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -341,9 +380,9 @@ where
 impl<K, V, R, O> Merger<K, V, (), R, OrdIndexedZSet<K, V, R, O>>
     for OrdIndexedZSetMerger<K, V, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -379,8 +418,8 @@ where
 pub struct OrdIndexedZSetCursor<K, V, R, O>
 where
     K: Ord + Clone,
-    V: Ord + Clone,
-    R: MonoidValue,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
 {
     cursor: OrderedCursor<OrderedLeaf<V, R>>,
     _phantom: PhantomData<(K, O)>,
@@ -388,9 +427,9 @@ where
 
 impl<K, V, R, O> Cursor<K, V, (), R> for OrdIndexedZSetCursor<K, V, R, O>
 where
-    K: Ord + Clone,
-    V: Ord + Clone,
-    R: MonoidValue,
+    K: Ord + Clone + Send,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -425,6 +464,12 @@ where
     fn seek_key(&mut self, storage: &Self::Storage, key: &K) {
         self.cursor.seek(&storage.layer, key);
     }
+    fn seek_key_with<P>(&mut self, storage: &Self::Storage, predicate: P)
+    where
+        P: Fn(&K) -> bool,
+    {
+        self.cursor.seek_with(&storage.layer, predicate);
+    }
     fn step_val(&mut self, storage: &Self::Storage) {
         self.cursor.child.step(&storage.layer.vals);
     }
@@ -443,8 +488,8 @@ where
 pub struct OrdIndexedZSetBuilder<K, V, R, O>
 where
     K: Ord,
-    V: Ord,
-    R: MonoidValue,
+    V: Ord + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -455,9 +500,9 @@ where
 impl<K, V, R, O> Builder<K, V, (), R, OrdIndexedZSet<K, V, R, O>>
     for OrdIndexedZSetBuilder<K, V, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -475,6 +520,15 @@ where
         }
     }
 
+    fn with_size_hint(_time: (), hint: SizeHint) -> Self {
+        OrdIndexedZSetBuilder {
+            builder:
+                <OrderedBuilder<K, OrderedLeafBuilder<V, R>, O> as TupleBuilder>::with_size_hint(
+                    hint,
+                ),
+        }
+    }
+
     #[inline]
     fn push(&mut self, (key, val, diff): (K, V, R)) {
         self.builder.push_tuple((key, (val, diff)));
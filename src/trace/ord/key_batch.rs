@@ -16,7 +16,7 @@ use crate::{
             Builder as TrieBuilder, Cursor as TrieCursor, MergeBuilder, Trie, TupleBuilder,
         },
         ord::merge_batcher::MergeBatcher,
-        Batch, BatchReader, Builder, Cursor, Merger,
+        Batch, BatchReader, Builder, Cursor, Merger, SizeHint,
     },
     Timestamp,
 };
@@ -25,11 +25,11 @@ use deepsize::DeepSizeOf;
 
 /// An immutable collection of update tuples, from a contiguous interval of
 /// logical times.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OrdKeyBatch<K, T, R, O = usize>
 where
     K: Ord,
-    T: Lattice,
+    T: Lattice + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -43,8 +43,8 @@ where
 impl<K, T, R, O> DeepSizeOf for OrdKeyBatch<K, T, R, O>
 where
     K: DeepSizeOf + Ord,
-    T: DeepSizeOf + Lattice,
-    R: DeepSizeOf,
+    T: DeepSizeOf + Lattice + Send,
+    R: DeepSizeOf + Send,
     O: DeepSizeOf + OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -56,9 +56,9 @@ where
 
 impl<K, T, R, O> BatchReader for OrdKeyBatch<K, T, R, O>
 where
-    K: Ord + Clone + 'static,
-    T: Timestamp + Lattice,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    T: Timestamp + Lattice + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -79,6 +79,9 @@ where
     fn len(&self) -> usize {
         <OrderedLayer<K, OrderedLeaf<T, R>, O> as Trie>::tuples(&self.layer)
     }
+    fn size_hint(&self) -> SizeHint {
+        self.layer.size_hint()
+    }
     fn lower(&self) -> &Antichain<T> {
         &self.lower
     }
@@ -89,9 +92,9 @@ where
 
 impl<K, T, R, O> Batch for OrdKeyBatch<K, T, R, O>
 where
-    K: Ord + Clone + 'static,
-    T: Lattice + Timestamp + Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    T: Lattice + Timestamp + Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -111,13 +114,17 @@ where
             self.do_recede_to(frontier);
         }
     }
+
+    fn retain(&mut self, retain: &dyn Fn(&K, &()) -> bool) {
+        self.layer.retain_children(|key, _, _| retain(key, &()));
+    }
 }
 
 impl<K, T, R, O> OrdKeyBatch<K, T, R, O>
 where
     K: Ord + Clone + 'static,
-    T: Lattice + Ord + Clone + 'static,
-    R: MonoidValue,
+    T: Lattice + Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -143,10 +150,10 @@ where
             // changed.     we will change batch.layer.vals.offs[i] in this
             // iteration, from `write_position`'s     initial value.
 
-            let lower: usize = self.layer.offs[i].try_into().unwrap();
-            let upper: usize = self.layer.offs[i + 1].try_into().unwrap();
+            let lower = self.layer.offs.get(i);
+            let upper = self.layer.offs.get(i + 1);
 
-            self.layer.offs[i] = O::try_from(write_position).unwrap();
+            self.layer.offs.set(i, write_position);
 
             let updates = &mut self.layer.vals.vals[..];
 
@@ -159,13 +166,13 @@ where
             }
         }
         self.layer.vals.vals.truncate(write_position);
-        self.layer.offs[self.layer.keys.len()] = O::try_from(write_position).unwrap();
+        self.layer.offs.set(self.layer.keys.len(), write_position);
 
         // 4. Remove empty keys.
         let mut write_position = 0;
         for i in 0..self.layer.keys.len() {
-            let lower: usize = self.layer.offs[i].try_into().unwrap();
-            let upper: usize = self.layer.offs[i + 1].try_into().unwrap();
+            let lower = self.layer.offs.get(i);
+            let upper = self.layer.offs.get(i + 1);
 
             if lower < upper {
                 self.layer.keys.swap(write_position, i);
@@ -183,8 +190,8 @@ where
 pub struct OrdKeyMerger<K, T, R, O = usize>
 where
     K: Ord + Clone + 'static,
-    T: Lattice + Ord + Clone + 'static,
-    R: MonoidValue,
+    T: Lattice + Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -203,9 +210,9 @@ where
 
 impl<K, T, R, O> Merger<K, (), T, R, OrdKeyBatch<K, T, R, O>> for OrdKeyMerger<K, T, R, O>
 where
-    K: Ord + Clone + 'static,
-    T: Lattice + Timestamp + Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    T: Lattice + Timestamp + Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -303,7 +310,7 @@ where
 
 /// A cursor for navigating a single layer.
 #[derive(Debug)]
-pub struct OrdKeyCursor<T: Lattice + Ord + Clone, R: MonoidValue, O = usize> {
+pub struct OrdKeyCursor<T: Lattice + Ord + Clone + Send, R: MonoidValue + Send, O = usize> {
     valid: bool,
     cursor: OrderedCursor<OrderedLeaf<T, R>>,
     phantom: PhantomData<O>,
@@ -312,8 +319,8 @@ pub struct OrdKeyCursor<T: Lattice + Ord + Clone, R: MonoidValue, O = usize> {
 impl<K, T, R, O> Cursor<K, (), T, R> for OrdKeyCursor<T, R, O>
 where
     K: Ord + Clone,
-    T: Lattice + Ord + Clone,
-    R: MonoidValue,
+    T: Lattice + Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -357,6 +364,13 @@ where
         self.cursor.seek(&storage.layer, key);
         self.valid = true;
     }
+    fn seek_key_with<P>(&mut self, storage: &Self::Storage, predicate: P)
+    where
+        P: Fn(&K) -> bool,
+    {
+        self.cursor.seek_with(&storage.layer, predicate);
+        self.valid = true;
+    }
     fn step_val(&mut self, _storage: &Self::Storage) {
         self.valid = false;
     }
@@ -374,8 +388,8 @@ where
 pub struct OrdKeyBuilder<K, T, R, O = usize>
 where
     K: Ord,
-    T: Ord + Lattice,
-    R: MonoidValue,
+    T: Ord + Lattice + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -386,9 +400,9 @@ where
 
 impl<K, T, R, O> Builder<K, (), T, R, OrdKeyBatch<K, T, R, O>> for OrdKeyBuilder<K, T, R, O>
 where
-    K: Ord + Clone + 'static,
-    T: Lattice + Timestamp + Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    T: Lattice + Timestamp + Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -408,6 +422,16 @@ where
         }
     }
 
+    fn with_size_hint(time: T, hint: SizeHint) -> Self {
+        OrdKeyBuilder {
+            time,
+            builder:
+                <OrderedBuilder<K, OrderedLeafBuilder<T, R>, O> as TupleBuilder>::with_size_hint(
+                    hint,
+                ),
+        }
+    }
+
     #[inline]
     fn push(&mut self, (key, _, diff): (K, (), R)) {
         self.builder.push_tuple((key, (self.time.clone(), diff)));
@@ -3,7 +3,7 @@
 use crate::{
     algebra::MonoidValue,
     lattice::Lattice,
-    trace::{Batch, Batcher, Builder},
+    trace::{Batch, Batcher, Builder, SizeHint},
     Timestamp,
 };
 use deepsize::DeepSizeOf;
@@ -41,10 +41,10 @@ where
 
 impl<K, V, T, R, B> Batcher<K, V, T, R, B> for MergeBatcher<K, V, T, R, B>
 where
-    K: Ord + Clone,
-    V: Ord + Clone,
+    K: Ord + Clone + Send,
+    V: Ord + Clone + Send,
     T: Lattice + Timestamp + Ord + Clone,
-    R: MonoidValue,
+    R: MonoidValue + Send,
     B: Batch<Key = K, Val = V, Time = T, R = R>,
 {
     fn new(time: T) -> Self {
@@ -71,16 +71,48 @@ where
     // or equal to `upper`.
     #[inline(never)]
     fn seal(mut self) -> B {
-        let mut builder = B::Builder::new(self.time.clone());
+        self.build()
+    }
+
+    fn seal_reuse(&mut self, time: T) -> B {
+        let batch = self.build();
+        self.time = time;
+        batch
+    }
+}
 
+impl<K, V, T, R, B> MergeBatcher<K, V, T, R, B>
+where
+    K: Ord + Clone + Send,
+    V: Ord + Clone + Send,
+    T: Lattice + Timestamp + Ord + Clone,
+    R: MonoidValue + Send,
+    B: Batch<Key = K, Val = V, Time = T, R = R>,
+{
+    // Builds a batch from all updates in the batcher, returning the drained
+    // buffers to the sorter's stash.
+    fn build(&mut self) -> B {
         let mut merged = Vec::new();
         self.sorter.finish_into(&mut merged);
 
-        // TODO: Re-use buffer, rather than dropping.
+        // Merged tuples are sorted, so we can count distinct keys in one pass
+        // and size the builder's layers accordingly.
+        let mut hint = SizeHint::default();
+        let mut last_key: Option<&K> = None;
+        for ((key, _), _) in merged.iter().flatten() {
+            if last_key != Some(key) {
+                hint.keys += 1;
+                last_key = Some(key);
+            }
+            hint.tuples += 1;
+        }
+        let mut builder = B::Builder::with_size_hint(self.time.clone(), hint);
+
         for mut buffer in merged.drain(..) {
             for ((key, val), diff) in buffer.drain(..) {
                 builder.push((key, val, diff));
             }
+            self.sorter.recycle(buffer);
         }
 
         builder.done()
@@ -107,17 +139,17 @@ impl<T> VecQueue<T> {
     #[inline]
     pub fn peek(&self) -> &T {
         debug_assert!(self.head < self.tail);
-        unsafe { self.list.get_unchecked(self.head) }
+        unsafe { &*self.list.as_ptr().add(self.head) }
     }
     #[inline]
     pub fn _peek_tail(&self) -> &T {
         debug_assert!(self.head < self.tail);
-        unsafe { self.list.get_unchecked(self.tail - 1) }
+        unsafe { &*self.list.as_ptr().add(self.tail - 1) }
     }
     #[inline]
     pub fn _slice(&self) -> &[T] {
         debug_assert!(self.head < self.tail);
-        unsafe { from_raw_parts(self.list.get_unchecked(self.head), self.tail - self.head) }
+        unsafe { from_raw_parts(self.list.as_ptr().add(self.head), self.tail - self.head) }
     }
     #[inline]
     pub fn from(mut list: Vec<T>) -> Self {
@@ -170,7 +202,7 @@ where
     }
 }
 
-impl<D: Ord, R: MonoidValue> MergeSorter<D, R> {
+impl<D: Ord + Send, R: MonoidValue + Send> MergeSorter<D, R> {
     const BUFFER_SIZE_BYTES: usize = 1 << 13;
 
     fn buffer_size() -> usize {
@@ -192,6 +224,9 @@ impl<D: Ord, R: MonoidValue> MergeSorter<D, R> {
         }
     }
 
+    /// Maximal number of empty buffers kept in the stash by [`Self::recycle`].
+    const MAX_RECYCLED: usize = 64;
+
     #[inline]
     pub fn empty(&mut self) -> Vec<(D, R)> {
         self.stash
@@ -199,6 +234,20 @@ impl<D: Ord, R: MonoidValue> MergeSorter<D, R> {
             .unwrap_or_else(|| Vec::with_capacity(Self::buffer_size()))
     }
 
+    /// Returns a drained buffer to the stash, so that it can be reused by
+    /// subsequent sorts.
+    ///
+    /// Only buffers of the standard size are kept, and at most
+    /// [`Self::MAX_RECYCLED`] of them, which bounds the memory retained by an
+    /// idle sorter.
+    #[inline]
+    pub fn recycle(&mut self, mut buffer: Vec<(D, R)>) {
+        if buffer.capacity() == Self::buffer_size() && self.stash.len() < Self::MAX_RECYCLED {
+            buffer.clear();
+            self.stash.push(buffer);
+        }
+    }
+
     #[inline(never)]
     pub fn _sort(&mut self, list: &mut Vec<Vec<(D, R)>>) {
         for mut batch in list.drain(..) {
@@ -219,7 +268,10 @@ impl<D: Ord, R: MonoidValue> MergeSorter<D, R> {
         };
 
         if !batch.is_empty() {
+            #[cfg(not(feature = "with-rayon"))]
             crate::trace::consolidation::consolidate_updates(&mut batch);
+            #[cfg(feature = "with-rayon")]
+            crate::trace::consolidation::par_consolidate(&mut batch);
             self.queue.push(vec![batch]);
             while self.queue.len() > 1
                 && (self.queue[self.queue.len() - 1].len()
@@ -289,7 +341,7 @@ impl<D: Ord, R: MonoidValue> MergeSorter<D, R> {
                         let (data1, mut diff1) = head1.pop();
                         let (_data2, diff2) = head2.pop();
                         diff1.add_assign_by_ref(&diff2);
-                        if !diff1.is_zero() {
+                        if !diff1.is_negligible() {
                             unsafe {
                                 push_unchecked(&mut result, (data1, diff1));
                             }
@@ -14,6 +14,13 @@
 //!   ordered and whose timestamp type is `()`.  Semantically, such collections
 //!   store `(key, weight)` tuples without timing information, and implement the
 //!   ZSet abstraction of DBSP.
+//! * `OrdValRle`: Like `OrdVal`, but stores timestamps as runs of identical
+//!   values, which is more compact when many updates share the same time,
+//!   e.g., in compacted traces of nested scopes.
+//! * `TimeIndexedZSet`: Collections of `(key, time, val)` tuples ordered by
+//!   key and then time, which support efficient scans of updates since a
+//!   given time.  Unlike the other types in this module, `TimeIndexedZSet` is
+//!   not a batch and cannot be stored in a trace.
 //!
 //! Although `OrdVal` is more general than `OrdKey`, the latter has a simpler
 //! representation and should consume fewer resources (computation and memory)
@@ -23,7 +30,7 @@
 //! `OrdKey` respectively, but are more light-weight.
 
 use crate::trace::spine_fueled::Spine;
-use std::rc::Rc;
+use std::{rc::Rc, sync::Arc};
 
 mod merge_batcher;
 
@@ -33,6 +40,13 @@ pub use val_batch::OrdValBatch;
 /// A trace implementation using a spine of ordered lists.
 pub type OrdValSpine<K, V, T, R, O = usize> = Spine<Rc<OrdValBatch<K, V, T, R, O>>>;
 
+pub mod rle_val_batch;
+pub use rle_val_batch::OrdValRleBatch;
+
+/// A trace implementation using a spine of ordered lists with run-length
+/// encoded timestamps.
+pub type OrdValRleSpine<K, V, T, R> = Spine<Rc<OrdValRleBatch<K, V, T, R>>>;
+
 pub mod key_batch;
 pub use key_batch::OrdKeyBatch;
 
@@ -49,3 +63,121 @@ pub mod indexed_zset_batch;
 pub use indexed_zset_batch::OrdIndexedZSet;
 
 pub type OrdIndexedZSetSpine<K, V, R, O = usize> = Spine<Rc<OrdIndexedZSet<K, V, R, O>>>;
+
+pub mod time_indexed_zset;
+pub use time_indexed_zset::TimeIndexedZSet;
+
+/// A trace implementation using a [`Spine`] of [`OrdZSet`] batches that can
+/// be shared across threads.
+pub type OrdZSetArcSpine<K, R> = Spine<Arc<OrdZSet<K, R>>>;
+
+/// A trace implementation using a [`Spine`] of [`OrdIndexedZSet`] batches
+/// that can be shared across threads.
+pub type OrdIndexedZSetArcSpine<K, V, R, O = usize> = Spine<Arc<OrdIndexedZSet<K, V, R, O>>>;
+
+#[cfg(test)]
+mod test {
+    use super::{OrdIndexedZSet, OrdKeyBatch, OrdValBatch, OrdZSet, OrdZSetArcSpine};
+    use crate::{
+        time::NestedTimestamp32,
+        trace::{cursor::Cursor, Batch, BatchReader, Batcher, Trace, TraceReader},
+    };
+    use std::{collections::BTreeMap, sync::Arc, thread};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn batches_are_send_sync() {
+        assert_send_sync::<OrdZSet<String, isize>>();
+        assert_send_sync::<OrdIndexedZSet<String, String, isize>>();
+        assert_send_sync::<OrdKeyBatch<String, NestedTimestamp32, isize>>();
+        assert_send_sync::<OrdValBatch<String, String, NestedTimestamp32, isize>>();
+        assert_send_sync::<Arc<OrdZSet<String, isize>>>();
+    }
+
+    #[test]
+    fn arc_spine() {
+        let mut spine = OrdZSetArcSpine::<u64, isize>::new(None);
+        spine.insert(Arc::new(OrdZSet::from_tuples(
+            (),
+            vec![((1, ()), 1), ((2, ()), 1)],
+        )));
+        spine.insert(Arc::new(OrdZSet::from_tuples(
+            (),
+            vec![((2, ()), 1), ((3, ()), -1)],
+        )));
+
+        // Batches in the spine can be read from another thread.
+        let mut batches = Vec::new();
+        spine.map_batches(|batch| batches.push(batch.clone()));
+        let len = thread::spawn(move || batches.iter().map(|b| b.len()).sum::<usize>())
+            .join()
+            .unwrap();
+        assert_eq!(len, 4);
+
+        let batch = spine.consolidate().unwrap();
+        assert_eq!(batch.len(), 3);
+    }
+
+    #[test]
+    fn batcher_seal_reuse() {
+        let mut batcher = <OrdZSet<u64, isize> as Batch>::Batcher::new(());
+
+        for round in 0..3u64 {
+            for chunk in 0..10u64 {
+                let mut tuples: Vec<((u64, ()), isize)> = (0..1000)
+                    .map(|i| (((i * 31 + chunk) % 500 + round, ()), 1))
+                    .collect();
+                batcher.push_batch(&mut tuples);
+            }
+            let batch = batcher.seal_reuse(());
+            assert_eq!(batch.len(), 500);
+            assert_eq!(batcher.tuples(), 0);
+
+            let mut expected = BTreeMap::new();
+            for chunk in 0..10u64 {
+                for i in 0..1000 {
+                    *expected.entry((i * 31 + chunk) % 500 + round).or_insert(0) += 1;
+                }
+            }
+            let mut cursor = batch.cursor();
+            let mut actual = BTreeMap::new();
+            while cursor.key_valid(&batch) {
+                actual.insert(*cursor.key(&batch), cursor.weight(&batch));
+                cursor.step_key(&batch);
+            }
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[cfg(feature = "with-rayon")]
+    #[test]
+    fn from_tuples_parallel() {
+        let n = 1usize << 17;
+        let tuples: Vec<((usize, ()), isize)> = (0..n)
+            .map(|i| (((i * 7919) % (n / 4), ()), if i % 3 == 0 { -1 } else { 1 }))
+            .collect();
+
+        let batch = OrdZSet::from_tuples_parallel((), tuples.clone());
+        assert_eq!(batch, OrdZSet::from_tuples((), tuples));
+    }
+
+    #[test]
+    fn zset_in_place() {
+        let mut batch = OrdZSet::from_tuples((), vec![((1, ()), 1), ((2, ()), 1), ((3, ()), -1)]);
+
+        // Order-preserving map.
+        batch.map_in_place(|x| x * 10);
+        assert_eq!(
+            batch,
+            OrdZSet::from_tuples((), vec![((10, ()), 1), ((20, ()), 1), ((30, ()), -1)])
+        );
+
+        // Reordering map that merges keys.
+        batch.map_in_place(|x| if x == 30 { 20 } else { 30 - x });
+        assert_eq!(batch, OrdZSet::from_tuples((), vec![((10, ()), 1)]));
+
+        batch.retain_keys(|_, w| *w < 0);
+        assert!(batch.is_empty());
+    }
+}
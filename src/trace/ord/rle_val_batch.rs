@@ -0,0 +1,685 @@
+//! A variant of [`OrdValBatch`](`super::OrdValBatch`) that run-length encodes
+//! timestamps.
+//!
+//! [`OrdValBatch`](`super::OrdValBatch`) stores a `(time, diff)` pair per
+//! update.  In nested scopes, traces are periodically compacted by
+//! [`recede_to`](`Batch::recede_to`), after which most updates in a batch
+//! share one or a few distinct timestamps.  [`OrdValRleBatch`] stores
+//! timestamps separately from weights as a sequence of runs of identical
+//! timestamps over the sorted sequence of updates.  A run may span many
+//! values and keys, so a batch whose updates all share the same timestamp
+//! stores that timestamp only once.
+
+use std::{
+    cmp::Ordering,
+    fmt::{Debug, Display, Formatter},
+    mem::take,
+};
+
+use timely::progress::Antichain;
+
+use crate::{
+    algebra::MonoidValue,
+    lattice::Lattice,
+    trace::{
+        consolidation::consolidate_slice, layers::advance, ord::merge_batcher::MergeBatcher, Batch,
+        BatchReader, Builder, Cursor, Merger, SizeHint,
+    },
+    Timestamp,
+};
+
+use deepsize::DeepSizeOf;
+
+/// An immutable collection of update tuples, from a contiguous interval of
+/// logical times, with run-length encoded timestamps.
+#[derive(Debug, Clone)]
+pub struct OrdValRleBatch<K, V, T, R> {
+    /// Where all the dataz is.
+    pub layer: OrdValRleLayer<K, V, T, R>,
+    pub lower: Antichain<T>,
+    pub upper: Antichain<T>,
+}
+
+/// Columnar storage of an [`OrdValRleBatch`].
+///
+/// Key `i` owns values `key_offs[i]..key_offs[i+1]`, value `j` owns updates
+/// `val_offs[j]..val_offs[j+1]`.  Updates of each value are sorted by time.
+/// Run `r` assigns timestamp `times[r]` to updates
+/// `run_ends[r-1]..run_ends[r]` (with `run_ends[-1] = 0`).
+#[derive(Debug, Clone, DeepSizeOf)]
+pub struct OrdValRleLayer<K, V, T, R> {
+    pub keys: Vec<K>,
+    pub key_offs: Vec<usize>,
+    pub vals: Vec<V>,
+    pub val_offs: Vec<usize>,
+    pub diffs: Vec<R>,
+    pub times: Vec<T>,
+    pub run_ends: Vec<usize>,
+}
+
+impl<K, V, T, R> OrdValRleLayer<K, V, T, R> {
+    fn with_capacity(keys: usize, updates: usize) -> Self {
+        let mut key_offs = Vec::with_capacity(keys + 1);
+        key_offs.push(0);
+        let mut val_offs = Vec::with_capacity(updates + 1);
+        val_offs.push(0);
+
+        Self {
+            keys: Vec::with_capacity(keys),
+            key_offs,
+            vals: Vec::with_capacity(updates),
+            val_offs,
+            diffs: Vec::with_capacity(updates),
+            times: Vec::new(),
+            run_ends: Vec::new(),
+        }
+    }
+
+    /// Index of the run containing update `index`.
+    fn run_of(&self, index: usize) -> usize {
+        advance(&self.run_ends, |end| *end <= index)
+    }
+
+    /// Applies `logic` to the time and weight of each update in
+    /// `lower..upper`.
+    fn map_updates<'a, L>(&'a self, lower: usize, upper: usize, mut logic: L)
+    where
+        L: FnMut(&'a T, &'a R),
+    {
+        if lower == upper {
+            return;
+        }
+
+        let mut run = self.run_of(lower);
+        for index in lower..upper {
+            while self.run_ends[run] <= index {
+                run += 1;
+            }
+            logic(&self.times[run], &self.diffs[index]);
+        }
+    }
+
+    /// Appends an update to the current value.
+    fn push_update(&mut self, time: T, diff: R)
+    where
+        T: PartialEq,
+    {
+        self.diffs.push(diff);
+        match self.times.last() {
+            Some(last) if *last == time => *self.run_ends.last_mut().unwrap() += 1,
+            _ => {
+                self.times.push(time);
+                self.run_ends.push(self.diffs.len());
+            }
+        }
+    }
+
+    /// Closes the current value, discarding it if it has no updates.
+    fn push_val(&mut self, val: V) {
+        if *self.val_offs.last().unwrap() < self.diffs.len() {
+            self.vals.push(val);
+            self.val_offs.push(self.diffs.len());
+        }
+    }
+
+    /// Closes the current key, discarding it if it has no values.
+    fn push_key(&mut self, key: K) {
+        if *self.key_offs.last().unwrap() < self.vals.len() {
+            self.keys.push(key);
+            self.key_offs.push(self.vals.len());
+        }
+    }
+}
+
+impl<K, V, T, R> OrdValRleLayer<K, V, T, R>
+where
+    K: Clone,
+    V: Clone,
+    T: Ord + Clone,
+    R: MonoidValue,
+{
+    /// Copies key `key` of `other` to `self`.
+    fn copy_key(&mut self, other: &Self, key: usize) {
+        for val in other.key_offs[key]..other.key_offs[key + 1] {
+            self.copy_val(other, val);
+        }
+        self.push_key(other.keys[key].clone());
+    }
+
+    /// Copies value `val` of `other` to the current key of `self`.
+    fn copy_val(&mut self, other: &Self, val: usize) {
+        other.map_updates(
+            other.val_offs[val],
+            other.val_offs[val + 1],
+            |time, diff| self.push_update(time.clone(), diff.clone()),
+        );
+        self.push_val(other.vals[val].clone());
+    }
+
+    /// Merges the updates of value `val1` of `source1` and value `val2` of
+    /// `source2` into the current value of `self`.
+    fn merge_updates(&mut self, source1: &Self, val1: usize, source2: &Self, val2: usize) {
+        let mut updates1 = Vec::new();
+        source1.map_updates(
+            source1.val_offs[val1],
+            source1.val_offs[val1 + 1],
+            |t, r| updates1.push((t, r)),
+        );
+        let mut updates2 = Vec::new();
+        source2.map_updates(
+            source2.val_offs[val2],
+            source2.val_offs[val2 + 1],
+            |t, r| updates2.push((t, r)),
+        );
+
+        let (mut i1, mut i2) = (0, 0);
+        while i1 < updates1.len() && i2 < updates2.len() {
+            let (time1, diff1) = updates1[i1];
+            let (time2, diff2) = updates2[i2];
+            match time1.cmp(time2) {
+                Ordering::Less => {
+                    self.push_update(time1.clone(), diff1.clone());
+                    i1 += 1;
+                }
+                Ordering::Equal => {
+                    let mut sum = diff1.clone();
+                    sum.add_assign_by_ref(diff2);
+                    if !sum.is_negligible() {
+                        self.push_update(time1.clone(), sum);
+                    }
+                    i1 += 1;
+                    i2 += 1;
+                }
+                Ordering::Greater => {
+                    self.push_update(time2.clone(), diff2.clone());
+                    i2 += 1;
+                }
+            }
+        }
+        for &(time, diff) in updates1[i1..].iter().chain(updates2[i2..].iter()) {
+            self.push_update(time.clone(), diff.clone());
+        }
+    }
+
+    /// Merges key `key1` of `source1` with key `key2` of `source2`, which
+    /// must be equal.
+    fn merge_key(&mut self, source1: &Self, key1: usize, source2: &Self, key2: usize)
+    where
+        V: Ord,
+    {
+        let (mut val1, upper1) = (source1.key_offs[key1], source1.key_offs[key1 + 1]);
+        let (mut val2, upper2) = (source2.key_offs[key2], source2.key_offs[key2 + 1]);
+
+        while val1 < upper1 && val2 < upper2 {
+            match source1.vals[val1].cmp(&source2.vals[val2]) {
+                Ordering::Less => {
+                    self.copy_val(source1, val1);
+                    val1 += 1;
+                }
+                Ordering::Equal => {
+                    self.merge_updates(source1, val1, source2, val2);
+                    self.push_val(source1.vals[val1].clone());
+                    val1 += 1;
+                    val2 += 1;
+                }
+                Ordering::Greater => {
+                    self.copy_val(source2, val2);
+                    val2 += 1;
+                }
+            }
+        }
+        for val in val1..upper1 {
+            self.copy_val(source1, val);
+        }
+        for val in val2..upper2 {
+            self.copy_val(source2, val);
+        }
+        self.push_key(source1.keys[key1].clone());
+    }
+
+    /// Rebuilds `self`, keeping only values that satisfy `retain` and
+    /// transforming the updates of each value with `updates`.
+    fn rebuild<F, U>(&mut self, retain: F, mut updates: U)
+    where
+        F: Fn(&K, &V) -> bool,
+        U: FnMut(&mut Vec<(T, R)>),
+    {
+        let old = take(self);
+        *self = Self::with_capacity(old.keys.len(), old.diffs.len());
+
+        let mut buffer = Vec::new();
+        for key in 0..old.keys.len() {
+            for val in old.key_offs[key]..old.key_offs[key + 1] {
+                if !retain(&old.keys[key], &old.vals[val]) {
+                    continue;
+                }
+                old.map_updates(old.val_offs[val], old.val_offs[val + 1], |t, r| {
+                    buffer.push((t.clone(), r.clone()))
+                });
+                updates(&mut buffer);
+                for (time, diff) in buffer.drain(..) {
+                    self.push_update(time, diff);
+                }
+                self.push_val(old.vals[val].clone());
+            }
+            self.push_key(old.keys[key].clone());
+        }
+    }
+}
+
+impl<K, V, T, R> Default for OrdValRleLayer<K, V, T, R> {
+    fn default() -> Self {
+        Self::with_capacity(0, 0)
+    }
+}
+
+impl<K, V, T, R> Display for OrdValRleBatch<K, V, T, R>
+where
+    K: Display,
+    V: Display,
+    T: Display + Debug + Send,
+    R: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        writeln!(
+            f,
+            "lower: {:?}, upper: {:?}\nlayer:",
+            self.lower, self.upper
+        )?;
+        let layer = &self.layer;
+        for key in 0..layer.keys.len() {
+            writeln!(f, "    {}:", layer.keys[key])?;
+            for val in layer.key_offs[key]..layer.key_offs[key + 1] {
+                writeln!(f, "        {}:", layer.vals[val])?;
+                let mut result = Ok(());
+                layer.map_updates(layer.val_offs[val], layer.val_offs[val + 1], |t, r| {
+                    if result.is_ok() {
+                        result = writeln!(f, "            {} -> {}", t, r);
+                    }
+                });
+                result?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K, V, T, R> DeepSizeOf for OrdValRleBatch<K, V, T, R>
+where
+    K: DeepSizeOf,
+    V: DeepSizeOf,
+    T: DeepSizeOf + Send,
+    R: DeepSizeOf,
+{
+    fn deep_size_of_children(&self, _context: &mut deepsize::Context) -> usize {
+        self.layer.deep_size_of()
+    }
+}
+
+impl<K, V, T, R> OrdValRleBatch<K, V, T, R> {
+    /// Number of runs of identical timestamps in the batch.
+    pub fn runs(&self) -> usize {
+        self.layer.times.len()
+    }
+}
+
+impl<K, V, T, R> BatchReader for OrdValRleBatch<K, V, T, R>
+where
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send + 'static,
+    T: Timestamp + Lattice + Send,
+    R: MonoidValue + Send,
+{
+    type Key = K;
+    type Val = V;
+    type Time = T;
+    type R = R;
+
+    type Cursor = OrdValRleCursor;
+    fn cursor(&self) -> Self::Cursor {
+        OrdValRleCursor { key: 0, val: 0 }
+    }
+    fn len(&self) -> usize {
+        self.layer.diffs.len()
+    }
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::new(self.layer.keys.len(), self.layer.diffs.len())
+    }
+    fn lower(&self) -> &Antichain<T> {
+        &self.lower
+    }
+    fn upper(&self) -> &Antichain<T> {
+        &self.upper
+    }
+}
+
+impl<K, V, T, R> Batch for OrdValRleBatch<K, V, T, R>
+where
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send + 'static,
+    T: Lattice + Timestamp + Ord + Clone + ::std::fmt::Debug + Send + 'static,
+    R: MonoidValue + Send,
+{
+    type Batcher = MergeBatcher<K, V, T, R, Self>;
+    type Builder = OrdValRleBuilder<K, V, T, R>;
+    type Merger = OrdValRleMerger<K, V, T, R>;
+
+    fn begin_merge(&self, other: &Self) -> Self::Merger {
+        OrdValRleMerger::new(self, other)
+    }
+
+    fn recede_to(&mut self, frontier: &T) {
+        // Nothing to do if the batch is entirely before the frontier.
+        if !self.upper().less_equal(frontier) {
+            self.layer.rebuild(
+                |_, _| true,
+                |updates| {
+                    for (time, _) in updates.iter_mut() {
+                        time.meet_assign(frontier);
+                    }
+                    let count = consolidate_slice(updates);
+                    updates.truncate(count);
+                },
+            );
+        }
+    }
+
+    fn retain(&mut self, retain: &dyn Fn(&K, &V) -> bool) {
+        self.layer.rebuild(retain, |_| {});
+    }
+}
+
+/// State for an in-progress merge.
+pub struct OrdValRleMerger<K, V, T, R> {
+    // first batch, and position therein.
+    lower1: usize,
+    upper1: usize,
+    // second batch, and position therein.
+    lower2: usize,
+    upper2: usize,
+    // result that we are currently assembling.
+    result: OrdValRleLayer<K, V, T, R>,
+    lower: Antichain<T>,
+    upper: Antichain<T>,
+}
+
+impl<K, V, T, R> Merger<K, V, T, R, OrdValRleBatch<K, V, T, R>> for OrdValRleMerger<K, V, T, R>
+where
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send + 'static,
+    T: Lattice + Timestamp + Ord + Clone + ::std::fmt::Debug + Send + 'static,
+    R: MonoidValue + Send,
+{
+    fn new(batch1: &OrdValRleBatch<K, V, T, R>, batch2: &OrdValRleBatch<K, V, T, R>) -> Self {
+        OrdValRleMerger {
+            lower1: 0,
+            upper1: batch1.layer.keys.len(),
+            lower2: 0,
+            upper2: batch2.layer.keys.len(),
+            result: OrdValRleLayer::with_capacity(
+                batch1.layer.keys.len() + batch2.layer.keys.len(),
+                batch1.len() + batch2.len(),
+            ),
+            lower: batch1.lower().meet(batch2.lower()),
+            upper: batch1.upper().join(batch2.upper()),
+        }
+    }
+    fn done(self) -> OrdValRleBatch<K, V, T, R> {
+        assert!(self.lower1 == self.upper1);
+        assert!(self.lower2 == self.upper2);
+
+        OrdValRleBatch {
+            layer: self.result,
+            lower: self.lower,
+            upper: self.upper,
+        }
+    }
+    fn work(
+        &mut self,
+        source1: &OrdValRleBatch<K, V, T, R>,
+        source2: &OrdValRleBatch<K, V, T, R>,
+        fuel: &mut isize,
+    ) {
+        let starting_updates = self.result.diffs.len();
+        let mut effort = 0isize;
+
+        while effort < *fuel {
+            if self.lower1 < self.upper1 && self.lower2 < self.upper2 {
+                match source1.layer.keys[self.lower1].cmp(&source2.layer.keys[self.lower2]) {
+                    Ordering::Less => {
+                        self.result.copy_key(&source1.layer, self.lower1);
+                        self.lower1 += 1;
+                    }
+                    Ordering::Equal => {
+                        self.result.merge_key(
+                            &source1.layer,
+                            self.lower1,
+                            &source2.layer,
+                            self.lower2,
+                        );
+                        self.lower1 += 1;
+                        self.lower2 += 1;
+                    }
+                    Ordering::Greater => {
+                        self.result.copy_key(&source2.layer, self.lower2);
+                        self.lower2 += 1;
+                    }
+                }
+            } else if self.lower1 < self.upper1 {
+                self.result.copy_key(&source1.layer, self.lower1);
+                self.lower1 += 1;
+            } else if self.lower2 < self.upper2 {
+                self.result.copy_key(&source2.layer, self.lower2);
+                self.lower2 += 1;
+            } else {
+                break;
+            }
+            effort = (self.result.diffs.len() - starting_updates) as isize;
+        }
+
+        *fuel -= effort;
+    }
+}
+
+/// A cursor for navigating an [`OrdValRleBatch`].
+#[derive(Debug, Clone)]
+pub struct OrdValRleCursor {
+    key: usize,
+    val: usize,
+}
+
+impl<K, V, T, R> Cursor<K, V, T, R> for OrdValRleCursor
+where
+    K: Ord + Clone,
+    V: Ord + Clone,
+    T: Lattice + Ord + Clone + Send,
+    R: MonoidValue,
+{
+    type Storage = OrdValRleBatch<K, V, T, R>;
+
+    fn key<'a>(&self, storage: &'a Self::Storage) -> &'a K {
+        &storage.layer.keys[self.key]
+    }
+    fn val<'a>(&self, storage: &'a Self::Storage) -> &'a V {
+        &storage.layer.vals[self.val]
+    }
+    fn map_times<L: FnMut(&T, &R)>(&mut self, storage: &Self::Storage, logic: L) {
+        storage.layer.map_updates(
+            storage.layer.val_offs[self.val],
+            storage.layer.val_offs[self.val + 1],
+            logic,
+        );
+    }
+    fn weight(&mut self, storage: &Self::Storage) -> R
+    where
+        T: PartialEq<()>,
+    {
+        debug_assert!(<Self as Cursor<K, V, T, R>>::val_valid(self, storage));
+        storage.layer.diffs[storage.layer.val_offs[self.val]].clone()
+    }
+
+    fn key_valid(&self, storage: &Self::Storage) -> bool {
+        self.key < storage.layer.keys.len()
+    }
+    fn val_valid(&self, storage: &Self::Storage) -> bool {
+        self.key < storage.layer.keys.len() && self.val < storage.layer.key_offs[self.key + 1]
+    }
+    fn step_key(&mut self, storage: &Self::Storage) {
+        self.key += 1;
+        <Self as Cursor<K, V, T, R>>::rewind_vals(self, storage);
+    }
+    fn seek_key(&mut self, storage: &Self::Storage, key: &K) {
+        self.key += advance(&storage.layer.keys[self.key..], |k| k.lt(key));
+        <Self as Cursor<K, V, T, R>>::rewind_vals(self, storage);
+    }
+    fn seek_key_with<P>(&mut self, storage: &Self::Storage, predicate: P)
+    where
+        P: Fn(&K) -> bool,
+    {
+        self.key += advance(&storage.layer.keys[self.key..], |k| !predicate(k));
+        <Self as Cursor<K, V, T, R>>::rewind_vals(self, storage);
+    }
+    fn step_val(&mut self, _storage: &Self::Storage) {
+        self.val += 1;
+    }
+    fn seek_val(&mut self, storage: &Self::Storage, val: &V) {
+        if <Self as Cursor<K, V, T, R>>::key_valid(self, storage) {
+            let upper = storage.layer.key_offs[self.key + 1];
+            self.val += advance(&storage.layer.vals[self.val..upper], |v| v.lt(val));
+        }
+    }
+    fn rewind_keys(&mut self, storage: &Self::Storage) {
+        self.key = 0;
+        <Self as Cursor<K, V, T, R>>::rewind_vals(self, storage);
+    }
+    fn rewind_vals(&mut self, storage: &Self::Storage) {
+        if <Self as Cursor<K, V, T, R>>::key_valid(self, storage) {
+            self.val = storage.layer.key_offs[self.key];
+        }
+    }
+}
+
+/// A builder for creating batches from sorted update tuples.
+pub struct OrdValRleBuilder<K, V, T, R> {
+    time: T,
+    layer: OrdValRleLayer<K, V, T, R>,
+    // Key and value whose updates are currently being pushed.
+    current: Option<(K, V)>,
+}
+
+impl<K, V, T, R> Builder<K, V, T, R, OrdValRleBatch<K, V, T, R>> for OrdValRleBuilder<K, V, T, R>
+where
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send + 'static,
+    T: Lattice + Timestamp + Ord + Clone + ::std::fmt::Debug + Send + 'static,
+    R: MonoidValue + Send,
+{
+    fn new(time: T) -> Self {
+        Self::with_capacity(time, 0)
+    }
+    fn with_capacity(time: T, cap: usize) -> Self {
+        Self::with_size_hint(time, SizeHint::new(cap, cap))
+    }
+    fn with_size_hint(time: T, hint: SizeHint) -> Self {
+        OrdValRleBuilder {
+            time,
+            layer: OrdValRleLayer::with_capacity(hint.keys, hint.tuples),
+            current: None,
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, (key, val, diff): (K, V, R)) {
+        match self.current.take() {
+            Some((current_key, current_val)) if current_key == key && current_val != val => {
+                self.layer.push_val(current_val);
+            }
+            Some((current_key, _)) if current_key == key => {}
+            Some((current_key, current_val)) => {
+                self.layer.push_val(current_val);
+                self.layer.push_key(current_key);
+            }
+            None => {}
+        }
+        self.layer.push_update(self.time.clone(), diff);
+        self.current = Some((key, val));
+    }
+
+    #[inline(never)]
+    fn done(mut self) -> OrdValRleBatch<K, V, T, R> {
+        if let Some((key, val)) = self.current.take() {
+            self.layer.push_val(val);
+            self.layer.push_key(key);
+        }
+
+        let time_next = self.time.advance(0);
+        let upper = if time_next <= self.time {
+            Antichain::new()
+        } else {
+            Antichain::from_elem(time_next)
+        };
+        OrdValRleBatch {
+            layer: self.layer,
+            lower: Antichain::from_elem(self.time),
+            upper,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        time::NestedTimestamp32,
+        trace::{
+            cursor::CursorDebug,
+            ord::{OrdValBatch, OrdValRleBatch},
+            Batch, BatchReader,
+        },
+    };
+
+    fn time(inner: u32) -> NestedTimestamp32 {
+        NestedTimestamp32::new(false, inner)
+    }
+
+    fn tuples(seed: u64) -> Vec<((u64, u64), isize)> {
+        (0..100)
+            .map(|i| {
+                (
+                    ((i * seed) % 7, (i * seed) % 11),
+                    if i % 3 == 0 { -1 } else { 1 },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rle_matches_ord_val_batch() {
+        let mut rle = OrdValRleBatch::<u64, u64, NestedTimestamp32, isize>::empty(time(0));
+        let mut ord = OrdValBatch::<u64, u64, NestedTimestamp32, isize>::empty(time(0));
+
+        for step in 0..5 {
+            rle = rle.merge(&OrdValRleBatch::from_tuples(
+                time(step),
+                tuples(step as u64 + 1),
+            ));
+            ord = ord.merge(&OrdValBatch::from_tuples(
+                time(step),
+                tuples(step as u64 + 1),
+            ));
+            assert_eq!(rle.len(), ord.len());
+            assert_eq!(rle.cursor().to_vec(&rle), ord.cursor().to_vec(&ord));
+        }
+        assert!(rle.runs() > 1);
+
+        rle.retain(&|k, v| k != v);
+        ord.retain(&|k, v| k != v);
+        assert_eq!(rle.cursor().to_vec(&rle), ord.cursor().to_vec(&ord));
+
+        // After compaction, all updates share a single timestamp.
+        rle.recede_to(&time(0));
+        ord.recede_to(&time(0));
+        assert_eq!(rle.len(), ord.len());
+        assert_eq!(rle.cursor().to_vec(&rle), ord.cursor().to_vec(&ord));
+        assert_eq!(rle.runs(), 1);
+    }
+}
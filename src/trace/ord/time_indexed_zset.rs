@@ -0,0 +1,210 @@
+//! Z-set indexed by key and time.
+//!
+//! [`TimeIndexedZSet`] stores `(key, time, val, weight)` updates in a trie
+//! ordered by key, then time, then value.  Unlike
+//! [`OrdValBatch`](`super::OrdValBatch`), which orders the updates of each
+//! key by value and only then by time, this layout keeps the updates of each
+//! key in time order, so that all updates of a key at or after a given time
+//! form a contiguous range that can be located with a single search.  This
+//! makes it a good fit for append-mostly temporal data kept as operator
+//! state, e.g., changelogs that must be replayed from a snapshot time.
+//!
+//! Since values of a key are not sorted, the collection does not implement
+//! [`BatchReader`](`crate::trace::BatchReader`) or
+//! [`Batch`](`crate::trace::Batch`), whose cursors enumerate values in
+//! order.  It therefore cannot be stored in a
+//! [`Spine`](`crate::trace::spine_fueled::Spine`) or flow through streams consumed by
+//! batch operators; its owner merges collections explicitly with
+//! [`TimeIndexedZSet::merge`] and reads them with
+//! [`TimeIndexedZSet::updates_since`].
+
+use crate::{
+    algebra::{AddAssignByRef, HasZero, MonoidValue},
+    trace::{
+        consolidation::consolidate,
+        layers::{
+            advance,
+            ordered::{OrderedBuilder, OrderedLayer},
+            ordered_leaf::{OrderedLeaf, OrderedLeafBuilder},
+            Builder as TrieBuilder, Trie, TupleBuilder,
+        },
+    },
+};
+use deepsize::DeepSizeOf;
+
+type TimeIndexedLayer<K, T, V, R> = OrderedLayer<K, OrderedLayer<T, OrderedLeaf<V, R>>>;
+
+/// A collection of `(key, time, val, weight)` updates ordered by key and
+/// time.
+#[derive(Debug, DeepSizeOf, Clone, Eq, PartialEq)]
+pub struct TimeIndexedZSet<K, T, V, R>
+where
+    K: Ord,
+    T: Ord,
+{
+    /// Where all the dataz is.
+    pub layer: TimeIndexedLayer<K, T, V, R>,
+}
+
+impl<K, T, V, R> TimeIndexedZSet<K, T, V, R>
+where
+    K: Ord + Clone,
+    T: Ord + Clone,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
+{
+    /// Creates an empty collection.
+    pub fn empty() -> Self {
+        Self {
+            layer: <TimeIndexedLayer<K, T, V, R> as HasZero>::zero(),
+        }
+    }
+
+    /// Creates a collection from unordered `((key, time, val), weight)`
+    /// tuples, consolidating updates with the same key, time, and value.
+    pub fn from_tuples(mut tuples: Vec<((K, T, V), R)>) -> Self {
+        consolidate(&mut tuples);
+
+        let mut builder = <OrderedBuilder<K, OrderedBuilder<T, OrderedLeafBuilder<V, R>>> as TupleBuilder>::with_capacity(tuples.len());
+        for ((key, time, val), weight) in tuples.into_iter() {
+            builder.push_tuple((key, (time, (val, weight))));
+        }
+
+        Self {
+            layer: builder.done(),
+        }
+    }
+
+    /// The number of updates in the collection.
+    pub fn len(&self) -> usize {
+        self.layer.tuples()
+    }
+
+    /// True if the collection is empty.
+    pub fn is_empty(&self) -> bool {
+        self.layer.is_empty()
+    }
+
+    /// The number of distinct keys in the collection.
+    pub fn keys(&self) -> usize {
+        self.layer.keys()
+    }
+
+    /// Merges `self` with `other`, adding up weights of identical updates.
+    pub fn merge(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        result.layer.add_assign_by_ref(&other.layer);
+        result
+    }
+
+    /// Applies `logic` to all updates of `key` with time greater than or
+    /// equal to `since`, in time order.
+    ///
+    /// Times are compared using their total order ([`Ord`]), which coincides
+    /// with the partial order for totally ordered times, such as event
+    /// timestamps.
+    pub fn updates_since<F>(&self, key: &K, since: &T, logic: F)
+    where
+        F: FnMut(&T, &V, &R),
+    {
+        let index = advance(&self.layer.keys, |k| k < key);
+        if index < self.layer.keys.len() && &self.layer.keys[index] == key {
+            self.map_key_since(index, since, logic);
+        }
+    }
+
+    /// Applies `logic` to all updates with time greater than or equal to
+    /// `since`, ordered by key and time.
+    ///
+    /// See [`Self::updates_since`].
+    pub fn all_updates_since<F>(&self, since: &T, mut logic: F)
+    where
+        F: FnMut(&K, &T, &V, &R),
+    {
+        for index in 0..self.layer.keys.len() {
+            let key = &self.layer.keys[index];
+            self.map_key_since(index, since, |time, val, weight| {
+                logic(key, time, val, weight)
+            });
+        }
+    }
+
+    // Applies `logic` to updates of the key at position `index` with time
+    // greater than or equal to `since`.
+    fn map_key_since<F>(&self, index: usize, since: &T, mut logic: F)
+    where
+        F: FnMut(&T, &V, &R),
+    {
+        let times = &self.layer.vals;
+        let leaf = &times.vals;
+
+        let lower = self.layer.offs.get(index);
+        let upper = self.layer.offs.get(index + 1);
+        let lower = lower + advance(&times.keys[lower..upper], |t| t < since);
+
+        for time in lower..upper {
+            for (val, weight) in leaf.vals[times.offs.get(time)..times.offs.get(time + 1)].iter() {
+                logic(&times.keys[time], val, weight);
+            }
+        }
+    }
+}
+
+impl<K, T, V, R> Default for TimeIndexedZSet<K, T, V, R>
+where
+    K: Ord + Clone,
+    T: Ord + Clone,
+    V: Ord + Clone + Send,
+    R: MonoidValue + Send,
+{
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TimeIndexedZSet;
+
+    #[test]
+    fn updates_since() {
+        let zset = TimeIndexedZSet::from_tuples(vec![
+            ((1, 10u64, "a"), 1isize),
+            ((1, 20, "b"), 1),
+            ((1, 20, "a"), -1),
+            ((2, 15, "c"), 1),
+            ((1, 30, "c"), 1),
+            ((1, 30, "c"), -1),
+        ]);
+        assert_eq!(zset.len(), 4);
+        assert_eq!(zset.keys(), 2);
+
+        let zset = zset.merge(&TimeIndexedZSet::from_tuples(vec![
+            ((1, 40, "a"), 1),
+            ((2, 15, "c"), -1),
+            ((3, 5, "d"), 1),
+        ]));
+        assert_eq!(zset.len(), 5);
+
+        let mut updates = Vec::new();
+        zset.updates_since(&1, &20, |t, v, w| updates.push((*t, *v, *w)));
+        assert_eq!(updates, vec![(20, "a", -1), (20, "b", 1), (40, "a", 1)]);
+
+        let mut updates = Vec::new();
+        zset.updates_since(&2, &0, |t, v, w| updates.push((*t, *v, *w)));
+        assert_eq!(updates, vec![]);
+
+        let mut updates = Vec::new();
+        zset.all_updates_since(&5, |k, t, v, w| updates.push((*k, *t, *v, *w)));
+        assert_eq!(
+            updates,
+            vec![
+                (1, 10, "a", 1),
+                (1, 20, "a", -1),
+                (1, 20, "b", 1),
+                (1, 40, "a", 1),
+                (3, 5, "d", 1)
+            ]
+        );
+    }
+}
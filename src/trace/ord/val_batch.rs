@@ -15,7 +15,7 @@ use crate::{
             Builder as TrieBuilder, Cursor as TrieCursor, MergeBuilder, Trie, TupleBuilder,
         },
         ord::merge_batcher::MergeBatcher,
-        Batch, BatchReader, Builder, Cursor, Merger,
+        Batch, BatchReader, Builder, Cursor, Merger, SizeHint,
     },
     Timestamp,
 };
@@ -24,12 +24,12 @@ use deepsize::DeepSizeOf;
 
 /// An immutable collection of update tuples, from a contiguous interval of
 /// logical times.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OrdValBatch<K, V, T, R, O = usize>
 where
     K: Ord,
     V: Ord,
-    T: Lattice,
+    T: Lattice + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -47,8 +47,8 @@ impl<K, V, T, R, O> Display for OrdValBatch<K, V, T, R, O>
 where
     K: Ord + Clone + Display,
     V: Ord + Clone + Display,
-    T: Lattice + Clone + Ord + Display + Debug,
-    R: Eq + HasZero + AddAssignByRef + Clone + Display,
+    T: Lattice + Clone + Ord + Display + Debug + Send,
+    R: Eq + HasZero + AddAssignByRef + Clone + Display + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -68,8 +68,8 @@ impl<K, V, T, R, O> DeepSizeOf for OrdValBatch<K, V, T, R, O>
 where
     K: DeepSizeOf + Ord,
     V: DeepSizeOf + Ord,
-    T: DeepSizeOf + Lattice,
-    R: DeepSizeOf,
+    T: DeepSizeOf + Lattice + Send,
+    R: DeepSizeOf + Send,
     O: DeepSizeOf + OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -81,10 +81,10 @@ where
 
 impl<K, V, T, R, O> BatchReader for OrdValBatch<K, V, T, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone + 'static,
-    T: Timestamp + Lattice,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send + 'static,
+    T: Timestamp + Lattice + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -103,6 +103,9 @@ where
     fn len(&self) -> usize {
         <OrdValBatchLayer<K, V, T, R, O> as Trie>::tuples(&self.layer)
     }
+    fn size_hint(&self) -> SizeHint {
+        self.layer.size_hint()
+    }
     fn lower(&self) -> &Antichain<T> {
         &self.lower
     }
@@ -113,10 +116,10 @@ where
 
 impl<K, V, T, R, O> Batch for OrdValBatch<K, V, T, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone + 'static,
-    T: Lattice + Timestamp + Ord + Clone + ::std::fmt::Debug + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send + 'static,
+    T: Lattice + Timestamp + Ord + Clone + ::std::fmt::Debug + Send + 'static,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -135,14 +138,19 @@ where
             self.do_recede_to(frontier);
         }
     }
+
+    fn retain(&mut self, retain: &dyn Fn(&K, &V) -> bool) {
+        self.layer
+            .retain_children(|key, vals, index| retain(key, &vals.keys[index]));
+    }
 }
 
 impl<K, V, T, R, O> OrdValBatch<K, V, T, R, O>
 where
     K: Ord + Clone + 'static,
     V: Ord + Clone + 'static,
-    T: Lattice + Ord + Clone + ::std::fmt::Debug + 'static,
-    R: MonoidValue,
+    T: Lattice + Ord + Clone + ::std::fmt::Debug + Send + 'static,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -168,10 +176,10 @@ where
             // changed.     we will change batch.layer.vals.offs[i] in this
             // iteration, from `write_position`'s     initial value.
 
-            let lower: usize = self.layer.vals.offs[i].try_into().unwrap();
-            let upper: usize = self.layer.vals.offs[i + 1].try_into().unwrap();
+            let lower = self.layer.vals.offs.get(i);
+            let upper = self.layer.vals.offs.get(i + 1);
 
-            self.layer.vals.offs[i] = O::try_from(write_position).unwrap();
+            self.layer.vals.offs.set(i, write_position);
 
             let updates = &mut self.layer.vals.vals.vals[..];
 
@@ -184,7 +192,10 @@ where
             }
         }
         self.layer.vals.vals.vals.truncate(write_position);
-        self.layer.vals.offs[self.layer.vals.keys.len()] = O::try_from(write_position).unwrap();
+        self.layer
+            .vals
+            .offs
+            .set(self.layer.vals.keys.len(), write_position);
 
         // 3. For each `(key, off)` pair, (values already sorted), filter vals, and
         // rewrite `off`.    This may leave `key` with an empty range. Filtering
@@ -194,18 +205,21 @@ where
             // NB: batch.layer.offs[i+1] must remain as is for the next iteration.
             //     instead, we update batch.layer.offs[i]
 
-            let lower: usize = self.layer.offs[i].try_into().unwrap();
-            let upper: usize = self.layer.offs[i + 1].try_into().unwrap();
+            let lower = self.layer.offs.get(i);
+            let upper = self.layer.offs.get(i + 1);
 
-            self.layer.offs[i] = O::try_from(write_position).unwrap();
+            self.layer.offs.set(i, write_position);
 
             // values should already be sorted, but some might now be empty.
             for index in lower..upper {
-                let val_lower: usize = self.layer.vals.offs[index].try_into().unwrap();
-                let val_upper: usize = self.layer.vals.offs[index + 1].try_into().unwrap();
+                let val_lower = self.layer.vals.offs.get(index);
+                let val_upper = self.layer.vals.offs.get(index + 1);
                 if val_lower < val_upper {
                     self.layer.vals.keys.swap(write_position, index);
-                    self.layer.vals.offs[write_position + 1] = self.layer.vals.offs[index + 1];
+                    self.layer
+                        .vals
+                        .offs
+                        .set(write_position + 1, self.layer.vals.offs.get(index + 1));
                     write_position += 1;
                 }
             }
@@ -213,13 +227,13 @@ where
         }
         self.layer.vals.keys.truncate(write_position);
         self.layer.vals.offs.truncate(write_position + 1);
-        self.layer.offs[self.layer.keys.len()] = O::try_from(write_position).unwrap();
+        self.layer.offs.set(self.layer.keys.len(), write_position);
 
         // 4. Remove empty keys.
         let mut write_position = 0;
         for i in 0..self.layer.keys.len() {
-            let lower: usize = self.layer.offs[i].try_into().unwrap();
-            let upper: usize = self.layer.offs[i + 1].try_into().unwrap();
+            let lower = self.layer.offs.get(i);
+            let upper = self.layer.offs.get(i + 1);
 
             if lower < upper {
                 self.layer.keys.swap(write_position, i);
@@ -238,8 +252,8 @@ pub struct OrdValMerger<K, V, T, R, O = usize>
 where
     K: Ord + Clone + 'static,
     V: Ord + Clone + 'static,
-    T: Lattice + Ord + Clone + ::std::fmt::Debug + 'static,
-    R: MonoidValue,
+    T: Lattice + Ord + Clone + ::std::fmt::Debug + Send + 'static,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -258,10 +272,10 @@ where
 
 impl<K, V, T, R, O> Merger<K, V, T, R, OrdValBatch<K, V, T, R, O>> for OrdValMerger<K, V, T, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone + 'static,
-    T: Lattice + Timestamp + Ord + Clone + ::std::fmt::Debug + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send + 'static,
+    T: Lattice + Timestamp + Ord + Clone + ::std::fmt::Debug + Send + 'static,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -356,8 +370,8 @@ where
 pub struct OrdValCursor<V, T, R, O = usize>
 where
     V: Ord + Clone,
-    T: Lattice + Ord + Clone,
-    R: MonoidValue,
+    T: Lattice + Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -369,8 +383,8 @@ impl<K, V, T, R, O> Cursor<K, V, T, R> for OrdValCursor<V, T, R, O>
 where
     K: Ord + Clone,
     V: Ord + Clone,
-    T: Lattice + Ord + Clone,
-    R: MonoidValue,
+    T: Lattice + Ord + Clone + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -418,6 +432,12 @@ where
     fn seek_key(&mut self, storage: &Self::Storage, key: &K) {
         self.cursor.seek(&storage.layer, key);
     }
+    fn seek_key_with<P>(&mut self, storage: &Self::Storage, predicate: P)
+    where
+        P: Fn(&K) -> bool,
+    {
+        self.cursor.seek_with(&storage.layer, predicate);
+    }
     fn step_val(&mut self, storage: &Self::Storage) {
         self.cursor.child.step(&storage.layer.vals);
     }
@@ -437,8 +457,8 @@ pub struct OrdValBuilder<K, V, T, R, O = usize>
 where
     K: Ord,
     V: Ord,
-    T: Ord + Lattice,
-    R: MonoidValue,
+    T: Ord + Lattice + Send,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -449,10 +469,10 @@ where
 
 impl<K, V, T, R, O> Builder<K, V, T, R, OrdValBatch<K, V, T, R, O>> for OrdValBuilder<K, V, T, R, O>
 where
-    K: Ord + Clone + 'static,
-    V: Ord + Clone + 'static,
-    T: Lattice + Timestamp + Ord + Clone + ::std::fmt::Debug + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    V: Ord + Clone + Send + 'static,
+    T: Lattice + Timestamp + Ord + Clone + ::std::fmt::Debug + Send + 'static,
+    R: MonoidValue + Send,
     O: OrdOffset,
     <O as TryFrom<usize>>::Error: Debug,
     <O as TryInto<usize>>::Error: Debug,
@@ -469,6 +489,12 @@ where
             builder: <OrderedBuilder<K, OrderedBuilder<V, OrderedLeafBuilder<T, R>, O>, O> as TupleBuilder>::with_capacity(cap)
         }
     }
+    fn with_size_hint(time: T, hint: SizeHint) -> Self {
+        OrdValBuilder {
+            time,
+            builder: <OrderedBuilder<K, OrderedBuilder<V, OrderedLeafBuilder<T, R>, O>, O> as TupleBuilder>::with_size_hint(hint)
+        }
+    }
 
     #[inline]
     fn push(&mut self, (key, val, diff): (K, V, R)) {
@@ -2,8 +2,10 @@ use std::{
     cmp::max,
     convert::TryFrom,
     fmt::{Debug, Display},
+    mem::take,
     ops::{Add, AddAssign, Neg},
     rc::Rc,
+    sync::Arc,
 };
 
 use timely::progress::Antichain;
@@ -12,6 +14,7 @@ use crate::{
     algebra::{AddAssignByRef, AddByRef, HasZero, MonoidValue, NegByRef},
     lattice::Lattice,
     trace::{
+        consolidation::consolidate,
         layers::{
             ordered_leaf::{OrderedLeaf, OrderedLeafBuilder, OrderedLeafCursor},
             Builder as TrieBuilder, Cursor as TrieCursor, MergeBuilder, Trie, TupleBuilder,
@@ -22,6 +25,8 @@ use crate::{
     NumEntries, SharedRef,
 };
 
+#[cfg(feature = "with-rayon")]
+use crate::trace::consolidation::par_consolidate_runs;
 use deepsize::DeepSizeOf;
 
 /// An immutable collection of `(key, weight)` pairs without timing information.
@@ -38,8 +43,8 @@ where
 
 impl<K, R> Display for OrdZSet<K, R>
 where
-    K: Ord + Clone + Display,
-    R: Eq + HasZero + AddAssignByRef + Clone + Display,
+    K: Ord + Clone + Display + Send,
+    R: Eq + HasZero + AddAssignByRef + Clone + Display + Send,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         writeln!(
@@ -52,7 +57,7 @@ where
 
 impl<K, R> From<OrderedLeaf<K, R>> for OrdZSet<K, R>
 where
-    K: Ord,
+    K: Ord + Send,
 {
     fn from(layer: OrderedLeaf<K, R>) -> Self {
         Self {
@@ -65,7 +70,7 @@ where
 
 impl<K, R> From<OrderedLeaf<K, R>> for Rc<OrdZSet<K, R>>
 where
-    K: Ord,
+    K: Ord + Send,
 {
     fn from(layer: OrderedLeaf<K, R>) -> Self {
         Rc::new(From::from(layer))
@@ -74,7 +79,7 @@ where
 
 impl<K, R> TryFrom<Rc<OrdZSet<K, R>>> for OrdZSet<K, R>
 where
-    K: Ord,
+    K: Ord + Send,
 {
     type Error = Rc<OrdZSet<K, R>>;
 
@@ -83,10 +88,88 @@ where
     }
 }
 
+impl<K, R> From<OrderedLeaf<K, R>> for Arc<OrdZSet<K, R>>
+where
+    K: Ord + Send,
+{
+    fn from(layer: OrderedLeaf<K, R>) -> Self {
+        Arc::new(From::from(layer))
+    }
+}
+
+impl<K, R> TryFrom<Arc<OrdZSet<K, R>>> for OrdZSet<K, R>
+where
+    K: Ord + Send,
+{
+    type Error = Arc<OrdZSet<K, R>>;
+
+    fn try_from(batch: Arc<OrdZSet<K, R>>) -> Result<Self, Self::Error> {
+        Arc::try_unwrap(batch)
+    }
+}
+
+#[cfg(feature = "with-rayon")]
+impl<K, R> OrdZSet<K, R>
+where
+    K: Ord + Clone + Send,
+    R: MonoidValue + Send,
+{
+    /// Parallel version of [`Batch::from_tuples`].
+    ///
+    /// Sorts and consolidates chunks of `tuples` on all threads in the rayon
+    /// thread pool and k-way merges the resulting sorted runs (see
+    /// [`par_consolidate_runs`]).  Use this method to bulk-load large
+    /// collections, e.g., the initial state of a circuit.
+    #[allow(clippy::type_complexity)]
+    pub fn from_tuples_parallel(_time: (), tuples: Vec<((K, ()), R)>) -> Self {
+        let tuples = tuples.into_iter().map(|((k, ()), r)| (k, r)).collect();
+        Self::from(OrderedLeaf {
+            vals: par_consolidate_runs(tuples),
+        })
+    }
+}
+
+impl<K, R> OrdZSet<K, R>
+where
+    K: Ord + Send,
+    R: MonoidValue + Send,
+{
+    /// Removes all keys that do not satisfy `retain`.
+    ///
+    /// Since filtering preserves the order of keys, this is done in place
+    /// without rebuilding the batch.
+    pub fn retain_keys<F>(&mut self, mut retain: F)
+    where
+        F: FnMut(&K, &R) -> bool,
+    {
+        self.layer.vals.retain(|(key, weight)| retain(key, weight));
+    }
+
+    /// Applies `map` to all keys in the batch, reusing its storage.
+    ///
+    /// If `map` preserves the order of keys, e.g., it is strictly monotonic,
+    /// the batch is not re-sorted.  Otherwise, the updates are sorted and
+    /// consolidated, as keys may have been reordered or mapped to the same
+    /// key.
+    pub fn map_in_place<F>(&mut self, mut map: F)
+    where
+        F: FnMut(K) -> K,
+    {
+        let mut vals: Vec<(K, R)> = take(&mut self.layer.vals)
+            .into_iter()
+            .map(|(key, weight)| (map(key), weight))
+            .collect();
+        if !vals.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            consolidate(&mut vals);
+        }
+        self.layer.vals = vals;
+    }
+}
+
 impl<K, R> DeepSizeOf for OrdZSet<K, R>
 where
-    K: DeepSizeOf + Ord,
-    R: DeepSizeOf,
+    K: DeepSizeOf + Ord + Send,
+    R: DeepSizeOf + Send,
 {
     fn deep_size_of_children(&self, _context: &mut deepsize::Context) -> usize {
         self.layer.deep_size_of()
@@ -95,8 +178,8 @@ where
 
 impl<K, R> NumEntries for OrdZSet<K, R>
 where
-    K: Ord + Clone,
-    R: Eq + HasZero + AddAssignByRef + Clone,
+    K: Ord + Clone + Send,
+    R: Eq + HasZero + AddAssignByRef + Clone + Send,
 {
     fn num_entries_shallow(&self) -> usize {
         self.layer.num_entries_shallow()
@@ -111,8 +194,8 @@ where
 
 impl<K, R> HasZero for OrdZSet<K, R>
 where
-    K: Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
 {
     fn zero() -> Self {
         Self::empty(())
@@ -125,8 +208,8 @@ where
 
 impl<K, R> SharedRef for OrdZSet<K, R>
 where
-    K: Ord + Clone,
-    R: Clone,
+    K: Ord + Clone + Send,
+    R: Clone + Send,
 {
     type Target = Self;
 
@@ -137,8 +220,8 @@ where
 
 impl<K, R> NegByRef for OrdZSet<K, R>
 where
-    K: Ord + Clone,
-    R: MonoidValue + NegByRef,
+    K: Ord + Clone + Send,
+    R: MonoidValue + NegByRef + Send,
 {
     fn neg_by_ref(&self) -> Self {
         Self {
@@ -151,8 +234,8 @@ where
 
 impl<K, R> Neg for OrdZSet<K, R>
 where
-    K: Ord + Clone,
-    R: MonoidValue + Neg<Output = R>,
+    K: Ord + Clone + Send,
+    R: MonoidValue + NegByRef + Send,
 {
     type Output = Self;
 
@@ -168,8 +251,8 @@ where
 // TODO: by-value merge
 impl<K, R> Add<Self> for OrdZSet<K, R>
 where
-    K: Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
 {
     type Output = Self;
 
@@ -187,8 +270,8 @@ where
 
 impl<K, R> AddAssign<Self> for OrdZSet<K, R>
 where
-    K: Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
 {
     fn add_assign(&mut self, rhs: Self) {
         self.lower = self.lower().meet(rhs.lower());
@@ -199,8 +282,8 @@ where
 
 impl<K, R> AddAssignByRef for OrdZSet<K, R>
 where
-    K: Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
 {
     fn add_assign_by_ref(&mut self, rhs: &Self) {
         self.layer.add_assign_by_ref(&rhs.layer);
@@ -211,8 +294,8 @@ where
 
 impl<K, R> AddByRef for OrdZSet<K, R>
 where
-    K: Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
 {
     fn add_by_ref(&self, rhs: &Self) -> Self {
         Self {
@@ -225,8 +308,8 @@ where
 
 impl<K, R> BatchReader for OrdZSet<K, R>
 where
-    K: Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
 {
     type Key = K;
     type Val = ();
@@ -254,8 +337,8 @@ where
 
 impl<K, R> Batch for OrdZSet<K, R>
 where
-    K: Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
 {
     type Batcher = MergeBatcher<K, (), (), R, Self>;
     type Builder = OrdZSetBuilder<K, R>;
@@ -266,13 +349,22 @@ where
     }
 
     fn recede_to(&mut self, _frontier: &()) {}
+
+    fn retain(&mut self, retain: &dyn Fn(&K, &()) -> bool) {
+        self.layer.vals.retain(|(key, _)| retain(key, &()));
+    }
+
+    fn map_keys_in_place(&mut self, map: &dyn Fn(K) -> K) -> bool {
+        self.map_in_place(map);
+        true
+    }
 }
 
 /// State for an in-progress merge.
 pub struct OrdZSetMerger<K, R>
 where
-    K: Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
 {
     // result that we are currently assembling.
     result: <OrderedLeaf<K, R> as Trie>::MergeBuilder,
@@ -280,8 +372,8 @@ where
 
 impl<K, R> Merger<K, (), (), R, OrdZSet<K, R>> for OrdZSetMerger<K, R>
 where
-    K: Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
 {
     fn new(batch1: &OrdZSet<K, R>, batch2: &OrdZSet<K, R>) -> Self {
         OrdZSetMerger {
@@ -317,8 +409,8 @@ pub struct OrdZSetCursor {
 
 impl<K, R> Cursor<K, (), (), R> for OrdZSetCursor
 where
-    K: Ord + Clone,
-    R: MonoidValue,
+    K: Ord + Clone + Send,
+    R: MonoidValue + Send,
 {
     type Storage = OrdZSet<K, R>;
 
@@ -351,6 +443,14 @@ where
         self.cursor.seek_key(&storage.layer, key);
         self.valid = true;
     }
+    fn seek_key_with<P>(&mut self, storage: &Self::Storage, predicate: P)
+    where
+        P: Fn(&K) -> bool,
+    {
+        self.cursor
+            .seek_with(&storage.layer, |(key, _)| predicate(key));
+        self.valid = true;
+    }
     fn step_val(&mut self, _storage: &Self::Storage) {
         self.valid = false;
     }
@@ -367,16 +467,16 @@ where
 /// A builder for creating layers from unsorted update tuples.
 pub struct OrdZSetBuilder<K, R>
 where
-    K: Ord,
-    R: MonoidValue,
+    K: Ord + Send,
+    R: MonoidValue + Send,
 {
     builder: OrderedLeafBuilder<K, R>,
 }
 
 impl<K, R> Builder<K, (), (), R, OrdZSet<K, R>> for OrdZSetBuilder<K, R>
 where
-    K: Ord + Clone + 'static,
-    R: MonoidValue,
+    K: Ord + Clone + Send + 'static,
+    R: MonoidValue + Send,
 {
     fn new(_time: ()) -> Self {
         OrdZSetBuilder {
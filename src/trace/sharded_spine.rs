@@ -0,0 +1,485 @@
+//! A trace that partitions keys across multiple spines.
+
+use crate::{
+    lattice::Lattice,
+    time::Timestamp,
+    trace::{
+        cursor::{Cursor, CursorList},
+        spine_fueled::{Spine, SpineCursor},
+        Antichain, Batch, BatchReader, EffortConfig, MaintenanceDebt, Trace, TraceReader,
+    },
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+use timely::scheduling::activate::Activator;
+
+/// Default number of shards in a [`ShardedSpine`].
+pub const DEFAULT_SHARDS: usize = 4;
+
+/// Seed of the hash function that assigns keys to the shards of a
+/// [`ShardedSpine`].
+///
+/// The trace of a worker only contains keys that the worker owns (see
+/// [`Stream::shard`](crate::circuit::Stream::shard)).  Hashing keys the same
+/// way for both would map all of them to the same shard of the trace when
+/// the number of shards equals the number of workers.
+const SPINE_SHARD_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Returns the shard in `0..shards` that `key` belongs to.
+///
+/// Different `seed`s yield independent assignments of keys to shards.
+pub(crate) fn hash_shard<K>(key: &K, seed: u64, shards: usize) -> usize
+where
+    K: Hash + ?Sized,
+{
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    key.hash(&mut hasher);
+    (hasher.finish() % shards as u64) as usize
+}
+
+/// A trace that partitions keys into shards, each maintained by its own
+/// [`Spine`].
+///
+/// Since each key belongs to exactly one shard, shards can be merged
+/// independently.  With the `with-rayon` feature, [`Trace::exert`] performs
+/// merges in all shards in parallel, improving merge throughput on
+/// multi-core machines even in single-worker circuits.  Reads see a unified
+/// view of all shards through [`ShardedSpineCursor`].
+pub struct ShardedSpine<B>
+where
+    B: Batch,
+{
+    shards: Vec<Spine<B>>,
+    lower: Antichain<B::Time>,
+    upper: Antichain<B::Time>,
+    activator: Option<Activator>,
+    dirty: bool,
+}
+
+impl<B> ShardedSpine<B>
+where
+    B: Batch + Clone + 'static,
+    B::Key: Ord + Hash,
+    B::Val: Ord,
+{
+    /// Creates an empty trace with `shards` shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is 0.
+    pub fn with_shards(shards: usize, activator: Option<Activator>) -> Self {
+        assert_ne!(shards, 0, "a sharded spine must have at least one shard");
+
+        Self {
+            // Shards do not reschedule the operator themselves; the sharded
+            // spine does it on their behalf.
+            shards: (0..shards).map(|_| Spine::new(None)).collect(),
+            lower: Antichain::from_elem(B::Time::minimum()),
+            upper: Antichain::new(),
+            activator,
+            dirty: false,
+        }
+    }
+
+    /// The number of shards.
+    pub fn num_shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Shards of the trace.
+    pub fn shards(&self) -> &[Spine<B>] {
+        &self.shards
+    }
+
+    /// Returns the shard that `key` belongs to.
+    pub fn shard_of(&self, key: &B::Key) -> usize {
+        hash_shard(key, SPINE_SHARD_SEED, self.shards.len())
+    }
+
+    /// Splits `batch` into per-shard batches, one for each distinct
+    /// timestamp in `batch`.
+    #[allow(clippy::type_complexity)]
+    fn partition(&self, batch: &B) -> Vec<Vec<B>>
+    where
+        B::Key: Clone,
+        B::Val: Clone,
+    {
+        let mut tuples: Vec<Vec<(B::Time, Vec<((B::Key, B::Val), B::R)>)>> =
+            vec![Vec::new(); self.shards.len()];
+
+        let mut cursor = batch.cursor();
+        while cursor.key_valid(batch) {
+            let shard = &mut tuples[self.shard_of(cursor.key(batch))];
+            while cursor.val_valid(batch) {
+                let key = cursor.key(batch);
+                let val = cursor.val(batch);
+                cursor.map_times(batch, |time, weight| {
+                    let index = match shard.iter().position(|(t, _)| t == time) {
+                        Some(index) => index,
+                        None => {
+                            shard.push((time.clone(), Vec::new()));
+                            shard.len() - 1
+                        }
+                    };
+                    shard[index]
+                        .1
+                        .push(((key.clone(), val.clone()), weight.clone()));
+                });
+                cursor.step_val(batch);
+            }
+            cursor.step_key(batch);
+        }
+
+        tuples
+            .into_iter()
+            .map(|shard| {
+                shard
+                    .into_iter()
+                    .map(|(time, tuples)| B::from_tuples(time, tuples))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Exclusive reference to a shard that can be sent to a rayon worker thread.
+#[cfg(feature = "with-rayon")]
+struct SendShard<'a, B: Batch>(&'a mut Spine<B>);
+
+// Safety: a `Spine` is `!Send` only because it may hold a timely `Activator`
+// and a merge observer, which reference thread-local state.  Shards are
+// created without either and never expose them, so sending a shard to
+// another thread only moves its batches, mergers, and frontiers, which are
+// `Send` by the bounds below.
+#[cfg(feature = "with-rayon")]
+unsafe impl<'a, B> Send for SendShard<'a, B>
+where
+    B: Batch + Send,
+    B::Merger: Send,
+    B::Time: Send,
+{
+}
+
+impl<B> ShardedSpine<B>
+where
+    B: Batch + Clone + Send + 'static,
+    B::Key: Ord,
+    B::Val: Ord,
+    B::Merger: Send,
+    B::Time: Send,
+{
+    #[cfg(not(feature = "with-rayon"))]
+    fn exert_shards(&mut self, effort: isize) {
+        for shard in self.shards.iter_mut() {
+            let mut shard_effort = effort;
+            shard.exert(&mut shard_effort);
+        }
+    }
+
+    #[cfg(feature = "with-rayon")]
+    fn exert_shards(&mut self, effort: isize) {
+        use rayon::prelude::*;
+
+        self.shards
+            .iter_mut()
+            .map(SendShard)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .for_each(|shard| {
+                let mut shard_effort = effort;
+                shard.0.exert(&mut shard_effort);
+            });
+    }
+}
+
+impl<B> BatchReader for ShardedSpine<B>
+where
+    B: Batch + Clone + 'static,
+    B::Key: Ord,
+    B::Val: Ord,
+{
+    type Key = B::Key;
+    type Val = B::Val;
+    type Time = B::Time;
+    type R = B::R;
+    type Cursor = ShardedSpineCursor<B>;
+
+    fn cursor(&self) -> Self::Cursor {
+        ShardedSpineCursor {
+            cursor: CursorList::new(
+                self.shards.iter().map(|shard| shard.cursor()).collect(),
+                &self.shards.as_slice(),
+            ),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.len()).sum()
+    }
+
+    fn lower(&self) -> &Antichain<Self::Time> {
+        &self.lower
+    }
+
+    fn upper(&self) -> &Antichain<Self::Time> {
+        &self.upper
+    }
+}
+
+impl<B> TraceReader for ShardedSpine<B>
+where
+    B: Batch + Clone + 'static,
+    B::Key: Ord,
+    B::Val: Ord,
+{
+    type Batch = B;
+
+    fn map_batches<F: FnMut(&Self::Batch)>(&self, mut f: F) {
+        for shard in self.shards.iter() {
+            shard.map_batches(&mut f);
+        }
+    }
+
+    fn maintenance_debt(&self) -> MaintenanceDebt {
+        let mut debt = MaintenanceDebt::default();
+        for shard in self.shards.iter() {
+            let shard_debt = shard.maintenance_debt();
+            debt.merges += shard_debt.merges;
+            debt.tuples += shard_debt.tuples;
+            debt.fuel += shard_debt.fuel;
+        }
+        debt
+    }
+}
+
+impl<B> Trace for ShardedSpine<B>
+where
+    B: Batch + Clone + Send + 'static,
+    B::Key: Ord + Clone + Hash,
+    B::Val: Ord + Clone,
+    B::Merger: Send,
+    B::Time: Send,
+{
+    fn new(activator: Option<Activator>) -> Self {
+        Self::with_shards(DEFAULT_SHARDS, activator)
+    }
+
+    fn recede_to(&mut self, frontier: &B::Time) {
+        for shard in self.shards.iter_mut() {
+            shard.recede_to(frontier);
+        }
+    }
+
+    fn exert(&mut self, effort: &mut isize) {
+        if self.shards.iter().all(|shard| shard.reduced()) {
+            return;
+        }
+
+        // Each shard receives the full effort, as shards are merged
+        // independently.
+        self.exert_shards(*effort);
+
+        if !self.shards.iter().all(|shard| shard.reduced()) {
+            if let Some(activator) = &self.activator {
+                activator.activate();
+            }
+        }
+    }
+
+    fn consolidate(self) -> Option<B> {
+        self.shards
+            .into_iter()
+            .filter_map(|shard| shard.consolidate())
+            .reduce(|batch1, batch2| batch1.merge(&batch2))
+    }
+
+    fn insert(&mut self, batch: B) {
+        assert!(batch.lower() != batch.upper());
+
+        if batch.is_empty() {
+            return;
+        }
+
+        self.dirty = true;
+        self.lower = self.lower.meet(batch.lower());
+        self.upper = self.upper.join(batch.upper());
+
+        for (shard, batches) in self.partition(&batch).into_iter().enumerate() {
+            for batch in batches {
+                self.shards[shard].insert(batch);
+            }
+        }
+
+        if !self.shards.iter().all(|shard| shard.reduced()) {
+            if let Some(activator) = &self.activator {
+                activator.activate();
+            }
+        }
+    }
+
+    fn retain<F>(&mut self, retain: F)
+    where
+        F: Fn(&B::Key, &B::Val) -> bool + Send + Sync + 'static,
+    {
+        let retain = Arc::new(retain);
+        for shard in self.shards.iter_mut() {
+            let retain = retain.clone();
+            shard.retain(move |key, val| retain(key, val));
+        }
+    }
+
+    fn configure_effort(&mut self, config: &EffortConfig) {
+        for shard in self.shards.iter_mut() {
+            shard.configure_effort(config);
+        }
+    }
+
+    fn clear_dirty_flag(&mut self) {
+        self.dirty = false;
+    }
+
+    fn dirty(&self) -> bool {
+        self.dirty
+    }
+}
+
+/// Cursor over the contents of a [`ShardedSpine`].
+pub struct ShardedSpineCursor<B>
+where
+    B: Batch,
+    B::Key: Ord,
+    B::Val: Ord,
+{
+    #[allow(clippy::type_complexity)]
+    cursor: CursorList<B::Key, B::Val, B::Time, B::R, SpineCursor<B>>,
+}
+
+impl<B> Cursor<B::Key, B::Val, B::Time, B::R> for ShardedSpineCursor<B>
+where
+    B: Batch,
+    B::Key: Ord,
+    B::Val: Ord,
+{
+    type Storage = ShardedSpine<B>;
+
+    #[inline]
+    fn key_valid(&self, _spine: &Self::Storage) -> bool {
+        self.cursor.key_valid_in()
+    }
+    #[inline]
+    fn val_valid(&self, _spine: &Self::Storage) -> bool {
+        self.cursor.val_valid_in()
+    }
+
+    #[inline]
+    fn key<'a>(&self, spine: &'a Self::Storage) -> &'a B::Key {
+        self.cursor.key_in(&spine.shards.as_slice())
+    }
+    #[inline]
+    fn val<'a>(&self, spine: &'a Self::Storage) -> &'a B::Val {
+        self.cursor.val_in(&spine.shards.as_slice())
+    }
+    #[inline]
+    fn map_times<L: FnMut(&B::Time, &B::R)>(&mut self, spine: &Self::Storage, logic: L) {
+        self.cursor.map_times_in(&spine.shards.as_slice(), logic);
+    }
+
+    #[inline]
+    fn weight(&mut self, spine: &Self::Storage) -> B::R
+    where
+        B::Time: PartialEq<()>,
+    {
+        self.cursor.weight_in(&spine.shards.as_slice())
+    }
+
+    #[inline]
+    fn step_key(&mut self, spine: &Self::Storage) {
+        self.cursor.step_key_in(&spine.shards.as_slice());
+    }
+
+    #[inline]
+    fn seek_key(&mut self, spine: &Self::Storage, key: &B::Key) {
+        self.cursor.seek_key_in(&spine.shards.as_slice(), key);
+    }
+
+    #[inline]
+    fn seek_key_with<P>(&mut self, spine: &Self::Storage, predicate: P)
+    where
+        P: Fn(&B::Key) -> bool,
+    {
+        self.cursor
+            .seek_key_with_in(&spine.shards.as_slice(), predicate);
+    }
+
+    #[inline]
+    fn step_val(&mut self, spine: &Self::Storage) {
+        self.cursor.step_val_in(&spine.shards.as_slice());
+    }
+
+    #[inline]
+    fn seek_val(&mut self, spine: &Self::Storage, val: &B::Val) {
+        self.cursor.seek_val_in(&spine.shards.as_slice(), val);
+    }
+
+    #[inline]
+    fn rewind_keys(&mut self, spine: &Self::Storage) {
+        self.cursor.rewind_keys_in(&spine.shards.as_slice());
+    }
+
+    #[inline]
+    fn rewind_vals(&mut self, spine: &Self::Storage) {
+        self.cursor.rewind_vals_in(&spine.shards.as_slice());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hash_shard, ShardedSpine};
+    use crate::trace::{ord::OrdZSet, Batch, BatchReader, Cursor, Trace};
+    use std::sync::Arc;
+
+    #[test]
+    fn sharded_spine() {
+        let mut spine = ShardedSpine::<Arc<OrdZSet<u64, isize>>>::with_shards(3, None);
+
+        for i in 0..50 {
+            spine.insert(Arc::new(OrdZSet::from_tuples(
+                (),
+                vec![((i, ()), 1), ((i + 1, ()), 1)],
+            )));
+            let mut fuel = 100;
+            spine.exert(&mut fuel);
+        }
+
+        // The cursor enumerates keys from all shards in order.
+        let mut contents = Vec::new();
+        let mut cursor = spine.cursor();
+        while cursor.key_valid(&spine) {
+            contents.push((*cursor.key(&spine), cursor.weight(&spine)));
+            cursor.step_key(&spine);
+        }
+        let mut expected = vec![(0, 1)];
+        expected.extend((1..50).map(|i| (i, 2)));
+        expected.push((50, 1));
+        assert_eq!(contents, expected);
+
+        let batch = spine.consolidate().unwrap();
+        assert_eq!(batch.len(), 51);
+    }
+
+    #[test]
+    fn shards_independent_of_workers() {
+        let spine = ShardedSpine::<Arc<OrdZSet<u64, isize>>>::with_shards(4, None);
+
+        // Keys owned by one of four workers are spread across all shards.
+        let mut shards = [0; 4];
+        for key in (0..1000u64).filter(|key| hash_shard(key, 0, 4) == 0) {
+            shards[spine.shard_of(&key)] += 1;
+        }
+        assert!(shards.iter().all(|&keys| keys > 0));
+    }
+}
@@ -0,0 +1,384 @@
+//! Point-in-time views of traces.
+
+use crate::{
+    lattice::Lattice,
+    time::Timestamp,
+    trace::{
+        cursor::{Cursor, CursorList},
+        Antichain, Batch, BatchReader, TraceReader,
+    },
+};
+
+#[cfg(doc)]
+use crate::trace::Trace;
+
+/// An immutable snapshot of the contents of a trace.
+///
+/// A snapshot holds clones of the batches that formed the trace when the
+/// snapshot was taken, along with the trace's frontiers.  The snapshot is not
+/// affected by subsequent modifications of the trace, including operations
+/// that modify existing batches, such as [`Trace::recede_to`] and garbage
+/// collection via [`Trace::retain`], so readers outside the circuit can
+/// navigate it without racing with `insert` or `exert`.
+///
+/// Taking and cloning a snapshot is cheap when `B` is a reference-counted
+/// batch type, e.g., `Rc<OrdZSet<K, R>>` or `Arc<OrdZSet<K, R>>`: the trace
+/// copies a batch shared with a snapshot before modifying it.  For other
+/// batch types, taking a snapshot copies the contents of the trace.  In
+/// either case, the snapshot keeps its batches alive, so memory released by
+/// merges and garbage collection in the trace is only reclaimed once the
+/// snapshot is dropped.
+#[derive(Clone)]
+pub struct TraceSnapshot<B>
+where
+    B: Batch,
+{
+    batches: Vec<B>,
+    lower: Antichain<B::Time>,
+    upper: Antichain<B::Time>,
+}
+
+impl<B> TraceSnapshot<B>
+where
+    B: Batch,
+{
+    /// Create a snapshot consisting of `batches` with frontiers `lower` and
+    /// `upper`.
+    pub fn new(batches: Vec<B>, lower: Antichain<B::Time>, upper: Antichain<B::Time>) -> Self {
+        Self {
+            batches,
+            lower,
+            upper,
+        }
+    }
+
+    /// Batches in the snapshot.
+    pub fn batches(&self) -> &[B] {
+        &self.batches
+    }
+}
+
+impl<B> BatchReader for TraceSnapshot<B>
+where
+    B: Batch,
+    B::Key: Ord,
+    B::Val: Ord,
+{
+    type Key = B::Key;
+    type Val = B::Val;
+    type Time = B::Time;
+    type R = B::R;
+    type Cursor = TraceSnapshotCursor<B>;
+
+    fn cursor(&self) -> Self::Cursor {
+        TraceSnapshotCursor {
+            cursor: CursorList::new(
+                self.batches.iter().map(|batch| batch.cursor()).collect(),
+                &self.batches.as_slice(),
+            ),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.batches.iter().map(|batch| batch.len()).sum()
+    }
+
+    fn lower(&self) -> &Antichain<Self::Time> {
+        &self.lower
+    }
+
+    fn upper(&self) -> &Antichain<Self::Time> {
+        &self.upper
+    }
+}
+
+/// Tracks the updates of a trace consumed by a reader.
+///
+/// An operator that processes a trace incrementally, i.e., only needs the
+/// updates added to the trace since it last read it, keeps a
+/// `ConsumedFrontier` and calls [`updates`](`Self::updates`) instead of
+/// scanning the whole trace.  The method returns the batches that may
+/// contain updates at or after the consumed frontier (see
+/// [`TraceReader::updates_since`]) and advances the frontier to the upper
+/// bounds of all batches in the trace.
+///
+/// Only traces whose timestamps advance can be tracked this way.  Batches
+/// with unit timestamps, i.e., untimed traces, have an empty upper bound,
+/// which makes updates added after the first call to
+/// [`updates`](`Self::updates`) indistinguishable from consumed ones.
+#[derive(Clone, Debug)]
+pub struct ConsumedFrontier<T> {
+    frontier: Antichain<T>,
+}
+
+impl<T> Default for ConsumedFrontier<T>
+where
+    T: Timestamp + Lattice,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ConsumedFrontier<T>
+where
+    T: Timestamp + Lattice,
+{
+    /// Create a tracker that has not consumed any updates.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` is an untimed timestamp type, i.e., one whose clock
+    /// does not advance, such as `()`.
+    pub fn new() -> Self {
+        assert!(
+            T::minimum().advance(0) != T::minimum(),
+            "ConsumedFrontier cannot track updates of untimed traces"
+        );
+        Self {
+            frontier: Antichain::from_elem(T::minimum()),
+        }
+    }
+
+    /// All updates at times not greater than or equal to an element of the
+    /// frontier have been consumed.
+    pub fn frontier(&self) -> &Antichain<T> {
+        &self.frontier
+    }
+
+    /// Returns the updates in `trace` that have not been consumed yet and
+    /// marks all updates in the trace as consumed.
+    pub fn updates<Tr>(&mut self, trace: &Tr) -> TraceSnapshot<Tr::Batch>
+    where
+        Tr: TraceReader<Time = T>,
+    {
+        let snapshot = trace.updates_since(&self.frontier);
+
+        let frontier = &mut self.frontier;
+        trace.map_batches(|batch| {
+            if !batch.is_empty() {
+                *frontier = frontier.join(batch.upper());
+            }
+        });
+        snapshot
+    }
+
+    /// Forget consumed updates, e.g., when the trace is cleared or its
+    /// timestamps are pushed back via
+    /// [`Trace::recede_to`](`crate::trace::Trace::recede_to`).
+    pub fn reset(&mut self) {
+        self.frontier = Antichain::from_elem(T::minimum());
+    }
+}
+
+/// Cursor over the contents of a [`TraceSnapshot`].
+pub struct TraceSnapshotCursor<B>
+where
+    B: Batch,
+{
+    #[allow(clippy::type_complexity)]
+    cursor: CursorList<B::Key, B::Val, B::Time, B::R, B::Cursor>,
+}
+
+impl<B> Cursor<B::Key, B::Val, B::Time, B::R> for TraceSnapshotCursor<B>
+where
+    B: Batch,
+    B::Key: Ord,
+    B::Val: Ord,
+{
+    type Storage = TraceSnapshot<B>;
+
+    #[inline]
+    fn key_valid(&self, _snapshot: &Self::Storage) -> bool {
+        self.cursor.key_valid_in()
+    }
+    #[inline]
+    fn val_valid(&self, _snapshot: &Self::Storage) -> bool {
+        self.cursor.val_valid_in()
+    }
+
+    #[inline]
+    fn key<'a>(&self, snapshot: &'a Self::Storage) -> &'a B::Key {
+        self.cursor.key_in(&snapshot.batches.as_slice())
+    }
+    #[inline]
+    fn val<'a>(&self, snapshot: &'a Self::Storage) -> &'a B::Val {
+        self.cursor.val_in(&snapshot.batches.as_slice())
+    }
+    #[inline]
+    fn map_times<L: FnMut(&B::Time, &B::R)>(&mut self, snapshot: &Self::Storage, logic: L) {
+        self.cursor
+            .map_times_in(&snapshot.batches.as_slice(), logic);
+    }
+
+    #[inline]
+    fn weight(&mut self, snapshot: &Self::Storage) -> B::R
+    where
+        B::Time: PartialEq<()>,
+    {
+        self.cursor.weight_in(&snapshot.batches.as_slice())
+    }
+
+    #[inline]
+    fn step_key(&mut self, snapshot: &Self::Storage) {
+        self.cursor.step_key_in(&snapshot.batches.as_slice());
+    }
+
+    #[inline]
+    fn seek_key(&mut self, snapshot: &Self::Storage, key: &B::Key) {
+        self.cursor.seek_key_in(&snapshot.batches.as_slice(), key);
+    }
+
+    #[inline]
+    fn seek_key_with<P>(&mut self, snapshot: &Self::Storage, predicate: P)
+    where
+        P: Fn(&B::Key) -> bool,
+    {
+        self.cursor
+            .seek_key_with_in(&snapshot.batches.as_slice(), predicate);
+    }
+
+    #[inline]
+    fn step_val(&mut self, snapshot: &Self::Storage) {
+        self.cursor.step_val_in(&snapshot.batches.as_slice());
+    }
+
+    #[inline]
+    fn seek_val(&mut self, snapshot: &Self::Storage, val: &B::Val) {
+        self.cursor.seek_val_in(&snapshot.batches.as_slice(), val);
+    }
+
+    #[inline]
+    fn rewind_keys(&mut self, snapshot: &Self::Storage) {
+        self.cursor.rewind_keys_in(&snapshot.batches.as_slice());
+    }
+
+    #[inline]
+    fn rewind_vals(&mut self, snapshot: &Self::Storage) {
+        self.cursor.rewind_vals_in(&snapshot.batches.as_slice());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        time::NestedTimestamp32,
+        trace::{
+            ord::{OrdKeyBatch, OrdKeySpine, OrdZSet, OrdZSetSpine},
+            Batch, BatchReader, ConsumedFrontier, Cursor, Trace, TraceReader, TraceSnapshot,
+        },
+    };
+    use std::rc::Rc;
+    use timely::progress::Antichain;
+
+    #[test]
+    fn snapshot() {
+        let mut spine = OrdZSetSpine::<u64, isize>::new(None);
+        spine.insert(Rc::new(OrdZSet::from_tuples(
+            (),
+            vec![((1, ()), 1), ((2, ()), 1)],
+        )));
+
+        let snapshot = spine.snapshot();
+
+        // Modifying the trace does not affect the snapshot.
+        spine.insert(Rc::new(OrdZSet::from_tuples((), vec![((2, ()), 1)])));
+        let mut fuel = isize::MAX;
+        spine.exert(&mut fuel);
+
+        let mut contents = Vec::new();
+        let mut cursor = snapshot.cursor();
+        while cursor.key_valid(&snapshot) {
+            contents.push((*cursor.key(&snapshot), cursor.weight(&snapshot)));
+            cursor.step_key(&snapshot);
+        }
+        assert_eq!(contents, vec![(1, 1), (2, 1)]);
+        assert_eq!(snapshot.len(), 2);
+    }
+
+    #[test]
+    fn snapshot_recede_retain() {
+        let time = |inner| NestedTimestamp32::new(false, inner);
+        let mut spine = OrdKeySpine::<u64, NestedTimestamp32, isize>::new(None);
+        spine.insert(Rc::new(OrdKeyBatch::from_tuples(
+            time(2),
+            vec![((1, ()), 1), ((2, ()), 1)],
+        )));
+
+        // Returns `(key, time, weight)` tuples in `snapshot`.
+        let contents = |snapshot: &TraceSnapshot<_>| {
+            let mut result = Vec::new();
+            let mut cursor = snapshot.cursor();
+            while cursor.key_valid(snapshot) {
+                let key = *cursor.key(snapshot);
+                cursor.map_times(snapshot, |t: &NestedTimestamp32, w| {
+                    result.push((key, t.clone(), *w))
+                });
+                cursor.step_key(snapshot);
+            }
+            result
+        };
+
+        let before = spine.snapshot();
+        spine.recede_to(&time(1));
+        spine.retain(|key, _| key % 2 == 0);
+        spine.compact();
+
+        assert_eq!(contents(&before), vec![(1, time(2), 1), (2, time(2), 1)]);
+        assert_eq!(contents(&spine.snapshot()), vec![(2, time(1), 1)]);
+    }
+
+    #[test]
+    fn updates_since() {
+        let time = |inner| NestedTimestamp32::new(false, inner);
+        let mut spine = OrdKeySpine::<u64, NestedTimestamp32, isize>::new(None);
+        let mut consumed = ConsumedFrontier::new();
+
+        // Returns updates at times not less than `frontier` in `snapshot`.
+        let recent = |snapshot: &TraceSnapshot<_>, frontier: &Antichain<NestedTimestamp32>| {
+            let mut result = Vec::new();
+            let mut cursor = snapshot.cursor();
+            while cursor.key_valid(snapshot) {
+                let key = *cursor.key(snapshot);
+                cursor.map_times(snapshot, |t, w| {
+                    if frontier.less_equal(t) {
+                        result.push((key, *w));
+                    }
+                });
+                cursor.step_key(snapshot);
+            }
+            result
+        };
+
+        spine.insert(Rc::new(OrdKeyBatch::from_tuples(
+            time(0),
+            vec![((1, ()), 1), ((2, ()), 1)],
+        )));
+        let frontier = consumed.frontier().clone();
+        let snapshot = consumed.updates(&spine);
+        assert_eq!(recent(&snapshot, &frontier), vec![(1, 1), (2, 1)]);
+        assert_eq!(consumed.frontier(), &Antichain::from_elem(time(1)));
+
+        // Nothing new.
+        assert!(consumed.updates(&spine).is_empty());
+
+        spine.insert(Rc::new(OrdKeyBatch::from_tuples(
+            time(1),
+            vec![((2, ()), -1), ((3, ()), 1)],
+        )));
+        let frontier = consumed.frontier().clone();
+        let snapshot = consumed.updates(&spine);
+        assert_eq!(recent(&snapshot, &frontier), vec![(2, -1), (3, 1)]);
+
+        // The frontier is beyond all updates in the trace.
+        assert!(spine
+            .updates_since(&Antichain::from_elem(time(2)))
+            .is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "ConsumedFrontier cannot track updates of untimed traces")]
+    fn untimed_consumed_frontier() {
+        ConsumedFrontier::<()>::new();
+    }
+}
@@ -80,23 +80,177 @@
 //! layers by continuing to provide fuel as updates arrive.
 
 use std::{
-    cell::RefCell,
     fmt::{Display, Formatter},
-    mem::replace,
+    mem::{replace, take},
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 use crate::{
     lattice::Lattice,
     time::Timestamp,
     trace::{
-        cursor::{Cursor, CursorList},
-        Antichain, Batch, BatchReader, Merger, Trace, TraceReader,
+        cursor::{Cursor, CursorList, CursorStorage},
+        Antichain, Batch, BatchReader, MaintenanceDebt, Merger, Trace, TraceReader,
     },
     NumEntries,
 };
 use deepsize::DeepSizeOf;
 use textwrap::indent;
 
+/// Receives notifications about merges performed by a [`Spine`].
+///
+/// Observers allow applications and metrics exporters to monitor compaction,
+/// e.g., to detect merge debt building up when merges take longer to
+/// complete than new batches take to arrive.  Levels are numbered from 0,
+/// with batches at level `i` containing roughly `2^i` tuples.
+pub trait MergeObserver {
+    /// Invoked when the spine starts merging two batches with a total of
+    /// `tuples` tuples at `level`.
+    fn on_merge_start(&self, _level: usize, _tuples: usize) {}
+
+    /// Invoked when a merge at `level` completes, producing a batch with
+    /// `tuples` tuples.  `duration` is the wall-clock time elapsed since the
+    /// merge started, including time the merge spent waiting for fuel.
+    fn on_merge_complete(&self, _level: usize, _tuples: usize, _duration: Duration) {}
+}
+
+/// Feedback controller that adjusts the effort multiplier of a [`Spine`]
+/// (see [`Spine::with_effort`]) at runtime.
+///
+/// After each insertion, the controller compares the time the spine spent
+/// on the insertion, including merge work, against a target latency and
+/// inspects the merge backlog, i.e., the number of tuples in merges that are
+/// still in progress (see [`Spine::merge_backlog`]):
+///
+/// * If the backlog exceeds `max_backlog`, the effort is doubled, so that
+///   merges catch up before they must be forcibly completed.
+/// * Otherwise, if the insertion took longer than the target latency, the
+///   effort is halved to return control to the circuit promptly.
+/// * Otherwise, if there is outstanding merge work, the effort is increased
+///   by one to use the remaining latency budget to pay it off.
+///
+/// The effort always stays within `[min_effort, max_effort]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EffortController {
+    target_latency: Duration,
+    min_effort: usize,
+    max_effort: usize,
+    max_backlog: usize,
+}
+
+impl EffortController {
+    /// Create a controller that aims to keep the latency of insertions below
+    /// `target_latency`, with effort between 1 and 64 and a maximal backlog
+    /// of 2^20 tuples.
+    pub fn new(target_latency: Duration) -> Self {
+        Self {
+            target_latency,
+            min_effort: 1,
+            max_effort: 64,
+            max_backlog: 1 << 20,
+        }
+    }
+
+    /// Set the range of effort values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min_effort` is zero or greater than `max_effort`.
+    pub fn with_bounds(mut self, min_effort: usize, max_effort: usize) -> Self {
+        assert!(min_effort > 0, "effort must be at least one");
+        assert!(
+            min_effort <= max_effort,
+            "minimal effort exceeds maximal effort"
+        );
+        self.min_effort = min_effort;
+        self.max_effort = max_effort;
+        self
+    }
+
+    /// Set the number of tuples in in-progress merges above which the
+    /// controller increases the effort regardless of latency.
+    pub fn with_max_backlog(mut self, max_backlog: usize) -> Self {
+        self.max_backlog = max_backlog;
+        self
+    }
+
+    /// Compute the next effort value from the current `effort`, the
+    /// `latency` of the last insertion and the current merge `backlog`.
+    pub fn next_effort(&self, effort: usize, latency: Duration, backlog: usize) -> usize {
+        let effort = if backlog > self.max_backlog {
+            effort.saturating_mul(2)
+        } else if latency > self.target_latency {
+            effort / 2
+        } else if backlog > 0 {
+            effort + 1
+        } else {
+            effort
+        };
+        effort.clamp(self.min_effort, self.max_effort)
+    }
+}
+
+/// Merge effort of a trace: the initial effort multiplier (see
+/// [`Spine::with_effort`]) and an optional [`EffortController`] that adjusts
+/// it at runtime.
+///
+/// Traces maintained by operators are configured with
+/// [`Circuit::with_trace_effort`](`crate::circuit::Circuit::with_trace_effort`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EffortConfig {
+    /// Initial effort multiplier.  Zero is treated as one.
+    pub effort: usize,
+    /// Controller that adjusts the effort after every insertion.
+    pub controller: Option<EffortController>,
+}
+
+impl EffortConfig {
+    /// Fixed effort multiplier `effort`.
+    pub fn new(effort: usize) -> Self {
+        Self {
+            effort,
+            controller: None,
+        }
+    }
+
+    /// Adjust the effort at runtime using `controller`, starting from the
+    /// effort multiplier of `self`.
+    pub fn with_controller(mut self, controller: EffortController) -> Self {
+        self.controller = Some(controller);
+        self
+    }
+}
+
+impl Default for EffortConfig {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+/// Predicate registered with [`Trace::retain`].
+type RetainFn<B> =
+    Arc<dyn Fn(&<B as BatchReader>::Key, &<B as BatchReader>::Val) -> bool + Send + Sync>;
+
+/// Applies the retention predicate, if any, to a merged batch.
+fn retain_merged<B: Batch>(batch: &mut B, retain: &Option<RetainFn<B>>) {
+    if let Some(retain) = retain {
+        retain_batch(batch, &**retain);
+    }
+}
+
+/// Removes updates that do not satisfy `retain` from `batch`.
+///
+/// The batch is filtered in place unless it is shared, e.g., with a
+/// [`TraceSnapshot`](`crate::trace::TraceSnapshot`), in which case it is
+/// replaced with a filtered copy and the other references keep seeing the
+/// original contents.
+fn retain_batch<B: Batch>(batch: &mut B, retain: &dyn Fn(&B::Key, &B::Val) -> bool) {
+    if !batch.try_retain(retain) {
+        batch.retain(retain);
+    }
+}
+
 /// An append-only collection of update tuples.
 ///
 /// A spine maintains a small number of immutable collections of update tuples,
@@ -110,13 +264,16 @@ where
     merging: Vec<MergeState<B>>,
     lower: Antichain<B::Time>,
     upper: Antichain<B::Time>,
-    // Batches from `merging` stored in a flat array, for use by `SpineCursor`.
-    // Any operation that modifies spine invalidates this vector (and the associated
-    // cursor, if any).
-    cursor_storage: RefCell<Vec<B>>,
     effort: usize,
     activator: Option<timely::scheduling::activate::Activator>,
     dirty: bool,
+    observer: Option<Arc<dyn MergeObserver>>,
+    max_batches: Option<usize>,
+    retain: Option<RetainFn<B>>,
+    effort_controller: Option<EffortController>,
+    // Incremented whenever batches in `merging` may be replaced, moved or
+    // modified, which invalidates outstanding cursors.
+    generation: u64,
 }
 
 impl<B> Display for Spine<B>
@@ -209,42 +366,34 @@ where
 
     fn cursor(&self) -> Self::Cursor {
         let mut cursors = Vec::new();
-        let mut storage = Vec::new();
+        let mut locations = Vec::new();
 
-        for merge_state in self.merging.iter().rev() {
+        for (level, merge_state) in self.merging.iter().enumerate().rev() {
             match merge_state {
-                MergeState::Double(variant) => match variant {
-                    MergeVariant::InProgress(batch1, batch2, _) => {
-                        if !batch1.is_empty() {
-                            cursors.push(batch1.cursor());
-                            storage.push(batch1.clone());
-                        }
-                        if !batch2.is_empty() {
-                            cursors.push(batch2.cursor());
-                            storage.push(batch2.clone());
-                        }
+                MergeState::Double(MergeVariant::InProgress(batch1, batch2, ..)) => {
+                    if !batch1.is_empty() {
+                        cursors.push(batch1.cursor());
+                        locations.push((level, 0));
                     }
-                    MergeVariant::Complete(Some(batch)) => {
-                        if !batch.is_empty() {
-                            cursors.push(batch.cursor());
-                            storage.push(batch.clone());
-                        }
+                    if !batch2.is_empty() {
+                        cursors.push(batch2.cursor());
+                        locations.push((level, 1));
                     }
-                    MergeVariant::Complete(None) => {}
-                },
-                MergeState::Single(Some(batch)) => {
+                }
+                MergeState::Double(MergeVariant::Complete(Some(batch)))
+                | MergeState::Single(Some(batch)) => {
                     if !batch.is_empty() {
                         cursors.push(batch.cursor());
-                        storage.push(batch.clone());
+                        locations.push((level, 0));
                     }
                 }
-                MergeState::Single(None) => {}
-                MergeState::Vacant => {}
+                MergeState::Double(MergeVariant::Complete(None))
+                | MergeState::Single(None)
+                | MergeState::Vacant => {}
             }
         }
 
-        *self.cursor_storage.borrow_mut() = storage;
-        SpineCursor::new(cursors, self)
+        SpineCursor::new(cursors, locations, self)
     }
 }
 
@@ -259,7 +408,7 @@ where
     fn map_batches<F: FnMut(&Self::Batch)>(&self, mut f: F) {
         for batch in self.merging.iter().rev() {
             match batch {
-                MergeState::Double(MergeVariant::InProgress(batch1, batch2, _)) => {
+                MergeState::Double(MergeVariant::InProgress(batch1, batch2, ..)) => {
                     f(batch1);
                     f(batch2);
                 }
@@ -269,19 +418,78 @@ where
             }
         }
     }
+
+    fn maintenance_debt(&self) -> MaintenanceDebt {
+        let mut debt = MaintenanceDebt::default();
+        for merge_state in self.merging.iter() {
+            if merge_state.is_inprogress() {
+                debt.merges += 1;
+                debt.tuples += merge_state.len();
+                debt.fuel += merge_state.remaining_fuel();
+            }
+        }
+        debt
+    }
 }
 
 impl<B: Batch> Spine<B> {
-    fn cursor_storage_unchecked(&self) -> &Vec<B> {
-        // Safety: references returned by this method should never escape this module
-        // and should only ne used in non-reentrant code.
-        unsafe { &*self.cursor_storage.as_ptr() }
+    /// Invalidates outstanding cursors.  Must be called by every method
+    /// that may modify `self.merging`.
+    fn invalidate_cursors(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Returns the batch at `location`, specified as a level in `merging` and
+    /// the index of the batch within the level.
+    fn batch_at(&self, (level, index): (usize, usize)) -> &B {
+        match (&self.merging[level], index) {
+            (MergeState::Double(MergeVariant::InProgress(batch, ..)), 0)
+            | (MergeState::Double(MergeVariant::InProgress(_, batch, ..)), 1)
+            | (MergeState::Double(MergeVariant::Complete(Some(batch))), 0)
+            | (MergeState::Single(Some(batch)), 0) => batch,
+            _ => panic!("SpineCursor used after the spine was modified"),
+        }
+    }
+}
+
+/// Batches of a spine, in the order of cursors in a `SpineCursor`.
+struct SpineBatches<'s, 'l, B: Batch> {
+    spine: &'s Spine<B>,
+    locations: &'l [(usize, usize)],
+}
+
+impl<'s, 'l, B: Batch> SpineBatches<'s, 'l, B> {
+    /// Panics if the spine was modified since the cursor that owns
+    /// `locations` was created at `generation`.
+    fn new(spine: &'s Spine<B>, locations: &'l [(usize, usize)], generation: u64) -> Self {
+        assert_eq!(
+            spine.generation, generation,
+            "SpineCursor used after the spine was modified"
+        );
+        Self { spine, locations }
     }
 }
 
+impl<'s, 'l, B: Batch> CursorStorage<'s, B> for SpineBatches<'s, 'l, B> {
+    #[inline]
+    fn storage(&self, index: usize) -> &'s B {
+        self.spine.batch_at(self.locations[index])
+    }
+}
+
+/// Cursor over all batches in a [`Spine`].
+///
+/// The cursor references batches in the spine directly, without cloning
+/// them, and is invalidated by any operation that modifies the spine, e.g.,
+/// inserting a batch or applying fuel to merges.  Using an invalidated cursor
+/// panics.
 pub struct SpineCursor<B: Batch> {
     #[allow(clippy::type_complexity)]
     cursor: CursorList<B::Key, B::Val, B::Time, B::R, B::Cursor>,
+    // Location of the batch traversed by each cursor in `cursor`.
+    locations: Vec<(usize, usize)>,
+    // `Spine::generation` when the cursor was created.
+    generation: u64,
 }
 
 impl<B: Batch> SpineCursor<B>
@@ -289,11 +497,35 @@ where
     B::Key: Ord,
     B::Val: Ord,
 {
-    fn new(cursors: Vec<B::Cursor>, spine: &Spine<B>) -> Self {
+    fn new(cursors: Vec<B::Cursor>, locations: Vec<(usize, usize)>, spine: &Spine<B>) -> Self {
+        let generation = spine.generation;
+        let cursor = CursorList::new(cursors, &SpineBatches::new(spine, &locations, generation));
         Self {
-            cursor: CursorList::new(cursors, spine.cursor_storage_unchecked()),
+            cursor,
+            locations,
+            generation,
         }
     }
+
+    fn batches<'s, 'l>(&'l self, spine: &'s Spine<B>) -> SpineBatches<'s, 'l, B> {
+        SpineBatches::new(spine, &self.locations, self.generation)
+    }
+
+    /// Like [`Self::batches`], but also returns the underlying cursor list,
+    /// for methods that advance the cursor.
+    #[allow(clippy::type_complexity)]
+    fn split<'s, 'l>(
+        &'l mut self,
+        spine: &'s Spine<B>,
+    ) -> (
+        &'l mut CursorList<B::Key, B::Val, B::Time, B::R, B::Cursor>,
+        SpineBatches<'s, 'l, B>,
+    ) {
+        (
+            &mut self.cursor,
+            SpineBatches::new(spine, &self.locations, self.generation),
+        )
+    }
 }
 
 impl<B: Batch> Cursor<B::Key, B::Val, B::Time, B::R> for SpineCursor<B>
@@ -304,26 +536,26 @@ where
     type Storage = Spine<B>;
 
     #[inline]
-    fn key_valid(&self, spine: &Self::Storage) -> bool {
-        self.cursor.key_valid(spine.cursor_storage_unchecked())
+    fn key_valid(&self, _spine: &Self::Storage) -> bool {
+        self.cursor.key_valid_in()
     }
     #[inline]
-    fn val_valid(&self, spine: &Self::Storage) -> bool {
-        self.cursor.val_valid(spine.cursor_storage_unchecked())
+    fn val_valid(&self, _spine: &Self::Storage) -> bool {
+        self.cursor.val_valid_in()
     }
 
     #[inline]
     fn key<'a>(&self, spine: &'a Self::Storage) -> &'a B::Key {
-        self.cursor.key(spine.cursor_storage_unchecked())
+        self.cursor.key_in(&self.batches(spine))
     }
     #[inline]
     fn val<'a>(&self, spine: &'a Self::Storage) -> &'a B::Val {
-        self.cursor.val(spine.cursor_storage_unchecked())
+        self.cursor.val_in(&self.batches(spine))
     }
     #[inline]
     fn map_times<L: FnMut(&B::Time, &B::R)>(&mut self, spine: &Self::Storage, logic: L) {
-        self.cursor
-            .map_times(spine.cursor_storage_unchecked(), logic);
+        let (cursor, batches) = self.split(spine);
+        cursor.map_times_in(&batches, logic);
     }
 
     #[inline]
@@ -331,37 +563,53 @@ where
     where
         B::Time: PartialEq<()>,
     {
-        self.cursor.weight(spine.cursor_storage_unchecked())
+        let (cursor, batches) = self.split(spine);
+        cursor.weight_in(&batches)
     }
 
     #[inline]
     fn step_key(&mut self, spine: &Self::Storage) {
-        self.cursor.step_key(spine.cursor_storage_unchecked());
+        let (cursor, batches) = self.split(spine);
+        cursor.step_key_in(&batches);
     }
 
     #[inline]
     fn seek_key(&mut self, spine: &Self::Storage, key: &B::Key) {
-        self.cursor.seek_key(spine.cursor_storage_unchecked(), key);
+        let (cursor, batches) = self.split(spine);
+        cursor.seek_key_in(&batches, key);
+    }
+
+    #[inline]
+    fn seek_key_with<P>(&mut self, spine: &Self::Storage, predicate: P)
+    where
+        P: Fn(&B::Key) -> bool,
+    {
+        let (cursor, batches) = self.split(spine);
+        cursor.seek_key_with_in(&batches, predicate);
     }
 
     #[inline]
     fn step_val(&mut self, spine: &Self::Storage) {
-        self.cursor.step_val(spine.cursor_storage_unchecked());
+        let (cursor, batches) = self.split(spine);
+        cursor.step_val_in(&batches);
     }
 
     #[inline]
     fn seek_val(&mut self, spine: &Self::Storage, val: &B::Val) {
-        self.cursor.seek_val(spine.cursor_storage_unchecked(), val);
+        let (cursor, batches) = self.split(spine);
+        cursor.seek_val_in(&batches, val);
     }
 
     #[inline]
     fn rewind_keys(&mut self, spine: &Self::Storage) {
-        self.cursor.rewind_keys(spine.cursor_storage_unchecked());
+        let (cursor, batches) = self.split(spine);
+        cursor.rewind_keys_in(&batches);
     }
 
     #[inline]
     fn rewind_vals(&mut self, spine: &Self::Storage) {
-        self.cursor.rewind_vals(spine.cursor_storage_unchecked());
+        let (cursor, batches) = self.split(spine);
+        cursor.rewind_vals_in(&batches);
     }
 }
 
@@ -376,10 +624,9 @@ where
     }
 
     fn recede_to(&mut self, frontier: &B::Time) {
-        self.cursor_storage.borrow_mut().clear();
-
         // Complete all in-progress merges, as we don't have an easy way to update
         // timestamps in an ongoing merge.
+        self.invalidate_cursors();
         self.complete_merges();
 
         self.map_batches_mut(|b| b.recede_to(frontier));
@@ -391,8 +638,7 @@ where
     /// thought of as analogous to inserting as many empty updates,
     /// where the trace is permitted to perform proportionate work.
     fn exert(&mut self, effort: &mut isize) {
-        self.cursor_storage.borrow_mut().clear();
-
+        self.invalidate_cursors();
         // If there is work to be done, ...
         self.tidy_layers();
         if !self.reduced() {
@@ -411,11 +657,10 @@ where
                 activator.activate();
             }
         }
+        self.enforce_max_batches();
     }
 
     fn consolidate(mut self) -> Option<Self::Batch> {
-        self.cursor_storage.borrow_mut().clear();
-
         // Merge batches until there is nothing left to merge.
         let mut fuel = isize::max_value();
         while !self.reduced() {
@@ -440,8 +685,6 @@ where
     fn insert(&mut self, batch: Self::Batch) {
         assert!(batch.lower() != batch.upper());
 
-        self.cursor_storage.borrow_mut().clear();
-
         // Ignore empty batches.
         // Note: we may want to use empty batches to artificially force compaction.
         if batch.is_empty() {
@@ -449,14 +692,24 @@ where
         }
 
         self.dirty = true;
+        self.invalidate_cursors();
         self.lower = self.lower.meet(batch.lower());
         self.upper = self.upper.join(batch.upper());
 
         // Leonid: we do not require batch bounds to grow monotonically.
         //assert_eq!(batch.lower(), &self.upper);
 
+        // Only measure the latency of the insertion if there is a controller
+        // to consume it.
+        let start = self.effort_controller.as_ref().map(|_| Instant::now());
         let index = batch.len().next_power_of_two();
         self.introduce_batch(Some(batch), index.trailing_zeros() as usize);
+        self.enforce_max_batches();
+
+        if let (Some(controller), Some(start)) = (&self.effort_controller, start) {
+            self.effort =
+                controller.next_effort(self.effort, start.elapsed(), self.merge_backlog());
+        }
 
         // If more than one batch remains reschedule ourself.
         if !self.reduced() {
@@ -466,6 +719,18 @@ where
         }
     }
 
+    fn retain<F>(&mut self, retain: F)
+    where
+        F: Fn(&B::Key, &B::Val) -> bool + Send + Sync + 'static,
+    {
+        self.retain = Some(Arc::new(retain));
+    }
+
+    fn configure_effort(&mut self, config: &EffortConfig) {
+        self.set_effort(config.effort);
+        self.set_effort_controller(config.controller.clone());
+    }
+
     fn clear_dirty_flag(&mut self) {
         self.dirty = false;
     }
@@ -486,7 +751,7 @@ where
     /// When true, there is no maintenance work to perform in the trace, other
     /// than compaction. We do not yet have logic in place to determine if
     /// compaction would improve a trace, so for now we are ignoring that.
-    fn reduced(&self) -> bool {
+    pub(crate) fn reduced(&self) -> bool {
         let mut non_empty = 0;
         for index in 0..self.merging.len() {
             if self.merging[index].is_double() {
@@ -533,13 +798,174 @@ where
         }
 
         Spine {
-            cursor_storage: RefCell::new(Vec::new()),
             lower: Antichain::from_elem(B::Time::minimum()),
             upper: Antichain::new(),
             merging: Vec::new(),
             effort,
             activator,
             dirty: false,
+            observer: None,
+            max_batches: None,
+            retain: None,
+            effort_controller: None,
+            generation: 0,
+        }
+    }
+
+    /// Creates a spine that contains `batches`, ordered from oldest to
+    /// newest, without merging them.
+    ///
+    /// The batches are shared with the caller when `B` is a reference-counted
+    /// batch type.  Each batch occupies its own layer, newest first, and is
+    /// merged with its neighbors by subsequent maintenance work.
+    pub fn from_batches<I>(
+        batches: I,
+        activator: Option<timely::scheduling::activate::Activator>,
+    ) -> Self
+    where
+        I: IntoIterator<Item = B>,
+    {
+        let mut spine = Self::with_effort(1, activator);
+        for batch in batches {
+            if batch.is_empty() {
+                continue;
+            }
+            spine.dirty = true;
+            spine.lower = spine.lower.meet(batch.lower());
+            spine.upper = spine.upper.join(batch.upper());
+            spine.merging.push(MergeState::Single(Some(batch)));
+        }
+        spine.merging.reverse();
+        spine
+    }
+
+    /// Registers an observer to be notified about merges in the spine,
+    /// replacing the previous observer, if any.
+    pub fn set_merge_observer(&mut self, observer: Arc<dyn MergeObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// The current effort multiplier.
+    pub fn effort(&self) -> usize {
+        self.effort
+    }
+
+    /// Sets the effort multiplier (see [`Self::with_effort`]).  Zero is
+    /// treated as one.
+    pub fn set_effort(&mut self, effort: usize) {
+        self.effort = effort.max(1);
+    }
+
+    /// Installs a controller that adjusts the effort multiplier after every
+    /// insertion (see [`EffortController`]), or removes the controller,
+    /// leaving the effort at its current value.
+    pub fn set_effort_controller(&mut self, controller: Option<EffortController>) {
+        self.effort_controller = controller;
+    }
+
+    /// The number of tuples in merges that are still in progress (see
+    /// [`MaintenanceDebt::tuples`]).
+    pub fn merge_backlog(&self) -> usize {
+        self.maintenance_debt().tuples
+    }
+
+    /// Caps the number of non-empty batches in the spine.
+    ///
+    /// Reading a spine requires merging cursors over all of its batches, so
+    /// read-heavy workloads, e.g., joins that repeatedly probe a large trace,
+    /// benefit from keeping the number of batches small.  When the number of
+    /// batches exceeds `max_batches` after an insertion or maintenance step,
+    /// the spine completes all merges and compacts its contents into a single
+    /// batch, trading step latency for cheaper reads.  `None` (the default)
+    /// disables the cap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_batches` is `Some(0)`.
+    pub fn set_max_batches(&mut self, max_batches: Option<usize>) {
+        assert_ne!(
+            max_batches,
+            Some(0),
+            "a spine must be allowed at least one batch"
+        );
+        self.max_batches = max_batches;
+        self.enforce_max_batches();
+    }
+
+    /// The number of non-empty batches in the spine.
+    pub fn num_batches(&self) -> usize {
+        let mut result = 0;
+        self.map_batches(|batch| {
+            if !batch.is_empty() {
+                result += 1
+            }
+        });
+        result
+    }
+
+    /// Compacts the spine if it contains more than `self.max_batches` batches.
+    fn enforce_max_batches(&mut self) {
+        if let Some(max_batches) = self.max_batches {
+            if self.num_batches() > max_batches {
+                self.compact();
+            }
+        }
+    }
+
+    /// Merges all batches in the spine into a single batch.
+    ///
+    /// Updates that do not satisfy the predicate registered with
+    /// [`Trace::retain`] are discarded from the merged batch, so compacting
+    /// the spine forces garbage collection of all such updates.
+    pub fn compact(&mut self) {
+        self.compact_inner(None);
+    }
+
+    /// Like [`Self::compact`], but additionally discards updates that do not
+    /// satisfy `retain`.
+    ///
+    /// Unlike the predicate registered with [`Trace::retain`], `retain` is
+    /// only applied once, so it can be used to drop data identified at
+    /// runtime, e.g., expired keys, without affecting future updates.
+    pub fn compact_with(&mut self, retain: &dyn Fn(&B::Key, &B::Val) -> bool) {
+        self.compact_inner(Some(retain));
+    }
+
+    /// Discards updates that do not satisfy `retain` from all batches in the
+    /// spine.
+    ///
+    /// Unlike [`Self::compact_with`], this method does not merge batches.  It
+    /// completes merges in progress and then filters each batch separately,
+    /// in place unless the batch is shared (see [`Batch::try_retain`]).
+    pub fn retain_batches(&mut self, retain: &dyn Fn(&B::Key, &B::Val) -> bool) {
+        self.invalidate_cursors();
+        self.complete_merges();
+        self.map_batches_mut(|batch| retain_batch(batch, retain));
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn compact_inner(&mut self, extra_retain: Option<&dyn Fn(&B::Key, &B::Val) -> bool>) {
+        self.invalidate_cursors();
+        self.complete_merges();
+
+        // Levels are ordered from smallest to largest batches.
+        let mut merged: Option<B> = None;
+        for mut merge_state in take(&mut self.merging) {
+            if let Some(batch) = merge_state.complete() {
+                merged = Some(match merged {
+                    Some(smaller) => smaller.merge(&batch),
+                    None => batch,
+                });
+            }
+        }
+
+        if let Some(mut batch) = merged {
+            retain_merged(&mut batch, &self.retain);
+            if let Some(retain) = extra_retain {
+                retain_batch(&mut batch, retain);
+            }
+            let level = batch.len().next_power_of_two().trailing_zeros() as usize;
+            self.insert_at(Some(batch), level);
         }
     }
 
@@ -549,6 +975,8 @@ where
     /// it can also be used to artificially fuel the computation by supplying
     /// empty batches at non-trivial indices, to move merges along.
     pub fn introduce_batch(&mut self, batch: Option<B>, batch_index: usize) {
+        self.invalidate_cursors();
+
         // Step 0.  Determine an amount of fuel to use for the computation.
         //
         //          Fuel is used to drive maintenance of the data structure,
@@ -671,6 +1099,8 @@ where
     /// (at the risk of completing merges of large batches later, but tbh
     /// probably not much later).
     pub fn apply_fuel(&mut self, fuel: &mut isize) {
+        self.invalidate_cursors();
+
         // For the moment our strategy is to apply fuel independently to each merge
         // in progress, rather than prioritizing small merges. This sounds like a
         // great idea, but we need better accounting in place to ensure that merges
@@ -680,7 +1110,14 @@ where
             // Give each level independent fuel, for now.
             let mut fuel = *fuel;
             // Pass along various logging stuffs, in case we need to report success.
+            let started = self.merging[index].merge_started();
             self.merging[index].work(&mut fuel);
+            if self.merging[index].is_complete() && started.is_some() {
+                if let Some(batch) = self.merging[index].merged_batch_mut() {
+                    retain_merged(batch, &self.retain);
+                }
+                self.notify_merge_complete(index, started, self.merging[index].len());
+            }
             // `fuel` could have a deficit at this point, meaning we over-spent when
             // we took a merge step. We could ignore this, or maintain the deficit
             // and account future fuel against it before spending again. It isn't
@@ -716,6 +1153,11 @@ where
             }
             MergeState::Single(old) => {
                 self.merging[index] = MergeState::begin_merge(old, batch);
+                if self.merging[index].is_inprogress() {
+                    if let Some(observer) = &self.observer {
+                        observer.on_merge_start(index, self.merging[index].len());
+                    }
+                }
             }
             MergeState::Double(_) => {
                 panic!("Attempted to insert batch into incomplete merge!")
@@ -725,7 +1167,22 @@ where
 
     /// Completes and extracts what ever is at layer `index`.
     fn complete_at(&mut self, index: usize) -> Option<B> {
-        self.merging[index].complete()
+        let started = self.merging[index].merge_started();
+        let mut batch = self.merging[index].complete();
+        if let (Some(batch), Some(_)) = (&mut batch, started) {
+            retain_merged(batch, &self.retain);
+        }
+        self.notify_merge_complete(index, started, batch.as_ref().map_or(0, |b| b.len()));
+        batch
+    }
+
+    /// Notifies the observer that a merge at `index` that started at
+    /// `started` has completed.  Does nothing if `started` is `None`, i.e.,
+    /// the layer did not contain an in-progress merge.
+    fn notify_merge_complete(&self, index: usize, started: Option<Instant>, tuples: usize) {
+        if let (Some(observer), Some(started)) = (&self.observer, started) {
+            observer.on_merge_complete(index, tuples, started.elapsed());
+        }
     }
 
     /// Attempts to draw down large layers to size appropriate layers.
@@ -796,10 +1253,14 @@ where
 
     /// Complete all in-progress merges (without starting any new ones).
     fn complete_merges(&mut self) {
-        for merge_state in self.merging.iter_mut() {
-            if merge_state.is_inprogress() {
+        for index in 0..self.merging.len() {
+            if let Some(started) = self.merging[index].merge_started() {
                 let mut fuel = isize::max_value();
-                merge_state.work(&mut fuel);
+                self.merging[index].work(&mut fuel);
+                if let Some(batch) = self.merging[index].merged_batch_mut() {
+                    retain_merged(batch, &self.retain);
+                }
+                self.notify_merge_complete(index, Some(started), self.merging[index].len());
             }
         }
         assert!(self.merging.iter().all(|m| !m.is_inprogress()));
@@ -810,7 +1271,7 @@ where
     fn map_batches_mut<F: FnMut(&mut <Self as TraceReader>::Batch)>(&mut self, mut f: F) {
         for batch in self.merging.iter_mut().rev() {
             match batch {
-                MergeState::Double(MergeVariant::InProgress(_batch1, _batch2, _)) => {
+                MergeState::Double(MergeVariant::InProgress(..)) => {
                     panic!("map_batches_mut called on an in-progress batch")
                 }
                 MergeState::Double(MergeVariant::Complete(Some(batch))) => f(batch),
@@ -842,7 +1303,7 @@ impl<B: Batch> MergeState<B> {
     fn len(&self) -> usize {
         match self {
             MergeState::Single(Some(b)) => b.len(),
-            MergeState::Double(MergeVariant::InProgress(b1, b2, _)) => b1.len() + b2.len(),
+            MergeState::Double(MergeVariant::InProgress(b1, b2, ..)) => b1.len() + b2.len(),
             MergeState::Double(MergeVariant::Complete(Some(b))) => b.len(),
             _ => 0,
         }
@@ -889,6 +1350,34 @@ impl<B: Batch> MergeState<B> {
         matches!(self, MergeState::Double(MergeVariant::InProgress(..)))
     }
 
+    /// The batch produced by a completed merge in this layer, if any.
+    fn merged_batch_mut(&mut self) -> Option<&mut B> {
+        match self {
+            MergeState::Double(MergeVariant::Complete(Some(batch))) => Some(batch),
+            _ => None,
+        }
+    }
+
+    /// The time when the in-progress merge in this layer started, if any.
+    fn merge_started(&self) -> Option<Instant> {
+        match self {
+            MergeState::Double(MergeVariant::InProgress(_, _, _, started, _)) => Some(*started),
+            _ => None,
+        }
+    }
+
+    /// Estimated fuel required to complete the in-progress merge in this
+    /// layer, if any, assuming that merging consumes one unit of fuel per
+    /// input tuple.
+    fn remaining_fuel(&self) -> usize {
+        match self {
+            MergeState::Double(MergeVariant::InProgress(b1, b2, _, _, spent)) => {
+                (b1.len() + b2.len()).saturating_sub(*spent)
+            }
+            _ => 0,
+        }
+    }
+
     /// Performs a bounded amount of work towards a merge.
     ///
     /// If the merge completes, the resulting batch is returned.
@@ -924,7 +1413,7 @@ impl<B: Batch> MergeState<B> {
                 //assert!(batch1.upper() == batch2.lower());
 
                 let begin_merge = <B as Batch>::begin_merge(&batch1, &batch2);
-                MergeVariant::InProgress(batch1, batch2, begin_merge)
+                MergeVariant::InProgress(batch1, batch2, begin_merge, Instant::now(), 0)
             }
             (None, Some(x)) => MergeVariant::Complete(Some(x)),
             (Some(x), None) => MergeVariant::Complete(Some(x)),
@@ -936,8 +1425,10 @@ impl<B: Batch> MergeState<B> {
 }
 
 enum MergeVariant<B: Batch> {
-    /// Describes an actual in-progress merge between two non-trivial batches.
-    InProgress(B, B, <B as Batch>::Merger),
+    /// Describes an actual in-progress merge between two non-trivial batches,
+    /// along with the time when the merge started and the amount of fuel
+    /// spent on it so far.
+    InProgress(B, B, <B as Batch>::Merger, Instant, usize),
     /// A merge that requires no further work. May or may not represent a
     /// non-trivial batch.
     Complete(Option<B>),
@@ -964,15 +1455,199 @@ impl<B: Batch> MergeVariant<B> {
     /// This allows the caller to manage the released resources.
     fn work(&mut self, fuel: &mut isize) {
         let variant = replace(self, MergeVariant::Complete(None));
-        if let MergeVariant::InProgress(b1, b2, mut merge) = variant {
+        if let MergeVariant::InProgress(b1, b2, mut merge, started, spent) = variant {
+            let initial_fuel = *fuel;
             merge.work(&b1, &b2, fuel);
             if *fuel > 0 {
                 *self = MergeVariant::Complete(Some(merge.done()));
             } else {
-                *self = MergeVariant::InProgress(b1, b2, merge);
+                let spent = spent + initial_fuel.saturating_sub(*fuel).max(0) as usize;
+                *self = MergeVariant::InProgress(b1, b2, merge, started, spent);
             }
         } else {
             *self = variant;
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{EffortController, MergeObserver, Spine};
+    use crate::trace::{ord::OrdZSet, Batch, BatchReader, Cursor, Trace, TraceReader};
+    use std::{
+        rc::Rc,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    #[derive(Default)]
+    struct CountingObserver {
+        started: AtomicUsize,
+        completed: AtomicUsize,
+    }
+
+    impl MergeObserver for CountingObserver {
+        fn on_merge_start(&self, _level: usize, _tuples: usize) {
+            self.started.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_merge_complete(&self, _level: usize, _tuples: usize, _duration: Duration) {
+            self.completed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn effort_controller() {
+        let controller = EffortController::new(Duration::from_millis(10)).with_max_backlog(100);
+        let fast = Duration::from_millis(1);
+        let slow = Duration::from_millis(20);
+
+        assert_eq!(controller.next_effort(4, fast, 0), 4);
+        assert_eq!(controller.next_effort(4, fast, 10), 5);
+        assert_eq!(controller.next_effort(4, slow, 10), 2);
+        assert_eq!(controller.next_effort(4, slow, 1000), 8);
+        assert_eq!(controller.next_effort(1, slow, 10), 1);
+        assert_eq!(controller.next_effort(64, fast, 1000), 64);
+
+        // A generous latency target lets the controller raise the effort
+        // while there is merge work to do.
+        let mut spine = Spine::<Rc<OrdZSet<u64, isize>>>::new(None);
+        spine.set_effort_controller(Some(
+            EffortController::new(Duration::from_secs(3600)).with_bounds(1, 16),
+        ));
+        for i in 0..1000 {
+            spine.insert(Rc::new(OrdZSet::from_tuples((), vec![((i, ()), 1)])));
+            assert!((1..=16).contains(&spine.effort()));
+        }
+        assert!(spine.effort() > 1);
+        assert_eq!(spine.len(), 1000);
+    }
+
+    #[test]
+    fn maintenance_debt() {
+        let mut spine = Spine::<Rc<OrdZSet<u64, isize>>>::new(None);
+        assert!(spine.maintenance_debt().is_empty());
+
+        let mut saw_debt = false;
+        for i in 0..1000 {
+            spine.insert(Rc::new(OrdZSet::from_tuples((), vec![((i, ()), 1)])));
+            let debt = spine.maintenance_debt();
+            assert!(debt.fuel <= debt.tuples);
+            if !debt.is_empty() {
+                saw_debt = true;
+                assert!(debt.tuples > 0);
+            }
+        }
+        assert!(saw_debt);
+
+        let mut fuel = isize::MAX;
+        while !spine.maintenance_debt().is_empty() {
+            spine.exert(&mut fuel);
+            fuel = isize::MAX;
+        }
+        assert_eq!(spine.len(), 1000);
+    }
+
+    #[test]
+    fn max_batches() {
+        let mut spine = Spine::<Rc<OrdZSet<u64, isize>>>::new(None);
+        spine.set_max_batches(Some(2));
+
+        for i in 0..100 {
+            spine.insert(Rc::new(OrdZSet::from_tuples((), vec![((i, ()), 1)])));
+            assert!(spine.num_batches() <= 2);
+        }
+        assert_eq!(spine.len(), 100);
+    }
+
+    #[test]
+    fn retain() {
+        let mut spine = Spine::<Rc<OrdZSet<u64, isize>>>::new(None);
+        spine.retain(|key, _| key % 2 == 0);
+
+        for i in 0..10 {
+            spine.insert(Rc::new(OrdZSet::from_tuples((), vec![((i, ()), 1)])));
+        }
+        let batch = spine.consolidate().unwrap();
+        assert_eq!(batch.len(), 5);
+    }
+
+    #[test]
+    fn retain_shared() {
+        let mut spine = Spine::<Rc<OrdZSet<u64, isize>>>::new(None);
+        for i in 0..10 {
+            spine.insert(Rc::new(OrdZSet::from_tuples((), vec![((i, ()), 1)])));
+        }
+
+        // Batches shared with a snapshot are copied rather than modified.
+        let snapshot = spine.snapshot();
+        spine.retain(|key, _| key % 2 == 0);
+        spine.compact();
+        assert_eq!(snapshot.len(), 10);
+
+        let snapshot = spine.snapshot();
+        spine.compact_with(&|key, _| *key < 4);
+        assert_eq!(snapshot.len(), 5);
+        assert_eq!(spine.len(), 2);
+    }
+
+    #[test]
+    fn retain_batches() {
+        let mut spine = Spine::<Rc<OrdZSet<u64, isize>>>::new(None);
+        for i in 0..10 {
+            spine.insert(Rc::new(OrdZSet::from_tuples((), vec![((i, ()), 1)])));
+        }
+
+        // Batches are filtered without being merged into one.
+        spine.retain_batches(&|key, _| key % 2 == 0);
+        assert!(spine.num_batches() > 1);
+        assert_eq!(spine.len(), 5);
+    }
+
+    #[test]
+    fn from_batches() {
+        let batches: Vec<_> = (0..10)
+            .map(|i| Rc::new(OrdZSet::from_tuples((), vec![((i % 5, ()), 1)])))
+            .collect();
+
+        // The spine shares the batches until it merges them.
+        let mut spine = Spine::from_batches(batches.iter().cloned(), None);
+        assert_eq!(spine.num_batches(), 10);
+        assert_eq!(Rc::strong_count(&batches[0]), 2);
+
+        spine.insert(Rc::new(OrdZSet::from_tuples((), vec![((0, ()), 1)])));
+        let batch = spine.consolidate().unwrap();
+        assert_eq!(batch.len(), 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "SpineCursor used after the spine was modified")]
+    fn cursor_after_insert() {
+        let mut spine = Spine::<Rc<OrdZSet<u64, isize>>>::new(None);
+        spine.insert(Rc::new(OrdZSet::from_tuples((), vec![((0, ()), 1)])));
+
+        let mut cursor = spine.cursor();
+        spine.insert(Rc::new(OrdZSet::from_tuples((), vec![((1, ()), 1)])));
+        cursor.step_key(&spine);
+    }
+
+    #[test]
+    fn merge_observer() {
+        let observer = Arc::new(CountingObserver::default());
+        let mut spine = Spine::<Rc<OrdZSet<u64, isize>>>::new(None);
+        spine.set_merge_observer(observer.clone());
+
+        for i in 0..100 {
+            spine.insert(Rc::new(OrdZSet::from_tuples((), vec![((i, ()), 1)])));
+        }
+        let batch = spine.consolidate().unwrap();
+        assert_eq!(batch.len(), 100);
+
+        let started = observer.started.load(Ordering::Relaxed);
+        assert!(started > 0);
+        assert_eq!(observer.completed.load(Ordering::Relaxed), started);
+    }
+}